@@ -0,0 +1,97 @@
+//! Benchmarks for the three stages of reading zoneinfo files: parsing lines,
+//! building them into a table, and calculating a zone’s transitions.
+//!
+//! Rather than depending on a copy of the database, the input gets
+//! generated here, shaped like the worst of the real thing: a rule set with
+//! two rules for every year, and a zone that keeps switching between
+//! offsets while using it, so there are plenty of transitions to work out.
+//!
+//! Run them with `cargo bench`.
+
+#[macro_use] extern crate criterion;
+extern crate zoneinfo_parse;
+
+use criterion::{Criterion, Benchmark, Throughput, black_box};
+
+use zoneinfo_parse::line::Line;
+use zoneinfo_parse::table::{Table, TableBuilder};
+use zoneinfo_parse::transitions::{TableTransitions, TransitionOptions};
+
+
+/// How many ordinary zones to put in the generated table, alongside the
+/// worst-case one.
+const ZONE_COUNT: usize = 300;
+
+/// The name of the zone with the most transitions.
+const WORST_ZONE: &'static str = "Bench/Worst";
+
+
+/// Generates the text of a zoneinfo file to benchmark against.
+fn input() -> String {
+    let mut text = String::new();
+
+    for year in 1916 .. 2038 {
+        text.push_str(&format!("Rule\tMany\t{}\tonly\t-\tMar\tlastSun\t1:00u\t1:00\tS\n", year));
+        text.push_str(&format!("Rule\tMany\t{}\tonly\t-\tOct\tlastSun\t1:00u\t0\t-\n", year));
+    }
+
+    text.push_str(&format!("Zone\t{}\t0:14:44\t-\tLMT\t1901 Jan  1  0:00s\n", WORST_ZONE));
+    for decade in 191 .. 203 {
+        let offset = if decade % 2 == 0 { "1:00" } else { "2:00" };
+        text.push_str(&format!("\t\t\t{}\tMany\tCE%sT\t{}0 Jan  1\n", offset, decade + 1));
+    }
+    text.push_str("\t\t\t1:00\tMany\tCE%sT\n");
+
+    for n in 0 .. ZONE_COUNT {
+        text.push_str(&format!("Zone\tBench/Zone{}\t-0:{:02}:00\t-\tLMT\t1880\n", n, n % 60));
+        text.push_str("\t\t\t0:00\t-\tGMT\t1916 May 21  2:00s\n");
+        text.push_str("\t\t\t1:00\tMany\tCE%sT\n");
+        text.push_str(&format!("Link\tBench/Zone{}\tBench/Link{}\n", n, n));
+    }
+
+    text
+}
+
+/// Parses every line of the input and builds them into a table.
+fn build_table(text: &str) -> Table {
+    let mut builder = TableBuilder::new();
+
+    for line in text.lines() {
+        match Line::from_str(line).unwrap() {
+            Line::Space              => {},
+            Line::Zone(zone)         => builder.add_zone_line(zone).unwrap(),
+            Line::Continuation(cont) => builder.add_continuation_line(cont).unwrap(),
+            Line::Rule(rule)         => builder.add_rule_line(rule).unwrap(),
+            Line::Link(link)         => builder.add_link_line(link).unwrap(),
+        }
+    }
+
+    builder.build()
+}
+
+
+fn parse_lines(c: &mut Criterion) {
+    let text = input();
+    let bytes = text.len() as u32;
+
+    c.bench("line", Benchmark::new("from_str", move |b| b.iter(|| {
+        for line in text.lines() {
+            let _ = black_box(Line::from_str(line));
+        }
+    })).throughput(Throughput::Bytes(bytes)));
+}
+
+fn table_build(c: &mut Criterion) {
+    let text = input();
+    c.bench_function("table build", move |b| b.iter(|| build_table(&text)));
+}
+
+fn worst_timespans(c: &mut Criterion) {
+    let table = build_table(&input());
+    let options = TransitionOptions::default();
+    c.bench_function("timespans", move |b| b.iter(|| table.timespans(WORST_ZONE, &options)));
+}
+
+
+criterion_group!(benches, parse_lines, table_build, worst_timespans);
+criterion_main!(benches);
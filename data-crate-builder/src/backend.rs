@@ -0,0 +1,76 @@
+//! What a data crate gets turned into.
+//!
+//! Working out which zones and links there are, and computing their
+//! transitions, is the same whatever the output looks like, so a
+//! `DataCrate` does that part itself and hands the results to a
+//! `CodegenBackend` to write out. The data crate walks through every zone
+//! and link in sorted order, calling `emit_zone` for each one with its
+//! timespans, and then calls `emit_index` once with the whole dataset, for
+//! the files that cover every zone at once, such as a module listing them.
+//!
+//! Formats that put every zone in the same file only need `emit_index`,
+//! while formats with one file per zone do most of their work in
+//! `emit_zone`. Every built-in `Format` is written by a backend like this,
+//! and other targets can be added by implementing the trait and passing it
+//! to `DataCrate::write_with`.
+//!
+//! ## Example
+//!
+//! A backend that writes each zone’s number of transitions to a text file
+//! next to where the zone would go:
+//!
+//! ```
+//! extern crate build_data_crate;
+//! extern crate zoneinfo_parse;
+//!
+//! use std::io::Write;
+//! use std::io::Result as IOResult;
+//! use std::path::PathBuf;
+//!
+//! use build_data_crate::backend::CodegenBackend;
+//! use build_data_crate::data_crate::Dataset;
+//! use build_data_crate::output::OutputSink;
+//! use zoneinfo_parse::transitions::FixedTimespanSet;
+//!
+//! struct TransitionCounts;
+//!
+//! impl CodegenBackend for TransitionCounts {
+//!     fn emit_zone<S: OutputSink>(&mut self, sink: &mut S, name: &str, set: &FixedTimespanSet) -> IOResult<()> {
+//!         let path: PathBuf = format!("{}.txt", name).split('/').collect();
+//!         let mut w = try!(sink.file(&path));
+//!         try!(writeln!(w, "{}", set.rest.len()));
+//!         sink.finish(w)
+//!     }
+//!
+//!     fn emit_index<S: OutputSink>(&mut self, _sink: &mut S, _dataset: &Dataset) -> IOResult<()> {
+//!         Ok(())
+//!     }
+//! }
+//! # fn main() {}
+//! ```
+
+use std::io::Result as IOResult;
+
+use zoneinfo_parse::transitions::FixedTimespanSet;
+
+use data_crate::Dataset;
+use output::OutputSink;
+
+
+/// Something that writes out a data crate’s zones in a particular format.
+pub trait CodegenBackend {
+
+    /// Writes whatever this format has for one zone or link, given its name
+    /// and its timespans (which, for a link, are those of the zone it links
+    /// to). This gets called for every zone and link in sorted order,
+    /// before `emit_index`. Formats that write every zone in the same file
+    /// can leave this as it is, and do nothing here.
+    fn emit_zone<S: OutputSink>(&mut self, sink: &mut S, name: &str, set: &FixedTimespanSet) -> IOResult<()> {
+        let _ = (sink, name, set);
+        Ok(())
+    }
+
+    /// Writes the files that cover the whole dataset, once every zone has
+    /// been emitted.
+    fn emit_index<S: OutputSink>(&mut self, sink: &mut S, dataset: &Dataset) -> IOResult<()>;
+}
@@ -0,0 +1,306 @@
+//! Writing every zone into one compact binary blob, along with a small Rust
+//! module that loads zones from it.
+//!
+//! Generating a Rust file for every zone makes the data crate slow to
+//! compile, as the compiler has to process hundreds of large static
+//! structures. Instead, this writes the whole dataset into `zones.bin`, and
+//! writes a `mod.rs` that pulls it in with `include_bytes!` and decodes
+//! zones on demand, which compiles in moments.
+//!
+//! The format of the blob is described in the `blob_loader` module, whose
+//! source is what gets copied into the generated `mod.rs`. It provides a
+//! `lookup` function that returns a `BlobZone`, and a `names` function that
+//! lists every zone and link.
+//!
+//! With delta timestamps, each transition’s time is written as the number of
+//! seconds since the previous one, which takes four bytes instead of eight
+//! nearly every time, at the cost of a little decoding work in the loader.
+//!
+//! Compressed, the blob gets deflated into `zones.bin.deflate` instead, and
+//! the `mod.rs` inflates it back into memory the first time a zone is
+//! looked up. That costs a few milliseconds at runtime, once, in exchange
+//! for a binary that’s hundreds of kilobytes smaller. The generated code
+//! uses the `miniz_oxide` crate to do this, so the crate it goes in needs
+//! to depend on that.
+
+use std::collections::BTreeMap;
+use std::io::{Write, Error as IOError, ErrorKind};
+use std::io::Result as IOResult;
+
+use miniz_oxide::deflate::compress_to_vec;
+use zoneinfo_parse::transitions::{FixedTimespan, FixedTimespanSet};
+
+use blob_loader::{BLOB_MAGIC, BLOB_VERSION, BLOB_DELTA_VERSION, DELTA_ESCAPE, HEADER_SIZE, INDEX_ENTRY_SIZE};
+use data_crate::Dataset;
+
+
+/// The source of the loader, which goes at the top of the generated
+/// `mod.rs` file.
+pub const LOADER_SOURCE: &'static str = include_str!("blob_loader.rs");
+
+/// The rest of the generated `mod.rs` file, which includes the blob and
+/// wraps the loader’s functions.
+pub const LOADER_FOOTER: &'static str = r##"
+
+/// The blob of zone data, written by the data crate builder.
+static BLOB: &'static [u8] = include_bytes!("zones.bin");
+
+/// Looks up the zone or link with the given name.
+pub fn lookup(name: &str) -> Option<BlobZone<'static>> {
+    blob_lookup(BLOB, name)
+}
+
+/// Returns the names of every zone and link, in sorted order.
+pub fn names() -> Vec<&'static str> {
+    blob_names(BLOB)
+}
+"##;
+
+/// The rest of the generated `mod.rs` file when the blob is compressed,
+/// which decompresses it the first time it’s needed.
+pub const COMPRESSED_LOADER_FOOTER: &'static str = r##"
+
+/// The compressed blob of zone data, written by the data crate builder.
+static COMPRESSED_BLOB: &'static [u8] = include_bytes!("zones.bin.deflate");
+
+/// Returns the blob of zone data, decompressing it if this is the first
+/// time it’s been asked for.
+fn blob() -> &'static [u8] {
+    static BLOB: ::std::sync::OnceLock<Vec<u8>> = ::std::sync::OnceLock::new();
+    BLOB.get_or_init(|| ::miniz_oxide::inflate::decompress_to_vec(COMPRESSED_BLOB).expect("zone data is corrupt"))
+}
+
+/// Looks up the zone or link with the given name.
+pub fn lookup(name: &str) -> Option<BlobZone<'static>> {
+    blob_lookup(blob(), name)
+}
+
+/// Returns the names of every zone and link, in sorted order.
+pub fn names() -> Vec<&'static str> {
+    blob_names(blob())
+}
+"##;
+
+/// How hard to try to compress the blob: this is the highest standard
+/// deflate level, and as the blob only gets compressed once, there’s no
+/// reason to go for anything faster.
+const COMPRESSION_LEVEL: u8 = 9;
+
+
+/// Writes every zone and link in the dataset as a blob, with transition
+/// times stored as deltas from the previous transition if `delta_timestamps`
+/// is set.
+///
+/// Returns an error if a zone can’t be represented, because it has more
+/// than 255 different timespans, an abbreviation longer than 255 bytes, or
+/// an offset of more than 68 years.
+pub fn write_blob<W: Write>(w: &mut W, dataset: &Dataset, delta_timestamps: bool) -> IOResult<()> {
+
+    // Encode the records first, so the index can point to them. Links share
+    // the record of the zone they link to, and any that don’t link to a
+    // zone get left out.
+    let mut records = Vec::new();
+    let mut record_offsets = BTreeMap::new();
+
+    for (name, set) in &dataset.zonesets {
+        let _ = record_offsets.insert(&**name, records.len());
+        try!(encode_record(&mut records, set, delta_timestamps).map_err(|message| invalid(name, message)));
+    }
+
+    let entries: Vec<(&str, usize)> = dataset.names().into_iter().filter_map(|name| {
+        let target = dataset.links.get(name).map(|t| &**t).unwrap_or(name);
+        record_offsets.get(target).map(|&offset| (name, offset))
+    }).collect();
+
+    let names_start = HEADER_SIZE + entries.len() * INDEX_ENTRY_SIZE;
+    let records_start = names_start + entries.iter().map(|e| e.0.len()).sum::<usize>();
+
+    try!(w.write_all(BLOB_MAGIC));
+    try!(w.write_all(&[ if delta_timestamps { BLOB_DELTA_VERSION } else { BLOB_VERSION } ]));
+    try!(w.write_all(&u32_bytes(entries.len() as u32)));
+
+    let mut name_offset = names_start;
+    for &(name, record_offset) in &entries {
+        try!(w.write_all(&u32_bytes(name_offset as u32)));
+        try!(w.write_all(&[ (name.len() >> 8) as u8, name.len() as u8 ]));
+        try!(w.write_all(&u32_bytes((records_start + record_offset) as u32)));
+        name_offset += name.len();
+    }
+
+    for &(name, _) in &entries {
+        try!(w.write_all(name.as_bytes()));
+    }
+
+    w.write_all(&records)
+}
+
+/// Compresses a blob with deflate, for the generated code to decompress.
+pub fn compress(blob: &[u8]) -> Vec<u8> {
+    compress_to_vec(blob, COMPRESSION_LEVEL)
+}
+
+/// Appends the record for one zone to the buffer, or returns a description
+/// of why it can’t be represented.
+fn encode_record(buf: &mut Vec<u8>, set: &FixedTimespanSet, delta_timestamps: bool) -> Result<(), &'static str> {
+
+    // As in TZif files, each distinct timespan becomes a type, and the
+    // first timespan is always the first type.
+    let mut types: Vec<&FixedTimespan> = vec![ &set.first ];
+    let mut transition_types = Vec::new();
+
+    for &(_, ref timespan) in &set.rest {
+        let index = match types.iter().position(|t| same_type(t, timespan)) {
+            Some(index) => index,
+            None        => { types.push(timespan); types.len() - 1 },
+        };
+
+        transition_types.push(index);
+    }
+
+    if types.len() > 255 {
+        return Err("too many different timespans");
+    }
+
+    buf.push(types.len() as u8);
+    for t in &types {
+        let offset = t.total_offset();
+        if offset > i32::max_value() as i64 || offset < i32::min_value() as i64 {
+            return Err("offset out of range");
+        }
+        else if t.name.len() > 255 {
+            return Err("abbreviation too long");
+        }
+
+        buf.extend_from_slice(&u32_bytes(offset as i32 as u32));
+        buf.push(if t.is_dst { 1 } else { 0 });
+        buf.push(t.name.len() as u8);
+        buf.extend_from_slice(t.name.as_bytes());
+    }
+
+    buf.extend_from_slice(&u32_bytes(set.rest.len() as u32));
+    let mut previous = 0;
+    for (&(at, _), &index) in set.rest.iter().zip(transition_types.iter()) {
+
+        // Times that don’t fit as a delta get escaped, and written in full.
+        if delta_timestamps {
+            let delta = at.wrapping_sub(previous);
+            if delta >= 0 && delta < DELTA_ESCAPE as i64 {
+                buf.extend_from_slice(&u32_bytes(delta as u32));
+            }
+            else {
+                buf.extend_from_slice(&u32_bytes(DELTA_ESCAPE));
+                buf.extend_from_slice(&i64_bytes(at));
+            }
+        }
+        else {
+            buf.extend_from_slice(&i64_bytes(at));
+        }
+
+        buf.push(index as u8);
+        previous = at;
+    }
+
+    Ok(())
+}
+
+/// Returns whether two timespans would be decoded as the same thing.
+fn same_type(a: &FixedTimespan, b: &FixedTimespan) -> bool {
+    a.total_offset() == b.total_offset() && a.is_dst == b.is_dst && a.name == b.name
+}
+
+/// Returns the big-endian bytes of an `i64`.
+fn i64_bytes(value: i64) -> [u8; 8] {
+    let (high, low) = (u32_bytes((value >> 32) as u32), u32_bytes(value as u32));
+    [ high[0], high[1], high[2], high[3], low[0], low[1], low[2], low[3] ]
+}
+
+/// Returns the big-endian bytes of a `u32`.
+fn u32_bytes(value: u32) -> [u8; 4] {
+    [ (value >> 24) as u8, (value >> 16) as u8, (value >> 8) as u8, value as u8 ]
+}
+
+/// Returns the error for a zone that can’t be represented in a blob.
+fn invalid(zone_name: &str, message: &str) -> IOError {
+    IOError::new(ErrorKind::InvalidData, format!("zone {} can’t be written to a blob: {}", zone_name, message))
+}
+
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use blob_loader::{blob_lookup, blob_names, BlobTimespan};
+    use data_crate::Dataset;
+    use miniz_oxide::inflate::decompress_to_vec;
+    use zoneinfo_parse::transitions::{FixedTimespan, FixedTimespanSet};
+
+    fn dataset() -> Dataset {
+        let mut dataset = Dataset::default();
+        let _ = dataset.zonesets.insert("Europe/London".to_owned(), FixedTimespanSet {
+            first: FixedTimespan::new(-75, 0, "LMT"),
+            rest:  vec![
+                (-3_852_662_325, FixedTimespan::new(0, 0,    "GMT")),
+                (-1_691_964_000, FixedTimespan::new(0, 3600, "BST")),
+                (-1_680_472_800, FixedTimespan::new(0, 0,    "GMT")),
+            ],
+        });
+        let _ = dataset.zonesets.insert("Etc/UTC".to_owned(), FixedTimespanSet {
+            first: FixedTimespan::new(0, 0, "UTC"),
+            rest:  vec![],
+        });
+        let _ = dataset.links.insert("GB".to_owned(), "Europe/London".to_owned());
+        let _ = dataset.links.insert("Nowhere".to_owned(), "Does/Not/Exist".to_owned());
+        dataset
+    }
+
+    #[test]
+    fn round_trip() {
+        let mut blob = Vec::new();
+        write_blob(&mut blob, &dataset(), false).unwrap();
+
+        assert_eq!(blob_names(&blob), vec![ "Etc/UTC", "Europe/London", "GB" ]);
+
+        let london = blob_lookup(&blob, "GB").unwrap();
+        assert_eq!(london.name, "GB");
+        assert_eq!(london.first, BlobTimespan { offset: -75, is_dst: false, name: "LMT" });
+        assert_eq!(london.transitions, vec![
+            (-3_852_662_325, BlobTimespan { offset: 0,    is_dst: false, name: "GMT" }),
+            (-1_691_964_000, BlobTimespan { offset: 3600, is_dst: true,  name: "BST" }),
+            (-1_680_472_800, BlobTimespan { offset: 0,    is_dst: false, name: "GMT" }),
+        ]);
+
+        assert_eq!(blob_lookup(&blob, "Etc/UTC").unwrap().transitions, vec![]);
+        assert_eq!(blob_lookup(&blob, "Nowhere"), None);
+    }
+
+    #[test]
+    fn delta_round_trip() {
+        let mut full = Vec::new();
+        write_blob(&mut full, &dataset(), false).unwrap();
+
+        let mut deltas = Vec::new();
+        write_blob(&mut deltas, &dataset(), true).unwrap();
+        assert!(deltas.len() < full.len());
+
+        // The first transition is before 1970, so it gets escaped, but the
+        // rest fit as deltas.
+        assert_eq!(blob_lookup(&deltas, "GB"), blob_lookup(&full, "GB"));
+        assert_eq!(blob_lookup(&deltas, "Etc/UTC"), blob_lookup(&full, "Etc/UTC"));
+        assert_eq!(blob_names(&deltas), blob_names(&full));
+    }
+
+    #[test]
+    fn compressed() {
+        let mut blob = Vec::new();
+        write_blob(&mut blob, &dataset(), false).unwrap();
+
+        let compressed = compress(&blob);
+        assert!(compressed.len() < blob.len());
+        assert_eq!(decompress_to_vec(&compressed).unwrap(), blob);
+    }
+
+    #[test]
+    fn garbage() {
+        assert_eq!(blob_lookup(b"ZIDB\x01\x00\x00\x00\x05", "Europe/London"), None);
+        assert_eq!(blob_names(b"not a blob"), Vec::<&str>::new());
+    }
+}
@@ -0,0 +1,219 @@
+//! Reading zones out of a blob written by the data crate builder.
+//!
+//! This module only uses the standard library, because as well as being
+//! part of the builder, its source gets copied into the `mod.rs` file
+//! generated alongside the blob, where it’s used to decode a zone each time
+//! it gets looked up.
+//!
+//! The blob starts with a header, followed by an index of the zone names in
+//! sorted order, then the names themselves, then the zone records. All the
+//! numbers are big-endian:
+//!
+//! - the header is the bytes `ZIDB`, a version byte, and the number of
+//!   entries in the index as a `u32`;
+//! - each index entry is the offset and length of the name (`u32` and `u16`)
+//!   and the offset of the zone’s record (`u32`), all from the start of the
+//!   blob, with links pointing at the same record as their target;
+//! - each record has the number of types (`u8`), then each type’s total
+//!   offset (`i32`), DST flag (`u8`), and abbreviation (a `u8` length and
+//!   the bytes), then the number of transitions (`u32`), and then each
+//!   transition’s time (`i64`) and type index (`u8`). The first type is in
+//!   effect before the first transition.
+//!
+//! Blobs with the delta version byte store each transition’s time as the
+//! number of seconds since the previous transition (or since 1970, for the
+//! first one) in a `u32`, which is nearly always big enough, and makes the
+//! records about half the size. When the gap doesn’t fit, or is negative,
+//! the `u32` is `0xFFFFFFFF` and the time follows in full as an `i64`.
+
+use std::str;
+
+
+/// Returns the value inside an `Option`, or returns `None` from the
+/// function it’s used in. (This is `try!` for options.)
+macro_rules! try_opt {
+    ($e:expr) => (match $e { Some(v) => v, None => return None })
+}
+
+
+/// The bytes that every blob starts with.
+pub const BLOB_MAGIC: &'static [u8] = b"ZIDB";
+
+/// The version of the blob format that this module reads.
+pub const BLOB_VERSION: u8 = 1;
+
+/// The version of the blob format with delta-encoded transition times,
+/// which this module also reads.
+pub const BLOB_DELTA_VERSION: u8 = 2;
+
+/// The delta that means a transition’s time follows in full instead.
+pub const DELTA_ESCAPE: u32 = 0xFFFF_FFFF;
+
+/// The size of the header, and of each index entry, in bytes.
+pub const HEADER_SIZE: usize = 9;
+pub const INDEX_ENTRY_SIZE: usize = 10;
+
+
+/// One zone, decoded from a blob.
+#[derive(PartialEq, Debug, Clone)]
+pub struct BlobZone<'a> {
+
+    /// The name the zone was looked up by.
+    pub name: &'a str,
+
+    /// The timespan in effect before the first transition.
+    pub first: BlobTimespan<'a>,
+
+    /// The time of each transition, and the timespan it starts.
+    pub transitions: Vec<(i64, BlobTimespan<'a>)>,
+}
+
+/// A period of time with the same offset, DST flag, and abbreviation.
+#[derive(PartialEq, Debug, Copy, Clone)]
+pub struct BlobTimespan<'a> {
+
+    /// The total UTC offset, in seconds.
+    pub offset: i64,
+
+    /// Whether daylight-saving time is in effect.
+    pub is_dst: bool,
+
+    /// The abbreviation, such as “GMT”.
+    pub name: &'a str,
+}
+
+
+/// Returns the names of every zone and link in the blob, in sorted order,
+/// or nothing if the blob isn’t valid.
+pub fn blob_names(blob: &[u8]) -> Vec<&str> {
+    let count = match blob_entry_count(blob) {
+        Some(c) => c,
+        None    => return Vec::new(),
+    };
+
+    (0 .. count).filter_map(|index| blob_entry(blob, index).map(|e| e.0)).collect()
+}
+
+/// Looks up the zone or link with the given name in the blob, decoding its
+/// record. Returns `None` if there’s no such zone, or the blob isn’t valid.
+pub fn blob_lookup<'a>(blob: &'a [u8], name: &str) -> Option<BlobZone<'a>> {
+    let count = match blob_entry_count(blob) {
+        Some(c) => c,
+        None    => return None,
+    };
+
+    // The index is sorted, so it can be binary searched.
+    let (mut low, mut high) = (0, count);
+    while low < high {
+        let middle = (low + high) / 2;
+        let (entry_name, record) = match blob_entry(blob, middle) {
+            Some(e) => e,
+            None    => return None,
+        };
+
+        if entry_name == name {
+            return blob_record(blob, entry_name, record);
+        }
+        else if entry_name < name {
+            low = middle + 1;
+        }
+        else {
+            high = middle;
+        }
+    }
+
+    None
+}
+
+/// Checks the header, and returns the number of index entries.
+fn blob_entry_count(blob: &[u8]) -> Option<usize> {
+    if blob.len() < HEADER_SIZE || &blob[0 .. 4] != BLOB_MAGIC || (blob[4] != BLOB_VERSION && blob[4] != BLOB_DELTA_VERSION) {
+        return None;
+    }
+
+    blob_u32(blob, 5).map(|c| c as usize)
+}
+
+/// Returns the name and record offset of the index entry at the given
+/// position.
+fn blob_entry(blob: &[u8], index: usize) -> Option<(&str, usize)> {
+    let at = HEADER_SIZE + index * INDEX_ENTRY_SIZE;
+    let name_start = try_opt!(blob_u32(blob, at)) as usize;
+    let name_length = try_opt!(blob_u16(blob, at + 4)) as usize;
+    let record = try_opt!(blob_u32(blob, at + 6)) as usize;
+
+    let name_bytes = try_opt!(blob_slice(blob, name_start, name_length));
+    str::from_utf8(name_bytes).ok().map(|name| (name, record))
+}
+
+/// Decodes the record at the given offset.
+fn blob_record<'a>(blob: &'a [u8], name: &'a str, mut at: usize) -> Option<BlobZone<'a>> {
+    let type_count = *try_opt!(blob.get(at)) as usize;
+    at += 1;
+
+    let mut types = Vec::with_capacity(type_count);
+    for _ in 0 .. type_count {
+        let offset = try_opt!(blob_u32(blob, at)) as i32 as i64;
+        let is_dst = *try_opt!(blob.get(at + 4)) != 0;
+        let length = *try_opt!(blob.get(at + 5)) as usize;
+        let abbreviation = try_opt!(str::from_utf8(try_opt!(blob_slice(blob, at + 6, length))).ok());
+
+        types.push(BlobTimespan { offset: offset, is_dst: is_dst, name: abbreviation });
+        at += 6 + length;
+    }
+
+    let transition_count = try_opt!(blob_u32(blob, at)) as usize;
+    at += 4;
+
+    let deltas = blob[4] == BLOB_DELTA_VERSION;
+    let mut previous = 0;
+
+    let mut transitions = Vec::with_capacity(transition_count);
+    for _ in 0 .. transition_count {
+        let delta = if deltas { try_opt!(blob_u32(blob, at)) } else { DELTA_ESCAPE };
+        if deltas {
+            at += 4;
+        }
+
+        let time = if delta == DELTA_ESCAPE {
+            at += 8;
+            try_opt!(blob_i64(blob, at - 8))
+        }
+        else {
+            previous + delta as i64
+        };
+
+        let timespan = *try_opt!(types.get(*try_opt!(blob.get(at)) as usize));
+        transitions.push((time, timespan));
+        previous = time;
+        at += 1;
+    }
+
+    Some(BlobZone {
+        name: name,
+        first: *try_opt!(types.first()),
+        transitions: transitions,
+    })
+}
+
+/// Reads a big-endian `i64` at the given offset.
+fn blob_i64(blob: &[u8], at: usize) -> Option<i64> {
+    let high = try_opt!(blob_u32(blob, at)) as u64;
+    let low = try_opt!(blob_u32(blob, at + 4)) as u64;
+    Some(((high << 32) | low) as i64)
+}
+
+/// Reads a big-endian `u32` at the given offset.
+fn blob_u32(blob: &[u8], at: usize) -> Option<u32> {
+    blob_slice(blob, at, 4).map(|b| (b[0] as u32) << 24 | (b[1] as u32) << 16 | (b[2] as u32) << 8 | b[3] as u32)
+}
+
+/// Reads a big-endian `u16` at the given offset.
+fn blob_u16(blob: &[u8], at: usize) -> Option<u16> {
+    blob_slice(blob, at, 2).map(|b| (b[0] as u16) << 8 | b[1] as u16)
+}
+
+/// Returns the bytes in the given range, if the blob is long enough.
+fn blob_slice(blob: &[u8], at: usize, length: usize) -> Option<&[u8]> {
+    if at + length <= blob.len() { Some(&blob[at .. at + length]) } else { None }
+}
@@ -0,0 +1,163 @@
+//! Building a data crate, or the same data in another format.
+//!
+//! This is what the builder does when it’s given the `build` command, or no
+//! command at all: it reads the input files, computes every zone’s
+//! transitions, and writes them out in the format given with `--format`
+//! (see `data_crate::Format`). Extra data can be mixed in from CLDR’s
+//! `windowsZones.xml` with `--windows-zones`, and from `zone1970.tab` with
+//! `--zone-tab`.
+//!
+//! Passing `--filter` keeps only some of the zones, in the same way as the
+//! `ZONEINFO_FILTER` variable does for build scripts (see the
+//! `build_script` module).
+
+use std::collections::BTreeMap;
+use std::ffi::OsString;
+use std::io::Read;
+use std::path::Path;
+
+use getopts;
+
+use zoneinfo_parse::tab::{parse_zone_tab, ZoneLocation};
+use zoneinfo_parse::transitions::TransitionOptions;
+use zoneinfo_parse::windows::{parse_windows_zones, default_zones};
+
+use build_script::ZoneFilter;
+use cache::Cache;
+use commands;
+use data_crate::{DataCrate, CodegenOptions, Format, Options, source_leap_seconds, source_version, with_backzone};
+use diagnostics::ErrorFormat;
+use errors::CliError;
+use output::MemorySink;
+use system::Filesystem;
+use {Report, number_option, optional_number_option};
+
+
+/// The options the `build` command takes.
+pub fn options() -> getopts::Options {
+    let mut opts = getopts::Options::new();
+    opts.reqopt("o", "output", "directory to write the crate into", "DIR");
+    opts.optopt("", "cache", "directory to cache parsed data in between runs", "DIR");
+    opts.optflag("", "validate", "check the input for problems before generating anything");
+    opts.optopt("", "min-year", "first year to expand daylight-saving rules in", "YEAR");
+    opts.optopt("", "max-year", "last year to expand daylight-saving rules in", "YEAR");
+    opts.optopt("", "window-start", "first year to keep transitions from, dropping the ones before it", "YEAR");
+    opts.optopt("", "window-end", "last year to keep transitions from, dropping the ones after it", "YEAR");
+    opts.optflag("", "no-pre-1970", "collapse the history before 1970 into each zone’s first timespan, and ignore backzone");
+    opts.optflag("", "backzone", "also read the backzone file from input directories, replacing links with its zones");
+    opts.optflag("", "no-backward", "leave out the backward file, and the deprecated names it links from");
+    opts.optflag("", "continue-on-error", "skip lines that can’t be used, with a warning, and build everything else");
+    opts.optflag("", "no-dedup", "keep transitions that don’t change the offset, DST flag, or abbreviation");
+    opts.optopt("", "filter", "comma-separated zones to keep, where a trailing * matches any ending (default: all)", "ZONES");
+    opts.optopt("", "format", "format to write: rust, rust-file, tzif, zi, json, csv, blob, chrono-tz, or time (default: rust)", "FORMAT");
+    opts.optflag("", "no-std", "generate Rust code that doesn’t use the standard library");
+    opts.optflag("", "feature-gates", "put each region of the generated Rust code behind a Cargo feature");
+    opts.optopt("", "scaffold", "write a Cargo.toml and lib.rs too, making a crate with the given name", "NAME");
+    opts.optopt("", "tz-version", "release of the tz database being built (default: read from the version file)", "VERSION");
+    opts.optopt("", "windows-zones", "CLDR windowsZones.xml file to generate a map of Windows zone names from", "FILE");
+    opts.optopt("", "zone-tab", "zone1970.tab file to generate a list of zone locations from", "FILE");
+    opts.optflag("", "delta-timestamps", "store blob transition times as deltas from the previous transition");
+    opts.optflag("", "compress", "compress the blob with deflate, to be decompressed the first time it’s used");
+    opts.optflag("", "dry-run", "list the files that would be created or changed, without writing anything");
+    opts.optopt("", "error-format", "how to print errors and warnings: text or json (default: text)", "FORMAT");
+    opts.optflag("q", "quiet", "only print errors");
+    opts.optflagmulti("v", "verbose", "print more about what’s being done (twice for even more)");
+    opts
+}
+
+/// Runs the `build` command with the given arguments, which come after the
+/// word `build`, if it was given. The free arguments are the input files.
+pub fn run<F: Filesystem>(args: &[OsString], fs: &F) -> Result<Report, CliError> {
+    let matches = try!(commands::find("build").unwrap().parse(args));
+    let output = matches.opt_str("output").unwrap();
+
+    if let Some(name) = matches.opt_str("error-format") {
+        if ErrorFormat::from_name(&name).is_none() {
+            return Err(CliError::InvalidArgument("error-format", name));
+        }
+    }
+
+    let format = match matches.opt_str("format") {
+        Some(name) => try!(Format::from_name(&name).ok_or(CliError::InvalidArgument("format", name))),
+        None       => Format::default(),
+    };
+
+    let defaults = TransitionOptions::default();
+    let options = Options {
+        validate: matches.opt_present("validate"),
+        transitions: TransitionOptions {
+            min_year: try!(number_option(&matches, "min-year", defaults.min_year)),
+            max_year: try!(number_option(&matches, "max-year", defaults.max_year)),
+            dedup:    !matches.opt_present("no-dedup"),
+        },
+        window_start: try!(optional_number_option(&matches, "window-start")),
+        window_end: try!(optional_number_option(&matches, "window-end")),
+        collapse_pre_1970: matches.opt_present("no-pre-1970"),
+        skip_backward: matches.opt_present("no-backward"),
+        skip_errors: matches.opt_present("continue-on-error"),
+    };
+
+    let input_paths = if matches.opt_present("backzone") { with_backzone(fs, &matches.free) } else { matches.free.clone() };
+
+    let (mut data_crate, mut warnings) = match matches.opt_str("cache") {
+        Some(dir) => try!(DataCrate::new_cached(fs, &*output, &input_paths, &options, &Cache::new(dir))),
+        None      => try!(DataCrate::new(fs, &*output, &input_paths, &options)),
+    };
+
+    if let Some(filter) = matches.opt_str("filter").and_then(|f| ZoneFilter::parse(&f)) {
+        filter.apply(&mut data_crate, &mut warnings);
+    }
+
+    let windows_zones = match matches.opt_str("windows-zones") {
+        Some(path) => try!(read_windows_zones(fs, &path)),
+        None       => BTreeMap::new(),
+    };
+
+    let locations = match matches.opt_str("zone-tab") {
+        Some(path) => try!(read_zone_tab(fs, &path)),
+        None       => Vec::new(),
+    };
+
+    let codegen = CodegenOptions {
+        no_std:           matches.opt_present("no-std"),
+        feature_gates:    matches.opt_present("feature-gates"),
+        scaffold:         matches.opt_str("scaffold"),
+        tz_version:       matches.opt_str("tz-version").or_else(|| source_version(fs, &matches.free)),
+        windows_zones:    windows_zones,
+        locations:        locations,
+        leap_seconds:     try!(source_leap_seconds(fs, &input_paths)),
+        delta_timestamps: matches.opt_present("delta-timestamps"),
+        compress:         matches.opt_present("compress"),
+    };
+
+    let mut report = data_crate.report(warnings);
+    if matches.opt_present("dry-run") {
+        let mut sink = MemorySink::new();
+        try!(data_crate.write_to(&mut sink, format, &codegen));
+        for change in try!(sink.changes(fs, Path::new(&output))) {
+            report.output.push_str(&format!("{}\n", change));
+        }
+    }
+    else {
+        try!(data_crate.run_with_codegen(fs, format, &codegen));
+    }
+
+    Ok(report)
+}
+
+/// Reads the default zone for each Windows zone name from a CLDR
+/// `windowsZones.xml` file.
+fn read_windows_zones<F: Filesystem>(fs: &F, path: &str) -> Result<BTreeMap<String, String>, CliError> {
+    let mut contents = String::new();
+    let _ = try!(try!(fs.open(Path::new(path))).read_to_string(&mut contents));
+    let mappings = try!(parse_windows_zones(&contents).map_err(|e| CliError::WindowsZones(path.to_owned(), e)));
+    Ok(default_zones(&mappings))
+}
+
+/// Reads the location of each zone’s principal city from a `zone1970.tab`
+/// or `zone.tab` file.
+fn read_zone_tab<F: Filesystem>(fs: &F, path: &str) -> Result<Vec<ZoneLocation>, CliError> {
+    let mut contents = String::new();
+    let _ = try!(try!(fs.open(Path::new(path))).read_to_string(&mut contents));
+    parse_zone_tab(&contents).map_err(|e| CliError::Tab(path.to_owned(), e))
+}
@@ -0,0 +1,178 @@
+//! Generating a data crate from another crate’s build script.
+//!
+//! Rather than running the builder binary and checking its output into
+//! their repository, a downstream crate can depend on this one as a build
+//! dependency and call `generate` from its `build.rs`, writing the zone
+//! data into `OUT_DIR` and including it from there:
+//!
+//! ```no_run
+//! extern crate build_data_crate;
+//!
+//! use std::env;
+//! use build_data_crate::{generate, GenerateOptions};
+//!
+//! fn main() {
+//!     let out_dir = env::var("OUT_DIR").unwrap();
+//!     generate(&out_dir, &[ "tzdata" ], &GenerateOptions::default()).unwrap();
+//! }
+//! ```
+//!
+//! Along the way, it prints the `cargo:rerun-if-changed` lines that make
+//! Cargo re-run the build script only when the source files change.
+//!
+//! Crates that only need a few zones can set the `ZONEINFO_FILTER`
+//! environment variable when building to a comma-separated list of zone
+//! names, where a name ending in `*` matches every zone that starts with
+//! what comes before it: `Europe/*,America/New_York` keeps just the
+//! European zones and New York. The zones that any remaining links point
+//! to are kept too.
+
+use std::env;
+use std::io::{self, Write};
+use std::path::Path;
+
+use data_crate::{DataCrate, CodegenOptions, Format, Options, expand_inputs, source_leap_seconds, source_version};
+use errors::CliError;
+use system::{Filesystem, RealFilesystem};
+use warnings::Warning;
+use Report;
+
+
+/// The environment variable that limits which zones get generated.
+pub const FILTER_VARIABLE: &'static str = "ZONEINFO_FILTER";
+
+
+/// Settings for generating a data crate from a build script.
+#[derive(PartialEq, Debug, Clone, Default)]
+pub struct GenerateOptions {
+
+    /// The format to write the data in.
+    pub format: Format,
+
+    /// The options used to build the data crate.
+    pub options: Options,
+
+    /// The options for the Rust code that gets generated.
+    pub codegen: CodegenOptions,
+}
+
+
+/// A list of patterns that zone and link names get matched against.
+#[derive(PartialEq, Debug, Clone)]
+pub struct ZoneFilter {
+    patterns: Vec<String>,
+}
+
+impl ZoneFilter {
+
+    /// Parses a comma-separated list of patterns, as given in the
+    /// `ZONEINFO_FILTER` variable. Returns `None` if there aren’t any, in
+    /// which case nothing should get filtered out.
+    pub fn parse(input: &str) -> Option<ZoneFilter> {
+        let patterns: Vec<String> = input.split(',')
+                                         .map(|p| p.trim())
+                                         .filter(|p| !p.is_empty())
+                                         .map(|p| p.to_owned())
+                                         .collect();
+
+        if patterns.is_empty() { None } else { Some(ZoneFilter { patterns: patterns }) }
+    }
+
+    /// Returns whether the zone or link with the given name matches any of
+    /// the patterns.
+    pub fn matches(&self, name: &str) -> bool {
+        self.patterns.iter().any(|pattern| {
+            if pattern.ends_with('*') {
+                name.starts_with(&pattern[.. pattern.len() - 1])
+            }
+            else {
+                name == pattern
+            }
+        })
+    }
+
+    /// Removes the zones and links that don’t match from the data crate, as
+    /// with `DataCrate::retain`, along with the warnings about the zones
+    /// that got removed.
+    pub fn apply(&self, data_crate: &mut DataCrate, warnings: &mut Vec<Warning>) {
+        data_crate.retain(|name| self.matches(name));
+
+        let zonesets = &data_crate.dataset().zonesets;
+        warnings.retain(|warning| match *warning {
+            Warning::SuspiciousAbbreviation { ref zone, .. } => zonesets.contains_key(zone),
+            _                                                 => true,
+        });
+    }
+}
+
+
+/// Generates a data crate in the given directory from the given source
+/// files or directories, filtering the zones according to the
+/// `ZONEINFO_FILTER` variable, and printing Cargo’s `rerun-if` lines to
+/// standard output. This is meant to be called from a build script.
+pub fn generate<P, S>(out_dir: P, sources: &[S], options: &GenerateOptions) -> Result<Report, CliError>
+where P: AsRef<Path>, S: AsRef<str> {
+    let filter = env::var(FILTER_VARIABLE).ok().and_then(|f| ZoneFilter::parse(&f));
+    let stdout = io::stdout();
+    generate_with(out_dir, sources, options, filter.as_ref(), &RealFilesystem, &mut stdout.lock())
+}
+
+/// Generates a data crate in the same way as `generate`, but using the
+/// given filter instead of reading it from the environment, the given
+/// filesystem instead of the real one, and writing the lines for Cargo to
+/// the given writer.
+pub fn generate_with<P, S, F, W>(out_dir: P, sources: &[S], options: &GenerateOptions, filter: Option<&ZoneFilter>, fs: &F, cargo: &mut W) -> Result<Report, CliError>
+where P: AsRef<Path>, S: AsRef<str>, F: Filesystem, W: Write {
+    let sources: Vec<String> = sources.iter().map(|s| s.as_ref().to_owned()).collect();
+
+    // Cargo checks the modification times of directories as well as files,
+    // so listing a directory means adding a new file to it gets noticed.
+    for source in sources.iter().chain(try!(expand_inputs(fs, &sources)).iter()) {
+        try!(writeln!(cargo, "cargo:rerun-if-changed={}", source));
+    }
+    try!(writeln!(cargo, "cargo:rerun-if-env-changed={}", FILTER_VARIABLE));
+
+    let out_dir = out_dir.as_ref();
+    if !fs.is_dir(out_dir) {
+        try!(fs.create_dir(out_dir));
+    }
+
+    let (mut data_crate, mut warnings) = try!(DataCrate::new(fs, out_dir, &sources, &options.options));
+    if let Some(filter) = filter {
+        filter.apply(&mut data_crate, &mut warnings);
+    }
+
+    // Unless the version and leap seconds were given, read them from the
+    // sources, as the binary does.
+    let mut codegen = options.codegen.clone();
+    if codegen.tz_version.is_none() {
+        codegen.tz_version = source_version(fs, &sources);
+    }
+    if codegen.leap_seconds.is_none() {
+        codegen.leap_seconds = try!(source_leap_seconds(fs, &sources));
+    }
+
+    try!(data_crate.run_with_codegen(fs, options.format, &codegen));
+    Ok(data_crate.report(warnings))
+}
+
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn filter() {
+        let filter = ZoneFilter::parse(" Europe/*, America/New_York,").unwrap();
+        assert!(filter.matches("Europe/London"));
+        assert!(filter.matches("America/New_York"));
+        assert!(!filter.matches("America/New_York_City"));
+        assert!(!filter.matches("Asia/Tokyo"));
+    }
+
+    #[test]
+    fn empty_filter() {
+        assert_eq!(ZoneFilter::parse(""), None);
+        assert_eq!(ZoneFilter::parse(" , "), None);
+    }
+}
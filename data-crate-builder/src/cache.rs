@@ -0,0 +1,284 @@
+//! Caching computed datasets between runs.
+//!
+//! Parsing the whole tz database and computing every zone’s transitions
+//! takes a noticeable amount of time, and the result only depends on the
+//! contents of the input files (and the options used to compute it). So the
+//! result can be stored in a cache directory, under a key made from a
+//! SHA-256 hash of those inputs, and re-used the next time the builder is
+//! run on the same data. The hash doesn’t depend on the platform or the
+//! version of Rust the builder was compiled with, so a cache directory can
+//! be shared between machines.
+//!
+//! Cached datasets are stored in a simple line-based text format, with one
+//! line per zone, timespan, or link. Each timespan has its UTC offset, DST
+//! offset, whether it’s daylight-saving time, and abbreviation:
+//!
+//! ```text
+//! zone Europe/London
+//! first -75 0 0 LMT
+//! at -3852662325 0 0 0 GMT
+//! link GB Europe/London
+//! ```
+//!
+//! A cache file that can’t be read or understood is treated as missing,
+//! rather than as an error, so a corrupt cache never stops a build.
+
+use std::io::{Read, Write, BufRead, BufReader, BufWriter};
+use std::io::Result as IOResult;
+use std::path::{Path, PathBuf};
+
+use sha2::{Digest, Sha256};
+
+use zoneinfo_parse::transitions::{FixedTimespanSet, FixedTimespan};
+
+use data_crate::{Dataset, Options};
+use system::{Filesystem, create_dirs};
+
+
+/// The version of the cache file format. Bump this whenever the format (or
+/// the way transitions get computed) changes, so old entries get ignored.
+const CACHE_FORMAT_VERSION: u32 = 2;
+
+
+/// A directory of cached datasets.
+#[derive(PartialEq, Debug, Clone)]
+pub struct Cache {
+
+    /// The directory the cache files live in.
+    dir: PathBuf,
+}
+
+impl Cache {
+
+    /// Creates a cache that reads and writes files in the given directory,
+    /// which gets created the first time something is stored. All of its
+    /// files get read and written through the filesystem passed to each
+    /// method.
+    pub fn new<P>(dir: P) -> Cache
+    where P: Into<PathBuf> {
+        Cache { dir: dir.into() }
+    }
+
+    /// Computes the cache key for the given input files, based on their
+    /// contents and the options used to build the dataset.
+    ///
+    /// Returns an error if any of the files can’t be read.
+    pub fn key<F: Filesystem>(&self, fs: &F, input_file_paths: &[String], options: &Options) -> IOResult<String> {
+        let mut hasher = Sha256::new();
+        hash_field(&mut hasher, CACHE_FORMAT_VERSION.to_string().as_bytes());
+        hash_field(&mut hasher, env!("CARGO_PKG_VERSION").as_bytes());
+        hash_field(&mut hasher, format!("{:?}", options).as_bytes());
+
+        for path in input_file_paths {
+            let mut contents = Vec::new();
+            let mut file = try!(fs.open(Path::new(path)));
+            let _ = try!(file.read_to_end(&mut contents));
+            hash_field(&mut hasher, &contents);
+        }
+
+        Ok(hasher.finalize().iter().map(|b| format!("{:02x}", b)).collect())
+    }
+
+    /// Loads the dataset stored under the given key, if there is one.
+    pub fn load<F: Filesystem>(&self, fs: &F, key: &str) -> Option<Dataset> {
+        let file = match fs.open(&self.path(key)) {
+            Ok(f)   => f,
+            Err(_)  => return None,
+        };
+
+        read_dataset(BufReader::new(file))
+    }
+
+    /// Stores the dataset under the given key, replacing anything that was
+    /// there before.
+    pub fn store<F: Filesystem>(&self, fs: &F, key: &str, dataset: &Dataset) -> IOResult<()> {
+        try!(create_dirs(fs, &self.dir));
+
+        // Write to a temporary file first, then move it into place, so a
+        // half-written file can never be read back.
+        let temp_path = self.path(key).with_extension("tmp");
+        {
+            let mut w = BufWriter::new(try!(fs.create(&temp_path)));
+            try!(write_dataset(&mut w, dataset));
+            try!(w.flush());
+        }
+
+        fs.rename(&temp_path, &self.path(key))
+    }
+
+    /// Returns the path of the file for the given key.
+    fn path(&self, key: &str) -> PathBuf {
+        self.dir.join(key).with_extension("dataset")
+    }
+}
+
+
+/// Adds one of the inputs to the hash, preceded by its length, so the
+/// boundaries between inputs can’t shift without changing the key.
+fn hash_field(hasher: &mut Sha256, bytes: &[u8]) {
+    hasher.update((bytes.len() as u64).to_le_bytes());
+    hasher.update(bytes);
+}
+
+/// Writes a dataset in the cache file format.
+fn write_dataset<W: Write>(w: &mut W, dataset: &Dataset) -> IOResult<()> {
+    for (name, set) in &dataset.zonesets {
+        try!(writeln!(w, "zone {}", name));
+        try!(writeln!(w, "first {}", describe_timespan(&set.first)));
+
+        for &(time, ref timespan) in &set.rest {
+            try!(writeln!(w, "at {} {}", time, describe_timespan(timespan)));
+        }
+    }
+
+    for (name, target) in &dataset.links {
+        try!(writeln!(w, "link {} {}", name, target));
+    }
+
+    Ok(())
+}
+
+/// Writes the UTC offset, DST offset, DST flag, and name fields of a
+/// timespan.
+fn describe_timespan(timespan: &FixedTimespan) -> String {
+    format!("{} {} {} {}", timespan.utc_offset, timespan.dst_offset, if timespan.is_dst { 1 } else { 0 }, timespan.name)
+}
+
+/// Reads a dataset in the cache file format, returning `None` if anything
+/// about it is wrong.
+fn read_dataset<R: BufRead>(r: R) -> Option<Dataset> {
+    let mut dataset = Dataset::default();
+    let mut current: Option<(String, FixedTimespanSet)> = None;
+
+    for line in r.lines() {
+        let line = match line {
+            Ok(l)   => l,
+            Err(_)  => return None,
+        };
+
+        let fields: Vec<&str> = line.split(' ').collect();
+        match (fields[0], fields.len()) {
+            ("zone", 2) => {
+                if let Some((name, set)) = current.take() {
+                    let _ = dataset.zonesets.insert(name, set);
+                }
+
+                let placeholder = FixedTimespan { utc_offset: 0, dst_offset: 0, is_dst: false, name: String::new() };
+                current = Some((fields[1].to_owned(), FixedTimespanSet { first: placeholder, rest: Vec::new() }));
+            },
+
+            ("first", 5) => {
+                let set = match current { Some((_, ref mut set)) => set, None => return None };
+                set.first = match read_timespan(&fields[1..]) { Some(t) => t, None => return None };
+            },
+
+            ("at", 6) => {
+                let set = match current { Some((_, ref mut set)) => set, None => return None };
+                let time = match fields[1].parse() { Ok(t) => t, Err(_) => return None };
+                let timespan = match read_timespan(&fields[2..]) { Some(t) => t, None => return None };
+                set.rest.push((time, timespan));
+            },
+
+            ("link", 3) => {
+                let _ = dataset.links.insert(fields[1].to_owned(), fields[2].to_owned());
+            },
+
+            _ => return None,
+        }
+    }
+
+    if let Some((name, set)) = current.take() {
+        let _ = dataset.zonesets.insert(name, set);
+    }
+
+    Some(dataset)
+}
+
+/// Reads the UTC offset, DST offset, DST flag, and name fields of a
+/// timespan.
+fn read_timespan(fields: &[&str]) -> Option<FixedTimespan> {
+    let is_dst = match fields[2] {
+        "0"  => false,
+        "1"  => true,
+        _    => return None,
+    };
+
+    match (fields[0].parse(), fields[1].parse()) {
+        (Ok(utc_offset), Ok(dst_offset)) => Some(FixedTimespan {
+            utc_offset: utc_offset,
+            dst_offset: dst_offset,
+            is_dst:     is_dst,
+            name:       fields[3].to_owned(),
+        }),
+        _ => None,
+    }
+}
+
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use zoneinfo_parse::transitions::{FixedTimespanSet, FixedTimespan};
+    use data_crate::{Dataset, Options};
+    use system::MemoryFilesystem;
+
+    fn dataset() -> Dataset {
+        let mut dataset = Dataset::default();
+        let _ = dataset.zonesets.insert("Test/Zone".to_owned(), FixedTimespanSet {
+            first: FixedTimespan { utc_offset: -75, dst_offset: 0, is_dst: false, name: "LMT".to_owned() },
+            rest: vec![
+                (-3_852_662_325, FixedTimespan { utc_offset: 0, dst_offset: 0,    is_dst: false, name: "GMT".to_owned() }),
+                (-1_691_964_000, FixedTimespan { utc_offset: 0, dst_offset: 3600, is_dst:  true, name: "BST".to_owned() }),
+            ],
+        });
+        let _ = dataset.links.insert("Test/Link".to_owned(), "Test/Zone".to_owned());
+        dataset
+    }
+
+    #[test]
+    fn round_trip() {
+        let mut buf = Vec::new();
+        write_dataset(&mut buf, &dataset()).unwrap();
+        assert_eq!(read_dataset(&buf[..]), Some(dataset()));
+    }
+
+    #[test]
+    fn keys() {
+        let fs = MemoryFilesystem::new();
+        fs.add_file("tz/europe", "Zone  Europe/London  0:00  -  GMT\n");
+        fs.add_file("tz/asia", "Zone  Asia/Tokyo  9:00  -  JST\n");
+        let cache = Cache::new("cache");
+        let inputs = vec![ "tz/europe".to_owned(), "tz/asia".to_owned() ];
+
+        let key = cache.key(&fs, &inputs, &Options::default()).unwrap();
+        assert_eq!(key.len(), 64);
+        assert!(key.chars().all(|c| c.is_digit(16)));
+        assert_eq!(cache.key(&fs, &inputs, &Options::default()).unwrap(), key);
+
+        // Changing the contents of any file changes the key.
+        fs.add_file("tz/asia", "Zone  Asia/Tokyo  9:00  -  JST  1888\n");
+        assert!(cache.key(&fs, &inputs, &Options::default()).unwrap() != key);
+
+        assert!(cache.key(&fs, &[ "tz/africa".to_owned() ], &Options::default()).is_err());
+    }
+
+    #[test]
+    fn store_then_load() {
+        let fs = MemoryFilesystem::new();
+        let cache = Cache::new("cache/datasets");
+        assert_eq!(cache.load(&fs, "key"), None);
+
+        cache.store(&fs, "key", &dataset()).unwrap();
+        assert_eq!(cache.load(&fs, "key"), Some(dataset()));
+        assert_eq!(fs.paths(), vec![ PathBuf::from("cache"), PathBuf::from("cache/datasets"), PathBuf::from("cache/datasets/key.dataset") ]);
+
+        // A corrupt file is treated as missing.
+        fs.add_file("cache/datasets/key.dataset", "zone\nwhat");
+        assert_eq!(cache.load(&fs, "key"), None);
+    }
+
+    #[test]
+    fn garbage() {
+        assert_eq!(read_dataset(&b"zone\nwhat"[..]), None);
+    }
+}
@@ -0,0 +1,172 @@
+//! Writing a dataset in the layout that the `chrono-tz` crate expects.
+//!
+//! `chrono-tz` compiles the tz database in its build script into a file
+//! called `timezones.rs`, which it includes into its `timezones` module.
+//! This writes the same file, so the builder can stand in for that build
+//! script: a `Tz` enum with a variant for every zone and link, a `FromStr`
+//! implementation backed by a `phf` map, `name`, `Debug`, and `Display`,
+//! and a `TimeSpans` implementation that returns each zone’s transitions in
+//! `chrono-tz`’s own `FixedTimespanSet` type.
+//!
+//! Variants are named after their zones the way `chrono-tz` names them:
+//! slashes become double underscores, `+` becomes `Plus`, and `-` either
+//! becomes `Minus` (before a digit) or gets removed, so `Etc/GMT-10`
+//! becomes `Etc__GMTMinus10` and `America/Port-au-Prince` becomes
+//! `America__PortauPrince`.
+
+use std::io::Write;
+use std::io::Result as IOResult;
+
+use phf_codegen::Map as PHFMap;
+
+use zoneinfo_parse::transitions::FixedTimespan;
+
+use data_crate::Dataset;
+
+
+/// The imports at the top of the generated file.
+const CHRONO_TZ_HEADER: &'static str = r##"
+use core::fmt::{self, Debug, Display, Formatter};
+use core::str::FromStr;
+
+use ::timezone_impl::{TimeSpans, FixedTimespanSet, FixedTimespan};
+"##;
+
+
+/// Writes every zone and link in the dataset as a `chrono-tz` timezones
+/// file. Links that don’t link to a zone get left out, as there’s nothing
+/// for their variants’ timespans to return.
+pub fn write_chrono_tz<W: Write>(w: &mut W, dataset: &Dataset) -> IOResult<()> {
+    let names: Vec<&str> = dataset.names().into_iter().filter(|n| dataset.timespans(n).is_some()).collect();
+    try!(writeln!(w, "{}", CHRONO_TZ_HEADER));
+
+    try!(writeln!(w, "/// TimeZones built at compile time from the tz database."));
+    try!(writeln!(w, "#[derive(Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]"));
+    try!(writeln!(w, "#[allow(non_camel_case_types)]"));
+    try!(writeln!(w, "pub enum Tz {{"));
+    for name in &names {
+        try!(writeln!(w, "    /// {}\n    {},", name, variant_name(name)));
+    }
+    try!(writeln!(w, "}}\n"));
+
+    try!(write!(w, "static TIMEZONES: ::phf::Map<&'static str, Tz> = "));
+    let mut phf_map = PHFMap::new();
+    for name in &names {
+        phf_map.entry(*name, &format!("Tz::{}", variant_name(name)));
+    }
+    try!(phf_map.build(w));
+    try!(writeln!(w, ";\n"));
+
+    try!(writeln!(w, "impl FromStr for Tz {{"));
+    try!(writeln!(w, "    type Err = String;"));
+    try!(writeln!(w, "    fn from_str(s: &str) -> Result<Self, Self::Err> {{"));
+    try!(writeln!(w, "        TIMEZONES.get(s).cloned().ok_or(s.to_string())"));
+    try!(writeln!(w, "    }}"));
+    try!(writeln!(w, "}}\n"));
+
+    try!(writeln!(w, "impl Tz {{"));
+    try!(writeln!(w, "    pub fn name(self) -> &'static str {{"));
+    try!(writeln!(w, "        match self {{"));
+    for name in &names {
+        try!(writeln!(w, "            Tz::{} => {:?},", variant_name(name), name));
+    }
+    try!(writeln!(w, "        }}"));
+    try!(writeln!(w, "    }}"));
+    try!(writeln!(w, "}}\n"));
+
+    try!(writeln!(w, "impl Debug for Tz {{"));
+    try!(writeln!(w, "    fn fmt(&self, f: &mut Formatter) -> fmt::Result {{"));
+    try!(writeln!(w, "        f.write_str(self.name().as_ref())"));
+    try!(writeln!(w, "    }}"));
+    try!(writeln!(w, "}}\n"));
+
+    try!(writeln!(w, "impl Display for Tz {{"));
+    try!(writeln!(w, "    fn fmt(&self, f: &mut Formatter) -> fmt::Result {{"));
+    try!(writeln!(w, "        f.write_str(self.name().as_ref())"));
+    try!(writeln!(w, "    }}"));
+    try!(writeln!(w, "}}\n"));
+
+    try!(writeln!(w, "impl TimeSpans for Tz {{"));
+    try!(writeln!(w, "    fn timespans(&self) -> FixedTimespanSet {{"));
+    try!(writeln!(w, "        match *self {{"));
+    for name in &names {
+        let set = dataset.timespans(name).unwrap();
+        try!(writeln!(w, "            Tz::{} => {{", variant_name(name)));
+        try!(writeln!(w, "                const REST: &[(i64, FixedTimespan)] = &["));
+        for &(at, ref timespan) in &set.rest {
+            try!(writeln!(w, "                    ({}, {}),", at, timespan_literal(timespan)));
+        }
+        try!(writeln!(w, "                ];"));
+        try!(writeln!(w, "                FixedTimespanSet {{ first: {}, rest: REST }}", timespan_literal(&set.first)));
+        try!(writeln!(w, "            }},"));
+    }
+    try!(writeln!(w, "        }}"));
+    try!(writeln!(w, "    }}"));
+    try!(writeln!(w, "}}\n"));
+
+    try!(writeln!(w, "/// An array of every known variant, for iterating over known timezones."));
+    try!(writeln!(w, "pub static TZ_VARIANTS: [Tz; {}] = [", names.len()));
+    for name in &names {
+        try!(writeln!(w, "    Tz::{},", variant_name(name)));
+    }
+    writeln!(w, "];")
+}
+
+/// Returns a timespan as a `chrono-tz` `FixedTimespan` expression.
+fn timespan_literal(timespan: &FixedTimespan) -> String {
+    format!("FixedTimespan {{ utc_offset: {}, dst_offset: {}, name: {:?} }}",
+            timespan.utc_offset, timespan.dst_offset, timespan.name)
+}
+
+/// Returns the name of the `Tz` variant for a zone, as `chrono-tz` would
+/// name it.
+pub fn variant_name(zone_name: &str) -> String {
+    let name = zone_name.replace("/", "__").replace("+", "Plus");
+
+    match name.find('-') {
+        Some(pos) if name[pos + 1 ..].chars().next().map_or(false, |c| c.is_numeric()) => name.replace("-", "Minus"),
+        Some(_)                                                                       => name.replace("-", ""),
+        None                                                                          => name,
+    }
+}
+
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use data_crate::Dataset;
+    use zoneinfo_parse::transitions::{FixedTimespan, FixedTimespanSet};
+
+    #[test]
+    fn variants() {
+        assert_eq!(variant_name("Europe/London"), "Europe__London");
+        assert_eq!(variant_name("Etc/GMT-10"), "Etc__GMTMinus10");
+        assert_eq!(variant_name("Etc/GMT+3"), "Etc__GMTPlus3");
+        assert_eq!(variant_name("America/Port-au-Prince"), "America__PortauPrince");
+    }
+
+    #[test]
+    fn timespans() {
+        let mut dataset = Dataset::default();
+        let _ = dataset.zonesets.insert("Europe/London".to_owned(), FixedTimespanSet {
+            first: FixedTimespan::new(-75, 0, "LMT"),
+            rest:  vec![ (-3_852_662_325, FixedTimespan::new(0, 0, "GMT")) ],
+        });
+        let _ = dataset.links.insert("GB".to_owned(), "Europe/London".to_owned());
+        let _ = dataset.links.insert("Nowhere".to_owned(), "Does/Not/Exist".to_owned());
+
+        let mut buf = Vec::new();
+        write_chrono_tz(&mut buf, &dataset).unwrap();
+        let source = String::from_utf8(buf).unwrap();
+
+        assert!(source.contains("    /// Europe/London\n    Europe__London,\n    /// GB\n    GB,\n}"));
+        assert!(source.contains("            Tz::GB => \"GB\",\n"));
+        assert!(source.contains("            Tz::GB => {\n\
+                                 \x20               const REST: &[(i64, FixedTimespan)] = &[\n\
+                                 \x20                   (-3852662325, FixedTimespan { utc_offset: 0, dst_offset: 0, name: \"GMT\" }),\n\
+                                 \x20               ];\n\
+                                 \x20               FixedTimespanSet { first: FixedTimespan { utc_offset: -75, dst_offset: 0, name: \"LMT\" }, rest: REST }\n"));
+        assert!(source.contains("pub static TZ_VARIANTS: [Tz; 2] = [\n    Tz::Europe__London,\n    Tz::GB,\n];"));
+        assert!(!source.contains("Nowhere"));
+    }
+}
@@ -0,0 +1,185 @@
+//! The commands the builder understands, and how to use each one.
+//!
+//! The first argument names the command to run, such as `build` or `dump`,
+//! and the rest go to that command, which parses them with its own set of
+//! options. When the first argument isn’t a command, the whole lot goes to
+//! `build`, so the builder can still be run the way it was before it had
+//! any other commands.
+//!
+//! Running the binary with `help`, or with `--help` after a command, prints
+//! the list of commands or the options a command takes. Running it with no
+//! arguments at all prints the list of commands too, as there’s nothing for
+//! `build` to do without them. When the arguments don’t fit, the error says
+//! which command they were for, so it can point at the right usage.
+
+use std::ffi::OsString;
+
+use getopts;
+
+use errors::CliError;
+
+
+/// One of the things the builder can do.
+#[derive(Debug)]
+pub struct Command {
+
+    /// The word that picks this command, given as the first argument.
+    pub name: &'static str,
+
+    /// The arguments that go after the options, as they’re shown in the
+    /// usage, such as `ZONE INPUT...`.
+    pub arguments: &'static str,
+
+    /// A one-line description of what the command does.
+    pub summary: &'static str,
+
+    /// Returns the options the command takes.
+    pub options: fn() -> getopts::Options,
+}
+
+/// Every command, in alphabetical order.
+pub static COMMANDS: &'static [Command] = &[
+    Command { name: "build",           arguments: "INPUT...",                summary: "generate a data crate, or another format of zone data",     options: ::build::options },
+    Command { name: "compile",         arguments: "INPUT...",                summary: "write a directory of TZif files, like zic",                 options: ::compile::options },
+    Command { name: "convert",         arguments: "INPUT...",                summary: "convert zoneinfo source between the fat and slim forms",    options: ::convert::options },
+    Command { name: "country-offsets", arguments: "INPUT...",                summary: "write a CSV of each country’s history of UTC offsets",      options: ::country_offsets::options },
+    Command { name: "diff",            arguments: "OLD NEW",                 summary: "list the upcoming transitions two releases disagree on",    options: ::diff::options },
+    Command { name: "dump",            arguments: "ZONE INPUT...",           summary: "write one zone’s transitions, like zdump -v",               options: ::dump::options },
+    Command { name: "fetch",           arguments: "",                        summary: "download the latest release of the tz database",            options: ::fetch::options },
+    Command { name: "fmt",             arguments: "FILE...",                 summary: "rewrite zoneinfo source files in a consistent layout",      options: ::reformat::options },
+    Command { name: "ics",             arguments: "INPUT...",                summary: "write a calendar of upcoming clock changes",                options: ::ics::options },
+    Command { name: "lint",            arguments: "INPUT...",                summary: "look for things in the input that are probably mistakes",   options: ::lint::options },
+    Command { name: "query",           arguments: "ZONE DATETIME INPUT...",  summary: "say what a local time means in a zone",                     options: ::query::options },
+    Command { name: "timeline",        arguments: "ZONE INPUT...",           summary: "draw a zone’s offsets over the years as an SVG image",      options: ::timeline::options },
+];
+
+/// Returns the command with the given name, if there is one.
+pub fn find(name: &str) -> Option<&'static Command> {
+    COMMANDS.iter().find(|c| c.name == name)
+}
+
+
+impl Command {
+
+    /// Returns how to run this command, with a description of each of its
+    /// options.
+    pub fn usage(&self) -> String {
+        let synopsis = match self.arguments {
+            ""         => format!("build-data-crate {} [OPTIONS]", self.name),
+            arguments  => format!("build-data-crate {} [OPTIONS] {}", self.name, arguments),
+        };
+
+        let brief = format!("Usage: {}\n\n{}.", synopsis, capitalise(self.summary));
+        (self.options)().usage(&brief)
+    }
+
+    /// Parses the arguments that come after the command’s name, with the
+    /// command’s options.
+    pub fn parse(&self, args: &[OsString]) -> Result<getopts::Matches, CliError> {
+        (self.options)().parse(args).map_err(|e| CliError::Usage(self.name, e))
+    }
+
+    /// Returns the long names of the options the command takes, without
+    /// the leading dashes, in the order they’re listed in the usage.
+    pub fn option_names(&self) -> Vec<String> {
+        // getopts doesn’t say what options it has, only how to describe
+        // them, so the names get picked out of each option’s row of the
+        // usage, where the long name is the first word with two dashes.
+        let names = (self.options)().usage_with_format(|rows| {
+            let names: Vec<String> = rows.filter_map(|row| row.split_whitespace().find(|w| w.starts_with("--"))
+                                                          .map(|w| w.trim_left_matches('-').to_owned()))
+                                         .collect();
+            names.join("\n")
+        });
+
+        names.lines().map(|name| name.to_owned()).collect()
+    }
+
+    /// Returns the error for when a free argument the command needs, such
+    /// as `ZONE`, wasn’t given.
+    pub fn missing(&self, argument: &'static str) -> CliError {
+        CliError::MissingArgument(self.name, argument)
+    }
+}
+
+
+/// Returns how to run the builder, with a list of its commands.
+pub fn usage() -> String {
+    let mut usage = "Usage: build-data-crate [COMMAND] [OPTIONS] [ARGUMENTS...]\n\nCommands:\n".to_owned();
+    for command in COMMANDS {
+        usage.push_str(&format!("    {:<16} {}\n", command.name, command.summary));
+    }
+
+    usage.push_str("\nWithout a command, the builder runs `build`. Run `build-data-crate COMMAND --help` to see a command’s options.\n");
+    usage
+}
+
+/// Returns the usage to print if the given arguments ask for help, by
+/// starting with `help` (optionally followed by a command), or by including
+/// `--help` or `-h`, or if there aren’t any arguments.
+pub fn help(args: &[OsString]) -> Option<String> {
+    if args.is_empty() {
+        return Some(usage());
+    }
+
+    let args: Vec<&str> = args.iter().filter_map(|a| a.to_str()).collect();
+
+    let asked_about = match args.first() {
+        Some(&"help")                                            => args.get(1),
+        _ if args.iter().any(|a| *a == "--help" || *a == "-h")  => args.first(),
+        _                                                        => return None,
+    };
+
+    match asked_about.and_then(|name| find(name)) {
+        Some(command)  => Some(command.usage()),
+        None           => Some(usage()),
+    }
+}
+
+fn capitalise(text: &str) -> String {
+    let mut chars = text.chars();
+    match chars.next() {
+        Some(first)  => first.to_uppercase().chain(chars).collect(),
+        None         => String::new(),
+    }
+}
+
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn args(args: &[&str]) -> Vec<OsString> {
+        args.iter().map(OsString::from).collect()
+    }
+
+    #[test]
+    fn sorted() {
+        let names: Vec<&str> = COMMANDS.iter().map(|c| c.name).collect();
+        let mut sorted = names.clone();
+        sorted.sort();
+        assert_eq!(names, sorted);
+    }
+
+    #[test]
+    fn command_usage() {
+        let usage = find("dump").unwrap().usage();
+        assert!(usage.starts_with("Usage: build-data-crate dump [OPTIONS] ZONE INPUT...\n\nWrite one zone’s transitions, like zdump -v.\n"));
+        assert!(usage.contains("--output FILE"));
+    }
+
+    #[test]
+    fn asking_for_help() {
+        assert_eq!(help(&args(&[])), Some(usage()));
+        assert_eq!(help(&args(&[ "help" ])), Some(usage()));
+        assert_eq!(help(&args(&[ "help", "query" ])), Some(find("query").unwrap().usage()));
+        assert_eq!(help(&args(&[ "diff", "--help" ])), Some(find("diff").unwrap().usage()));
+        assert_eq!(help(&args(&[ "--output", "out", "-h" ])), Some(usage()));
+        assert_eq!(help(&args(&[ "--output", "out", "tz" ])), None);
+    }
+
+    #[test]
+    fn option_names() {
+        assert_eq!(find("compile").unwrap().option_names(), vec![ "output", "links", "min-year", "max-year" ]);
+    }
+}
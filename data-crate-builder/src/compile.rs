@@ -0,0 +1,245 @@
+//! Compiling zoneinfo source into a directory of TZif files, as `zic` does.
+//!
+//! The output has the same layout as `/usr/share/zoneinfo`: one TZif file
+//! per zone, in directories for the parts of its name before the last
+//! slash, so `Europe/London` ends up in `Europe/London` under the output
+//! directory. Each link becomes either a hard link to the file of the zone
+//! it points to, which is what `zic` does by default, or a copy of it, for
+//! filesystems or archive formats that can’t store hard links.
+//!
+//! Like the `tzif` format of the data crate, the files have no TZ string
+//! footer, so the transitions have to extend far enough into the future to
+//! be useful: use `--max-year` to choose how far that is.
+
+use std::io::{BufWriter, Write};
+use std::io::Result as IOResult;
+use std::path::{Path, PathBuf};
+
+use getopts;
+
+use zoneinfo_parse::table::Table;
+use zoneinfo_parse::transitions::{TableTransitions, TransitionOptions};
+
+use commands;
+use data_crate::read_table;
+use errors::CliError;
+use output::zone_file_path;
+use system::Filesystem;
+use tzif::write_tzif;
+use {Report, number_option};
+
+
+/// How links end up in the output directory.
+#[derive(PartialEq, Debug, Copy, Clone)]
+pub enum LinkMode {
+
+    /// As hard links to the zone’s file, so they take up no extra space.
+    Hard,
+
+    /// As separate copies of the zone’s file.
+    Copy,
+}
+
+impl LinkMode {
+
+    /// Returns the link mode with the given name, as it’s given to the
+    /// `--links` option.
+    pub fn from_name(name: &str) -> Option<LinkMode> {
+        match name {
+            "hard"  => Some(LinkMode::Hard),
+            "copy"  => Some(LinkMode::Copy),
+             _      => None,
+        }
+    }
+}
+
+
+/// Writes a TZif file for every zone in the table into the given directory,
+/// then a hard link or copy for every link, creating any directories that
+/// don’t exist yet (including the output directory itself).
+pub fn compile<F: Filesystem>(fs: &F, table: &Table, output: &Path, options: &TransitionOptions, links: LinkMode) -> Result<Report, CliError> {
+
+    // Check that every name can be used as a file name first, so a bad one
+    // doesn’t leave a half-compiled directory behind.
+    for name in table.zonesets.keys().chain(table.links.keys()) {
+        let _ = try!(zone_file_path(name));
+    }
+
+    let mut zone_names: Vec<&String> = table.zonesets.keys().collect();
+    zone_names.sort();
+
+    for name in zone_names {
+        let set = table.timespans(name, options).unwrap();
+        let path = try!(zone_path(fs, output, name));
+
+        let mut w = BufWriter::new(try!(fs.create(&path)));
+        try!(write_tzif(&mut w, &set));
+        try!(w.flush());
+    }
+
+    let mut link_names: Vec<&String> = table.links.keys().collect();
+    link_names.sort();
+
+    for name in &link_names {
+        let target = try!(resolve_link(table, name));
+        let original = output.join(try!(zone_file_path(target)));
+        let path = try!(zone_path(fs, output, name));
+
+        match links {
+            LinkMode::Hard => try!(fs.hard_link(&original, &path)),
+            LinkMode::Copy => {
+                let contents = try!(fs.read(&original));
+                let mut w = try!(fs.create(&path));
+                try!(w.write_all((*contents).as_ref()));
+            },
+        }
+    }
+
+    Ok(Report {
+        zone_count: table.zonesets.len() + link_names.len(),
+        link_count: link_names.len(),
+        warnings:   Vec::new(),
+        output:     String::new(),
+    })
+}
+
+/// Follows a link, and any links it points to, until reaching a zone,
+/// returning that zone’s name.
+fn resolve_link<'table>(table: &'table Table, name: &'table str) -> Result<&'table str, CliError> {
+    let mut current = name;
+
+    // Circular links get rejected when the table is built, so this only
+    // stops following them in case one got through.
+    for _ in 0 .. table.links.len() + 1 {
+        if table.zonesets.contains_key(current) {
+            return Ok(current);
+        }
+
+        current = match table.links.get(current) {
+            Some(target) => target,
+            None         => break,
+        };
+    }
+
+    Err(CliError::UnknownZone(current.to_owned()))
+}
+
+/// Returns the path of the file for the zone with the given name, creating
+/// the directories it goes in if they don’t exist yet. Any file already
+/// there gets removed, rather than written over, in case it’s a hard link
+/// to another zone’s file from an earlier run.
+fn zone_path<F: Filesystem>(fs: &F, output: &Path, name: &str) -> IOResult<PathBuf> {
+    let path = output.join(try!(zone_file_path(name)));
+
+    let mut dirs = Vec::new();
+    let mut next = path.parent();
+    while let Some(dir) = next {
+        if dir == Path::new("") || fs.is_dir(dir) {
+            break;
+        }
+
+        dirs.push(dir);
+        next = dir.parent();
+    }
+
+    for dir in dirs.into_iter().rev() {
+        try!(fs.create_dir(dir));
+    }
+
+    if fs.is_file(&path) {
+        try!(fs.remove_file(&path));
+    }
+
+    Ok(path)
+}
+
+
+/// The options the `compile` mode takes.
+pub fn options() -> getopts::Options {
+    let mut opts = getopts::Options::new();
+    opts.reqopt("o", "output", "directory to write the TZif files into", "DIR");
+    opts.optopt("", "links", "how to write links: hard or copy (default: hard)", "MODE");
+    opts.optopt("", "min-year", "first year to expand daylight-saving rules in", "YEAR");
+    opts.optopt("", "max-year", "last year to expand daylight-saving rules in", "YEAR");
+    opts
+}
+
+/// Runs the `compile` mode of the builder with the given arguments, which
+/// come after the word `compile`. The free arguments are the input files.
+pub fn run<F: Filesystem>(args: &[::std::ffi::OsString], fs: &F) -> Result<Report, CliError> {
+    let command = commands::find("compile").unwrap();
+    let matches = try!(command.parse(args));
+
+    let links = match matches.opt_str("links") {
+        Some(name) => try!(LinkMode::from_name(&name).ok_or(CliError::InvalidArgument("links", name))),
+        None       => LinkMode::Hard,
+    };
+
+    let defaults = TransitionOptions::default();
+    let options = TransitionOptions {
+        min_year: try!(number_option(&matches, "min-year", defaults.min_year)),
+        max_year: try!(number_option(&matches, "max-year", defaults.max_year)),
+        .. defaults
+    };
+
+    let (table, warnings) = try!(read_table(fs, &matches.free));
+    let output = matches.opt_str("output").unwrap();
+
+    let report = try!(compile(fs, &table, Path::new(&output), &options, links));
+    Ok(Report { warnings: warnings, .. report })
+}
+
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use system::MemoryFilesystem;
+    use zoneinfo_parse::table::TableBuilder;
+    use zoneinfo_parse::line::Line;
+
+    fn table(text: &str) -> Table {
+        let mut builder = TableBuilder::new();
+        for line in text.lines() {
+            match Line::from_str(line).unwrap() {
+                Line::Zone(zone)  => builder.add_zone_line(zone).unwrap(),
+                Line::Link(link)  => builder.add_link_line(link).unwrap(),
+                _                 => {},
+            }
+        }
+        builder.build()
+    }
+
+    #[test]
+    fn layout() {
+        let fs = MemoryFilesystem::new();
+        let table = table("Zone  Europe/London  0:00  -  GMT\n\
+                           Link  Europe/London  GB\n\
+                           Link  GB  Etc/Old\n");
+
+        let report = compile(&fs, &table, Path::new("out/zoneinfo"), &TransitionOptions::default(), LinkMode::Hard).unwrap();
+        assert_eq!((report.zone_count, report.link_count), (3, 2));
+
+        let zone = fs.bytes("out/zoneinfo/Europe/London").unwrap();
+        assert!(zone.starts_with(b"TZif2"));
+        assert_eq!(fs.bytes("out/zoneinfo/GB"), Some(zone.clone()));
+        assert_eq!(fs.bytes("out/zoneinfo/Etc/Old"), Some(zone));
+    }
+
+    #[test]
+    fn names_outside_the_output_directory() {
+        let fs = MemoryFilesystem::new();
+        let table = table("Zone  Test/Zone  1:00  -  TST\n\
+                           Link  Test/Zone  ../../escaped\n");
+
+        let err = compile(&fs, &table, Path::new("out/a/b"), &TransitionOptions::default(), LinkMode::Hard).unwrap_err();
+        assert_eq!(err.to_string(), "IO error: \"../../escaped\": zone name can’t be used as a file name");
+        assert!(fs.paths().is_empty());
+    }
+
+    #[test]
+    fn link_modes() {
+        assert_eq!(LinkMode::from_name("hard"), Some(LinkMode::Hard));
+        assert_eq!(LinkMode::from_name("copy"), Some(LinkMode::Copy));
+        assert_eq!(LinkMode::from_name("symbolic"), None);
+    }
+}
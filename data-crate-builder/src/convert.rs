@@ -0,0 +1,125 @@
+//! Converting zoneinfo source between the fat and slim forms.
+//!
+//! The tz database comes as a set of “fat” source files, one per region,
+//! with the columns lined up and comments explaining where the data came
+//! from. It also comes as a single `tzdata.zi` file in the “slim” form,
+//! which has the same lines with the comments taken out and everything
+//! spelled as briefly as `zic` allows. More and more distributions only
+//! ship the slim file. The `convert` mode reads either form and writes the
+//! other one:
+//!
+//! ```text
+//! Rule	EU	1981	max	-	Mar	lastSun	1:00u	1:00	S
+//! R EU 1981 ma - Mar lastSu 1u 1 S
+//! ```
+//!
+//! Either way, the output has the same zones, rules, and links as the
+//! input, but it doesn’t keep the original’s layout or comments: the fat
+//! form is written the same way as `Table::write_source` writes it.
+
+use std::io::BufWriter;
+use std::path::Path;
+
+use getopts;
+
+use commands;
+use data_crate::read_table;
+use errors::CliError;
+use system::Filesystem;
+use Report;
+
+
+/// The two forms zoneinfo source can be written in.
+#[derive(PartialEq, Debug, Copy, Clone)]
+pub enum Form {
+
+    /// Several files with every keyword spelled out, such as `europe`.
+    Fat,
+
+    /// One file with everything abbreviated, such as `tzdata.zi`.
+    Slim,
+}
+
+impl Form {
+
+    /// Parses the value of the `--to` option.
+    pub fn from_name(name: &str) -> Option<Form> {
+        match name {
+            "fat"   => Some(Form::Fat),
+            "slim"  => Some(Form::Slim),
+            _       => None,
+        }
+    }
+
+    /// Guesses which form to convert the given input files to: a single
+    /// `.zi` file is probably slim, so it gets converted to the fat form,
+    /// and anything else gets converted to the slim form.
+    pub fn opposite_of(inputs: &[String]) -> Form {
+        if inputs.len() == 1 && inputs[0].ends_with(".zi") {
+            Form::Fat
+        }
+        else {
+            Form::Slim
+        }
+    }
+}
+
+
+/// The options the `convert` mode takes.
+pub fn options() -> getopts::Options {
+    let mut opts = getopts::Options::new();
+    opts.reqopt("o", "output", "file to write the converted source to", "FILE");
+    opts.optopt("", "to", "form to convert to: ‘fat’ or ‘slim’", "FORM");
+    opts
+}
+
+/// Runs the `convert` mode of the builder with the given arguments, which
+/// come after the word `convert`. The free arguments are the input files,
+/// in either form. Without `--to`, the output is in whichever form the
+/// input probably isn’t in.
+pub fn run<F: Filesystem>(args: &[::std::ffi::OsString], fs: &F) -> Result<Report, CliError> {
+    let command = commands::find("convert").unwrap();
+    let matches = try!(command.parse(args));
+    let form = match matches.opt_str("to") {
+        Some(name) => match Form::from_name(&name) {
+            Some(f) => f,
+            None    => return Err(CliError::InvalidArgument("to", name)),
+        },
+        None => Form::opposite_of(&matches.free),
+    };
+
+    let (table, warnings) = try!(read_table(fs, &matches.free));
+
+    let mut w = BufWriter::new(try!(fs.create(Path::new(&matches.opt_str("output").unwrap()))));
+    match form {
+        Form::Fat   => try!(table.write_source(&mut w)),
+        Form::Slim  => try!(table.write_slim_source(&mut w)),
+    }
+
+    Ok(Report {
+        zone_count: table.zonesets.len() + table.links.len(),
+        link_count: table.links.len(),
+        warnings:   warnings,
+        output:     String::new(),
+    })
+}
+
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn names() {
+        assert_eq!(Form::from_name("fat"), Some(Form::Fat));
+        assert_eq!(Form::from_name("slim"), Some(Form::Slim));
+        assert_eq!(Form::from_name("thin"), None);
+    }
+
+    #[test]
+    fn guessing() {
+        assert_eq!(Form::opposite_of(&[ "tzdata.zi".to_owned() ]), Form::Fat);
+        assert_eq!(Form::opposite_of(&[ "europe".to_owned() ]), Form::Slim);
+        assert_eq!(Form::opposite_of(&[ "a.zi".to_owned(), "b.zi".to_owned() ]), Form::Slim);
+    }
+}
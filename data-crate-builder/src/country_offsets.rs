@@ -0,0 +1,181 @@
+//! Exporting the history of each country’s UTC offsets as a CSV file.
+//!
+//! This combines the country codes in `zone1970.tab` with the transitions
+//! computed from the zoneinfo files to produce one row for each period
+//! during which a zone used by a country kept the same UTC offset:
+//!
+//! ```text
+//! country,zone,from,to,offset
+//! GB,Europe/London,,1847-12-01T00:01:15Z,-75
+//! GB,Europe/London,1847-12-01T00:01:15Z,1916-05-21T02:00:00Z,0
+//! ```
+//!
+//! The `from` and `to` columns are UTC times, and are left empty for the
+//! periods that stretch back to the start of time or on into the future. The
+//! `offset` column is the total UTC offset in seconds, including any
+//! daylight-saving time. As with the `ics` mode, transitions that only
+//! change the abbreviation don’t start a new row.
+
+use std::io::{Read, Write, BufWriter};
+use std::io::Result as IOResult;
+use std::path::Path;
+
+use getopts;
+
+use zoneinfo_parse::table::Table;
+use zoneinfo_parse::tab::{parse_zone_tab, ZoneLocation};
+use zoneinfo_parse::transitions::{TableTransitions, TransitionOptions};
+
+use commands;
+use csv::csv_timestamp;
+use data_crate::read_table;
+use errors::CliError;
+use system::Filesystem;
+use Report;
+
+
+/// One period during which a zone used by a country had the same offset.
+#[derive(PartialEq, Debug, Clone)]
+pub struct OffsetPeriod {
+
+    /// The ISO 3166 code of the country.
+    pub country: String,
+
+    /// The name of the zone.
+    pub zone: String,
+
+    /// The Unix timestamp at which the period starts, or `None` if it
+    /// begins before the zone’s first transition.
+    pub from: Option<i64>,
+
+    /// The Unix timestamp at which the period ends, or `None` if it
+    /// carries on after the zone’s last transition.
+    pub to: Option<i64>,
+
+    /// The total UTC offset during the period, in seconds.
+    pub offset: i64,
+}
+
+
+/// Returns every offset period for every country and zone listed in the
+/// given locations, sorted by country, then zone, then time. Returns an
+/// error naming the first zone that isn’t in the table.
+pub fn offset_periods(table: &Table, locations: &[ZoneLocation], options: &TransitionOptions) -> Result<Vec<OffsetPeriod>, String> {
+    let mut periods = Vec::new();
+
+    for location in locations {
+        let set = match table.timespans(&location.zone, options) {
+            Some(s) => s,
+            None    => return Err(location.zone.clone()),
+        };
+
+        // Work out the periods for the zone first, merging together any
+        // consecutive timespans with the same total offset.
+        let mut zone_periods = vec![ (None, set.first.total_offset()) ];
+        for &(at, ref timespan) in &set.rest {
+            if timespan.total_offset() != zone_periods.last().unwrap().1 {
+                zone_periods.push((Some(at), timespan.total_offset()));
+            }
+        }
+
+        for country in &location.countries {
+            for (index, &(from, offset)) in zone_periods.iter().enumerate() {
+                periods.push(OffsetPeriod {
+                    country:  country.clone(),
+                    zone:     location.zone.clone(),
+                    from:     from,
+                    to:       zone_periods.get(index + 1).and_then(|p| p.0),
+                    offset:   offset,
+                });
+            }
+        }
+    }
+
+    periods.sort_by(|a, b| a.country.cmp(&b.country)
+                           .then_with(|| a.zone.cmp(&b.zone))
+                           .then_with(|| a.from.cmp(&b.from)));
+    Ok(periods)
+}
+
+
+/// Writes the given offset periods as CSV, with a header row.
+///
+/// None of the fields can contain commas or quotes, as country codes and
+/// zone names are made of letters, digits, and a few punctuation characters,
+/// so nothing gets quoted.
+pub fn write_csv<W: Write>(w: &mut W, periods: &[OffsetPeriod]) -> IOResult<()> {
+    try!(writeln!(w, "country,zone,from,to,offset"));
+
+    for period in periods {
+        try!(writeln!(w, "{},{},{},{},{}", period.country, period.zone,
+                      csv_timestamp(period.from), csv_timestamp(period.to), period.offset));
+    }
+
+    Ok(())
+}
+
+
+/// The options the `country-offsets` mode takes.
+pub fn options() -> getopts::Options {
+    let mut opts = getopts::Options::new();
+    opts.reqopt("o", "output", "file to write the CSV to", "FILE");
+    opts.reqopt("", "zone-tab", "zone1970.tab file mapping countries to zones", "FILE");
+    opts.optmulti("c", "country", "country code to include (can be repeated; default: all)", "CC");
+    opts
+}
+
+/// Runs the `country-offsets` mode of the builder with the given arguments,
+/// which come after the word `country-offsets`.
+pub fn run<F: Filesystem>(args: &[::std::ffi::OsString], fs: &F) -> Result<Report, CliError> {
+    let command = commands::find("country-offsets").unwrap();
+    let matches = try!(command.parse(args));
+    let tab_path = matches.opt_str("zone-tab").unwrap();
+    let countries = matches.opt_strs("country");
+
+    let mut contents = String::new();
+    let _ = try!(try!(fs.open(Path::new(&tab_path))).read_to_string(&mut contents));
+    let mut locations = try!(parse_zone_tab(&contents).map_err(|e| CliError::Tab(tab_path, e)));
+
+    if !countries.is_empty() {
+        for location in &mut locations {
+            location.countries.retain(|c| countries.contains(c));
+        }
+
+        locations.retain(|l| !l.countries.is_empty());
+    }
+
+    let (table, warnings) = try!(read_table(fs, &matches.free));
+    let periods = try!(offset_periods(&table, &locations, &TransitionOptions::default())
+                           .map_err(CliError::UnknownZone));
+
+    let mut w = BufWriter::new(try!(fs.create(Path::new(&matches.opt_str("output").unwrap()))));
+    try!(write_csv(&mut w, &periods));
+
+    Ok(Report {
+        zone_count: locations.len(),
+        link_count: locations.iter().filter(|l| table.links.contains_key(&l.zone)).count(),
+        warnings:   warnings,
+        output:     String::new(),
+    })
+}
+
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn rows() {
+        let periods = vec![
+            OffsetPeriod { country: "GB".to_owned(), zone: "Europe/London".to_owned(), from: None,                to: Some(-3_852_662_325), offset: -75 },
+            OffsetPeriod { country: "GB".to_owned(), zone: "Europe/London".to_owned(), from: Some(-3_852_662_325), to: None,                offset: 0 },
+        ];
+
+        let mut buf = Vec::new();
+        write_csv(&mut buf, &periods).unwrap();
+        assert_eq!(String::from_utf8(buf).unwrap(),
+                   "country,zone,from,to,offset\n\
+                    GB,Europe/London,,1847-12-01T00:01:15Z,-75\n\
+                    GB,Europe/London,1847-12-01T00:01:15Z,,0\n");
+    }
+}
@@ -0,0 +1,93 @@
+//! Writing a dataset as a CSV file, with one row for each transition in
+//! each zone, for loading into spreadsheets and data analysis tools.
+//!
+//! ```text
+//! zone,at,time,total_offset,utc_offset,dst_offset,abbreviation,is_dst
+//! Europe/London,,,-75,-75,0,LMT,0
+//! Europe/London,-3852662325,1847-12-01T00:01:15Z,0,0,0,GMT,0
+//! ```
+//!
+//! Each zone starts with a row for its first timespan, which has no time
+//! as it doesn’t start with a transition. The `at` column is the Unix
+//! timestamp of the transition, and the `time` column is the same instant
+//! as an ISO 8601 UTC date-time. Links aren’t included, as their rows would
+//! be the same as the zones they link to.
+
+use std::io::Write;
+use std::io::Result as IOResult;
+
+use datetime::{LocalDateTime, DatePiece, TimePiece};
+
+use zoneinfo_parse::transitions::FixedTimespan;
+
+use data_crate::Dataset;
+
+
+/// Writes every transition of every zone in the dataset as CSV, with a
+/// header row.
+///
+/// None of the fields can contain commas or quotes, as zone names and
+/// abbreviations are made of letters, digits, and a few punctuation
+/// characters, so nothing gets quoted.
+pub fn write_csv<W: Write>(w: &mut W, dataset: &Dataset) -> IOResult<()> {
+    try!(writeln!(w, "zone,at,time,total_offset,utc_offset,dst_offset,abbreviation,is_dst"));
+
+    for (name, set) in &dataset.zonesets {
+        try!(write_row(w, name, None, &set.first));
+
+        for &(at, ref timespan) in &set.rest {
+            try!(write_row(w, name, Some(at), timespan));
+        }
+    }
+
+    Ok(())
+}
+
+/// Writes one row, for a timespan that starts at the given time.
+fn write_row<W: Write>(w: &mut W, zone_name: &str, at: Option<i64>, timespan: &FixedTimespan) -> IOResult<()> {
+    writeln!(w, "{},{},{},{},{},{},{},{}", zone_name, at.map(|a| a.to_string()).unwrap_or_else(String::new), csv_timestamp(at),
+             timespan.total_offset(), timespan.utc_offset, timespan.dst_offset, timespan.name,
+             if timespan.is_dst { 1 } else { 0 })
+}
+
+/// Formats a Unix timestamp as an ISO 8601 UTC date-time, or as nothing if
+/// there isn’t one.
+pub fn csv_timestamp(timestamp: Option<i64>) -> String {
+    match timestamp {
+        Some(ts) => {
+            let t = LocalDateTime::at(ts);
+            format!("{:04}-{:02}-{:02}T{:02}:{:02}:{:02}Z", t.year(), t.month().months_from_january() + 1, t.day(),
+                                                          t.hour(), t.minute(), t.second())
+        },
+        None => String::new(),
+    }
+}
+
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use data_crate::Dataset;
+    use zoneinfo_parse::transitions::{FixedTimespan, FixedTimespanSet};
+
+    #[test]
+    fn rows() {
+        let mut dataset = Dataset::default();
+        let _ = dataset.zonesets.insert("Europe/London".to_owned(), FixedTimespanSet {
+            first: FixedTimespan::new(-75, 0, "LMT"),
+            rest:  vec![
+                (-3_852_662_325, FixedTimespan::new(0, 0,    "GMT")),
+                (-1_691_964_000, FixedTimespan::new(0, 3600, "BST")),
+            ],
+        });
+        let _ = dataset.links.insert("GB".to_owned(), "Europe/London".to_owned());
+
+        let mut buf = Vec::new();
+        write_csv(&mut buf, &dataset).unwrap();
+        assert_eq!(String::from_utf8(buf).unwrap(),
+                   "zone,at,time,total_offset,utc_offset,dst_offset,abbreviation,is_dst\n\
+                    Europe/London,,,-75,-75,0,LMT,0\n\
+                    Europe/London,-3852662325,1847-12-01T00:01:15Z,0,0,0,GMT,0\n\
+                    Europe/London,-1691964000,1916-05-21T02:00:00Z,3600,0,3600,BST,1\n");
+    }
+}
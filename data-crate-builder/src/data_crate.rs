@@ -1,23 +1,42 @@
 //! Creating the data crate from several input files, and the writing of Rust
 //! files afterwards.
 
-use std::error::Error as ErrorTrait;
-use std::io::{Read, BufRead, BufReader};
+use std::borrow::Cow;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::io::Read;
 use std::io::Write;
 use std::io::Result as IOResult;
-use std::fs::{File, OpenOptions, create_dir};
-use std::path::PathBuf;
+use std::io::{Error as IOError, ErrorKind};
+use std::path::{Path, PathBuf};
+use std::str;
+use std::thread;
 
-use datetime::{LocalDateTime, ISO};
+use datetime::{LocalDate, LocalDateTime, LocalTime, Month, ISO};
 
+use zoneinfo_parse::leap::{LeapSecondTable, parse_leapseconds};
 use zoneinfo_parse::line::{Line};
-use zoneinfo_parse::table::{Table, TableBuilder};
-use zoneinfo_parse::structure::{Structure, Child};
-use zoneinfo_parse::transitions::{TableTransitions};
+use zoneinfo_parse::table::{Table, TableBuilder, MergeError};
+use zoneinfo_parse::structure::{TableStructure, Child};
+use zoneinfo_parse::tab::ZoneLocation;
+use zoneinfo_parse::transitions::{TableTransitions, TransitionOptions, FixedTimespanSet};
+use zoneinfo_parse::validation::TableValidation;
 
 use phf_codegen::Map as PHFMap;
 
-use errors::{Error, ParseError};
+use backend::CodegenBackend;
+use cache::Cache;
+use errors::{CliError, ParseError};
+use blob::{self, write_blob};
+use chrono_tz::{write_chrono_tz, variant_name};
+use csv::write_csv;
+use json::write_json;
+use output::{OutputSink, FilesystemSink, zone_file_path};
+use system::{Filesystem, Contents};
+use time_crate::write_time_zones;
+use tzif::write_tzif;
+use zi::write_zi;
+use warnings::{Warning, is_suspicious_abbreviation};
+use Report;
 
 
 /// The entire contents of some zoneinfo data files.
@@ -27,209 +46,1870 @@ pub struct DataCrate {
     base_path: PathBuf,
 
     /// The data to write.
-    table: Table,
+    dataset: Dataset,
 }
 
-impl DataCrate {
 
-    /// Creates a new data crate based on the contents of several files,
-    /// returning an error if any of the files can’t be opened or any of the
-    /// lines doesn’t parse correctly. The resulting data crate value can then
-    /// be turned into many Rust files of time zone info.
+/// Settings that change how a data crate gets built.
+#[derive(PartialEq, Debug, Clone, Default)]
+pub struct Options {
+
+    /// Whether to check the parsed table for problems, such as unused rule
+    /// sets or links to nonexistent zones, before computing anything. Any
+    /// problems cause the build to fail.
+    pub validate: bool,
+
+    /// The options used to compute each zone’s timespans.
+    pub transitions: TransitionOptions,
+
+    /// The first year whose transitions get kept, if any. Every zone’s
+    /// first timespan becomes the one in effect at the start of this year,
+    /// so applications that never deal with historical dates don’t have to
+    /// carry around the transitions before it.
+    pub window_start: Option<i64>,
+
+    /// The last year whose transitions get kept, if any. The timespan in
+    /// effect at the end of this year carries on indefinitely.
+    pub window_end: Option<i64>,
+
+    /// Whether to collapse everything before 1970 into each zone’s first
+    /// timespan, following the tz database’s own view that its data from
+    /// before then is only its best effort. The `backzone` file gets left
+    /// out of the input too, as all it does is give zones their own
+    /// history before 1970, where they’d otherwise be links.
+    pub collapse_pre_1970: bool,
+
+    /// Whether to leave the `backward` file out of the input, so the links
+    /// it has for names that have been replaced, such as `US/Pacific`,
+    /// don’t get built. Links from the other files are kept, as they’re
+    /// for places that are still current but share a zone with another.
+    pub skip_backward: bool,
+
+    /// Whether to carry on past lines of input that couldn’t be parsed or
+    /// added to the table, leaving them out and building everything else,
+    /// rather than failing. Each skipped line gets returned as a warning.
+    /// Links to zones that got left out this way get left out too.
+    pub skip_errors: bool,
+}
+
+
+/// Settings that change the Rust code that gets generated, without changing
+/// the data in it.
+#[derive(PartialEq, Debug, Clone, Default)]
+pub struct CodegenOptions {
+
+    /// Whether the generated code should avoid the standard library, so the
+    /// data crate can be used in `#![no_std]` crates. Rather than using the
+    /// types from `datetime`, whose names are `Cow`s, the root module
+    /// defines its own `StaticTimeZone`, `FixedTimespanSet`, and
+    /// `FixedTimespan` types with plain `&str` names, and only needs `core`
+    /// and `phf` (which has to be built without its default features).
+    ///
+    /// This only affects the Rust formats: the others either don’t contain
+    /// code, or only ever use `core`.
+    pub no_std: bool,
+
+    /// Whether to put each region — the part of a zone’s name before the
+    /// first slash, such as `Europe` — behind a Cargo feature named after
+    /// it in lowercase, so crates using the data crate can leave out the
+    /// regions they don’t need. Every feature is on by default. Zones with
+    /// no slash in their name are always included.
+    ///
+    /// This only affects the Rust formats, which also get a
+    /// `features.toml` file with the `[features]` section for the data
+    /// crate’s manifest.
+    pub feature_gates: bool,
+
+    /// The name of the crate to generate around the Rust code, if any. With
+    /// a name, the output directory becomes a crate that builds on its own:
+    /// it gets a `Cargo.toml` with the dependencies the code needs (and the
+    /// features, if the regions are feature-gated), and a `src/lib.rs` that
+    /// declares the `zones` module with the code in and re-exports its
+    /// `lookup` function. The types the code uses come from `datetime`, or
+    /// get defined in the `zones` module without the standard library.
     ///
-    /// All the errors are stored and returned in one go, rather than
-    /// returning early after the first one.
-    pub fn new<P>(base_path: P, input_file_paths: &[String]) -> Result<DataCrate, Error>
-    where P: Into<PathBuf> {
+    /// Like the other options, this only affects the Rust formats.
+    pub scaffold: Option<String>,
 
-        let mut builder = TableBuilder::new();
-        let mut errors = Vec::new();
+    /// The release of the tz database that the data comes from, such as
+    /// `2025a`, which the Rust formats include as the `TZDB_VERSION`
+    /// constant. If it isn’t known, the constant is `unknown`.
+    pub tz_version: Option<String>,
 
-        for arg in input_file_paths {
-            let f = try!(File::open(arg));
-            let reader = BufReader::new(f);
+    /// The names Windows uses for time zones, mapped to the names of the
+    /// zones to use for them, such as from CLDR’s `windowsZones.xml` (see
+    /// `zoneinfo_parse::windows`). If there are any, the Rust formats
+    /// include them as the `WINDOWS_ZONES` map, next to a `lookup_windows`
+    /// function that finds the zone for a Windows name.
+    pub windows_zones: BTreeMap<String, String>,
 
-            for (line_number, line) in reader.lines().enumerate() {
-                let line = line.unwrap();
+    /// The locations of the zones’ principal cities, such as from
+    /// `zone1970.tab` (see `zoneinfo_parse::tab`). If there are any, the
+    /// Rust formats include them as the `ZONE_LOCATIONS` list, with each
+    /// zone’s countries, coordinates, and comment, next to a
+    /// `zone_location` function that finds a zone’s entry and a
+    /// `nearest_zone` function that finds the zone whose city is closest to
+    /// a point — unless the code can’t use the standard library, which has
+    /// the trigonometry that last function needs.
+    pub locations: Vec<ZoneLocation>,
 
-                // Strip out the comment portion from the line, if any.
-                let line_portion = match line.find('#') {
-                    Some(pos) => &line[..pos],
-                    None      => &line[..],
-                };
+    /// The leap seconds from the tz database’s `leapseconds` file, if it
+    /// was among the inputs (see `source_leap_seconds`). If there are any,
+    /// the Rust formats include them as the `LEAP_SECONDS` list, next to
+    /// the `LEAP_SECONDS_EXPIRY` timestamp.
+    pub leap_seconds: Option<LeapSecondTable>,
 
-                let result = match Line::from_str(line_portion) {
+    /// Whether to store each transition’s time as the number of seconds
+    /// since the previous one, which nearly always fits in four bytes rather
+    /// than eight, making the data about half the size for targets where
+    /// binary size matters. The generated loader decodes them again.
+    ///
+    /// This only affects the blob format.
+    pub delta_timestamps: bool,
 
-                    // If there’s an error, then display which line failed to parse.
-                    Err(e) => {
-                        let error = ParseError {
-                            filename: arg.clone(),
-                            line: line_number + 1,
-                            error: e.description().to_owned(),
-                        };
+    /// Whether to compress the blob with deflate, so it takes up a fraction
+    /// of the space in the compiled binary. The generated code decompresses
+    /// it the first time a zone gets looked up, which needs the
+    /// `miniz_oxide` crate, and keeps the result around after that.
+    ///
+    /// Like the delta timestamps, this only affects the blob format.
+    pub compress: bool,
+}
 
-                        errors.push(error);
-                        continue;
-                    },
 
-                    // Ignore any spaces
-                    Ok(Line::Space) => { continue },
+/// The formats that a data crate can be written out in.
+#[derive(PartialEq, Debug, Copy, Clone)]
+pub enum Format {
+
+    /// Rust source files, one for each zone and link, arranged in modules
+    /// with a `mod.rs` file at the top. This is the default.
+    Rust,
+
+    /// The same Rust code, but all in a single `zones.rs` file, with the
+    /// modules declared inline.
+    RustFile,
+
+    /// Compiled TZif files, one for each zone and link, in the same layout
+    /// as `/usr/share/zoneinfo` (see the `tzif` module).
+    TZif,
+
+    /// A single `tzdata.zi` file of zoneinfo source (see the `zi` module).
+    Zi,
+
+    /// A single `zones.json` file (see the `json` module).
+    Json,
+
+    /// A single `transitions.csv` file (see the `csv` module).
+    Csv,
+
+    /// A `zones.bin` file with every zone in, and a `mod.rs` file that
+    /// loads them from it (see the `blob` module).
+    Blob,
+
+    /// A single `timezones.rs` file laid out the way the `chrono-tz` crate
+    /// generates it (see the `chrono_tz` module).
+    ChronoTz,
+
+    /// A single `time_zones.rs` file of zones for use with the `time` crate
+    /// (see the `time_crate` module).
+    Time,
+}
+
+/// Every format, in the order they’re listed in the builder’s capabilities.
+pub static FORMATS: &'static [Format] = &[ Format::Rust, Format::RustFile, Format::TZif, Format::Zi, Format::Json, Format::Csv, Format::Blob, Format::ChronoTz, Format::Time ];
+
+impl Format {
+
+    /// Returns the format with the given name, as passed to `--format`.
+    pub fn from_name(name: &str) -> Option<Format> {
+        FORMATS.iter().find(|f| f.name() == name).cloned()
+    }
+
+    /// Returns the name of this format.
+    pub fn name(self) -> &'static str {
+        match self {
+            Format::Rust      => "rust",
+            Format::RustFile  => "rust-file",
+            Format::TZif      => "tzif",
+            Format::Zi        => "zi",
+            Format::Json      => "json",
+            Format::Csv       => "csv",
+            Format::Blob      => "blob",
+            Format::ChronoTz  => "chrono-tz",
+            Format::Time      => "time",
+        }
+    }
+}
+
+impl Default for Format {
+    fn default() -> Format {
+        Format::Rust
+    }
+}
+
+
+/// The time zone data that ends up in a data crate, with every zone’s
+/// transitions already computed.
+#[derive(PartialEq, Debug, Default)]
+pub struct Dataset {
+
+    /// Mapping of zone names to their computed timespans.
+    pub zonesets: BTreeMap<String, FixedTimespanSet>,
+
+    /// Mapping of link names to the names of the zones they link to.
+    pub links: BTreeMap<String, String>,
+}
+
+impl Dataset {
+
+    /// Computes the timespans for every zone in the given table, using the
+    /// given options.
+    pub fn from_table(table: &Table, options: &TransitionOptions) -> Dataset {
+        let mut dataset = Dataset::default();
+
+        for name in table.zonesets.keys() {
+            trace!("Computing transitions for {}", name);
+            let set = table.timespans(name, options).unwrap();
+            let _ = dataset.zonesets.insert(name.clone(), set);
+        }
+
+        for (name, target) in &table.links {
+            let _ = dataset.links.insert(name.clone(), target.clone());
+        }
+
+        dataset
+    }
+
+    /// Returns the timespans for the zone with the given name, following
+    /// links if necessary.
+    pub fn timespans(&self, zone_name: &str) -> Option<&FixedTimespanSet> {
+        match self.links.get(zone_name) {
+            Some(target) => self.zonesets.get(target),
+            None         => self.zonesets.get(zone_name),
+        }
+    }
+
+    /// Returns every abbreviation used by any zone, in sorted order, with
+    /// each one only appearing once.
+    pub fn abbreviations(&self) -> Vec<&str> {
+        let abbreviations: BTreeSet<&str> = self.zonesets.values()
+                                                .flat_map(|set| Some(&set.first).into_iter().chain(set.rest.iter().map(|t| &t.1)))
+                                                .map(|t| &*t.name)
+                                                .collect();
+        abbreviations.into_iter().collect()
+    }
+
+    /// Returns the names of all the zones and links, in sorted order.
+    pub fn names(&self) -> Vec<&str> {
+        let mut names: Vec<&str> = self.zonesets.keys().chain(self.links.keys()).map(|n| &**n).collect();
+        names.sort();
+        names
+    }
+
+    /// Drops every zone’s transitions outside the given range of Unix
+    /// timestamps, as with `FixedTimespanSet::truncate`.
+    pub fn truncate(&mut self, start: i64, end: i64) {
+        for set in self.zonesets.values_mut() {
+            set.truncate(start, end);
+        }
+    }
+
+    /// Removes every zone and link whose name isn’t accepted by the given
+    /// function, except for zones that a remaining link points to, as the
+    /// link would be no use without them.
+    pub fn retain<P: Fn(&str) -> bool>(&mut self, keep: P) {
+        let links: BTreeMap<String, String> = self.links.iter()
+                                                  .filter(|&(name, _)| keep(name))
+                                                  .map(|(name, target)| (name.clone(), target.clone()))
+                                                  .collect();
+
+        let zonesets = self.zonesets.iter()
+                                    .filter(|&(name, _)| keep(name) || links.values().any(|t| t == name))
+                                    .map(|(name, set)| (name.clone(), set.clone()))
+                                    .collect();
+
+        self.zonesets = zonesets;
+        self.links = links;
+    }
+}
+
+
+/// The files in a tzdata release that contain zoneinfo source lines, as
+/// opposed to documentation, tables, leap second data, or build scripts.
+pub static SOURCE_FILES: &'static [&'static str] = &[
+    "africa", "antarctica", "asia", "australasia", "europe",
+    "northamerica", "southamerica", "etcetera", "backward",
+];
+
+/// Returns the paths of the source files that are present in the given
+/// directory, in the order they appear in `SOURCE_FILES`.
+pub fn source_files<F: Filesystem>(fs: &F, dir: &Path) -> Vec<String> {
+    SOURCE_FILES.iter()
+                .map(|name| dir.join(name))
+                .filter(|path| fs.is_file(path))
+                .map(|path| path.to_string_lossy().into_owned())
+                .collect()
+}
+
+/// Returns the release of the tz database that the given input paths come
+/// from, read from the `version` file that tzdata releases have in them.
+/// The file is looked for in any directories among the paths, and in the
+/// directories that any files are in.
+pub fn source_version<F: Filesystem>(fs: &F, input_paths: &[String]) -> Option<String> {
+    for path in input_paths.iter().filter(|p| *p != STDIN_PATH) {
+        let path = Path::new(path);
+        let dir = if fs.is_dir(path) { path } else { path.parent().unwrap_or(Path::new("")) };
+
+        let mut contents = String::new();
+        if let Ok(mut file) = fs.open(&dir.join("version")) {
+            if file.read_to_string(&mut contents).is_ok() && !contents.trim().is_empty() {
+                return Some(contents.trim().to_owned());
+            }
+        }
+    }
+
+    None
+}
+
+/// The file in a tzdata release with the list of leap seconds.
+pub const LEAPSECONDS_FILE: &'static str = "leapseconds";
+
+/// Reads the leap seconds from the `leapseconds` file among the given input
+/// paths, or in any directories among them, if there is one. Returns an
+/// error if the file can’t be read or parsed.
+pub fn source_leap_seconds<F: Filesystem>(fs: &F, input_paths: &[String]) -> Result<Option<LeapSecondTable>, CliError> {
+    for path in input_paths.iter().filter(|p| *p != STDIN_PATH) {
+        let path = Path::new(path);
+        let file_path = if fs.is_dir(path) { path.join(LEAPSECONDS_FILE) } else { path.to_path_buf() };
+        if file_path.file_name() != Some(LEAPSECONDS_FILE.as_ref()) || !fs.is_file(&file_path) {
+            continue;
+        }
+
+        let mut contents = String::new();
+        let _ = try!(try!(fs.open(&file_path)).read_to_string(&mut contents));
+        let table = try!(parse_leapseconds(&contents).map_err(|e| CliError::LeapSeconds(file_path.to_string_lossy().into_owned(), e)));
+        return Ok(Some(table));
+    }
+
+    Ok(None)
+}
+
+/// The input path that means “read from standard input” instead of a file.
+pub const STDIN_PATH: &'static str = "-";
+
+/// The filename that errors and warnings about lines read from standard
+/// input refer to.
+pub const STDIN_FILENAME: &'static str = "<stdin>";
+
+/// Replaces any directories among the input paths with the source files
+/// inside them, leaving the paths to files (and `-`, for standard input) as
+/// they are. Returns an error if a directory doesn’t contain any source
+/// files.
+pub fn expand_inputs<F: Filesystem>(fs: &F, input_paths: &[String]) -> Result<Vec<String>, CliError> {
+    let mut expanded = Vec::new();
+
+    for path in input_paths {
+        if path != STDIN_PATH && fs.is_dir(Path::new(path)) {
+            let files = source_files(fs, Path::new(path));
+            if files.is_empty() {
+                let message = format!("{}: no zoneinfo source files in directory", path);
+                return Err(IOError::new(ErrorKind::NotFound, message).into());
+            }
+
+            expanded.extend(files);
+        }
+        else {
+            expanded.push(path.clone());
+        }
+    }
+
+    Ok(expanded)
+}
+
+/// The file in a tzdata release with the pre-1970 history of zones that
+/// are otherwise links.
+pub const BACKZONE_FILE: &'static str = "backzone";
+
+/// The file in a tzdata release with the links from names that are only
+/// kept for backward compatibility.
+pub const BACKWARD_FILE: &'static str = "backward";
+
+/// Returns whether the given input path is to a `backzone` file.
+fn is_backzone(path: &str) -> bool {
+    Path::new(path).file_name() == Some(BACKZONE_FILE.as_ref())
+}
+
+/// Removes the files with the given name from a list of input paths.
+fn without_file(input_paths: Vec<String>, file_name: &str) -> Vec<String> {
+    input_paths.into_iter()
+               .filter(|path| Path::new(path).file_name() != Some(file_name.as_ref()))
+               .collect()
+}
+
+/// Adds the `backzone` file from each of the directories among the input
+/// paths, if they have one, to the end of the paths. Directories normally
+/// only contain the files in `SOURCE_FILES`, as `make` leaves `backzone`
+/// out unless it’s asked for with `PACKRATDATA`.
+pub fn with_backzone<F: Filesystem>(fs: &F, input_paths: &[String]) -> Vec<String> {
+    let backzones = input_paths.iter()
+                               .filter(|path| *path != STDIN_PATH && fs.is_dir(Path::new(path)))
+                               .map(|path| Path::new(path).join(BACKZONE_FILE))
+                               .filter(|path| fs.is_file(path))
+                               .map(|path| path.to_string_lossy().into_owned());
+
+    input_paths.iter().cloned().chain(backzones).collect()
+}
+
+/// Reads several zoneinfo files into one table, returning an error if any of
+/// the files can’t be opened or any of the lines doesn’t parse correctly.
+/// Lines that are odd but usable produce warnings, which get returned along
+/// with the table. Directories get replaced with the standard source files
+/// inside them, as with `expand_inputs`, and a path of `-` reads from
+/// standard input instead of a file.
+///
+/// All the errors are stored and returned in one go, rather than returning
+/// early after the first one.
+///
+/// The files only get read one at a time, but each one gets parsed on a
+/// thread of its own, into a partial table that gets merged with the others
+/// in order once they’re all done. The results are the same as if they’d
+/// been parsed one after another.
+pub fn read_table<F: Filesystem>(fs: &F, input_file_paths: &[String]) -> Result<(Table, Vec<Warning>), CliError> {
+    let (table, warnings, errors) = try!(read_partial_table(fs, input_file_paths));
+
+    // If there are *any* errors, then we can’t return success.
+    if errors.is_empty() {
+        Ok((table, warnings))
+    }
+    else {
+        Err(errors.into())
+    }
+}
+
+/// Reads several zoneinfo files into one table in the same way as
+/// `read_table`, but carries on past the lines that couldn’t be parsed or
+/// added to the table, leaving them out. Each one gets returned as a
+/// warning instead of an error, so the table has everything that was
+/// usable. Only failing to read a file at all is still an error.
+pub fn read_table_skipping_errors<F: Filesystem>(fs: &F, input_file_paths: &[String]) -> Result<(Table, Vec<Warning>), CliError> {
+    let (table, mut warnings, errors) = try!(read_partial_table(fs, input_file_paths));
+
+    for error in errors {
+        warnings.push(Warning::SkippedLine {
+            filename: error.filename,
+            line: error.line,
+            column: error.column,
+            error: error.error,
+        });
+    }
+
+    Ok((table, warnings))
+}
+
+/// Reads several zoneinfo files into a table of everything in them that
+/// could be used, returning the warnings and errors for the rest.
+///
+/// A `backzone` file gets read after all the others, whichever order they
+/// were given in, and its zones replace the links with the same names
+/// instead of clashing with them.
+fn read_partial_table<F: Filesystem>(fs: &F, input_file_paths: &[String]) -> Result<(Table, Vec<Warning>, Vec<ParseError>), CliError> {
+    let mut input_file_paths = try!(expand_inputs(fs, input_file_paths));
+    input_file_paths.sort_by_key(|path| is_backzone(path));
+    let mut threads = Vec::new();
+
+    for arg in &input_file_paths {
+        debug!("Reading {}", arg);
+        let (filename, contents): (String, Contents) = if arg == STDIN_PATH {
+            let contents = try!(fs.read_stdin().map_err(|e| read_error(STDIN_FILENAME, e)));
+            (STDIN_FILENAME.to_owned(), Box::new(contents))
+        }
+        else {
+            (arg.clone(), try!(fs.read(Path::new(arg)).map_err(|e| read_error(arg, e))))
+        };
+
+        threads.push(thread::spawn(move || parse_file(filename, contents)));
+    }
+
+    let mut builder = TableBuilder::new();
+    let mut errors = Vec::new();
+    let mut warnings = Vec::new();
+    let mut links = HashMap::new();
+
+    for thread in threads {
+        let file = thread.join().unwrap();
+        let mut file_errors = file.errors;
+        let mut file_warnings = file.warnings;
+
+        let merge_errors = if is_backzone(&file.filename) { builder.overlay(file.builder) } else { builder.merge(file.builder) };
+        for error in merge_errors {
+            let line = match error {
+                MergeError::UnknownRuleset { .. } | MergeError::DuplicateZone(_) => file.zone_lines[error.name()],
+                MergeError::DuplicateLink(_) | MergeError::CircularLink(_)      => file.links[error.name()].0,
+            };
+
+            // A link that repeats one from an earlier file is only worth a
+            // warning, the same as one repeated in the same file.
+            if let MergeError::DuplicateLink(ref name) = error {
+                if links.get(name) == Some(&file.links[name].1) {
+                    file_warnings.push(Warning::DuplicateLink {
+                        filename: file.filename.clone(),
+                        line: line,
+                        name: name.clone(),
+                    });
+
+                    continue;
+                }
+            }
+
+            file_errors.push(ParseError {
+                filename: file.filename.clone(),
+                line: line,
+                column: None,
+                error: error.to_string(),
+            });
+        }
+
+        file_errors.sort_by_key(|e| e.line);
+        errors.extend(file_errors);
+        warnings.extend(file_warnings);
+
+        for (name, (_, target)) in file.links {
+            let _ = links.entry(name).or_insert(target);
+        }
+    }
+
+    Ok((builder.build(), warnings, errors))
+}
+
+/// Adds the name of the file that couldn’t be read to an IO error, so it’s
+/// clear which one of the inputs was the problem.
+pub fn read_error(filename: &str, err: IOError) -> CliError {
+    IOError::new(err.kind(), format!("{}: {}", filename, err)).into()
+}
+
+/// One input file, parsed into a partial table.
+struct ParsedFile {
+
+    /// The name of the file, as it appears in errors and warnings.
+    filename: String,
+
+    /// The partial table of everything that could be added from the file.
+    builder: TableBuilder,
+
+    /// The lines that couldn’t be parsed or added to the table.
+    errors: Vec<ParseError>,
+
+    /// The lines that were odd but usable.
+    warnings: Vec<Warning>,
+
+    /// The number of the line that defined each zone.
+    zone_lines: HashMap<String, usize>,
 
-                    Ok(Line::Rule(rule))         => builder.add_rule_line(rule),
-                    Ok(Line::Link(link))         => builder.add_link_line(link),
-                    Ok(Line::Zone(zone))         => builder.add_zone_line(zone),
-                    Ok(Line::Continuation(cont)) => builder.add_continuation_line(cont),
+    /// The number of the first line that defined each link, and the name
+    /// it linked to.
+    links: HashMap<String, (usize, String)>,
+}
+
+/// Parses the contents of one input file into a partial table. The lines
+/// get parsed straight from the contents, without copying each one.
+fn parse_file(filename: String, contents: Contents) -> ParsedFile {
+    let mut file = ParsedFile {
+        filename:   filename,
+        builder:    TableBuilder::partial(),
+        errors:     Vec::new(),
+        warnings:   Vec::new(),
+        zone_lines: HashMap::new(),
+        links:      HashMap::new(),
+    };
+
+    for (line_number, bytes) in lines((*contents).as_ref()).into_iter().enumerate() {
+
+        // Some old comments are in Latin-1 rather than UTF-8. Those
+        // characters get replaced, and as long as they were only in the
+        // comment, the line still means what it always did.
+        let line = match str::from_utf8(bytes) {
+            Ok(line) => Cow::Borrowed(line),
+            Err(_)   => {
+                file.warnings.push(Warning::InvalidUtf8 {
+                    filename: file.filename.clone(),
+                    line: line_number + 1,
+                });
+
+                String::from_utf8_lossy(bytes)
+            },
+        };
+        // Whitespace at the end of a line, including the carriage return of
+        // a Windows line ending, doesn’t mean anything.
+        let line = line.trim_end();
+
+        // Strip out the comment portion from the line, if any.
+        let line_portion = match line.find('#') {
+            Some(pos) => &line[..pos],
+            None      => line,
+        };
+
+        // Rules with an obsolete TYPE column won’t parse, but they can
+        // be used once it’s taken out.
+        let without_type = match rule_without_type(line_portion) {
+            Some((rest, value)) => {
+                file.warnings.push(Warning::IgnoredType {
+                    filename: file.filename.clone(),
+                    line: line_number + 1,
+                    value: value,
+                });
+
+                Some(rest)
+            },
+            None => None,
+        };
+
+        let line_portion = match without_type {
+            Some(ref rest) => &rest[..],
+            None           => line_portion,
+        };
+
+        let result = match Line::from_str(line_portion) {
+
+            // If there’s an error, then display which line failed to parse,
+            // and which field if the parser could tell. The field’s offset
+            // is only into the line as it was written if the TYPE column
+            // didn’t have to be taken out first.
+            Err(e) => {
+                let column = match without_type {
+                    Some(_) => None,
+                    None    => e.offset().map(|offset| line[.. offset].chars().count() + 1),
                 };
 
-                if let Err(e) = result {
-                    let error = ParseError {
-                        filename: arg.clone(),
+                let error = ParseError {
+                    filename: file.filename.clone(),
+                    line: line_number + 1,
+                    column: column,
+                    error: e.to_string(),
+                };
+
+                file.errors.push(error);
+                continue;
+            },
+
+            // Ignore any spaces
+            Ok(Line::Space) => { continue },
+
+            Ok(Line::Rule(rule))         => file.builder.add_rule_line(rule),
+            Ok(Line::Link(link)) => {
+
+                // The builder rejects links with names it’s seen before, so
+                // keep track of them here to spot the ones that are just
+                // repeats.
+                if file.links.get(link.new).map(|&(_, ref t)| &**t) == Some(link.existing) {
+                    file.warnings.push(Warning::DuplicateLink {
+                        filename: file.filename.clone(),
                         line: line_number + 1,
-                        error: e.description().to_owned(),
-                    };
+                        name: link.new.to_owned(),
+                    });
 
-                    errors.push(error);
+                    continue;
                 }
+
+                let _ = file.links.entry(link.new.to_owned()).or_insert_with(|| (line_number + 1, link.existing.to_owned()));
+                file.builder.add_link_line(link)
+            },
+
+            Ok(Line::Zone(zone)) => {
+                let _ = file.zone_lines.entry(zone.name.to_owned()).or_insert(line_number + 1);
+                file.builder.add_zone_line(zone)
+            },
+
+            Ok(Line::Continuation(cont)) => file.builder.add_continuation_line(cont),
+        };
+
+        if let Err(e) = result {
+            let error = ParseError {
+                filename: file.filename.clone(),
+                line: line_number + 1,
+                column: None,
+                error: e.to_string(),
+            };
+
+            file.errors.push(error);
+        }
+    }
+
+    file
+}
+
+/// Splits the given bytes into lines at each newline, leaving out a
+/// byte-order mark at the start. Anything else at the end of a line, such
+/// as a carriage return, is left for `parse_file` to trim once the line
+/// has been decoded, so the lines end up the same as the ones
+/// `zoneinfo_parse::line::split_lines` gives.
+fn lines(bytes: &[u8]) -> Vec<&[u8]> {
+    let bytes = if bytes.starts_with(b"\xef\xbb\xbf") { &bytes[3 ..] } else { bytes };
+    let mut lines: Vec<&[u8]> = bytes.split(|&b| b == b'\n').collect();
+
+    // A newline at the very end doesn’t start another line.
+    if lines.last().map(|line| line.is_empty()) == Some(true) {
+        let _ = lines.pop();
+    }
+
+    lines
+}
+
+
+/// If the given line is a rule line with something other than `-` in its
+/// TYPE column, returns the line with a `-` there instead, along with what
+/// was there originally.
+fn rule_without_type(line: &str) -> Option<(String, String)> {
+    let mut fields: Vec<&str> = line.split_whitespace().collect();
+
+    if fields.len() < 10 || fields[0] != "Rule" || fields[4] == "-" || fields[4] == "\u{2010}" {
+        return None;
+    }
+
+    let value = fields[4].to_owned();
+    fields[4] = "-";
+    Some((fields.join(" "), value))
+}
+
+/// Returns the Unix timestamp of the start of the given year, in UTC.
+fn year_start(year: i64) -> i64 {
+    LocalDateTime::new(LocalDate::ymd(year, Month::January, 1).unwrap(), LocalTime::midnight()).to_instant().seconds()
+}
+
+/// Returns a warning for each abbreviation that `zic` would complain about,
+/// once per zone.
+fn abbreviation_warnings(dataset: &Dataset) -> Vec<Warning> {
+    let mut warnings = Vec::new();
+
+    for (name, set) in &dataset.zonesets {
+        let abbreviations = Some(&set.first).into_iter()
+                                .chain(set.rest.iter().map(|t| &t.1))
+                                .map(|t| &*t.name)
+                                .filter(|a| is_suspicious_abbreviation(a))
+                                .collect::<BTreeSet<_>>();
+
+        for abbreviation in abbreviations {
+            warnings.push(Warning::SuspiciousAbbreviation {
+                zone: name.clone(),
+                abbreviation: abbreviation.to_owned(),
+            });
+        }
+    }
+
+    warnings
+}
+
+
+impl DataCrate {
+
+    /// Creates a new data crate based on the contents of several files,
+    /// returning an error if any of the files can’t be opened, any of the
+    /// lines doesn’t parse correctly, or validation was asked for and found
+    /// problems. The resulting data crate value can then be turned into many
+    /// Rust files of time zone info.
+    ///
+    /// Anything odd about the input that didn’t stop the data crate from
+    /// being created gets returned alongside it as a list of warnings.
+    pub fn new<F, P>(fs: &F, base_path: P, input_file_paths: &[String], options: &Options) -> Result<(DataCrate, Vec<Warning>), CliError>
+    where F: Filesystem, P: Into<PathBuf> {
+        // The leap seconds aren’t zoneinfo source, so they get read
+        // separately, by `source_leap_seconds`.
+        let mut input_file_paths = without_file(try!(expand_inputs(fs, input_file_paths)), LEAPSECONDS_FILE);
+        if options.collapse_pre_1970 {
+            input_file_paths = without_file(input_file_paths, BACKZONE_FILE);
+        }
+
+        if options.skip_backward {
+            input_file_paths = without_file(input_file_paths, BACKWARD_FILE);
+        }
+
+        let (table, mut warnings) = if options.skip_errors {
+            try!(read_table_skipping_errors(fs, &input_file_paths))
+        }
+        else {
+            try!(read_table(fs, &input_file_paths))
+        };
+
+        if options.validate {
+            let problems = table.validate();
+            if !problems.is_empty() {
+                return Err(problems.into());
+            }
+        }
+
+        let mut dataset = Dataset::from_table(&table, &options.transitions);
+        if options.skip_errors {
+            let zonesets = &dataset.zonesets;
+            dataset.links.retain(|_, target| zonesets.contains_key(target));
+        }
+
+        if options.window_start.is_some() || options.window_end.is_some() || options.collapse_pre_1970 {
+            let mut start = options.window_start.map(year_start).unwrap_or(i64::min_value());
+            if options.collapse_pre_1970 && start < 0 {
+                start = 0;
             }
+
+            let end = options.window_end.map(|year| year_start(year + 1)).unwrap_or(i64::max_value());
+            dataset.truncate(start, end);
         }
 
-        // If there are *any* errors, then we can’t return success.
-        if errors.is_empty() {
-            Ok(DataCrate {
+        warnings.extend(abbreviation_warnings(&dataset));
+
+        let data_crate = DataCrate {
+            base_path: base_path.into(),
+            dataset: dataset,
+        };
+
+        Ok((data_crate, warnings))
+    }
+
+    /// Creates a new data crate in the same way as `new`, but first checks
+    /// the cache for a dataset computed from identical input files, skipping
+    /// the parsing and computation if there is one. Otherwise, the new
+    /// dataset gets stored in the cache for next time.
+    ///
+    /// As nothing gets read when the dataset comes from the cache, there
+    /// are no warnings in that case: they will have been returned by the
+    /// run that filled the cache.
+    ///
+    /// Standard input can only be read once, so input that includes it
+    /// never gets cached, and this behaves the same as `new`.
+    pub fn new_cached<F, P>(fs: &F, base_path: P, input_file_paths: &[String], options: &Options, cache: &Cache) -> Result<(DataCrate, Vec<Warning>), CliError>
+    where F: Filesystem, P: Into<PathBuf> {
+        let input_file_paths = without_file(try!(expand_inputs(fs, input_file_paths)), LEAPSECONDS_FILE);
+        if input_file_paths.iter().any(|path| path == STDIN_PATH) {
+            return DataCrate::new(fs, base_path, &input_file_paths, options);
+        }
+
+        let key = try!(cache.key(fs, &input_file_paths, options));
+
+        if let Some(dataset) = cache.load(fs, &key) {
+            debug!("Using cached data for {}", key);
+            let data_crate = DataCrate {
                 base_path: base_path.into(),
-                table: builder.build()
-            })
+                dataset: dataset,
+            };
+
+            return Ok((data_crate, Vec::new()));
+        }
+
+        let (data_crate, warnings) = try!(DataCrate::new(fs, base_path, &input_file_paths, options));
+        debug!("Caching data as {}", key);
+        try!(cache.store(fs, &key, &data_crate.dataset));
+        Ok((data_crate, warnings))
+    }
+
+    /// Returns the computed data that gets written out.
+    pub fn dataset(&self) -> &Dataset {
+        &self.dataset
+    }
+
+    /// Removes zones and links from the data that gets written out, as with
+    /// `Dataset::retain`.
+    pub fn retain<P: Fn(&str) -> bool>(&mut self, keep: P) {
+        self.dataset.retain(keep)
+    }
+
+    /// Summarises the contents of this data crate, along with the given
+    /// warnings from when it was created.
+    pub fn report(&self, warnings: Vec<Warning>) -> Report {
+        Report {
+            zone_count: self.dataset.zonesets.len() + self.dataset.links.len(),
+            link_count: self.dataset.links.len(),
+            warnings:   warnings,
+            output:     String::new(),
+        }
+    }
+
+    /// Writes the data as Rust files. There are two steps to this: creating
+    /// the directories the data goes in (and the `mod.rs` files for those
+    /// directories), and then creating the files inside those directories.
+    pub fn run<F: Filesystem>(&self, fs: &F) -> IOResult<()> {
+        self.run_as(fs, Format::Rust)
+    }
+
+    /// Writes the data in the given format.
+    pub fn run_as<F: Filesystem>(&self, fs: &F, format: Format) -> IOResult<()> {
+        self.run_with_codegen(fs, format, &CodegenOptions::default())
+    }
+
+    /// Writes the data in the given format, generating any Rust code with
+    /// the given options.
+    pub fn run_with_codegen<F: Filesystem>(&self, fs: &F, format: Format, codegen: &CodegenOptions) -> IOResult<()> {
+        self.write_to(&mut FilesystemSink::new(fs, self.base_path.clone()), format, codegen)
+    }
+
+    /// Writes the data in the given format to the given sink, rather than
+    /// to the output directory, generating any Rust code with the given
+    /// options.
+    pub fn write_to<S: OutputSink>(&self, sink: &mut S, format: Format, codegen: &CodegenOptions) -> IOResult<()> {
+        match format {
+            Format::Rust => {
+                try!(check_identifiers(&self.dataset.names()));
+                let mut dir = try!(self.rust_code_dir(sink, codegen));
+                if codegen.scaffold.is_some() {
+                    dir.push("zones");
+                }
+
+                let abbreviations = self.dataset.abbreviations().into_iter().map(|a| a.to_owned()).collect();
+                let links = self.dataset.links.clone();
+                self.write_with(sink, &mut RustModules { base_path: dir, codegen: codegen, abbreviations: abbreviations, links: links })
+            },
+            Format::RustFile => {
+                try!(check_identifiers(&self.dataset.names()));
+                let dir = try!(self.rust_code_dir(sink, codegen));
+                self.write_with(sink, &mut RustSingleFile { base_path: dir, codegen: codegen })
+            },
+            Format::TZif      => self.write_with(sink, &mut TzifFiles),
+            Format::Zi        => self.write_with(sink, &mut ZiFile),
+            Format::Json      => self.write_with(sink, &mut JsonFile),
+            Format::Csv       => self.write_with(sink, &mut CsvFile),
+            Format::Blob      => self.write_with(sink, &mut BlobFiles { delta_timestamps: codegen.delta_timestamps, compress: codegen.compress }),
+            Format::ChronoTz  => self.write_with(sink, &mut ChronoTzFile),
+            Format::Time      => self.write_with(sink, &mut TimeFile),
+        }
+    }
+
+    /// Writes the data to the given sink with the given backend, which gets
+    /// each zone and link in sorted order, and then the whole dataset for
+    /// its index (see the `backend` module).
+    pub fn write_with<S: OutputSink, B: CodegenBackend>(&self, sink: &mut S, backend: &mut B) -> IOResult<()> {
+        for name in self.dataset.names() {
+            try!(backend.emit_zone(sink, name, self.dataset.timespans(name).unwrap()));
+        }
+
+        backend.emit_index(sink, &self.dataset)
+    }
+
+    /// Returns the directory that the Rust code goes in, relative to the
+    /// output directory. When generating a crate, this writes the crate’s
+    /// other files first, and the code goes in its `src` directory.
+    /// Otherwise, it goes straight in the output directory, along with a
+    /// `features.toml` file if the regions are feature-gated, for copying
+    /// into the manifest of whichever crate the code ends up in.
+    fn rust_code_dir<S: OutputSink>(&self, sink: &mut S, codegen: &CodegenOptions) -> IOResult<PathBuf> {
+        if let Some(ref crate_name) = codegen.scaffold {
+            return self.write_scaffold(sink, crate_name, codegen);
+        }
+
+        if codegen.feature_gates {
+            let mut w = try!(sink.file(Path::new("features.toml")));
+            try!(write!(w, "{}", self.features_section()));
+            try!(sink.finish(w));
+        }
+
+        Ok(PathBuf::new())
+    }
+
+    /// Writes the `Cargo.toml` and `src/lib.rs` files that turn the output
+    /// directory into a crate with the given name, returning the `src`
+    /// directory that the Rust code should go in.
+    fn write_scaffold<S: OutputSink>(&self, sink: &mut S, crate_name: &str, codegen: &CodegenOptions) -> IOResult<PathBuf> {
+        let mut w = try!(sink.file(Path::new("Cargo.toml")));
+        try!(writeln!(w, "[package]"));
+        try!(writeln!(w, "name = {:?}", crate_name));
+        try!(writeln!(w, "version = \"0.1.0\""));
+        try!(writeln!(w, "\n[dependencies]"));
+        if codegen.no_std {
+            try!(writeln!(w, "phf = {{ version = {:?}, default-features = false }}", PHF_VERSION));
+        }
+        else {
+            try!(writeln!(w, "phf = {:?}", PHF_VERSION));
+            try!(writeln!(w, "\n[dependencies.datetime]\ngit = {:?}", DATETIME_GIT));
+        }
+        if codegen.feature_gates {
+            try!(write!(w, "\n{}", self.features_section()));
+        }
+        try!(sink.finish(w));
+
+        let src_path = PathBuf::from("src");
+        let mut w = try!(sink.file(&src_path.join("lib.rs")));
+        try!(writeln!(w, "{}", WARNING_HEADER));
+        try!(writeln!(w, "#![allow(non_upper_case_globals, non_snake_case)]"));
+        if codegen.no_std {
+            try!(writeln!(w, "#![no_std]\n"));
+            try!(writeln!(w, "extern crate phf;"));
         }
         else {
-            Err(errors.into())
+            try!(writeln!(w, ""));
+            try!(writeln!(w, "extern crate datetime;"));
+            try!(writeln!(w, "extern crate phf;"));
+        }
+        try!(writeln!(w, "\npub mod zones;"));
+        try!(writeln!(w, "pub use zones::lookup;"));
+        if !codegen.windows_zones.is_empty() {
+            try!(writeln!(w, "pub use zones::lookup_windows;"));
+        }
+        if !codegen.locations.is_empty() && !codegen.no_std {
+            try!(writeln!(w, "pub use zones::nearest_zone;"));
         }
+        if !codegen.locations.is_empty() {
+            try!(writeln!(w, "pub use zones::zone_location;"));
+        }
+        try!(sink.finish(w));
+
+        Ok(src_path)
     }
 
-    /// There are two steps to writing the data: creating the directories the
-    /// data goes in (and the `mod.rs` files for those directories), and then
-    /// creating the files inside those directories.
-    pub fn run(&self) -> IOResult<()> {
-        try!(self.create_structure_directories());
-        try!(self.write_zonesets());
-        Ok(())
+    /// Returns the `[features]` section of the manifest, with a feature for
+    /// each region, all turned on by default.
+    fn features_section(&self) -> String {
+        let features: Vec<String> = regions(&self.dataset.names()).iter().map(|r| feature_name(r)).collect();
+        let mut section = String::from("[features]\n");
+        section.push_str(&format!("default = [{}]\n", features.iter().map(|f| format!("{:?}", f)).collect::<Vec<_>>().join(", ")));
+
+        for feature in &features {
+            section.push_str(&format!("{} = []\n", feature));
+        }
+
+        section
     }
+}
 
-    /// Creates the directories that the Rust files get written to later. Also
-    /// creates `mod.rs` files inside those directories.
-    fn create_structure_directories(&self) -> IOResult<()> {
-        let mut open_opts = OpenOptions::new();
-        open_opts.write(true).create(true).truncate(true);
+/// Writes Rust code as a tree of files: one for each zone and link, in
+/// directories for the parts of their names before the last slash, along
+/// with a `mod.rs` file for each directory.
+struct RustModules<'codegen> {
+    base_path: PathBuf,
+    codegen: &'codegen CodegenOptions,
+    abbreviations: Vec<String>,
+    links: BTreeMap<String, String>,
+}
 
-        let base_mod_path = self.base_path.join("mod.rs");
-        let mut base_w = try!(open_opts.open(base_mod_path));
+impl<'codegen> CodegenBackend for RustModules<'codegen> {
+
+    /// Writes the zone file as a Rust file.
+    ///
+    /// The table of abbreviations is in the root module, as are the types
+    /// without the standard library, so each file imports them from however
+    /// many levels up that is. Links that can share the data of the zone
+    /// they link to don’t get a file at all, as their parent module
+    /// re-exports that zone instead.
+    fn emit_zone<S: OutputSink>(&mut self, sink: &mut S, name: &str, set: &FixedTimespanSet) -> IOResult<()> {
+        if shared_link_target(&self.links, name, self.codegen).is_some() {
+            return Ok(());
+        }
+
+        let components: PathBuf = name.split('/').map(sanitise_name).collect();
+        let mut w = try!(sink.file(&self.base_path.join(&components).with_extension("rs")));
+        try!(writeln!(w, "{}", WARNING_HEADER));
+        let root: String = components.iter().map(|_| "super::").collect();
+        if self.codegen.no_std {
+            try!(writeln!(w, "use {}{{StaticTimeZone, FixedTimespanSet, FixedTimespan, ABBREVIATIONS}};\n", root));
+        }
+        else {
+            try!(writeln!(w, "{}", ZONEINFO_HEADER));
+            try!(writeln!(w, "use {}ABBREVIATIONS;\n", root));
+        }
+        try!(write_zone_static(&mut w, "ZONE", name, set, &self.abbreviations, self.codegen));
+        sink.finish(w)
+    }
+
+    /// Writes the `mod.rs` files for the directories the zone files are in.
+    fn emit_index<S: OutputSink>(&mut self, sink: &mut S, dataset: &Dataset) -> IOResult<()> {
+        let codegen = self.codegen;
+        let mut base_w = try!(sink.file(&self.base_path.join("mod.rs")));
 
         try!(writeln!(base_w, "{}", WARNING_HEADER));
-        try!(writeln!(base_w, "{}", MOD_HEADER));
+        if codegen.no_std {
+            try!(writeln!(base_w, "use phf;\n{}", NO_STD_TYPES));
+        }
+        else {
+            try!(writeln!(base_w, "{}", MOD_HEADER));
+        }
+        try!(write_version(&mut base_w, codegen));
+        try!(write_abbreviations(&mut base_w, &self.abbreviations));
 
-        for entry in self.table.structure() {
-            if !entry.name.contains('/') {
-                try!(writeln!(base_w, "pub mod {};", entry.name));
-            }
+        let names = dataset.names();
 
-            let components: PathBuf = entry.name.split('/').collect();
-            let dir_path = self.base_path.join(components);
-            if !dir_path.is_dir() {
-                println!("Creating directory {:?}", &dir_path);
-                try!(create_dir(&dir_path));
+        for entry in TableStructure::from_names(names.iter().cloned()) {
+            if !entry.name.contains('/') {
+                try!(write_feature_gate(&mut base_w, &entry.name, codegen));
+                try!(writeln!(base_w, "pub mod {};", identifier(&entry.name)));
             }
 
-            let mod_path = dir_path.join("mod.rs");
-            let mut w = try!(open_opts.open(mod_path));
+            let components: PathBuf = entry.name.split('/').map(sanitise_name).collect();
+            let root: String = components.iter().map(|_| "super::").collect();
+            let mut w = try!(sink.file(&self.base_path.join(components).join("mod.rs")));
             for child in &entry.children {
                 match *child {
                     Child::TimeZone(ref name) => {
-                        let sanichild = sanitise_name(name);
-                        try!(writeln!(w, "mod {};", sanichild));
-                        try!(writeln!(w, "pub use self::{}::ZONE as {};\n", sanichild, sanichild));
+                        let sanichild = identifier(name);
+                        let full_name = format!("{}/{}", entry.name, name);
+                        if let Some(target) = shared_link_target(&self.links, &full_name, codegen) {
+                            try!(writeln!(w, "pub use {}{} as {};\n", root, static_path(target), sanichild));
+                        }
+                        else {
+                            try!(writeln!(w, "mod {};", sanichild));
+                            try!(writeln!(w, "pub use self::{}::ZONE as {};\n", sanichild, sanichild));
+                        }
                     },
                     Child::Submodule(ref name) => {
-                        let sanichild = sanitise_name(name);
+                        let sanichild = identifier(name);
                         try!(writeln!(w, "pub mod {};\n", sanichild));
                     },
                 }
             }
+            try!(sink.finish(w));
         }
 
-        let mut keys: Vec<_> = self.table.zonesets.keys().chain(self.table.links.keys()).collect();
-        keys.sort();
-
         try!(writeln!(base_w, "\n\n"));
-        for name in keys.iter().filter(|f| !f.contains('/')) {
-            let sanichild = sanitise_name(name);
-            try!(writeln!(base_w, "mod {};", sanichild));
-            try!(writeln!(base_w, "pub use self::{}::ZONE as {};\n", sanichild, sanichild));
+        for name in names.iter().filter(|f| !f.contains('/')) {
+            let sanichild = identifier(name);
+            if let Some(target) = shared_link_target(&self.links, name, codegen) {
+                try!(writeln!(base_w, "pub use self::{} as {};\n", static_path(target), sanichild));
+            }
+            else {
+                try!(writeln!(base_w, "mod {};", sanichild));
+                try!(writeln!(base_w, "pub use self::{}::ZONE as {};\n", sanichild, sanichild));
+            }
         }
 
         try!(writeln!(base_w, "\n\n"));
-        try!(write!(base_w, "static ZONES: phf::Map<&'static str, &'static StaticTimeZone<'static>> = "));
+        try!(write_lookup(&mut base_w, &names, codegen));
+        try!(write_find(&mut base_w));
+        try!(write_aliases(&mut base_w, &dataset.links));
+        try!(write_windows_zones(&mut base_w, &codegen.windows_zones));
+        try!(write_locations(&mut base_w, &names, codegen));
+        try!(write_leap_seconds(&mut base_w, codegen));
+        try!(write_zone_id_enum(&mut base_w, &names, codegen));
+        sink.finish(base_w)
+    }
+}
+
+/// Writes Rust code as a single `zones.rs` file, with the same module tree
+/// as the separate files, but declared inline.
+struct RustSingleFile<'codegen> {
+    base_path: PathBuf,
+    codegen: &'codegen CodegenOptions,
+}
+
+impl<'codegen> CodegenBackend for RustSingleFile<'codegen> {
+    fn emit_index<S: OutputSink>(&mut self, sink: &mut S, dataset: &Dataset) -> IOResult<()> {
+        let codegen = self.codegen;
+        let mut w = try!(sink.file(&self.base_path.join("zones.rs")));
+        try!(writeln!(w, "{}", WARNING_HEADER));
+        try!(writeln!(w, "#![allow(non_upper_case_globals, non_snake_case)]"));
+        if codegen.no_std {
+            try!(writeln!(w, "{}", NO_STD_TYPES));
+        }
+        else {
+            try!(writeln!(w, "{}", ZONEINFO_HEADER));
+        }
+        try!(writeln!(w, "use phf;\n"));
+        try!(write_version(&mut w, codegen));
+
+        let abbreviations: Vec<String> = dataset.abbreviations().into_iter().map(|a| a.to_owned()).collect();
+        try!(write_abbreviations(&mut w, &abbreviations));
+
+        // Work out which zones go in which module first, so each module
+        // only gets opened once.
+        let names = dataset.names();
+        let mut tree = ModuleTree::default();
+        for name in &names {
+            let mut node = &mut tree;
+            let mut components: Vec<&str> = name.split('/').collect();
+            let leaf = components.pop().unwrap();
+
+            for component in components {
+                node = node.submodules.entry(component).or_insert_with(ModuleTree::default);
+            }
 
-        let mut phf_map = PHFMap::new();
-        for name in &keys {
-            phf_map.entry(&***name, &format!("&{}", sanitise_name(name).replace("/", "::")));
+            node.zones.push((leaf, name));
         }
-        try!(phf_map.build(&mut base_w));
 
-        try!(writeln!(base_w, ";\n\npub fn lookup(input: &str) -> Option<&'static StaticTimeZone<'static>> {{"));
-        try!(writeln!(base_w, "    ZONES.get(input).cloned()"));
-        try!(writeln!(base_w, "}}"));
+        try!(write_module_tree(&mut w, dataset, &tree, &abbreviations, codegen, true));
+
+        try!(writeln!(w, ""));
+        try!(write_lookup(&mut w, &names, codegen));
+        try!(write_find(&mut w));
+        try!(write_aliases(&mut w, &dataset.links));
+        try!(write_windows_zones(&mut w, &codegen.windows_zones));
+        try!(write_locations(&mut w, &names, codegen));
+        try!(write_leap_seconds(&mut w, codegen));
+        try!(write_zone_id_enum(&mut w, &names, codegen));
+        sink.finish(w)
+    }
+}
 
+/// Writes each zone and link as a TZif file, in directories for the parts
+/// of their names before the last slash.
+struct TzifFiles;
+
+impl CodegenBackend for TzifFiles {
+    fn emit_zone<S: OutputSink>(&mut self, sink: &mut S, name: &str, set: &FixedTimespanSet) -> IOResult<()> {
+        let path = try!(zone_file_path(name));
+        let mut w = try!(sink.file(&path));
+        try!(write_tzif(&mut w, set));
+        sink.finish(w)
+    }
+
+    fn emit_index<S: OutputSink>(&mut self, _sink: &mut S, _dataset: &Dataset) -> IOResult<()> {
         Ok(())
     }
+}
 
-    /// Writes each zone file as a Rust file.
-    fn write_zonesets(&self) -> IOResult<()> {
-        for name in self.table.zonesets.keys().chain(self.table.links.keys()) {
-            let components: PathBuf = name.split('/').map(sanitise_name).collect();
-            let zoneset_path = self.base_path.join(components).with_extension("rs");
-            let mut w = try!(OpenOptions::new().write(true).create(true).truncate(true).open(zoneset_path));
-            try!(writeln!(w, "{}", WARNING_HEADER));
-            try!(writeln!(w, "{}", ZONEINFO_HEADER));
+/// Writes the whole dataset as zoneinfo source, in `tzdata.zi`.
+struct ZiFile;
+
+impl CodegenBackend for ZiFile {
+    fn emit_index<S: OutputSink>(&mut self, sink: &mut S, dataset: &Dataset) -> IOResult<()> {
+        let mut w = try!(sink.file(Path::new("tzdata.zi")));
+        try!(write_zi(&mut w, dataset));
+        sink.finish(w)
+    }
+}
+
+/// Writes the whole dataset as JSON, in `zones.json`.
+struct JsonFile;
 
-            try!(writeln!(w, "pub static ZONE: StaticTimeZone<'static> = StaticTimeZone {{"));
-            try!(writeln!(w, "    name: {:?},", name));
-            try!(writeln!(w, "    fixed_timespans: FixedTimespanSet {{"));
+impl CodegenBackend for JsonFile {
+    fn emit_index<S: OutputSink>(&mut self, sink: &mut S, dataset: &Dataset) -> IOResult<()> {
+        let mut w = try!(sink.file(Path::new("zones.json")));
+        try!(write_json(&mut w, dataset));
+        sink.finish(w)
+    }
+}
 
-            let set = self.table.timespans(&*name).unwrap();
+/// Writes every transition as a row of `transitions.csv`.
+struct CsvFile;
 
-            try!(writeln!(w, "        first: FixedTimespan {{"));
-            try!(writeln!(w, "            offset: {:?},  // UTC offset {:?}, DST offset {:?}", set.first.total_offset(), set.first.utc_offset, set.first.dst_offset));
-            try!(writeln!(w, "            is_dst: {:?},", set.first.dst_offset != 0));
-            try!(writeln!(w, "            name:   Cow::Borrowed({:?}),", set.first.name));
-            try!(writeln!(w, "        }},"));
+impl CodegenBackend for CsvFile {
+    fn emit_index<S: OutputSink>(&mut self, sink: &mut S, dataset: &Dataset) -> IOResult<()> {
+        let mut w = try!(sink.file(Path::new("transitions.csv")));
+        try!(write_csv(&mut w, dataset));
+        sink.finish(w)
+    }
+}
 
-            try!(writeln!(w, "        rest: &["));
+/// Writes the whole dataset as a binary blob in `zones.bin`, along with the
+/// Rust code that loads it in `mod.rs`.
+struct BlobFiles {
+    delta_timestamps: bool,
+    compress: bool,
+}
 
-            for t in &set.rest {
-                try!(writeln!(w, "        ({:?}, FixedTimespan {{  // {} UTC", t.0, LocalDateTime::at(t.0).iso()));
+impl CodegenBackend for BlobFiles {
+    fn emit_index<S: OutputSink>(&mut self, sink: &mut S, dataset: &Dataset) -> IOResult<()> {
+        let mut blob_data = Vec::new();
+        try!(write_blob(&mut blob_data, dataset, self.delta_timestamps));
 
-                // Write the total offset (the only value that gets used)
-                // and both the offsets that get added together, as a
-                // comment in the data crate.
-                try!(writeln!(w, "            offset: {:?},  // UTC offset {:?}, DST offset {:?}", t.1.total_offset(), t.1.utc_offset, t.1.dst_offset));
-                try!(writeln!(w, "            is_dst: {:?},", t.1.dst_offset != 0));
-                try!(writeln!(w, "            name:   Cow::Borrowed({:?}),", t.1.name));
-                try!(writeln!(w, "        }}),"));
-            }
-            try!(writeln!(w, "    ]}},"));
-            try!(writeln!(w, "}};\n\n"));
+        let (file_name, footer) = if self.compress {
+            blob_data = blob::compress(&blob_data);
+            ("zones.bin.deflate", blob::COMPRESSED_LOADER_FOOTER)
         }
+        else {
+            ("zones.bin", blob::LOADER_FOOTER)
+        };
 
-        Ok(())
+        let mut w = try!(sink.file(Path::new(file_name)));
+        try!(w.write_all(&blob_data));
+        try!(sink.finish(w));
+
+        let mut w = try!(sink.file(Path::new("mod.rs")));
+        try!(writeln!(w, "{}", WARNING_HEADER));
+        try!(write!(w, "{}{}", blob::LOADER_SOURCE, footer));
+        sink.finish(w)
+    }
+}
+
+/// Writes the `timezones.rs` file that `chrono-tz` builds.
+struct ChronoTzFile;
+
+impl CodegenBackend for ChronoTzFile {
+    fn emit_index<S: OutputSink>(&mut self, sink: &mut S, dataset: &Dataset) -> IOResult<()> {
+        let mut w = try!(sink.file(Path::new("timezones.rs")));
+        try!(writeln!(w, "{}", WARNING_HEADER));
+        try!(write_chrono_tz(&mut w, dataset));
+        sink.finish(w)
     }
 }
 
+/// Writes the `time_zones.rs` file of zones for the `time` crate.
+struct TimeFile;
+
+impl CodegenBackend for TimeFile {
+    fn emit_index<S: OutputSink>(&mut self, sink: &mut S, dataset: &Dataset) -> IOResult<()> {
+        let mut w = try!(sink.file(Path::new("time_zones.rs")));
+        try!(writeln!(w, "{}", WARNING_HEADER));
+        try!(write_time_zones(&mut w, dataset));
+        sink.finish(w)
+    }
+}
+
+
+/// The zones and submodules in one module of the single-file output.
+#[derive(Default)]
+struct ModuleTree<'name> {
+    zones: Vec<(&'name str, &'name str)>,
+    submodules: BTreeMap<&'name str, ModuleTree<'name>>,
+}
+
+/// Writes the zones in one module of the tree, followed by its submodules,
+/// each of which imports everything from its parent. The modules at the top
+/// are the regions, which may be feature-gated.
+fn write_module_tree<W: Write>(w: &mut W, dataset: &Dataset, tree: &ModuleTree, abbreviations: &[String], codegen: &CodegenOptions, top: bool) -> IOResult<()> {
+    for &(leaf, name) in &tree.zones {
+        if let Some(target) = shared_link_target(&dataset.links, name, codegen) {
+            let root = if name.contains('/') { name.matches('/').map(|_| "super::").collect() } else { "self::".to_owned() };
+            try!(writeln!(w, "pub use {}{} as {};\n", root, static_path(target), identifier(leaf)));
+        }
+        else {
+            try!(write_zone_static(w, &identifier(leaf), name, dataset.timespans(name).unwrap(), abbreviations, codegen));
+        }
+    }
+
+    for (module_name, submodule) in &tree.submodules {
+        if top {
+            try!(write_feature_gate(w, module_name, codegen));
+        }
+        try!(writeln!(w, "pub mod {} {{\nuse super::*;\n", identifier(module_name)));
+        try!(write_module_tree(w, dataset, submodule, abbreviations, codegen, false));
+        try!(writeln!(w, "}}\n"));
+    }
+
+    Ok(())
+}
+
+/// Writes the table of every abbreviation, which the zones’ timespans refer
+/// to by their index in it, rather than each having their own copy.
+fn write_abbreviations<W: Write>(w: &mut W, abbreviations: &[String]) -> IOResult<()> {
+    try!(writeln!(w, "/// Every abbreviation used by any zone, in sorted order, which the"));
+    try!(writeln!(w, "/// timespans refer to by index so each one is only stored once."));
+    try!(writeln!(w, "pub const ABBREVIATIONS: [&'static str; {}] = [", abbreviations.len()));
+    for abbreviation in abbreviations {
+        try!(writeln!(w, "    {:?},", abbreviation));
+    }
+    writeln!(w, "];\n")
+}
+
+/// Writes the constant with the release of the tz database that the data
+/// comes from.
+fn write_version<W: Write>(w: &mut W, codegen: &CodegenOptions) -> IOResult<()> {
+    let version = codegen.tz_version.as_ref().map(|v| &**v).unwrap_or("unknown");
+    try!(writeln!(w, "/// The release of the tz database that this data was built from, such"));
+    try!(writeln!(w, "/// as “2025a”, or “unknown” if the builder didn’t know."));
+    writeln!(w, "pub const TZDB_VERSION: &'static str = {:?};\n", version)
+}
+
+/// Writes the maps of zone names to zones, and the `lookup` and
+/// `lookup_ignore_case` functions that use them.
+///
+/// With feature gates, the zones in each region get separate maps behind
+/// that region’s feature, and the functions pick the map to use from the
+/// part of the name before the first slash, falling back to the map of the
+/// ungated zones. The region arms of the `match` get left out along with
+/// their maps, so a zone in a region that’s turned off isn’t found.
+fn write_lookup<W: Write>(w: &mut W, names: &[&str], codegen: &CodegenOptions) -> IOResult<()> {
+    try!(write_lookup_maps(w, names, codegen, false));
+    try!(writeln!(w, "\npub fn lookup(input: &str) -> Option<&'static StaticTimeZone<'static>> {{"));
+    try!(write_lookup_body(w, names, codegen, false));
+    try!(writeln!(w, "}}\n"));
+
+    // Names are all ASCII, so lowercasing the ASCII letters of the input
+    // into a buffer is enough to match the lowercased keys, and doesn’t need
+    // the standard library. Anything longer than the longest name can’t
+    // match anything anyway.
+    let std = if codegen.no_std { "core" } else { "std" };
+    let longest = names.iter().map(|n| n.len()).max().unwrap_or(0);
+
+    try!(write_lookup_maps(w, names, codegen, true));
+    try!(writeln!(w, "\n/// Looks up a zone by name, ignoring the case of ASCII letters, so"));
+    try!(writeln!(w, "/// `europe/london` finds `Europe/London`."));
+    try!(writeln!(w, "pub fn lookup_ignore_case(input: &str) -> Option<&'static StaticTimeZone<'static>> {{"));
+    try!(writeln!(w, "    let mut buffer = [0u8; {}];", longest));
+    try!(writeln!(w, "    if input.len() > buffer.len() {{"));
+    try!(writeln!(w, "        return None;"));
+    try!(writeln!(w, "    }}\n"));
+    try!(writeln!(w, "    for (lower, byte) in buffer.iter_mut().zip(input.bytes()) {{"));
+    try!(writeln!(w, "        *lower = if byte >= b'A' && byte <= b'Z' {{ byte + (b'a' - b'A') }} else {{ byte }};"));
+    try!(writeln!(w, "    }}\n"));
+    try!(writeln!(w, "    let input = match ::{}::str::from_utf8(&buffer[.. input.len()]) {{", std));
+    try!(writeln!(w, "        Ok(lowercased) => lowercased,"));
+    try!(writeln!(w, "        Err(_)         => return None,"));
+    try!(writeln!(w, "    }};\n"));
+    try!(write_lookup_body(w, names, codegen, true));
+    writeln!(w, "}}")
+}
+
+/// Writes the maps used by one of the lookup functions: either one map of
+/// every zone, or, with feature gates, one for each region and one for the
+/// zones that aren’t in a region. The keys are lowercased for the
+/// case-insensitive lookup.
+fn write_lookup_maps<W: Write>(w: &mut W, names: &[&str], codegen: &CodegenOptions, ignore_case: bool) -> IOResult<()> {
+    if !codegen.feature_gates {
+        return write_zones_map(w, &lookup_map_name(None, ignore_case), names, ignore_case);
+    }
+
+    let ungated: Vec<&str> = names.iter().cloned().filter(|n| !n.contains('/')).collect();
+    try!(write_zones_map(w, &lookup_map_name(None, ignore_case), &ungated, ignore_case));
+
+    for region in &regions(names) {
+        let prefix = format!("{}/", region);
+        let zones: Vec<&str> = names.iter().cloned().filter(|n| n.starts_with(&prefix)).collect();
+
+        try!(writeln!(w, ""));
+        try!(write_feature_gate(w, region, codegen));
+        try!(write_zones_map(w, &lookup_map_name(Some(region), ignore_case), &zones, ignore_case));
+    }
+
+    Ok(())
+}
+
+/// Writes the body of one of the lookup functions, which looks `input` up
+/// in the right map.
+fn write_lookup_body<W: Write>(w: &mut W, names: &[&str], codegen: &CodegenOptions, ignore_case: bool) -> IOResult<()> {
+    if !codegen.feature_gates {
+        return writeln!(w, "    {}.get(input).cloned()", lookup_map_name(None, ignore_case));
+    }
+
+    try!(writeln!(w, "    match input.find('/').map(|slash| &input[.. slash]) {{"));
+    for region in &regions(names) {
+        let key = if ignore_case { region.to_lowercase() } else { region.to_string() };
+        try!(writeln!(w, "        #[cfg(feature = {:?})]", feature_name(region)));
+        try!(writeln!(w, "        Some({:?}) => {}.get(input).cloned(),", key, lookup_map_name(Some(region), ignore_case)));
+    }
+    try!(writeln!(w, "        _ => {}.get(input).cloned(),", lookup_map_name(None, ignore_case)));
+    writeln!(w, "    }}")
+}
+
+/// Writes a static map from the given zones’ names to the zones, with the
+/// names lowercased if the map is for the case-insensitive lookup. If two
+/// names are the same apart from their case, only the first is kept.
+fn write_zones_map<W: Write>(w: &mut W, map_name: &str, names: &[&str], ignore_case: bool) -> IOResult<()> {
+    try!(write!(w, "static {}: phf::Map<&'static str, &'static StaticTimeZone<'static>> = ", map_name));
+
+    let mut keys = BTreeSet::new();
+    let mut phf_map = PHFMap::new();
+    for name in names {
+        let key = if ignore_case { name.to_lowercase() } else { name.to_string() };
+        if keys.insert(key.clone()) {
+            phf_map.entry(key, &format!("&{}", static_path(name)));
+        }
+    }
+    try!(phf_map.build(w));
+
+    writeln!(w, ";")
+}
+
+/// Returns the name of the static map that a lookup function uses for the
+/// zones in the given region, or the ones that aren’t in a region.
+fn lookup_map_name(region: Option<&str>, ignore_case: bool) -> String {
+    let base = if ignore_case { "LOWERCASE_ZONES" } else { "ZONES" };
+
+    match region {
+        Some(region) => format!("{}_{}", sanitise_name(region).to_uppercase(), base),
+        None         => base.to_owned(),
+    }
+}
+
+/// Writes the `find` function, which finds the timespan a zone is in at an
+/// instant. Each zone’s transitions are written out in order, so it can
+/// search them by bisection, rather than leaving that to every program
+/// that uses the data.
+fn write_find<W: Write>(w: &mut W) -> IOResult<()> {
+    try!(writeln!(w, "/// Returns the timespan in effect in the given zone at the given Unix"));
+    try!(writeln!(w, "/// timestamp. The transitions are sorted, so this finds it with a binary"));
+    try!(writeln!(w, "/// search rather than looking through them all."));
+    try!(writeln!(w, "pub fn find<'a>(zone: &'a StaticTimeZone<'a>, timestamp: i64) -> &'a FixedTimespan<'a> {{"));
+    try!(writeln!(w, "    let set = &zone.fixed_timespans;"));
+    try!(writeln!(w, "    match set.rest.binary_search_by(|&(at, _)| at.cmp(&timestamp)) {{"));
+    try!(writeln!(w, "        Ok(index)   => &set.rest[index].1,"));
+    try!(writeln!(w, "        Err(0)      => &set.first,"));
+    try!(writeln!(w, "        Err(index)  => &set.rest[index - 1].1,"));
+    try!(writeln!(w, "    }}"));
+    writeln!(w, "}}\n")
+}
+
+/// Writes the map of aliases — the names of links — to the names of the
+/// canonical zones they link to, and the `canonical_name` function that
+/// uses it.
+///
+/// The function goes through `lookup`, so names that aren’t available
+/// because their region’s feature is turned off aren’t found, even though
+/// the map of aliases always has every link in.
+fn write_aliases<W: Write>(w: &mut W, links: &BTreeMap<String, String>) -> IOResult<()> {
+    try!(writeln!(w, "\n/// The names of links to other zones, which are deprecated or"));
+    try!(writeln!(w, "/// alternative names, mapped to the names of the zones they link to."));
+    try!(write!(w, "pub static ALIASES: phf::Map<&'static str, &'static str> = "));
+
+    let mut phf_map = PHFMap::new();
+    for (name, target) in links {
+        phf_map.entry(&**name, &format!("{:?}", target));
+    }
+    try!(phf_map.build(w));
+
+    try!(writeln!(w, ";\n"));
+    try!(writeln!(w, "/// Returns the name of the zone with the given name, or of the zone it"));
+    try!(writeln!(w, "/// links to if it’s an alias."));
+    try!(writeln!(w, "pub fn canonical_name(input: &str) -> Option<&'static str> {{"));
+    try!(writeln!(w, "    lookup(input).map(|zone| ALIASES.get(zone.name).cloned().unwrap_or(zone.name))"));
+    writeln!(w, "}}")
+}
+
+/// Writes the map of Windows zone names to zone names, and the
+/// `lookup_windows` function that uses it, unless there aren’t any.
+///
+/// As with `canonical_name`, the function goes through `lookup`, so zones
+/// that aren’t in the data crate aren’t found, even if the map has them.
+fn write_windows_zones<W: Write>(w: &mut W, windows_zones: &BTreeMap<String, String>) -> IOResult<()> {
+    if windows_zones.is_empty() {
+        return Ok(());
+    }
+
+    try!(writeln!(w, "\n/// The names Windows uses for time zones, mapped to the names of the"));
+    try!(writeln!(w, "/// zones to use for them."));
+    try!(write!(w, "pub static WINDOWS_ZONES: phf::Map<&'static str, &'static str> = "));
+
+    let mut phf_map = PHFMap::new();
+    for (windows_name, zone_name) in windows_zones {
+        phf_map.entry(&**windows_name, &format!("{:?}", zone_name));
+    }
+    try!(phf_map.build(w));
+
+    try!(writeln!(w, ";\n"));
+    try!(writeln!(w, "/// Looks up a zone by the name Windows uses for it, such as"));
+    try!(writeln!(w, "/// `W. Europe Standard Time`."));
+    try!(writeln!(w, "pub fn lookup_windows(input: &str) -> Option<&'static StaticTimeZone<'static>> {{"));
+    try!(writeln!(w, "    WINDOWS_ZONES.get(input).and_then(|name| lookup(name))"));
+    writeln!(w, "}}")
+}
+
+/// Writes the list of the locations of the zones’ principal cities, and
+/// the `zone_location` and `nearest_zone` functions that search it, unless
+/// there aren’t any.
+///
+/// Only the zones in the data crate get listed. Without the standard
+/// library, there’s no `sin` or `cos` to measure distances with, so the
+/// list gets written without the function.
+fn write_locations<W: Write>(w: &mut W, names: &[&str], codegen: &CodegenOptions) -> IOResult<()> {
+    let locations: Vec<&ZoneLocation> = codegen.locations.iter().filter(|l| names.contains(&&*l.zone)).collect();
+    if locations.is_empty() {
+        return Ok(());
+    }
+
+    try!(writeln!(w, "\n/// Where a zone is used, and where its principal city is."));
+    try!(writeln!(w, "#[derive(PartialEq, Debug, Copy, Clone)]"));
+    try!(writeln!(w, "pub struct ZoneLocation {{"));
+    try!(writeln!(w, "    /// The name of the zone."));
+    try!(writeln!(w, "    pub zone: &'static str,"));
+    try!(writeln!(w, "    /// The ISO 3166 codes of the countries that use the zone, most"));
+    try!(writeln!(w, "    /// populous first."));
+    try!(writeln!(w, "    pub countries: &'static [&'static str],"));
+    try!(writeln!(w, "    /// The latitude of the city, in degrees north."));
+    try!(writeln!(w, "    pub latitude: f64,"));
+    try!(writeln!(w, "    /// The longitude of the city, in degrees east."));
+    try!(writeln!(w, "    pub longitude: f64,"));
+    try!(writeln!(w, "    /// The comment describing which part of the countries use the"));
+    try!(writeln!(w, "    /// zone, if there is one."));
+    try!(writeln!(w, "    pub comment: Option<&'static str>,"));
+    try!(writeln!(w, "}}\n"));
+
+    try!(writeln!(w, "/// The locations of the zones’ principal cities."));
+    try!(writeln!(w, "pub static ZONE_LOCATIONS: &'static [ZoneLocation] = &["));
+    for location in &locations {
+        try!(writeln!(w, "    ZoneLocation {{ zone: {:?}, countries: &{:?}, latitude: {:?}, longitude: {:?}, comment: {:?} }},",
+                      location.zone, location.countries, location.latitude, location.longitude, location.comment));
+    }
+    try!(writeln!(w, "];\n"));
+
+    // A link only has its own entry if the tab file is older than the
+    // data, so fall back to the entry of the zone it links to.
+    try!(writeln!(w, "/// Looks up the location of the zone with the given name, or of the"));
+    try!(writeln!(w, "/// zone it links to if it doesn’t have one of its own."));
+    try!(writeln!(w, "pub fn zone_location(input: &str) -> Option<&'static ZoneLocation> {{"));
+    try!(writeln!(w, "    let name = match lookup(input) {{"));
+    try!(writeln!(w, "        Some(zone) => zone.name,"));
+    try!(writeln!(w, "        None => return None,"));
+    try!(writeln!(w, "    }};"));
+    try!(writeln!(w, "    ZONE_LOCATIONS.iter().find(|location| location.zone == name)"));
+    try!(writeln!(w, "        .or_else(|| canonical_name(name).and_then(|target| ZONE_LOCATIONS.iter().find(|location| location.zone == target)))"));
+    try!(writeln!(w, "}}"));
+
+    if codegen.no_std {
+        return Ok(());
+    }
+
+    // Comparing the haversine of each distance is enough to find the
+    // smallest, without taking the square root and arcsine.
+    try!(writeln!(w, "\n/// Looks up the zone whose principal city is closest to the given"));
+    try!(writeln!(w, "/// latitude and longitude, in degrees north and east."));
+    try!(writeln!(w, "pub fn nearest_zone(latitude: f64, longitude: f64) -> Option<&'static StaticTimeZone<'static>> {{"));
+    try!(writeln!(w, "    let phi = latitude.to_radians();"));
+    try!(writeln!(w, "    let mut nearest: Option<(f64, &'static str)> = None;"));
+    try!(writeln!(w, "    for location in ZONE_LOCATIONS {{"));
+    try!(writeln!(w, "        let location_phi = location.latitude.to_radians();"));
+    try!(writeln!(w, "        let half_dphi = (location_phi - phi) / 2.0;"));
+    try!(writeln!(w, "        let half_dlambda = (location.longitude - longitude).to_radians() / 2.0;"));
+    try!(writeln!(w, "        let a = half_dphi.sin().powi(2) + phi.cos() * location_phi.cos() * half_dlambda.sin().powi(2);"));
+    try!(writeln!(w, "        match nearest {{"));
+    try!(writeln!(w, "            Some((best, _)) if best <= a => {{}},"));
+    try!(writeln!(w, "            _ => nearest = Some((a, location.zone)),"));
+    try!(writeln!(w, "        }}"));
+    try!(writeln!(w, "    }}"));
+    try!(writeln!(w, "    nearest.and_then(|(_, name)| lookup(name))"));
+    writeln!(w, "}}")
+}
+
+/// Writes the list of leap seconds and when it expires, unless there isn’t
+/// one.
+fn write_leap_seconds<W: Write>(w: &mut W, codegen: &CodegenOptions) -> IOResult<()> {
+    let table = match codegen.leap_seconds {
+        Some(ref table) => table,
+        None            => return Ok(()),
+    };
+
+    try!(writeln!(w, "\n/// Every leap second: the Unix timestamp at which the difference between"));
+    try!(writeln!(w, "/// UTC and TAI changes, and the number of seconds it changes by."));
+    try!(writeln!(w, "pub static LEAP_SECONDS: &'static [(i64, i32)] = &["));
+    for leap in &table.leap_seconds {
+        try!(writeln!(w, "    ({}, {}),", leap.timestamp, leap.correction));
+    }
+    try!(writeln!(w, "];\n"));
+
+    try!(writeln!(w, "/// The Unix timestamp after which there could be leap seconds missing"));
+    try!(writeln!(w, "/// from `LEAP_SECONDS`, if the `leapseconds` file said when that is."));
+    writeln!(w, "pub const LEAP_SECONDS_EXPIRY: Option<i64> = {:?};", table.expires)
+}
+
+/// Writes the `TimeZoneId` enum, with a variant for every zone and link,
+/// which is smaller to store and nicer to match on than a name.
+///
+/// Its methods are all `match`es rather than map lookups, so with feature
+/// gates, the variants and arms for each zone can be left out along with
+/// the zone’s region.
+fn write_zone_id_enum<W: Write>(w: &mut W, names: &[&str], codegen: &CodegenOptions) -> IOResult<()> {
+    let std = if codegen.no_std { "core" } else { "std" };
+
+    try!(writeln!(w, "\n/// The identifier of a zone or link, which can be turned into its"));
+    try!(writeln!(w, "/// name or its data without looking anything up."));
+    try!(writeln!(w, "#[derive(PartialEq, Eq, PartialOrd, Ord, Hash, Debug, Copy, Clone)]"));
+    try!(writeln!(w, "#[allow(non_camel_case_types)]"));
+    try!(writeln!(w, "pub enum TimeZoneId {{"));
+    for name in names {
+        try!(write_zone_gate(w, "    ", name, codegen));
+        try!(writeln!(w, "    {},", variant_name(name)));
+    }
+    try!(writeln!(w, "}}\n"));
+
+    try!(writeln!(w, "impl TimeZoneId {{"));
+    try!(writeln!(w, "    /// Returns the name of the zone or link."));
+    try!(writeln!(w, "    pub fn as_str(self) -> &'static str {{"));
+    try!(writeln!(w, "        match self {{"));
+    for name in names {
+        try!(write_zone_gate(w, "            ", name, codegen));
+        try!(writeln!(w, "            TimeZoneId::{} => {:?},", variant_name(name), name));
+    }
+    try!(writeln!(w, "        }}"));
+    try!(writeln!(w, "    }}\n"));
+    try!(writeln!(w, "    /// Returns the zone’s data."));
+    try!(writeln!(w, "    pub fn time_zone(self) -> &'static StaticTimeZone<'static> {{"));
+    try!(writeln!(w, "        match self {{"));
+    for name in names {
+        try!(write_zone_gate(w, "            ", name, codegen));
+        try!(writeln!(w, "            TimeZoneId::{} => &{},", variant_name(name), static_path(name)));
+    }
+    try!(writeln!(w, "        }}"));
+    try!(writeln!(w, "    }}"));
+    try!(writeln!(w, "}}\n"));
+
+    try!(writeln!(w, "/// The error returned when parsing the name of a zone that isn’t known."));
+    try!(writeln!(w, "#[derive(PartialEq, Debug, Copy, Clone)]"));
+    try!(writeln!(w, "pub struct UnknownTimeZone;\n"));
+
+    try!(writeln!(w, "impl ::{}::str::FromStr for TimeZoneId {{", std));
+    try!(writeln!(w, "    type Err = UnknownTimeZone;\n"));
+    try!(writeln!(w, "    fn from_str(input: &str) -> Result<TimeZoneId, UnknownTimeZone> {{"));
+    try!(writeln!(w, "        match input {{"));
+    for name in names {
+        try!(write_zone_gate(w, "            ", name, codegen));
+        try!(writeln!(w, "            {:?} => Ok(TimeZoneId::{}),", name, variant_name(name)));
+    }
+    try!(writeln!(w, "            _ => Err(UnknownTimeZone),"));
+    try!(writeln!(w, "        }}"));
+    try!(writeln!(w, "    }}"));
+    writeln!(w, "}}")
+}
+
+/// Writes the attribute that puts the next line behind the feature for the
+/// given zone’s region, if the regions are feature-gated and the zone is in
+/// one.
+fn write_zone_gate<W: Write>(w: &mut W, indent: &str, zone_name: &str, codegen: &CodegenOptions) -> IOResult<()> {
+    match zone_name.find('/') {
+        Some(slash) if codegen.feature_gates => writeln!(w, "{}#[cfg(feature = {:?})]", indent, feature_name(&zone_name[.. slash])),
+        _                                    => Ok(()),
+    }
+}
+
+/// Returns the zone that the given name links to, if it’s a link that can
+/// re-export that zone’s static rather than having its own copy. That’s
+/// any link, unless its regions are feature-gated and the zone is in a
+/// different region, which could be turned off while the link’s is on.
+fn shared_link_target<'l>(links: &'l BTreeMap<String, String>, name: &str, codegen: &CodegenOptions) -> Option<&'l str> {
+    let region = |name: &str| name.find('/').map(|slash| name[.. slash].to_owned());
+
+    match links.get(name) {
+        Some(target) if !codegen.feature_gates || region(target).is_none() || region(target) == region(name) => Some(target),
+        _                                                                                                  => None,
+    }
+}
+
+/// Returns the path to a zone’s static, from the root module.
+fn static_path(zone_name: &str) -> String {
+    zone_name.split('/').map(identifier).collect::<Vec<_>>().join("::")
+}
+
+/// Writes the attribute that puts the next item behind the given region’s
+/// feature, if the regions are feature-gated.
+fn write_feature_gate<W: Write>(w: &mut W, region: &str, codegen: &CodegenOptions) -> IOResult<()> {
+    if codegen.feature_gates {
+        try!(writeln!(w, "#[cfg(feature = {:?})]", feature_name(region)));
+    }
+
+    Ok(())
+}
+
+/// Returns the regions that the zones with the given names are in: the
+/// parts of the names before the first slash, for those that have one.
+fn regions<'name>(names: &[&'name str]) -> BTreeSet<&'name str> {
+    names.iter().filter_map(|name| name.find('/').map(|slash| &name[.. slash])).collect()
+}
+
+/// Returns the name of the Cargo feature that turns on a region.
+fn feature_name(region: &str) -> String {
+    region.to_lowercase()
+}
+
+
+/// Writes one zone as a static with the given identifier, its timespans’
+/// abbreviations referring to their index in the given table.
+fn write_zone_static<W: Write>(w: &mut W, identifier: &str, name: &str, set: &FixedTimespanSet, abbreviations: &[String], codegen: &CodegenOptions) -> IOResult<()> {
+    let abbreviation = |name: &str| {
+        let index = abbreviations.binary_search_by(|a| (**a).cmp(name)).unwrap();
+        if codegen.no_std {
+            format!("ABBREVIATIONS[{}],  // {}", index, name)
+        }
+        else {
+            format!("Cow::Borrowed(ABBREVIATIONS[{}]),  // {}", index, name)
+        }
+    };
+
+    try!(writeln!(w, "pub static {}: StaticTimeZone<'static> = StaticTimeZone {{", identifier));
+    try!(writeln!(w, "    name: {:?},", name));
+    try!(writeln!(w, "    fixed_timespans: FixedTimespanSet {{"));
+
+    try!(writeln!(w, "        first: FixedTimespan {{"));
+    try!(writeln!(w, "            offset: {:?},  // UTC offset {:?}, DST offset {:?}", set.first.total_offset(), set.first.utc_offset, set.first.dst_offset));
+    try!(writeln!(w, "            is_dst: {:?},", set.first.is_dst));
+    try!(writeln!(w, "            name:   {}", abbreviation(&set.first.name)));
+    try!(writeln!(w, "        }},"));
+
+    try!(writeln!(w, "        rest: &["));
+
+    for t in &set.rest {
+        try!(writeln!(w, "        ({:?}, FixedTimespan {{  // {} UTC", t.0, LocalDateTime::at(t.0).iso()));
+
+        // Write the total offset (the only value that gets used)
+        // and both the offsets that get added together, as a
+        // comment in the data crate.
+        try!(writeln!(w, "            offset: {:?},  // UTC offset {:?}, DST offset {:?}", t.1.total_offset(), t.1.utc_offset, t.1.dst_offset));
+        try!(writeln!(w, "            is_dst: {:?},", t.1.is_dst));
+        try!(writeln!(w, "            name:   {}", abbreviation(&t.1.name)));
+        try!(writeln!(w, "        }}),"));
+    }
+    try!(writeln!(w, "    ]}},"));
+    writeln!(w, "}};\n\n")
+}
+
 /// Rust places constraints on what modules can be named, so we need to
 /// “sanitise” some of the time zone names before they can be made into
-/// modules.
+/// modules. This works on one component of a name — the part between two
+/// slashes — at a time, and gives the name of its file as well as its
+/// module, so it doesn’t deal with keywords: see `identifier` for that.
+///
+/// A `+` becomes `Plus`, so `Etc/GMT+5` and `Etc/GMT-5` don’t end up with
+/// the same name, and anything else that can’t go in an identifier, such as
+/// a `-`, becomes an underscore. Names that start with a digit get an
+/// underscore in front.
 fn sanitise_name(name: &str) -> String {
-    name.replace("-", "_")
+    let mut sanitised = String::with_capacity(name.len());
+
+    if name.chars().next().map_or(true, |c| c.is_ascii_digit()) {
+        sanitised.push('_');
+    }
+
+    for c in name.chars() {
+        match c {
+            '+'                                         => sanitised.push_str("Plus"),
+            c if c.is_ascii_alphanumeric() || c == '_'  => sanitised.push(c),
+            _                                           => sanitised.push('_'),
+        }
+    }
+
+    sanitised
 }
 
+/// Returns the Rust identifier for one component of a name, which is its
+/// sanitised name, as a raw identifier if that’s a keyword. The few
+/// keywords that can’t be raw identifiers get an underscore on the end
+/// instead.
+fn identifier(name: &str) -> String {
+    let sanitised = sanitise_name(name);
+
+    if UNRAWABLE_KEYWORDS.contains(&&*sanitised) {
+        sanitised + "_"
+    }
+    else if KEYWORDS.contains(&&*sanitised) {
+        format!("r#{}", sanitised)
+    }
+    else {
+        sanitised
+    }
+}
+
+/// Checks that no two zones, links, or modules would end up with the same
+/// Rust identifier once their names have been sanitised, as the generated
+/// code wouldn’t compile if they did. Returns an error naming both of them
+/// for the first pair that would.
+fn check_identifiers(names: &[&str]) -> IOResult<()> {
+    let mut identifiers: HashMap<String, &str> = HashMap::new();
+
+    for name in names {
+        let mut end = 0;
+        for component in name.split('/') {
+            end += component.len();
+            let path = &name[.. end];
+            let sanitised = path.split('/').map(sanitise_name).collect::<Vec<_>>().join("/");
+
+            match identifiers.get(&sanitised) {
+                Some(existing) if *existing != path => {
+                    let message = format!("{} and {} would both be named {} in the generated code", existing, path, sanitised.replace("/", "::"));
+                    return Err(IOError::new(ErrorKind::InvalidData, message));
+                },
+                Some(_) => {},
+                None    => { let _ = identifiers.insert(sanitised, path); },
+            }
+
+            end += 1;
+        }
+    }
+
+    Ok(())
+}
+
+/// Words that can’t be used as identifiers as they are, including the ones
+/// reserved for the future.
+const KEYWORDS: &'static [&'static str] = &[
+    "abstract", "as", "async", "await", "become", "box", "break", "const",
+    "continue", "do", "dyn", "else", "enum", "extern", "false", "final",
+    "fn", "for", "gen", "if", "impl", "in", "let", "loop", "macro", "match",
+    "mod", "move", "mut", "override", "priv", "pub", "ref", "return",
+    "static", "struct", "trait", "true", "try", "type", "typeof", "unsafe",
+    "unsized", "use", "virtual", "where", "while", "yield",
+];
+
+/// Keywords that can’t even be used as raw identifiers.
+const UNRAWABLE_KEYWORDS: &'static [&'static str] = &[ "_", "crate", "self", "Self", "super" ];
+
 
 /// The comment placed at the top of all autogenerated files, so they aren’t
 /// ever changed by a human and then overwritten by this program later.
@@ -246,8 +1926,43 @@ use std::borrow::Cow;
 use datetime::zone::{StaticTimeZone, FixedTimespanSet, FixedTimespan};
 "##;
 
+/// The types used by zoneinfo Rust files that don’t use the standard
+/// library, which take the place of the ones from `datetime`.
+const NO_STD_TYPES: &'static str = r##"
+/// A time zone, with all its timespans known at compile time.
+#[derive(PartialEq, Debug, Copy, Clone)]
+pub struct StaticTimeZone<'a> {
+    pub name: &'a str,
+    pub fixed_timespans: FixedTimespanSet<'a>,
+}
+
+/// The timespan in effect before the first transition, and then the time
+/// of each transition with the timespan it starts.
+#[derive(PartialEq, Debug, Copy, Clone)]
+pub struct FixedTimespanSet<'a> {
+    pub first: FixedTimespan<'a>,
+    pub rest: &'a [(i64, FixedTimespan<'a>)],
+}
+
+/// A period of time with the same offset, DST flag, and abbreviation.
+#[derive(PartialEq, Debug, Copy, Clone)]
+pub struct FixedTimespan<'a> {
+    pub offset: i64,
+    pub is_dst: bool,
+    pub name: &'a str,
+}
+"##;
+
+/// The version of `phf` that the generated maps are for, which has to match
+/// the version of `phf_codegen` that generates them.
+const PHF_VERSION: &'static str = "0.7.12";
+
+/// Where the `datetime` crate, with the types used by the generated code,
+/// comes from.
+const DATETIME_GIT: &'static str = "https://github.com/rust-datetime/datetime.git";
+
 /// The imports needed for a `mod.rs` file.
 const MOD_HEADER: &'static str = r##"
-use datetime::zone::StaticTimeZone;
+use datetime::zone::{StaticTimeZone, FixedTimespan};
 use phf;
 "##;
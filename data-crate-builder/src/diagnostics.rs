@@ -0,0 +1,286 @@
+//! Errors and warnings as records for other programs to read.
+//!
+//! The binary normally prints errors and warnings as text, which is fine
+//! for people but awkward for tools, such as bots that annotate patches to
+//! the tz database with whatever’s wrong with them. Run with
+//! `--error-format json`, it prints each one as a JSON object on a line of
+//! its own instead:
+//!
+//! ```json
+//! {"file": "europe", "line": 12, "column": 45, "message": "invalid SAVE field", "severity": "error"}
+//! ```
+//!
+//! The column is where the field the parser didn’t like starts, counting
+//! from 1, or `null` if it couldn’t tell which field was wrong, such as
+//! when the line doesn’t start with a known keyword. Errors that aren’t
+//! about any particular line, such as a file that couldn’t be read, or a
+//! problem found by validation, have a `null` file and line as well.
+//!
+//! Everything the builder prints along the way, such as the directories it
+//! creates, goes through the `log` crate, so programs that use the library
+//! can silence or capture it. The binary prints it to stderr, so it never
+//! gets mixed up with a command’s output, unless run with `-q`, and prints
+//! more with `-v`, or `-vv`.
+
+use std::ffi::OsString;
+
+use log::LevelFilter;
+
+use errors::{CliError, ParseError};
+use json::escape;
+use warnings::Warning;
+
+
+/// The way errors and warnings get printed.
+#[derive(PartialEq, Debug, Copy, Clone)]
+pub enum ErrorFormat {
+
+    /// As text, one line each (the default).
+    Text,
+
+    /// As JSON objects, one line each.
+    Json,
+}
+
+impl ErrorFormat {
+
+    /// Returns the error format with the given name, as given to
+    /// `--error-format`.
+    pub fn from_name(name: &str) -> Option<ErrorFormat> {
+        match name {
+            "text"  => Some(ErrorFormat::Text),
+            "json"  => Some(ErrorFormat::Json),
+            _       => None,
+        }
+    }
+
+    /// Returns the error format asked for in the given command-line
+    /// arguments, so it can be known before the arguments get parsed, in
+    /// case parsing them fails. Anything other than `json` means text.
+    pub fn from_args(args: &[OsString]) -> ErrorFormat {
+        let mut args = args.iter().filter_map(|a| a.to_str());
+
+        while let Some(arg) = args.next() {
+            let value = if arg == "--error-format" { args.next() }
+                        else if arg.starts_with("--error-format=") { Some(&arg["--error-format=".len() ..]) }
+                        else { continue };
+
+            if value == Some("json") {
+                return ErrorFormat::Json;
+            }
+        }
+
+        ErrorFormat::Text
+    }
+
+    /// Returns the given warning as it should be printed.
+    pub fn warning(&self, warning: &Warning) -> String {
+        match *self {
+            ErrorFormat::Text  => format!("warning: {}", warning),
+            ErrorFormat::Json  => Diagnostic::from(warning).to_json(),
+        }
+    }
+
+    /// Returns the given error as it should be printed, which, for errors
+    /// made up of several others, may be several lines.
+    pub fn error(&self, err: &CliError) -> String {
+        match *self {
+            ErrorFormat::Text  => err.to_string(),
+            ErrorFormat::Json  => {
+                let lines: Vec<String> = diagnostics(err).iter().map(Diagnostic::to_json).collect();
+                lines.join("\n")
+            },
+        }
+    }
+}
+
+
+/// Returns the most detailed level of log messages to print, going by the
+/// `-q` and `-v` flags in the given command-line arguments: only errors
+/// with `-q`, debugging messages with `-v`, everything with `-vv`, and the
+/// informational messages in between otherwise.
+pub fn log_level(args: &[OsString]) -> LevelFilter {
+    let mut verbosity = 0;
+
+    for arg in args.iter().filter_map(|a| a.to_str()) {
+        match arg {
+            "-q" | "--quiet"    => return LevelFilter::Error,
+            "-v" | "--verbose"  => verbosity += 1,
+            "-vv"               => verbosity += 2,
+            _                   => {},
+        }
+    }
+
+    match verbosity {
+        0  => LevelFilter::Info,
+        1  => LevelFilter::Debug,
+        _  => LevelFilter::Trace,
+    }
+}
+
+
+/// How bad a diagnostic is.
+#[derive(PartialEq, Debug, Copy, Clone)]
+pub enum Severity {
+
+    /// Something that stopped anything from being generated.
+    Error,
+
+    /// Something odd that didn’t stop anything.
+    Warning,
+}
+
+impl Severity {
+
+    /// Returns the name of this severity, as it appears in the JSON.
+    pub fn name(&self) -> &'static str {
+        match *self {
+            Severity::Error    => "error",
+            Severity::Warning  => "warning",
+        }
+    }
+}
+
+
+/// One error or warning, with the place in the input it’s about.
+#[derive(PartialEq, Debug, Clone)]
+pub struct Diagnostic {
+
+    /// The file the diagnostic is about, if it’s about one.
+    pub filename: Option<String>,
+
+    /// The number of the line in that file, counting from 1.
+    pub line: Option<usize>,
+
+    /// The column in that line, counting from 1, if it’s known.
+    pub column: Option<usize>,
+
+    /// A human-readable description of what’s wrong.
+    pub message: String,
+
+    /// Whether this is an error or a warning.
+    pub severity: Severity,
+}
+
+impl Diagnostic {
+
+    /// Returns this diagnostic as a single-line JSON object.
+    pub fn to_json(&self) -> String {
+        format!("{{\"file\": {}, \"line\": {}, \"column\": {}, \"message\": {}, \"severity\": {}}}",
+                self.filename.as_ref().map(|f| escape(f)).unwrap_or_else(|| "null".to_owned()),
+                json_number(self.line),
+                json_number(self.column),
+                escape(&self.message),
+                escape(self.severity.name()))
+    }
+}
+
+impl<'a> From<&'a ParseError> for Diagnostic {
+    fn from(err: &'a ParseError) -> Diagnostic {
+        Diagnostic {
+            filename: Some(err.filename.clone()),
+            line:     Some(err.line),
+            column:   err.column,
+            message:  err.error.clone(),
+            severity: Severity::Error,
+        }
+    }
+}
+
+impl<'a> From<&'a Warning> for Diagnostic {
+    fn from(warning: &'a Warning) -> Diagnostic {
+        let location = warning.location();
+
+        Diagnostic {
+            filename: location.map(|(f, _)| f.to_owned()),
+            line:     location.map(|(_, l)| l),
+            column:   warning.column(),
+            message:  warning.message(),
+            severity: Severity::Warning,
+        }
+    }
+}
+
+/// Returns the diagnostics for an error: one for each line that couldn’t be
+/// parsed, or each problem found by validation or linting, or just one for
+/// anything else.
+pub fn diagnostics(err: &CliError) -> Vec<Diagnostic> {
+    let unlocated = |message: String| Diagnostic {
+        filename: None,
+        line:     None,
+        column:   None,
+        message:  message,
+        severity: Severity::Error,
+    };
+
+    match *err {
+        CliError::Errors(ref errs)        => errs.iter().map(Diagnostic::from).collect(),
+        CliError::Invalid(ref problems)   => problems.iter().map(|p| unlocated(p.to_string())).collect(),
+        CliError::Lint(ref findings)      => findings.iter().map(|f| unlocated(f.to_string())).collect(),
+        ref other                         => vec![ unlocated(other.to_string()) ],
+    }
+}
+
+fn json_number(number: Option<usize>) -> String {
+    number.map(|n| n.to_string()).unwrap_or_else(|| "null".to_owned())
+}
+
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn parse_errors() {
+        let err = CliError::from(vec![
+            ParseError { filename: "europe".to_owned(), line: 12, column: Some(45), error: "invalid SAVE field".to_owned() },
+            ParseError { filename: "asia".to_owned(),   line: 3,  column: None,     error: "Rule \"X\" has a \"quote\"".to_owned() },
+        ]);
+
+        assert_eq!(ErrorFormat::Json.error(&err),
+                   "{\"file\": \"europe\", \"line\": 12, \"column\": 45, \"message\": \"invalid SAVE field\", \"severity\": \"error\"}\n\
+                    {\"file\": \"asia\", \"line\": 3, \"column\": null, \"message\": \"Rule \\\"X\\\" has a \\\"quote\\\"\", \"severity\": \"error\"}");
+    }
+
+    #[test]
+    fn other_errors() {
+        let err = CliError::UnknownZone("Nowhere".to_owned());
+        assert_eq!(ErrorFormat::Json.error(&err),
+                   "{\"file\": null, \"line\": null, \"column\": null, \"message\": \"Unknown time zone: Nowhere\", \"severity\": \"error\"}");
+    }
+
+    #[test]
+    fn warnings() {
+        let warning = Warning::DuplicateLink { filename: "backward".to_owned(), line: 7, name: "GB".to_owned() };
+        assert_eq!(ErrorFormat::Json.warning(&warning),
+                   "{\"file\": \"backward\", \"line\": 7, \"column\": null, \"message\": \"skipping duplicate link \\\"GB\\\"\", \"severity\": \"warning\"}");
+        assert_eq!(ErrorFormat::Text.warning(&warning), "warning: backward:7: skipping duplicate link \"GB\"");
+    }
+
+    #[test]
+    fn skipped_line_columns() {
+        let warning = Warning::SkippedLine { filename: "europe".to_owned(), line: 2, column: Some(19), error: "invalid IN field".to_owned() };
+        assert_eq!(ErrorFormat::Json.warning(&warning),
+                   "{\"file\": \"europe\", \"line\": 2, \"column\": 19, \"message\": \"skipping line: invalid IN field\", \"severity\": \"warning\"}");
+        assert_eq!(ErrorFormat::Text.warning(&warning), "warning: europe:2:19: skipping line: invalid IN field");
+    }
+
+    #[test]
+    fn from_args() {
+        let args = |args: &[&str]| args.iter().map(OsString::from).collect::<Vec<_>>();
+        assert_eq!(ErrorFormat::from_args(&args(&[ "--error-format", "json", "europe" ])), ErrorFormat::Json);
+        assert_eq!(ErrorFormat::from_args(&args(&[ "--error-format=json" ])), ErrorFormat::Json);
+        assert_eq!(ErrorFormat::from_args(&args(&[ "--error-format", "text" ])), ErrorFormat::Text);
+        assert_eq!(ErrorFormat::from_args(&args(&[ "json" ])), ErrorFormat::Text);
+    }
+
+    #[test]
+    fn log_levels() {
+        let args = |args: &[&str]| args.iter().map(OsString::from).collect::<Vec<_>>();
+        assert_eq!(log_level(&args(&[ "tz" ])), LevelFilter::Info);
+        assert_eq!(log_level(&args(&[ "-q", "tz" ])), LevelFilter::Error);
+        assert_eq!(log_level(&args(&[ "-v", "tz" ])), LevelFilter::Debug);
+        assert_eq!(log_level(&args(&[ "-vv", "tz" ])), LevelFilter::Trace);
+        assert_eq!(log_level(&args(&[ "--verbose", "-v" ])), LevelFilter::Trace);
+    }
+}
@@ -0,0 +1,144 @@
+//! Comparing the future transitions of two releases of the tz database.
+//!
+//! When a new release comes out, the question that decides how urgently it
+//! needs to be rolled out is which zones’ clocks will now change at
+//! different times, or to different offsets or abbreviations, than they
+//! would have done with the release already in use. The `diff` mode loads
+//! both releases and prints, for each zone whose upcoming transitions
+//! differ, the transitions only the old release has, marked with `-`, and
+//! the ones only the new release has, marked with `+`:
+//!
+//! ```text
+//! America/Asuncion: future transitions changed
+//! - 2025-03-23 03:00:00 UTC: offset -04:00, abbreviation -04, DST no
+//! + 2025-03-23 03:00:00 UTC: offset -03:00, abbreviation -03, DST no
+//! ```
+//!
+//! Zones that only one of the releases has are listed as added or removed.
+//! Transitions before the current time are ignored, as changes to the past
+//! don’t affect any clocks. The comparison itself is done by
+//! `zoneinfo_parse::diff::TableDiff`.
+
+use std::io::{Write, BufWriter};
+use std::io::Result as IOResult;
+use std::path::Path;
+
+use getopts;
+
+use zoneinfo_parse::diff::{TableDiff, ZoneChange};
+use zoneinfo_parse::transitions::{TransitionOptions, FixedTimespan};
+
+use commands;
+use data_crate::read_table;
+use errors::CliError;
+use query::{describe_time, describe_timespan};
+use system::{Clock, Filesystem};
+use {Report, number_option};
+
+
+/// Writes the differences in the layout above.
+pub fn write_diff<W: Write>(w: &mut W, changes: &[(String, ZoneChange)]) -> IOResult<()> {
+    for &(ref name, ref change) in changes {
+        match *change {
+            ZoneChange::Added    => try!(writeln!(w, "{}: added", name)),
+            ZoneChange::Removed  => try!(writeln!(w, "{}: removed", name)),
+            ZoneChange::Transitions { ref removed, ref added } => {
+                try!(writeln!(w, "{}: future transitions changed", name));
+
+                // Interleave the two lists by time, so a transition that
+                // changed shows up as a pair of lines.
+                let mut lines: Vec<(i64, char, &FixedTimespan)> = Vec::new();
+                lines.extend(removed.iter().map(|&(at, ref timespan)| (at, '-', timespan)));
+                lines.extend(added.iter().map(|&(at, ref timespan)| (at, '+', timespan)));
+                lines.sort_by_key(|&(at, sign, _)| (at, sign == '+'));
+
+                for (at, sign, timespan) in lines {
+                    try!(writeln!(w, "{} {} UTC: {}", sign, describe_time(at), describe_timespan(timespan)));
+                }
+            },
+        }
+    }
+
+    Ok(())
+}
+
+
+/// The options the `diff` mode takes.
+pub fn options() -> getopts::Options {
+    let mut opts = getopts::Options::new();
+    opts.optopt("o", "output", "file to write the differences to, instead of printing them", "FILE");
+    opts.optopt("", "max-year", "last year to expand daylight-saving rules in", "YEAR");
+    opts
+}
+
+/// Runs the `diff` mode of the builder with the given arguments, which come
+/// after the word `diff`. The two free arguments are the old and new
+/// releases, each a directory of source files or a single file. Without
+/// `--output`, the differences go in the report’s `output` to be printed.
+pub fn run<C: Clock, F: Filesystem>(args: &[::std::ffi::OsString], clock: &C, fs: &F) -> Result<Report, CliError> {
+    let command = commands::find("diff").unwrap();
+    let matches = try!(command.parse(args));
+    let (old_path, new_path) = match (matches.free.get(0), matches.free.get(1)) {
+        (Some(old), Some(new))  => (old, new),
+        (Some(_), None)         => return Err(command.missing("NEW")),
+        (None, _)               => return Err(command.missing("OLD")),
+    };
+    if let Some(extra) = matches.free.get(2) {
+        return Err(CliError::Usage(command.name, getopts::Fail::UnrecognizedOption(extra.clone())));
+    }
+
+    let defaults = TransitionOptions::default();
+    let options = TransitionOptions {
+        max_year: try!(number_option(&matches, "max-year", defaults.max_year)),
+        .. defaults
+    };
+
+    let (old, mut warnings) = try!(read_table(fs, &[ old_path.clone() ]));
+    let (new, new_warnings) = try!(read_table(fs, &[ new_path.clone() ]));
+    warnings.extend(new_warnings);
+
+    let changes = TableDiff::new(&old, &new).changed_transitions(clock.now(), &options);
+
+    let mut output = Vec::new();
+    match matches.opt_str("output") {
+        Some(path) => {
+            let mut w = BufWriter::new(try!(fs.create(Path::new(&path))));
+            try!(write_diff(&mut w, &changes));
+        },
+        None => try!(write_diff(&mut output, &changes)),
+    }
+
+    Ok(Report {
+        zone_count: changes.len(),
+        link_count: 0,
+        warnings:   warnings,
+        output:     String::from_utf8(output).unwrap(),
+    })
+}
+
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use zoneinfo_parse::diff::ZoneChange;
+    use zoneinfo_parse::transitions::FixedTimespan;
+
+    #[test]
+    fn layout() {
+        let changes = vec![
+            ("Test/Zone".to_owned(), ZoneChange::Transitions {
+                removed: vec![ (1_742_691_600, FixedTimespan { utc_offset: -14400, dst_offset: 0, is_dst: false, name: "-04".to_owned() }) ],
+                added:   vec![ (1_742_691_600, FixedTimespan { utc_offset: -10800, dst_offset: 0, is_dst: false, name: "-03".to_owned() }) ],
+            }),
+            ("Test/Gone".to_owned(), ZoneChange::Removed),
+        ];
+
+        let mut buf = Vec::new();
+        write_diff(&mut buf, &changes).unwrap();
+        assert_eq!(String::from_utf8(buf).unwrap(),
+                   "Test/Zone: future transitions changed\n\
+                    - 2025-03-23 01:00:00 UTC: offset -04:00, abbreviation -04, DST no\n\
+                    + 2025-03-23 01:00:00 UTC: offset -03:00, abbreviation -03, DST no\n\
+                    Test/Gone: removed\n");
+    }
+}
@@ -0,0 +1,146 @@
+//! Printing a zone’s transitions in the same layout as `zdump -v`.
+//!
+//! For each transition, `zdump -v` prints two lines: one for the last second
+//! before the transition, and one for the instant it happens. Each line has
+//! the time in UT, the local time, the abbreviation, whether daylight-saving
+//! time is in effect, and the total UTC offset in seconds:
+//!
+//! ```text
+//! Europe/London  Sun Mar 31 00:59:59 2024 UT = Sun Mar 31 00:59:59 2024 GMT isdst=0 gmtoff=0
+//! Europe/London  Sun Mar 31 01:00:00 2024 UT = Sun Mar 31 02:00:00 2024 BST isdst=1 gmtoff=3600
+//! ```
+//!
+//! This makes it easy to compare the computed transitions against the ones
+//! on the system, with something like `diff`. The lines `zdump` prints for
+//! the earliest and latest representable times, which don’t correspond to
+//! any transition, are left out.
+
+use std::io::{Write, BufWriter};
+use std::io::Result as IOResult;
+use std::path::Path;
+
+use datetime::{LocalDateTime, DatePiece, TimePiece, Weekday};
+use getopts;
+
+use zoneinfo_parse::transitions::{TableTransitions, TransitionOptions, FixedTimespan, FixedTimespanSet};
+
+use commands;
+use data_crate::read_table;
+use errors::CliError;
+use system::Filesystem;
+use {Report, number_option};
+
+
+/// Writes every transition in the given timespan set, as two lines each in
+/// the layout of `zdump -v`.
+pub fn write_dump<W: Write>(w: &mut W, zone_name: &str, set: &FixedTimespanSet) -> IOResult<()> {
+    let mut before = &set.first;
+
+    for &(at, ref after) in &set.rest {
+        try!(write_line(w, zone_name, at - 1, before));
+        try!(write_line(w, zone_name, at, after));
+        before = after;
+    }
+
+    Ok(())
+}
+
+/// Writes one line, for the given instant during the given timespan.
+fn write_line<W: Write>(w: &mut W, zone_name: &str, instant: i64, timespan: &FixedTimespan) -> IOResult<()> {
+    writeln!(w, "{}  {} UT = {} {} isdst={} gmtoff={}",
+             zone_name, zdump_time(instant), zdump_time(instant + timespan.total_offset()),
+             timespan.name, if timespan.is_dst { 1 } else { 0 }, timespan.total_offset())
+}
+
+/// Formats a number of seconds since the Unix epoch the way `zdump` does,
+/// such as “Sun Mar 31 01:00:00 2024”, with the day of the month padded
+/// with a space.
+fn zdump_time(timestamp: i64) -> String {
+    const MONTHS: [&'static str; 12] = [ "Jan", "Feb", "Mar", "Apr", "May", "Jun",
+                                         "Jul", "Aug", "Sep", "Oct", "Nov", "Dec" ];
+
+    let t = LocalDateTime::at(timestamp);
+    let weekday = match t.weekday() {
+        Weekday::Sunday    => "Sun",
+        Weekday::Monday    => "Mon",
+        Weekday::Tuesday   => "Tue",
+        Weekday::Wednesday => "Wed",
+        Weekday::Thursday  => "Thu",
+        Weekday::Friday    => "Fri",
+        Weekday::Saturday  => "Sat",
+    };
+
+    format!("{} {} {:2} {:02}:{:02}:{:02} {}", weekday, MONTHS[t.month().months_from_january()], t.day(),
+                                               t.hour(), t.minute(), t.second(), t.year())
+}
+
+
+/// The options the `dump` mode takes.
+pub fn options() -> getopts::Options {
+    let mut opts = getopts::Options::new();
+    opts.reqopt("o", "output", "file to write the transitions to", "FILE");
+    opts.optopt("", "min-year", "first year to expand daylight-saving rules in", "YEAR");
+    opts.optopt("", "max-year", "last year to expand daylight-saving rules in", "YEAR");
+    opts
+}
+
+/// Runs the `dump` mode of the builder with the given arguments, which come
+/// after the word `dump`. The first free argument is the zone to dump, and
+/// the rest are the input files.
+pub fn run<F: Filesystem>(args: &[::std::ffi::OsString], fs: &F) -> Result<Report, CliError> {
+    let command = commands::find("dump").unwrap();
+    let matches = try!(command.parse(args));
+    let (zone_name, inputs) = match matches.free.split_first() {
+        Some((zone, inputs)) => (zone, inputs),
+        None                 => return Err(command.missing("ZONE")),
+    };
+
+    let defaults = TransitionOptions::default();
+    let options = TransitionOptions {
+        min_year: try!(number_option(&matches, "min-year", defaults.min_year)),
+        max_year: try!(number_option(&matches, "max-year", defaults.max_year)),
+        .. defaults
+    };
+
+    let (table, warnings) = try!(read_table(fs, inputs));
+    let set = match table.timespans(zone_name, &options) {
+        Some(s) => s,
+        None    => return Err(CliError::UnknownZone(zone_name.clone())),
+    };
+
+    let mut w = BufWriter::new(try!(fs.create(Path::new(&matches.opt_str("output").unwrap()))));
+    try!(write_dump(&mut w, zone_name, &set));
+
+    Ok(Report {
+        zone_count: 1,
+        link_count: if table.links.contains_key(zone_name) { 1 } else { 0 },
+        warnings:   warnings,
+        output:     String::new(),
+    })
+}
+
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use zoneinfo_parse::transitions::{FixedTimespan, FixedTimespanSet};
+
+    #[test]
+    fn layout() {
+        let set = FixedTimespanSet {
+            first: FixedTimespan { utc_offset: 0, dst_offset: 0,    is_dst: false, name: "GMT".to_owned() },
+            rest:  vec![ (1_711_846_800, FixedTimespan { utc_offset: 0, dst_offset: 3600, is_dst:  true, name: "BST".to_owned() }) ],
+        };
+
+        let mut buf = Vec::new();
+        write_dump(&mut buf, "Europe/London", &set).unwrap();
+        assert_eq!(String::from_utf8(buf).unwrap(),
+                   "Europe/London  Sun Mar 31 00:59:59 2024 UT = Sun Mar 31 00:59:59 2024 GMT isdst=0 gmtoff=0\n\
+                    Europe/London  Sun Mar 31 01:00:00 2024 UT = Sun Mar 31 02:00:00 2024 BST isdst=1 gmtoff=3600\n");
+    }
+
+    #[test]
+    fn padded_day() {
+        assert_eq!(zdump_time(0), "Thu Jan  1 00:00:00 1970");
+    }
+}
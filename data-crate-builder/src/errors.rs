@@ -1,16 +1,28 @@
 //! Any errors that can happen ever.
 
+use std::collections::BTreeMap;
 use std::fmt;
 use std::io::Error as IOError;
+use std::process::ExitStatus;
+use std::slice;
+use std::vec;
 
 use getopts;
 
+use commands::COMMANDS;
+use lint::Finding;
+
+use zoneinfo_parse::leap::Error as LeapError;
+use zoneinfo_parse::tab::Error as TabError;
+use zoneinfo_parse::validation::Problem;
+use zoneinfo_parse::windows::Error as WindowsZonesError;
+
 quick_error! {
 
     /// Anything that can go wrong at any stage in the program, causing it to
-    /// return 1 instead of 0.
+    /// exit with a non-zero code. Use `exit_code` to find out which one.
     #[derive(Debug)]
-    pub enum Error {
+    pub enum CliError {
 
         /// A file or directory couldn’t be read or written to.
         IO(err: IOError) {
@@ -18,16 +30,129 @@ quick_error! {
             display(x) -> ("IO error: {}", err)
         }
 
+        /// An external program, such as `curl`, ran but didn’t succeed.
+        CommandFailed(program: &'static str, status: ExitStatus) {
+            display(x) -> ("{} failed ({})", program, status)
+        }
+
+        /// A downloaded file didn’t have the checksum it was meant to.
+        ChecksumMismatch(expected: String, actual: String) {
+            display(x) -> ("Checksum mismatch: expected {}, got {}", expected, actual)
+        }
+
+        /// A downloaded file had a good signature, but not from the key it
+        /// was meant to be signed with.
+        SignatureMismatch(expected: &'static str) {
+            display(x) -> ("Signature mismatch: not signed by key {}", expected)
+        }
+
+        /// A download would have been used without either of the checks
+        /// that make sure it’s the real thing.
+        Unverified {
+            display("Refusing to unpack an archive without checking its signature or checksum")
+        }
+
         /// The `zoneinfo-parse` crate didn’t like one or more lines of input.
         Errors(errs: Errors) {
             from(es: Vec<ParseError>) -> (Errors(es))
             display(x) -> ("{}", errs)
         }
 
-        /// The `getopts` crate didn’t like the user’s command-line args.
-        Getopts(err: getopts::Fail) {
-            from()
-            display(x) -> ("Error parsing options: {}", err)
+        /// The input parsed, but validation found problems with it.
+        Invalid(problems: Problems) {
+            from(ps: Vec<Problem>) -> (Problems(ps))
+            display(x) -> ("{}", problems)
+        }
+
+        /// A table of zone locations couldn’t be parsed.
+        Tab(filename: String, err: TabError) {
+            display(x) -> ("{}: {}", filename, err)
+        }
+
+        /// A list of leap seconds couldn’t be parsed.
+        LeapSeconds(filename: String, err: LeapError) {
+            display(x) -> ("{}: {}", filename, err)
+        }
+
+        /// A mapping of Windows zone names couldn’t be parsed.
+        WindowsZones(filename: String, err: WindowsZonesError) {
+            display(x) -> ("{}: {}", filename, err)
+        }
+
+        /// Files that were checked with `fmt --check` weren’t formatted.
+        Unformatted(filenames: Vec<String>) {
+            display(x) -> ("Not formatted: {}", filenames.join(", "))
+        }
+
+        /// Linting found something suspicious in the input.
+        Lint(findings: Vec<Finding>) {
+            display(x) -> ("{}", findings.iter().map(|f| f.to_string()).collect::<Vec<_>>().join("\n"))
+        }
+
+        /// A command-line option was given a value that doesn’t make sense.
+        InvalidArgument(option: &'static str, value: String) {
+            display(x) -> ("Invalid value for --{}: {:?}", option, value)
+        }
+
+        /// A zone was asked for by name, but the input doesn’t contain it.
+        UnknownZone(name: String) {
+            display(x) -> ("Unknown time zone: {}", name)
+        }
+
+        /// The `getopts` crate didn’t like the user’s command-line args for
+        /// the given command.
+        Usage(command: &'static str, err: getopts::Fail) {
+            display(x) -> ("Error parsing options: {}\nRun `build-data-crate {} --help` to see its options.", err, command)
+        }
+
+        /// One of the arguments the given command needs after its options,
+        /// such as `ZONE`, wasn’t given.
+        MissingArgument(command: &'static str, argument: &'static str) {
+            display(x) -> ("Missing {} argument\nRun `build-data-crate {} --help` to see its options.", argument, command)
+        }
+
+        /// The first argument looked like the name of a command, but there
+        /// isn’t a command with that name.
+        UnknownCommand(name: String) {
+            display(x) -> ("Unknown command: {}\nThe commands are: {}", name, COMMANDS.iter().map(|c| c.name).collect::<Vec<_>>().join(", "))
+        }
+    }
+}
+
+/// The exit code for when one or more lines of input were invalid, the
+/// input failed validation, or a zone table, list of leap seconds, or
+/// Windows zone mapping couldn’t be parsed.
+pub const EXIT_DATA_ERROR: i32 = 1;
+
+/// The exit code for when the command-line arguments were invalid.
+pub const EXIT_USAGE: i32 = 2;
+
+/// The exit code for when a file couldn’t be read or written, or a download
+/// failed or couldn’t be verified.
+pub const EXIT_IO_ERROR: i32 = 3;
+
+impl CliError {
+
+    /// Returns the code the process should exit with because of this error.
+    pub fn exit_code(&self) -> i32 {
+        match *self {
+            CliError::IO(_)                => EXIT_IO_ERROR,
+            CliError::CommandFailed(..)    => EXIT_IO_ERROR,
+            CliError::ChecksumMismatch(..) => EXIT_IO_ERROR,
+            CliError::SignatureMismatch(_) => EXIT_IO_ERROR,
+            CliError::Unverified           => EXIT_USAGE,
+            CliError::Errors(_)            => EXIT_DATA_ERROR,
+            CliError::Invalid(_)           => EXIT_DATA_ERROR,
+            CliError::Tab(..)              => EXIT_DATA_ERROR,
+            CliError::LeapSeconds(..)      => EXIT_DATA_ERROR,
+            CliError::WindowsZones(..)     => EXIT_DATA_ERROR,
+            CliError::Unformatted(_)       => EXIT_DATA_ERROR,
+            CliError::Lint(_)              => EXIT_DATA_ERROR,
+            CliError::InvalidArgument(..)  => EXIT_USAGE,
+            CliError::UnknownZone(_)       => EXIT_USAGE,
+            CliError::Usage(..)            => EXIT_USAGE,
+            CliError::MissingArgument(..)  => EXIT_USAGE,
+            CliError::UnknownCommand(_)    => EXIT_USAGE,
         }
     }
 }
@@ -43,21 +168,120 @@ pub struct ParseError {
     /// The number of the line that failed to be parsed.
     pub line: usize,
 
+    /// The column of the field that failed to be parsed, counting from 1,
+    /// if the parser could tell which field it was.
+    pub column: Option<usize>,
+
     /// A human-readable description of what the error was.
     pub error: String,
 }
 
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        match self.column {
+            Some(column)  => write!(f, "{}:{}:{}: {}", self.filename, self.line, column, self.error),
+            None          => write!(f, "{}:{}: {}", self.filename, self.line, self.error),
+        }
+    }
+}
 
-/// Wrapper around a vector of parse errors for a custom `fmt::Display`
-/// implementation used by the definition in `quick-error!` above.
+
+/// Every parse error from a run, in the order the lines were read.
+///
+/// As well as having a custom `fmt::Display` implementation used by the
+/// definition in `quick-error!` above, this can be iterated over, split up
+/// by file, or turned back into a vector, for programs that want to handle
+/// the errors themselves.
 #[derive(Debug)]
 pub struct Errors(Vec<ParseError>);
 
+impl Errors {
+
+    /// Returns an iterator over the errors.
+    pub fn iter(&self) -> slice::Iter<ParseError> {
+        self.0.iter()
+    }
+
+    /// Returns the number of errors.
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    /// Returns whether there are no errors at all.
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// Groups the errors by the name of the file they were in, keeping them
+    /// in order within each file.
+    pub fn by_file(&self) -> BTreeMap<&str, Vec<&ParseError>> {
+        let mut files = BTreeMap::new();
+
+        for err in &self.0 {
+            files.entry(&*err.filename).or_insert_with(Vec::new).push(err);
+        }
+
+        files
+    }
+}
+
+impl From<Vec<ParseError>> for Errors {
+    fn from(errs: Vec<ParseError>) -> Errors {
+        Errors(errs)
+    }
+}
+
+impl From<Errors> for Vec<ParseError> {
+    fn from(errs: Errors) -> Vec<ParseError> {
+        errs.0
+    }
+}
+
+impl IntoIterator for Errors {
+    type Item = ParseError;
+    type IntoIter = vec::IntoIter<ParseError>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.0.into_iter()
+    }
+}
+
+impl<'a> IntoIterator for &'a Errors {
+    type Item = &'a ParseError;
+    type IntoIter = slice::Iter<'a, ParseError>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.0.iter()
+    }
+}
+
 impl fmt::Display for Errors {
     fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
-        for err in &self.0 {
-            try!(write!(f, "{}:{}: {}\n", err.filename, err.line, err.error));
+        for err in self {
+            try!(write!(f, "{}\n", err));
         }
         Ok(())
     }
-}
\ No newline at end of file
+}
+
+/// Wrapper around a vector of validation problems, for the same reason as
+/// `Errors` above.
+#[derive(Debug)]
+pub struct Problems(Vec<Problem>);
+
+impl Problems {
+
+    /// Returns an iterator over the problems.
+    pub fn iter(&self) -> slice::Iter<Problem> {
+        self.0.iter()
+    }
+}
+
+impl fmt::Display for Problems {
+    fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        for problem in &self.0 {
+            try!(write!(f, "{}\n", problem));
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,258 @@
+//! Downloading the latest release of the tz database.
+//!
+//! IANA publishes each release of the database as a gzipped tarball, with a
+//! stable URL that always points to the latest one. This downloads it into a
+//! directory, unpacks it there, and returns the paths of the source files,
+//! ready to be passed to `DataCrate::new`.
+//!
+//! The archive gets checked before it’s unpacked, against the detached GPG
+//! signature published alongside it, and against a SHA-512 checksum (such
+//! as the one in IANA’s release announcement) if one is given. A signature
+//! only counts if it was made with the tz maintainers’ key, whose
+//! fingerprint is written in below, so a signature from any other key in
+//! the keyring gets rejected. The signature check can only be skipped if
+//! there’s a checksum to check instead, so nothing gets unpacked without
+//! being checked, and either check failing stops anything from being
+//! unpacked.
+//!
+//! Rather than pulling in an HTTP client, an archive library, and an
+//! OpenPGP implementation, this runs the `curl`, `tar`, and `gpg` programs,
+//! which are available nearly everywhere the builder gets run.
+
+use std::ffi::OsString;
+use std::path::Path;
+use std::process::{Command, Stdio};
+
+use getopts;
+use sha2::{Digest, Sha512};
+
+use commands;
+use data_crate::{read_table, source_files};
+use errors::CliError;
+use system::{Filesystem, create_dirs};
+use Report;
+
+
+/// Where IANA publishes the latest release of the tz database.
+pub const TZDATA_URL: &'static str = "https://data.iana.org/time-zones/tzdata-latest.tar.gz";
+
+/// The fingerprint of the key the tz maintainers sign releases with, as
+/// given on IANA’s tz page.
+pub const TZDATA_SIGNING_KEY: &'static str = "7E3792A9D8ACF7D633BC1588ED97E90E62AA7E34";
+
+
+/// How to check a downloaded archive before unpacking it. By default, only
+/// its signature gets checked.
+#[derive(PartialEq, Debug, Clone)]
+pub struct FetchOptions {
+
+    /// The SHA-512 checksum the archive must have, in hexadecimal, if any.
+    pub sha512: Option<String>,
+
+    /// Whether to download the archive’s detached signature and check it
+    /// with `gpg`. It has to have been made with the tz maintainers’ key,
+    /// which has to be in the keyring already. This can only be turned off
+    /// if there’s a checksum to check instead.
+    pub verify_signature: bool,
+}
+
+impl Default for FetchOptions {
+    fn default() -> FetchOptions {
+        FetchOptions {
+            sha512:           None,
+            verify_signature: true,
+        }
+    }
+}
+
+
+/// Downloads the latest release of the tz database into the given
+/// directory, creating it if necessary, checks it as the options say to,
+/// and unpacks it there. Returns the paths to the source files.
+///
+/// Returns an error if the options don’t ask for any checks, the directory
+/// can’t be created, any of the programs can’t be run or fail, or a check
+/// fails. An archive that fails a check gets deleted.
+pub fn fetch<F: Filesystem>(fs: &F, dir: &Path, options: &FetchOptions) -> Result<Vec<String>, CliError> {
+    if options.sha512.is_none() && !options.verify_signature {
+        return Err(CliError::Unverified);
+    }
+
+    try!(create_dirs(fs, dir));
+
+    let archive = dir.join("tzdata-latest.tar.gz");
+    try!(download(TZDATA_URL, &archive));
+
+    if let Err(e) = verify(fs, &archive, options) {
+        let _ = fs.remove_file(&archive);
+        return Err(e);
+    }
+
+    try!(run_command("tar", Command::new("tar").arg("-xzf").arg(&archive).arg("-C").arg(dir)));
+
+    Ok(source_files(fs, dir))
+}
+
+/// Checks the archive against the checksum and signature, as the options
+/// say to.
+fn verify<F: Filesystem>(fs: &F, archive: &Path, options: &FetchOptions) -> Result<(), CliError> {
+    if let Some(ref expected) = options.sha512 {
+        let contents = try!(fs.read(archive));
+        let actual = sha512((*contents).as_ref());
+
+        if actual != expected.trim().to_lowercase() {
+            return Err(CliError::ChecksumMismatch(expected.clone(), actual));
+        }
+    }
+
+    if options.verify_signature {
+        let mut signature = archive.as_os_str().to_owned();
+        signature.push(".asc");
+
+        try!(download(&format!("{}.asc", TZDATA_URL), Path::new(&signature)));
+
+        // gpg succeeds for a good signature from any key in the keyring, so
+        // its status output has to be checked for which key that was.
+        let output = try!(Command::new("gpg").arg("--status-fd").arg("1").arg("--verify").arg(&signature).arg(archive)
+                                             .stderr(Stdio::inherit()).output());
+        if !output.status.success() {
+            return Err(CliError::CommandFailed("gpg", output.status));
+        }
+
+        if !signed_by(&String::from_utf8_lossy(&output.stdout), TZDATA_SIGNING_KEY) {
+            return Err(CliError::SignatureMismatch(TZDATA_SIGNING_KEY));
+        }
+    }
+
+    Ok(())
+}
+
+/// Whether `gpg --status-fd` output says there was a valid signature made
+/// with the key with the given fingerprint, or one of its subkeys.
+fn signed_by(status: &str, fingerprint: &str) -> bool {
+    status.lines().any(|line| {
+        let fields: Vec<&str> = line.split_whitespace().collect();
+
+        // The line has the fingerprint of the key that made the signature
+        // third, and that of its primary key twelfth.
+        fields.len() >= 3 && fields[0] == "[GNUPG:]" && fields[1] == "VALIDSIG"
+            && (fields[2].eq_ignore_ascii_case(fingerprint) || fields.get(11).map_or(false, |f| f.eq_ignore_ascii_case(fingerprint)))
+    })
+}
+
+/// Downloads the file at the given URL to the given path.
+fn download(url: &str, path: &Path) -> Result<(), CliError> {
+    run_command("curl", Command::new("curl").arg("--fail").arg("--silent").arg("--show-error")
+                                            .arg("--location").arg("--output").arg(path).arg(url))
+}
+
+/// Computes the SHA-512 checksum of some bytes, in lowercase hexadecimal.
+pub fn sha512(bytes: &[u8]) -> String {
+    Sha512::digest(bytes).iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+/// Runs a command to completion, returning an error if it couldn’t be
+/// started or didn’t succeed.
+fn run_command(program: &'static str, command: &mut Command) -> Result<(), CliError> {
+    let status = try!(command.status());
+
+    if status.success() {
+        Ok(())
+    }
+    else {
+        Err(CliError::CommandFailed(program, status))
+    }
+}
+
+
+/// The options the `fetch` mode takes.
+pub fn options() -> getopts::Options {
+    let mut opts = getopts::Options::new();
+    opts.reqopt("o", "output", "directory to download and unpack the data into", "DIR");
+    opts.optopt("", "sha512", "SHA-512 checksum the archive must have", "HEX");
+    opts.optflag("", "no-verify-signature", "don’t check the archive’s GPG signature (needs --sha512)");
+    opts
+}
+
+/// Runs the `fetch` mode of the builder with the given arguments, which come
+/// after the word `fetch`. Once the files have been downloaded, they get
+/// parsed to check they’re all there, and the report says how many zones
+/// they contain.
+pub fn run<F: Filesystem>(args: &[OsString], fs: &F) -> Result<Report, CliError> {
+    let command = commands::find("fetch").unwrap();
+    let matches = try!(command.parse(args));
+    let options = FetchOptions {
+        sha512:           matches.opt_str("sha512"),
+        verify_signature: !matches.opt_present("no-verify-signature"),
+    };
+
+    let paths = try!(fetch(fs, Path::new(&matches.opt_str("output").unwrap()), &options));
+    let (table, warnings) = try!(read_table(fs, &paths));
+
+    Ok(Report {
+        zone_count: table.zonesets.len() + table.links.len(),
+        link_count: table.links.len(),
+        warnings:   warnings,
+        output:     String::new(),
+    })
+}
+
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use system::MemoryFilesystem;
+
+    #[test]
+    fn checksum() {
+        let fs = MemoryFilesystem::new();
+        fs.add_file("tz/tzdata-latest.tar.gz", "abc");
+        let path = Path::new("tz/tzdata-latest.tar.gz");
+
+        let expected = "ddaf35a193617abacc417349ae20413112e6fa4e89a97ea20a9eeee64b55d39a\
+                        2192992a274fc1a836ba3c23a3feebbd454d4423643ce80e2a9ac94fa54ca49f";
+
+        let options = FetchOptions { sha512: Some(expected.to_uppercase()), verify_signature: false };
+        assert!(verify(&fs, path, &options).is_ok());
+
+        let options = FetchOptions { sha512: Some("00".repeat(64)), verify_signature: false };
+        match verify(&fs, path, &options) {
+            Err(CliError::ChecksumMismatch(_, actual)) => assert_eq!(actual, expected),
+            otherwise => panic!("Expected a mismatch, got {:?}", otherwise),
+        }
+    }
+
+    #[test]
+    fn unchecked() {
+        let options = FetchOptions { sha512: None, verify_signature: false };
+        let fs = MemoryFilesystem::new();
+        match fetch(&fs, Path::new("tz"), &options) {
+            Err(CliError::Unverified) => {},
+            otherwise => panic!("Expected an error, got {:?}", otherwise),
+        }
+        assert!(fs.paths().is_empty());
+
+        assert!(FetchOptions::default().verify_signature);
+    }
+
+    #[test]
+    fn signatures() {
+        let status = "[GNUPG:] NEWSIG eggert@cs.ucla.edu\n\
+                      [GNUPG:] GOODSIG ED97E90E62AA7E34 Paul Eggert <eggert@cs.ucla.edu>\n\
+                      [GNUPG:] VALIDSIG 7E3792A9D8ACF7D633BC1588ED97E90E62AA7E34 2024-02-01 1706812345 0 4 0 1 10 00 \
+                      7E3792A9D8ACF7D633BC1588ED97E90E62AA7E34\n";
+        assert!(signed_by(status, TZDATA_SIGNING_KEY));
+
+        // A subkey’s signature counts, as the primary key comes last.
+        let status = "[GNUPG:] VALIDSIG 0123456789ABCDEF0123456789ABCDEF01234567 2024-02-01 1706812345 0 4 0 1 10 00 \
+                      7e3792a9d8acf7d633bc1588ed97e90e62aa7e34\n";
+        assert!(signed_by(status, TZDATA_SIGNING_KEY));
+
+        // A good signature from anyone else doesn’t.
+        let status = "[GNUPG:] GOODSIG 0123456789ABCDEF Someone Else <someone@example.com>\n\
+                      [GNUPG:] VALIDSIG 0123456789ABCDEF0123456789ABCDEF01234567 2024-02-01 1706812345 0 4 0 1 10 00 \
+                      0123456789ABCDEF0123456789ABCDEF01234567\n";
+        assert!(!signed_by(status, TZDATA_SIGNING_KEY));
+        assert!(!signed_by("", TZDATA_SIGNING_KEY));
+    }
+}
@@ -0,0 +1,268 @@
+//! Generating an iCalendar feed of upcoming clock changes.
+//!
+//! Rather than a data crate, this produces a single `.ics` file with one
+//! event for each time the clocks change in a chosen set of zones over the
+//! next few years, which can be subscribed to from a calendar program. It’s
+//! useful for anyone who needs to plan around the changes, such as people
+//! scheduling maintenance windows.
+//!
+//! Only changes to the total UTC offset count as clock changes: transitions
+//! where just the abbreviation changes get left out.
+
+use std::io::{Write, BufWriter};
+use std::io::Result as IOResult;
+use std::path::Path;
+
+use datetime::{LocalDateTime, DatePiece, TimePiece};
+use getopts;
+
+use zoneinfo_parse::table::Table;
+use zoneinfo_parse::transitions::{TableTransitions, TransitionOptions, FixedTimespan};
+
+use commands;
+use data_crate::read_table;
+use errors::CliError;
+use system::{Clock, Filesystem};
+use {Report, number_option};
+
+
+/// The average length of a year in the Gregorian calendar, in seconds, used
+/// to work out how far ahead “the next N years” goes.
+const AVERAGE_YEAR: i64 = 31_556_952;
+
+
+/// One occasion on which a zone’s clocks change.
+#[derive(PartialEq, Debug, Clone)]
+pub struct ClockChange {
+
+    /// The name of the zone whose clocks change.
+    pub zone: String,
+
+    /// The instant the change happens, as a Unix timestamp.
+    pub at: i64,
+
+    /// The timespan in effect before the change.
+    pub before: FixedTimespan,
+
+    /// The timespan in effect after the change.
+    pub after: FixedTimespan,
+}
+
+impl ClockChange {
+
+    /// The number of seconds the clocks move by: positive if they go
+    /// forward, and negative if they go back.
+    pub fn amount(&self) -> i64 {
+        self.after.total_offset() - self.before.total_offset()
+    }
+}
+
+
+/// Returns every clock change in the zone with the given name, from the
+/// `from` timestamp up to (but not including) the `until` timestamp. Returns
+/// `None` if the table doesn’t contain a zone with that name.
+pub fn clock_changes(table: &Table, zone_name: &str, from: i64, until: i64) -> Option<Vec<ClockChange>> {
+    let options = TransitionOptions {
+        max_year: LocalDateTime::at(until).year() + 1,
+        .. TransitionOptions::default()
+    };
+
+    let set = match table.timespans(zone_name, &options) {
+        Some(s) => s,
+        None    => return None,
+    };
+
+    let mut changes = Vec::new();
+    let mut before = &set.first;
+
+    for &(at, ref after) in &set.rest {
+        if at >= from && at < until && after.total_offset() != before.total_offset() {
+            changes.push(ClockChange {
+                zone:    zone_name.to_owned(),
+                at:      at,
+                before:  before.clone(),
+                after:   after.clone(),
+            });
+        }
+
+        before = after;
+    }
+
+    Some(changes)
+}
+
+
+/// Writes the given clock changes as an iCalendar file, using `now` as the
+/// time the events were created.
+pub fn write_ics<W: Write>(w: &mut W, changes: &[ClockChange], now: i64) -> IOResult<()> {
+    try!(write_line(w, "BEGIN:VCALENDAR"));
+    try!(write_line(w, "VERSION:2.0"));
+    try!(write_line(w, "PRODID:-//zoneinfo-parse//Clock changes//EN"));
+    try!(write_line(w, "CALSCALE:GREGORIAN"));
+
+    for change in changes {
+        let direction = if change.amount() > 0 { "forward" } else { "back" };
+
+        try!(write_line(w, "BEGIN:VEVENT"));
+        try!(write_line(w, &format!("UID:{}-{}@zoneinfo-parse", change.zone, change.at)));
+        try!(write_line(w, &format!("DTSTAMP:{}", ics_timestamp(now))));
+        try!(write_line(w, &format!("DTSTART:{}", ics_timestamp(change.at))));
+        try!(write_line(w, &format!("SUMMARY:{}", escape(&format!(
+            "{}: clocks go {} {} ({} to {})", change.zone, direction,
+            describe_duration(change.amount().abs()), change.before.name, change.after.name)))));
+        try!(write_line(w, &format!("DESCRIPTION:{}", escape(&format!(
+            "The UTC offset in {} changes from {} to {}.", change.zone,
+            describe_offset(change.before.total_offset()), describe_offset(change.after.total_offset()))))));
+        try!(write_line(w, "TRANSP:TRANSPARENT"));
+        try!(write_line(w, "END:VEVENT"));
+    }
+
+    try!(write_line(w, "END:VCALENDAR"));
+    Ok(())
+}
+
+/// Writes one line of an iCalendar file. Lines end with CRLF, and lines
+/// longer than 75 bytes get folded onto the next line, which begins with a
+/// space, without splitting any characters in two.
+fn write_line<W: Write>(w: &mut W, line: &str) -> IOResult<()> {
+    let mut rest = line;
+    let mut limit = 75;
+
+    while rest.len() > limit {
+        let mut split = limit;
+        while !rest.is_char_boundary(split) {
+            split -= 1;
+        }
+
+        try!(write!(w, "{}\r\n ", &rest[..split]));
+        rest = &rest[split..];
+
+        // The space at the start of a continuation line counts.
+        limit = 74;
+    }
+
+    write!(w, "{}\r\n", rest)
+}
+
+/// Escapes the characters that have special meanings in iCalendar text.
+fn escape(text: &str) -> String {
+    text.replace('\\', "\\\\").replace(';', "\\;").replace(',', "\\,").replace('\n', "\\n")
+}
+
+/// Formats a Unix timestamp as an iCalendar UTC date-time.
+fn ics_timestamp(timestamp: i64) -> String {
+    let t = LocalDateTime::at(timestamp);
+    format!("{:04}{:02}{:02}T{:02}{:02}{:02}Z", t.year(), t.month().months_from_january() + 1, t.day(),
+                                                t.hour(), t.minute(), t.second())
+}
+
+/// Describes a length of time in hours and minutes, such as “1 hour” or
+/// “30 minutes”.
+fn describe_duration(seconds: i64) -> String {
+    let (hours, minutes) = (seconds / 3600, seconds % 3600 / 60);
+    let plural = |n| if n == 1 { "" } else { "s" };
+
+    match (hours, minutes) {
+        (h, 0) => format!("{} hour{}", h, plural(h)),
+        (0, m) => format!("{} minute{}", m, plural(m)),
+        (h, m) => format!("{} hour{} {} minute{}", h, plural(h), m, plural(m)),
+    }
+}
+
+/// Formats a UTC offset as a sign, hours, and minutes, such as “+05:30”.
+fn describe_offset(seconds: i64) -> String {
+    let sign = if seconds < 0 { '-' } else { '+' };
+    let seconds = seconds.abs();
+    format!("{}{:02}:{:02}", sign, seconds / 3600, seconds % 3600 / 60)
+}
+
+
+/// The options the `ics` mode takes.
+pub fn options() -> getopts::Options {
+    let mut opts = getopts::Options::new();
+    opts.reqopt("o", "output", "file to write the calendar to", "FILE");
+    opts.optmulti("z", "zone", "zone to include clock changes for (can be repeated)", "ZONE");
+    opts.optopt("", "years", "how many years ahead to include (default: 1)", "N");
+    opts
+}
+
+/// Runs the `ics` mode of the builder with the given arguments, which come
+/// after the word `ics`.
+pub fn run<C: Clock, F: Filesystem>(args: &[::std::ffi::OsString], clock: &C, fs: &F) -> Result<Report, CliError> {
+    let command = commands::find("ics").unwrap();
+    let matches = try!(command.parse(args));
+    let zones = matches.opt_strs("zone");
+    if zones.is_empty() {
+        return Err(CliError::Usage(command.name, getopts::Fail::OptionMissing("zone".to_owned())));
+    }
+
+    let years = try!(number_option(&matches, "years", 1));
+    let (table, warnings) = try!(read_table(fs, &matches.free));
+    let now = clock.now();
+
+    let mut changes = Vec::new();
+    for zone in &zones {
+        match clock_changes(&table, zone, now, now + years * AVERAGE_YEAR) {
+            Some(c) => changes.extend(c),
+            None    => return Err(CliError::UnknownZone(zone.clone())),
+        }
+    }
+
+    changes.sort_by(|a, b| a.at.cmp(&b.at).then_with(|| a.zone.cmp(&b.zone)));
+
+    let mut w = BufWriter::new(try!(fs.create(Path::new(&matches.opt_str("output").unwrap()))));
+    try!(write_ics(&mut w, &changes, now));
+
+    Ok(Report {
+        zone_count: zones.len(),
+        link_count: zones.iter().filter(|z| table.links.contains_key(&**z)).count(),
+        warnings:   warnings,
+        output:     String::new(),
+    })
+}
+
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use zoneinfo_parse::transitions::FixedTimespan;
+
+    #[test]
+    fn event() {
+        let changes = vec![ ClockChange {
+            zone:    "Europe/London".to_owned(),
+            at:      1_711_846_800,
+            before:  FixedTimespan::new(0, 0, "GMT"),
+            after:   FixedTimespan::new(0, 3600, "BST"),
+        } ];
+
+        let mut buf = Vec::new();
+        write_ics(&mut buf, &changes, 1_700_000_000).unwrap();
+        let ics = String::from_utf8(buf).unwrap();
+
+        assert!(ics.starts_with("BEGIN:VCALENDAR\r\n"));
+        assert!(ics.contains("\r\nDTSTART:20240331T010000Z\r\n"));
+        assert!(ics.contains("\r\nSUMMARY:Europe/London: clocks go forward 1 hour (GMT to BST)\r\n"));
+        assert!(ics.ends_with("END:VCALENDAR\r\n"));
+    }
+
+    #[test]
+    fn folding() {
+        let mut buf = Vec::new();
+        write_line(&mut buf, &"é".repeat(50)).unwrap();
+        let text = String::from_utf8(buf).unwrap();
+
+        let lines = text.split("\r\n").collect::<Vec<_>>();
+        assert_eq!(lines.len(), 3);
+        assert!(lines[0].len() <= 75 && lines[1].len() <= 75);
+        assert_eq!(lines[1].chars().next(), Some(' '));
+    }
+
+    #[test]
+    fn durations() {
+        assert_eq!(describe_duration(1800), "30 minutes");
+        assert_eq!(describe_duration(3600), "1 hour");
+        assert_eq!(describe_duration(5400), "1 hour 30 minutes");
+        assert_eq!(describe_offset(-12600), "-03:30");
+    }
+}
@@ -0,0 +1,140 @@
+//! Writing a dataset as JSON, for programs in other languages.
+//!
+//! The whole dataset goes in one object, with a list of zones and an object
+//! mapping link names to their targets. Each zone has its first timespan,
+//! and then each transition with the timespan it starts:
+//!
+//! ```json
+//! {
+//!   "zones": [
+//!     {
+//!       "name": "Europe/London",
+//!       "first": {"total_offset": -75, "utc_offset": -75, "dst_offset": 0, "abbreviation": "LMT", "is_dst": false},
+//!       "transitions": [
+//!         {"at": -3852662325, "total_offset": 0, "utc_offset": 0, "dst_offset": 0, "abbreviation": "GMT", "is_dst": false}
+//!       ]
+//!     }
+//!   ],
+//!   "links": {
+//!     "GB": "Europe/London"
+//!   }
+//! }
+//! ```
+//!
+//! The `at` times are Unix timestamps, in seconds, and the offsets are in
+//! seconds too. Every timestamp fits within the range of integers that
+//! JavaScript numbers can represent exactly.
+
+use std::io::Write;
+use std::io::Result as IOResult;
+
+use zoneinfo_parse::transitions::FixedTimespan;
+
+use data_crate::Dataset;
+
+
+/// Writes every zone and link in the dataset as a JSON object.
+pub fn write_json<W: Write>(w: &mut W, dataset: &Dataset) -> IOResult<()> {
+    try!(writeln!(w, "{{"));
+    try!(writeln!(w, "  \"zones\": ["));
+
+    for (index, (name, set)) in dataset.zonesets.iter().enumerate() {
+        try!(writeln!(w, "    {{"));
+        try!(writeln!(w, "      \"name\": {},", escape(name)));
+        try!(writeln!(w, "      \"first\": {{{}}},", timespan_fields(&set.first)));
+        try!(writeln!(w, "      \"transitions\": ["));
+
+        for (t_index, &(at, ref timespan)) in set.rest.iter().enumerate() {
+            try!(writeln!(w, "        {{\"at\": {}, {}}}{}", at, timespan_fields(timespan), comma(t_index, set.rest.len())));
+        }
+
+        try!(writeln!(w, "      ]"));
+        try!(writeln!(w, "    }}{}", comma(index, dataset.zonesets.len())));
+    }
+
+    try!(writeln!(w, "  ],"));
+    try!(writeln!(w, "  \"links\": {{"));
+
+    for (index, (name, target)) in dataset.links.iter().enumerate() {
+        try!(writeln!(w, "    {}: {}{}", escape(name), escape(target), comma(index, dataset.links.len())));
+    }
+
+    try!(writeln!(w, "  }}"));
+    writeln!(w, "}}")
+}
+
+/// Returns the fields describing a timespan, without the surrounding
+/// braces, so the transition objects can put their time first.
+fn timespan_fields(timespan: &FixedTimespan) -> String {
+    format!("\"total_offset\": {}, \"utc_offset\": {}, \"dst_offset\": {}, \"abbreviation\": {}, \"is_dst\": {}",
+            timespan.total_offset(), timespan.utc_offset, timespan.dst_offset, escape(&timespan.name), timespan.is_dst)
+}
+
+/// Returns the separator to put after the element at the given index of a
+/// list with the given length: a comma for all but the last one.
+fn comma(index: usize, length: usize) -> &'static str {
+    if index + 1 < length { "," } else { "" }
+}
+
+/// Returns a string as a quoted JSON string, escaping quotes, backslashes,
+/// and control characters.
+pub fn escape(text: &str) -> String {
+    let mut escaped = String::from("\"");
+
+    for c in text.chars() {
+        match c {
+            '"'                    => escaped.push_str("\\\""),
+            '\\'                   => escaped.push_str("\\\\"),
+            c if (c as u32) < 0x20 => escaped.push_str(&format!("\\u{:04x}", c as u32)),
+            c                      => escaped.push(c),
+        }
+    }
+
+    escaped.push('"');
+    escaped
+}
+
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use data_crate::Dataset;
+    use zoneinfo_parse::transitions::{FixedTimespan, FixedTimespanSet};
+
+    #[test]
+    fn zones_and_links() {
+        let mut dataset = Dataset::default();
+        let _ = dataset.zonesets.insert("Europe/London".to_owned(), FixedTimespanSet {
+            first: FixedTimespan::new(-75, 0, "LMT"),
+            rest:  vec![
+                (-3_852_662_325, FixedTimespan::new(0, 0,    "GMT")),
+                (-1_691_964_000, FixedTimespan::new(0, 3600, "BST")),
+            ],
+        });
+        let _ = dataset.links.insert("GB".to_owned(), "Europe/London".to_owned());
+
+        let mut buf = Vec::new();
+        write_json(&mut buf, &dataset).unwrap();
+        assert_eq!(String::from_utf8(buf).unwrap(), r#"{
+  "zones": [
+    {
+      "name": "Europe/London",
+      "first": {"total_offset": -75, "utc_offset": -75, "dst_offset": 0, "abbreviation": "LMT", "is_dst": false},
+      "transitions": [
+        {"at": -3852662325, "total_offset": 0, "utc_offset": 0, "dst_offset": 0, "abbreviation": "GMT", "is_dst": false},
+        {"at": -1691964000, "total_offset": 3600, "utc_offset": 0, "dst_offset": 3600, "abbreviation": "BST", "is_dst": true}
+      ]
+    }
+  ],
+  "links": {
+    "GB": "Europe/London"
+  }
+}
+"#);
+    }
+
+    #[test]
+    fn escaping() {
+        assert_eq!(escape("a\"b\\c\n"), r#""a\"b\\c\u000a""#);
+    }
+}
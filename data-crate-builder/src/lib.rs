@@ -0,0 +1,285 @@
+//! The workflow behind the `build-data-crate` binary, exposed as a library
+//! so it can be embedded in other programs and driven from tests without
+//! spawning a process.
+//!
+//! The entry point is `run`, which takes the same arguments as the binary
+//! (minus the program name) and returns either a `Report` of what was
+//! written and any warnings about the input, or a `CliError` that knows
+//! which exit code it should produce.
+//!
+//! The first argument names the command to run, each of which parses the
+//! rest with its own options (see the `commands` module). Without one, the
+//! builder runs `build`, which writes a data crate (see the `build` module).
+//! If the first argument is `ics`, then instead of a data crate, the builder
+//! writes a calendar of upcoming clock changes (see the `ics` module). If it’s
+//! `country-offsets`, it writes a CSV file of the history of each country’s
+//! UTC offsets (see the `country_offsets` module), and if it’s `dump`, it
+//! writes out one zone’s transitions like `zdump -v` (see the `dump` module).
+//! The `fetch` mode downloads the latest tz database to build from (see the
+//! `fetch` module), and the `timeline` mode draws a zone’s offsets over the
+//! years as an SVG image (see the `timeline` module). The `compile` mode
+//! writes a directory of TZif files like `zic` does (see the `compile`
+//! module). The `fmt` mode rewrites zoneinfo source files in a consistent
+//! layout (see the `reformat` module), and the `lint` mode looks for
+//! things in them that are probably mistakes (see the `lint` module). The
+//! `query` mode says what a local time means in a zone (see the `query`
+//! module), and the `convert` mode converts zoneinfo source between the fat
+//! form and the slim form of `tzdata.zi` (see the `convert` module). The
+//! `diff` mode lists the upcoming transitions that differ between two
+//! releases of the tz database (see the `diff` module).
+//!
+//! The data can be written as Rust code (as a tree of files or just one),
+//! TZif files, zoneinfo source, JSON,
+//! CSV, a binary blob with a Rust loader, the `timezones.rs` file that
+//! `chrono-tz` builds, or zones for the `time` crate, depending on the
+//! `--format` option (see `data_crate::Format`).
+//! Each format is written by a `CodegenBackend`, and other crates can add
+//! their own formats by implementing one (see the `backend` module).
+//! Given CLDR’s `windowsZones.xml` with `--windows-zones`, the Rust code
+//! can also look zones up by their Windows names, and given
+//! `zone1970.tab` with `--zone-tab`, it can say where each zone is used
+//! and find the zone nearest to a point.
+//!
+//! Tools that drive the builder can call `capabilities` (or run the binary
+//! with `--capabilities`) to check what it supports before running it.
+//!
+//! Other crates can generate a data crate from their build scripts by
+//! calling `generate` (see the `build_script` module).
+//!
+//! Tests can call `run_with` instead of `run` to give the builder a
+//! different clock and filesystem, such as an in-memory one, so nothing
+//! touches the disk (see the `system` module). With the `mmap` feature
+//! enabled, the real filesystem maps input files into memory rather than
+//! reading them.
+//!
+//! A data crate writes its files through an `OutputSink`, which usually puts
+//! them in the output directory, but can keep them in memory or send them
+//! somewhere else instead (see the `output` module).
+
+extern crate datetime;
+extern crate getopts;
+extern crate miniz_oxide;
+extern crate phf_codegen;
+extern crate sha2;
+extern crate zoneinfo_parse;
+
+#[macro_use]
+extern crate log;
+
+#[macro_use]
+extern crate quick_error;
+
+#[cfg(feature = "mmap")] extern crate memmap2;
+
+use std::ffi::{OsStr, OsString};
+use std::fmt;
+use std::path::Path;
+
+pub mod backend;
+
+pub mod blob;
+
+pub mod blob_loader;
+
+pub mod build;
+
+pub mod build_script;
+pub use build_script::{generate, GenerateOptions};
+
+pub mod chrono_tz;
+
+pub mod compile;
+
+pub mod cache;
+
+pub mod commands;
+use commands::COMMANDS;
+
+pub mod convert;
+
+pub mod country_offsets;
+
+pub mod csv;
+
+pub mod data_crate;
+use data_crate::FORMATS;
+
+pub mod diagnostics;
+
+pub mod diff;
+
+pub mod dump;
+
+pub mod errors;
+pub use errors::CliError;
+
+pub mod fetch;
+
+pub mod ics;
+
+pub mod json;
+
+pub mod lint;
+
+pub mod output;
+
+pub mod query;
+
+pub mod reformat;
+
+pub mod system;
+use system::{Clock, Filesystem, SystemClock, RealFilesystem};
+
+pub mod time_crate;
+
+pub mod timeline;
+
+pub mod tzif;
+
+pub mod warnings;
+use warnings::Warning;
+
+pub mod zi;
+
+
+/// A summary of what a successful run produced.
+#[derive(PartialEq, Debug, Clone)]
+pub struct Report {
+
+    /// The number of time zones written, including links.
+    pub zone_count: usize,
+
+    /// How many of those time zones were links to other zones.
+    pub link_count: usize,
+
+    /// Anything odd about the input that didn’t stop it from being used.
+    pub warnings: Vec<Warning>,
+
+    /// Text for the caller to print, such as the list of changes a dry run
+    /// would make, or the answer to a query without `--output`. Nothing in
+    /// this crate prints it itself.
+    pub output: String,
+}
+
+
+/// A description of what this build of the data crate builder supports,
+/// including the capabilities of the parser it was built with.
+#[derive(PartialEq, Debug, Clone)]
+pub struct Capabilities {
+
+    /// The version of the data crate builder.
+    pub version: &'static str,
+
+    /// The capabilities of the `zoneinfo_parse` crate doing the parsing.
+    pub parser: zoneinfo_parse::Capabilities,
+
+    /// The kinds of output that can be generated.
+    pub targets: Vec<&'static str>,
+
+    /// The commands that can be run, in alphabetical order.
+    pub commands: Vec<&'static str>,
+
+    /// The long names of the options any of the commands take, in
+    /// alphabetical order.
+    pub options: Vec<String>,
+}
+
+/// Returns the capabilities of this build of the data crate builder.
+pub fn capabilities() -> Capabilities {
+    let mut options: Vec<String> = COMMANDS.iter().flat_map(|c| c.option_names()).collect();
+    options.sort();
+    options.dedup();
+
+    Capabilities {
+        version:  env!("CARGO_PKG_VERSION"),
+        parser:   zoneinfo_parse::capabilities(),
+        targets:  FORMATS.iter().map(|f| f.name()).collect(),
+        commands: COMMANDS.iter().map(|c| c.name).collect(),
+        options:  options,
+    }
+}
+
+/// The capabilities get printed one per line, with the name of each list
+/// followed by its space-separated entries, so they can be read by scripts.
+impl fmt::Display for Capabilities {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        try!(writeln!(f, "version {}", self.version));
+        try!(writeln!(f, "parser-version {}", self.parser.version));
+        try!(write_list(f, "grammar", &self.parser.grammar));
+        try!(write_list(f, "formats", &self.parser.formats));
+        try!(write_list(f, "features", &self.parser.features));
+        try!(write_list(f, "targets", &self.targets));
+        try!(write_list(f, "commands", &self.commands));
+
+        let options: Vec<&str> = self.options.iter().map(|o| &**o).collect();
+        try!(write_list(f, "options", &options));
+        Ok(())
+    }
+}
+
+fn write_list(f: &mut fmt::Formatter, name: &str, entries: &[&str]) -> fmt::Result {
+    try!(write!(f, "{}", name));
+    for entry in entries {
+        try!(write!(f, " {}", entry));
+    }
+    writeln!(f, "")
+}
+
+
+/// Runs the data crate builder with the given command-line arguments, not
+/// including the program name.
+///
+/// Nothing is printed to stdout or stderr: on success, it’s up to the
+/// caller to print the report’s `output`, and on failure, to display the
+/// error and exit with its `exit_code`.
+pub fn run<I>(args: I) -> Result<Report, CliError>
+where I: IntoIterator, I::Item: AsRef<OsStr> {
+    run_with(args, &SystemClock, &RealFilesystem)
+}
+
+/// Runs the data crate builder in the same way as `run`, but using the given
+/// clock and filesystem instead of the real ones.
+pub fn run_with<I, C, F>(args: I, clock: &C, fs: &F) -> Result<Report, CliError>
+where I: IntoIterator, I::Item: AsRef<OsStr>, C: Clock, F: Filesystem {
+    let args: Vec<OsString> = args.into_iter().map(|a| a.as_ref().to_owned()).collect();
+    match args.first().and_then(|a| a.to_str()) {
+        Some("build")                              => build::run(&args[1..], fs),
+        Some("compile")                            => compile::run(&args[1..], fs),
+        Some("convert")                            => convert::run(&args[1..], fs),
+        Some("country-offsets")                    => country_offsets::run(&args[1..], fs),
+        Some("diff")                               => diff::run(&args[1..], clock, fs),
+        Some("dump")                               => dump::run(&args[1..], fs),
+        Some("fetch")                              => fetch::run(&args[1..], fs),
+        Some("fmt")                                => reformat::run(&args[1..], fs),
+        Some("ics")                                => ics::run(&args[1..], clock, fs),
+        Some("lint")                               => lint::run(&args[1..], fs),
+        Some("query")                              => query::run(&args[1..], fs),
+        Some("timeline")                           => timeline::run(&args[1..], fs),
+        Some(name) if looks_like_command(name, fs) => Err(CliError::UnknownCommand(name.to_owned())),
+        _                                          => build::run(&args, fs),
+    }
+}
+
+/// Whether the first argument looks like it was meant to be the name of a
+/// command, rather than an option or an input file for `build`.
+fn looks_like_command<F: Filesystem>(argument: &str, fs: &F) -> bool {
+    let path = Path::new(argument);
+    !argument.starts_with('-') && !argument.contains('/') && !fs.is_file(path) && !fs.is_dir(path)
+}
+
+/// Parses the value of a numeric option that doesn’t have to be given.
+fn optional_number_option(matches: &getopts::Matches, option: &'static str) -> Result<Option<i64>, CliError> {
+    match matches.opt_str(option) {
+        Some(value) => value.parse().map(Some).map_err(|_| CliError::InvalidArgument(option, value)),
+        None        => Ok(None),
+    }
+}
+
+/// Parses the value of a numeric option, using the default if it wasn’t
+/// given.
+fn number_option(matches: &getopts::Matches, option: &'static str, default: i64) -> Result<i64, CliError> {
+    match matches.opt_str(option) {
+        Some(value) => value.parse().map_err(|_| CliError::InvalidArgument(option, value)),
+        None        => Ok(default),
+    }
+}
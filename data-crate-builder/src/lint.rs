@@ -0,0 +1,341 @@
+//! Looking for things in zoneinfo source that parse fine, but probably
+//! aren’t what was meant.
+//!
+//! Validation (with `--validate`) stops the build for mistakes that make
+//! the data unusable. Linting is for things that are more likely to be
+//! mistakes than not, so they can be caught in CI before they get released:
+//!
+//! - rules that never take effect, because none of the zones that use their
+//!   ruleset use it during the years the rule is for;
+//! - zone lines whose FORMAT can produce an abbreviation that `zic` would
+//!   complain about, one that isn’t three to six letters, digits, `+`, or
+//!   `-`, given the letters of the rules it gets used with;
+//! - zone lines whose UNTIL time is no later than the one before them;
+//! - links with the same name as a zone, which get ignored, as the zone is
+//!   found first.
+//!
+//! Run with `lint` as the first argument, the builder prints everything it
+//! finds and exits with a non-zero code if it found anything.
+
+use std::collections::HashSet;
+use std::fmt;
+
+use getopts;
+
+use zoneinfo_parse::line::{ChangeTime, YearSpec};
+use zoneinfo_parse::table::{Table, RuleInfo, Saving};
+use zoneinfo_parse::validation::{TableValidation, Problem};
+
+use commands;
+use data_crate::read_table;
+use errors::CliError;
+use system::Filesystem;
+use Report;
+
+
+/// Something suspicious found in a table.
+#[derive(PartialEq, Eq, PartialOrd, Ord, Debug, Clone)]
+pub enum Finding {
+
+    /// A rule in a ruleset never takes effect in any of the zones that use
+    /// the ruleset.
+    RuleNeverFires {
+
+        /// The name of the ruleset.
+        ruleset: String,
+
+        /// The index of the rule within the ruleset, starting at zero.
+        index: usize,
+    },
+
+    /// A zone line can produce an abbreviation that isn’t valid.
+    InvalidAbbreviation {
+
+        /// The name of the zone.
+        zone: String,
+
+        /// The index of the line within the zoneset, starting at zero.
+        index: usize,
+
+        /// The abbreviation it can produce.
+        abbreviation: String,
+    },
+
+    /// A zone line’s ‘until’ time is no later than the one before it.
+    UntilOutOfOrder {
+
+        /// The name of the zone.
+        zone: String,
+
+        /// The index of the line within the zoneset, starting at zero.
+        index: usize,
+    },
+
+    /// A link has the same name as a zone.
+    LinkShadowsZone(String),
+}
+
+impl fmt::Display for Finding {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Finding::RuleNeverFires { ref ruleset, index }                    => write!(f, "rule {} of ruleset {} never takes effect", index + 1, ruleset),
+            Finding::InvalidAbbreviation { ref zone, index, ref abbreviation } => write!(f, "zone {} line {} can produce the invalid abbreviation {:?}", zone, index + 1, abbreviation),
+            Finding::UntilOutOfOrder { ref zone, index }                      => write!(f, "zone {} line {} has an until time no later than the line before it", zone, index + 1),
+            Finding::LinkShadowsZone(ref name)                                => write!(f, "link {} has the same name as a zone, so it gets ignored", name),
+        }
+    }
+}
+
+
+/// Checks the given table for anything suspicious, returning everything it
+/// finds in a sorted order.
+pub fn lint(table: &Table) -> Vec<Finding> {
+    let mut findings = Vec::new();
+    let mut fired = HashSet::new();
+
+    for (zone_name, zoneset) in &table.zonesets {
+        let mut start = None;
+
+        for (index, zone_info) in zoneset.iter().enumerate() {
+            let end = zone_info.end_time.and_then(until_time);
+
+            let mut abbreviations = Vec::new();
+            match zone_info.saving {
+                Saving::NoSaving       => abbreviations.push(zone_info.format.format(zone_info.offset, false, None)),
+                Saving::OneOff(amount) => abbreviations.push(zone_info.format.format(zone_info.offset + amount, amount != 0, None)),
+                Saving::Multiple(ref ruleset_name) => {
+                    if let Some(rules) = table.rulesets.get(ruleset_name) {
+                        for rule_index in rules_in_effect(rules, start, end) {
+                            let rule = &rules[rule_index];
+                            let _ = fired.insert((&**ruleset_name, rule_index));
+                            abbreviations.push(zone_info.format.format(zone_info.offset + rule.time_to_add, rule.is_daylight_saving(), rule.letters.as_ref().map(|l| &**l)));
+                        }
+                    }
+                },
+            }
+
+            abbreviations.sort();
+            abbreviations.dedup();
+            for abbreviation in abbreviations {
+                if !is_valid_abbreviation(&abbreviation) {
+                    findings.push(Finding::InvalidAbbreviation {
+                        zone:          zone_name.clone(),
+                        index:         index,
+                        abbreviation:  abbreviation,
+                    });
+                }
+            }
+
+            start = end;
+        }
+    }
+
+    for (ruleset_name, rules) in &table.rulesets {
+        for index in 0 .. rules.len() {
+            if !fired.contains(&(&**ruleset_name, index)) {
+                findings.push(Finding::RuleNeverFires { ruleset: ruleset_name.clone(), index: index });
+            }
+        }
+    }
+
+    for problem in table.validate() {
+        if let Problem::UntilOutOfOrder { zone, index } = problem {
+            findings.push(Finding::UntilOutOfOrder { zone: zone, index: index });
+        }
+    }
+
+    for link_name in table.links.keys() {
+        if table.zonesets.contains_key(link_name) {
+            findings.push(Finding::LinkShadowsZone(link_name.clone()));
+        }
+    }
+
+    findings.sort();
+    findings
+}
+
+/// Returns the indices of the rules that can be in effect in a zone that
+/// uses them between the given times, which are the ‘until’ times of the
+/// zone line before and the zone line itself, as years and timestamps, or
+/// `None` for the beginning and the end of time.
+///
+/// As well as the rules that happen in that time, that includes the last
+/// rule to happen before it, as that sets the saving and letters in use
+/// when the zone starts using the ruleset.
+fn rules_in_effect(rules: &[RuleInfo], start: Option<(i64, i64)>, end: Option<(i64, i64)>) -> Vec<usize> {
+    let mut in_effect: Vec<usize> = (0 .. rules.len()).filter(|&i| happens_between(&rules[i], start, end)).collect();
+
+    if let Some((start_year, start_time)) = start {
+        let previous: Vec<(usize, i64)> = rules.iter().enumerate()
+                                               .filter_map(|(i, r)| last_time_before(r, start_year, start_time).map(|t| (i, t)))
+                                               .collect();
+
+        if let Some(latest) = previous.iter().map(|&(_, t)| t).max() {
+            in_effect.extend(previous.iter().filter(|&&(_, t)| t == latest).map(|&(i, _)| i));
+        }
+    }
+
+    in_effect
+}
+
+/// Returns whether the given rule happens at least once between the given
+/// times. Years strictly between the two definitely count, so only the
+/// years at either end need the times checking.
+fn happens_between(rule: &RuleInfo, start: Option<(i64, i64)>, end: Option<(i64, i64)>) -> bool {
+    let (from, to) = rule_years(rule);
+    let first = start.map_or(from, |(year, _)| from.max(year));
+    let last = end.map_or(to, |(year, _)| to.min(year));
+    if first > last {
+        return false;
+    }
+
+    let inner_first = start.map_or(first, |(year, _)| first.max(year.saturating_add(1)));
+    let inner_last = end.map_or(last, |(year, _)| last.min(year.saturating_sub(1)));
+    if inner_first <= inner_last {
+        return true;
+    }
+
+    [ first, last ].iter().any(|&year| {
+        let time = rule_time(rule, year);
+        start.map_or(true, |(_, start_time)| time >= start_time) && end.map_or(true, |(_, end_time)| time < end_time)
+    })
+}
+
+/// Returns the time the given rule last happened before the given time, if
+/// it happened at all.
+fn last_time_before(rule: &RuleInfo, year: i64, time: i64) -> Option<i64> {
+    let (from, to) = rule_years(rule);
+    let candidate = to.min(year);
+    if candidate < from {
+        return None;
+    }
+
+    // Only in the year of the time itself can the rule happen after it.
+    let candidate_time = rule_time(rule, candidate);
+    if candidate_time < time {
+        Some(candidate_time)
+    }
+    else if candidate > from {
+        Some(rule_time(rule, candidate - 1))
+    }
+    else {
+        None
+    }
+}
+
+/// Returns the first and last years a rule is for.
+fn rule_years(rule: &RuleInfo) -> (i64, i64) {
+    let from = year_number(rule.from_year);
+    let to = rule.to_year.map(year_number).unwrap_or(from);
+    (from, to)
+}
+
+/// Returns when the given rule happens in the given year, going by the time
+/// written in it, in the same way as `ChangeTime::to_timestamp` does.
+fn rule_time(rule: &RuleInfo, year: i64) -> i64 {
+    rule.absolute_datetime(year, 0, 0).to_instant().seconds()
+}
+
+/// Returns the year and the timestamp of a zone line’s ‘until’ time, or
+/// `None` if it uses a year such as `max` that can’t be turned into one.
+fn until_time(time: ChangeTime) -> Option<(i64, i64)> {
+    let year = match time {
+        ChangeTime::UntilYear(y)          => y,
+        ChangeTime::UntilMonth(y, ..)     => y,
+        ChangeTime::UntilDay(y, ..)       => y,
+        ChangeTime::UntilTime(y, ..)      => y,
+    };
+
+    match year {
+        YearSpec::Number(n)  => Some((n, time.to_timestamp())),
+        _                    => None,
+    }
+}
+
+fn year_number(year: YearSpec) -> i64 {
+    match year {
+        YearSpec::Minimum    => i64::min_value(),
+        YearSpec::Maximum    => i64::max_value(),
+        YearSpec::Number(n)  => n,
+    }
+}
+
+/// Returns whether `zic` would accept the given abbreviation without a
+/// warning: it has to be between three and six characters long, and made
+/// of ASCII letters, digits, `+`, and `-`.
+fn is_valid_abbreviation(abbreviation: &str) -> bool {
+    abbreviation.len() >= 3 && abbreviation.len() <= 6
+        && abbreviation.chars().all(|c| c.is_ascii_alphanumeric() || c == '+' || c == '-')
+}
+
+
+/// The options the `lint` mode takes.
+pub fn options() -> getopts::Options {
+    getopts::Options::new()
+}
+
+/// Runs the `lint` mode of the builder with the given arguments, which come
+/// after the word `lint`. The free arguments are the input files.
+pub fn run<F: Filesystem>(args: &[::std::ffi::OsString], fs: &F) -> Result<Report, CliError> {
+    let command = commands::find("lint").unwrap();
+    let matches = try!(command.parse(args));
+
+    let (table, warnings) = try!(read_table(fs, &matches.free));
+    let findings = lint(&table);
+    if !findings.is_empty() {
+        return Err(CliError::Lint(findings));
+    }
+
+    Ok(Report {
+        zone_count: table.zonesets.len() + table.links.len(),
+        link_count: table.links.len(),
+        warnings:   warnings,
+        output:     String::new(),
+    })
+}
+
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use zoneinfo_parse::parse_table;
+
+    #[test]
+    fn nothing_suspicious() {
+        let table = parse_table("Rule  Test  1990  max  -  Mar  lastSun  1:00u  1:00  S\n\
+                                 Rule  Test  1990  max  -  Oct  lastSun  1:00u  0     -\n\
+                                 Zone  Test/Zone  0:00  -     LMT    1980\n\
+                                 \x20                1:00  Test  TE%sT\n\
+                                 Link  Test/Zone  Test/Link\n").unwrap();
+        assert_eq!(lint(&table), vec![]);
+    }
+
+    #[test]
+    fn rules_before_the_zone_uses_them() {
+        let table = parse_table("Rule  Test  1970  only  -  Mar  1  0:00  1:00  S\n\
+                                 Rule  Test  1975  only  -  Mar  1  0:00  0     -\n\
+                                 Rule  Test  1980  only  -  Mar  1  0:00  1:00  S\n\
+                                 Zone  Test/Zone  0:00  -     LMT    1978\n\
+                                 \x20                1:00  Test  TE%sT\n").unwrap();
+        assert_eq!(lint(&table), vec![
+            Finding::RuleNeverFires { ruleset: "Test".to_owned(), index: 0 },
+        ]);
+    }
+
+    #[test]
+    fn suspicious() {
+        let table = parse_table("Rule  Test  2000  1990  -  Mar  lastSun  1:00u  1:00  Summer\n\
+                                 Rule  Test  1990  max   -  Oct  lastSun  1:00u  0     -\n\
+                                 Zone  Test/Zone  0:00  Test  TE%sT  2000\n\
+                                 \x20                1:00  -     T      1990\n\
+                                 Zone  Test/Link  0:00  -  TST\n\
+                                 Link  Test/Zone  Test/Link\n").unwrap();
+
+        assert_eq!(lint(&table), vec![
+            Finding::RuleNeverFires { ruleset: "Test".to_owned(), index: 0 },
+            Finding::InvalidAbbreviation { zone: "Test/Zone".to_owned(), index: 1, abbreviation: "T".to_owned() },
+            Finding::UntilOutOfOrder { zone: "Test/Zone".to_owned(), index: 1 },
+            Finding::LinkShadowsZone("Test/Link".to_owned()),
+        ]);
+    }
+}
@@ -1,40 +1,72 @@
 use std::env::args_os;
+use std::ffi::OsString;
 use std::io::{Write, stderr};
 use std::process::exit;
 
-extern crate datetime;
-extern crate getopts;
-extern crate phf_codegen;
-extern crate zoneinfo_parse;
+extern crate build_data_crate;
+use build_data_crate::diagnostics::{ErrorFormat, log_level};
 
 #[macro_use]
-extern crate quick_error;
-
-mod data_crate;
-use data_crate::DataCrate;
-
-mod errors;
-use errors::Error;
+extern crate log;
+use log::{Log, Metadata, Record};
 
 #[macro_use]
 mod util;
 
 
-fn main() {
-    if let Err(e) = build_data_crate() {
-        println_stderr!("{}", e);
-        exit(1);
+/// Prints log messages as they are, all of them to stderr, so stdout only
+/// gets the report’s output, and can be piped somewhere else.
+struct Logger;
+
+static LOGGER: Logger = Logger;
+
+impl Log for Logger {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        metadata.level() <= log::max_level()
     }
+
+    fn log(&self, record: &Record) {
+        if !self.enabled(record.metadata()) {
+            return;
+        }
+
+        println_stderr!("{}", record.args());
+    }
+
+    fn flush(&self) {}
 }
 
-fn build_data_crate() -> Result<(), Error> {
-    let mut opts = getopts::Options::new();
-    opts.reqopt("o", "output", "directory to write the crate into", "DIR");
 
-    let matches = try!(opts.parse(args_os().skip(1)));
-    let data_crate = try!(DataCrate::new(matches.opt_str("output").unwrap(), &matches.free));
-    try!(data_crate.run());
+fn main() {
+    let args: Vec<OsString> = args_os().skip(1).collect();
+
+    if args.iter().any(|arg| arg.to_str() == Some("--capabilities")) {
+        print!("{}", build_data_crate::capabilities());
+        return;
+    }
+
+    if let Some(usage) = build_data_crate::commands::help(&args) {
+        print!("{}", usage);
+        return;
+    }
 
-    println!("All done.");
-    Ok(())
+    let error_format = ErrorFormat::from_args(&args);
+    let _ = log::set_logger(&LOGGER);
+    log::set_max_level(log_level(&args));
+
+    match build_data_crate::run(args) {
+        Ok(report) => {
+            print!("{}", report.output);
+
+            for warning in &report.warnings {
+                warn!("{}", error_format.warning(warning));
+            }
+
+            info!("All done.");
+        },
+        Err(e) => {
+            error!("{}", error_format.error(&e));
+            exit(e.exit_code());
+        },
+    }
 }
@@ -0,0 +1,372 @@
+//! Where the generated files end up.
+//!
+//! A data crate doesn’t write its files to the filesystem directly: it opens
+//! each one through an `OutputSink`, by its path relative to the output
+//! directory, writes to it, and then hands it back to the sink to finish it
+//! off. `FilesystemSink` puts the files in a directory through a
+//! `Filesystem`, creating subdirectories as they’re needed, while
+//! `MemorySink` just keeps hold of their contents, so the generated code can
+//! be checked without a filesystem at all. Other sinks could put the files
+//! somewhere else entirely, such as into an archive.
+//!
+//! Writing to a `MemorySink` first also means the files can be compared
+//! against what’s already in the output directory, to list the `Change`s
+//! that writing them would make without making them.
+//!
+//! Some paths come from zone names in the input, which can’t always be
+//! trusted, so both sinks refuse paths that would leave the output
+//! directory, and `zone_file_path` turns a zone name into a path only if it
+//! stays inside.
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt;
+use std::io::{Read, Write};
+use std::io::{Error as IOError, ErrorKind};
+use std::io::Result as IOResult;
+use std::path::{Component, Path, PathBuf};
+
+use system::Filesystem;
+
+
+/// Something that generated files can be written to.
+pub trait OutputSink {
+
+    /// The type that a file gets written through.
+    type Writer: Write;
+
+    /// Opens the file at the given path, relative to the output directory,
+    /// for writing. Any directories it’s in are the sink’s business.
+    fn file(&mut self, path: &Path) -> IOResult<Self::Writer>;
+
+    /// Finishes writing a file opened with `file`, once everything has been
+    /// written to it.
+    fn finish(&mut self, file: Self::Writer) -> IOResult<()>;
+}
+
+
+/// Returns the path of the file for the zone or link with the given name,
+/// relative to the output directory, with a directory for each part of the
+/// name before the last slash.
+///
+/// As with `zic`, names that start with a slash, or that have an empty,
+/// `.`, or `..` component, are an error, as their files would end up
+/// somewhere other than under the output directory.
+pub fn zone_file_path(name: &str) -> IOResult<PathBuf> {
+    let mut path = PathBuf::new();
+    for component in name.split('/') {
+        match component {
+            "" | "." | ".."  => return Err(IOError::new(ErrorKind::InvalidInput, format!("{:?}: zone name can’t be used as a file name", name))),
+            _                => path.push(component),
+        }
+    }
+
+    try!(check_relative(&path));
+    Ok(path)
+}
+
+/// Checks that a path given to a sink stays inside the output directory,
+/// which it only does if every component of it is a plain name.
+fn check_relative(path: &Path) -> IOResult<()> {
+    let plain = path.components().all(|c| match c {
+        Component::Normal(_)  => true,
+        _                     => false,
+    });
+
+    if plain && path.components().next().is_some() {
+        Ok(())
+    }
+    else {
+        Err(IOError::new(ErrorKind::InvalidInput, format!("{}: path is outside the output directory", path.display())))
+    }
+}
+
+
+/// A file that’s being written to a sink, kept in memory until it gets
+/// finished.
+#[derive(PartialEq, Debug, Clone)]
+pub struct OutputFile {
+
+    /// The path of the file, as the sink wants it.
+    pub path: PathBuf,
+
+    /// Everything that’s been written to the file so far.
+    pub contents: Vec<u8>,
+}
+
+impl Write for OutputFile {
+    fn write(&mut self, buf: &[u8]) -> IOResult<usize> {
+        self.contents.write(buf)
+    }
+
+    fn flush(&mut self) -> IOResult<()> {
+        Ok(())
+    }
+}
+
+
+/// A sink that writes files into a directory, which has to exist already.
+///
+/// The subdirectories that files go in get created the first time they’re
+/// needed. Files that already have exactly the contents being written get
+/// left alone, so regenerating a data crate only touches the files that
+/// actually changed, and their modification times stay the same, so Cargo
+/// doesn’t recompile the whole crate every time.
+#[derive(Debug)]
+pub struct FilesystemSink<'fs, F: Filesystem + 'fs> {
+    fs: &'fs F,
+    base_path: PathBuf,
+}
+
+impl<'fs, F: Filesystem + 'fs> FilesystemSink<'fs, F> {
+
+    /// Creates a new sink that writes files into the given directory.
+    pub fn new<P: Into<PathBuf>>(fs: &'fs F, base_path: P) -> FilesystemSink<'fs, F> {
+        FilesystemSink {
+            fs: fs,
+            base_path: base_path.into(),
+        }
+    }
+}
+
+impl<'fs, F: Filesystem + 'fs> OutputSink for FilesystemSink<'fs, F> {
+    type Writer = OutputFile;
+
+    fn file(&mut self, path: &Path) -> IOResult<OutputFile> {
+        try!(check_relative(path));
+
+        let mut dir_path = self.base_path.clone();
+        if let Some(parent) = path.parent() {
+            for component in parent.components() {
+                dir_path.push(component.as_os_str());
+
+                if !self.fs.is_dir(&dir_path) {
+                    info!("Creating directory {:?}", &dir_path);
+                    try!(self.fs.create_dir(&dir_path));
+                }
+            }
+        }
+
+        Ok(OutputFile {
+            path: self.base_path.join(path),
+            contents: Vec::new(),
+        })
+    }
+
+    fn finish(&mut self, file: OutputFile) -> IOResult<()> {
+        if self.fs.is_file(&file.path) {
+            let mut existing = Vec::new();
+            let _ = try!(try!(self.fs.open(&file.path)).read_to_end(&mut existing));
+            if existing == file.contents {
+                info!("Skipping unchanged file {:?}", &file.path);
+                return Ok(());
+            }
+        }
+
+        debug!("Writing file {:?} ({} bytes)", &file.path, file.contents.len());
+        let mut w = try!(self.fs.create(&file.path));
+        w.write_all(&file.contents)
+    }
+}
+
+
+/// A sink that keeps every file in memory, for tests, or for doing
+/// something else with the files afterwards.
+#[derive(PartialEq, Debug, Clone, Default)]
+pub struct MemorySink {
+    files: BTreeMap<PathBuf, Vec<u8>>,
+}
+
+impl MemorySink {
+
+    /// Creates a new sink with no files in it.
+    pub fn new() -> MemorySink {
+        MemorySink::default()
+    }
+
+    /// Returns every file that’s been finished, by path, in order.
+    pub fn files(&self) -> &BTreeMap<PathBuf, Vec<u8>> {
+        &self.files
+    }
+
+    /// Returns the contents of the file at the given path, if there is one
+    /// and it’s valid UTF-8.
+    pub fn contents<P: AsRef<Path>>(&self, path: P) -> Option<&str> {
+        self.files.get(path.as_ref()).and_then(|bytes| ::std::str::from_utf8(bytes).ok())
+    }
+
+    /// Returns the changes that writing these files into the given
+    /// directory with a `FilesystemSink` would make, in order, without
+    /// actually making them. Files that already have the same contents
+    /// would get left alone, so they don’t count as changes.
+    ///
+    /// As with writing the files, it’s an error for the directory not to
+    /// exist.
+    pub fn changes<F: Filesystem>(&self, fs: &F, base_path: &Path) -> IOResult<Vec<Change>> {
+        if !fs.is_dir(base_path) {
+            return Err(IOError::new(ErrorKind::NotFound, format!("{}: no such directory", base_path.display())));
+        }
+
+        let mut changes = Vec::new();
+        let mut new_dirs = BTreeSet::new();
+
+        for (path, contents) in &self.files {
+            let mut dir_path = base_path.to_path_buf();
+            if let Some(parent) = path.parent() {
+                for component in parent.components() {
+                    dir_path.push(component.as_os_str());
+
+                    if !fs.is_dir(&dir_path) && new_dirs.insert(dir_path.clone()) {
+                        changes.push(Change::CreateDirectory(dir_path.clone()));
+                    }
+                }
+            }
+
+            let file_path = base_path.join(path);
+            if fs.is_file(&file_path) {
+                let mut existing = Vec::new();
+                let _ = try!(try!(fs.open(&file_path)).read_to_end(&mut existing));
+                if existing != *contents {
+                    changes.push(Change::ChangeFile(file_path, contents.len()));
+                }
+            }
+            else {
+                changes.push(Change::CreateFile(file_path, contents.len()));
+            }
+        }
+
+        Ok(changes)
+    }
+}
+
+impl OutputSink for MemorySink {
+    type Writer = OutputFile;
+
+    fn file(&mut self, path: &Path) -> IOResult<OutputFile> {
+        try!(check_relative(path));
+
+        Ok(OutputFile {
+            path: path.to_path_buf(),
+            contents: Vec::new(),
+        })
+    }
+
+    /// Finishing a file that’s already there replaces its contents.
+    fn finish(&mut self, file: OutputFile) -> IOResult<()> {
+        let _ = self.files.insert(file.path, file.contents);
+        Ok(())
+    }
+}
+
+
+/// Something that writing files into a directory would do.
+#[derive(PartialEq, Debug, Clone)]
+pub enum Change {
+
+    /// A directory would get created at this path.
+    CreateDirectory(PathBuf),
+
+    /// A new file would get created at this path, with this many bytes.
+    CreateFile(PathBuf, usize),
+
+    /// The existing file at this path would get its contents replaced with
+    /// this many different bytes.
+    ChangeFile(PathBuf, usize),
+}
+
+impl fmt::Display for Change {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Change::CreateDirectory(ref path)     => write!(f, "Would create directory {:?}", path),
+            Change::CreateFile(ref path, bytes)   => write!(f, "Would create file {:?} ({} bytes)", path, bytes),
+            Change::ChangeFile(ref path, bytes)   => write!(f, "Would change file {:?} ({} bytes)", path, bytes),
+        }
+    }
+}
+
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use system::MemoryFilesystem;
+    use std::io::Write;
+    use std::path::{Path, PathBuf};
+
+    #[test]
+    fn memory() {
+        let mut sink = MemorySink::new();
+        let mut file = sink.file(Path::new("Europe/London.rs")).unwrap();
+        file.write_all(b"hello").unwrap();
+        assert_eq!(sink.contents("Europe/London.rs"), None);
+
+        sink.finish(file).unwrap();
+        assert_eq!(sink.contents("Europe/London.rs"), Some("hello"));
+    }
+
+    #[test]
+    fn subdirectories() {
+        let fs = MemoryFilesystem::new();
+        fs.add_dir("out");
+
+        let mut sink = FilesystemSink::new(&fs, "out");
+        let mut file = sink.file(Path::new("America/Indiana/Knox")).unwrap();
+        file.write_all(b"hello").unwrap();
+        sink.finish(file).unwrap();
+
+        assert!(fs.is_dir(Path::new("out/America/Indiana")));
+        assert_eq!(fs.contents("out/America/Indiana/Knox"), Some("hello".to_owned()));
+    }
+
+    #[test]
+    fn changes() {
+        let fs = MemoryFilesystem::new();
+        fs.add_file("out/Europe/London.rs", "same");
+        fs.add_file("out/Europe/Paris.rs", "old");
+
+        let mut sink = MemorySink::new();
+        for &(path, contents) in &[ ("Europe/London.rs", "same"), ("Europe/Paris.rs", "new!"), ("Asia/Tokyo.rs", "new") ] {
+            let mut file = sink.file(Path::new(path)).unwrap();
+            file.write_all(contents.as_bytes()).unwrap();
+            sink.finish(file).unwrap();
+        }
+
+        assert_eq!(sink.changes(&fs, Path::new("out")).unwrap(), vec![
+            Change::CreateDirectory(PathBuf::from("out/Asia")),
+            Change::CreateFile(PathBuf::from("out/Asia/Tokyo.rs"), 3),
+            Change::ChangeFile(PathBuf::from("out/Europe/Paris.rs"), 4),
+        ]);
+
+        assert!(sink.changes(&fs, Path::new("elsewhere")).is_err());
+    }
+
+    #[test]
+    fn missing_base_directory() {
+        let fs = MemoryFilesystem::new();
+        let mut sink = FilesystemSink::new(&fs, "out");
+        assert!(sink.file(Path::new("Europe/London")).is_err());
+    }
+
+    #[test]
+    fn paths_outside_the_output_directory() {
+        let fs = MemoryFilesystem::new();
+        fs.add_dir("out");
+
+        let mut sink = FilesystemSink::new(&fs, "out");
+        let mut memory = MemorySink::new();
+        for path in &[ "../escaped", "Europe/../../escaped", "/etc/passwd", "./here", "" ] {
+            assert!(sink.file(Path::new(path)).is_err(), "{}", path);
+            assert!(memory.file(Path::new(path)).is_err(), "{}", path);
+        }
+
+        assert_eq!(fs.paths(), vec![ PathBuf::from("out") ]);
+    }
+
+    #[test]
+    fn zone_file_paths() {
+        assert_eq!(zone_file_path("America/Indiana/Knox").unwrap(), PathBuf::from("America/Indiana/Knox"));
+        assert_eq!(zone_file_path("GB").unwrap(), PathBuf::from("GB"));
+
+        for name in &[ "../../escaped", "Europe/..", "/etc/passwd", "Europe//London", "./GB", "Europe/" ] {
+            assert!(zone_file_path(name).is_err(), "{}", name);
+        }
+    }
+}
@@ -0,0 +1,217 @@
+//! Finding out what a local time means in a zone.
+//!
+//! Working out the offset in effect at some local time means finding the
+//! zone’s lines, the rules they use, and which of those rules had happened
+//! by then, which is tedious to do by hand. The `query` mode does it given
+//! a zone name and a local date and time, printing whether that local time
+//! happened once, twice (when the clocks went back), or not at all (when
+//! they went forward), followed by the UTC offset, abbreviation, and
+//! whether daylight-saving time was in effect:
+//!
+//! ```text
+//! Europe/London 2024-10-27 01:30:00 is ambiguous
+//! offset +01:00, abbreviation BST, DST yes, at 2024-10-27 00:30:00 UTC
+//! offset +00:00, abbreviation GMT, DST no, at 2024-10-27 01:30:00 UTC
+//! ```
+//!
+//! For a local time that never happened, it prints the timespans on either
+//! side of the gap instead.
+
+use std::io::{Write, BufWriter};
+use std::io::Result as IOResult;
+use std::path::Path;
+
+use datetime::{LocalDate, LocalDateTime, LocalTime, Month, DatePiece, TimePiece};
+use getopts;
+
+use zoneinfo_parse::transitions::{TableTransitions, TransitionOptions, FixedTimespan, LocalResolution};
+
+use commands;
+use data_crate::read_table;
+use errors::CliError;
+use system::Filesystem;
+use Report;
+
+
+/// Writes what the local time means in the zone, in the layout above.
+pub fn write_query<W: Write>(w: &mut W, zone_name: &str, local: LocalDateTime, resolution: &LocalResolution) -> IOResult<()> {
+    let local_seconds = local.to_instant().seconds();
+
+    match *resolution {
+        LocalResolution::Unique(ref timespan) => {
+            try!(writeln!(w, "{} {} is unique", zone_name, describe_time(local_seconds)));
+            writeln!(w, "{}, at {} UTC", describe_timespan(timespan), describe_time(local_seconds - timespan.total_offset()))
+        },
+
+        LocalResolution::Ambiguous(ref earlier, ref later) => {
+            try!(writeln!(w, "{} {} is ambiguous", zone_name, describe_time(local_seconds)));
+            try!(writeln!(w, "{}, at {} UTC", describe_timespan(earlier), describe_time(local_seconds - earlier.total_offset())));
+            writeln!(w, "{}, at {} UTC", describe_timespan(later), describe_time(local_seconds - later.total_offset()))
+        },
+
+        LocalResolution::Gap(ref before, ref after) => {
+            try!(writeln!(w, "{} {} is nonexistent", zone_name, describe_time(local_seconds)));
+            try!(writeln!(w, "before the gap: {}", describe_timespan(before)));
+            writeln!(w, "after the gap: {}", describe_timespan(after))
+        },
+    }
+}
+
+/// Describes a timespan’s total offset, abbreviation, and whether it’s
+/// daylight-saving time.
+pub fn describe_timespan(timespan: &FixedTimespan) -> String {
+    format!("offset {}, abbreviation {}, DST {}",
+            describe_offset(timespan.total_offset()), timespan.name,
+            if timespan.is_dst { "yes" } else { "no" })
+}
+
+/// Formats an offset in seconds as `+hh:mm`, or `+hh:mm:ss` if it isn’t a
+/// whole number of minutes.
+fn describe_offset(offset: i64) -> String {
+    let sign = if offset < 0 { '-' } else { '+' };
+    let offset = offset.abs();
+    let (hours, minutes, seconds) = (offset / 3600, offset / 60 % 60, offset % 60);
+
+    if seconds == 0 {
+        format!("{}{:02}:{:02}", sign, hours, minutes)
+    }
+    else {
+        format!("{}{:02}:{:02}:{:02}", sign, hours, minutes, seconds)
+    }
+}
+
+/// Formats a number of seconds since the Unix epoch as a date and time,
+/// such as “2024-10-27 01:30:00”.
+pub fn describe_time(timestamp: i64) -> String {
+    let t = LocalDateTime::at(timestamp);
+    format!("{:04}-{:02}-{:02} {:02}:{:02}:{:02}", t.year(), t.month().months_from_january() + 1, t.day(),
+                                                   t.hour(), t.minute(), t.second())
+}
+
+/// Parses a date and time given on the command line, such as
+/// `2024-10-27T01:30` or `2024-10-27 01:30:00`. The seconds are optional.
+pub fn parse_datetime(input: &str) -> Option<LocalDateTime> {
+    let mut parts = input.splitn(2, |c| c == 'T' || c == ' ');
+    let date: Vec<&str> = parts.next().unwrap_or("").split('-').collect();
+    let time: Vec<&str> = parts.next().unwrap_or("").split(':').collect();
+    if date.len() != 3 || time.len() < 2 || time.len() > 3 {
+        return None;
+    }
+
+    let year   = match date[0].parse() { Ok(y) => y, Err(_) => return None };
+    let month  = match date[1].parse().ok().and_then(|m| Month::from_one(m).ok()) { Some(m) => m, None => return None };
+    let day    = match date[2].parse() { Ok(d) => d, Err(_) => return None };
+    let hour   = match time[0].parse() { Ok(h) => h, Err(_) => return None };
+    let minute = match time[1].parse() { Ok(m) => m, Err(_) => return None };
+    let second = match time.get(2).map_or(Ok(0), |s| s.parse()) { Ok(s) => s, Err(_) => return None };
+
+    match (LocalDate::ymd(year, month, day), LocalTime::hms(hour, minute, second)) {
+        (Ok(date), Ok(time))  => Some(LocalDateTime::new(date, time)),
+        _                     => None,
+    }
+}
+
+
+/// The options the `query` mode takes.
+pub fn options() -> getopts::Options {
+    let mut opts = getopts::Options::new();
+    opts.optopt("o", "output", "file to write the answer to, instead of printing it", "FILE");
+    opts
+}
+
+/// Runs the `query` mode of the builder with the given arguments, which
+/// come after the word `query`. The first free argument is the zone to
+/// query, the second is the local date and time, and the rest are the
+/// input files. Without `--output`, the answer goes in the report’s
+/// `output` to be printed.
+pub fn run<F: Filesystem>(args: &[::std::ffi::OsString], fs: &F) -> Result<Report, CliError> {
+    let command = commands::find("query").unwrap();
+    let matches = try!(command.parse(args));
+    let (zone_name, datetime, inputs) = match matches.free.split_first() {
+        Some((zone, rest)) => match rest.split_first() {
+            Some((datetime, inputs)) => (zone, datetime, inputs),
+            None                     => return Err(command.missing("DATETIME")),
+        },
+        None => return Err(command.missing("ZONE")),
+    };
+
+    let local = match parse_datetime(datetime) {
+        Some(l) => l,
+        None    => return Err(CliError::InvalidArgument("datetime", datetime.clone())),
+    };
+
+    // Rules have to be expanded over the year being asked about, even if
+    // it’s a long way off.
+    let defaults = TransitionOptions::default();
+    let options = TransitionOptions {
+        min_year: defaults.min_year.min(local.year() - 1),
+        max_year: defaults.max_year.max(local.year() + 1),
+        .. defaults
+    };
+
+    let (table, warnings) = try!(read_table(fs, inputs));
+    let resolution = match table.resolve_local(zone_name, local, &options) {
+        Some(r) => r,
+        None    => return Err(CliError::UnknownZone(zone_name.clone())),
+    };
+
+    let mut output = Vec::new();
+    match matches.opt_str("output") {
+        Some(path) => {
+            let mut w = BufWriter::new(try!(fs.create(Path::new(&path))));
+            try!(write_query(&mut w, zone_name, local, &resolution));
+        },
+        None => try!(write_query(&mut output, zone_name, local, &resolution)),
+    }
+
+    Ok(Report {
+        zone_count: 1,
+        link_count: if table.links.contains_key(zone_name) { 1 } else { 0 },
+        warnings:   warnings,
+        output:     String::from_utf8(output).unwrap(),
+    })
+}
+
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use datetime::{LocalDate, LocalDateTime, LocalTime, Month};
+    use zoneinfo_parse::transitions::{FixedTimespan, LocalResolution};
+
+    fn local(y: i64, m: Month, d: i8, h: i8, mm: i8, s: i8) -> LocalDateTime {
+        LocalDateTime::new(LocalDate::ymd(y, m, d).unwrap(), LocalTime::hms(h, mm, s).unwrap())
+    }
+
+    #[test]
+    fn datetimes() {
+        assert_eq!(parse_datetime("2024-10-27T01:30"), Some(local(2024, Month::October, 27, 1, 30, 0)));
+        assert_eq!(parse_datetime("2024-10-27 01:30:15"), Some(local(2024, Month::October, 27, 1, 30, 15)));
+        assert_eq!(parse_datetime("2024-10-27"), None);
+        assert_eq!(parse_datetime("2024-13-27T01:30"), None);
+    }
+
+    #[test]
+    fn ambiguous() {
+        let resolution = LocalResolution::Ambiguous(FixedTimespan::new(0, 3600, "BST"), FixedTimespan::new(0, 0, "GMT"));
+        let mut buf = Vec::new();
+        write_query(&mut buf, "Europe/London", local(2024, Month::October, 27, 1, 30, 0), &resolution).unwrap();
+
+        assert_eq!(String::from_utf8(buf).unwrap(),
+                   "Europe/London 2024-10-27 01:30:00 is ambiguous\n\
+                    offset +01:00, abbreviation BST, DST yes, at 2024-10-27 00:30:00 UTC\n\
+                    offset +00:00, abbreviation GMT, DST no, at 2024-10-27 01:30:00 UTC\n");
+    }
+
+    #[test]
+    fn nonexistent() {
+        let resolution = LocalResolution::Gap(FixedTimespan::new(-18000, 0, "EST"), FixedTimespan::new(-18000, 3600, "EDT"));
+        let mut buf = Vec::new();
+        write_query(&mut buf, "America/New_York", local(2024, Month::March, 10, 2, 30, 0), &resolution).unwrap();
+
+        assert_eq!(String::from_utf8(buf).unwrap(),
+                   "America/New_York 2024-03-10 02:30:00 is nonexistent\n\
+                    before the gap: offset -05:00, abbreviation EST, DST no\n\
+                    after the gap: offset -04:00, abbreviation EDT, DST yes\n");
+    }
+}
@@ -0,0 +1,419 @@
+//! Rewriting zoneinfo source files in a canonical layout, like `rustfmt`
+//! does for Rust code.
+//!
+//! Files that get edited by hand drift apart in how they’re laid out: some
+//! lines use tabs and some use spaces, months get written as `MAR` or
+//! `March`, and columns stop lining up, which makes for noisy diffs. The
+//! `fmt` mode rewrites each file so that:
+//!
+//! - keywords, months, and weekdays are written the same way every time,
+//!   such as `Rule`, `only`, `max`, `Mar`, and `lastSun`;
+//! - the fields of consecutive lines of the same kind are lined up in
+//!   columns, separated by at least two spaces, with a zone’s continuation
+//!   lines lined up under its first line, and any comments at the ends of
+//!   those lines lined up too;
+//! - comment lines are kept as they are, and runs of blank lines are
+//!   squashed into one.
+//!
+//! Lines of the same kind stay in the same group of columns across comment
+//! lines, but a blank line starts a new group. Nothing gets reordered, and
+//! field values other than keywords are left alone.
+//!
+//! A file with a line that couldn’t be parsed is left untouched. With
+//! `--check`, no files are written at all, and the run fails if any of them
+//! would have been changed, for use in CI.
+
+use std::io::{Write, Error as IOError, ErrorKind};
+use std::path::Path;
+
+use getopts;
+
+use zoneinfo_parse::line::{Line, split_lines};
+
+use commands;
+use data_crate::read_error;
+use errors::{CliError, ParseError};
+use system::Filesystem;
+use Report;
+
+
+/// The smallest gap between two columns, in spaces.
+const GAP: usize = 2;
+
+
+/// The kinds of lines that get lined up in columns with their neighbours.
+#[derive(PartialEq, Debug, Copy, Clone)]
+enum Kind {
+    Rule,
+    Zone,
+    Link,
+}
+
+/// One line of a file, split up ready to be written out again.
+#[derive(PartialEq, Debug, Clone)]
+enum Row {
+
+    /// An empty line.
+    Blank,
+
+    /// A line with nothing but a comment on it, which gets written as it is.
+    Comment(String),
+
+    /// A rule, zone, continuation, or link line, as its fields, and the
+    /// text of the comment after them, if there is one. The number is the
+    /// group of columns the line is in.
+    Fields(usize, Vec<String>, Option<String>),
+}
+
+
+/// Returns the given zoneinfo source text in the canonical layout, or the
+/// lines that couldn’t be parsed if there were any.
+pub fn reformat(filename: &str, text: &str) -> Result<String, Vec<ParseError>> {
+    let mut rows = Vec::new();
+    let mut errors = Vec::new();
+    let mut group: Option<(usize, Kind)> = None;
+    let mut group_count = 0;
+
+    for (index, line) in split_lines(text).enumerate() {
+        let (code, comment) = match line.find('#') {
+            Some(pos)  => (&line[.. pos], Some(&line[pos + 1 ..])),
+            None       => (line, None),
+        };
+
+        let fields: Vec<&str> = code.split_whitespace().collect();
+        if fields.is_empty() {
+            match comment {
+                Some(_)  => rows.push(Row::Comment(line.to_owned())),
+                None     => { group = None; rows.push(Row::Blank) },
+            }
+
+            continue;
+        }
+
+        let error = |error: &str| ParseError {
+            filename: filename.to_owned(),
+            line:     index + 1,
+            column:   None,
+            error:    error.to_owned(),
+        };
+
+        let continuation = code.starts_with(char::is_whitespace);
+        let (kind, fields) = match canonical_fields(&fields, continuation) {
+            Some(f)  => f,
+            None     => { errors.push(error("parse error")); continue },
+        };
+
+        // Make sure the result still means something, and means the same
+        // thing, by running it through the parser.
+        let mut canonical = fields.join(" ");
+        if continuation {
+            canonical.insert(0, ' ');
+        }
+
+        if let Err(e) = Line::from_str(&canonical) {
+            errors.push(error(&e.to_string()));
+            continue;
+        }
+
+        let number = match (group, kind, continuation) {
+            (Some((number, Kind::Zone)), Kind::Zone, true)  => number,
+            (_, Kind::Zone, true)                           => { errors.push(error("continuation line without a zone")); continue },
+            (Some((number, k)), _, _) if k == kind && kind != Kind::Zone => number,
+            _                                               => { group_count += 1; group_count },
+        };
+
+        group = Some((number, kind));
+        rows.push(Row::Fields(number, fields, comment.map(|c| c.to_owned())));
+    }
+
+    if errors.is_empty() {
+        Ok(write_rows(&rows))
+    }
+    else {
+        Err(errors)
+    }
+}
+
+/// Returns the kind of line the given fields are from, along with the
+/// fields with their keywords written the canonical way, or `None` if the
+/// first field isn’t a keyword that’s known.
+fn canonical_fields(fields: &[&str], continuation: bool) -> Option<(Kind, Vec<String>)> {
+    let mut result: Vec<String> = fields.iter().map(|f| (*f).to_owned()).collect();
+
+    // The fields at which the date in a zone’s UNTIL column starts.
+    let until = if continuation {
+        result.splice(0 .. 0, vec![ String::new(), String::new() ]);
+        2
+    }
+    else {
+        match &*fields[0].to_ascii_lowercase() {
+            "rule"  => {
+                result[0] = "Rule".to_owned();
+                map_field(&mut result, 2, year);
+                map_field(&mut result, 3, year);
+                map_field(&mut result, 5, month);
+                map_field(&mut result, 6, day);
+                return Some((Kind::Rule, result));
+            },
+            "link"  => {
+                result[0] = "Link".to_owned();
+                return Some((Kind::Link, result));
+            },
+            "zone"  => {
+                result[0] = "Zone".to_owned();
+                2
+            },
+            _       => return None,
+        }
+    };
+
+    map_field(&mut result, until + 3, year);
+    map_field(&mut result, until + 4, month);
+    map_field(&mut result, until + 5, day);
+    Some((Kind::Zone, result))
+}
+
+/// Replaces the field at the given index, if there is one, with its
+/// canonical spelling, if it has one.
+fn map_field(fields: &mut [String], index: usize, canonical: fn(&str) -> Option<String>) {
+    if let Some(field) = fields.get_mut(index) {
+        if let Some(replacement) = canonical(field) {
+            *field = replacement;
+        }
+    }
+}
+
+/// Returns the canonical spelling of a year keyword.
+fn year(field: &str) -> Option<String> {
+    let keyword = match &*field.to_ascii_lowercase() {
+        "only"               => "only",
+        "min" | "minimum"    => "min",
+        "max" | "maximum"    => "max",
+        _                    => return None,
+    };
+
+    Some(keyword.to_owned())
+}
+
+/// Returns the canonical spelling of a month name.
+fn month(field: &str) -> Option<String> {
+    const MONTHS: [&'static str; 12] = [ "january", "february", "march", "april", "may", "june",
+                                         "july", "august", "september", "october", "november", "december" ];
+
+    let field = field.to_ascii_lowercase();
+    MONTHS.iter().find(|m| **m == field || m[.. 3] == field).map(|m| title_case(&m[.. 3]))
+}
+
+/// Returns the canonical spelling of a weekday name.
+fn weekday(field: &str) -> Option<String> {
+    const WEEKDAYS: [&'static str; 7] = [ "monday", "tuesday", "wednesday", "thursday",
+                                          "friday", "saturday", "sunday" ];
+
+    let field = field.to_ascii_lowercase();
+    WEEKDAYS.iter().find(|d| **d == field || d[.. 3] == field).map(|d| title_case(&d[.. 3]))
+}
+
+/// Returns the canonical spelling of a day field, such as `lastSun` or
+/// `Sun>=8`, leaving days of the month as they are.
+fn day(field: &str) -> Option<String> {
+    if field.len() > 4 && field[.. 4].eq_ignore_ascii_case("last") {
+        weekday(&field[4 ..]).map(|w| format!("last{}", w))
+    }
+    else if let Some(pos) = field.find(|c| c == '<' || c == '>') {
+        weekday(&field[.. pos]).map(|w| format!("{}{}", w, &field[pos ..]))
+    }
+    else {
+        None
+    }
+}
+
+fn title_case(word: &str) -> String {
+    word[.. 1].to_ascii_uppercase() + &word[1 ..]
+}
+
+/// Writes out the rows, lining up the fields of each group in columns.
+fn write_rows(rows: &[Row]) -> String {
+    let mut output = String::new();
+
+    for (index, row) in rows.iter().enumerate() {
+        match *row {
+            Row::Blank => {
+                let after_blank = index == 0 || rows[index - 1] == Row::Blank;
+                let at_end = rows[index ..].iter().all(|r| *r == Row::Blank);
+                if !after_blank && !at_end {
+                    output.push('\n');
+                }
+            },
+
+            Row::Comment(ref text) => {
+                output.push_str(text);
+                output.push('\n');
+            },
+
+            Row::Fields(number, ref fields, ref comment) => {
+                let group = rows.iter().filter_map(|r| match *r {
+                    Row::Fields(n, ref fs, _) if n == number  => Some(fs),
+                    _                                         => None,
+                });
+
+                let mut widths = Vec::new();
+                for fields in group {
+                    for (column, field) in fields.iter().enumerate() {
+                        if column == widths.len() {
+                            widths.push(0);
+                        }
+
+                        widths[column] = widths[column].max(field.len());
+                    }
+                }
+
+                let line = join_fields(fields, &widths);
+                output.push_str(&line);
+
+                if let Some(ref comment) = *comment {
+                    let comment_column = rows.iter().filter_map(|r| match *r {
+                        Row::Fields(n, ref fs, _) if n == number  => Some(join_fields(fs, &widths).len()),
+                        _                                         => None,
+                    }).max().unwrap();
+
+                    output.push_str(&" ".repeat(comment_column + GAP - line.len()));
+                    output.push('#');
+                    output.push_str(comment.trim_end());
+                }
+
+                output.push('\n');
+            },
+        }
+    }
+
+    output
+}
+
+/// Joins the fields of a line, padding each one but the last to the width
+/// of its column.
+fn join_fields(fields: &[String], widths: &[usize]) -> String {
+    let mut line = String::new();
+
+    for (column, field) in fields.iter().enumerate() {
+        line.push_str(field);
+        if column + 1 < fields.len() {
+            line.push_str(&" ".repeat(widths[column] - field.len() + GAP));
+        }
+    }
+
+    line
+}
+
+
+/// The options the `fmt` mode takes.
+pub fn options() -> getopts::Options {
+    let mut opts = getopts::Options::new();
+    opts.optflag("", "check", "don’t write anything, but fail if any file isn’t formatted");
+    opts
+}
+
+/// Runs the `fmt` mode of the builder with the given arguments, which come
+/// after the word `fmt`. The free arguments are the files to reformat,
+/// which get rewritten in place.
+pub fn run<F: Filesystem>(args: &[::std::ffi::OsString], fs: &F) -> Result<Report, CliError> {
+    let command = commands::find("fmt").unwrap();
+    let matches = try!(command.parse(args));
+    if matches.free.is_empty() {
+        return Err(command.missing("FILE"));
+    }
+
+    let check = matches.opt_present("check");
+    let mut errors = Vec::new();
+    let mut changed = Vec::new();
+    let mut zone_count = 0;
+    let mut link_count = 0;
+
+    for filename in &matches.free {
+        let path = Path::new(filename);
+        let contents = try!(fs.read(path).map_err(|e| read_error(filename, e)));
+        let text = match String::from_utf8((*contents).as_ref().to_vec()) {
+            Ok(t)   => t,
+            Err(e)  => return Err(read_error(filename, IOError::new(ErrorKind::InvalidData, e))),
+        };
+
+        let formatted = match reformat(filename, &text) {
+            Ok(f)    => f,
+            Err(es)  => { errors.extend(es); continue },
+        };
+
+        for line in formatted.lines() {
+            if line.starts_with("Zone ") { zone_count += 1 }
+            if line.starts_with("Link ") { zone_count += 1; link_count += 1 }
+        }
+
+        if formatted != text {
+            if !check {
+                info!("Reformatting {}", filename);
+                let mut w = try!(fs.create(path));
+                try!(w.write_all(formatted.as_bytes()));
+            }
+
+            changed.push(filename.clone());
+        }
+    }
+
+    if !errors.is_empty() {
+        return Err(errors.into());
+    }
+
+    if check && !changed.is_empty() {
+        return Err(CliError::Unformatted(changed));
+    }
+
+    Ok(Report {
+        zone_count: zone_count,
+        link_count: link_count,
+        warnings:   Vec::new(),
+        output:     String::new(),
+    })
+}
+
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn columns() {
+        let text = "# Rules\n\
+                    rule\tTest\t2000\tONLY\t-\tmarch\tlastsun\t1:00u\t1:00\tS\n\
+                    Rule Test 2000 Only - Oct sun>=1 1:00u 0 - # Autumn\n\
+                    \n\
+                    \n\
+                    Zone Test/Zone 0:00 Test TE%sT 2001 mar 1\n\
+                    # Changed its mind\n\
+                    \t\t1:00 - TST\t# Now\n\
+                    LINK  Test/Zone  Test/Link\n";
+
+        assert_eq!(reformat("test", text).unwrap(),
+                   "# Rules\n\
+                    Rule  Test  2000  only  -  Mar  lastSun  1:00u  1:00  S\n\
+                    Rule  Test  2000  only  -  Oct  Sun>=1   1:00u  0     -  # Autumn\n\
+                    \n\
+                    Zone  Test/Zone  0:00  Test  TE%sT  2001  Mar  1\n\
+                    # Changed its mind\n\
+                    \x20                1:00  -     TST                  # Now\n\
+                    Link  Test/Zone  Test/Link\n");
+    }
+
+    #[test]
+    fn already_formatted() {
+        let text = "Rule  Test  2000  only  -  Mar  lastSun  1:00u  1:00  S\n";
+        assert_eq!(reformat("test", text).unwrap(), text);
+    }
+
+    #[test]
+    fn errors() {
+        let errors = reformat("test", "Zone  Test/Zone\n\
+                                       \x20    1:00  -  TST\n\
+                                       Nonsense\n").unwrap_err();
+
+        let lines: Vec<usize> = errors.iter().map(|e| e.line).collect();
+        assert_eq!(lines, vec![ 1, 2, 3 ]);
+    }
+}
@@ -0,0 +1,506 @@
+//! The clock and filesystem, as seen by the builder.
+//!
+//! Everything the builder does that depends on the outside world — reading
+//! the input files, creating directories, writing the generated files, and
+//! working out what time it is now — goes through the `Clock` and
+//! `Filesystem` traits, rather than calling into `std` directly. The binary
+//! uses `SystemClock` and `RealFilesystem`, which do the obvious things, but
+//! tests can use a `FixedClock` and a `MemoryFilesystem` instead, and check
+//! exactly what got written without touching the disk or depending on the
+//! date they’re run.
+//!
+//! The one exception is the programs the `fetch` mode runs to download and
+//! unpack the database, which work on the real disk; the directory they
+//! work in, and the files the mode reads and removes itself, still go
+//! through the `Filesystem`.
+
+use std::cell::RefCell;
+use std::collections::BTreeMap;
+use std::env;
+use std::fs::{self, File};
+use std::io::{self, Cursor, Error as IOError, ErrorKind, Read, Write};
+use std::io::Result as IOResult;
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+#[cfg(feature = "mmap")] use memmap2::Mmap;
+
+
+/// Something that knows what time it is.
+pub trait Clock {
+
+    /// Returns the current time, as a number of seconds since the Unix
+    /// epoch.
+    fn now(&self) -> i64;
+}
+
+/// The system’s clock.
+///
+/// For reproducible builds, if the `SOURCE_DATE_EPOCH` environment variable
+/// is set to a number of seconds since the Unix epoch, that gets used as
+/// the current time instead, so the timestamps in the output don’t change
+/// from one run to the next.
+#[derive(PartialEq, Debug, Copy, Clone, Default)]
+pub struct SystemClock;
+
+/// The environment variable that overrides the system’s clock.
+pub const SOURCE_DATE_EPOCH: &'static str = "SOURCE_DATE_EPOCH";
+
+impl Clock for SystemClock {
+    fn now(&self) -> i64 {
+        if let Some(epoch) = env::var(SOURCE_DATE_EPOCH).ok().and_then(|e| e.trim().parse().ok()) {
+            return epoch;
+        }
+
+        match SystemTime::now().duration_since(UNIX_EPOCH) {
+            Ok(d)   => d.as_secs() as i64,
+            Err(_)  => 0,
+        }
+    }
+}
+
+/// A clock that’s stopped at the given number of seconds since the Unix
+/// epoch.
+#[derive(PartialEq, Debug, Copy, Clone)]
+pub struct FixedClock(pub i64);
+
+impl Clock for FixedClock {
+    fn now(&self) -> i64 {
+        self.0
+    }
+}
+
+
+/// The file operations the builder needs, along with reading standard input,
+/// which can be used in place of an input file.
+pub trait Filesystem {
+
+    /// The type that a file gets read through.
+    type Reader: Read;
+
+    /// The type that a file gets written through.
+    type Writer: Write;
+
+    /// Opens the file at the given path for reading.
+    fn open(&self, path: &Path) -> IOResult<Self::Reader>;
+
+    /// Returns the whole contents of the file at the given path, in a form
+    /// that can be sent to another thread to be parsed there. By default,
+    /// this reads the file into a buffer.
+    fn read(&self, path: &Path) -> IOResult<Contents> {
+        let mut contents = Vec::new();
+        let _ = try!(try!(self.open(path)).read_to_end(&mut contents));
+        Ok(Box::new(contents))
+    }
+
+    /// Creates the file at the given path for writing, truncating it if it
+    /// already exists. The directory it’s in has to exist.
+    fn create(&self, path: &Path) -> IOResult<Self::Writer>;
+
+    /// Creates a directory at the given path. Its parent directory has to
+    /// exist, but the directory itself mustn’t.
+    fn create_dir(&self, path: &Path) -> IOResult<()>;
+
+    /// Removes the file at the given path.
+    fn remove_file(&self, path: &Path) -> IOResult<()>;
+
+    /// Moves the file at the first path to the second, replacing anything
+    /// already there. By default, this copies the file and removes the
+    /// original, for filesystems that can’t move files in one step.
+    fn rename(&self, from: &Path, to: &Path) -> IOResult<()> {
+        let contents = try!(self.read(from));
+        {
+            let mut w = try!(self.create(to));
+            try!(w.write_all((*contents).as_ref()));
+        }
+        self.remove_file(from)
+    }
+
+    /// Makes a hard link at the second path to the file at the first, so
+    /// both paths share the same contents. Nothing can exist at the second
+    /// path yet. By default, this copies the file instead, for filesystems
+    /// that don’t have links.
+    fn hard_link(&self, original: &Path, link: &Path) -> IOResult<()> {
+        let contents = try!(self.read(original));
+        let mut w = try!(self.create(link));
+        w.write_all((*contents).as_ref())
+    }
+
+    /// Returns whether there’s a directory at the given path.
+    fn is_dir(&self, path: &Path) -> bool;
+
+    /// Returns whether there’s a file at the given path.
+    fn is_file(&self, path: &Path) -> bool;
+
+    /// Reads everything from standard input.
+    fn read_stdin(&self) -> IOResult<Vec<u8>>;
+}
+
+/// Creates the given directory, and any of its parents that don’t exist
+/// yet.
+pub fn create_dirs<F: Filesystem>(fs: &F, dir: &Path) -> IOResult<()> {
+    if dir == Path::new("") || fs.is_dir(dir) {
+        return Ok(());
+    }
+
+    if let Some(parent) = dir.parent() {
+        try!(create_dirs(fs, parent));
+    }
+
+    fs.create_dir(dir)
+}
+
+/// The contents of a file, which could be in a buffer, or mapped into
+/// memory.
+pub type Contents = Box<AsRef<[u8]> + Send>;
+
+/// The filesystem on disk.
+///
+/// With the `mmap` feature enabled, reading a whole file maps it into
+/// memory, so its contents can be parsed straight from the page cache
+/// without being copied into a buffer first. The catch is that if another
+/// program truncates the file while it’s mapped, reading the part that’s
+/// gone raises SIGBUS, which kills the process instead of returning an
+/// error.
+#[derive(PartialEq, Debug, Copy, Clone, Default)]
+pub struct RealFilesystem;
+
+impl Filesystem for RealFilesystem {
+    type Reader = File;
+    type Writer = File;
+
+    fn open(&self, path: &Path) -> IOResult<File> {
+        File::open(path)
+    }
+
+    #[cfg(feature = "mmap")]
+    fn read(&self, path: &Path) -> IOResult<Contents> {
+        let file = try!(File::open(path));
+
+        // Empty files can’t be mapped, but there’s nothing to read anyway.
+        if try!(file.metadata()).len() == 0 {
+            return Ok(Box::new(Vec::new()));
+        }
+
+        // Safety: the mapping is only sound as long as nothing else changes
+        // the file while it’s mapped. If the file’s contents change, the
+        // input is nonsense, but if it gets truncated, touching the pages
+        // past its new end raises SIGBUS and crashes the process. Nothing
+        // here can guard against that, so it’s only worth the risk for
+        // input files nothing else is writing to, which is why the feature
+        // is off by default.
+        let map = try!(unsafe { Mmap::map(&file) });
+        Ok(Box::new(map))
+    }
+
+    fn create(&self, path: &Path) -> IOResult<File> {
+        File::create(path)
+    }
+
+    fn create_dir(&self, path: &Path) -> IOResult<()> {
+        fs::create_dir(path)
+    }
+
+    fn remove_file(&self, path: &Path) -> IOResult<()> {
+        fs::remove_file(path)
+    }
+
+    fn rename(&self, from: &Path, to: &Path) -> IOResult<()> {
+        fs::rename(from, to)
+    }
+
+    fn hard_link(&self, original: &Path, link: &Path) -> IOResult<()> {
+        fs::hard_link(original, link)
+    }
+
+    fn is_dir(&self, path: &Path) -> bool {
+        path.is_dir()
+    }
+
+    fn is_file(&self, path: &Path) -> bool {
+        path.is_file()
+    }
+
+    fn read_stdin(&self) -> IOResult<Vec<u8>> {
+        let mut contents = Vec::new();
+        let _ = try!(io::stdin().read_to_end(&mut contents));
+        Ok(contents)
+    }
+}
+
+
+/// A filesystem that only exists in memory, for tests.
+///
+/// It starts off empty, apart from the current directory (the empty path),
+/// so the directories that get written into have to be added first, just
+/// as they would have to exist on disk. Standard input starts off empty too.
+#[derive(Debug, Default)]
+pub struct MemoryFilesystem {
+    entries: RefCell<BTreeMap<PathBuf, Entry>>,
+    stdin: RefCell<Vec<u8>>,
+}
+
+/// A directory, or a file and its contents. The contents are shared with
+/// any `MemoryFile` writing to them.
+#[derive(Debug, Clone)]
+enum Entry {
+    Directory,
+    File(Rc<RefCell<Vec<u8>>>),
+}
+
+impl MemoryFilesystem {
+
+    /// Creates a new, empty filesystem.
+    pub fn new() -> MemoryFilesystem {
+        MemoryFilesystem::default()
+    }
+
+    /// Adds a directory at the given path, along with any of its parents
+    /// that don’t exist yet.
+    pub fn add_dir<P: AsRef<Path>>(&self, path: P) {
+        let mut entries = self.entries.borrow_mut();
+        let mut next = Some(path.as_ref());
+
+        while let Some(dir) = next {
+            if dir != Path::new("") && dir != Path::new("/") {
+                let _ = entries.entry(dir.to_path_buf()).or_insert(Entry::Directory);
+            }
+
+            next = dir.parent();
+        }
+    }
+
+    /// Adds a file with the given contents, along with any of the
+    /// directories it’s in that don’t exist yet.
+    pub fn add_file<P: AsRef<Path>>(&self, path: P, contents: &str) {
+        if let Some(parent) = path.as_ref().parent() {
+            self.add_dir(parent);
+        }
+
+        let contents = Rc::new(RefCell::new(contents.as_bytes().to_vec()));
+        let _ = self.entries.borrow_mut().insert(path.as_ref().to_path_buf(), Entry::File(contents));
+    }
+
+    /// Sets what gets read from standard input.
+    pub fn set_stdin(&self, contents: &str) {
+        *self.stdin.borrow_mut() = contents.as_bytes().to_vec();
+    }
+
+    /// Returns the contents of the file at the given path, if there is one
+    /// and it’s valid UTF-8.
+    pub fn contents<P: AsRef<Path>>(&self, path: P) -> Option<String> {
+        self.bytes(path).and_then(|bytes| String::from_utf8(bytes).ok())
+    }
+
+    /// Returns the contents of the file at the given path, if there is one,
+    /// as bytes.
+    pub fn bytes<P: AsRef<Path>>(&self, path: P) -> Option<Vec<u8>> {
+        match self.entries.borrow().get(path.as_ref()) {
+            Some(&Entry::File(ref contents)) => Some(contents.borrow().clone()),
+            _                                => None,
+        }
+    }
+
+    /// Returns the paths of every file and directory, in order.
+    pub fn paths(&self) -> Vec<PathBuf> {
+        self.entries.borrow().keys().cloned().collect()
+    }
+
+    /// Returns an error unless the parent of the given path is a directory.
+    fn check_parent(&self, path: &Path) -> IOResult<()> {
+        match path.parent() {
+            Some(parent) if parent == Path::new("") || parent == Path::new("/") || self.is_dir(parent) => Ok(()),
+            _ => Err(not_found(path)),
+        }
+    }
+}
+
+impl Filesystem for MemoryFilesystem {
+    type Reader = Cursor<Vec<u8>>;
+    type Writer = MemoryFile;
+
+    fn open(&self, path: &Path) -> IOResult<Cursor<Vec<u8>>> {
+        match self.entries.borrow().get(path) {
+            Some(&Entry::File(ref contents)) => Ok(Cursor::new(contents.borrow().clone())),
+            _                                => Err(not_found(path)),
+        }
+    }
+
+    fn create(&self, path: &Path) -> IOResult<MemoryFile> {
+        try!(self.check_parent(path));
+        if self.is_dir(path) {
+            return Err(IOError::new(ErrorKind::Other, format!("{}: is a directory", path.display())));
+        }
+
+        let contents = Rc::new(RefCell::new(Vec::new()));
+        let _ = self.entries.borrow_mut().insert(path.to_path_buf(), Entry::File(contents.clone()));
+        Ok(MemoryFile(contents))
+    }
+
+    fn create_dir(&self, path: &Path) -> IOResult<()> {
+        try!(self.check_parent(path));
+        if self.entries.borrow().contains_key(path) {
+            return Err(IOError::new(ErrorKind::AlreadyExists, format!("{}: already exists", path.display())));
+        }
+
+        let _ = self.entries.borrow_mut().insert(path.to_path_buf(), Entry::Directory);
+        Ok(())
+    }
+
+    fn remove_file(&self, path: &Path) -> IOResult<()> {
+        if !self.is_file(path) {
+            return Err(not_found(path));
+        }
+
+        let _ = self.entries.borrow_mut().remove(path);
+        Ok(())
+    }
+
+    fn rename(&self, from: &Path, to: &Path) -> IOResult<()> {
+        try!(self.check_parent(to));
+        if !self.is_file(from) {
+            return Err(not_found(from));
+        }
+        if self.is_dir(to) {
+            return Err(IOError::new(ErrorKind::Other, format!("{}: is a directory", to.display())));
+        }
+
+        let mut entries = self.entries.borrow_mut();
+        let entry = entries.remove(from).unwrap();
+        let _ = entries.insert(to.to_path_buf(), entry);
+        Ok(())
+    }
+
+    fn hard_link(&self, original: &Path, link: &Path) -> IOResult<()> {
+        try!(self.check_parent(link));
+        let contents = match self.entries.borrow().get(original) {
+            Some(&Entry::File(ref contents)) => contents.clone(),
+            _                                => return Err(not_found(original)),
+        };
+
+        if self.entries.borrow().contains_key(link) {
+            return Err(IOError::new(ErrorKind::AlreadyExists, format!("{}: already exists", link.display())));
+        }
+
+        let _ = self.entries.borrow_mut().insert(link.to_path_buf(), Entry::File(contents));
+        Ok(())
+    }
+
+    fn is_dir(&self, path: &Path) -> bool {
+        match self.entries.borrow().get(path) {
+            Some(&Entry::Directory) => true,
+            _                       => false,
+        }
+    }
+
+    fn is_file(&self, path: &Path) -> bool {
+        match self.entries.borrow().get(path) {
+            Some(&Entry::File(_)) => true,
+            _                     => false,
+        }
+    }
+
+    /// Standard input can only be read once: reading it again returns
+    /// nothing.
+    fn read_stdin(&self) -> IOResult<Vec<u8>> {
+        Ok(::std::mem::replace(&mut *self.stdin.borrow_mut(), Vec::new()))
+    }
+}
+
+/// A file in a `MemoryFilesystem` that’s open for writing.
+#[derive(Debug)]
+pub struct MemoryFile(Rc<RefCell<Vec<u8>>>);
+
+impl Write for MemoryFile {
+    fn write(&mut self, buf: &[u8]) -> IOResult<usize> {
+        self.0.borrow_mut().extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> IOResult<()> {
+        Ok(())
+    }
+}
+
+/// The error for a path that doesn’t exist, as the real filesystem would
+/// return it.
+fn not_found(path: &Path) -> IOError {
+    IOError::new(ErrorKind::NotFound, format!("{}: no such file or directory", path.display()))
+}
+
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::io::{Read, Write};
+    use std::path::Path;
+
+    #[test]
+    fn write_then_read() {
+        let fs = MemoryFilesystem::new();
+        fs.add_dir("out");
+
+        fs.create(Path::new("out/file")).unwrap().write_all(b"hello").unwrap();
+
+        let mut contents = String::new();
+        let _ = fs.open(Path::new("out/file")).unwrap().read_to_string(&mut contents).unwrap();
+        assert_eq!(contents, "hello");
+        assert!(fs.is_file(Path::new("out/file")));
+    }
+
+    #[test]
+    fn read_whole_file() {
+        let fs = MemoryFilesystem::new();
+        fs.add_file("tz/europe", "Zone  Europe/London  0:00  -  GMT\n");
+
+        let contents = fs.read(Path::new("tz/europe")).unwrap();
+        assert_eq!((*contents).as_ref(), &b"Zone  Europe/London  0:00  -  GMT\n"[..]);
+        assert!(fs.read(Path::new("tz/asia")).is_err());
+    }
+
+    #[test]
+    fn missing_parent() {
+        let fs = MemoryFilesystem::new();
+        assert!(fs.create(Path::new("out/file")).is_err());
+        assert!(fs.create_dir(Path::new("out/dir")).is_err());
+    }
+
+    #[test]
+    fn directories() {
+        let fs = MemoryFilesystem::new();
+        fs.add_dir("out");
+        fs.create_dir(Path::new("out/dir")).unwrap();
+        assert!(fs.create_dir(Path::new("out/dir")).is_err());
+        assert!(fs.is_dir(Path::new("out/dir")));
+        assert!(!fs.is_file(Path::new("out/dir")));
+    }
+
+    #[test]
+    fn hard_links() {
+        let fs = MemoryFilesystem::new();
+        fs.add_file("out/original", "before");
+
+        fs.hard_link(Path::new("out/original"), Path::new("out/link")).unwrap();
+        assert_eq!(fs.contents("out/link"), Some("before".to_owned()));
+        assert!(fs.hard_link(Path::new("out/original"), Path::new("out/link")).is_err());
+        assert!(fs.hard_link(Path::new("out/missing"), Path::new("out/other")).is_err());
+
+        fs.remove_file(Path::new("out/original")).unwrap();
+        assert!(!fs.is_file(Path::new("out/original")));
+        assert!(fs.is_file(Path::new("out/link")));
+    }
+
+    #[test]
+    fn renames() {
+        let fs = MemoryFilesystem::new();
+        fs.add_file("out/new", "new");
+        fs.add_file("out/old", "old");
+
+        fs.rename(Path::new("out/new"), Path::new("out/old")).unwrap();
+        assert_eq!(fs.contents("out/old"), Some("new".to_owned()));
+        assert!(!fs.is_file(Path::new("out/new")));
+        assert!(fs.rename(Path::new("out/new"), Path::new("out/other")).is_err());
+        assert!(fs.rename(Path::new("out/old"), Path::new("elsewhere/old")).is_err());
+    }
+}
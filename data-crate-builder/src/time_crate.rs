@@ -0,0 +1,162 @@
+//! Writing a dataset as Rust code for use with the `time` crate.
+//!
+//! The `time` crate has types for offsets and instants, but no time zone
+//! data, so projects that use it have to get that from somewhere else.
+//! This writes a `time_zones.rs` file to include into such a project: a
+//! `ZONES` array of every zone and link, sorted by name, each with every
+//! timespan it goes through, and a `lookup` function that finds one by name
+//! with a binary search. A zone gives its offset at an `OffsetDateTime` as
+//! a `UtcOffset`, and can convert an instant into its local time.
+//!
+//! Rather than the separate UTC and DST offsets of the other formats, each
+//! timespan has its total offset, as that’s what a `UtcOffset` holds, along
+//! with whether it’s daylight-saving time and its abbreviation. A link
+//! shares the timespans of the zone it links to.
+
+use std::io::Write;
+use std::io::Result as IOResult;
+
+use data_crate::Dataset;
+
+
+/// The types and functions at the top of the generated file, which only
+/// depend on the `time` crate.
+const TIME_HEADER: &'static str = r##"
+use time::{OffsetDateTime, UtcOffset};
+
+/// One of a zone’s timespans: the offset and abbreviation in use from its
+/// start until the start of the next one.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct Timespan {
+
+    /// The Unix timestamp this timespan starts at, or `i64::MIN` for the
+    /// first one of a zone.
+    pub start: i64,
+
+    /// The total offset from UTC in seconds, daylight-saving time included.
+    pub offset_seconds: i32,
+
+    /// Whether this timespan is daylight-saving time.
+    pub is_dst: bool,
+
+    /// The abbreviation in use during this timespan, such as `BST`.
+    pub abbreviation: &'static str,
+}
+
+impl Timespan {
+
+    /// Returns this timespan’s offset from UTC.
+    pub fn offset(&self) -> UtcOffset {
+        UtcOffset::from_whole_seconds(self.offset_seconds).unwrap()
+    }
+}
+
+/// A time zone, with every timespan it goes through in order.
+#[derive(Debug)]
+pub struct Zone {
+
+    /// The name of the zone, such as `Europe/London`.
+    pub name: &'static str,
+
+    /// The zone’s timespans, earliest first.
+    pub timespans: &'static [Timespan],
+}
+
+impl Zone {
+
+    /// Returns the timespan in effect at the given instant.
+    pub fn timespan_at(&self, datetime: OffsetDateTime) -> &'static Timespan {
+        let timestamp = datetime.unix_timestamp();
+        match self.timespans.binary_search_by(|t| t.start.cmp(&timestamp)) {
+            Ok(index)   => &self.timespans[index],
+            Err(index)  => &self.timespans[index - 1],
+        }
+    }
+
+    /// Returns the offset in effect at the given instant.
+    pub fn offset_at(&self, datetime: OffsetDateTime) -> UtcOffset {
+        self.timespan_at(datetime).offset()
+    }
+
+    /// Returns the given instant with the offset in effect in this zone at
+    /// that instant, so its date and time are the local ones.
+    pub fn to_zone(&self, datetime: OffsetDateTime) -> OffsetDateTime {
+        datetime.to_offset(self.offset_at(datetime))
+    }
+}
+
+/// Returns the zone or link with the given name, if there is one.
+pub fn lookup(name: &str) -> Option<&'static Zone> {
+    ZONES.binary_search_by(|z| z.name.cmp(name)).ok().map(|index| &ZONES[index])
+}
+"##;
+
+
+/// Writes every zone and link in the dataset as Rust code for the `time`
+/// crate. Links that don’t link to a zone get left out, as they’d have no
+/// timespans.
+pub fn write_time_zones<W: Write>(w: &mut W, dataset: &Dataset) -> IOResult<()> {
+    try!(writeln!(w, "{}", TIME_HEADER));
+
+    let zone_names: Vec<&String> = dataset.zonesets.keys().collect();
+    for (index, name) in zone_names.iter().enumerate() {
+        let set = &dataset.zonesets[*name];
+        try!(writeln!(w, "/// {}", name));
+        try!(writeln!(w, "static TIMESPANS_{}: [Timespan; {}] = [", index, set.rest.len() + 1));
+        try!(writeln!(w, "    {},", timespan_literal("i64::MIN", set.first.total_offset(), set.first.is_dst, &set.first.name)));
+        for &(at, ref timespan) in &set.rest {
+            try!(writeln!(w, "    {},", timespan_literal(&at.to_string(), timespan.total_offset(), timespan.is_dst, &timespan.name)));
+        }
+        try!(writeln!(w, "];\n"));
+    }
+
+    let names: Vec<&str> = dataset.names().into_iter().filter(|n| dataset.timespans(n).is_some()).collect();
+    try!(writeln!(w, "/// Every zone and link, sorted by name."));
+    try!(writeln!(w, "pub static ZONES: [Zone; {}] = [", names.len()));
+    for name in &names {
+        let target = dataset.links.get(*name).map(|t| &**t).unwrap_or(name);
+        let index = zone_names.iter().position(|n| *n == target).unwrap();
+        try!(writeln!(w, "    Zone {{ name: {:?}, timespans: &TIMESPANS_{} }},", name, index));
+    }
+    writeln!(w, "];")
+}
+
+/// Returns a timespan as a `Timespan` expression.
+fn timespan_literal(start: &str, offset: i64, is_dst: bool, abbreviation: &str) -> String {
+    format!("Timespan {{ start: {}, offset_seconds: {}, is_dst: {}, abbreviation: {:?} }}",
+            start, offset, is_dst, abbreviation)
+}
+
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use data_crate::Dataset;
+    use zoneinfo_parse::transitions::{FixedTimespan, FixedTimespanSet};
+
+    #[test]
+    fn zones() {
+        let mut dataset = Dataset::default();
+        let _ = dataset.zonesets.insert("Europe/London".to_owned(), FixedTimespanSet {
+            first: FixedTimespan::new(0, 0, "GMT"),
+            rest:  vec![ (1_711_846_800, FixedTimespan::new(0, 3600, "BST")) ],
+        });
+        let _ = dataset.links.insert("GB".to_owned(), "Europe/London".to_owned());
+        let _ = dataset.links.insert("Nowhere".to_owned(), "Does/Not/Exist".to_owned());
+
+        let mut buf = Vec::new();
+        write_time_zones(&mut buf, &dataset).unwrap();
+        let source = String::from_utf8(buf).unwrap();
+
+        assert!(source.contains("/// Europe/London\n\
+                                 static TIMESPANS_0: [Timespan; 2] = [\n\
+                                 \x20   Timespan { start: i64::MIN, offset_seconds: 0, is_dst: false, abbreviation: \"GMT\" },\n\
+                                 \x20   Timespan { start: 1711846800, offset_seconds: 3600, is_dst: true, abbreviation: \"BST\" },\n\
+                                 ];\n"));
+        assert!(source.contains("pub static ZONES: [Zone; 2] = [\n\
+                                 \x20   Zone { name: \"Europe/London\", timespans: &TIMESPANS_0 },\n\
+                                 \x20   Zone { name: \"GB\", timespans: &TIMESPANS_0 },\n\
+                                 ];"));
+        assert!(!source.contains("Nowhere"));
+    }
+}
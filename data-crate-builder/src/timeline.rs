@@ -0,0 +1,254 @@
+//! Drawing a zone’s offsets over the years as an SVG timeline.
+//!
+//! The timeline is a step chart: time runs from left to right, and each
+//! timespan is drawn as a band whose height is its total UTC offset, so
+//! changes of offset show up as steps. Timespans during daylight-saving
+//! time are shaded in a different colour, and hovering over a band shows
+//! its abbreviation and offset.
+//!
+//! It’s meant for documentation, and for eyeballing what changed in a zone
+//! after updating the tz database.
+
+use std::io::{Write, BufWriter};
+use std::io::Result as IOResult;
+use std::ops::Range;
+use std::path::Path;
+
+use datetime::{LocalDate, LocalDateTime, LocalTime, Month};
+use getopts;
+
+use zoneinfo_parse::transitions::{TableTransitions, TransitionOptions, FixedTimespan, FixedTimespanSet};
+
+use commands;
+use data_crate::read_table;
+use errors::CliError;
+use system::Filesystem;
+use {Report, number_option};
+
+
+/// The size of the whole image, in pixels.
+const WIDTH: i64 = 1000;
+const HEIGHT: i64 = 260;
+
+/// The gaps between the edges of the image and the chart, in pixels, which
+/// leave room for the title and the axis labels.
+const LEFT: i64 = 70;
+const RIGHT: i64 = 20;
+const TOP: i64 = 40;
+const BOTTOM: i64 = 40;
+
+/// The fill colours of standard and daylight-saving time bands.
+const STANDARD_COLOUR: &'static str = "#8fb3d9";
+const DST_COLOUR: &'static str = "#f2a65a";
+
+/// The colours of the axes and of the other grid lines.
+const AXIS_COLOUR: &'static str = "#444";
+const GRID_COLOUR: &'static str = "#ddd";
+
+
+/// Writes an SVG timeline of the given timespan set, covering the years in
+/// the given range (including the start year, but not the end year).
+pub fn write_svg<W: Write>(w: &mut W, zone_name: &str, set: &FixedTimespanSet, years: Range<i64>) -> IOResult<()> {
+    let (start, end) = (year_start(years.start), year_start(years.end));
+    let spans = spans_between(set, start, end);
+
+    // The vertical scale goes to the whole hours either side of the
+    // offsets, and always includes UTC itself.
+    let lowest  = spans.iter().map(|s| s.2.total_offset()).min().unwrap_or(0).min(0);
+    let highest = spans.iter().map(|s| s.2.total_offset()).max().unwrap_or(0).max(0);
+    let lowest  = (lowest as f64 / 3600.0).floor() as i64 * 3600;
+    let highest = ((highest as f64 / 3600.0).ceil() as i64 * 3600).max(lowest + 3600);
+
+    let x = |t: i64| LEFT + (t - start) * (WIDTH - LEFT - RIGHT) / (end - start);
+    let y = |offset: i64| TOP + (highest - offset) * (HEIGHT - TOP - BOTTOM) / (highest - lowest);
+
+    try!(writeln!(w, r#"<svg xmlns="http://www.w3.org/2000/svg" width="{}" height="{}" viewBox="0 0 {} {}" font-family="sans-serif" font-size="12">"#,
+                  WIDTH, HEIGHT, WIDTH, HEIGHT));
+    try!(writeln!(w, r#"<text x="{}" y="{}" font-size="16">{}</text>"#, LEFT, TOP / 2 + 6, escape(zone_name)));
+
+    // Horizontal grid lines and labels, one per hour of offset.
+    let mut offset = lowest;
+    while offset <= highest {
+        try!(writeln!(w, r#"<line x1="{}" y1="{}" x2="{}" y2="{}" stroke="{}"/>"#,
+                      LEFT, y(offset), WIDTH - RIGHT, y(offset), if offset == 0 { AXIS_COLOUR } else { GRID_COLOUR }));
+        try!(writeln!(w, r#"<text x="{}" y="{}" text-anchor="end">{}</text>"#,
+                      LEFT - 6, y(offset) + 4, describe_offset(offset)));
+        offset += 3600;
+    }
+
+    // The bands themselves, drawn from UTC to each timespan’s offset.
+    for &(from, until, timespan) in &spans {
+        let (top, bottom) = (y(timespan.total_offset().max(0)), y(timespan.total_offset().min(0)));
+        try!(writeln!(w, r#"<rect x="{}" y="{}" width="{}" height="{}" fill="{}"><title>{} ({})</title></rect>"#,
+                      x(from), top, (x(until) - x(from)).max(1), (bottom - top).max(1),
+                      if timespan.is_dst { DST_COLOUR } else { STANDARD_COLOUR },
+                      escape(&timespan.name), describe_offset(timespan.total_offset())));
+    }
+
+    // Year labels along the bottom, spaced so there are no more than ten.
+    let step = year_step(years.end - years.start);
+    let mut year = (years.start + step - 1) / step * step;
+    while year < years.end {
+        let at = x(year_start(year));
+        try!(writeln!(w, r#"<line x1="{}" y1="{}" x2="{}" y2="{}" stroke="{}"/>"#, at, HEIGHT - BOTTOM, at, HEIGHT - BOTTOM + 5, AXIS_COLOUR));
+        try!(writeln!(w, r#"<text x="{}" y="{}" text-anchor="middle">{}</text>"#, at, HEIGHT - BOTTOM + 20, year));
+        year += step;
+    }
+
+    writeln!(w, "</svg>")
+}
+
+/// Returns each timespan in effect between the two instants, along with
+/// when it starts and ends, clamped to those instants.
+fn spans_between(set: &FixedTimespanSet, start: i64, end: i64) -> Vec<(i64, i64, &FixedTimespan)> {
+    let mut spans = Vec::new();
+    let mut from = start;
+    let mut current = &set.first;
+
+    for &(at, ref timespan) in &set.rest {
+        if at >= end {
+            break;
+        }
+        else if at > from {
+            spans.push((from, at, current));
+            from = at;
+        }
+
+        current = timespan;
+    }
+
+    spans.push((from, end, current));
+    spans
+}
+
+/// Returns the Unix timestamp at the start of the given year.
+fn year_start(year: i64) -> i64 {
+    LocalDateTime::new(LocalDate::ymd(year, Month::January, 1).unwrap(), LocalTime::midnight()).to_instant().seconds()
+}
+
+/// Picks a round number of years between labels, so that a range of the
+/// given length gets no more than ten of them.
+fn year_step(years: i64) -> i64 {
+    for &step in &[ 1, 2, 5, 10, 20, 25, 50, 100 ] {
+        if years <= step * 10 {
+            return step;
+        }
+    }
+
+    (years / 1000 + 1) * 100
+}
+
+/// Formats a UTC offset, such as “UTC+5:30” or just “UTC”.
+fn describe_offset(seconds: i64) -> String {
+    if seconds == 0 {
+        return "UTC".to_owned();
+    }
+
+    let sign = if seconds < 0 { '-' } else { '+' };
+    let seconds = seconds.abs();
+
+    match (seconds / 3600, seconds % 3600 / 60) {
+        (h, 0) => format!("UTC{}{}", sign, h),
+        (h, m) => format!("UTC{}{}:{:02}", sign, h, m),
+    }
+}
+
+/// Escapes the characters that have special meanings in XML text.
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;")
+}
+
+
+/// The options the `timeline` mode takes.
+pub fn options() -> getopts::Options {
+    let mut opts = getopts::Options::new();
+    opts.reqopt("o", "output", "file to write the SVG to", "FILE");
+    opts.optopt("", "from", "first year to draw (default: 1900)", "YEAR");
+    opts.optopt("", "until", "year to stop drawing at (default: 2040)", "YEAR");
+    opts
+}
+
+/// Runs the `timeline` mode of the builder with the given arguments, which
+/// come after the word `timeline`. The first free argument is the zone to
+/// draw, and the rest are the input files.
+pub fn run<F: Filesystem>(args: &[::std::ffi::OsString], fs: &F) -> Result<Report, CliError> {
+    let command = commands::find("timeline").unwrap();
+    let matches = try!(command.parse(args));
+    let (zone_name, inputs) = match matches.free.split_first() {
+        Some((zone, inputs)) => (zone, inputs),
+        None                 => return Err(command.missing("ZONE")),
+    };
+
+    let from  = try!(number_option(&matches, "from", 1900));
+    let until = try!(number_option(&matches, "until", 2040));
+    if until <= from {
+        return Err(CliError::InvalidArgument("until", until.to_string()));
+    }
+
+    // Rules have to be expanded over every year that gets drawn.
+    let defaults = TransitionOptions::default();
+    let options = TransitionOptions {
+        min_year: defaults.min_year.min(from),
+        max_year: defaults.max_year.max(until),
+        .. defaults
+    };
+
+    let (table, warnings) = try!(read_table(fs, inputs));
+    let set = match table.timespans(zone_name, &options) {
+        Some(s) => s,
+        None    => return Err(CliError::UnknownZone(zone_name.clone())),
+    };
+
+    let mut w = BufWriter::new(try!(fs.create(Path::new(&matches.opt_str("output").unwrap()))));
+    try!(write_svg(&mut w, zone_name, &set, from .. until));
+
+    Ok(Report {
+        zone_count: 1,
+        link_count: if table.links.contains_key(zone_name) { 1 } else { 0 },
+        warnings:   warnings,
+        output:     String::new(),
+    })
+}
+
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use zoneinfo_parse::transitions::{FixedTimespan, FixedTimespanSet};
+
+    #[test]
+    fn bands() {
+        let set = FixedTimespanSet {
+            first: FixedTimespan::new(-75, 0, "LMT"),
+            rest:  vec![
+                (-3_852_662_325, FixedTimespan::new(0, 0,    "GMT")),
+                (-1_691_964_000, FixedTimespan::new(0, 3600, "BST")),
+                (-1_680_472_800, FixedTimespan::new(0, 0,    "GMT")),
+            ],
+        };
+
+        let mut buf = Vec::new();
+        write_svg(&mut buf, "Europe/London", &set, 1900 .. 1920).unwrap();
+        let svg = String::from_utf8(buf).unwrap();
+
+        // LMT ended before 1900, so only the other three get drawn.
+        assert_eq!(svg.matches("<rect").count(), 3);
+        assert_eq!(svg.matches(DST_COLOUR).count(), 1);
+        assert!(svg.contains("<title>BST (UTC+1)</title>"));
+        assert!(svg.contains(">1910</text>"));
+        assert!(svg.ends_with("</svg>\n"));
+    }
+
+    #[test]
+    fn offsets() {
+        assert_eq!(describe_offset(0), "UTC");
+        assert_eq!(describe_offset(19800), "UTC+5:30");
+        assert_eq!(describe_offset(-18000), "UTC-5");
+    }
+
+    #[test]
+    fn steps() {
+        assert_eq!(year_step(20), 2);
+        assert_eq!(year_step(140), 20);
+    }
+}
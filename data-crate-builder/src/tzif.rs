@@ -0,0 +1,142 @@
+//! Writing zones as compiled TZif files, the binary format read by the C
+//! library and most other time zone implementations.
+//!
+//! This writes version 2 of the format, as described in RFC 8536. As with
+//! `zic -b slim`, the version 1 data block is left as small as possible, as
+//! only very old readers use it: every transition goes in the 64-bit block
+//! that follows it.
+//!
+//! The footer, which normally has a POSIX TZ string describing the zone’s
+//! rules after its last transition, is left empty, as the timespans don’t
+//! record the rules they came from. Readers use the last timespan for every
+//! time after the last transition, so make sure the transitions extend far
+//! enough into the future with `--max-year`.
+
+use std::io::Write;
+use std::io::Result as IOResult;
+
+use zoneinfo_parse::transitions::{FixedTimespan, FixedTimespanSet};
+
+
+/// Writes the given timespan set as a TZif file.
+pub fn write_tzif<W: Write>(w: &mut W, set: &FixedTimespanSet) -> IOResult<()> {
+
+    // Each distinct combination of offset, DST flag, and abbreviation gets
+    // one local time type. The first timespan has to be type 0, as that’s
+    // the one readers use for times before the first transition.
+    let mut types: Vec<&FixedTimespan> = vec![ &set.first ];
+    let mut transition_types = Vec::new();
+
+    for &(_, ref timespan) in &set.rest {
+        let index = match types.iter().position(|t| same_type(t, timespan)) {
+            Some(index) => index,
+            None        => { types.push(timespan); types.len() - 1 },
+        };
+
+        transition_types.push(index as u8);
+    }
+
+    // The abbreviations are stored together, each one terminated with a
+    // NUL byte, and the types refer to them by their starting index.
+    let mut chars: Vec<u8> = Vec::new();
+    let mut abbreviation_indices = Vec::new();
+    for t in &types {
+        let mut abbreviation = t.name.as_bytes().to_vec();
+        abbreviation.push(0);
+
+        let index = match chars.windows(abbreviation.len()).position(|window| window == &abbreviation[..]) {
+            Some(index) => index,
+            None        => { let index = chars.len(); chars.extend(abbreviation); index },
+        };
+
+        abbreviation_indices.push(index as u8);
+    }
+
+    // The version 1 header and data block, with no transitions and a
+    // single placeholder type.
+    try!(write_header(w, 0, 1, 1));
+    try!(write_i32(w, 0));
+    try!(w.write_all(&[ 0, 0, 0 ]));
+
+    // The version 2 header and data block.
+    try!(write_header(w, set.rest.len(), types.len(), chars.len()));
+
+    for &(at, _) in &set.rest {
+        try!(write_i64(w, at));
+    }
+
+    try!(w.write_all(&transition_types));
+
+    for (t, &index) in types.iter().zip(abbreviation_indices.iter()) {
+        try!(write_i32(w, t.total_offset() as i32));
+        try!(w.write_all(&[ if t.is_dst { 1 } else { 0 }, index ]));
+    }
+
+    try!(w.write_all(&chars));
+
+    // The footer, with an empty TZ string.
+    w.write_all(b"\n\n")
+}
+
+/// Writes a TZif header with the given numbers of transitions, local time
+/// types, and bytes of abbreviations. There are never any leap seconds, or
+/// standard/wall or UT/local indicators.
+fn write_header<W: Write>(w: &mut W, transitions: usize, types: usize, chars: usize) -> IOResult<()> {
+    try!(w.write_all(b"TZif2"));
+    try!(w.write_all(&[0; 15]));
+
+    for &count in &[ 0, 0, 0, transitions, types, chars ] {
+        try!(write_i32(w, count as i32));
+    }
+
+    Ok(())
+}
+
+/// Returns whether two timespans would have the same local time type.
+fn same_type(a: &FixedTimespan, b: &FixedTimespan) -> bool {
+    a.total_offset() == b.total_offset() && a.is_dst == b.is_dst && a.name == b.name
+}
+
+/// Writes a 32-bit integer in big-endian order.
+fn write_i32<W: Write>(w: &mut W, value: i32) -> IOResult<()> {
+    w.write_all(&[ (value >> 24) as u8, (value >> 16) as u8, (value >> 8) as u8, value as u8 ])
+}
+
+/// Writes a 64-bit integer in big-endian order.
+fn write_i64<W: Write>(w: &mut W, value: i64) -> IOResult<()> {
+    try!(write_i32(w, (value >> 32) as i32));
+    write_i32(w, value as i32)
+}
+
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use zoneinfo_parse::transitions::{FixedTimespan, FixedTimespanSet};
+
+    #[test]
+    fn layout() {
+        let set = FixedTimespanSet {
+            first: FixedTimespan::new(0, 0, "GMT"),
+            rest:  vec![
+                (1_711_846_800, FixedTimespan::new(0, 3600, "BST")),
+                (1_729_990_800, FixedTimespan::new(0, 0,    "GMT")),
+            ],
+        };
+
+        let mut buf = Vec::new();
+        write_tzif(&mut buf, &set).unwrap();
+
+        // The version 1 block is a header, one 6-byte type, and one byte
+        // of abbreviations. The version 2 block has a header, two 9-byte
+        // transitions, two 6-byte types, and eight bytes of abbreviations,
+        // and then there’s the two-byte footer.
+        assert_eq!(buf.len(), 51 + 44 + 18 + 12 + 8 + 2);
+        assert_eq!(&buf[0..5], b"TZif2");
+        assert_eq!(&buf[51..56], b"TZif2");
+
+        // The second transition goes back to type 0.
+        assert_eq!(&buf[95 + 16 .. 95 + 18], &[ 1, 0 ]);
+        assert_eq!(&buf[buf.len() - 10 ..], b"GMT\0BST\0\n\n");
+    }
+}
@@ -0,0 +1,157 @@
+//! Oddities in the input that are worth mentioning, but not bad enough to
+//! stop the build.
+//!
+//! Unlike a `CliError`, a warning doesn’t stop anything from being
+//! generated: the warnings found while reading the input get returned
+//! alongside the result, in the `Report`, for the caller to show or ignore
+//! as it sees fit.
+
+use std::fmt;
+
+
+/// Something odd about the input that didn’t stop it being used.
+#[derive(PartialEq, Debug, Clone)]
+pub enum Warning {
+
+    /// A rule line had something other than `-` in its TYPE column. This
+    /// column used to name a type of year for the rule to apply in, but it’s
+    /// long obsolete, so whatever was there got ignored.
+    IgnoredType {
+
+        /// The filename that contained the line.
+        filename: String,
+
+        /// The number of the line.
+        line: usize,
+
+        /// What was in the TYPE column.
+        value: String,
+    },
+
+    /// A link line was exactly the same as an earlier one, so it got
+    /// skipped. (A link with the same name as an earlier one but a
+    /// different target is still an error.)
+    DuplicateLink {
+
+        /// The filename that contained the line.
+        filename: String,
+
+        /// The number of the line.
+        line: usize,
+
+        /// The name of the link.
+        name: String,
+    },
+
+    /// A line had bytes that aren’t valid UTF-8, which got replaced with
+    /// U+FFFD REPLACEMENT CHARACTER before the line was parsed. This is
+    /// harmless when they’re in a comment, as they tend to be.
+    InvalidUtf8 {
+
+        /// The filename that contained the line.
+        filename: String,
+
+        /// The number of the line.
+        line: usize,
+    },
+
+    /// A line couldn’t be parsed or added to the table, but errors were
+    /// being skipped, so it got left out.
+    SkippedLine {
+
+        /// The filename that contained the line.
+        filename: String,
+
+        /// The number of the line.
+        line: usize,
+
+        /// The column of the field that was wrong, counting from 1, if
+        /// it’s known.
+        column: Option<usize>,
+
+        /// A human-readable description of what was wrong with it.
+        error: String,
+    },
+
+    /// A zone uses an abbreviation that `zic` would warn about: one shorter
+    /// than three characters or longer than six, or with characters other
+    /// than letters, digits, `+`, or `-`.
+    SuspiciousAbbreviation {
+
+        /// The name of the zone.
+        zone: String,
+
+        /// The abbreviation itself.
+        abbreviation: String,
+    },
+}
+
+impl Warning {
+
+    /// Returns the filename and line number this warning is about, if it’s
+    /// about a particular line.
+    pub fn location(&self) -> Option<(&str, usize)> {
+        match *self {
+            Warning::IgnoredType { ref filename, line, .. }    => Some((filename, line)),
+            Warning::DuplicateLink { ref filename, line, .. }  => Some((filename, line)),
+            Warning::InvalidUtf8 { ref filename, line }        => Some((filename, line)),
+            Warning::SkippedLine { ref filename, line, .. }    => Some((filename, line)),
+            Warning::SuspiciousAbbreviation { .. }             => None,
+        }
+    }
+
+    /// Returns the column this warning is about, counting from 1, if it’s
+    /// about a particular place in a line.
+    pub fn column(&self) -> Option<usize> {
+        match *self {
+            Warning::SkippedLine { column, .. }  => column,
+            _                                    => None,
+        }
+    }
+
+    /// Returns what this warning is about, without its location.
+    pub fn message(&self) -> String {
+        match *self {
+            Warning::IgnoredType { ref value, .. }                          => format!("ignoring obsolete rule type {:?}", value),
+            Warning::DuplicateLink { ref name, .. }                         => format!("skipping duplicate link {:?}", name),
+            Warning::InvalidUtf8 { .. }                                     => "replacing bytes that aren’t valid UTF-8".to_owned(),
+            Warning::SkippedLine { ref error, .. }                          => format!("skipping line: {}", error),
+            Warning::SuspiciousAbbreviation { ref zone, ref abbreviation }  => format!("zone {} uses suspicious abbreviation {:?}", zone, abbreviation),
+        }
+    }
+}
+
+impl fmt::Display for Warning {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match (self.location(), self.column()) {
+            (Some((filename, line)), Some(column))  => write!(f, "{}:{}:{}: {}", filename, line, column, self.message()),
+            (Some((filename, line)), None)          => write!(f, "{}:{}: {}", filename, line, self.message()),
+            (None, _)                               => write!(f, "{}", self.message()),
+        }
+    }
+}
+
+
+/// Returns whether `zic` would warn about the given time zone abbreviation.
+pub fn is_suspicious_abbreviation(abbreviation: &str) -> bool {
+    let length = abbreviation.chars().count();
+
+    length < 3 || length > 6
+        || !abbreviation.chars().all(|c| (c.is_alphanumeric() && (c as u32) < 128) || c == '+' || c == '-')
+}
+
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn abbreviations() {
+        assert!(!is_suspicious_abbreviation("GMT"));
+        assert!(!is_suspicious_abbreviation("+0530"));
+        assert!(!is_suspicious_abbreviation("-00"));
+        assert!(is_suspicious_abbreviation("Z"));
+        assert!(is_suspicious_abbreviation("CHOST+1"));
+        assert!(is_suspicious_abbreviation("M/T"));
+    }
+}
@@ -0,0 +1,119 @@
+//! Writing a dataset back out as zoneinfo source, in a single file like the
+//! `tzdata.zi` file that comes with the tz database.
+//!
+//! The rules the timespans were computed from aren’t kept, so instead each
+//! zone gets written with one line per timespan, giving its offsets and
+//! abbreviation directly, and lasting until the next transition:
+//!
+//! ```text
+//! Zone	Europe/London	-0:01:15	-	LMT	1847 Dec 1 0:00
+//! 		0:00	-	GMT	1916 May 21 2:00
+//! 		0:00	1:00	BST	1916 Oct 1 3:00
+//! ```
+//!
+//! The “until” times are wall-clock times, so they mean the same thing to
+//! `zic` as they do to this crate. Running the file through `zic` produces
+//! the same transitions as the original source, over the range of years
+//! the dataset was computed for.
+
+use std::io::Write;
+use std::io::Result as IOResult;
+
+use datetime::{LocalDateTime, DatePiece, TimePiece};
+
+use zoneinfo_parse::transitions::{FixedTimespan, FixedTimespanSet};
+
+use data_crate::Dataset;
+
+
+/// Writes every zone and link in the dataset as zoneinfo source.
+pub fn write_zi<W: Write>(w: &mut W, dataset: &Dataset) -> IOResult<()> {
+    try!(writeln!(w, "# This file is autogenerated, with every rule expanded into fixed offsets."));
+
+    for (name, set) in &dataset.zonesets {
+        try!(write_zone(w, name, set));
+    }
+
+    for (name, target) in &dataset.links {
+        try!(writeln!(w, "Link\t{}\t{}", target, name));
+    }
+
+    Ok(())
+}
+
+/// Writes one zone, with a line for each of its timespans.
+fn write_zone<W: Write>(w: &mut W, name: &str, set: &FixedTimespanSet) -> IOResult<()> {
+    let mut timespan = &set.first;
+    try!(write!(w, "Zone\t{}\t", name));
+
+    for &(at, ref next) in &set.rest {
+        try!(write_line(w, timespan));
+
+        // The zone line ends at the wall-clock time of the transition, as
+        // seen from before it.
+        let until = LocalDateTime::at(at + timespan.total_offset());
+        try!(writeln!(w, "\t{} {} {} {}", until.year(), MONTHS[until.month().months_from_january()], until.day(),
+                                          describe_time(until.hour() as i64 * 3600 + until.minute() as i64 * 60 + until.second() as i64)));
+        try!(write!(w, "\t\t"));
+        timespan = next;
+    }
+
+    try!(write_line(w, timespan));
+    writeln!(w, "")
+}
+
+/// Writes the offset, saving, and abbreviation columns for a timespan.
+fn write_line<W: Write>(w: &mut W, timespan: &FixedTimespan) -> IOResult<()> {
+    let saving = match timespan.dst_offset {
+        0 => "-".to_owned(),
+        s => describe_time(s),
+    };
+
+    write!(w, "{}\t{}\t{}", describe_time(timespan.utc_offset), saving, timespan.name)
+}
+
+/// Formats a number of seconds as hours, minutes, and seconds, leaving off
+/// the seconds when there are none, such as “5:30” or “-0:01:15”.
+fn describe_time(seconds: i64) -> String {
+    let sign = if seconds < 0 { "-" } else { "" };
+    let seconds = seconds.abs();
+
+    match seconds % 60 {
+        0 => format!("{}{}:{:02}", sign, seconds / 3600, seconds % 3600 / 60),
+        s => format!("{}{}:{:02}:{:02}", sign, seconds / 3600, seconds % 3600 / 60, s),
+    }
+}
+
+/// The month abbreviations that zoneinfo source uses.
+const MONTHS: [&'static str; 12] = [ "Jan", "Feb", "Mar", "Apr", "May", "Jun",
+                                     "Jul", "Aug", "Sep", "Oct", "Nov", "Dec" ];
+
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use data_crate::Dataset;
+    use zoneinfo_parse::transitions::{FixedTimespan, FixedTimespanSet};
+
+    #[test]
+    fn zones_and_links() {
+        let mut dataset = Dataset::default();
+        let _ = dataset.zonesets.insert("Europe/London".to_owned(), FixedTimespanSet {
+            first: FixedTimespan::new(-75, 0, "LMT"),
+            rest:  vec![
+                (-3_852_662_325, FixedTimespan::new(0, 0,    "GMT")),
+                (-1_691_964_000, FixedTimespan::new(0, 3600, "BST")),
+            ],
+        });
+        let _ = dataset.links.insert("GB".to_owned(), "Europe/London".to_owned());
+
+        let mut buf = Vec::new();
+        write_zi(&mut buf, &dataset).unwrap();
+        assert_eq!(String::from_utf8(buf).unwrap().lines().skip(1).collect::<Vec<_>>(), vec![
+            "Zone\tEurope/London\t-0:01:15\t-\tLMT\t1847 Dec 1 0:00",
+            "\t\t0:00\t-\tGMT\t1916 May 21 2:00",
+            "\t\t0:00\t1:00\tBST",
+            "Link\tEurope/London\tGB",
+        ]);
+    }
+}
@@ -0,0 +1,1381 @@
+extern crate build_data_crate;
+extern crate zoneinfo_parse;
+
+use std::cell::RefCell;
+use std::env::temp_dir;
+use std::fs::{File, create_dir_all, metadata, remove_dir_all};
+use std::io::{Cursor, Read, Write};
+use std::io::Result as IOResult;
+use std::path::{Path, PathBuf};
+
+use build_data_crate::{run, run_with, capabilities, Report, CliError, GenerateOptions};
+use build_data_crate::backend::CodegenBackend;
+use build_data_crate::build_script::{generate_with, ZoneFilter};
+use build_data_crate::commands::COMMANDS;
+use build_data_crate::data_crate::{DataCrate, CodegenOptions, Dataset, Format, Options};
+use build_data_crate::diagnostics::ErrorFormat;
+use build_data_crate::output::{MemorySink, OutputSink};
+use build_data_crate::system::{FixedClock, Filesystem, MemoryFilesystem, MemoryFile};
+use build_data_crate::warnings::Warning;
+use build_data_crate::errors::{ParseError, EXIT_DATA_ERROR, EXIT_IO_ERROR, EXIT_USAGE};
+
+use zoneinfo_parse::transitions::FixedTimespanSet;
+
+
+/// Creates a fresh, empty directory for one test to write into.
+fn scratch_dir(name: &str) -> PathBuf {
+    let path = temp_dir().join(format!("build-data-crate-{}", name));
+    let _ = remove_dir_all(&path);
+    create_dir_all(&path).unwrap();
+    path
+}
+
+/// Writes a zoneinfo file with the given contents into the directory.
+fn write_input(dir: &PathBuf, contents: &str) -> String {
+    let path = dir.join("input");
+    let mut f = File::create(&path).unwrap();
+    f.write_all(contents.as_bytes()).unwrap();
+    path.to_str().unwrap().to_owned()
+}
+
+
+#[test]
+fn missing_output() {
+    let err = run(Vec::<String>::new()).unwrap_err();
+    assert_eq!(err.exit_code(), EXIT_USAGE);
+}
+
+#[test]
+fn missing_input_file() {
+    let dir = scratch_dir("missing-input");
+    let output = dir.to_str().unwrap();
+
+    let err = run(vec![ "--output", output, "/this/file/does/not/exist" ]).unwrap_err();
+    assert_eq!(err.exit_code(), EXIT_IO_ERROR);
+    assert!(err.to_string().starts_with("IO error: /this/file/does/not/exist: "));
+}
+
+#[test]
+fn unparseable_input() {
+    let dir = scratch_dir("unparseable-input");
+    let input = write_input(&dir, "Zone  Nowhere/Land  what is this\n");
+    let output = dir.to_str().unwrap();
+
+    let err = run(vec![ "--output", output, &*input ]).unwrap_err();
+    assert_eq!(err.exit_code(), EXIT_DATA_ERROR);
+}
+
+#[test]
+fn errors_by_file() {
+    let dir = scratch_dir("errors-by-file");
+    let first = write_input(&dir, "Zone  Nowhere/Land  what is this\n\nLink  nowhere\n");
+    let second = dir.join("second");
+    File::create(&second).unwrap().write_all(b"Rule  who knows\n").unwrap();
+    let output = dir.to_str().unwrap();
+
+    let errs = match run(vec![ "--output", output, &*first, second.to_str().unwrap() ]) {
+        Err(CliError::Errors(errs)) => errs,
+        otherwise => panic!("Expected parse errors, got {:?}", otherwise),
+    };
+
+    assert_eq!(errs.len(), 3);
+    assert_eq!(errs.iter().map(|e| e.line).collect::<Vec<_>>(), vec![ 1, 3, 1 ]);
+
+    let files = errs.by_file();
+    assert_eq!(files[&*first].len(), 2);
+    assert_eq!(files[second.to_str().unwrap()].len(), 1);
+
+    let errs: Vec<ParseError> = errs.into();
+    assert_eq!(errs[2].filename, second.to_str().unwrap());
+}
+
+#[test]
+fn errors_across_files() {
+    let fs = MemoryFilesystem::new();
+    fs.add_file("tz/first", "Rule  Test  2000  only  -  Mar  lastSun  1:00u  1:00  S\n\
+                             Zone  Test/Zone  0:00  Test  TE%sT\n\
+                             Zone  Test/Late  0:00  Late  TE%sT\n\
+                             Link  Test/Zone  Test/Link\n");
+    fs.add_file("tz/second", "Link  Test/Zone  Test/Link\n\
+                              Zone  Test/Zone  1:00  -  TST\n\
+                              what is this\n\
+                              Zone  Test/Other  0:00  Test  TE%sT\n\
+                              Link  Test/Other  Test/Link\n\
+                              Rule  Late  2000  only  -  Mar  lastSun  1:00u  1:00  S\n");
+    fs.add_dir("out");
+
+    // Each file gets parsed separately, but rules from earlier files can
+    // still be used, and the errors come out in order, as if they’d been
+    // parsed one after another.
+    let args = vec![ "--output", "out", "tz/first", "tz/second" ];
+    let errs: Vec<ParseError> = match run_with(args, &FixedClock(0), &fs) {
+        Err(CliError::Errors(errs)) => errs.into(),
+        otherwise => panic!("Expected parse errors, got {:?}", otherwise),
+    };
+
+    let lines: Vec<(&str, usize)> = errs.iter().map(|e| (&*e.filename, e.line)).collect();
+    assert_eq!(lines, vec![ ("tz/first", 3), ("tz/second", 2), ("tz/second", 3), ("tz/second", 5) ]);
+    assert!(errs[0].error.contains("unknown ruleset \"Late\""));
+    assert!(errs[3].error.contains("duplicate link \"Test/Link\""));
+
+    // A link repeated exactly in a later file is only a warning.
+    let fs = MemoryFilesystem::new();
+    fs.add_file("tz/first", "Zone  Test/Zone  0:00  -  TST\nLink  Test/Zone  Test/Link\n");
+    fs.add_file("tz/second", "Link  Test/Zone  Test/Link\n");
+    fs.add_dir("out");
+
+    let report = run_with(vec![ "--output", "out", "tz/first", "tz/second" ], &FixedClock(0), &fs).unwrap();
+    assert_eq!(report.warnings, vec![
+        Warning::DuplicateLink { filename: "tz/second".to_owned(), line: 1, name: "Test/Link".to_owned() },
+    ]);
+}
+
+#[test]
+fn invalid_year() {
+    let dir = scratch_dir("invalid-year");
+    let input = write_input(&dir, "Zone  Test/Zone  1:00  -  TST\n");
+    let output = dir.to_str().unwrap();
+
+    let err = run(vec![ "--output", output, "--max-year", "soon", &*input ]).unwrap_err();
+    assert_eq!(err.exit_code(), EXIT_USAGE);
+}
+
+#[test]
+fn failed_validation() {
+    let dir = scratch_dir("failed-validation");
+    let input = write_input(&dir, "Rule  Unused  1980  only  -  Jan  1  0:00  0  -\nZone  Test/Zone  1:00  -  TST\n");
+    let output = dir.to_str().unwrap();
+
+    let err = run(vec![ "--output", output, "--validate", &*input ]).unwrap_err();
+    assert_eq!(err.exit_code(), EXIT_DATA_ERROR);
+    assert_eq!(err.to_string(), "ruleset Unused is never used\n");
+    assert!(!dir.join("mod.rs").exists());
+}
+
+#[test]
+fn successful_run() {
+    let dir = scratch_dir("successful-run");
+    let input = write_input(&dir, "Zone  Test/Zone  1:00  -  TST\nLink  Test/Zone  Test/Link\n");
+    let output = dir.to_str().unwrap();
+
+    let report = run(vec![ "--output", output, &*input ]).unwrap();
+    assert_eq!(report, Report { zone_count: 2, link_count: 1, warnings: vec![], output: String::new() });
+    assert!(dir.join("mod.rs").is_file());
+    assert!(dir.join("Test").join("Zone.rs").is_file());
+}
+
+#[test]
+fn source_directory() {
+    let dir = scratch_dir("source-directory");
+    let tz = dir.join("tz");
+    create_dir_all(&tz).unwrap();
+    File::create(tz.join("europe")).unwrap().write_all(b"Zone  Test/Zone  1:00  -  TST\n").unwrap();
+    File::create(tz.join("backward")).unwrap().write_all(b"Link  Test/Zone  Test/Link\n").unwrap();
+    File::create(tz.join("Makefile")).unwrap().write_all(b"all: nothing to see here\n").unwrap();
+
+    let output = dir.join("output");
+    create_dir_all(&output).unwrap();
+
+    let report = run(vec![ "--output", output.to_str().unwrap(), tz.to_str().unwrap() ]).unwrap();
+    assert_eq!(report, Report { zone_count: 2, link_count: 1, warnings: vec![], output: String::new() });
+
+    // A directory without any source files in is an error.
+    let err = run(vec![ "--output", output.to_str().unwrap(), output.to_str().unwrap() ]).unwrap_err();
+    assert_eq!(err.exit_code(), EXIT_IO_ERROR);
+}
+
+#[test]
+fn warnings() {
+    let dir = scratch_dir("warnings");
+    let input = write_input(&dir, "Rule  Test  2000  only  odd  Mar  lastSun  1:00u  1:00  S\n\
+                                   Rule  Test  2000  only  -    Oct  lastSun  1:00u  0     -\n\
+                                   Zone  Test/Zone  0:00  Test  TE%sT\n\
+                                   Link  Test/Zone  Test/Link\n\
+                                   Link  Test/Zone  Test/Link\n\
+                                   Zone  Test/Other  0:00  -  Z\n");
+    let output = dir.join("output");
+    create_dir_all(&output).unwrap();
+
+    let report = run(vec![ "--output", output.to_str().unwrap(), &*input ]).unwrap();
+    assert_eq!(report.warnings, vec![
+        Warning::IgnoredType { filename: input.clone(), line: 1, value: "odd".to_owned() },
+        Warning::DuplicateLink { filename: input.clone(), line: 5, name: "Test/Link".to_owned() },
+        Warning::SuspiciousAbbreviation { zone: "Test/Other".to_owned(), abbreviation: "Z".to_owned() },
+    ]);
+}
+
+#[test]
+fn invalid_utf8() {
+    let dir = scratch_dir("invalid-utf8");
+    let input = dir.join("input");
+    File::create(&input).unwrap().write_all(b"# Ren\xe9 wrote this\r\nZone  Test/Zone  1:00  -  TST  # caf\xe9\r\n").unwrap();
+    let input = input.to_str().unwrap().to_owned();
+    let output = dir.join("output");
+    create_dir_all(&output).unwrap();
+
+    let report = run(vec![ "--output", output.to_str().unwrap(), &*input ]).unwrap();
+    assert_eq!(report.zone_count, 1);
+    assert_eq!(report.warnings, vec![
+        Warning::InvalidUtf8 { filename: input.clone(), line: 1 },
+        Warning::InvalidUtf8 { filename: input.clone(), line: 2 },
+    ]);
+}
+
+#[test]
+fn windows_text() {
+    let fs = MemoryFilesystem::new();
+    fs.add_file("tz/europe", "\u{feff}Zone\u{a0}Test/Zone  1:00  -  TST\r\r\n# Made\u{a0}up\r\nLink  Test/Zone  Test/Link\r\n");
+    fs.add_dir("out");
+
+    let report = run_with(vec![ "--output", "out", "tz" ], &FixedClock(0), &fs).unwrap();
+    assert_eq!(report, Report { zone_count: 2, link_count: 1, warnings: vec![], output: String::new() });
+}
+
+#[test]
+fn successful_run_in_memory() {
+    let fs = MemoryFilesystem::new();
+    fs.add_file("tz/europe", "Zone  Test/Zone  1:00  -  TST\nZone  Test/Sub/Zone  2:00  -  SST\n");
+    fs.add_dir("out");
+
+    let report = run_with(vec![ "--output", "out", "tz" ], &FixedClock(0), &fs).unwrap();
+    assert_eq!(report, Report { zone_count: 2, link_count: 0, warnings: vec![], output: String::new() });
+    assert!(fs.contents("out/mod.rs").unwrap().contains("pub mod Test;"));
+    assert!(fs.contents("out/Test/Sub/Zone.rs").unwrap().contains("name: \"Test/Sub/Zone\","));
+
+    // Nothing gets written if the output directory doesn’t exist.
+    let fs = MemoryFilesystem::new();
+    fs.add_file("tz/europe", "Zone  Test/Zone  1:00  -  TST\n");
+    let err = run_with(vec![ "--output", "out", "tz" ], &FixedClock(0), &fs).unwrap_err();
+    assert_eq!(err.exit_code(), EXIT_IO_ERROR);
+}
+
+#[test]
+fn output_sink() {
+    let fs = MemoryFilesystem::new();
+    fs.add_file("tz/europe", "Zone  Test/Zone  1:00  -  TST\nZone  Test/Sub/Zone  2:00  -  SST\n");
+
+    // The output directory isn’t needed when writing to a sink.
+    let (data_crate, _) = DataCrate::new(&fs, "out", &[ "tz/europe".to_owned() ], &Options::default()).unwrap();
+    let mut sink = MemorySink::new();
+    data_crate.write_to(&mut sink, Format::Rust, &CodegenOptions::default()).unwrap();
+
+    let paths: Vec<&Path> = sink.files().keys().map(|p| &**p).collect();
+    assert_eq!(paths, vec![ Path::new("Test/Sub/Zone.rs"), Path::new("Test/Sub/mod.rs"), Path::new("Test/Zone.rs"),
+                            Path::new("Test/mod.rs"), Path::new("mod.rs") ]);
+    assert!(sink.contents("Test/Sub/Zone.rs").unwrap().contains("name: \"Test/Sub/Zone\","));
+    assert_eq!(fs.paths(), vec![ PathBuf::from("tz"), PathBuf::from("tz/europe") ]);
+}
+
+/// A backend that lists what it was given, rather than writing anything.
+#[derive(Default)]
+struct RecordingBackend {
+    zones: Vec<(String, usize)>,
+    index_names: Vec<String>,
+}
+
+impl CodegenBackend for RecordingBackend {
+    fn emit_zone<S: OutputSink>(&mut self, _sink: &mut S, name: &str, set: &FixedTimespanSet) -> IOResult<()> {
+        self.zones.push((name.to_owned(), set.rest.len()));
+        Ok(())
+    }
+
+    fn emit_index<S: OutputSink>(&mut self, sink: &mut S, dataset: &Dataset) -> IOResult<()> {
+        self.index_names = dataset.names().iter().map(|n| n.to_string()).collect();
+        let mut w = try!(sink.file(Path::new("index.txt")));
+        try!(write!(w, "{}", self.index_names.join("\n")));
+        sink.finish(w)
+    }
+}
+
+#[test]
+fn custom_backend() {
+    let fs = MemoryFilesystem::new();
+    fs.add_file("tz/europe", "Zone  Test/Zone  1:00  -  TST  2000\n  2:00  -  SST\nLink  Test/Zone  Test/Alias\n");
+
+    let (data_crate, _) = DataCrate::new(&fs, "out", &[ "tz/europe".to_owned() ], &Options::default()).unwrap();
+    let mut sink = MemorySink::new();
+    let mut backend = RecordingBackend::default();
+    data_crate.write_with(&mut sink, &mut backend).unwrap();
+
+    // Links get emitted too, with the timespans of the zone they point to.
+    assert_eq!(backend.zones, vec![ ("Test/Alias".to_owned(), 1), ("Test/Zone".to_owned(), 1) ]);
+    assert_eq!(backend.index_names, vec![ "Test/Alias", "Test/Zone" ]);
+    assert_eq!(sink.contents("index.txt"), Some("Test/Alias\nTest/Zone"));
+}
+
+#[test]
+fn standard_input() {
+    let fs = MemoryFilesystem::new();
+    fs.add_file("backward", "Link  Test/Zone  Test/Link\n");
+    fs.set_stdin("Zone  Test/Zone  1:00  -  TST\n");
+    fs.add_dir("out");
+
+    let report = run_with(vec![ "--output", "out", "-", "backward" ], &FixedClock(0), &fs).unwrap();
+    assert_eq!(report, Report { zone_count: 2, link_count: 1, warnings: vec![], output: String::new() });
+
+    // Errors in standard input say where they came from.
+    fs.set_stdin("Zone  Nowhere/Land  what is this\n");
+    match run_with(vec![ "--output", "out", "-" ], &FixedClock(0), &fs) {
+        Err(CliError::Errors(errors)) => assert_eq!(errors.iter().next().unwrap().filename, "<stdin>"),
+        otherwise => panic!("Expected parse errors, got {:?}", otherwise),
+    }
+}
+
+#[test]
+fn tzif_output() {
+    let fs = MemoryFilesystem::new();
+    fs.add_file("tz/europe", "Zone  Test/Sub/Zone  1:00  -  TST\nLink  Test/Sub/Zone  Test/Link\n");
+    fs.add_dir("out");
+
+    let report = run_with(vec![ "--output", "out", "--format", "tzif", "tz" ], &FixedClock(0), &fs).unwrap();
+    assert_eq!(report, Report { zone_count: 2, link_count: 1, warnings: vec![], output: String::new() });
+    assert!(fs.is_dir(Path::new("out/Test/Sub")));
+    assert!(fs.is_file(Path::new("out/Test/Sub/Zone")));
+    assert!(fs.is_file(Path::new("out/Test/Link")));
+    assert!(!fs.is_file(Path::new("out/mod.rs")));
+}
+
+#[test]
+fn tzif_output_outside_the_output_directory() {
+    let fs = MemoryFilesystem::new();
+    fs.add_file("tz/europe", "Zone  Test/Zone  1:00  -  TST\nLink  Test/Zone  ../../escaped\n");
+    fs.add_dir("out");
+    fs.add_dir("out/a");
+    fs.add_dir("out/a/b");
+
+    let err = run_with(vec![ "--output", "out/a/b", "--format", "tzif", "tz" ], &FixedClock(0), &fs).unwrap_err();
+    assert_eq!(err.exit_code(), EXIT_IO_ERROR);
+    assert!(!fs.is_file(Path::new("out/escaped")));
+}
+
+#[test]
+fn zi_output_round_trip() {
+    let fs = MemoryFilesystem::new();
+    fs.add_file("tz/europe", "Rule  Test  2000  2001  -  Mar  lastSun  1:00u  1:00  S\n\
+                              Rule  Test  2000  2001  -  Oct  lastSun  1:00u  0     -\n\
+                              Zone  Test/Zone  0:30  -  LMT  1999\n\
+                              \x20                1:00  Test  T%sT\n");
+    fs.add_dir("out");
+
+    let _ = run_with(vec![ "--output", "out", "--format", "zi", "tz" ], &FixedClock(0), &fs).unwrap();
+    assert!(fs.contents("out/tzdata.zi").unwrap().contains("\t\t1:00\t1:00\tTST\t2000 Oct 29 3:00\n"));
+
+    // Reading the file back in gives the same transitions.
+    let _ = run_with(vec![ "dump", "--output", "original.txt", "Test/Zone", "tz" ], &FixedClock(0), &fs).unwrap();
+    let _ = run_with(vec![ "dump", "--output", "round-trip.txt", "Test/Zone", "out/tzdata.zi" ], &FixedClock(0), &fs).unwrap();
+    assert_eq!(fs.contents("original.txt"), fs.contents("round-trip.txt"));
+}
+
+#[test]
+fn json_output() {
+    let fs = MemoryFilesystem::new();
+    fs.add_file("tz/europe", "Zone  Test/Zone  0:30  -  LMT  1970\n\
+                              \x20                1:00  -  TST\n\
+                              Link  Test/Zone  Test/Link\n");
+    fs.add_dir("out");
+
+    let _ = run_with(vec![ "--output", "out", "--format", "json", "tz" ], &FixedClock(0), &fs).unwrap();
+    let json = fs.contents("out/zones.json").unwrap();
+    assert!(json.contains("\"name\": \"Test/Zone\","));
+    assert!(json.contains("{\"at\": -1800, \"total_offset\": 3600, \"utc_offset\": 3600, \"dst_offset\": 0, \"abbreviation\": \"TST\", \"is_dst\": false}\n"));
+    assert!(json.contains("\"Test/Link\": \"Test/Zone\"\n"));
+}
+
+#[test]
+fn csv_output() {
+    let fs = MemoryFilesystem::new();
+    fs.add_file("tz/europe", "Zone  Test/Zone  0:30  -  LMT  1970\n\
+                              \x20                1:00  -  TST\n\
+                              Link  Test/Zone  Test/Link\n");
+    fs.add_dir("out");
+
+    let _ = run_with(vec![ "--output", "out", "--format", "csv", "tz" ], &FixedClock(0), &fs).unwrap();
+    assert_eq!(fs.contents("out/transitions.csv").unwrap(),
+               "zone,at,time,total_offset,utc_offset,dst_offset,abbreviation,is_dst\n\
+                Test/Zone,,,1800,1800,0,LMT,0\n\
+                Test/Zone,-1800,1969-12-31T23:30:00Z,3600,3600,0,TST,0\n");
+}
+
+#[test]
+fn year_window() {
+    let fs = MemoryFilesystem::new();
+    fs.add_file("tz/europe", "Rule  Test  1990  max  -  Mar  lastSun  1:00u  1:00  S\n\
+                              Rule  Test  1990  max  -  Oct  lastSun  1:00u  0     -\n\
+                              Zone  Test/Zone  0:30  -  LMT  1900\n\
+                              \x20                0:00  Test  T%sT\n");
+    fs.add_dir("out");
+
+    let _ = run_with(vec![ "--output", "out", "--format", "csv", "--window-start", "2000", "--window-end", "2000", "tz" ], &FixedClock(0), &fs).unwrap();
+    assert_eq!(fs.contents("out/transitions.csv").unwrap(),
+               "zone,at,time,total_offset,utc_offset,dst_offset,abbreviation,is_dst\n\
+                Test/Zone,,,0,0,0,TT,0\n\
+                Test/Zone,954032400,2000-03-26T01:00:00Z,3600,0,3600,TST,1\n\
+                Test/Zone,972781200,2000-10-29T01:00:00Z,0,0,0,TT,0\n");
+}
+
+#[test]
+fn no_pre_1970() {
+    let fs = MemoryFilesystem::new();
+    fs.add_file("tz/europe", "Zone  Test/Zone  0:30  -  LMT  1900\n\
+                              \x20                1:00  -  TST  1980\n\
+                              \x20                2:00  -  SST\n\
+                              Link  Test/Zone  Test/Link\n");
+    fs.add_file("tz/backzone", "Zone  Test/Link  0:45  -  LMT  1900\n\
+                                \x20                1:00  -  TST  1980\n\
+                                \x20                2:00  -  SST\n");
+    fs.add_dir("out");
+
+    let report = run_with(vec![ "--output", "out", "--format", "csv", "--no-pre-1970", "tz/europe", "tz/backzone" ], &FixedClock(0), &fs).unwrap();
+    assert_eq!(report.link_count, 1);
+    assert_eq!(fs.contents("out/transitions.csv").unwrap(),
+               "zone,at,time,total_offset,utc_offset,dst_offset,abbreviation,is_dst\n\
+                Test/Zone,,,3600,3600,0,TST,0\n\
+                Test/Zone,315529200,1979-12-31T23:00:00Z,7200,7200,0,SST,0\n");
+}
+
+#[test]
+fn backzone() {
+    let fs = MemoryFilesystem::new();
+    fs.add_file("tz/europe", "Zone  Test/Zone  0:30  -  LMT  1900\n\
+                              \x20                1:00  -  TST\n\
+                              Link  Test/Zone  Test/Link\n\
+                              Link  Test/Zone  Test/Other\n");
+    fs.add_file("tz/backzone", "Zone  Test/Link  0:45  -  LMT  1900\n\
+                                \x20                1:00  -  TST\n");
+    fs.add_dir("out");
+
+    let report = run_with(vec![ "--output", "out", "--format", "csv", "tz" ], &FixedClock(0), &fs).unwrap();
+    assert_eq!(report.link_count, 2);
+
+    let report = run_with(vec![ "--output", "out", "--format", "csv", "--backzone", "tz" ], &FixedClock(0), &fs).unwrap();
+    assert_eq!(report.link_count, 1);
+    assert_eq!(fs.contents("out/transitions.csv").unwrap(),
+               "zone,at,time,total_offset,utc_offset,dst_offset,abbreviation,is_dst\n\
+                Test/Link,,,2700,2700,0,LMT,0\n\
+                Test/Link,-2208991500,1899-12-31T23:15:00Z,3600,3600,0,TST,0\n\
+                Test/Zone,,,1800,1800,0,LMT,0\n\
+                Test/Zone,-2208990600,1899-12-31T23:30:00Z,3600,3600,0,TST,0\n");
+
+    // Given by name, it gets read last, wherever it comes in the list.
+    let report = run_with(vec![ "--output", "out", "--format", "csv", "tz/backzone", "tz/europe" ], &FixedClock(0), &fs).unwrap();
+    assert_eq!(report.link_count, 1);
+}
+
+#[test]
+fn no_backward() {
+    let fs = MemoryFilesystem::new();
+    fs.add_file("tz/europe", "Zone  Test/Zone  1:00  -  TST\nLink  Test/Zone  Test/Current\n");
+    fs.add_file("tz/backward", "Link  Test/Zone  Test/Deprecated\n");
+    fs.add_dir("out");
+
+    let report = run_with(vec![ "--output", "out", "--format", "json", "tz" ], &FixedClock(0), &fs).unwrap();
+    assert_eq!(report.link_count, 2);
+
+    let report = run_with(vec![ "--output", "out", "--format", "json", "--no-backward", "tz" ], &FixedClock(0), &fs).unwrap();
+    assert_eq!(report.link_count, 1);
+    assert!(!fs.contents("out/zones.json").unwrap().contains("Test/Deprecated"));
+}
+
+#[test]
+fn blob_output() {
+    let fs = MemoryFilesystem::new();
+    fs.add_file("tz/europe", "Zone  Test/Zone  1:00  -  TST\nLink  Test/Zone  Test/Link\n");
+    fs.add_dir("out");
+
+    let report = run_with(vec![ "--output", "out", "--format", "blob", "tz" ], &FixedClock(0), &fs).unwrap();
+    assert_eq!(report, Report { zone_count: 2, link_count: 1, warnings: vec![], output: String::new() });
+    assert!(fs.is_file(Path::new("out/zones.bin")));
+    assert!(fs.contents("out/mod.rs").unwrap().contains("include_bytes!(\"zones.bin\")"));
+}
+
+#[test]
+fn compressed_blob_output() {
+    let fs = MemoryFilesystem::new();
+    fs.add_file("tz/europe", "Zone  Test/Zone  1:00  -  TST\nLink  Test/Zone  Test/Link\n");
+    fs.add_dir("out");
+
+    let _ = run_with(vec![ "--output", "out", "--format", "blob", "--compress", "tz" ], &FixedClock(0), &fs).unwrap();
+    assert!(fs.is_file(Path::new("out/zones.bin.deflate")));
+    assert!(!fs.is_file(Path::new("out/zones.bin")));
+    assert!(fs.contents("out/mod.rs").unwrap().contains("include_bytes!(\"zones.bin.deflate\")"));
+}
+
+#[test]
+fn single_file_output() {
+    let fs = MemoryFilesystem::new();
+    fs.add_file("tz/europe", "Zone  Test/Sub/Zone  1:00  -  TST\nZone  Test/Other-Zone  2:00  -  OST\nZone  Top  0:00  -  T\n");
+    fs.add_dir("out");
+
+    let _ = run_with(vec![ "--output", "out", "--format", "rust-file", "tz" ], &FixedClock(0), &fs).unwrap();
+    assert_eq!(fs.paths(), vec![ Path::new("out").to_path_buf(), Path::new("out/zones.rs").to_path_buf(),
+                                 Path::new("tz").to_path_buf(), Path::new("tz/europe").to_path_buf() ]);
+
+    let rust = fs.contents("out/zones.rs").unwrap();
+    assert!(rust.contains("pub static Top: StaticTimeZone<'static>"));
+    assert!(rust.contains("pub mod Test {\nuse super::*;\n\npub static Other_Zone: StaticTimeZone<'static>"));
+    assert!(rust.contains("pub mod Sub {\nuse super::*;\n\npub static Zone: StaticTimeZone<'static>"));
+    assert!(rust.contains("&Test::Sub::Zone"));
+}
+
+#[test]
+fn awkward_names() {
+    let fs = MemoryFilesystem::new();
+    fs.add_file("tz/etcetera", "Zone  Etc/GMT+5  -5:00  -  %z\nZone  Etc/GMT-5  5:00  -  %z\nZone  Test/type  0:00  -  T\nZone  Test/self/1st  0:00  -  T\n");
+    fs.add_dir("out");
+
+    let _ = run_with(vec![ "--output", "out", "tz" ], &FixedClock(0), &fs).unwrap();
+    let etc = fs.contents("out/Etc/mod.rs").unwrap();
+    assert!(etc.contains("mod GMTPlus5;\npub use self::GMTPlus5::ZONE as GMTPlus5;"));
+    assert!(etc.contains("mod GMT_5;\npub use self::GMT_5::ZONE as GMT_5;"));
+    assert!(fs.contents("out/Test/mod.rs").unwrap().contains("pub mod self_;\n\nmod r#type;\npub use self::r#type::ZONE as r#type;"));
+    assert!(fs.is_file(Path::new("out/Test/type.rs")));
+    assert!(fs.is_file(Path::new("out/Test/self/_1st.rs")));
+    assert!(fs.contents("out/mod.rs").unwrap().contains("&Test::self_::_1st"));
+}
+
+#[test]
+fn shared_link_data() {
+    let fs = MemoryFilesystem::new();
+    fs.add_file("tz/europe", "Zone  Europe/London  0:00  -  GMT\nLink  Europe/London  Europe/Belfast\nLink  Europe/London  GB\n");
+    fs.add_dir("out");
+
+    let _ = run_with(vec![ "--output", "out", "tz" ], &FixedClock(0), &fs).unwrap();
+    assert!(fs.contents("out/Europe/mod.rs").unwrap().contains("pub use super::Europe::London as Belfast;"));
+    assert!(fs.contents("out/mod.rs").unwrap().contains("pub use self::Europe::London as GB;"));
+    assert!(!fs.is_file(Path::new("out/Europe/Belfast.rs")));
+    assert!(!fs.is_file(Path::new("out/GB.rs")));
+
+    // With feature gates, the Europe region could be turned off while GB
+    // is still there, so it needs its own copy.
+    let _ = run_with(vec![ "--output", "out", "--feature-gates", "--format", "rust-file", "tz" ], &FixedClock(0), &fs).unwrap();
+    let rust = fs.contents("out/zones.rs").unwrap();
+    assert!(rust.contains("pub use super::Europe::London as Belfast;"));
+    assert!(rust.contains("pub static GB: StaticTimeZone<'static>"));
+}
+
+#[test]
+fn colliding_names() {
+    let fs = MemoryFilesystem::new();
+    fs.add_file("tz/europe", "Zone  Test/A-B  0:00  -  T\nZone  Test/A_B  0:00  -  T\n");
+    fs.add_dir("out");
+
+    match run_with(vec![ "--output", "out", "tz" ], &FixedClock(0), &fs) {
+        Err(CliError::IO(e)) => assert_eq!(e.to_string(), "Test/A-B and Test/A_B would both be named Test::A_B in the generated code"),
+        other                => panic!("expected a collision, got {:?}", other),
+    }
+}
+
+#[test]
+fn no_std_output() {
+    let fs = MemoryFilesystem::new();
+    fs.add_file("tz/europe", "Zone  Test/Sub/Zone  1:00  -  TST\nZone  Top  0:00  -  T\n");
+    fs.add_dir("out");
+
+    let _ = run_with(vec![ "--output", "out", "--no-std", "tz" ], &FixedClock(0), &fs).unwrap();
+    let root = fs.contents("out/mod.rs").unwrap();
+    assert!(root.contains("pub struct StaticTimeZone<'a> {"));
+    assert!(!root.contains("datetime"));
+
+    let zone = fs.contents("out/Test/Sub/Zone.rs").unwrap();
+    assert!(zone.contains("use super::super::super::{StaticTimeZone, FixedTimespanSet, FixedTimespan, ABBREVIATIONS};"));
+    assert!(zone.contains("name:   ABBREVIATIONS[1],  // TST"));
+    assert!(root.contains("pub const ABBREVIATIONS: [&'static str; 2] = [\n    \"T\",\n    \"TST\",\n];"));
+    assert!(!zone.contains("std"));
+    assert!(fs.contents("out/Top.rs").unwrap().contains("use super::{StaticTimeZone"));
+}
+
+#[test]
+fn case_insensitive_lookup() {
+    let fs = MemoryFilesystem::new();
+    fs.add_file("tz/europe", "Zone  Europe/London  0:00  -  GMT\nLink  Europe/London  GB\n");
+    fs.add_dir("out");
+
+    let _ = run_with(vec![ "--output", "out", "tz" ], &FixedClock(0), &fs).unwrap();
+    let root = fs.contents("out/mod.rs").unwrap();
+    assert!(root.contains("static LOWERCASE_ZONES: phf::Map<&'static str, &'static StaticTimeZone<'static>> = "));
+    assert!(root.contains("(\"europe/london\", &Europe::London)"));
+    assert!(root.contains("pub fn lookup_ignore_case(input: &str) -> Option<&'static StaticTimeZone<'static>> {\n    let mut buffer = [0u8; 13];\n"));
+    assert!(root.contains("    LOWERCASE_ZONES.get(input).cloned()\n}"));
+}
+
+#[test]
+fn find_function() {
+    let fs = MemoryFilesystem::new();
+    fs.add_file("tz/europe", "Zone  Europe/London  0:00  -  GMT\n");
+    fs.add_dir("out");
+
+    let _ = run_with(vec![ "--output", "out", "tz" ], &FixedClock(0), &fs).unwrap();
+    let root = fs.contents("out/mod.rs").unwrap();
+    assert!(root.contains("use datetime::zone::{StaticTimeZone, FixedTimespan};"));
+    assert!(root.contains("pub fn find<'a>(zone: &'a StaticTimeZone<'a>, timestamp: i64) -> &'a FixedTimespan<'a> {"));
+    assert!(root.contains("    match set.rest.binary_search_by(|&(at, _)| at.cmp(&timestamp)) {"));
+
+    let _ = run_with(vec![ "--output", "out", "--format", "rust-file", "tz" ], &FixedClock(0), &fs).unwrap();
+    assert!(fs.contents("out/zones.rs").unwrap().contains("pub fn find<'a>"));
+}
+
+#[test]
+fn alias_table() {
+    let fs = MemoryFilesystem::new();
+    fs.add_file("tz/europe", "Zone  Europe/London  0:00  -  GMT\nLink  Europe/London  GB\nLink  Europe/London  Europe/Belfast\n");
+    fs.add_dir("out");
+
+    let _ = run_with(vec![ "--output", "out", "--format", "rust-file", "tz" ], &FixedClock(0), &fs).unwrap();
+    let rust = fs.contents("out/zones.rs").unwrap();
+    assert!(rust.contains("pub static ALIASES: phf::Map<&'static str, &'static str> = "));
+    assert!(rust.contains("(\"GB\", \"Europe/London\")"));
+    assert!(rust.contains("(\"Europe/Belfast\", \"Europe/London\")"));
+    assert!(!rust.contains("(\"Europe/London\", \"Europe/London\")"));
+    assert!(rust.contains("pub fn canonical_name(input: &str) -> Option<&'static str> {"));
+}
+
+#[test]
+fn windows_zones() {
+    let fs = MemoryFilesystem::new();
+    fs.add_file("tz/europe", "Zone  Europe/Berlin  1:00  -  CET\nZone  Europe/Zurich  1:00  -  CET\n");
+    fs.add_file("windowsZones.xml", "<windowsZones>\n\
+        <mapZone other=\"W. Europe Standard Time\" territory=\"001\" type=\"Europe/Berlin\"/>\n\
+        <mapZone other=\"W. Europe Standard Time\" territory=\"CH\" type=\"Europe/Zurich\"/>\n\
+        </windowsZones>\n");
+    fs.add_dir("out");
+
+    let _ = run_with(vec![ "--output", "out", "--scaffold", "tz-data", "--windows-zones", "windowsZones.xml", "tz" ], &FixedClock(0), &fs).unwrap();
+    let root = fs.contents("out/src/zones/mod.rs").unwrap();
+    assert!(root.contains("pub static WINDOWS_ZONES: phf::Map<&'static str, &'static str> = "));
+    assert!(root.contains("(\"W. Europe Standard Time\", \"Europe/Berlin\")"));
+    assert!(!root.contains("Europe/Zurich\")"));
+    assert!(root.contains("pub fn lookup_windows(input: &str) -> Option<&'static StaticTimeZone<'static>> {"));
+    assert!(fs.contents("out/src/lib.rs").unwrap().contains("pub use zones::lookup_windows;"));
+
+    // Without the option, there’s no map.
+    let _ = run_with(vec![ "--output", "out", "--format", "rust-file", "tz" ], &FixedClock(0), &fs).unwrap();
+    assert!(!fs.contents("out/zones.rs").unwrap().contains("WINDOWS_ZONES"));
+
+    fs.add_file("bad.xml", "<mapZone other=\"W. Europe Standard Time\" type=\"Europe/Berlin\"/>");
+    let err = run_with(vec![ "--output", "out", "--windows-zones", "bad.xml", "tz" ], &FixedClock(0), &fs).unwrap_err();
+    assert_eq!(err.exit_code(), EXIT_DATA_ERROR);
+    assert_eq!(err.to_string(), "bad.xml: line 1: mapZone without \"territory\" attribute");
+}
+
+#[test]
+fn zone_locations() {
+    let fs = MemoryFilesystem::new();
+    fs.add_file("tz/europe", "Zone  Europe/London  0:00  -  GMT\nZone  Europe/Paris  1:00  -  CET\n");
+    fs.add_file("zone1970.tab", "GB,GG,IM,JE\t+513030-0000731\tEurope/London\n\
+        FR,MC\t+4852+00220\tEurope/Paris\n\
+        DE,DK,NO,SE,SJ\t+5230+01322\tEurope/Berlin\n");
+    fs.add_dir("out");
+
+    let _ = run_with(vec![ "--output", "out", "--scaffold", "tz-data", "--zone-tab", "zone1970.tab", "tz" ], &FixedClock(0), &fs).unwrap();
+    let root = fs.contents("out/src/zones/mod.rs").unwrap();
+    assert!(root.contains("pub static ZONE_LOCATIONS: &'static [ZoneLocation] = &["));
+    assert!(root.contains("    ZoneLocation { zone: \"Europe/Paris\", countries: &[\"FR\", \"MC\"], latitude: 48.86666666666667, longitude: 2.3333333333333335, comment: None },\n"));
+    assert!(root.contains("pub fn zone_location(input: &str) -> Option<&'static ZoneLocation> {"));
+    assert!(!root.contains("Europe/Berlin"));
+    assert!(root.contains("pub fn nearest_zone(latitude: f64, longitude: f64) -> Option<&'static StaticTimeZone<'static>> {"));
+    assert!(fs.contents("out/src/lib.rs").unwrap().contains("pub use zones::nearest_zone;"));
+    assert!(fs.contents("out/src/lib.rs").unwrap().contains("pub use zones::zone_location;"));
+
+    // Without the standard library, the locations are there, but there’s
+    // no function to search them.
+    let _ = run_with(vec![ "--output", "out", "--format", "rust-file", "--no-std", "--zone-tab", "zone1970.tab", "tz" ], &FixedClock(0), &fs).unwrap();
+    let file = fs.contents("out/zones.rs").unwrap();
+    assert!(file.contains("ZONE_LOCATIONS"));
+    assert!(file.contains("zone_location"));
+    assert!(!file.contains("nearest_zone"));
+
+    fs.add_file("bad.tab", "GB\t+5130\tEurope/London\n");
+    let err = run_with(vec![ "--output", "out", "--zone-tab", "bad.tab", "tz" ], &FixedClock(0), &fs).unwrap_err();
+    assert_eq!(err.exit_code(), EXIT_DATA_ERROR);
+    assert_eq!(err.to_string(), "bad.tab: line 1: invalid coordinates");
+}
+
+#[test]
+fn leap_seconds() {
+    let fs = MemoryFilesystem::new();
+    fs.add_file("tz/europe", "Zone  Europe/London  0:00  -  GMT\n");
+    fs.add_file("tz/leapseconds", "Leap\t1972\tJun\t30\t23:59:60\t+\tS\n\
+        Leap\t2016\tDec\t31\t23:59:60\t+\tS\n\
+        #expires 1782604800 (2026-06-28 00:00:00 UTC)\n");
+    fs.add_dir("out");
+
+    // It gets found in an input directory...
+    let report = run_with(vec![ "--output", "out", "tz" ], &FixedClock(0), &fs).unwrap();
+    assert_eq!(report.zone_count, 1);
+    let root = fs.contents("out/mod.rs").unwrap();
+    assert!(root.contains("pub static LEAP_SECONDS: &'static [(i64, i32)] = &[\n    (78796800, 1),\n    (1483228800, 1),\n];\n"));
+    assert!(root.contains("pub const LEAP_SECONDS_EXPIRY: Option<i64> = Some(1782604800);"));
+
+    // ...or given as a file, without being read as zoneinfo source.
+    let _ = run_with(vec![ "--output", "out", "--format", "rust-file", "tz/europe", "tz/leapseconds" ], &FixedClock(0), &fs).unwrap();
+    assert!(fs.contents("out/zones.rs").unwrap().contains("(1483228800, 1),"));
+
+    let _ = run_with(vec![ "--output", "out", "--format", "rust-file", "tz/europe" ], &FixedClock(0), &fs).unwrap();
+    assert!(!fs.contents("out/zones.rs").unwrap().contains("LEAP_SECONDS"));
+
+    fs.add_file("tz/leapseconds", "Leap\t2016\tDec\t31\t23:59:60\t*\tS\n");
+    let err = run_with(vec![ "--output", "out", "tz" ], &FixedClock(0), &fs).unwrap_err();
+    assert_eq!(err.exit_code(), EXIT_DATA_ERROR);
+    assert_eq!(err.to_string(), "tz/leapseconds: line 1: invalid correction");
+}
+
+#[test]
+fn tz_version() {
+    let fs = MemoryFilesystem::new();
+    fs.add_file("tz/europe", "Zone  Europe/London  0:00  -  GMT\n");
+    fs.add_file("tz/version", "2025a\n");
+    fs.add_dir("out");
+
+    // The version file gets found next to the input files…
+    let _ = run_with(vec![ "--output", "out", "tz/europe" ], &FixedClock(0), &fs).unwrap();
+    assert!(fs.contents("out/mod.rs").unwrap().contains("pub const TZDB_VERSION: &'static str = \"2025a\";"));
+
+    // …but can be overridden…
+    let _ = run_with(vec![ "--output", "out", "--tz-version", "2024b", "tz" ], &FixedClock(0), &fs).unwrap();
+    assert!(fs.contents("out/mod.rs").unwrap().contains("pub const TZDB_VERSION: &'static str = \"2024b\";"));
+
+    // …and isn’t always known.
+    let _ = run_with(vec![ "--output", "out", "--format", "rust-file", "-" ], &FixedClock(0), &fs).unwrap();
+    assert!(fs.contents("out/zones.rs").unwrap().contains("pub const TZDB_VERSION: &'static str = \"unknown\";"));
+}
+
+#[test]
+fn zone_id_enum() {
+    let fs = MemoryFilesystem::new();
+    fs.add_file("tz/europe", "Zone  Europe/London  0:00  -  GMT\nZone  America/Port-au-Prince  -5:00  -  EST\nLink  Europe/London  GB\n");
+    fs.add_dir("out");
+
+    let _ = run_with(vec![ "--output", "out", "--feature-gates", "tz" ], &FixedClock(0), &fs).unwrap();
+    let root = fs.contents("out/mod.rs").unwrap();
+    assert!(root.contains("pub enum TimeZoneId {\n    #[cfg(feature = \"america\")]\n    America__PortauPrince,\n    #[cfg(feature = \"europe\")]\n    Europe__London,\n    GB,\n}"));
+    assert!(root.contains("            TimeZoneId::America__PortauPrince => &America::Port_au_Prince,\n"));
+    assert!(root.contains("            TimeZoneId::GB => \"GB\",\n"));
+    assert!(root.contains("impl ::std::str::FromStr for TimeZoneId {"));
+    assert!(root.contains("            \"GB\" => Ok(TimeZoneId::GB),\n"));
+}
+
+#[test]
+fn feature_gated_output() {
+    let fs = MemoryFilesystem::new();
+    fs.add_file("tz/europe", "Zone  Europe/London  0:00  -  GMT\nZone  America/Argentina/Salta  -3:00  -  ART\nLink  Europe/London  GB\n");
+    fs.add_dir("out");
+
+    let _ = run_with(vec![ "--output", "out", "--feature-gates", "tz" ], &FixedClock(0), &fs).unwrap();
+    let root = fs.contents("out/mod.rs").unwrap();
+    assert!(root.contains("#[cfg(feature = \"europe\")]\npub mod Europe;"));
+    assert!(root.contains("#[cfg(feature = \"america\")]\nstatic AMERICA_ZONES: phf::Map"));
+    assert!(root.contains("        #[cfg(feature = \"europe\")]\n        Some(\"Europe\") => EUROPE_ZONES.get(input).cloned(),\n"));
+    assert!(root.contains("\nmod GB;"));
+    assert!(!root.contains("#[cfg(feature = \"gb\")]"));
+
+    assert!(root.contains("        #[cfg(feature = \"america\")]\n        Some(\"america\") => AMERICA_LOWERCASE_ZONES.get(input).cloned(),\n"));
+
+    assert_eq!(fs.contents("out/features.toml").unwrap(),
+               "[features]\ndefault = [\"america\", \"europe\"]\namerica = []\neurope = []\n");
+}
+
+#[test]
+fn crate_scaffold() {
+    let fs = MemoryFilesystem::new();
+    fs.add_file("tz/europe", "Zone  Europe/London  0:00  -  GMT\n");
+    fs.add_dir("out");
+
+    let _ = run_with(vec![ "--output", "out", "--scaffold", "tz-data", "tz" ], &FixedClock(0), &fs).unwrap();
+    assert_eq!(fs.contents("out/Cargo.toml").unwrap(),
+               "[package]\nname = \"tz-data\"\nversion = \"0.1.0\"\n\n\
+                [dependencies]\nphf = \"0.7.12\"\n\n\
+                [dependencies.datetime]\ngit = \"https://github.com/rust-datetime/datetime.git\"\n");
+
+    let lib = fs.contents("out/src/lib.rs").unwrap();
+    assert!(lib.contains("extern crate datetime;\nextern crate phf;\n\npub mod zones;\npub use zones::lookup;\n"));
+    assert!(fs.is_file(Path::new("out/src/zones/mod.rs")));
+    assert!(fs.is_file(Path::new("out/src/zones/Europe/London.rs")));
+    assert!(!fs.is_file(Path::new("out/mod.rs")));
+
+    // Without the standard library, there’s no datetime dependency, and the
+    // features go in the manifest.
+    let fs = MemoryFilesystem::new();
+    fs.add_file("tz/europe", "Zone  Europe/London  0:00  -  GMT\n");
+    fs.add_dir("out");
+
+    let _ = run_with(vec![ "--output", "out", "--scaffold", "tz-data", "--format", "rust-file", "--no-std", "--feature-gates", "tz" ], &FixedClock(0), &fs).unwrap();
+    let manifest = fs.contents("out/Cargo.toml").unwrap();
+    assert!(manifest.contains("phf = { version = \"0.7.12\", default-features = false }\n\n[features]\ndefault = [\"europe\"]\n"));
+    assert!(!manifest.contains("datetime"));
+    assert!(fs.contents("out/src/lib.rs").unwrap().contains("#![no_std]"));
+    assert!(fs.is_file(Path::new("out/src/zones.rs")));
+    assert!(!fs.is_file(Path::new("out/features.toml")));
+}
+
+#[test]
+fn chrono_tz_output() {
+    let fs = MemoryFilesystem::new();
+    fs.add_file("tz/europe", "Zone  Etc/GMT-1  1:00  -  TST\nLink  Etc/GMT-1  Test/Link\n");
+    fs.add_dir("out");
+
+    let _ = run_with(vec![ "--output", "out", "--format", "chrono-tz", "tz" ], &FixedClock(0), &fs).unwrap();
+    let rust = fs.contents("out/timezones.rs").unwrap();
+    assert!(rust.contains("pub enum Tz {\n    /// Etc/GMT-1\n    Etc__GMTMinus1,\n    /// Test/Link\n    Test__Link,\n}"));
+    assert!(rust.contains("Tz::Test__Link => \"Test/Link\","));
+    assert!(rust.contains("impl TimeSpans for Tz {"));
+}
+
+#[test]
+fn time_output() {
+    let fs = MemoryFilesystem::new();
+    fs.add_file("tz/europe", "Zone  Test/Zone  1:00  -  TST\nLink  Test/Zone  Test/Link\n");
+    fs.add_dir("out");
+
+    let _ = run_with(vec![ "--output", "out", "--format", "time", "tz" ], &FixedClock(0), &fs).unwrap();
+    let rust = fs.contents("out/time_zones.rs").unwrap();
+    assert!(rust.contains("use time::{OffsetDateTime, UtcOffset};"));
+    assert!(rust.contains("    Timespan { start: i64::MIN, offset_seconds: 3600, is_dst: false, abbreviation: \"TST\" },\n"));
+    assert!(rust.contains("    Zone { name: \"Test/Link\", timespans: &TIMESPANS_0 },\n    Zone { name: \"Test/Zone\", timespans: &TIMESPANS_0 },\n"));
+}
+
+#[test]
+fn reproducible_output() {
+    let europe = "Rule  EU  1981  max  -  Mar  lastSun  1:00u  1:00  S\n\
+                  Rule  EU  1996  max  -  Oct  lastSun  1:00u  0     -\n\
+                  Zone  Europe/London  0:00  EU  GMT/BST\n\
+                  Zone  Europe/Paris  1:00  EU  CE%sT\n\
+                  Link  Europe/London  GB\n";
+    let asia = "Zone  Asia/Tokyo  9:00  -  JST\nLink  Asia/Tokyo  Japan\n";
+
+    // The same zones, given in a different order, should produce exactly
+    // the same files in every format.
+    for format in &[ "rust", "rust-file", "tzif", "zi", "json", "csv", "blob", "chrono-tz", "time" ] {
+        let outputs: Vec<Vec<(PathBuf, Option<Vec<u8>>)>> = [ ("europe", "asia"), ("asia", "europe") ].iter().map(|&(first, second)| {
+            let fs = MemoryFilesystem::new();
+            fs.add_file("tz/europe", europe);
+            fs.add_file("tz/asia", asia);
+            fs.add_dir("out");
+
+            let args = vec![ "--output".to_owned(), "out".to_owned(), "--format".to_owned(), format.to_string(),
+                             "--feature-gates".to_owned(), format!("tz/{}", first), format!("tz/{}", second) ];
+            let _ = run_with(args, &FixedClock(0), &fs).unwrap();
+            fs.paths().into_iter().map(|path| { let bytes = fs.bytes(&path); (path, bytes) }).collect()
+        }).collect();
+
+        assert!(outputs[0] == outputs[1], "{} output differs", format);
+    }
+}
+
+/// A filesystem in memory that keeps track of which files get written to.
+#[derive(Default)]
+struct CountingFilesystem {
+    inner: MemoryFilesystem,
+    created: RefCell<Vec<PathBuf>>,
+}
+
+impl Filesystem for CountingFilesystem {
+    type Reader = Cursor<Vec<u8>>;
+    type Writer = MemoryFile;
+
+    fn open(&self, path: &Path) -> IOResult<Cursor<Vec<u8>>> { self.inner.open(path) }
+    fn create_dir(&self, path: &Path) -> IOResult<()> { self.inner.create_dir(path) }
+    fn is_dir(&self, path: &Path) -> bool { self.inner.is_dir(path) }
+    fn is_file(&self, path: &Path) -> bool { self.inner.is_file(path) }
+    fn remove_file(&self, path: &Path) -> IOResult<()> { self.inner.remove_file(path) }
+    fn read_stdin(&self) -> IOResult<Vec<u8>> { self.inner.read_stdin() }
+
+    fn create(&self, path: &Path) -> IOResult<MemoryFile> {
+        self.created.borrow_mut().push(path.to_path_buf());
+        self.inner.create(path)
+    }
+}
+
+#[test]
+fn unchanged_files_skipped() {
+    let fs = CountingFilesystem::default();
+    fs.inner.add_file("tz/europe", "Zone  Europe/London  0:00  -  GMT\nZone  Europe/Paris  1:00  -  CET\n");
+    fs.inner.add_file("tz/asia", "Zone  Asia/Tokyo  9:00  -  JST\n");
+    fs.inner.add_dir("out");
+
+    let args = vec![ "--output", "out", "--tz-version", "2016a", "tz" ];
+    let _ = run_with(args.clone(), &FixedClock(0), &fs).unwrap();
+    assert!(fs.created.borrow().contains(&PathBuf::from("out/Europe/London.rs")));
+    let first = fs.inner.contents("out/mod.rs").unwrap();
+
+    // Running it again with the same input shouldn’t write anything.
+    fs.created.borrow_mut().clear();
+    let _ = run_with(args.clone(), &FixedClock(0), &fs).unwrap();
+    assert_eq!(*fs.created.borrow(), Vec::<PathBuf>::new());
+    assert_eq!(fs.inner.contents("out/mod.rs").unwrap(), first);
+
+    // Changing one zone should only rewrite that zone’s file.
+    fs.inner.add_file("tz/asia", "Zone  Asia/Tokyo  9:00  -  JST  1888\n\t\t9:30  -  JST\n");
+    let _ = run_with(args, &FixedClock(0), &fs).unwrap();
+    assert_eq!(*fs.created.borrow(), vec![ PathBuf::from("out/Asia/Tokyo.rs") ]);
+}
+
+#[test]
+fn dry_run() {
+    let fs = MemoryFilesystem::new();
+    fs.add_file("tz/europe", "Zone  Europe/London  0:00  -  GMT\n");
+    fs.add_dir("out");
+
+    let report = run_with(vec![ "--output", "out", "--dry-run", "tz" ], &FixedClock(0), &fs).unwrap();
+    assert_eq!(report.zone_count, 1);
+    assert!(report.output.starts_with("Would create directory \"out/Europe\"\n\
+                                       Would create file \"out/Europe/London.rs\" ("));
+    assert_eq!(fs.paths(), vec![ PathBuf::from("out"), PathBuf::from("tz"), PathBuf::from("tz/europe") ]);
+
+    // It fails in the same way as a real run would.
+    let fs = MemoryFilesystem::new();
+    fs.add_file("tz/europe", "Zone  Europe/London  0:00  -  GMT\n");
+    let err = run_with(vec![ "--output", "out", "--dry-run", "tz" ], &FixedClock(0), &fs).unwrap_err();
+    assert_eq!(err.exit_code(), EXIT_IO_ERROR);
+}
+
+#[test]
+fn unknown_format() {
+    let fs = MemoryFilesystem::new();
+    fs.add_file("tz/europe", "Zone  Test/Zone  1:00  -  TST\n");
+
+    let err = run_with(vec![ "--output", "out", "--format", "xml", "tz" ], &FixedClock(0), &fs).unwrap_err();
+    assert_eq!(err.exit_code(), EXIT_USAGE);
+}
+
+#[test]
+fn continue_on_error() {
+    let fs = MemoryFilesystem::new();
+    fs.add_file("tz/europe", "Zone  Europe/London  0:00  -  GMT\n\
+                              Zone  Europe/Typo  1:00  -\n\
+                              Link  Europe/Typo  Europe/Alias\n\
+                              Link  Europe/London  GB\n");
+    fs.add_dir("out");
+
+    let err = run_with(vec![ "--output", "out", "tz" ], &FixedClock(0), &fs).unwrap_err();
+    assert_eq!(err.exit_code(), EXIT_DATA_ERROR);
+    assert!(fs.contents("out/mod.rs").is_none());
+
+    let report = run_with(vec![ "--output", "out", "--continue-on-error", "tz" ], &FixedClock(0), &fs).unwrap();
+    assert_eq!(report, Report {
+        zone_count: 2,
+        link_count: 1,
+        warnings: vec![
+            Warning::SkippedLine { filename: "tz/europe".to_owned(), line: 2, column: None, error: "parse error".to_owned() },
+        ],
+        output: String::new(),
+    });
+    assert!(fs.contents("out/Europe/London.rs").is_some());
+    assert!(!fs.contents("out/mod.rs").unwrap().contains("Alias"));
+}
+
+#[test]
+fn json_errors() {
+    let fs = MemoryFilesystem::new();
+    fs.add_file("tz/europe", "Zone  Test/Zone  1:00  -  TST\nZone  Test/Broken\n");
+
+    let err = run_with(vec![ "--output", "out", "--error-format", "json", "tz" ], &FixedClock(0), &fs).unwrap_err();
+    assert_eq!(err.exit_code(), EXIT_DATA_ERROR);
+    assert_eq!(ErrorFormat::Json.error(&err),
+               "{\"file\": \"tz/europe\", \"line\": 2, \"column\": null, \"message\": \"parse error\", \"severity\": \"error\"}");
+
+    fs.add_file("tz/europe", "Zone  Test/Zone  1:00  -  TST\nZone  Test/Broken  1:00  -  TST  1985  Foo\n");
+    let err = run_with(vec![ "--output", "out", "--error-format", "json", "tz" ], &FixedClock(0), &fs).unwrap_err();
+    assert_eq!(ErrorFormat::Json.error(&err),
+               "{\"file\": \"tz/europe\", \"line\": 2, \"column\": 40, \"message\": \"invalid MONTH field\", \"severity\": \"error\"}");
+
+    let err = run_with(vec![ "--output", "out", "--error-format", "xml", "tz" ], &FixedClock(0), &fs).unwrap_err();
+    assert_eq!(err.exit_code(), EXIT_USAGE);
+}
+
+#[test]
+fn build_script_generation() {
+    let fs = MemoryFilesystem::new();
+    fs.add_file("tz/europe", "Zone  Europe/London  0:00  -  GMT\nZone  Europe/Paris  1:00  -  CET\n");
+    fs.add_file("tz/asia", "Zone  Asia/Tokyo  9:00  -  JST\nZone  Asia/Seoul  9:00  -  KST\nLink  Asia/Tokyo  Japan\n");
+
+    let filter = ZoneFilter::parse("Europe/*,Japan");
+    let mut cargo = Vec::new();
+    let report = generate_with("out", &[ "tz" ], &GenerateOptions::default(), filter.as_ref(), &fs, &mut cargo).unwrap();
+
+    assert_eq!(report, Report { zone_count: 4, link_count: 1, warnings: vec![], output: String::new() });
+    assert_eq!(String::from_utf8(cargo).unwrap(),
+               "cargo:rerun-if-changed=tz\n\
+                cargo:rerun-if-changed=tz/asia\n\
+                cargo:rerun-if-changed=tz/europe\n\
+                cargo:rerun-if-env-changed=ZONEINFO_FILTER\n");
+    assert!(fs.is_file(Path::new("out/Europe/London.rs")));
+    assert!(fs.is_file(Path::new("out/Asia/Tokyo.rs")));
+    assert!(fs.contents("out/mod.rs").unwrap().contains("pub use self::Asia::Tokyo as Japan;"));
+    assert!(!fs.is_file(Path::new("out/Asia/Seoul.rs")));
+}
+
+#[test]
+fn filtered_build() {
+    let fs = MemoryFilesystem::new();
+    fs.add_file("tz/europe", "Zone  Europe/London  0:00  -  GMT\nZone  Europe/Paris  1:00  -  CET\n");
+    fs.add_file("tz/asia", "Zone  Asia/Tokyo  9:00  -  JST\nZone  Asia/Seoul  9:00  -  KST\nLink  Asia/Tokyo  Japan\n");
+    fs.add_dir("out");
+    fs.add_dir("all");
+
+    let report = run_with(vec![ "build", "--output", "out", "--filter", "Europe/*,Japan", "tz" ], &FixedClock(0), &fs).unwrap();
+    assert_eq!(report, Report { zone_count: 4, link_count: 1, warnings: vec![], output: String::new() });
+    assert!(fs.is_file(Path::new("out/Europe/London.rs")));
+    assert!(fs.is_file(Path::new("out/Asia/Tokyo.rs")));
+    assert!(!fs.is_file(Path::new("out/Asia/Seoul.rs")));
+
+    // Without a command, the arguments still go to `build`.
+    let report = run_with(vec![ "--output", "all", "tz" ], &FixedClock(0), &fs).unwrap();
+    assert_eq!(report, Report { zone_count: 5, link_count: 1, warnings: vec![], output: String::new() });
+}
+
+#[test]
+fn unknown_command() {
+    let fs = MemoryFilesystem::new();
+    fs.add_file("tz", "Zone  Test/Zone  1:00  -  TST\n");
+    fs.add_dir("out");
+
+    let err = run_with(vec![ "biuld", "--output", "out", "tz" ], &FixedClock(0), &fs).unwrap_err();
+    assert_eq!(err.exit_code(), EXIT_USAGE);
+    assert!(err.to_string().starts_with("Unknown command: biuld\nThe commands are: build, compile, "));
+
+    // An input file that happens to be in the current directory isn’t
+    // mistaken for a command.
+    let _ = run_with(vec![ "tz", "--output", "out" ], &FixedClock(0), &fs).unwrap();
+}
+
+#[test]
+fn usage_errors_name_their_command() {
+    let fs = MemoryFilesystem::new();
+    fs.add_file("tz", "Zone  Test/Zone  1:00  -  TST\n");
+
+    let err = run_with(vec![ "dump", "--output", "out" ], &FixedClock(0), &fs).unwrap_err();
+    assert_eq!(err.exit_code(), EXIT_USAGE);
+    assert_eq!(err.to_string(), "Missing ZONE argument\n\
+                                 Run `build-data-crate dump --help` to see its options.");
+
+    let err = run_with(vec![ "query", "Test/Zone" ], &FixedClock(0), &fs).unwrap_err();
+    assert_eq!(err.exit_code(), EXIT_USAGE);
+    assert!(err.to_string().starts_with("Missing DATETIME argument\n"));
+
+    let err = run_with(vec![ "timeline", "--frobnicate", "Test/Zone", "tz" ], &FixedClock(0), &fs).unwrap_err();
+    assert_eq!(err.exit_code(), EXIT_USAGE);
+    assert!(err.to_string().ends_with("\nRun `build-data-crate timeline --help` to see its options."));
+}
+
+#[test]
+fn every_command_is_dispatched() {
+    let fs = MemoryFilesystem::new();
+
+    // None of the commands get far enough to need any input, but each one
+    // should complain about its own options rather than being unknown.
+    for command in COMMANDS {
+        let err = run_with(vec![ command.name, "--not-an-option" ], &FixedClock(0), &fs).unwrap_err();
+        assert_eq!(err.exit_code(), EXIT_USAGE);
+        assert!(err.to_string().contains(&format!("`build-data-crate {} --help`", command.name)), "{}: {}", command.name, err);
+    }
+}
+
+#[test]
+fn cached_run() {
+    let dir = scratch_dir("cached-run");
+    let input = write_input(&dir, "Zone  Test/Zone  1:00  -  TST\n");
+    let cache = dir.join("cache");
+    let output = dir.to_str().unwrap();
+    let args = vec![ "--output", output, "--cache", cache.to_str().unwrap(), &*input ];
+
+    let first = run(args.clone()).unwrap();
+    assert_eq!(cache.read_dir().unwrap().count(), 1);
+
+    let second = run(args).unwrap();
+    assert_eq!(first, second);
+}
+
+#[test]
+fn cached_run_in_memory() {
+    let fs = MemoryFilesystem::new();
+    fs.add_file("tz/europe", "Zone  Europe/London  0:00  -  GMT\n");
+    fs.add_dir("out");
+    let args = vec![ "--output", "out", "--cache", "cache/dir", "tz" ];
+
+    let cached = || fs.paths().into_iter().filter(|p| p.starts_with("cache/dir") && fs.is_file(p)).collect::<Vec<PathBuf>>();
+
+    let first = run_with(args.clone(), &FixedClock(0), &fs).unwrap();
+    assert_eq!(cached().len(), 1);
+    assert_eq!(cached()[0].extension().unwrap(), "dataset");
+
+    let second = run_with(args.clone(), &FixedClock(0), &fs).unwrap();
+    assert_eq!(first, second);
+    assert_eq!(cached().len(), 1);
+
+    // Different input gets cached under a different key.
+    fs.add_file("tz/europe", "Zone  Europe/London  0:00  -  GMT  1880\n\t\t1:00  -  BST\n");
+    let _ = run_with(args, &FixedClock(0), &fs).unwrap();
+    assert_eq!(cached().len(), 2);
+}
+
+#[test]
+fn capabilities_report() {
+    let caps = capabilities().to_string();
+    assert!(caps.starts_with("version "));
+    assert!(caps.contains("\ngrammar rule zone continuation link\n"));
+    assert!(caps.contains("\ntargets rust rust-file tzif zi json csv blob chrono-tz time\n"));
+    assert!(caps.contains("\ncommands build compile convert country-offsets diff dump fetch fmt ics lint query timeline\n"));
+    assert!(caps.contains("\noptions backzone cache check compress continue-on-error country delta-timestamps dry-run error-format feature-gates filter format from links max-year min-year no-backward no-dedup no-pre-1970 no-std no-verify-signature output quiet scaffold sha512 to tz-version until validate verbose window-end window-start windows-zones years zone zone-tab\n"));
+}
+
+#[test]
+fn clock_change_calendar() {
+    let dir = scratch_dir("clock-change-calendar");
+    let input = write_input(&dir, "Rule  Test  2000  max  -  Mar  lastSun  1:00u  1:00  S\n\
+                                   Rule  Test  2000  max  -  Oct  lastSun  1:00u  0     -\n\
+                                   Zone  Test/Zone  0:00  Test  T%sT\n\
+                                   Link  Test/Zone  Test/Link\n");
+    let output = dir.join("changes.ics");
+
+    let report = run(vec![ "ics", "--output", output.to_str().unwrap(), "--zone", "Test/Link", "--years", "2", &*input ]).unwrap();
+    assert_eq!(report, Report { zone_count: 1, link_count: 1, warnings: vec![], output: String::new() });
+
+    let mut ics = String::new();
+    let _ = File::open(&output).unwrap().read_to_string(&mut ics).unwrap();
+    assert_eq!(ics.matches("BEGIN:VEVENT").count(), 4);
+    assert_eq!(ics.matches("clocks go forward 1 hour (TT to TST)").count(), 2);
+}
+
+#[test]
+fn clock_change_calendar_in_memory() {
+    let fs = MemoryFilesystem::new();
+    fs.add_file("tz/europe", "Rule  Test  2000  max  -  Mar  lastSun  1:00u  1:00  S\n\
+                              Rule  Test  2000  max  -  Oct  lastSun  1:00u  0     -\n\
+                              Zone  Test/Zone  0:00  Test  T%sT\n");
+
+    // 2024-01-01, so the next year has the changes in March and October.
+    let clock = FixedClock(1_704_067_200);
+    let _ = run_with(vec![ "ics", "--output", "changes.ics", "--zone", "Test/Zone", "tz" ], &clock, &fs).unwrap();
+
+    let ics = fs.contents("changes.ics").unwrap();
+    assert!(ics.contains("DTSTAMP:20240101T000000Z"));
+    assert!(ics.contains("DTSTART:20240331T010000Z"));
+    assert!(ics.contains("DTSTART:20241027T010000Z"));
+    assert_eq!(ics.matches("BEGIN:VEVENT").count(), 2);
+}
+
+#[test]
+fn clock_change_calendar_unknown_zone() {
+    let dir = scratch_dir("clock-change-unknown");
+    let input = write_input(&dir, "Zone  Test/Zone  0:00  -  TT\n");
+    let output = dir.join("changes.ics");
+
+    let err = run(vec![ "ics", "--output", output.to_str().unwrap(), "--zone", "Nowhere", &*input ]).unwrap_err();
+    assert_eq!(err.exit_code(), EXIT_USAGE);
+}
+
+#[test]
+fn country_offset_history() {
+    let dir = scratch_dir("country-offset-history");
+    let input = write_input(&dir, "Zone  Test/Zone  0:30  -  LMT  1900\n\
+                                   \x20                1:00  -  TST\n\
+                                   Zone  Test/Other  2:00  -  OST\n");
+
+    let tab = dir.join("zone1970.tab");
+    let mut f = File::create(&tab).unwrap();
+    f.write_all(b"# countries\nAA,BB\t+4230+00131\tTest/Zone\nCC\t-3352+15113\tTest/Other\n").unwrap();
+
+    let output = dir.join("offsets.csv");
+    let report = run(vec![ "country-offsets", "--output", output.to_str().unwrap(), "--zone-tab", tab.to_str().unwrap(),
+                           "--country", "BB", &*input ]).unwrap();
+    assert_eq!(report, Report { zone_count: 1, link_count: 0, warnings: vec![], output: String::new() });
+
+    let mut csv = String::new();
+    let _ = File::open(&output).unwrap().read_to_string(&mut csv).unwrap();
+    assert_eq!(csv, "country,zone,from,to,offset\n\
+                     BB,Test/Zone,,1899-12-31T23:30:00Z,1800\n\
+                     BB,Test/Zone,1899-12-31T23:30:00Z,,3600\n");
+}
+
+#[test]
+fn country_offset_history_bad_tab() {
+    let dir = scratch_dir("country-offset-history-bad-tab");
+    let input = write_input(&dir, "Zone  Test/Zone  1:00  -  TST\n");
+
+    let tab = dir.join("zone1970.tab");
+    let mut f = File::create(&tab).unwrap();
+    f.write_all(b"AA\tsomewhere\tTest/Zone\n").unwrap();
+
+    let output = dir.join("offsets.csv");
+    let err = run(vec![ "country-offsets", "--output", output.to_str().unwrap(), "--zone-tab", tab.to_str().unwrap(), &*input ]).unwrap_err();
+    assert_eq!(err.exit_code(), EXIT_DATA_ERROR);
+}
+
+#[test]
+fn zdump_layout() {
+    let dir = scratch_dir("zdump-layout");
+    let input = write_input(&dir, "Zone  Test/Zone  0:30  -  LMT  1970\n\
+                                   \x20                1:00  -  TST\n\
+                                   Link  Test/Zone  Test/Link\n");
+    let output = dir.join("dump.txt");
+
+    let report = run(vec![ "dump", "--output", output.to_str().unwrap(), "Test/Link", &*input ]).unwrap();
+    assert_eq!(report, Report { zone_count: 1, link_count: 1, warnings: vec![], output: String::new() });
+
+    let mut dump = String::new();
+    let _ = File::open(&output).unwrap().read_to_string(&mut dump).unwrap();
+    assert_eq!(dump, "Test/Link  Wed Dec 31 23:29:59 1969 UT = Wed Dec 31 23:59:59 1969 LMT isdst=0 gmtoff=1800\n\
+                      Test/Link  Wed Dec 31 23:30:00 1969 UT = Thu Jan  1 00:30:00 1970 TST isdst=0 gmtoff=3600\n");
+}
+
+#[test]
+fn svg_timeline() {
+    let dir = scratch_dir("svg-timeline");
+    let input = write_input(&dir, "Zone  Test/Zone  0:30  -  LMT  1970\n\
+                                   \x20                1:00  -  TST\n");
+    let output = dir.join("timeline.svg");
+
+    let report = run(vec![ "timeline", "--output", output.to_str().unwrap(), "--from", "1960", "--until", "1980", "Test/Zone", &*input ]).unwrap();
+    assert_eq!(report, Report { zone_count: 1, link_count: 0, warnings: vec![], output: String::new() });
+
+    let mut svg = String::new();
+    let _ = File::open(&output).unwrap().read_to_string(&mut svg).unwrap();
+    assert!(svg.starts_with("<svg "));
+    assert_eq!(svg.matches("<rect").count(), 2);
+}
+
+#[test]
+fn compile_zoneinfo() {
+    let dir = scratch_dir("compile-zoneinfo");
+    let input = write_input(&dir, "Zone  Test/Zone  0:30  -  LMT  1970\n\
+                                   \x20                1:00  -  TST\n\
+                                   Link  Test/Zone  Test/Link\n");
+    let output = dir.join("zoneinfo");
+
+    let report = run(vec![ "compile", "--output", output.to_str().unwrap(), &*input ]).unwrap();
+    assert_eq!(report, Report { zone_count: 2, link_count: 1, warnings: vec![], output: String::new() });
+
+    let zone = output.join("Test").join("Zone");
+    let link = output.join("Test").join("Link");
+    assert_eq!(metadata(&zone).unwrap().len(), metadata(&link).unwrap().len());
+
+    #[cfg(unix)] {
+        use std::os::unix::fs::MetadataExt;
+        assert_eq!(metadata(&zone).unwrap().ino(), metadata(&link).unwrap().ino());
+    }
+
+    let _ = run(vec![ "compile", "--output", output.to_str().unwrap(), "--links", "copy", &*input ]).unwrap();
+    #[cfg(unix)] {
+        use std::os::unix::fs::MetadataExt;
+        assert!(metadata(&zone).unwrap().ino() != metadata(&link).unwrap().ino());
+    }
+
+    let err = run(vec![ "compile", "--output", output.to_str().unwrap(), "--links", "symbolic", &*input ]).unwrap_err();
+    assert_eq!(err.exit_code(), EXIT_USAGE);
+}
+
+#[test]
+fn reformat_source() {
+    let fs = MemoryFilesystem::new();
+    fs.add_file("rules", "rule Test 2000 max - mar lastsun 1:00u 1:00 S\n\
+                          Rule\tTest\t2000\tmax\t-\tOct\tlastSun\t1:00u\t0\t-\n");
+
+    let err = run_with(vec![ "fmt", "--check", "rules" ], &FixedClock(0), &fs).unwrap_err();
+    assert_eq!(err.exit_code(), EXIT_DATA_ERROR);
+    assert_eq!(err.to_string(), "Not formatted: rules");
+
+    let _ = run_with(vec![ "fmt", "rules" ], &FixedClock(0), &fs).unwrap();
+    assert_eq!(fs.contents("rules").unwrap(),
+               "Rule  Test  2000  max  -  Mar  lastSun  1:00u  1:00  S\n\
+                Rule  Test  2000  max  -  Oct  lastSun  1:00u  0     -\n");
+
+    let _ = run_with(vec![ "fmt", "--check", "rules" ], &FixedClock(0), &fs).unwrap();
+}
+
+#[test]
+fn lint_findings() {
+    let fs = MemoryFilesystem::new();
+    fs.add_file("tz", "Zone  Test/Zone  0:00  -  TST\n\
+                       Zone  Test/Link  0:00  -  TST\n\
+                       Link  Test/Zone  Test/Link\n");
+
+    let err = run_with(vec![ "lint", "tz" ], &FixedClock(0), &fs).unwrap_err();
+    assert_eq!(err.exit_code(), EXIT_DATA_ERROR);
+    assert_eq!(err.to_string(), "link Test/Link has the same name as a zone, so it gets ignored");
+
+    fs.add_file("tz", "Zone  Test/Zone  0:00  -  TST\n");
+    let report = run_with(vec![ "lint", "tz" ], &FixedClock(0), &fs).unwrap();
+    assert_eq!(report.zone_count, 1);
+}
+
+#[test]
+fn query_local_time() {
+    let fs = MemoryFilesystem::new();
+    fs.add_file("tz", "Rule  Test  2000  max  -  Mar  lastSun  1:00u  1:00  S\n\
+                       Rule  Test  2000  max  -  Oct  lastSun  1:00u  0     -\n\
+                       Zone  Test/Zone  0:00  Test  T%sT\n");
+
+    let _ = run_with(vec![ "query", "--output", "answer.txt", "Test/Zone", "2024-03-31T01:30", "tz" ], &FixedClock(0), &fs).unwrap();
+    assert_eq!(fs.contents("answer.txt").unwrap(),
+               "Test/Zone 2024-03-31 01:30:00 is nonexistent\n\
+                before the gap: offset +00:00, abbreviation TT, DST no\n\
+                after the gap: offset +01:00, abbreviation TST, DST yes\n");
+
+    // Without `--output`, the answer is left for the caller to print.
+    let report = run_with(vec![ "query", "Test/Zone", "2024-03-31T01:30", "tz" ], &FixedClock(0), &fs).unwrap();
+    assert_eq!(report.output, fs.contents("answer.txt").unwrap());
+
+    let err = run_with(vec![ "query", "Test/Zone", "tomorrow", "tz" ], &FixedClock(0), &fs).unwrap_err();
+    assert_eq!(err.exit_code(), EXIT_USAGE);
+}
+
+#[test]
+fn convert_slim_and_back() {
+    let fs = MemoryFilesystem::new();
+    fs.add_file("tzdata.zi", "# version test\n\
+                              R T 2000 ma - Mar lastSu 1u 1 S\n\
+                              R T 2000 ma - O lastSu 1u 0 -\n\
+                              Z Test/Zone 0:30 - LMT 1900 Ja\n\
+                              0 T T%sT\n\
+                              L Test/Zone Test/Link\n");
+
+    let report = run_with(vec![ "convert", "--output", "europe", "tzdata.zi" ], &FixedClock(0), &fs).unwrap();
+    assert_eq!((report.zone_count, report.link_count), (2, 1));
+    assert_eq!(fs.contents("europe").unwrap(),
+               "Rule\tT\t2000\tmax\t-\tMar\tlastSun\t1:00u\t1:00\tS\n\
+                Rule\tT\t2000\tmax\t-\tOct\tlastSun\t1:00u\t0\t-\n\
+                Zone\tTest/Zone\t0:30\t-\tLMT\t1900 Jan\n\
+                \t\t\t0:00\tT\tT%sT\n\
+                Link\tTest/Zone\tTest/Link\n");
+
+    let _ = run_with(vec![ "convert", "--output", "slim.zi", "europe" ], &FixedClock(0), &fs).unwrap();
+    assert_eq!(fs.contents("slim.zi").unwrap(),
+               "R T 2000 ma - Mar lastSu 1u 1 S\n\
+                R T 2000 ma - O lastSu 1u 0 -\n\
+                Z Test/Zone 0:30 - LMT 1900 Ja\n\
+                0 T T%sT\n\
+                L Test/Zone Test/Link\n");
+
+    let err = run_with(vec![ "convert", "--output", "out", "--to", "thin", "europe" ], &FixedClock(0), &fs).unwrap_err();
+    assert_eq!(err.exit_code(), EXIT_USAGE);
+}
+
+#[test]
+fn diff_releases() {
+    let fs = MemoryFilesystem::new();
+    fs.add_file("old/europe", "Rule  Test  2000  max  -  Mar  lastSun  1:00u  1:00  S\n\
+                               Rule  Test  2000  max  -  Oct  lastSun  1:00u  0     -\n\
+                               Zone  Test/Zone  0:00  Test  T%sT\n\
+                               Zone  Test/Same  0:00  Test  T%sT\n");
+    fs.add_file("new/europe", "Rule  Test  2000  max  -  Mar  lastSun  1:00u  1:00  S\n\
+                               Rule  Test  2000  max  -  Oct  lastSun  1:00u  0     -\n\
+                               Zone  Test/Zone  0:00  Test  T%sT  2025 Mar 30 1:00u\n\
+                               \x20                1:00  -     TST\n\
+                               Zone  Test/Same  0:00  Test  T%sT\n");
+
+    // 2025-01-01, with the transitions going up to the end of 2025.
+    let report = run_with(vec![ "diff", "--max-year", "2025", "--output", "diff.txt", "old", "new" ], &FixedClock(1_735_689_600), &fs).unwrap();
+    assert_eq!(report.zone_count, 1);
+    assert_eq!(fs.contents("diff.txt").unwrap(),
+               "Test/Zone: future transitions changed\n\
+                - 2025-03-30 01:00:00 UTC: offset +01:00, abbreviation TST, DST yes\n\
+                + 2025-03-30 01:00:00 UTC: offset +01:00, abbreviation TST, DST no\n\
+                - 2025-10-26 01:00:00 UTC: offset +00:00, abbreviation TT, DST no\n");
+    assert_eq!(report.output, "");
+
+    let report = run_with(vec![ "diff", "--max-year", "2025", "old", "new" ], &FixedClock(1_735_689_600), &fs).unwrap();
+    assert_eq!(report.output, fs.contents("diff.txt").unwrap());
+
+    let err = run_with(vec![ "diff", "old" ], &FixedClock(0), &fs).unwrap_err();
+    assert_eq!(err.exit_code(), EXIT_USAGE);
+}
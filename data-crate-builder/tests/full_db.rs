@@ -0,0 +1,98 @@
+//! End-to-end test against a complete copy of the tz database.
+//!
+//! This is opt-in, as the data isn’t bundled with this repository. To run
+//! it, point the `ZONEINFO_TEST_DATA` environment variable at a directory
+//! containing the tzdata source files, and enable the feature:
+//!
+//! ```text
+//! ZONEINFO_TEST_DATA=/path/to/tzdata cargo test --features full-db
+//! ```
+//!
+//! It parses every file, checks some invariants that should hold for every
+//! time zone, and then generates the data crate into a temporary directory.
+//! The timings of each stage are printed (use `--nocapture` to see them),
+//! and if `ZONEINFO_TEST_MAX_SECONDS` is set, the whole run fails if it
+//! takes longer than that.
+
+#![cfg(feature = "full-db")]
+
+extern crate build_data_crate;
+
+use std::env::{var, temp_dir};
+use std::fs::{create_dir_all, remove_dir_all};
+use std::path::PathBuf;
+use std::time::Instant;
+
+use build_data_crate::data_crate::{DataCrate, Options};
+use build_data_crate::system::RealFilesystem;
+
+
+/// The files in the tz database that contain zone, rule, and link lines.
+static SOURCE_FILES: &'static [&'static str] = &[
+    "africa", "antarctica", "asia", "australasia", "europe",
+    "northamerica", "southamerica", "etcetera", "backward",
+];
+
+/// No place on Earth has ever been more than a day away from UTC.
+const MAX_OFFSET: i64 = 24 * 60 * 60;
+
+
+#[test]
+fn full_database() {
+    let data_dir = match var("ZONEINFO_TEST_DATA") {
+        Ok(dir) => PathBuf::from(dir),
+        Err(_)  => panic!("The full-db feature needs ZONEINFO_TEST_DATA to point to a tzdata directory"),
+    };
+
+    let inputs: Vec<String> = SOURCE_FILES.iter()
+        .map(|name| data_dir.join(name).to_str().unwrap().to_owned())
+        .collect();
+
+    let output_dir = temp_dir().join("build-data-crate-full-db");
+    let _ = remove_dir_all(&output_dir);
+    create_dir_all(&output_dir).unwrap();
+
+    let start = Instant::now();
+    let (data_crate, warnings) = DataCrate::new(&RealFilesystem, output_dir.clone(), &inputs, &Options::default()).unwrap();
+    for warning in &warnings {
+        println!("Warning: {}", warning);
+    }
+    println!("Parsed database in {:?}", start.elapsed());
+
+    let checking = Instant::now();
+    let dataset = data_crate.dataset();
+    assert!(dataset.zonesets.len() > 300, "Only {} zones found", dataset.zonesets.len());
+
+    for name in dataset.names() {
+        let set = dataset.timespans(name).unwrap_or_else(|| panic!("No timespans for {}", name));
+
+        assert!(!set.first.name.is_empty(), "{} has an empty abbreviation", name);
+        assert!(set.first.total_offset().abs() < MAX_OFFSET, "{} has a silly offset", name);
+
+        let mut previous = None;
+        for &(time, ref timespan) in &set.rest {
+            assert!(!timespan.name.is_empty(), "{} has an empty abbreviation at {}", name, time);
+            assert!(timespan.total_offset().abs() < MAX_OFFSET, "{} has a silly offset at {}", name, time);
+
+            if let Some(previous) = previous {
+                assert!(time > previous, "{} has out-of-order transitions at {}", name, time);
+            }
+            previous = Some(time);
+        }
+    }
+    println!("Checked invariants in {:?}", checking.elapsed());
+
+    let writing = Instant::now();
+    data_crate.run(&RealFilesystem).unwrap();
+    println!("Generated crate in {:?}", writing.elapsed());
+
+    assert!(output_dir.join("mod.rs").is_file());
+    assert!(output_dir.join("Europe").join("London.rs").is_file());
+    assert!(output_dir.join("America").join("Argentina").join("mod.rs").is_file());
+
+    if let Ok(max) = var("ZONEINFO_TEST_MAX_SECONDS") {
+        let max: u64 = max.parse().expect("ZONEINFO_TEST_MAX_SECONDS should be a number");
+        let taken = start.elapsed();
+        assert!(taken.as_secs() < max, "Full run took {:?}, more than {} seconds", taken, max);
+    }
+}
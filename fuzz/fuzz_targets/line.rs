@@ -0,0 +1,13 @@
+#![no_main]
+#[macro_use] extern crate libfuzzer_sys;
+extern crate zoneinfo_parse;
+
+use std::str;
+use zoneinfo_parse::line::Line;
+
+// Any line at all should either parse or fail to, without panicking.
+fuzz_target!(|data: &[u8]| {
+    if let Ok(input) = str::from_utf8(data) {
+        let _ = Line::from_str(input);
+    }
+});
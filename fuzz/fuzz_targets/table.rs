@@ -0,0 +1,29 @@
+#![no_main]
+#[macro_use] extern crate libfuzzer_sys;
+extern crate zoneinfo_parse;
+
+use std::str;
+use zoneinfo_parse::line::Line;
+use zoneinfo_parse::table::TableBuilder;
+
+// Feeds every line that parses into a table builder, as a file would be,
+// skipping the ones it rejects, and then builds the table.
+fuzz_target!(|data: &[u8]| {
+    let input = match str::from_utf8(data) {
+        Ok(input) => input,
+        Err(_)    => return,
+    };
+
+    let mut builder = TableBuilder::new();
+    for line in input.lines() {
+        let _ = match Line::from_str(line) {
+            Ok(Line::Zone(zone))         => builder.add_zone_line(zone),
+            Ok(Line::Continuation(cont)) => builder.add_continuation_line(cont),
+            Ok(Line::Rule(rule))         => builder.add_rule_line(rule),
+            Ok(Line::Link(link))         => builder.add_link_line(link),
+            Ok(Line::Space) | Err(_)     => continue,
+        };
+    }
+
+    let _ = builder.build();
+});
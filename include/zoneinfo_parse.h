@@ -0,0 +1,118 @@
+#ifndef ZONEINFO_PARSE_H
+#define ZONEINFO_PARSE_H
+
+#include <stdarg.h>
+#include <stdbool.h>
+#include <stddef.h>
+#include <stdint.h>
+#include <stdlib.h>
+
+// The number of bytes an abbreviation can take up in a `ZoneinfoTimespan`,
+// including the null byte at the end. The database’s abbreviations are at
+// most six letters long, so longer ones only come from mistakes, and get
+// cut short, without splitting a character in two.
+#define ZONEINFO_NAME_LEN 16
+
+// A table of zones read from source files, handed to C as an opaque
+// pointer.
+typedef struct ZoneinfoTable ZoneinfoTable;
+
+// The transitions of one zone between two instants, being stepped through
+// from C.
+typedef struct ZoneinfoTransitions ZoneinfoTransitions;
+
+// A period of time with a fixed offset, in a form C can read.
+typedef struct ZoneinfoTimespan {
+  // The number of seconds offset from UTC, not counting daylight-saving
+  // time.
+  int64_t utc_offset;
+  // The number of extra daylight-saving seconds.
+  int64_t dst_offset;
+  // Whether this timespan counts as daylight-saving time.
+  bool is_dst;
+  // The abbreviation in use, such as `CEST`, as a null-terminated
+  // string.
+  char name[ZONEINFO_NAME_LEN];
+} ZoneinfoTimespan;
+
+// Reads and parses the source files at the given paths, in order, into a
+// table. Returns the table, or null if any file couldn’t be read or had
+// lines that couldn’t be parsed, in which case, if `error` isn’t null, it
+// gets pointed at a description of what went wrong, with one line for
+// each problem.
+//
+// # Safety
+//
+// `paths` must point to `path_count` null-terminated strings, or be null
+// if `path_count` is zero.
+struct ZoneinfoTable *zoneinfo_table_parse(const char *const *paths,
+                                           size_t path_count,
+                                           char **error);
+
+// Frees a table returned by `zoneinfo_table_parse`. Does nothing if it’s
+// null.
+//
+// # Safety
+//
+// `table` must have come from `zoneinfo_table_parse`, must not have been
+// freed already, and must not have any transitions handles still using it.
+void zoneinfo_table_free(struct ZoneinfoTable *table);
+
+// Fills in `timespan` with the timespan in effect at the given Unix
+// timestamp in the zone with the given name. Returns whether the table has
+// a zone with that name; if it doesn’t, `timespan` is left alone.
+//
+// # Safety
+//
+// `table` must be a table from `zoneinfo_table_parse`, `zone_name` a
+// null-terminated string, and `timespan` somewhere to write one to.
+bool zoneinfo_offset_at(const struct ZoneinfoTable *table,
+                        const char *zone_name,
+                        int64_t timestamp,
+                        struct ZoneinfoTimespan *timespan);
+
+// Returns a handle for stepping through the transitions in the zone with
+// the given name that happen at or after the `start` Unix timestamp and
+// before the `end` one, or null if the table has no zone with that name.
+//
+// # Safety
+//
+// `table` must be a table from `zoneinfo_table_parse`, and must not be
+// freed until the handle has been, and `zone_name` must be a
+// null-terminated string.
+struct ZoneinfoTransitions *zoneinfo_transitions(const struct ZoneinfoTable *table,
+                                                 const char *zone_name,
+                                                 int64_t start,
+                                                 int64_t end);
+
+// Moves on to the next transition, filling in `at` with the Unix timestamp
+// it happens at and `timespan` with the timespan that comes into effect
+// then. Returns false, leaving them alone, once there are none left.
+//
+// # Safety
+//
+// `transitions` must be a handle from `zoneinfo_transitions`, and `at` and
+// `timespan` somewhere to write to.
+bool zoneinfo_transitions_next(struct ZoneinfoTransitions *transitions,
+                               int64_t *at,
+                               struct ZoneinfoTimespan *timespan);
+
+// Frees a handle returned by `zoneinfo_transitions`. Does nothing if it’s
+// null.
+//
+// # Safety
+//
+// `transitions` must have come from `zoneinfo_transitions`, and must not
+// have been freed already.
+void zoneinfo_transitions_free(struct ZoneinfoTransitions *transitions);
+
+// Frees a string returned by one of these functions. Does nothing if it’s
+// null.
+//
+// # Safety
+//
+// `string` must have come from one of these functions, and must not have
+// been freed already.
+void zoneinfo_string_free(char *string);
+
+#endif  /* ZONEINFO_PARSE_H */
@@ -0,0 +1,68 @@
+//! Reporting what this build of the crate is able to do.
+//!
+//! Programs that drive a generator built on this crate can call
+//! `capabilities` to check that it supports the features they need before
+//! asking it to do anything, rather than finding out halfway through a run.
+//! The lists only ever get added to, so checking for the presence of an
+//! entry is enough.
+//!
+//! ## Example
+//!
+//! ```
+//! let caps = zoneinfo_parse::capabilities();
+//! assert!(caps.grammar.contains(&"link"));
+//! ```
+
+
+/// A description of the parts of the zoneinfo format this build
+/// understands, and which optional features it was compiled with.
+#[derive(PartialEq, Debug, Clone)]
+pub struct Capabilities {
+
+    /// The version of this crate, as given in its Cargo manifest.
+    pub version: &'static str,
+
+    /// The grammar features the line parser understands, such as the kinds
+    /// of line it can read.
+    pub grammar: Vec<&'static str>,
+
+    /// The input formats that can be read.
+    pub formats: Vec<&'static str>,
+
+    /// The optional Cargo features this build was compiled with.
+    pub features: Vec<&'static str>,
+}
+
+
+/// Returns the capabilities of this build of the crate.
+pub fn capabilities() -> Capabilities {
+    let mut caps = Capabilities {
+        version:  env!("CARGO_PKG_VERSION"),
+        grammar:  vec![ "rule", "zone", "continuation", "link" ],
+        formats:  vec![ "zic" ],
+        features: Vec::new(),
+    };
+
+    if cfg!(feature = "geo") {
+        caps.formats.push("geojson");
+        caps.features.push("geo");
+    }
+
+    if cfg!(feature = "chrono") {
+        caps.features.push("chrono");
+    }
+
+    if cfg!(feature = "time") {
+        caps.features.push("time");
+    }
+
+    if cfg!(feature = "wasm") {
+        caps.features.push("wasm");
+    }
+
+    if cfg!(feature = "capi") {
+        caps.features.push("capi");
+    }
+
+    caps
+}
@@ -0,0 +1,462 @@
+//! Using the parser from C, and other languages that can call C functions.
+//!
+//! Services written in other languages would otherwise have to shell out to
+//! `zdump` to find a zone’s offsets. The functions here let them link
+//! against this crate instead: `zoneinfo_table_parse` reads source files
+//! into an opaque table handle, `zoneinfo_offset_at` gives the timespan in
+//! effect at an instant in one of its zones, and `zoneinfo_transitions`
+//! returns a handle for stepping through a zone’s transitions between two
+//! instants with `zoneinfo_transitions_next`. They’re declared for C in
+//! `include/zoneinfo_parse.h`.
+//!
+//! Every handle has to be given back to the function that frees it, and
+//! every string this module returns has to be freed with
+//! `zoneinfo_string_free`, as they were allocated by Rust rather than by
+//! `malloc`. Strings passed in have to be UTF-8, and nothing here keeps
+//! any state between calls, so handles can be used from any thread, as
+//! long as no two threads use the same transitions handle at once.
+//!
+//! Panicking inside one of these functions is caught before it can unwind
+//! into C, and the function returns null or false instead, as it would for
+//! a zone that doesn’t exist.
+//!
+//! It is only available when the crate is compiled with the `capi`
+//! feature.
+//!
+//! ## Example
+//!
+//! ```c
+//! char *error = NULL;
+//! const char *paths[] = { "tzdata/europe" };
+//! ZoneinfoTable *table = zoneinfo_table_parse(paths, 1, &error);
+//! if (table == NULL) {
+//!     fprintf(stderr, "%s\n", error);
+//!     zoneinfo_string_free(error);
+//!     return 1;
+//! }
+//!
+//! ZoneinfoTimespan timespan;
+//! if (zoneinfo_offset_at(table, "Europe/Paris", 1719792000, &timespan)) {
+//!     printf("%s is %lld seconds ahead\n", timespan.name, (long long) (timespan.utc_offset + timespan.dst_offset));
+//! }
+//!
+//! zoneinfo_table_free(table);
+//! ```
+
+use std::ffi::{CStr, CString};
+use std::fs::File;
+use std::io::Read;
+use std::os::raw::c_char;
+use std::panic::{self, AssertUnwindSafe};
+use std::ptr;
+use std::slice;
+
+use parse::parse_sources;
+use table::Table;
+use transitions::{FixedTimespan, TableTransitions, TransitionOptions, TransitionsBetween};
+
+
+/// The number of bytes an abbreviation can take up in a `ZoneinfoTimespan`,
+/// including the null byte at the end. The database’s abbreviations are at
+/// most six letters long, so longer ones only come from mistakes, and get
+/// cut short, without splitting a character in two.
+pub const ZONEINFO_NAME_LEN: usize = 16;
+
+
+/// A table of zones read from source files, handed to C as an opaque
+/// pointer.
+#[derive(Debug)]
+pub struct ZoneinfoTable {
+    table: Table,
+}
+
+/// The transitions of one zone between two instants, being stepped through
+/// from C.
+#[derive(Debug)]
+pub struct ZoneinfoTransitions {
+
+    // This borrows the table it came from for as long as C keeps the
+    // handle around, so the table must not be freed before it is.
+    transitions: TransitionsBetween<'static>,
+}
+
+/// A period of time with a fixed offset, in a form C can read.
+#[repr(C)]
+#[derive(Copy, Clone)]
+pub struct ZoneinfoTimespan {
+
+    /// The number of seconds offset from UTC, not counting daylight-saving
+    /// time.
+    pub utc_offset: i64,
+
+    /// The number of extra daylight-saving seconds.
+    pub dst_offset: i64,
+
+    /// Whether this timespan counts as daylight-saving time.
+    pub is_dst: bool,
+
+    /// The abbreviation in use, such as `CEST`, as a null-terminated
+    /// string.
+    pub name: [c_char; ZONEINFO_NAME_LEN],
+}
+
+impl ::std::fmt::Debug for ZoneinfoTimespan {
+    fn fmt(&self, f: &mut ::std::fmt::Formatter) -> ::std::fmt::Result {
+        let name = unsafe { CStr::from_ptr(self.name.as_ptr()) };
+        f.debug_struct("ZoneinfoTimespan")
+         .field("utc_offset", &self.utc_offset)
+         .field("dst_offset", &self.dst_offset)
+         .field("is_dst", &self.is_dst)
+         .field("name", &name)
+         .finish()
+    }
+}
+
+impl<'a> From<&'a FixedTimespan> for ZoneinfoTimespan {
+    fn from(timespan: &'a FixedTimespan) -> ZoneinfoTimespan {
+        let mut end = ::std::cmp::min(timespan.name.len(), ZONEINFO_NAME_LEN - 1);
+        while !timespan.name.is_char_boundary(end) {
+            end -= 1;
+        }
+
+        let mut name = [0; ZONEINFO_NAME_LEN];
+        for (byte, c) in timespan.name[.. end].bytes().zip(name.iter_mut()) {
+            *c = byte as c_char;
+        }
+
+        ZoneinfoTimespan {
+            utc_offset: timespan.utc_offset,
+            dst_offset: timespan.dst_offset,
+            is_dst:     timespan.is_dst,
+            name:       name,
+        }
+    }
+}
+
+
+/// Reads and parses the source files at the given paths, in order, into a
+/// table. Returns the table, or null if any file couldn’t be read or had
+/// lines that couldn’t be parsed, in which case, if `error` isn’t null, it
+/// gets pointed at a description of what went wrong, with one line for
+/// each problem.
+///
+/// # Safety
+///
+/// `paths` must point to `path_count` null-terminated strings, or be null
+/// if `path_count` is zero.
+#[no_mangle]
+pub unsafe extern "C" fn zoneinfo_table_parse(paths: *const *const c_char, path_count: usize, error: *mut *mut c_char) -> *mut ZoneinfoTable {
+    match panic::catch_unwind(AssertUnwindSafe(|| read_table(paths, path_count, error))) {
+        Ok(table)  => table,
+        Err(_)     => {
+            set_error(error, "Internal error while parsing".to_owned());
+            ptr::null_mut()
+        },
+    }
+}
+
+unsafe fn read_table(paths: *const *const c_char, path_count: usize, error: *mut *mut c_char) -> *mut ZoneinfoTable {
+    let paths = if paths.is_null() {
+        if path_count > 0 {
+            set_error(error, "No paths given".to_owned());
+            return ptr::null_mut();
+        }
+
+        &[][..]
+    }
+    else {
+        slice::from_raw_parts(paths, path_count)
+    };
+
+    let mut sources = Vec::new();
+    for &path in paths {
+        let path = match string_argument(path) {
+            Some(path)  => path,
+            None        => {
+                set_error(error, "Path is not a UTF-8 string".to_owned());
+                return ptr::null_mut();
+            },
+        };
+
+        let mut contents = String::new();
+        if let Err(e) = File::open(path).and_then(|mut f| f.read_to_string(&mut contents)) {
+            set_error(error, format!("{}: {}", path, e));
+            return ptr::null_mut();
+        }
+
+        sources.push((path, contents));
+    }
+
+    match parse_sources(sources.iter().map(|&(path, ref contents)| (path, &**contents))) {
+        Ok(table)   => Box::into_raw(Box::new(ZoneinfoTable { table: table })),
+        Err(errors) => {
+            let messages: Vec<String> = errors.iter().map(|e| e.to_string()).collect();
+            set_error(error, messages.join("\n"));
+            ptr::null_mut()
+        },
+    }
+}
+
+/// Frees a table returned by `zoneinfo_table_parse`. Does nothing if it’s
+/// null.
+///
+/// # Safety
+///
+/// `table` must have come from `zoneinfo_table_parse`, must not have been
+/// freed already, and must not have any transitions handles still using it.
+#[no_mangle]
+pub unsafe extern "C" fn zoneinfo_table_free(table: *mut ZoneinfoTable) {
+    if !table.is_null() {
+        catch_panic((), || drop(Box::from_raw(table)));
+    }
+}
+
+/// Fills in `timespan` with the timespan in effect at the given Unix
+/// timestamp in the zone with the given name. Returns whether the table has
+/// a zone with that name; if it doesn’t, `timespan` is left alone.
+///
+/// # Safety
+///
+/// `table` must be a table from `zoneinfo_table_parse`, `zone_name` a
+/// null-terminated string, and `timespan` somewhere to write one to.
+#[no_mangle]
+pub unsafe extern "C" fn zoneinfo_offset_at(table: *const ZoneinfoTable, zone_name: *const c_char, timestamp: i64, timespan: *mut ZoneinfoTimespan) -> bool {
+    if table.is_null() || timespan.is_null() {
+        return false;
+    }
+
+    let zone_name = match string_argument(zone_name) {
+        Some(name)  => name,
+        None        => return false,
+    };
+
+    catch_panic(false, || {
+        match (*table).table.offset_at(zone_name, timestamp, &TransitionOptions::default()) {
+            Some(found) => {
+                *timespan = ZoneinfoTimespan::from(&found);
+                true
+            },
+            None => false,
+        }
+    })
+}
+
+/// Returns a handle for stepping through the transitions in the zone with
+/// the given name that happen at or after the `start` Unix timestamp and
+/// before the `end` one, or null if the table has no zone with that name.
+///
+/// # Safety
+///
+/// `table` must be a table from `zoneinfo_table_parse`, and must not be
+/// freed until the handle has been, and `zone_name` must be a
+/// null-terminated string.
+#[no_mangle]
+pub unsafe extern "C" fn zoneinfo_transitions(table: *const ZoneinfoTable, zone_name: *const c_char, start: i64, end: i64) -> *mut ZoneinfoTransitions {
+    if table.is_null() {
+        return ptr::null_mut();
+    }
+
+    let zone_name = match string_argument(zone_name) {
+        Some(name)  => name,
+        None        => return ptr::null_mut(),
+    };
+
+    let table: &'static Table = &(*table).table;
+    catch_panic(ptr::null_mut(), || {
+        match table.transitions_between(zone_name, start, end, &TransitionOptions::default()) {
+            Some(transitions)  => Box::into_raw(Box::new(ZoneinfoTransitions { transitions: transitions })),
+            None               => ptr::null_mut(),
+        }
+    })
+}
+
+/// Moves on to the next transition, filling in `at` with the Unix timestamp
+/// it happens at and `timespan` with the timespan that comes into effect
+/// then. Returns false, leaving them alone, once there are none left.
+///
+/// # Safety
+///
+/// `transitions` must be a handle from `zoneinfo_transitions`, and `at` and
+/// `timespan` somewhere to write to.
+#[no_mangle]
+pub unsafe extern "C" fn zoneinfo_transitions_next(transitions: *mut ZoneinfoTransitions, at: *mut i64, timespan: *mut ZoneinfoTimespan) -> bool {
+    if transitions.is_null() || at.is_null() || timespan.is_null() {
+        return false;
+    }
+
+    catch_panic(false, || {
+        match (*transitions).transitions.next() {
+            Some((instant, next)) => {
+                *at = instant;
+                *timespan = ZoneinfoTimespan::from(&next);
+                true
+            },
+            None => false,
+        }
+    })
+}
+
+/// Frees a handle returned by `zoneinfo_transitions`. Does nothing if it’s
+/// null.
+///
+/// # Safety
+///
+/// `transitions` must have come from `zoneinfo_transitions`, and must not
+/// have been freed already.
+#[no_mangle]
+pub unsafe extern "C" fn zoneinfo_transitions_free(transitions: *mut ZoneinfoTransitions) {
+    if !transitions.is_null() {
+        catch_panic((), || drop(Box::from_raw(transitions)));
+    }
+}
+
+/// Frees a string returned by one of these functions. Does nothing if it’s
+/// null.
+///
+/// # Safety
+///
+/// `string` must have come from one of these functions, and must not have
+/// been freed already.
+#[no_mangle]
+pub unsafe extern "C" fn zoneinfo_string_free(string: *mut c_char) {
+    if !string.is_null() {
+        drop(CString::from_raw(string));
+    }
+}
+
+
+/// Runs the body of one of the functions above, returning the given value
+/// instead if it panics, as unwinding into C is undefined behaviour.
+fn catch_panic<T, F: FnOnce() -> T>(on_panic: T, body: F) -> T {
+    panic::catch_unwind(AssertUnwindSafe(body)).unwrap_or(on_panic)
+}
+
+/// Reads a string argument, returning `None` if it’s null or isn’t UTF-8.
+unsafe fn string_argument<'a>(string: *const c_char) -> Option<&'a str> {
+    if string.is_null() {
+        None
+    }
+    else {
+        CStr::from_ptr(string).to_str().ok()
+    }
+}
+
+/// Points `error` at a copy of the given message, if it isn’t null.
+unsafe fn set_error(error: *mut *mut c_char, message: String) {
+    if !error.is_null() {
+        // Errors can quote the source files, which could have null bytes
+        // in them, and C strings can’t.
+        let message = message.replace('\0', "");
+        *error = CString::new(message).unwrap().into_raw();
+    }
+}
+
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::env;
+    use std::fs;
+    use std::io::Write;
+
+    /// Writes the given source text to a file in the temporary directory,
+    /// returning its path.
+    fn source_file(name: &str, text: &str) -> CString {
+        let path = env::temp_dir().join(format!("zoneinfo_parse-capi-{}-{}", name, ::std::process::id()));
+        File::create(&path).unwrap().write_all(text.as_bytes()).unwrap();
+        CString::new(path.to_str().unwrap()).unwrap()
+    }
+
+    fn empty_timespan() -> ZoneinfoTimespan {
+        ZoneinfoTimespan { utc_offset: 0, dst_offset: 0, is_dst: false, name: [0; ZONEINFO_NAME_LEN] }
+    }
+
+    fn name(timespan: &ZoneinfoTimespan) -> &str {
+        unsafe { CStr::from_ptr(timespan.name.as_ptr()) }.to_str().unwrap()
+    }
+
+    #[test]
+    fn offsets_and_transitions() {
+        let path = source_file("europe", "Rule  EU  1981  max  -  Mar  lastSun  1:00u  1:00  S\n\
+                                          Rule  EU  1996  max  -  Oct  lastSun  1:00u  0     -\n\
+                                          Zone  Europe/Paris  1:00  EU  CE%sT\n");
+
+        unsafe {
+            let mut error = ptr::null_mut();
+            let table = zoneinfo_table_parse(&path.as_ptr(), 1, &mut error);
+            assert!(!table.is_null());
+            assert!(error.is_null());
+
+            let zone = CString::new("Europe/Paris").unwrap();
+            let mut timespan = empty_timespan();
+            assert!(zoneinfo_offset_at(table, zone.as_ptr(), 1_719_792_000, &mut timespan));
+            assert_eq!((timespan.utc_offset, timespan.dst_offset, timespan.is_dst), (3600, 3600, true));
+            assert_eq!(name(&timespan), "CEST");
+
+            let transitions = zoneinfo_transitions(table, zone.as_ptr(), 1_704_067_200, 1_735_689_600);
+            let mut at = 0;
+            let mut found = Vec::new();
+            while zoneinfo_transitions_next(transitions, &mut at, &mut timespan) {
+                found.push((at, name(&timespan).to_owned()));
+            }
+            zoneinfo_transitions_free(transitions);
+            assert_eq!(found, vec![ (1_711_846_800, "CEST".to_owned()), (1_729_990_800, "CET".to_owned()) ]);
+
+            let missing = CString::new("Europe/Atlantis").unwrap();
+            assert!(!zoneinfo_offset_at(table, missing.as_ptr(), 0, &mut timespan));
+            assert!(zoneinfo_transitions(table, missing.as_ptr(), 0, 1).is_null());
+
+            zoneinfo_table_free(table);
+        }
+
+        fs::remove_file(path.to_str().unwrap()).unwrap();
+    }
+
+    #[test]
+    fn errors() {
+        let path = source_file("broken", "Zone  Europe/Paris\n");
+
+        unsafe {
+            let mut error = ptr::null_mut();
+            assert!(zoneinfo_table_parse(&path.as_ptr(), 1, &mut error).is_null());
+
+            let message = CStr::from_ptr(error).to_str().unwrap().to_owned();
+            zoneinfo_string_free(error);
+            assert!(message.starts_with(&format!("{}:1: ", path.to_str().unwrap())));
+        }
+
+        fs::remove_file(path.to_str().unwrap()).unwrap();
+    }
+
+    #[test]
+    fn long_names_get_cut_short() {
+        let timespan = FixedTimespan { utc_offset: 0, dst_offset: 0, is_dst: false, name: "ABCDEFGHIJKLMNOPQRSTUVWXYZ".to_owned() };
+        assert_eq!(name(&ZoneinfoTimespan::from(&timespan)), "ABCDEFGHIJKLMNO");
+
+        // Each of these is two bytes long, so the fifteenth byte would be
+        // half of the eighth one.
+        let timespan = FixedTimespan { utc_offset: 0, dst_offset: 0, is_dst: false, name: "ÅÅÅÅÅÅÅÅÅÅ".to_owned() };
+        assert_eq!(name(&ZoneinfoTimespan::from(&timespan)), "ÅÅÅÅÅÅÅ");
+    }
+
+    #[test]
+    fn no_paths() {
+        unsafe {
+            let mut error = ptr::null_mut();
+            let table = zoneinfo_table_parse(ptr::null(), 0, &mut error);
+            assert!(!table.is_null());
+            assert!(error.is_null());
+            zoneinfo_table_free(table);
+
+            assert!(zoneinfo_table_parse(ptr::null(), 1, &mut error).is_null());
+            assert_eq!(CStr::from_ptr(error).to_str().unwrap(), "No paths given");
+            zoneinfo_string_free(error);
+        }
+    }
+
+    #[test]
+    fn panics_stay_in_rust() {
+        assert!(!catch_panic(false, || panic!("oh no")));
+        assert!(catch_panic(false, || true));
+    }
+}
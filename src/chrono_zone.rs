@@ -0,0 +1,170 @@
+//! Using computed timespans as a `chrono` time zone.
+//!
+//! The `chrono` crate does its time zone arithmetic through its `TimeZone`
+//! trait, which usually gets implemented by code generated ahead of time,
+//! as the `chrono-tz` crate does. A `ChronoZone` implements that trait
+//! directly on top of a `FixedTimespanSet`, so a table parsed at runtime
+//! can be used with `chrono` straight away, without generating any code.
+//!
+//! A `ChronoZone` borrows the timespan set it was made from, so it’s cheap
+//! to copy around, as `chrono` expects time zones to be. Its offsets carry
+//! the timespan they come from, so a `DateTime` in the zone can be printed
+//! with the right abbreviation.
+//!
+//! It is only available when the crate is compiled with the `chrono`
+//! feature.
+//!
+//! ## Example
+//!
+//! ```
+//! # #[cfg(feature = "chrono")] extern crate chrono;
+//! # extern crate zoneinfo_parse;
+//! # #[cfg(feature = "chrono")] fn main() {
+//! use chrono::TimeZone;
+//! use zoneinfo_parse::chrono_zone::ChronoZone;
+//! use zoneinfo_parse::transitions::{FixedTimespan, FixedTimespanSet};
+//!
+//! let set = FixedTimespanSet {
+//!     first: FixedTimespan { utc_offset: 19_800, dst_offset: 0, is_dst: false, name: "IST".to_owned() },
+//!     rest:  vec![],
+//! };
+//!
+//! let zone = ChronoZone::from(&set);
+//! let time = zone.timestamp_opt(0, 0).unwrap();
+//! assert_eq!(time.to_string(), "1970-01-01 05:30:00 IST");
+//! # }
+//! # #[cfg(not(feature = "chrono"))] fn main() {}
+//! ```
+
+use std::fmt;
+
+use chrono::{FixedOffset, LocalResult, NaiveDate, NaiveDateTime, NaiveTime, Offset, TimeZone};
+use datetime::LocalDateTime;
+
+use transitions::{FixedTimespan, FixedTimespanSet, LocalResolution};
+
+
+/// A set of timespans, as a `chrono` time zone.
+#[derive(PartialEq, Debug, Copy, Clone)]
+pub struct ChronoZone<'set> {
+
+    /// The timespans this zone goes through.
+    pub timespans: &'set FixedTimespanSet,
+}
+
+impl<'set> From<&'set FixedTimespanSet> for ChronoZone<'set> {
+    fn from(timespans: &'set FixedTimespanSet) -> ChronoZone<'set> {
+        ChronoZone { timespans: timespans }
+    }
+}
+
+/// The offset of a `ChronoZone` at a particular instant: the timespan in
+/// effect then, along with the zone it’s from, so `chrono` can get the
+/// zone back from a `DateTime`.
+#[derive(PartialEq, Debug, Clone)]
+pub struct ChronoOffset<'set> {
+
+    /// The zone this offset is from.
+    pub zone: ChronoZone<'set>,
+
+    /// The timespan in effect, with its offsets and abbreviation.
+    pub timespan: FixedTimespan,
+}
+
+impl<'set> ChronoOffset<'set> {
+    fn new(zone: ChronoZone<'set>, timespan: FixedTimespan) -> ChronoOffset<'set> {
+        ChronoOffset {
+            zone:     zone,
+            timespan: timespan,
+        }
+    }
+}
+
+impl<'set> Offset for ChronoOffset<'set> {
+    fn fix(&self) -> FixedOffset {
+        // Offsets in the tz database are all well within a day, which is
+        // the most a `FixedOffset` can hold.
+        FixedOffset::east_opt(self.timespan.total_offset() as i32).unwrap()
+    }
+}
+
+/// Offsets display as their abbreviation, such as `BST`.
+impl<'set> fmt::Display for ChronoOffset<'set> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.timespan.name)
+    }
+}
+
+impl<'set> TimeZone for ChronoZone<'set> {
+    type Offset = ChronoOffset<'set>;
+
+    fn from_offset(offset: &ChronoOffset<'set>) -> ChronoZone<'set> {
+        offset.zone
+    }
+
+    fn offset_from_local_date(&self, local: &NaiveDate) -> LocalResult<ChronoOffset<'set>> {
+        self.offset_from_local_datetime(&local.and_time(NaiveTime::MIN))
+    }
+
+    fn offset_from_local_datetime(&self, local: &NaiveDateTime) -> LocalResult<ChronoOffset<'set>> {
+        let local = LocalDateTime::at(local.and_utc().timestamp());
+
+        match self.timespans.resolve_local(local) {
+            LocalResolution::Unique(timespan)              => LocalResult::Single(ChronoOffset::new(*self, timespan)),
+            LocalResolution::Ambiguous(earlier, later)     => LocalResult::Ambiguous(ChronoOffset::new(*self, earlier), ChronoOffset::new(*self, later)),
+            LocalResolution::Gap(_, _)                     => LocalResult::None,
+        }
+    }
+
+    fn offset_from_utc_date(&self, utc: &NaiveDate) -> ChronoOffset<'set> {
+        self.offset_from_utc_datetime(&utc.and_time(NaiveTime::MIN))
+    }
+
+    fn offset_from_utc_datetime(&self, utc: &NaiveDateTime) -> ChronoOffset<'set> {
+        let timespan = self.timespans.timespan_at(utc.and_utc().timestamp());
+        ChronoOffset::new(*self, timespan.clone())
+    }
+}
+
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use transitions::london_2024;
+    use chrono::{TimeZone, Timelike};
+
+    #[test]
+    fn from_utc() {
+        let set = london_2024();
+        let zone = ChronoZone::from(&set);
+
+        let summer = zone.timestamp_opt(1_711_846_800, 0).unwrap();
+        assert_eq!(summer.hour(), 2);
+        assert_eq!(summer.offset().to_string(), "BST");
+        assert_eq!(summer.offset().fix(), FixedOffset::east_opt(3600).unwrap());
+
+        let winter = zone.timestamp_opt(1_711_846_799, 0).unwrap();
+        assert_eq!(winter.hour(), 0);
+        assert_eq!(winter.offset().to_string(), "GMT");
+    }
+
+    #[test]
+    fn from_local() {
+        let set = london_2024();
+        let zone = ChronoZone::from(&set);
+
+        let summer = zone.with_ymd_and_hms(2024, 6, 1, 12, 0, 0).unwrap();
+        assert_eq!(summer.timestamp(), 1_717_239_600);
+
+        assert_eq!(zone.with_ymd_and_hms(2024, 3, 31, 1, 30, 0), LocalResult::None);
+
+        match zone.with_ymd_and_hms(2024, 10, 27, 1, 30, 0) {
+            LocalResult::Ambiguous(earlier, later) => {
+                assert_eq!(earlier.offset().to_string(), "BST");
+                assert_eq!(later.offset().to_string(), "GMT");
+                assert_eq!(later.timestamp() - earlier.timestamp(), 3600);
+            },
+            other => panic!("expected an ambiguous time, got {:?}", other),
+        }
+    }
+}
@@ -0,0 +1,915 @@
+//! Reading a table back out of a directory of compiled zone files.
+//!
+//! Plenty of systems only have the compiled form of the database installed:
+//! a tree of TZif files such as `/usr/share/zoneinfo`, one per zone, with
+//! links as symbolic or hard links to them. `Table::from_zoneinfo_dir`
+//! reconstructs a table from such a tree, so the rest of the crate can work
+//! with it as if it had been read from the text files.
+//!
+//! The reconstruction can’t be exact, as compiling throws information away:
+//!
+//! - Each zone becomes one zone line per transition, each with a fixed
+//!   offset and abbreviation, ending at the next transition. The rules that
+//!   the transitions came from are gone, apart from the ones in the POSIX
+//!   TZ string at the end of each file, which describe the zone after its
+//!   last transition. These become a rule set named after the zone. Only
+//!   the `Mm.w.d` form of TZ string rules is understood, which is the only
+//!   one that `zic` writes for the current data.
+//!
+//! - The files only say whether each offset is daylight-saving time, not
+//!   how much of it is, so the amount is taken to be the difference from
+//!   the nearest standard offset.
+//!
+//! - Hard links can’t tell which of their names is the zone and which are
+//!   the links, so the one listed in `zone1970.tab` or `zone.tab` is picked
+//!   if there is one, and otherwise the first name with a slash in, in
+//!   alphabetical order. Hard links are only spotted on Unix.
+//!
+//! The `posix` and `right` directories that some systems install alongside
+//! the zones, with copies of every zone in them, get skipped, as do any
+//! files that aren’t TZif files.
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::error::Error as ErrorTrait;
+use std::fmt;
+use std::fs::{self, File, Metadata};
+use std::io::{self, Read};
+use std::path::{Path, PathBuf};
+use std::str;
+use std::sync::Arc;
+
+use datetime::{LocalDateTime, DatePiece, TimePiece};
+use datetime::zone::TimeType;
+
+use line::{ChangeTime, YearSpec, MonthSpec, DaySpec, WeekdaySpec, TimeSpec, unquote_abbreviation};
+use tab::parse_zone_tab;
+use table::{Table, ZoneInfo, RuleInfo, Saving, Format};
+
+
+/// Returns the value inside an `Option`, or returns `None` from the
+/// function it’s used in. (This is `try!` for options.)
+macro_rules! try_opt {
+    ($e:expr) => (match $e { Some(v) => v, None => return None })
+}
+
+
+/// The directories of copies of the zones that get skipped.
+const SKIPPED_DIRECTORIES: &'static [&'static str] = &[ "posix", "right" ];
+
+/// The amount of daylight-saving time assumed when there’s no standard
+/// offset to compare against.
+const DEFAULT_SAVING: i64 = 3600;
+
+
+impl Table {
+
+    /// Reconstructs a table from a directory of compiled TZif files, such
+    /// as `/usr/share/zoneinfo`, with a zone for each file and a link for
+    /// each symbolic or hard link to one (see the `compiled` module).
+    pub fn from_zoneinfo_dir<P: AsRef<Path>>(path: P) -> Result<Table, Error> {
+        let base = path.as_ref();
+        let canonical_base = try!(fs::canonicalize(base).map_err(|e| Error::IO(base.to_path_buf(), e)));
+
+        let mut walk = Walk::default();
+        try!(walk.visit(&canonical_base, &canonical_base));
+
+        let mut table = Table::default();
+        let tabbed = tabbed_zones(base);
+
+        // Files that are the same file get turned into one zone, with the
+        // others linking to it.
+        let mut groups: BTreeMap<(u64, u64), (PathBuf, Vec<String>)> = BTreeMap::new();
+        let mut files = Vec::new();
+        for (name, path, id) in walk.files {
+            match id {
+                Some(id) => groups.entry(id).or_insert_with(|| (path, Vec::new())).1.push(name),
+                None     => files.push((name, path)),
+            }
+        }
+
+        for (_, (path, mut names)) in groups {
+            names.sort();
+            let zone_name = names.iter().find(|n| tabbed.contains(*n))
+                                 .or_else(|| names.iter().find(|n| n.contains('/')))
+                                 .unwrap_or(&names[0])
+                                 .clone();
+
+            for name in &names {
+                if *name != zone_name {
+                    let _ = table.links.insert(name.clone(), zone_name.clone());
+                }
+            }
+
+            files.push((zone_name, path));
+        }
+
+        for (name, path) in files {
+            let mut contents = Vec::new();
+            let _ = try!(File::open(&path).and_then(|mut f| f.read_to_end(&mut contents))
+                                          .map_err(|e| Error::IO(path.clone(), e)));
+
+            let tzif = try!(parse_tzif(&contents).map_err(|e| Error::Invalid(path.clone(), e)));
+            let (zoneset, rules) = reconstruct(&name, &tzif);
+            if let Some(rules) = rules {
+                let _ = table.rulesets.insert(name.clone(), rules);
+            }
+            let _ = table.zonesets.insert(name, zoneset);
+        }
+
+        // Symbolic links have been resolved all the way to a file, which
+        // might not be the name of the zone the file became.
+        for (name, target) in walk.links {
+            let target = table.links.get(&target).cloned().unwrap_or(target);
+            if table.zonesets.contains_key(&target) {
+                let _ = table.links.insert(name, target);
+            }
+        }
+
+        Ok(table)
+    }
+}
+
+
+/// The files and symbolic links found in a directory tree.
+#[derive(Default)]
+struct Walk {
+
+    /// Each file’s zone name, path, and device and inode numbers, if
+    /// they’re available.
+    files: Vec<(String, PathBuf, Option<(u64, u64)>)>,
+
+    /// Each symbolic link’s name, and the name of the file it points to.
+    links: Vec<(String, String)>,
+}
+
+impl Walk {
+
+    /// Adds every TZif file and link to one in the given directory, and its
+    /// subdirectories, to the walk. Both paths have to be canonical.
+    fn visit(&mut self, base: &Path, dir: &Path) -> Result<(), Error> {
+        let entries = try!(fs::read_dir(dir).map_err(|e| Error::IO(dir.to_path_buf(), e)));
+        let mut paths = Vec::new();
+        for entry in entries {
+            paths.push(try!(entry.map_err(|e| Error::IO(dir.to_path_buf(), e))).path());
+        }
+        paths.sort();
+
+        for path in paths {
+            let metadata = try!(fs::symlink_metadata(&path).map_err(|e| Error::IO(path.clone(), e)));
+            let file_name = path.file_name().and_then(|n| n.to_str()).unwrap_or("");
+
+            if metadata.is_dir() {
+                if !(dir == base && SKIPPED_DIRECTORIES.contains(&file_name)) {
+                    try!(self.visit(base, &path));
+                }
+                continue;
+            }
+
+            let name = match zone_name(base, &path) {
+                Some(name) => name,
+                None       => continue,
+            };
+
+            if metadata.file_type().is_symlink() {
+
+                // Links to directories, which some systems have to make
+                // `posix` the same as the top level, get skipped, so as not
+                // to go round in circles. A link to somewhere outside the
+                // tree, such as `localtime`, gets read as a zone of its own.
+                let target = match fs::canonicalize(&path) {
+                    Ok(ref target) if target.is_dir() => continue,
+                    Ok(target)                        => target,
+                    Err(_)                            => continue,
+                };
+
+                if let Some(target) = zone_name(base, &target) {
+                    self.links.push((name, target));
+                    continue;
+                }
+            }
+
+            if try!(is_tzif(&path)) {
+                let metadata = try!(fs::metadata(&path).map_err(|e| Error::IO(path.clone(), e)));
+                self.files.push((name, path, file_id(&metadata)));
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Returns the name of the zone at the given canonical path, relative to
+/// the canonical base directory, if it’s inside it.
+fn zone_name(base: &Path, path: &Path) -> Option<String> {
+    let relative = match path.strip_prefix(base) {
+        Ok(relative) => relative,
+        Err(_)       => return None,
+    };
+
+    let components: Option<Vec<&str>> = relative.components().map(|c| c.as_os_str().to_str()).collect();
+    components.map(|c| c.join("/"))
+}
+
+/// Returns whether the file at the given path starts like a TZif file.
+fn is_tzif(path: &Path) -> Result<bool, Error> {
+    let mut magic = [0; 4];
+    let mut file = try!(File::open(path).map_err(|e| Error::IO(path.to_path_buf(), e)));
+    match file.read_exact(&mut magic) {
+        Ok(())                                              => Ok(&magic == b"TZif"),
+        Err(ref e) if e.kind() == io::ErrorKind::UnexpectedEof => Ok(false),
+        Err(e)                                              => Err(Error::IO(path.to_path_buf(), e)),
+    }
+}
+
+/// Returns the device and inode numbers of a file, which are the same for
+/// every hard link to it.
+#[cfg(unix)]
+fn file_id(metadata: &Metadata) -> Option<(u64, u64)> {
+    use std::os::unix::fs::MetadataExt;
+    Some((metadata.dev(), metadata.ino()))
+}
+
+#[cfg(not(unix))]
+fn file_id(_: &Metadata) -> Option<(u64, u64)> {
+    None
+}
+
+/// Returns the names of the zones listed in the base directory’s
+/// `zone1970.tab` and `zone.tab` files, if it has them.
+fn tabbed_zones(base: &Path) -> BTreeSet<String> {
+    let mut zones = BTreeSet::new();
+
+    for file_name in &[ "zone1970.tab", "zone.tab" ] {
+        let mut contents = String::new();
+        if File::open(base.join(file_name)).and_then(|mut f| f.read_to_string(&mut contents)).is_err() {
+            continue;
+        }
+
+        if let Ok(rows) = parse_zone_tab(&contents) {
+            zones.extend(rows.into_iter().map(|row| row.zone));
+        }
+    }
+
+    zones
+}
+
+
+/// The contents of a TZif file that a table gets reconstructed from.
+#[derive(PartialEq, Debug, Clone)]
+struct Tzif {
+
+    /// The instant of each transition, and the index of the type that
+    /// starts then.
+    transitions: Vec<(i64, usize)>,
+
+    /// The local time types. The first is in effect before the first
+    /// transition.
+    types: Vec<LocalTimeType>,
+
+    /// The POSIX TZ string from the footer, if the file has one.
+    footer: Option<String>,
+}
+
+/// One local time type of a TZif file.
+#[derive(PartialEq, Debug, Clone)]
+struct LocalTimeType {
+    utc_offset: i64,
+    is_dst: bool,
+    abbreviation: String,
+}
+
+/// Parses the contents of a TZif file, using the 64-bit data block if
+/// there is one.
+fn parse_tzif(input: &[u8]) -> Result<Tzif, &'static str> {
+    let mut reader = Reader { input: input };
+    let (version, counts) = try!(reader.header());
+
+    if version == 0 {
+        return reader.data_block(counts, 4);
+    }
+
+    // Skip over the 32-bit block to the second header.
+    try!(reader.skip(try!(counts.block_size(4).ok_or("truncated file"))));
+    let (_, counts) = try!(reader.header());
+    let mut tzif = try!(reader.data_block(counts, 8));
+
+    if reader.input.first() == Some(&b'\n') {
+        if let Some(end) = reader.input[1..].iter().position(|&b| b == b'\n') {
+            let footer = try!(str::from_utf8(&reader.input[1 .. end + 1]).map_err(|_| "invalid footer"));
+            if !footer.is_empty() {
+                tzif.footer = Some(footer.to_owned());
+            }
+        }
+    }
+
+    Ok(tzif)
+}
+
+/// The numbers of each kind of thing in a TZif data block, as given in the
+/// header before it.
+#[derive(PartialEq, Debug, Copy, Clone)]
+struct Counts {
+    isutcnt: usize,
+    isstdcnt: usize,
+    leapcnt: usize,
+    timecnt: usize,
+    typecnt: usize,
+    charcnt: usize,
+}
+
+impl Counts {
+
+    /// Returns the size of the data block, in bytes, if its transition
+    /// times are the given number of bytes long, or `None` if the counts
+    /// are too big for that to be worked out, which no file that size can
+    /// have room for anyway.
+    fn block_size(&self, time_size: usize) -> Option<usize> {
+        let times = try_opt!(self.timecnt.checked_mul(time_size + 1));
+        let types = try_opt!(self.typecnt.checked_mul(6));
+        let tail = try_opt!(self.tail_size(time_size));
+        times.checked_add(types).and_then(|size| size.checked_add(tail))
+    }
+
+    /// Returns the size of the part of the data block after the local
+    /// time types: the abbreviations, leap seconds, and indicators.
+    fn tail_size(&self, time_size: usize) -> Option<usize> {
+        let leaps = try_opt!(self.leapcnt.checked_mul(time_size + 4));
+        self.charcnt.checked_add(leaps)
+            .and_then(|size| size.checked_add(self.isstdcnt))
+            .and_then(|size| size.checked_add(self.isutcnt))
+    }
+}
+
+/// Reads big-endian numbers from the front of a TZif file.
+struct Reader<'input> {
+    input: &'input [u8],
+}
+
+impl<'input> Reader<'input> {
+
+    /// Reads a header, returning the version number (with `0` for the first
+    /// version, and `2` for version `2`) and the counts.
+    fn header(&mut self) -> Result<(u8, Counts), &'static str> {
+        if !self.input.starts_with(b"TZif") || self.input.len() < 44 {
+            return Err("invalid header");
+        }
+
+        let version = match self.input[4] {
+            0 => 0,
+            v => v.wrapping_sub(b'0'),
+        };
+
+        try!(self.skip(20));
+        let counts = Counts {
+            isutcnt:   try!(self.count()),
+            isstdcnt:  try!(self.count()),
+            leapcnt:   try!(self.count()),
+            timecnt:   try!(self.count()),
+            typecnt:   try!(self.count()),
+            charcnt:   try!(self.count()),
+        };
+
+        Ok((version, counts))
+    }
+
+    /// Reads a data block whose transition times are the given number of
+    /// bytes long.
+    fn data_block(&mut self, counts: Counts, time_size: usize) -> Result<Tzif, &'static str> {
+        let Counts { timecnt, typecnt, charcnt, .. } = counts;
+
+        let mut times = Vec::new();
+        for _ in 0 .. timecnt {
+            times.push(try!(self.number(time_size)));
+        }
+
+        let mut transitions = Vec::new();
+        for time in times {
+            let index = try!(self.number(1)) as usize;
+            if index >= typecnt {
+                return Err("invalid type index");
+            }
+            transitions.push((time, index));
+        }
+
+        let mut raw_types = Vec::new();
+        for _ in 0 .. typecnt {
+            let utc_offset = try!(self.number(4));
+            let is_dst = try!(self.number(1)) != 0;
+            let index = try!(self.number(1)) as usize;
+            raw_types.push((utc_offset, is_dst, index));
+        }
+
+        if self.input.len() < charcnt {
+            return Err("truncated abbreviations");
+        }
+        let chars = &self.input[.. charcnt];
+
+        let mut types = Vec::new();
+        for (utc_offset, is_dst, index) in raw_types {
+            let abbreviation = match chars.get(index ..).and_then(|c| c.iter().position(|&b| b == 0).map(|end| &c[.. end])) {
+                Some(bytes) => try!(str::from_utf8(bytes).map_err(|_| "invalid abbreviation")),
+                None        => return Err("invalid abbreviation index"),
+            };
+
+            types.push(LocalTimeType {
+                utc_offset:   utc_offset,
+                is_dst:       is_dst,
+                abbreviation: abbreviation.to_owned(),
+            });
+        }
+
+        if types.is_empty() {
+            return Err("no local time types");
+        }
+
+        try!(self.skip(try!(counts.tail_size(time_size).ok_or("truncated file"))));
+
+        Ok(Tzif {
+            transitions: transitions,
+            types:       types,
+            footer:      None,
+        })
+    }
+
+    /// Reads a signed big-endian number with the given number of bytes.
+    fn number(&mut self, size: usize) -> Result<i64, &'static str> {
+        if self.input.len() < size {
+            return Err("truncated file");
+        }
+
+        let mut value: i64 = 0;
+        for &byte in &self.input[.. size] {
+            value = (value << 8) | byte as i64;
+        }
+
+        // Sign-extend anything shorter than 64 bits.
+        let shift = 64 - size as u32 * 8;
+        let value = if size == 1 { value } else { (value << shift) >> shift };
+
+        self.input = &self.input[size ..];
+        Ok(value)
+    }
+
+    /// Reads one of the header’s counts, which are unsigned 32-bit numbers.
+    fn count(&mut self) -> Result<usize, &'static str> {
+        self.number(4).map(|n| n as u32 as usize)
+    }
+
+    /// Skips over the given number of bytes.
+    fn skip(&mut self, count: usize) -> Result<(), &'static str> {
+        if self.input.len() < count {
+            return Err("truncated file");
+        }
+
+        self.input = &self.input[count ..];
+        Ok(())
+    }
+}
+
+
+/// Turns the contents of a TZif file into a zone set, along with the rules
+/// from its footer, if it has any that can be used, which get named after
+/// the zone.
+fn reconstruct(name: &str, tzif: &Tzif) -> (Vec<ZoneInfo>, Option<Vec<RuleInfo>>) {
+    let savings = dst_savings(tzif);
+    let mut zoneset = Vec::new();
+
+    // Each transition ends the line before it, at the wall clock time in
+    // effect until then, as that’s how zone lines’ end times are read.
+    let mut current = 0;
+    for &(at, index) in &tzif.transitions {
+        let wall_time = at + tzif.types[current].utc_offset;
+        zoneset.push(fixed_line(&tzif.types[current], savings[current], Some(until(wall_time))));
+        current = index;
+    }
+
+    let footer = tzif.footer.as_ref().and_then(|f| PosixTz::parse(f));
+    let last_year = tzif.transitions.last().map(|&(at, _)| LocalDateTime::at(at).year());
+
+    match (footer, last_year) {
+        (Some(PosixTz { std_abbreviation, std_offset, dst: Some(dst) }), Some(year)) => {
+            zoneset.push(ZoneInfo {
+                offset:   std_offset,
+                saving:   Saving::Multiple(name.to_owned()),
+                format:   Format::Alternate { standard: Arc::from(&*std_abbreviation), dst: Arc::from(&*dst.abbreviation) },
+                end_time: None,
+            });
+
+            let rules = vec![
+                footer_rule(year, dst.start, dst.offset - std_offset),
+                footer_rule(year, dst.end, 0),
+            ];
+
+            (zoneset, Some(rules))
+        },
+        _ => {
+            zoneset.push(fixed_line(&tzif.types[current], savings[current], None));
+            (zoneset, None)
+        },
+    }
+}
+
+/// Returns the amount of daylight-saving time in each local time type: the
+/// difference from the standard offset in effect just before it’s used,
+/// or just after if there isn’t one before.
+fn dst_savings(tzif: &Tzif) -> Vec<i64> {
+    let sequence: Vec<usize> = Some(0).into_iter().chain(tzif.transitions.iter().map(|t| t.1)).collect();
+    let mut savings = vec![ 0; tzif.types.len() ];
+
+    for (position, &index) in sequence.iter().enumerate() {
+        let t = &tzif.types[index];
+        if !t.is_dst || savings[index] != 0 {
+            continue;
+        }
+
+        let standard = sequence[.. position].iter().rev().chain(sequence[position ..].iter())
+                                            .map(|&i| &tzif.types[i])
+                                            .find(|t| !t.is_dst);
+
+        savings[index] = match standard {
+            Some(standard) if standard.utc_offset != t.utc_offset => t.utc_offset - standard.utc_offset,
+            _                                                     => DEFAULT_SAVING,
+        };
+    }
+
+    savings
+}
+
+/// Returns a zone line with the fixed offset and abbreviation of the given
+/// local time type.
+fn fixed_line(t: &LocalTimeType, saving: i64, end_time: Option<ChangeTime>) -> ZoneInfo {
+    ZoneInfo {
+        offset:   t.utc_offset - saving,
+        saving:   if saving == 0 { Saving::NoSaving } else { Saving::OneOff(saving) },
+        format:   Format::Constant(Arc::from(&*t.abbreviation)),
+        end_time: end_time,
+    }
+}
+
+/// Returns the end time of a zone line that ends at the given wall clock
+/// time, as a number of seconds since the epoch.
+fn until(wall_time: i64) -> ChangeTime {
+    let t = LocalDateTime::at(wall_time);
+    let time = TimeSpec::HoursMinutesSeconds(t.hour(), t.minute(), t.second()).with_type(TimeType::Wall);
+    ChangeTime::UntilTime(YearSpec::Number(t.year()), MonthSpec(t.month()), DaySpec::Ordinal(t.day()), time)
+}
+
+/// Returns a rule that applies every year from the given one onwards.
+fn footer_rule(from_year: i64, rule: PosixRule, time_to_add: i64) -> RuleInfo {
+    RuleInfo {
+        from_year:    YearSpec::Number(from_year),
+        to_year:      Some(YearSpec::Maximum),
+        month:        rule.month,
+        day:          rule.day,
+        time:         rule.time,
+        time_type:    TimeType::Wall,
+        time_to_add:  time_to_add,
+        is_dst:       None,
+        letters:      None,
+    }
+}
+
+
+/// The POSIX TZ string at the end of a TZif file.
+#[derive(PartialEq, Debug, Clone)]
+struct PosixTz {
+    std_abbreviation: String,
+
+    /// The standard offset, in seconds east of UTC, the opposite way round
+    /// to how it’s written.
+    std_offset: i64,
+
+    dst: Option<PosixDst>,
+}
+
+/// The daylight-saving time part of a POSIX TZ string.
+#[derive(PartialEq, Debug, Clone)]
+struct PosixDst {
+    abbreviation: String,
+    offset: i64,
+    start: PosixRule,
+    end: PosixRule,
+}
+
+/// When daylight-saving time starts or ends, at a wall clock time.
+#[derive(PartialEq, Debug, Copy, Clone)]
+struct PosixRule {
+    month: MonthSpec,
+    day: DaySpec,
+    time: i64,
+}
+
+impl PosixTz {
+
+    /// Parses a TZ string, returning `None` if it doesn’t parse, or if it
+    /// uses a form of rule that isn’t understood.
+    fn parse(input: &str) -> Option<PosixTz> {
+        let mut rest = input;
+
+        let std_abbreviation = try_opt!(abbreviation(&mut rest));
+        let std_offset = -try_opt!(offset(&mut rest));
+
+        if rest.is_empty() {
+            return Some(PosixTz { std_abbreviation: std_abbreviation, std_offset: std_offset, dst: None });
+        }
+
+        let dst_abbreviation = try_opt!(abbreviation(&mut rest));
+        let dst_offset = if rest.starts_with(',') { std_offset + DEFAULT_SAVING } else { -try_opt!(offset(&mut rest)) };
+
+        if !rest.starts_with(',') {
+            return None;
+        }
+        rest = &rest[1..];
+
+        let start = try_opt!(rule(&mut rest));
+        if !rest.starts_with(',') {
+            return None;
+        }
+        rest = &rest[1..];
+
+        let end = try_opt!(rule(&mut rest));
+        if !rest.is_empty() {
+            return None;
+        }
+
+        Some(PosixTz {
+            std_abbreviation: std_abbreviation,
+            std_offset: std_offset,
+            dst: Some(PosixDst { abbreviation: dst_abbreviation, offset: dst_offset, start: start, end: end }),
+        })
+    }
+}
+
+/// Reads an abbreviation, either quoted in angle brackets or as a run of
+/// letters.
+fn abbreviation(rest: &mut &str) -> Option<String> {
+    let (abbreviation, remainder) = if rest.starts_with('<') {
+        let end = try_opt!(rest.find('>'));
+        (try_opt!(unquote_abbreviation(&rest[.. end + 1]).ok()), &rest[end + 1 ..])
+    }
+    else {
+        let end = rest.find(|c: char| !c.is_alphabetic()).unwrap_or(rest.len());
+        (&rest[.. end], &rest[end ..])
+    };
+
+    if abbreviation.is_empty() {
+        return None;
+    }
+
+    *rest = remainder;
+    Some(abbreviation.to_owned())
+}
+
+/// Reads a time or offset in `[+-]hh[:mm[:ss]]` form, in seconds.
+fn offset(rest: &mut &str) -> Option<i64> {
+    let end = rest.find(|c: char| !(c.is_digit(10) || c == ':' || c == '+' || c == '-')).unwrap_or(rest.len());
+    let (field, remainder) = rest.split_at(end);
+
+    let (sign, field) = match field.chars().next() {
+        Some('-') => (-1, &field[1..]),
+        Some('+') => (1, &field[1..]),
+        _         => (1, field),
+    };
+
+    let mut seconds = 0;
+    let mut multiplier = 3600;
+    for part in field.split(':') {
+        if multiplier == 0 || part.is_empty() {
+            return None;
+        }
+
+        let number: i64 = try_opt!(part.parse().ok());
+        seconds += number * multiplier;
+        multiplier /= 60;
+    }
+
+    *rest = remainder;
+    Some(sign * seconds)
+}
+
+/// Reads a rule in `Mm.w.d[/time]` form.
+fn rule(rest: &mut &str) -> Option<PosixRule> {
+    if !rest.starts_with('M') {
+        return None;
+    }
+
+    let end = rest.find(|c: char| c == '/' || c == ',').unwrap_or(rest.len());
+    let fields: Vec<&str> = rest[1 .. end].split('.').collect();
+    *rest = &rest[end ..];
+
+    if fields.len() != 3 {
+        return None;
+    }
+
+    let month: i8 = try_opt!(fields[0].parse().ok());
+    let week: i8 = try_opt!(fields[1].parse().ok());
+    let weekday: i8 = try_opt!(fields[2].parse().ok());
+
+    let month = MonthSpec(try_opt!(::datetime::Month::from_one(month).ok()));
+    let weekday = WeekdaySpec(try_opt!(::datetime::Weekday::from_zero(weekday).ok()));
+    let day = match week {
+        1 | 2 | 3 | 4  => DaySpec::FirstOnOrAfter(weekday, 1 + 7 * (week - 1)),
+        5              => DaySpec::Last(weekday),
+        _              => return None,
+    };
+
+    let time = if rest.starts_with('/') {
+        *rest = &rest[1..];
+        try_opt!(offset(rest))
+    }
+    else {
+        2 * 3600
+    };
+
+    Some(PosixRule { month: month, day: day, time: time })
+}
+
+
+/// An error that can occur when reading a directory of compiled zones.
+#[derive(Debug)]
+pub enum Error {
+
+    /// A file or directory couldn’t be read.
+    IO(PathBuf, io::Error),
+
+    /// A file started like a TZif file, but wasn’t a valid one.
+    Invalid(PathBuf, &'static str),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Error::IO(ref path, ref e)           => write!(f, "{}: {}", path.display(), e),
+            Error::Invalid(ref path, reason)     => write!(f, "{}: {}", path.display(), reason),
+        }
+    }
+}
+
+impl ErrorTrait for Error {
+    fn description(&self) -> &str {
+        match *self {
+            Error::IO(..)       => "IO error",
+            Error::Invalid(..)  => "invalid TZif file",
+        }
+    }
+
+    fn cause(&self) -> Option<&ErrorTrait> {
+        match *self {
+            Error::IO(_, ref e)  => Some(e),
+            Error::Invalid(..)   => None,
+        }
+    }
+}
+
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::env;
+    use std::fs::{self, File};
+    use std::io::Write;
+    use transitions::{TableTransitions, TransitionOptions};
+
+    /// Writes a version 2 TZif file with an empty 32-bit block.
+    fn tzif(transitions: &[(i64, u8)], types: &[(i32, bool, &str)], footer: &str) -> Vec<u8> {
+        let mut chars = Vec::new();
+        let mut indices = Vec::new();
+        for &(_, _, abbreviation) in types {
+            indices.push(chars.len() as u8);
+            chars.extend(abbreviation.bytes());
+            chars.push(0);
+        }
+
+        // The 32-bit block has no transitions, and one type with an empty
+        // abbreviation.
+        let mut buf = Vec::new();
+        header(&mut buf, 0, 1, 1);
+        buf.extend(&[0; 7]);
+        header(&mut buf, transitions.len(), types.len(), chars.len());
+
+        for &(at, _) in transitions {
+            for shift in (0 .. 8).rev() {
+                buf.push((at >> (shift * 8)) as u8);
+            }
+        }
+        buf.extend(transitions.iter().map(|t| t.1));
+        for (&(offset, is_dst, _), &index) in types.iter().zip(indices.iter()) {
+            buf.extend(&[ (offset >> 24) as u8, (offset >> 16) as u8, (offset >> 8) as u8, offset as u8, is_dst as u8, index ]);
+        }
+        buf.extend(chars);
+        buf.extend(format!("\n{}\n", footer).bytes());
+        buf
+    }
+
+    fn header(buf: &mut Vec<u8>, timecnt: usize, typecnt: usize, charcnt: usize) {
+        buf.extend(b"TZif2");
+        buf.extend(&[0; 15]);
+        for &count in &[ 0, 0, 0, timecnt, typecnt, charcnt ] {
+            buf.extend(&[ 0, 0, 0, count as u8 ]);
+        }
+    }
+
+    fn london() -> Vec<u8> {
+        tzif(&[ (-3_852_662_325, 1), (828_234_000, 2), (846_378_000, 1) ],
+             &[ (-75, false, "LMT"), (0, false, "GMT"), (3600, true, "BST") ],
+             "GMT0BST,M3.5.0/1,M10.5.0")
+    }
+
+    #[test]
+    fn posix_tz() {
+        assert_eq!(PosixTz::parse("<+0330>-3:30"), Some(PosixTz {
+            std_abbreviation: "+0330".to_owned(),
+            std_offset: 3 * 3600 + 30 * 60,
+            dst: None,
+        }));
+
+        let israel = PosixTz::parse("IST-2IDT,M3.4.4/26,M10.5.0").unwrap().dst.unwrap();
+        assert_eq!(israel.offset, 3 * 3600);
+        assert_eq!(israel.start, PosixRule {
+            month: MonthSpec(::datetime::Month::March),
+            day:   DaySpec::FirstOnOrAfter(WeekdaySpec(::datetime::Weekday::Thursday), 22),
+            time:  26 * 3600,
+        });
+        assert_eq!(israel.end.time, 2 * 3600);
+
+        assert_eq!(PosixTz::parse("<+05 30>-5:30"), None);
+        assert_eq!(PosixTz::parse("<>-5:30"), None);
+        assert_eq!(PosixTz::parse("EST5EDT,J60,J300"), None);
+        assert_eq!(PosixTz::parse(""), None);
+    }
+
+    #[test]
+    fn file_contents() {
+        let parsed = parse_tzif(&london()).unwrap();
+        assert_eq!(parsed.transitions, vec![ (-3_852_662_325, 1), (828_234_000, 2), (846_378_000, 1) ]);
+        assert_eq!(parsed.types[2], LocalTimeType { utc_offset: 3600, is_dst: true, abbreviation: "BST".to_owned() });
+        assert_eq!(parsed.footer, Some("GMT0BST,M3.5.0/1,M10.5.0".to_owned()));
+
+        assert_eq!(parse_tzif(b"TZif2"), Err("invalid header"));
+        assert_eq!(parse_tzif(&london()[.. 100]), Err("truncated file"));
+    }
+
+    #[test]
+    fn huge_counts() {
+        let mut buf = Vec::new();
+        header(&mut buf, 0, 1, 1);
+        for byte in &mut buf[20 .. 24] {
+            *byte = 0xFF;
+        }
+        buf.extend(&[0; 64]);
+
+        assert_eq!(parse_tzif(&buf), Err("truncated file"));
+    }
+
+    #[test]
+    fn reconstructed_transitions() {
+        let (zoneset, rules) = reconstruct("Europe/London", &parse_tzif(&london()).unwrap());
+        let mut table = Table::default();
+        let _ = table.zonesets.insert("Europe/London".to_owned(), zoneset);
+        let _ = table.rulesets.insert("Europe/London".to_owned(), rules.unwrap());
+
+        let options = TransitionOptions { max_year: 2000, .. TransitionOptions::default() };
+        let set = table.timespans("Europe/London", &options).unwrap();
+        assert_eq!(set.first.utc_offset, -75);
+
+        // The three transitions from the file, and then the ones from the
+        // footer’s rules, starting the spring after the last one.
+        let times: Vec<i64> = set.rest.iter().map(|t| t.0).collect();
+        assert_eq!(&times[.. 4], &[ -3_852_662_325, 828_234_000, 846_378_000, 859_683_600 ]);
+        assert_eq!(set.rest[1].1.dst_offset, 3600);
+        assert_eq!(set.rest[2].1.name, "GMT");
+        assert_eq!(set.rest[3].1.name, "BST");
+        assert_eq!(set.rest.len(), 3 + 2 * 4);
+    }
+
+    #[test]
+    fn directory() {
+        let dir = env::temp_dir().join(format!("zoneinfo_parse-compiled-{}", ::std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(dir.join("Europe")).unwrap();
+        fs::create_dir_all(dir.join("posix")).unwrap();
+
+        File::create(dir.join("Europe/London")).unwrap().write_all(&london()).unwrap();
+        File::create(dir.join("posix/Europe")).unwrap().write_all(&london()).unwrap();
+        File::create(dir.join("zone.tab")).unwrap().write_all(b"GB\t+513030-0000731\tEurope/London\n").unwrap();
+        File::create(dir.join("UTC")).unwrap().write_all(&tzif(&[], &[ (0, false, "UTC") ], "UTC0")).unwrap();
+        fs::hard_link(dir.join("Europe/London"), dir.join("GB")).unwrap();
+
+        #[cfg(unix)]
+        ::std::os::unix::fs::symlink("../GB", dir.join("Europe/Belfast")).unwrap();
+
+        let table = Table::from_zoneinfo_dir(&dir).unwrap();
+        fs::remove_dir_all(&dir).unwrap();
+
+        let mut zones: Vec<&str> = table.zonesets.keys().map(|k| &**k).collect();
+        zones.sort();
+        assert_eq!(zones, vec![ "Europe/London", "UTC" ]);
+        assert_eq!(table.zonesets["UTC"].len(), 1);
+
+        if cfg!(unix) {
+            assert_eq!(table.links.len(), 2);
+            assert_eq!(table.links["GB"], "Europe/London");
+            assert_eq!(table.links["Europe/Belfast"], "Europe/London");
+        }
+    }
+}
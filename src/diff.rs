@@ -0,0 +1,367 @@
+//! Comparing two tables to find out what an update changes.
+//!
+//! When a new version of the tz database comes out, anything that stored
+//! times as local times may need to know whether those times now mean
+//! something different. This module compares two tables — usually the
+//! previous and current versions of the database — and lists the instants at
+//! which a zone’s UTC offset differs between the two. A local time that was
+//! converted from one of those instants using the old table will convert
+//! back to a different instant using the new one.
+//!
+//! Only zones present in both tables get compared, and only their total
+//! offsets: a change to just an abbreviation doesn’t change what any local
+//! time means.
+//!
+//! It can also list the upcoming transitions that differ between the two
+//! tables, with `changed_transitions`, for deciding how soon an update
+//! needs to be rolled out. That counts changes to abbreviations and to
+//! whether it’s daylight-saving time as well, and lists zones that only
+//! one of the tables has.
+//!
+//! ## Example
+//!
+//! ```no_run
+//! # use zoneinfo_parse::table::Table;
+//! use zoneinfo_parse::diff::TableDiff;
+//!
+//! # let (old_table, new_table) = (Table::default(), Table::default());
+//! // Everything from 2000 to 2030.
+//! let diff = TableDiff::new(&old_table, &new_table);
+//! for r in diff.reinterpretations(946_684_800 .. 1_893_456_000) {
+//!     println!("{}: {:?} was {} but is now {}", r.zone, r.instants,
+//!              r.old.total_offset(), r.new.total_offset());
+//! }
+//! ```
+
+use std::ops::Range;
+
+use datetime::{LocalDateTime, DatePiece};
+
+use table::Table;
+use transitions::{TableTransitions, TransitionOptions, FixedTimespan};
+
+
+/// A pair of tables to compare.
+#[derive(Debug, Copy, Clone)]
+pub struct TableDiff<'table> {
+
+    /// The table from before the update.
+    pub old: &'table Table,
+
+    /// The table from after the update.
+    pub new: &'table Table,
+}
+
+impl<'table> TableDiff<'table> {
+
+    /// Creates a new comparison between an old and a new table.
+    pub fn new(old: &'table Table, new: &'table Table) -> TableDiff<'table> {
+        TableDiff { old: old, new: new }
+    }
+
+    /// Returns every range of instants within the given range during which
+    /// a zone’s total UTC offset differs between the old and new tables,
+    /// sorted by zone name and then by time. Links count as zones in their
+    /// own right, so a change to a zone also gets listed under every link to
+    /// it.
+    pub fn reinterpretations(&self, instants: Range<i64>) -> Vec<Reinterpretation> {
+        let options = TransitionOptions {
+            max_year: LocalDateTime::at(instants.end).year() + 1,
+            .. TransitionOptions::default()
+        };
+
+        let mut names = self.old.zonesets.keys().chain(self.old.links.keys())
+                            .filter(|name| self.new.get_zoneset(name).is_some())
+                            .collect::<Vec<_>>();
+        names.sort();
+
+        let mut results = Vec::new();
+
+        for name in names {
+            let old_spans = match spans_within(self.old, name, &instants, &options) {
+                Some(s) => s,
+                None    => continue,  // a dangling link in the old table
+            };
+
+            let new_spans = spans_within(self.new, name, &instants, &options).unwrap();
+            compare_spans(name, &old_spans, &new_spans, instants.end, &mut results);
+        }
+
+        results
+    }
+
+    /// Returns every zone whose transitions from the given instant onwards
+    /// differ between the old and new tables, along with how they differ,
+    /// sorted by zone name. Links count as zones in their own right. Two
+    /// transitions are the same if they happen at the same instant and
+    /// change to the same total offset, abbreviation, and whether it’s
+    /// daylight-saving time, however the offset is split up.
+    pub fn changed_transitions(&self, from: i64, options: &TransitionOptions) -> Vec<(String, ZoneChange)> {
+        let mut names = self.old.zonesets.keys().chain(self.old.links.keys())
+                            .chain(self.new.zonesets.keys()).chain(self.new.links.keys())
+                            .collect::<Vec<_>>();
+        names.sort();
+        names.dedup();
+
+        let mut results = Vec::new();
+
+        for name in names {
+            let change = match (transitions_from(self.old, name, from, options), transitions_from(self.new, name, from, options)) {
+                (Some(old), Some(new)) => {
+                    let removed = old.iter().filter(|t| !new.iter().any(|u| same_transition(t, u))).cloned().collect::<Vec<_>>();
+                    let added   = new.iter().filter(|t| !old.iter().any(|u| same_transition(t, u))).cloned().collect::<Vec<_>>();
+                    if removed.is_empty() && added.is_empty() {
+                        continue;
+                    }
+
+                    ZoneChange::Transitions { removed: removed, added: added }
+                },
+                (Some(_), None)  => ZoneChange::Removed,
+                (None, Some(_))  => ZoneChange::Added,
+                (None, None)     => continue,  // a dangling link in both tables
+            };
+
+            results.push((name.clone(), change));
+        }
+
+        results
+    }
+}
+
+
+/// How a zone’s upcoming transitions differ between the old and new tables.
+#[derive(PartialEq, Debug, Clone)]
+pub enum ZoneChange {
+
+    /// The zone is only in the new table.
+    Added,
+
+    /// The zone is only in the old table.
+    Removed,
+
+    /// The zone is in both tables, but some of its transitions differ.
+    Transitions {
+
+        /// The transitions, with the timespans they change to, that only
+        /// the old table has.
+        removed: Vec<(i64, FixedTimespan)>,
+
+        /// The transitions, with the timespans they change to, that only
+        /// the new table has.
+        added: Vec<(i64, FixedTimespan)>,
+    },
+}
+
+
+/// A range of instants in one zone that the old and new tables give
+/// different UTC offsets to.
+#[derive(PartialEq, Debug, Clone)]
+pub struct Reinterpretation {
+
+    /// The name of the zone.
+    pub zone: String,
+
+    /// The instants affected, as Unix timestamps, from the first one up to
+    /// (but not including) the end.
+    pub instants: Range<i64>,
+
+    /// The timespan the old table has in effect during these instants.
+    pub old: FixedTimespan,
+
+    /// The timespan the new table has in effect during these instants.
+    pub new: FixedTimespan,
+}
+
+impl Reinterpretation {
+
+    /// The number of seconds that a local time in this range moves by when
+    /// it gets converted back to an instant using the new table instead of
+    /// the old one: positive if it now refers to a later instant.
+    pub fn shift(&self) -> i64 {
+        self.old.total_offset() - self.new.total_offset()
+    }
+}
+
+
+/// Returns the timespans of a zone that are in effect during the given
+/// range, each paired with the instant it starts at, clamped so the first
+/// one starts at the beginning of the range.
+fn spans_within(table: &Table, name: &str, instants: &Range<i64>, options: &TransitionOptions) -> Option<Vec<(i64, FixedTimespan)>> {
+    let mut iter = match table.transitions_iter(name, options) {
+        Some(i) => i,
+        None    => return None,
+    };
+
+    let mut spans = vec![ (instants.start, iter.first().clone()) ];
+
+    for (at, timespan) in &mut iter {
+        if at >= instants.end {
+            break;
+        }
+        else if at <= instants.start {
+            spans[0].1 = timespan;
+        }
+        else {
+            spans.push((at, timespan));
+        }
+    }
+
+    Some(spans)
+}
+
+/// Returns a zone’s transitions from the given instant onwards.
+fn transitions_from(table: &Table, name: &str, from: i64, options: &TransitionOptions) -> Option<Vec<(i64, FixedTimespan)>> {
+    table.transitions_iter(name, options)
+         .map(|iter| iter.skip_while(|&(at, _)| at < from).collect())
+}
+
+/// Returns whether two transitions look the same to anyone using the zone.
+fn same_transition(a: &(i64, FixedTimespan), b: &(i64, FixedTimespan)) -> bool {
+    a.0 == b.0
+        && a.1.total_offset() == b.1.total_offset()
+        && a.1.name == b.1.name
+        && a.1.is_dst == b.1.is_dst
+}
+
+/// Walks through the two lists of timespans together, adding a
+/// reinterpretation for each stretch where their offsets differ. Stretches
+/// that follow on from one another with the same timespans get merged.
+fn compare_spans(name: &str, old: &[(i64, FixedTimespan)], new: &[(i64, FixedTimespan)], end: i64, results: &mut Vec<Reinterpretation>) {
+    let (mut i, mut j) = (0, 0);
+    let mut start = old[0].0;
+
+    while start < end {
+        let next_old = old.get(i + 1).map(|s| s.0).unwrap_or(end);
+        let next_new = new.get(j + 1).map(|s| s.0).unwrap_or(end);
+        let until = next_old.min(next_new);
+
+        let (old_span, new_span) = (&old[i].1, &new[j].1);
+        if old_span.total_offset() != new_span.total_offset() {
+            let merged = match results.last_mut() {
+                Some(last) if last.zone == name && last.instants.end == start
+                           && last.old == *old_span && last.new == *new_span => {
+                    last.instants.end = until;
+                    true
+                },
+                _ => false,
+            };
+
+            if !merged {
+                results.push(Reinterpretation {
+                    zone:      name.to_owned(),
+                    instants:  start .. until,
+                    old:       old_span.clone(),
+                    new:       new_span.clone(),
+                });
+            }
+        }
+
+        if next_old == until { i += 1; }
+        if next_new == until { j += 1; }
+        start = until;
+    }
+}
+
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use line::{ChangeTime, YearSpec};
+    use table::{Table, ZoneInfo, Saving, Format};
+
+    fn table(offset: i64, until: i64) -> Table {
+        let zone_info = |offset, until: Option<i64>| ZoneInfo {
+            offset:    offset,
+            saving:    Saving::NoSaving,
+            format:    Format::new("TEST"),
+            end_time:  until.map(|y| ChangeTime::UntilYear(YearSpec::Number(y))),
+        };
+
+        let mut table = Table::default();
+        let _ = table.zonesets.insert("Test/Zone".to_owned(), vec![ zone_info(offset, Some(until)), zone_info(0, None) ]);
+        let _ = table.links.insert("Test/Link".to_owned(), "Test/Zone".to_owned());
+        table
+    }
+
+    #[test]
+    fn identical() {
+        let old = table(3600, 2000);
+        let new = table(3600, 2000);
+        assert_eq!(TableDiff::new(&old, &new).reinterpretations(0 .. 2_000_000_000), vec![]);
+    }
+
+    #[test]
+    fn later_change() {
+        let old = table(3600, 2000);
+        let new = table(3600, 2010);
+
+        let expected = |zone: &str| Reinterpretation {
+            zone:      zone.to_owned(),
+            instants:  946_681_200 .. 1_262_300_400,
+            old:       FixedTimespan::new(0, 0, "TEST"),
+            new:       FixedTimespan::new(3600, 0, "TEST"),
+        };
+
+        let results = TableDiff::new(&old, &new).reinterpretations(0 .. 2_000_000_000);
+        assert_eq!(results, vec![ expected("Test/Link"), expected("Test/Zone") ]);
+        assert_eq!(results[0].shift(), -3600);
+    }
+
+    #[test]
+    fn clamped_to_range() {
+        let old = table(3600, 2000);
+        let new = table(7200, 2000);
+
+        // The new table’s change happens an hour earlier, so there’s an
+        // hour where the old table still says +1 but the new one says 0.
+        let results = TableDiff::new(&old, &new).reinterpretations(900_000_000 .. 2_000_000_000);
+        assert_eq!(results.len(), 4);
+        assert_eq!(results[2].instants, 900_000_000 .. 946_677_600);
+        assert_eq!(results[2].new, FixedTimespan::new(7200, 0, "TEST"));
+        assert_eq!(results[3].instants, 946_677_600 .. 946_681_200);
+        assert_eq!(results[3].new, FixedTimespan::new(0, 0, "TEST"));
+    }
+
+    #[test]
+    fn changed_transitions() {
+        let old = table(3600, 2000);
+        let mut new = table(3600, 2010);
+        let _ = new.links.remove("Test/Link");
+        let _ = new.links.insert("Test/Other".to_owned(), "Test/Zone".to_owned());
+
+        let options = TransitionOptions::default();
+        let results = TableDiff::new(&old, &new).changed_transitions(900_000_000, &options);
+        assert_eq!(results, vec![
+            ("Test/Link".to_owned(), ZoneChange::Removed),
+            ("Test/Other".to_owned(), ZoneChange::Added),
+            ("Test/Zone".to_owned(), ZoneChange::Transitions {
+                removed: vec![ (946_681_200, FixedTimespan::new(0, 0, "TEST")) ],
+                added:   vec![ (1_262_300_400, FixedTimespan::new(0, 0, "TEST")) ],
+            }),
+        ]);
+    }
+
+    #[test]
+    fn past_transitions_ignored() {
+        let old = table(3600, 2000);
+        let new = table(7200, 2000);
+
+        let options = TransitionOptions::default();
+        assert_eq!(TableDiff::new(&old, &new).changed_transitions(1_000_000_000, &options), vec![]);
+    }
+
+    #[test]
+    fn abbreviation_changes() {
+        let old = table(3600, 2000);
+        let mut new = table(3600, 2000);
+        new.zonesets.get_mut("Test/Zone").unwrap()[1].format = Format::new("NEW");
+
+        let options = TransitionOptions::default();
+        let results = TableDiff::new(&old, &new).changed_transitions(900_000_000, &options);
+        assert_eq!(results.len(), 2);
+        assert_eq!(results[1].1, ZoneChange::Transitions {
+            removed: vec![ (946_681_200, FixedTimespan::new(0, 0, "TEST")) ],
+            added:   vec![ (946_681_200, FixedTimespan::new(0, 0, "NEW")) ],
+        });
+    }
+}
@@ -0,0 +1,434 @@
+//! Looking up time zones by geographical position.
+//!
+//! The zoneinfo files say nothing about *where* each time zone is in use
+//! beyond the name of one city. To find the zone for a point on a map, you
+//! need the zones’ boundaries, such as those published by the
+//! [timezone-boundary-builder][tbb] project as GeoJSON.
+//!
+//! This module reads that GeoJSON release into a `BoundarySet`, which can
+//! then find the zone containing a latitude and longitude. Because the
+//! GeoJSON is huge and slow to parse, a boundary set can also be written out
+//! in a compact packed binary form and read back in later.
+//!
+//! It is only available when the crate is compiled with the `geo` feature,
+//! as it pulls in a JSON parser.
+//!
+//! [tbb]: https://github.com/evansiroky/timezone-boundary-builder
+//!
+//! ## Example
+//!
+//! ```
+//! # #[cfg(feature = "geo")] fn main() {
+//! use zoneinfo_parse::geo::BoundarySet;
+//!
+//! let geojson = r#"{
+//!     "type": "FeatureCollection",
+//!     "features": [{
+//!         "type": "Feature",
+//!         "properties": { "tzid": "Test/Square" },
+//!         "geometry": {
+//!             "type": "Polygon",
+//!             "coordinates": [[ [0, 0], [10, 0], [10, 10], [0, 10], [0, 0] ]]
+//!         }
+//!     }]
+//! }"#;
+//!
+//! let boundaries = BoundarySet::from_geojson(geojson.as_bytes()).unwrap();
+//! assert_eq!(boundaries.zone_for_point(5.0, 5.0), Some("Test/Square"));
+//! assert_eq!(boundaries.zone_for_point(50.0, 5.0), None);
+//! # }
+//! # #[cfg(not(feature = "geo"))] fn main() {}
+//! ```
+
+use std::error::Error as ErrorTrait;
+use std::fmt;
+use std::io::{self, Read, Write};
+
+use serde_json::{self, Value};
+
+
+/// The boundaries of a set of time zones.
+#[derive(PartialEq, Debug, Clone, Default)]
+pub struct BoundarySet {
+
+    /// Every zone with its boundary, in the order they were read.
+    pub zones: Vec<ZoneBoundary>,
+}
+
+/// The area covered by one time zone.
+#[derive(PartialEq, Debug, Clone)]
+pub struct ZoneBoundary {
+
+    /// The name of the zone, such as “Europe/London”.
+    pub name: String,
+
+    /// The polygons making up the zone. Zones with exclaves or islands have
+    /// more than one.
+    pub polygons: Vec<Polygon>,
+}
+
+/// A single polygon, possibly with holes in it.
+#[derive(PartialEq, Debug, Clone)]
+pub struct Polygon {
+
+    /// The outer edge of the polygon.
+    pub exterior: Ring,
+
+    /// Any areas inside the outer edge that are not part of the polygon,
+    /// such as a zone entirely surrounded by another.
+    pub holes: Vec<Ring>,
+}
+
+/// A closed line of points, each stored as a (longitude, latitude) pair in
+/// degrees, the same order GeoJSON uses.
+pub type Ring = Vec<(f64, f64)>;
+
+
+impl BoundarySet {
+
+    /// Reads a GeoJSON feature collection, such as the one released by
+    /// timezone-boundary-builder. Each feature must have a `tzid` property
+    /// and a `Polygon` or `MultiPolygon` geometry.
+    pub fn from_geojson<R: Read>(reader: R) -> Result<BoundarySet, Error> {
+        let json: Value = try!(serde_json::from_reader(reader));
+
+        let features = match json.get("features").and_then(Value::as_array) {
+            Some(f) => f,
+            None    => return Err(Error::Structure("missing features array".to_owned())),
+        };
+
+        let mut set = BoundarySet::default();
+
+        for feature in features {
+            let name = match feature.pointer("/properties/tzid").and_then(Value::as_str) {
+                Some(n) => n.to_owned(),
+                None    => return Err(Error::Structure("feature without a tzid".to_owned())),
+            };
+
+            let geometry = match feature.get("geometry") {
+                Some(g) => g,
+                None    => return Err(Error::Structure(format!("{} has no geometry", name))),
+            };
+
+            let coordinates = geometry.get("coordinates");
+            let polygons = match (geometry.get("type").and_then(Value::as_str), coordinates) {
+                (Some("Polygon"), Some(c)) => {
+                    vec![ try!(polygon_from_json(c, &name)) ]
+                },
+
+                (Some("MultiPolygon"), Some(&Value::Array(ref polys))) => {
+                    let mut polygons = Vec::new();
+                    for p in polys {
+                        polygons.push(try!(polygon_from_json(p, &name)));
+                    }
+                    polygons
+                },
+
+                _ => return Err(Error::Structure(format!("{} has an unsupported geometry", name))),
+            };
+
+            set.zones.push(ZoneBoundary { name: name, polygons: polygons });
+        }
+
+        Ok(set)
+    }
+
+    /// Returns the name of the zone containing the given point, or `None`
+    /// if no zone contains it. If zones overlap, the first one read wins.
+    pub fn zone_for_point(&self, latitude: f64, longitude: f64) -> Option<&str> {
+        let point = (longitude, latitude);
+
+        self.zones.iter()
+            .find(|zone| zone.polygons.iter().any(|p| p.contains(point)))
+            .map(|zone| &*zone.name)
+    }
+
+    /// Writes this set in the packed binary form, which stores each
+    /// coordinate as a fixed-point number to a precision of 10⁻⁷ degrees
+    /// (about a centimetre).
+    ///
+    /// The format is the magic bytes `TZGEO`, a version byte, then a count
+    /// of zones. Each zone is its name’s length in one byte, the name, and a
+    /// count of polygons. Each polygon is a count of rings (the exterior
+    /// first, then any holes), and each ring is a count of points followed
+    /// by the points as longitude and latitude pairs. All counts and
+    /// coordinates are 32-bit little-endian integers.
+    pub fn write_packed<W: Write>(&self, w: &mut W) -> io::Result<()> {
+        try!(w.write_all(PACKED_MAGIC));
+        try!(w.write_all(&[ PACKED_VERSION ]));
+        try!(write_u32(w, self.zones.len() as u32));
+
+        for zone in &self.zones {
+            try!(w.write_all(&[ zone.name.len() as u8 ]));
+            try!(w.write_all(zone.name.as_bytes()));
+            try!(write_u32(w, zone.polygons.len() as u32));
+
+            for polygon in &zone.polygons {
+                try!(write_u32(w, polygon.holes.len() as u32 + 1));
+
+                for ring in Some(&polygon.exterior).into_iter().chain(polygon.holes.iter()) {
+                    try!(write_u32(w, ring.len() as u32));
+
+                    for &(lon, lat) in ring {
+                        try!(write_u32(w, to_fixed(lon) as u32));
+                        try!(write_u32(w, to_fixed(lat) as u32));
+                    }
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Reads a set that was written by `write_packed`.
+    pub fn read_packed<R: Read>(r: &mut R) -> Result<BoundarySet, Error> {
+        let mut magic = [0; 6];
+        try!(r.read_exact(&mut magic));
+        if &magic[..5] != PACKED_MAGIC || magic[5] != PACKED_VERSION {
+            return Err(Error::Structure("not a packed boundary index".to_owned()));
+        }
+
+        let mut set = BoundarySet::default();
+
+        for _ in 0 .. try!(read_u32(r)) {
+            let mut len = [0; 1];
+            try!(r.read_exact(&mut len));
+            let mut name = vec![0; len[0] as usize];
+            try!(r.read_exact(&mut name));
+
+            let name = match String::from_utf8(name) {
+                Ok(n)   => n,
+                Err(_)  => return Err(Error::Structure("zone name is not UTF-8".to_owned())),
+            };
+
+            let mut polygons = Vec::new();
+            for _ in 0 .. try!(read_u32(r)) {
+                let mut rings = Vec::new();
+                for _ in 0 .. try!(read_u32(r)) {
+                    let mut ring = Vec::new();
+                    for _ in 0 .. try!(read_u32(r)) {
+                        let lon = from_fixed(try!(read_u32(r)) as i32);
+                        let lat = from_fixed(try!(read_u32(r)) as i32);
+                        ring.push((lon, lat));
+                    }
+                    rings.push(ring);
+                }
+
+                if rings.is_empty() {
+                    return Err(Error::Structure(format!("{} has a polygon with no rings", name)));
+                }
+
+                let exterior = rings.remove(0);
+                polygons.push(Polygon { exterior: exterior, holes: rings });
+            }
+
+            set.zones.push(ZoneBoundary { name: name, polygons: polygons });
+        }
+
+        Ok(set)
+    }
+}
+
+impl Polygon {
+
+    /// Returns whether the given (longitude, latitude) point is inside this
+    /// polygon, and not inside any of its holes.
+    pub fn contains(&self, point: (f64, f64)) -> bool {
+        ring_contains(&self.exterior, point)
+            && !self.holes.iter().any(|hole| ring_contains(hole, point))
+    }
+}
+
+/// Tests whether a point is inside a ring by counting how many of the ring’s
+/// edges a line heading east from the point crosses: an odd number means
+/// it’s inside.
+fn ring_contains(ring: &[(f64, f64)], (x, y): (f64, f64)) -> bool {
+    if ring.is_empty() {
+        return false;
+    }
+
+    let mut inside = false;
+    let mut j = ring.len() - 1;
+
+    for i in 0 .. ring.len() {
+        let (xi, yi) = ring[i];
+        let (xj, yj) = ring[j];
+
+        if (yi > y) != (yj > y) && x < (xj - xi) * (y - yi) / (yj - yi) + xi {
+            inside = !inside;
+        }
+
+        j = i;
+    }
+
+    inside
+}
+
+fn polygon_from_json(json: &Value, name: &str) -> Result<Polygon, Error> {
+    let malformed = || Error::Structure(format!("{} has malformed coordinates", name));
+
+    let mut rings = Vec::new();
+    for ring_json in try!(json.as_array().ok_or_else(&malformed)) {
+        let mut ring = Vec::new();
+
+        for point in try!(ring_json.as_array().ok_or_else(&malformed)) {
+            let lon = point.get(0).and_then(Value::as_f64);
+            let lat = point.get(1).and_then(Value::as_f64);
+
+            match (lon, lat) {
+                (Some(lon), Some(lat)) => ring.push((lon, lat)),
+                _                      => return Err(malformed()),
+            }
+        }
+
+        rings.push(ring);
+    }
+
+    if rings.is_empty() {
+        return Err(malformed());
+    }
+
+    let exterior = rings.remove(0);
+    Ok(Polygon { exterior: exterior, holes: rings })
+}
+
+
+static PACKED_MAGIC: &'static [u8] = b"TZGEO";
+const PACKED_VERSION: u8 = 1;
+
+fn to_fixed(degrees: f64) -> i32 {
+    (degrees * 10_000_000.0).round() as i32
+}
+
+fn from_fixed(fixed: i32) -> f64 {
+    fixed as f64 / 10_000_000.0
+}
+
+fn write_u32<W: Write>(w: &mut W, n: u32) -> io::Result<()> {
+    w.write_all(&[ n as u8, (n >> 8) as u8, (n >> 16) as u8, (n >> 24) as u8 ])
+}
+
+fn read_u32<R: Read>(r: &mut R) -> io::Result<u32> {
+    let mut buf = [0; 4];
+    try!(r.read_exact(&mut buf));
+    Ok(buf[0] as u32 | (buf[1] as u32) << 8 | (buf[2] as u32) << 16 | (buf[3] as u32) << 24)
+}
+
+
+/// An error that can occur when reading boundaries.
+#[derive(Debug)]
+pub enum Error {
+
+    /// The input couldn’t be read.
+    IO(io::Error),
+
+    /// The input wasn’t valid JSON.
+    JSON(serde_json::Error),
+
+    /// The input was readable, but not in the expected shape.
+    Structure(String),
+}
+
+impl From<io::Error> for Error {
+    fn from(error: io::Error) -> Error {
+        Error::IO(error)
+    }
+}
+
+impl From<serde_json::Error> for Error {
+    fn from(error: serde_json::Error) -> Error {
+        Error::JSON(error)
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Error::IO(ref e)         => write!(f, "IO error: {}", e),
+            Error::JSON(ref e)       => write!(f, "JSON error: {}", e),
+            Error::Structure(ref s)  => write!(f, "invalid boundaries: {}", s),
+        }
+    }
+}
+
+impl ErrorTrait for Error {
+    fn description(&self) -> &str {
+        match *self {
+            Error::IO(_)         => "IO error",
+            Error::JSON(_)       => "JSON error",
+            Error::Structure(_)  => "invalid boundaries",
+        }
+    }
+}
+
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    static DONUT: &'static str = r#"{
+        "type": "FeatureCollection",
+        "features": [
+            {
+                "type": "Feature",
+                "properties": { "tzid": "Test/Hole" },
+                "geometry": {
+                    "type": "Polygon",
+                    "coordinates": [[ [4, 4], [6, 4], [6, 6], [4, 6], [4, 4] ]]
+                }
+            },
+            {
+                "type": "Feature",
+                "properties": { "tzid": "Test/Donut" },
+                "geometry": {
+                    "type": "MultiPolygon",
+                    "coordinates": [
+                        [
+                            [ [0, 0], [10, 0], [10, 10], [0, 10], [0, 0] ],
+                            [ [4, 4], [6, 4], [6, 6], [4, 6], [4, 4] ]
+                        ],
+                        [[ [-20.5, -20.5], [-19.5, -20.5], [-19.5, -19.5], [-20.5, -19.5], [-20.5, -20.5] ]]
+                    ]
+                }
+            }
+        ]
+    }"#;
+
+    #[test]
+    fn lookup() {
+        let set = BoundarySet::from_geojson(DONUT.as_bytes()).unwrap();
+        assert_eq!(set.zone_for_point(1.0, 1.0), Some("Test/Donut"));
+        assert_eq!(set.zone_for_point(5.0, 5.0), Some("Test/Hole"));
+        assert_eq!(set.zone_for_point(-20.0, -20.0), Some("Test/Donut"));
+        assert_eq!(set.zone_for_point(20.0, 20.0), None);
+    }
+
+    #[test]
+    fn hole() {
+        let set = BoundarySet::from_geojson(DONUT.as_bytes()).unwrap();
+        assert!(!set.zones[1].polygons[0].contains((5.0, 5.0)));
+        assert!(set.zones[1].polygons[0].contains((5.0, 8.0)));
+    }
+
+    #[test]
+    fn packed_round_trip() {
+        let set = BoundarySet::from_geojson(DONUT.as_bytes()).unwrap();
+
+        let mut packed = Vec::new();
+        set.write_packed(&mut packed).unwrap();
+
+        let unpacked = BoundarySet::read_packed(&mut &packed[..]).unwrap();
+        assert_eq!(unpacked, set);
+    }
+
+    #[test]
+    fn not_packed() {
+        assert!(BoundarySet::read_packed(&mut &b"{\"type\": 1}"[..]).is_err());
+    }
+
+    #[test]
+    fn missing_tzid() {
+        let json = r#"{ "features": [ { "properties": {}, "geometry": null } ] }"#;
+        assert!(BoundarySet::from_geojson(json.as_bytes()).is_err());
+    }
+}
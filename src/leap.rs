@@ -0,0 +1,294 @@
+//! Parsing the `leapseconds` file of leap seconds.
+//!
+//! Alongside the zoneinfo files, the tz database has a `leapseconds` file
+//! listing every leap second since 1972, with one `Leap` line each:
+//!
+//! ```text
+//! Leap	2016	Dec	31	23:59:60	+	S
+//! ```
+//!
+//! The fields are the date and UTC time of the leap second, whether it was
+//! added (`+`) or removed (`-`), and whether the time is in UTC (`S`, for
+//! “stationary”) or local time (`R`, for “rolling”), which has never been
+//! used.
+//!
+//! Leap seconds are only announced six months or so ahead, so the list is
+//! only known to be complete up until a certain date. The file says when
+//! that is in two ways: with an `Expires` line in the same layout as a
+//! `Leap` line but without the last two fields, and with an `#expires`
+//! comment giving it as a Unix timestamp. As older versions of `zic`
+//! reject the `Expires` line, it’s usually commented out, leaving just the
+//! comment. Either one is read here, so programs can warn when their copy
+//! of the list has gone stale.
+//!
+//! ## Example
+//!
+//! ```
+//! use zoneinfo_parse::leap::parse_leapseconds;
+//!
+//! let table = parse_leapseconds("Leap\t2016\tDec\t31\t23:59:60\t+\tS\n\
+//!                                #expires 1782604800 (2026-06-28 00:00:00 UTC)\n").unwrap();
+//! assert_eq!(table.leap_seconds[0].timestamp, 1_483_228_800);
+//! assert_eq!(table.expires, Some(1_782_604_800));
+//! assert!(table.is_expired(1_800_000_000));
+//! ```
+
+use std::error::Error as ErrorTrait;
+use std::fmt;
+
+use datetime::{LocalDate, LocalDateTime, LocalTime};
+
+use line::{MonthSpec, YearSpec, split_lines};
+
+
+/// One leap second, from a `Leap` line.
+#[derive(PartialEq, Debug, Copy, Clone)]
+pub struct LeapSecond {
+
+    /// The Unix timestamp at which the leap second ends, and the difference
+    /// between UTC and TAI changes: midnight at the end of the day it was
+    /// added to or removed from, for all the leap seconds so far.
+    pub timestamp: i64,
+
+    /// The number of seconds this changes the difference between UTC and
+    /// TAI by: `1` for a second that was added, or `-1` for one that was
+    /// removed.
+    pub correction: i64,
+
+    /// Whether the time was given in local time (`R`), rather than UTC.
+    pub rolling: bool,
+}
+
+/// The contents of a `leapseconds` file.
+#[derive(PartialEq, Debug, Clone)]
+pub struct LeapSecondTable {
+
+    /// Every leap second, in the order they appear in the file.
+    pub leap_seconds: Vec<LeapSecond>,
+
+    /// The Unix timestamp after which there could be leap seconds missing
+    /// from the list, if the file says when that is.
+    pub expires: Option<i64>,
+}
+
+impl LeapSecondTable {
+
+    /// Returns whether the list could be missing leap seconds by the given
+    /// Unix timestamp, because it has expired by then. A list without an
+    /// expiry date never expires.
+    pub fn is_expired(&self, now: i64) -> bool {
+        match self.expires {
+            Some(expires) => now >= expires,
+            None          => false,
+        }
+    }
+}
+
+
+/// Parses the contents of a `leapseconds` file. Blank lines and comments
+/// are skipped, apart from an `#expires` comment. If there’s both an
+/// `Expires` line and an `#expires` comment, the line takes precedence.
+pub fn parse_leapseconds(input: &str) -> Result<LeapSecondTable, Error> {
+    let mut leap_seconds = Vec::new();
+    let mut expires_line = None;
+    let mut expires_comment = None;
+
+    for (index, line) in split_lines(input).enumerate() {
+        let line_number = index + 1;
+
+        if line.starts_with("#expires") {
+            let timestamp = line["#expires".len() ..].split_whitespace().next().and_then(|t| t.parse().ok());
+            expires_comment = Some(try!(timestamp.ok_or(Error::BadExpiry(line_number))));
+            continue;
+        }
+
+        let line = match line.find('#') {
+            Some(pos) => &line[.. pos],
+            None      => line,
+        };
+
+        let fields: Vec<&str> = line.split_whitespace().collect();
+        match fields.first() {
+            Some(&"Leap") => {
+                if fields.len() != 7 {
+                    return Err(Error::WrongFieldCount(line_number));
+                }
+
+                let correction = match fields[5] {
+                    "+"  => 1,
+                    "-"  => -1,
+                     _   => return Err(Error::BadCorrection(line_number)),
+                };
+
+                let rolling = match fields[6] {
+                    "S"  => false,
+                    "R"  => true,
+                     _   => return Err(Error::BadCorrection(line_number)),
+                };
+
+                let time = try!(parse_time(&fields[1..5]).ok_or(Error::BadTime(line_number)));
+
+                // A removed leap second is given as the second that gets
+                // skipped, so the difference changes at the end of it.
+                leap_seconds.push(LeapSecond {
+                    timestamp:  if correction < 0 { time + 1 } else { time },
+                    correction: correction,
+                    rolling:    rolling,
+                });
+            },
+
+            Some(&"Expires") => {
+                if fields.len() != 5 {
+                    return Err(Error::WrongFieldCount(line_number));
+                }
+
+                expires_line = Some(try!(parse_time(&fields[1..5]).ok_or(Error::BadTime(line_number))));
+            },
+
+            Some(_)  => return Err(Error::UnknownLine(line_number)),
+            None     => {},
+        }
+    }
+
+    Ok(LeapSecondTable {
+        leap_seconds: leap_seconds,
+        expires:      expires_line.or(expires_comment),
+    })
+}
+
+/// Parses the year, month, day, and time fields of a `Leap` or `Expires`
+/// line into a Unix timestamp. The time can have a seconds field of 60,
+/// which counts as the first second of the next day.
+fn parse_time(fields: &[&str]) -> Option<i64> {
+    let year = match fields[0].parse() {
+        Ok(YearSpec::Number(year)) => year,
+        _                          => return None,
+    };
+
+    let month = match fields[1].parse() {
+        Ok(MonthSpec(month)) => month,
+        Err(_)               => return None,
+    };
+
+    let day = match fields[2].parse() {
+        Ok(day) => day,
+        Err(_)  => return None,
+    };
+
+    let parts: Vec<i64> = match fields[3].split(':').map(|p| p.parse().ok()).collect() {
+        Some(parts) => parts,
+        None        => return None,
+    };
+
+    if parts.len() != 3 || parts[0] >= 24 || parts[1] >= 60 || parts[2] > 60 {
+        return None;
+    }
+
+    let date = match LocalDate::ymd(year, month, day) {
+        Ok(date) => date,
+        Err(_)   => return None,
+    };
+
+    Some(LocalDateTime::new(date, LocalTime::midnight()).to_instant().seconds() + parts[0] * 3600 + parts[1] * 60 + parts[2])
+}
+
+
+/// An error that can occur when parsing a `leapseconds` file.
+#[derive(PartialEq, Debug, Copy, Clone)]
+pub enum Error {
+
+    /// A line started with something other than `Leap` or `Expires`.
+    UnknownLine(usize),
+
+    /// A `Leap` line didn’t have seven fields, or an `Expires` line five.
+    WrongFieldCount(usize),
+
+    /// A line’s date or time wasn’t valid.
+    BadTime(usize),
+
+    /// A `Leap` line’s correction wasn’t `+` or `-`, or its last field
+    /// wasn’t `S` or `R`.
+    BadCorrection(usize),
+
+    /// An `#expires` comment didn’t start with a timestamp.
+    BadExpiry(usize),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Error::UnknownLine(line)      => write!(f, "line {}: unknown line type", line),
+            Error::WrongFieldCount(line)  => write!(f, "line {}: wrong number of fields", line),
+            Error::BadTime(line)          => write!(f, "line {}: invalid date or time", line),
+            Error::BadCorrection(line)    => write!(f, "line {}: invalid correction", line),
+            Error::BadExpiry(line)        => write!(f, "line {}: invalid expiry timestamp", line),
+        }
+    }
+}
+
+impl ErrorTrait for Error {
+    fn description(&self) -> &str {
+        match *self {
+            Error::UnknownLine(_)      => "unknown line type",
+            Error::WrongFieldCount(_)  => "wrong number of fields",
+            Error::BadTime(_)          => "invalid date or time",
+            Error::BadCorrection(_)    => "invalid correction",
+            Error::BadExpiry(_)        => "invalid expiry timestamp",
+        }
+    }
+}
+
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const SAMPLE: &'static str = "# comment\n\
+                                  Leap\t1972\tJun\t30\t23:59:60\t+\tS\n\
+                                  Leap\t1972\tDec\t31\t23:59:60\t+\tS\n\
+                                  \n\
+                                  #Expires 2026\tJun\t28\t00:00:00\n\
+                                  #updated 1751846400 (2025-07-07 00:00:00 UTC)\n\
+                                  #expires 1782604800 (2026-06-28 00:00:00 UTC)\n";
+
+    #[test]
+    fn leap_seconds() {
+        let table = parse_leapseconds(SAMPLE).unwrap();
+        assert_eq!(table.leap_seconds, vec![
+            LeapSecond { timestamp: 78_796_800, correction: 1, rolling: false },
+            LeapSecond { timestamp: 94_694_400, correction: 1, rolling: false },
+        ]);
+    }
+
+    #[test]
+    fn expiry_comment() {
+        let table = parse_leapseconds(SAMPLE).unwrap();
+        assert_eq!(table.expires, Some(1_782_604_800));
+        assert!(!table.is_expired(1_782_604_799));
+        assert!(table.is_expired(1_782_604_800));
+    }
+
+    #[test]
+    fn expiry_line() {
+        let table = parse_leapseconds("Expires 2026\tJun\t28\t00:00:00\n#expires 1\n").unwrap();
+        assert_eq!(table.expires, Some(1_782_604_800));
+        assert_eq!(parse_leapseconds("Leap 2016 Dec 31 23:59:60 + S\n").unwrap().expires, None);
+    }
+
+    #[test]
+    fn negative() {
+        let table = parse_leapseconds("Leap 2030 Dec 31 23:59:59 - S\n").unwrap();
+        assert_eq!(table.leap_seconds[0].timestamp, 1_924_992_000);
+        assert_eq!(table.leap_seconds[0].correction, -1);
+    }
+
+    #[test]
+    fn errors() {
+        assert_eq!(parse_leapseconds("Zone Europe/London 0:00 - GMT\n"), Err(Error::UnknownLine(1)));
+        assert_eq!(parse_leapseconds("\nLeap 2016 Dec 31 23:59:60 +\n"), Err(Error::WrongFieldCount(2)));
+        assert_eq!(parse_leapseconds("Leap 2016 Dec 32 23:59:60 + S\n"), Err(Error::BadTime(1)));
+        assert_eq!(parse_leapseconds("Leap 2016 Dec 31 23:59:61 + S\n"), Err(Error::BadTime(1)));
+        assert_eq!(parse_leapseconds("Leap 2016 Dec 31 23:59:60 * S\n"), Err(Error::BadCorrection(1)));
+        assert_eq!(parse_leapseconds("#expires soon\n"), Err(Error::BadExpiry(1)));
+    }
+}
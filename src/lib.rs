@@ -26,6 +26,39 @@
 //!   `table` module;
 //! - **Calculating transitions** from this table is done by the `transitions`
 //!   module.
+//!
+//! The first two stages can be done in one go, for whole files at a time,
+//! with the `parse_table` and `parse_sources` functions, and the `source`
+//! module turns a table back into text.
+//!
+//! The transitions of one zone can then be wrapped in a `TimeZone`, from the
+//! `timezone` module, to find its offset at an instant or convert between
+//! instants and local times.
+//!
+//! Tables can also be checked for mistakes, such as links to zones that
+//! don’t exist, by the `validation` module, and the `capabilities` function
+//! reports which parts of the format this build supports. The `tab` module
+//! reads the tables of which countries use each zone, and the `windows`
+//! module reads CLDR’s mapping of Windows zone names to these ones. The
+//! `leap` module reads the list of leap seconds, and when it expires, and
+//! the `tai` module uses them to convert between UTC and TAI or GPS time. Two
+//! versions of a table can be compared with the `diff` module, to find out
+//! which instants an update gives different offsets to, and computed
+//! transitions can be checked against the output of `zdump -v` with the
+//! `zdump` module.
+//!
+//! On systems with only the compiled form of the database installed, the
+//! `compiled` module can reconstruct a table from a directory of TZif
+//! files, such as `/usr/share/zoneinfo`.
+//!
+//! With the `geo` feature enabled, the `geo` module can also find which zone
+//! a point on a map is in, using a separate set of zone boundaries. With
+//! the `chrono` feature enabled, the `chrono_zone` module lets a set of
+//! computed timespans be used as a `chrono` time zone, and with the `time`
+//! feature, the `time_offsets` module gives their offsets as `time` crate
+//! `UtcOffset`s. The `wasm` feature adds the `wasm` module, which lets
+//! JavaScript parse tables and query them through WebAssembly, and the
+//! `capi` feature adds the `capi` module, which lets C do the same.
 
 #![crate_name = "zoneinfo_parse"]
 #![crate_type = "rlib"]
@@ -42,8 +75,43 @@
 extern crate datetime;
 extern crate regex;
 #[macro_use] extern crate lazy_static;
+#[cfg(feature = "geo")] extern crate serde_json;
+#[cfg(feature = "chrono")] extern crate chrono;
+#[cfg(feature = "time")] extern crate time;
+#[cfg(feature = "wasm")] extern crate wasm_bindgen;
 
 pub mod line;
 pub mod table;
 pub mod transitions;
+pub mod timezone;
 pub mod structure;
+pub mod validation;
+pub mod diff;
+pub mod tab;
+pub mod windows;
+pub mod compiled;
+pub mod leap;
+pub mod tai;
+pub mod source;
+pub mod zdump;
+
+#[cfg(feature = "geo")]
+pub mod geo;
+
+#[cfg(feature = "chrono")]
+pub mod chrono_zone;
+
+#[cfg(feature = "time")]
+pub mod time_offsets;
+
+#[cfg(feature = "wasm")]
+pub mod wasm;
+
+#[cfg(feature = "capi")]
+pub mod capi;
+
+mod capabilities;
+pub use capabilities::{capabilities, Capabilities};
+
+mod parse;
+pub use parse::{parse_table, parse_sources, parse_sources_with, ParseError, ParseOptions};
@@ -6,8 +6,13 @@
 //! `Zone`, or `Continuation` lines.
 //!
 //! `Line` is the type that parses and holds zoneinfo line data. To try to
-//! parse a string, use the `Line::from_str` constructor. (This isn’t the
-//! `FromStr` trait, so you can’t use `parse` on a string. Sorry!)
+//! parse a string, use the `Line::from_str` constructor, or `Rule::from_str`,
+//! `Zone::from_str`, or `Link::from_str` for one kind of line. (These aren’t
+//! the `FromStr` trait, as the parsed lines borrow their names from the
+//! string, which the trait has no way to say. Sorry!) The field types that
+//! don’t borrow anything, such as `DaySpec` and `ChangeTime`, do implement
+//! `FromStr`, and so do the owned `RuleInfo` and `ZoneInfo` types in the
+//! `table` module, so a single field or line can be parsed with `parse`.
 //!
 //! ## Examples
 //!
@@ -31,6 +36,7 @@
 //!     day:          DaySpec::FirstOnOrAfter(WeekdaySpec(Weekday::Sunday), 1),
 //!     time:         TimeSpec::HoursMinutes(1, 0).with_type(TimeType::UTC),
 //!     time_to_add:  TimeSpec::HoursMinutes(1, 0),
+//!     is_dst:       None,
 //!     letters:      Some("S"),
 //! })));
 //! # }
@@ -76,11 +82,55 @@
 //!     new:       "Asia/Istanbul",
 //! })));
 //! ```
+//!
+//! ## The slim form
+//!
+//! As well as the usual spelling, lines can use the abbreviations that
+//! `zic` accepts, which the `tzdata.zi` file that comes with the tz
+//! database uses to save space: keywords can be cut down to any start of
+//! them, such as `R`, `Z`, and `L`, or `o` and `ma` for `only` and `max`;
+//! month and weekday names to any start that isn’t the start of another,
+//! such as `Ja` or `Su`; and times to plain hours, such as `2s`.
+//! Continuation lines don’t have to be indented.
+//!
+//! Fields can be separated by any whitespace, not just spaces and tabs, so
+//! non-breaking spaces that crept in through a mirror don’t stop a line
+//! from parsing. To read a whole file, `split_lines` splits it up in a way
+//! that also copes with Windows line endings and byte-order marks.
+//!
+//! The SAVE field of a rule can end with `s` or `d`, which newer releases
+//! use to say outright whether the rule’s time is standard or
+//! daylight-saving time, such as `0d` for a rule that moves the clocks
+//! back in summer. It’s kept in `Rule::is_dst`; without it, any non-zero
+//! amount counts as daylight-saving time.
+//!
+//! Abbreviations in the FORMAT field can be quoted in angle brackets, as
+//! POSIX TZ strings do for numeric ones such as `<+0530>`. The brackets
+//! are kept in the parsed line, and `unquote_abbreviation` takes them off.
+//!
+//! ## Building fields in code
+//!
+//! The fields can also be built without parsing anything, using the
+//! constructors on each field type, which check that their values make
+//! sense: `DaySpec::first_on_or_after(Weekday::Sunday, 1)` is the `Sun>=1`
+//! from the first example, and `DaySpec::last(Weekday::Sunday)` would be
+//! `lastSun`. Their accessors, such as `DaySpec::weekday` and
+//! `TimeSpec::components`, pick parsed fields apart again without having
+//! to match on every variant.
+//!
+//! Lines and fields can all be compared for equality, ordered, and hashed,
+//! so they can be used as map keys or deduplicated. The ordering goes by
+//! their structure rather than by time, though: `Hours(3)` comes before
+//! `HoursMinutes(2, 30)`, and `max` before any year number.
 
 use std::ascii::AsciiExt;
+use std::cmp::Ordering;
 use std::error::Error as ErrorTrait;
 use std::fmt;
-use std::str::FromStr;
+use std::hash::{Hash, Hasher};
+use std::iter::Map;
+use std::num::ParseIntError;
+use std::str::{FromStr, Lines};
 
 use datetime::{LocalDate, LocalTime, LocalDateTime, Month, Weekday};
 use datetime::zone::TimeType;
@@ -96,7 +146,7 @@ lazy_static! {
 
     /// Format of a Rule line: one capturing group per field.
     static ref RULE_LINE: Regex = Regex::new(r##"(?x) ^
-        Rule \s+
+        R (?: u (?: le? )? )? \s+
         ( ?P<name>    \S+)  \s+
         ( ?P<from>    \S+)  \s+
         ( ?P<to>      \S+)  \s+
@@ -115,17 +165,25 @@ lazy_static! {
         ( ?P<day>     \d+ )
     $ "##).unwrap();
 
+    /// Format of an hour specification with a time type after it, which
+    /// plain hours without one don’t need a regex for.
+    static ref H_FIELD: Regex = Regex::new(r##"(?x) ^
+        ( ?P<sign> -? )
+        ( ?P<hour> \d{1,2} )
+        ( ?P<flag> [wsugz] )
+    $ "##).unwrap();
+
     /// Format of an hour and a minute specification.
     static ref HM_FIELD: Regex = Regex::new(r##"(?x) ^
         ( ?P<sign> -? )
-        ( ?P<hour> \d{1,2} ) : ( ?P<minute> \d{2} )
+        ( ?P<hour> \d{1,2} ) : ( ?P<minute> \d{1,2} )
         ( ?P<flag> [wsugz] )?
     $ "##).unwrap();
 
     /// Format of an hour, a minute, and a second specification.
     static ref HMS_FIELD: Regex = Regex::new(r##"(?x) ^
         ( ?P<sign> -? )
-        ( ?P<hour> \d{1,2} ) : ( ?P<minute> \d{2} ) : ( ?P<second> \d{2} )
+        ( ?P<hour> \d{1,2} ) : ( ?P<minute> \d{1,2} ) : ( ?P<second> \d{1,2} )
         ( ?P<flag> [wsugz] )?
     $ "##).unwrap();
 
@@ -133,7 +191,7 @@ lazy_static! {
 
     /// Format of a Zone line, with one capturing group per field.
     static ref ZONE_LINE: Regex = Regex::new(r##"(?x) ^
-        Zone \s+
+        Z (?: o (?: ne? )? )? \s+
         ( ?P<name> [ A-Z a-z 0-9 / _ + - ]+ )  \s+
         ( ?P<gmtoff>     \S+ )  \s+
         ( ?P<rulessave>  \S+ )  \s+
@@ -145,9 +203,10 @@ lazy_static! {
     "##).unwrap();
 
     /// Format of a Continuation Zone line, which is the same as the opening
-    /// Zone line except the first two fields are replaced by whitespace.
+    /// Zone line except the first two fields are replaced by whitespace, or
+    /// left out entirely in the slim `tzdata.zi` form.
     static ref CONTINUATION_LINE: Regex = Regex::new(r##"(?x) ^
-        \s+
+        \s*
         ( ?P<gmtoff>     \S+ )  \s+
         ( ?P<rulessave>  \S+ )  \s+
         ( ?P<format>     \S+ )  \s*
@@ -159,7 +218,7 @@ lazy_static! {
 
     /// Format of a Link line, with one capturing group per field.
     static ref LINK_LINE: Regex = Regex::new(r##"(?x) ^
-        Link  \s+
+        L (?: i (?: nk? )? )?  \s+
         ( ?P<target>  \S+ )  \s+
         ( ?P<name>    \S+ )
     "##).unwrap();
@@ -183,7 +242,7 @@ lazy_static! {
 ///
 /// Apart from the opening `Rule` to specify which kind of line this is, and
 /// the `type` column, every column in the line has a field in this struct.
-#[derive(PartialEq, Debug, Copy, Clone)]
+#[derive(PartialEq, Eq, PartialOrd, Ord, Hash, Debug, Copy, Clone)]
 pub struct Rule<'line> {
 
     /// The name of the set of rules that this rule is part of.
@@ -207,6 +266,10 @@ pub struct Rule<'line> {
     /// The amount of time to be added when the rule is in effect.
     pub time_to_add: TimeSpec,
 
+    /// Whether the rule’s time counts as daylight-saving time, if the SAVE
+    /// field says so with an `s` or `d` suffix, or `None` if it doesn’t.
+    pub is_dst: Option<bool>,
+
     /// The variable part of time zone abbreviations to be used when this rule
     /// is in effect, if any.
     pub letters: Option<&'line str>,
@@ -217,30 +280,31 @@ impl<'line> Rule<'line> {
     /// Attempts to parse the given string into a value of this type.
     pub fn from_str(input: &str) -> Result<Rule, Error> {
         if let Some(caps) = RULE_LINE.captures(input) {
-            let name      = caps.name("name").unwrap();
-            let from_year = try!(caps.name("from").unwrap().parse());
+            let name      = caps.name("name").map(|m| m.as_str()).unwrap();
+            let from_year = try!(parse_field(&caps, "from", "FROM"));
 
             // The end year can be ‘only’ to indicate that this rule only
             // takes place on that year.
-            let to_year = match caps.name("to").unwrap() {
-                "only"  => None,
-                to      => Some(try!(to.parse())),
+            let to_year = match caps.name("to").map(|m| m.as_str()).unwrap() {
+                to if is_abbreviation(to, "only", 1)  => None,
+                _                                     => Some(try!(parse_field(&caps, "to", "TO"))),
             };
 
             // According to the spec, the only value inside the ‘type’ column
             // should be “-”, so throw an error if it isn’t. (It only exists
             // for compatibility with old versions that used to contain year
             // types.) Sometimes “‐”, a Unicode hyphen, is used as well.
-            let t = caps.name("type").unwrap();
+            let t = caps.name("type").map(|m| m.as_str()).unwrap();
             if t != "-" && t != "\u{2010}"  {
-                return Err(Error::Fail);
+                return Err(invalid_field(&caps, "type", "TYPE"));
             }
 
-            let month        = try!(caps.name("in").unwrap().parse());
-            let day          = try!(caps.name("on").unwrap().parse());
-            let time         = try!(caps.name("at").unwrap().parse());
-            let time_to_add  = try!(caps.name("save").unwrap().parse());
-            let letters      = match caps.name("letters").unwrap() {
+            let month        = try!(parse_field(&caps, "in", "IN"));
+            let day          = try!(parse_field(&caps, "on", "ON"));
+            let time         = try!(parse_field(&caps, "at", "AT"));
+            let (time_to_add, is_dst) = try!(parse_save(caps.name("save").map(|m| m.as_str()).unwrap())
+                                                 .map_err(|_| invalid_field(&caps, "save", "SAVE")));
+            let letters      = match caps.name("letters").map(|m| m.as_str()).unwrap() {
                 "-"  => None,
                 l    => Some(l),
             };
@@ -253,6 +317,7 @@ impl<'line> Rule<'line> {
                 day:          day,
                 time:         time,
                 time_to_add:  time_to_add,
+                is_dst:       is_dst,
                 letters:      letters,
             })
         }
@@ -282,7 +347,7 @@ impl<'line> Rule<'line> {
 ///
 /// A continuation rule line contains all the same fields apart from the
 /// `Name` column and the opening `Zone` identifier.
-#[derive(PartialEq, Debug, Copy, Clone)]
+#[derive(PartialEq, Eq, PartialOrd, Ord, Hash, Debug, Copy, Clone)]
 pub struct Zone<'line> {
 
     /// The name of the time zone.
@@ -297,7 +362,7 @@ impl<'line> Zone<'line> {
     /// Attempts to parse the given string into a value of this type.
     pub fn from_str(input: &str) -> Result<Zone, Error> {
         if let Some(caps) = ZONE_LINE.captures(input) {
-            let name = caps.name("name").unwrap();
+            let name = caps.name("name").map(|m| m.as_str()).unwrap();
             let info = try!(ZoneInfo::from_captures(caps));
 
             Ok(Zone {
@@ -313,7 +378,7 @@ impl<'line> Zone<'line> {
 
 
 /// The information contained in both zone lines *and* zone continuation lines.
-#[derive(PartialEq, Debug, Copy, Clone)]
+#[derive(PartialEq, Eq, PartialOrd, Ord, Hash, Debug, Copy, Clone)]
 pub struct ZoneInfo<'line> {
 
     /// The amount of time that needs to be added to UTC to get the standard
@@ -324,7 +389,8 @@ pub struct ZoneInfo<'line> {
     /// amount of time to add.
     pub saving: Saving<'line>,
 
-    /// The format for time zone abbreviations, with `%s` as the string marker.
+    /// The format for time zone abbreviations, with `%s` as the string
+    /// marker, as it was written, including any angle brackets.
     pub format: &'line str,
 
     /// The time at which the rules change for this location, or `None` if
@@ -334,18 +400,28 @@ pub struct ZoneInfo<'line> {
 
 impl<'line> ZoneInfo<'line> {
     fn from_captures(caps: Captures<'line>) -> Result<ZoneInfo<'line>, Error> {
-        let utc_offset    = try!(caps.name("gmtoff").unwrap().parse());
-        let saving        = try!(Saving::from_str(caps.name("rulessave").unwrap()));
-        let format        = caps.name("format").unwrap();
+        let utc_offset    = try!(parse_field(&caps, "gmtoff", "GMTOFF"));
+        let saving        = try!(Saving::from_str(caps.name("rulessave").map(|m| m.as_str()).unwrap())
+                                     .map_err(|_| invalid_field(&caps, "rulessave", "RULES/SAVE")));
+        let format        = caps.name("format").map(|m| m.as_str()).unwrap();
+
+        if format.split('/').any(|abbreviation| unquote_abbreviation(abbreviation).is_err()) {
+            return Err(invalid_field(&caps, "format", "FORMAT"));
+        }
 
         // The year, month, day, and time fields are all optional, meaning
         // that it should be impossible to, say, have a defined month but not
         // a defined year.
-        let time = match (caps.name("year"), caps.name("month"), caps.name("day"), caps.name("time")) {
-            (Some(y), Some(m), Some(d), Some(t)) => Some(ChangeTime::UntilTime  (try!(y.parse()), try!(m.parse()), try!(d.parse()), try!(t.parse()))),
-            (Some(y), Some(m), Some(d), _      ) => Some(ChangeTime::UntilDay   (try!(y.parse()), try!(m.parse()), try!(d.parse()))),
-            (Some(y), Some(m), _      , _      ) => Some(ChangeTime::UntilMonth (try!(y.parse()), try!(m.parse()))),
-            (Some(y), _      , _      , _      ) => Some(ChangeTime::UntilYear  (try!(y.parse()))),
+        let year   = try!(parse_optional_field(&caps, "year", "UNTILYEAR"));
+        let month  = try!(parse_optional_field(&caps, "month", "MONTH"));
+        let day    = try!(parse_optional_field(&caps, "day", "DAY"));
+        let until  = try!(parse_optional_field(&caps, "time", "TIME"));
+
+        let time = match (year, month, day, until) {
+            (Some(y), Some(m), Some(d), Some(t)) => Some(ChangeTime::UntilTime(y, m, d, t)),
+            (Some(y), Some(m), Some(d), None   ) => Some(ChangeTime::UntilDay(y, m, d)),
+            (Some(y), Some(m), None   , None   ) => Some(ChangeTime::UntilMonth(y, m)),
+            (Some(y), None   , None   , None   ) => Some(ChangeTime::UntilYear(y)),
             (None   , None   , None   , None   ) => None,
             _                                    => unreachable!("Out-of-order capturing groups!"),
         };
@@ -360,10 +436,47 @@ impl<'line> ZoneInfo<'line> {
 }
 
 
+/// Returns an abbreviation without the angle brackets it can be quoted in,
+/// such as `+0530` for `<+0530>`, as long as there’s something inside them
+/// and it’s only letters, digits, `+`, `-`, and `%`. Abbreviations that
+/// aren’t quoted are returned as they are.
+pub fn unquote_abbreviation(input: &str) -> Result<&str, Error> {
+    if !input.starts_with('<') && !input.ends_with('>') {
+        return Ok(input);
+    }
+    else if input.len() < 3 || !input.starts_with('<') || !input.ends_with('>') {
+        return Err(Error::Fail);
+    }
+
+    let inner = &input[1 .. input.len() - 1];
+    if inner.chars().all(|c| (c.is_ascii() && c.is_alphanumeric()) || c == '+' || c == '-' || c == '%') {
+        Ok(inner)
+    }
+    else {
+        Err(Error::Fail)
+    }
+}
+
+
+/// Splits the text of a whole file into lines, as `str::lines` does, but
+/// without a byte-order mark at the start of the first line, or any
+/// whitespace at the end of each one. Files that have been through Windows
+/// can have both, and this way they end up as the same lines as the
+/// original, rather than with a stray `\r` on the end of the last field.
+pub fn split_lines<'a>(text: &'a str) -> Map<Lines<'a>, fn(&'a str) -> &'a str> {
+    fn trim(line: &str) -> &str {
+        line.trim_end()
+    }
+
+    let text = if text.starts_with('\u{feff}') { &text['\u{feff}'.len_utf8() ..] } else { text };
+    text.lines().map(trim)
+}
+
+
 /// The amount of daylight saving time (DST) to apply to this timespan. This
 /// is a special type for a certain field in a zone line, which can hold
 /// different types of value.
-#[derive(PartialEq, Debug, Copy, Clone)]
+#[derive(PartialEq, Eq, PartialOrd, Ord, Hash, Debug, Copy, Clone)]
 pub enum Saving<'line> {
 
     /// Just stick to the base offset.
@@ -380,20 +493,19 @@ pub enum Saving<'line> {
 }
 
 impl<'line> Saving<'line> {
-    fn from_str(input: &str) -> Result<Saving, Error> {
+
+    /// Attempts to parse the given string into a value of this type.
+    pub fn from_str(input: &str) -> Result<Saving, Error> {
         if input == "-" {
             Ok(Saving::NoSaving)
         }
         else if input.chars().all(|c| c == '-' || c == '_' || c.is_alphabetic()) {
             Ok(Saving::Multiple(input))
         }
-        else if HM_FIELD.is_match(input) {
+        else {
             let time = try!(input.parse());
             Ok(Saving::OneOff(time))
         }
-        else {
-            Err(Error::Fail)
-        }
     }
 }
 
@@ -404,7 +516,7 @@ impl<'line> Saving<'line> {
 /// the beginning of the year lists only the year, a change that occurs on a
 /// particular day has to list the year, month, and day, and one that occurs
 /// at a particular second has to list everything.
-#[derive(PartialEq, Debug, Copy, Clone)]
+#[derive(PartialEq, Eq, PartialOrd, Ord, Hash, Debug, Copy, Clone)]
 pub enum ChangeTime {
 
     /// The earliest point in a particular **year**.
@@ -447,11 +559,34 @@ impl ChangeTime {
             _ => unreachable!("What happened? {:?}", self),
         }.to_instant().seconds()
     }
+
+    /// Parses the year, month, day, and time fields of a change time, of
+    /// which there can be between one and four.
+    fn from_fields(fields: &[&str]) -> Result<ChangeTime, Error> {
+        match fields.len() {
+            4  => Ok(ChangeTime::UntilTime  (try!(fields[0].parse()), try!(fields[1].parse()), try!(fields[2].parse()), try!(fields[3].parse()))),
+            3  => Ok(ChangeTime::UntilDay   (try!(fields[0].parse()), try!(fields[1].parse()), try!(fields[2].parse()))),
+            2  => Ok(ChangeTime::UntilMonth (try!(fields[0].parse()), try!(fields[1].parse()))),
+            1  => Ok(ChangeTime::UntilYear  (try!(fields[0].parse()))),
+            _  => Err(Error::Fail),
+        }
+    }
+}
+
+/// Parses the UNTIL columns of a zone line, such as `1971 Oct 31 2:00`,
+/// separated by whitespace.
+impl FromStr for ChangeTime {
+    type Err = Error;
+
+    fn from_str(input: &str) -> Result<ChangeTime, Self::Err> {
+        let fields: Vec<&str> = input.split_whitespace().collect();
+        ChangeTime::from_fields(&fields)
+    }
 }
 
 
 /// A **link** definition line.
-#[derive(PartialEq, Debug, Copy, Clone)]
+#[derive(PartialEq, Eq, PartialOrd, Ord, Hash, Debug, Copy, Clone)]
 pub struct Link<'line> {
 
     /// The target time zone, which should appear as the name in a zone definition.
@@ -466,8 +601,8 @@ impl<'line> Link<'line> {
     /// Attempts to parse the given string into a value of this type.
     pub fn from_str(input: &str) -> Result<Link, Error> {
         if let Some(caps) = LINK_LINE.captures(input) {
-            let target  = caps.name("target").unwrap();
-            let name    = caps.name("name").unwrap();
+            let target  = caps.name("target").map(|m| m.as_str()).unwrap();
+            let name    = caps.name("name").map(|m| m.as_str()).unwrap();
             Ok(Link { existing: target, new: name })
         }
         else {
@@ -486,7 +621,7 @@ impl<'line> Link<'line> {
 /// - `max` or `maximum`, the maximum year possible, for when a rule needs to
 ///   apply after the last rule with a specific year;
 /// - a year number, referring to a specific year.
-#[derive(PartialEq, Debug, Copy, Clone)]
+#[derive(PartialEq, Eq, PartialOrd, Ord, Hash, Debug, Copy, Clone)]
 pub enum YearSpec {
 
     /// The minimum year possible: `min` or `minimum`.
@@ -503,14 +638,14 @@ impl FromStr for YearSpec {
     type Err = Error;
 
     fn from_str(input: &str) -> Result<YearSpec, Self::Err> {
-        if input == "min" || input == "minimum" {
+        if is_abbreviation(input, "minimum", 2) {
             Ok(YearSpec::Minimum)
         }
-        else if input == "max" || input == "maximum" {
+        else if is_abbreviation(input, "maximum", 2) {
             Ok(YearSpec::Maximum)
         }
         else if input.chars().all(|c| c.is_digit(10)) {
-            Ok(YearSpec::Number(input.parse().unwrap()))
+            Ok(YearSpec::Number(try!(input.parse())))
         }
         else {
             Err(Error::Fail)
@@ -518,32 +653,75 @@ impl FromStr for YearSpec {
     }
 }
 
+impl YearSpec {
+
+    /// Returns the year number, if this is a specific year rather than the
+    /// minimum or maximum.
+    pub fn number(&self) -> Option<i64> {
+        match *self {
+            YearSpec::Number(year) => Some(year),
+            _                      => None,
+        }
+    }
+}
+
 
 /// A **month** field, which is actually just a wrapper around
 /// `datetime::Month`.
 #[derive(PartialEq, Debug, Copy, Clone)]
 pub struct MonthSpec(pub Month);
 
+impl MonthSpec {
+
+    /// Creates a month field for the given month.
+    pub fn new(month: Month) -> MonthSpec {
+        MonthSpec(month)
+    }
+
+    /// Returns the month this field refers to.
+    pub fn month(&self) -> Month {
+        self.0
+    }
+}
+
 impl FromStr for MonthSpec {
     type Err = Error;
 
     /// Attempts to parse the given string into a value of this type.
     fn from_str(input: &str) -> Result<MonthSpec, Self::Err> {
-        Ok(match &*input.to_ascii_lowercase() {
-            "jan" | "january"    => MonthSpec(Month::January),
-            "feb" | "february"   => MonthSpec(Month::February),
-            "mar" | "march"      => MonthSpec(Month::March),
-            "apr" | "april"      => MonthSpec(Month::April),
-            "may"                => MonthSpec(Month::May),
-            "jun" | "june"       => MonthSpec(Month::June),
-            "jul" | "july"       => MonthSpec(Month::July),
-            "aug" | "august"     => MonthSpec(Month::August),
-            "sep" | "september"  => MonthSpec(Month::September),
-            "oct" | "october"    => MonthSpec(Month::October),
-            "nov" | "november"   => MonthSpec(Month::November),
-            "dec" | "december"   => MonthSpec(Month::December),
-                  _              => return Err(Error::Fail),
-        })
+        const MONTHS: [(&'static str, Month); 12] = [
+            ("january",    Month::January),    ("february",   Month::February),
+            ("march",      Month::March),      ("april",      Month::April),
+            ("may",        Month::May),        ("june",       Month::June),
+            ("july",       Month::July),       ("august",     Month::August),
+            ("september",  Month::September),  ("october",    Month::October),
+            ("november",   Month::November),   ("december",   Month::December),
+        ];
+
+        unique_abbreviation(input, &MONTHS).map(MonthSpec)
+    }
+}
+
+// `datetime::Month` can’t be hashed, so months get compared and hashed by
+// their numbers instead.
+
+impl Eq for MonthSpec {}
+
+impl PartialOrd for MonthSpec {
+    fn partial_cmp(&self, other: &MonthSpec) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for MonthSpec {
+    fn cmp(&self, other: &MonthSpec) -> Ordering {
+        (self.0 as i8).cmp(&(other.0 as i8))
+    }
+}
+
+impl Hash for MonthSpec {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        (self.0 as i8).hash(state)
     }
 }
 
@@ -553,21 +731,54 @@ impl FromStr for MonthSpec {
 #[derive(PartialEq, Debug, Copy, Clone)]
 pub struct WeekdaySpec(pub Weekday);
 
+impl WeekdaySpec {
+
+    /// Creates a weekday field for the given weekday.
+    pub fn new(weekday: Weekday) -> WeekdaySpec {
+        WeekdaySpec(weekday)
+    }
+
+    /// Returns the weekday this field refers to.
+    pub fn weekday(&self) -> Weekday {
+        self.0
+    }
+}
+
 impl FromStr for WeekdaySpec {
     type Err = Error;
 
     fn from_str(input: &str) -> Result<WeekdaySpec, Self::Err> {
 
-        Ok(match &*input.to_ascii_lowercase() {
-            "mon" | "monday"     => WeekdaySpec(Weekday::Monday),
-            "tue" | "tuesday"    => WeekdaySpec(Weekday::Tuesday),
-            "wed" | "wednesday"  => WeekdaySpec(Weekday::Wednesday),
-            "thu" | "thursday"   => WeekdaySpec(Weekday::Thursday),
-            "fri" | "friday"     => WeekdaySpec(Weekday::Friday),
-            "sat" | "saturday"   => WeekdaySpec(Weekday::Saturday),
-            "sun" | "sunday"     => WeekdaySpec(Weekday::Sunday),
-                  _              => return Err(Error::Fail),
-        })
+        const WEEKDAYS: [(&'static str, Weekday); 7] = [
+            ("monday",     Weekday::Monday),     ("tuesday",    Weekday::Tuesday),
+            ("wednesday",  Weekday::Wednesday),  ("thursday",   Weekday::Thursday),
+            ("friday",     Weekday::Friday),     ("saturday",   Weekday::Saturday),
+            ("sunday",     Weekday::Sunday),
+        ];
+
+        unique_abbreviation(input, &WEEKDAYS).map(WeekdaySpec)
+    }
+}
+
+// The same goes for `datetime::Weekday`, with weeks starting on Sunday.
+
+impl Eq for WeekdaySpec {}
+
+impl PartialOrd for WeekdaySpec {
+    fn partial_cmp(&self, other: &WeekdaySpec) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for WeekdaySpec {
+    fn cmp(&self, other: &WeekdaySpec) -> Ordering {
+        (self.0 as i8).cmp(&(other.0 as i8))
+    }
+}
+
+impl Hash for WeekdaySpec {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        (self.0 as i8).hash(state)
     }
 }
 
@@ -580,7 +791,7 @@ impl FromStr for WeekdaySpec {
 ///
 /// Note that in the last example, it’s allowed for that particular Friday to
 /// *be* the 13th in question.
-#[derive(PartialEq, Debug, Copy, Clone)]
+#[derive(PartialEq, Eq, PartialOrd, Ord, Hash, Debug, Copy, Clone)]
 pub enum DaySpec {
 
     /// A specific day of the month, given by its number.
@@ -600,16 +811,74 @@ pub enum DaySpec {
 
 impl DaySpec {
 
+    /// Creates a day field for the given day of the month, which has to be
+    /// between 1 and 31.
+    pub fn ordinal(day: i8) -> Option<DaySpec> {
+        if day >= 1 && day <= 31 { Some(DaySpec::Ordinal(day)) } else { None }
+    }
+
+    /// Creates a day field for the last day of the month with the given
+    /// weekday, as `lastSun` would be written.
+    pub fn last(weekday: Weekday) -> DaySpec {
+        DaySpec::Last(WeekdaySpec(weekday))
+    }
+
+    /// Creates a day field for the last day with the given weekday on or
+    /// before the given day of the month, as `Sun<=25` would be written.
+    /// The day has to be between 1 and 31.
+    pub fn last_on_or_before(weekday: Weekday, day: i8) -> Option<DaySpec> {
+        DaySpec::ordinal(day).map(|_| DaySpec::LastOnOrBefore(WeekdaySpec(weekday), day))
+    }
+
+    /// Creates a day field for the first day with the given weekday on or
+    /// after the given day of the month, as `Sun>=8` would be written. The
+    /// day has to be between 1 and 31.
+    pub fn first_on_or_after(weekday: Weekday, day: i8) -> Option<DaySpec> {
+        DaySpec::ordinal(day).map(|_| DaySpec::FirstOnOrAfter(WeekdaySpec(weekday), day))
+    }
+
+    /// Returns the weekday this field looks for, if it’s one of the
+    /// relative forms.
+    pub fn weekday(&self) -> Option<Weekday> {
+        match *self {
+            DaySpec::Ordinal(_)             => None,
+            DaySpec::Last(w)                => Some(w.0),
+            DaySpec::LastOnOrBefore(w, _)   => Some(w.0),
+            DaySpec::FirstOnOrAfter(w, _)   => Some(w.0),
+        }
+    }
+
+    /// Returns the day of the month this field gives or is relative to, if
+    /// there is one: every form has one apart from `last`.
+    pub fn day_of_month(&self) -> Option<i8> {
+        match *self {
+            DaySpec::Ordinal(day)             => Some(day),
+            DaySpec::Last(_)                  => None,
+            DaySpec::LastOnOrBefore(_, day)   => Some(day),
+            DaySpec::FirstOnOrAfter(_, day)   => Some(day),
+        }
+    }
+
     /// Converts this day specification to a concrete date, given the year and
     /// month it should occur in.
     pub fn to_concrete_date(&self, year: i64, month: Month) -> LocalDate {
-        use datetime::{LocalDate, DatePiece, Year};
+        use datetime::{LocalDate, Year, Duration};
+
+        // The relative forms can end up in an adjacent month: `Fri<=1` in
+        // April can fall in March, and `Sun>=29` in February can fall in
+        // March. So search through the week on either side of the given
+        // day, rather than only through the days of this month.
+        let week = |day, direction: i64| {
+            let first = LocalDateTime::new(LocalDate::ymd(year, month, 1).unwrap(), LocalTime::midnight());
+            let anchor = first + Duration::of((day as i64 - 1) * 86400);
+            (0..7).map(move |i| (anchor + Duration::of(i * direction * 86400)).date())
+        };
 
         match *self {
             DaySpec::Ordinal(day)           => LocalDate::ymd(year, month, day).unwrap(),
             DaySpec::Last(w)                => DaySpec::find_weekday(w, Year(year).month(month).days(..).rev()),
-            DaySpec::LastOnOrBefore(w, day) => DaySpec::find_weekday(w, Year(year).month(month).days(..).rev().filter(|d| d.day() < day)),
-            DaySpec::FirstOnOrAfter(w, day) => DaySpec::find_weekday(w, Year(year).month(month).days(..).skip(day as usize - 1)),
+            DaySpec::LastOnOrBefore(w, day) => DaySpec::find_weekday(w, week(day, -1)),
+            DaySpec::FirstOnOrAfter(w, day) => DaySpec::find_weekday(w, week(day, 1)),
         }
     }
 
@@ -631,7 +900,7 @@ impl FromStr for DaySpec {
 
         // Parse the field as a number if it vaguely resembles one.
         if input.chars().all(|c| c.is_digit(10)) {
-            Ok(DaySpec::Ordinal(input.parse().unwrap()))
+            Ok(DaySpec::Ordinal(try!(input.parse())))
         }
 
         // Check if it stars with ‘last’, and trim off the first four bytes if
@@ -643,10 +912,10 @@ impl FromStr for DaySpec {
 
         // Check if it’s a relative expression with the regex.
         else if let Some(caps) = DAY_FIELD.captures(input) {
-            let weekday = caps.name("weekday").unwrap().parse().unwrap();
-            let day     = caps.name("day").unwrap().parse().unwrap();
+            let weekday = try!(caps.name("weekday").map(|m| m.as_str()).unwrap().parse());
+            let day     = try!(caps.name("day").map(|m| m.as_str()).unwrap().parse());
 
-            match caps.name("sign").unwrap() {
+            match caps.name("sign").map(|m| m.as_str()).unwrap() {
                 "<=" => Ok(DaySpec::LastOnOrBefore(weekday, day)),
                 ">=" => Ok(DaySpec::FirstOnOrAfter(weekday, day)),
                  _   => unreachable!("The regex only matches one of those two!"),
@@ -669,7 +938,7 @@ impl FromStr for DaySpec {
 /// Hour 0 is midnight at the start of the day, and Hour 24 is midnight at the
 /// end of the day.
 ///
-#[derive(PartialEq, Debug, Copy, Clone)]
+#[derive(PartialEq, Eq, PartialOrd, Ord, Hash, Debug, Copy, Clone)]
 pub enum TimeSpec {
 
     /// A number of hours.
@@ -690,8 +959,82 @@ pub enum TimeSpec {
 #[derive(PartialEq, Debug, Copy, Clone)]
 pub struct TimeSpecAndType(pub TimeSpec, pub TimeType);
 
+impl TimeSpecAndType {
+
+    /// Returns the time, without its type.
+    pub fn time_spec(&self) -> TimeSpec {
+        self.0
+    }
+
+    /// Returns whether the time is wall clock time, standard time, or UTC.
+    pub fn time_type(&self) -> TimeType {
+        self.1
+    }
+}
+
+// Nor can `datetime::zone::TimeType` be compared or hashed, so the types
+// go by their position in the order wall clock time, standard time, UTC.
+
+/// Returns the position of a time type, for comparing and hashing.
+fn time_type_index(time_type: TimeType) -> u8 {
+    match time_type {
+        TimeType::Wall      => 0,
+        TimeType::Standard  => 1,
+        TimeType::UTC       => 2,
+    }
+}
+
+impl Eq for TimeSpecAndType {}
+
+impl PartialOrd for TimeSpecAndType {
+    fn partial_cmp(&self, other: &TimeSpecAndType) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for TimeSpecAndType {
+    fn cmp(&self, other: &TimeSpecAndType) -> Ordering {
+        (self.0, time_type_index(self.1)).cmp(&(other.0, time_type_index(other.1)))
+    }
+}
+
+impl Hash for TimeSpecAndType {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.0.hash(state);
+        time_type_index(self.1).hash(state);
+    }
+}
+
 impl TimeSpec {
 
+    /// Creates a time field from hours, minutes, and seconds, using the
+    /// shortest form that can hold them, so `2:00` and `2:00:00` both come
+    /// out as `Hours(2)`. A negative time has every non-zero component
+    /// negative, and the minutes and seconds have to be less than 60
+    /// either way.
+    pub fn new(hours: i8, minutes: i8, seconds: i8) -> Option<TimeSpec> {
+        let negative = hours < 0 || minutes < 0 || seconds < 0;
+        let positive = hours > 0 || minutes > 0 || seconds > 0;
+        if (negative && positive) || minutes.abs() >= 60 || seconds.abs() >= 60 {
+            return None;
+        }
+
+        Some(if seconds != 0 { TimeSpec::HoursMinutesSeconds(hours, minutes, seconds) }
+        else if minutes != 0 { TimeSpec::HoursMinutes(hours, minutes) }
+        else                 { TimeSpec::Hours(hours) })
+    }
+
+    /// Returns the hours, minutes, and seconds of this time, with zeroes
+    /// for the components it doesn’t have.
+    pub fn components(&self) -> (i8, i8, i8) {
+        match *self {
+            TimeSpec::Zero                         => (0, 0, 0),
+            TimeSpec::Hours(h)                     => (h, 0, 0),
+            TimeSpec::HoursMinutes(h, m)           => (h, m, 0),
+            TimeSpec::HoursMinutesSeconds(h, m, s) => (h, m, s),
+        }
+    }
+
     /// Shorthand `TimeSpecAndType` constructor for testing.
     pub fn with_type(self, time_type: TimeType) -> TimeSpecAndType {
         TimeSpecAndType(self, time_type)
@@ -717,23 +1060,30 @@ impl FromStr for TimeSpecAndType {
             Ok(TimeSpecAndType(TimeSpec::Zero, TimeType::Wall))
         }
         else if input.chars().all(|c| c == '-' || c.is_digit(10)) {
-            Ok(TimeSpecAndType(TimeSpec::Hours(input.parse().unwrap()), TimeType::Wall))
+            Ok(TimeSpecAndType(TimeSpec::Hours(try!(input.parse())), TimeType::Wall))
+        }
+        else if let Some(caps) = H_FIELD.captures(input) {
+            let sign   : i8 = if caps.name("sign").map(|m| m.as_str()).unwrap() == "-" { -1 } else { 1 };
+            let hour   : i8 = try!(caps.name("hour").map(|m| m.as_str()).unwrap().parse());
+            let flag        = parse_time_type(caps.name("flag").map(|m| m.as_str()).unwrap()).unwrap();
+
+            Ok(TimeSpecAndType(TimeSpec::Hours(hour * sign), flag))
         }
         else if let Some(caps) = HM_FIELD.captures(input) {
-            let sign   : i8 = if caps.name("sign").unwrap() == "-" { -1 } else { 1 };
-            let hour   : i8 = caps.name("hour").unwrap().parse().unwrap();
-            let minute : i8 = caps.name("minute").unwrap().parse().unwrap();
-            let flag        = caps.name("flag").and_then(|c| parse_time_type(&c[0..1]))
+            let sign   : i8 = if caps.name("sign").map(|m| m.as_str()).unwrap() == "-" { -1 } else { 1 };
+            let hour   : i8 = try!(caps.name("hour").map(|m| m.as_str()).unwrap().parse());
+            let minute : i8 = try!(caps.name("minute").map(|m| m.as_str()).unwrap().parse());
+            let flag        = caps.name("flag").map(|m| m.as_str()).and_then(|c| parse_time_type(&c[0..1]))
                                           .unwrap_or(TimeType::Wall);
 
             Ok(TimeSpecAndType(TimeSpec::HoursMinutes(hour * sign, minute * sign), flag))
         }
         else if let Some(caps) = HMS_FIELD.captures(input) {
-            let sign   : i8 = if caps.name("sign").unwrap() == "-" { -1 } else { 1 };
-            let hour   : i8 = caps.name("hour").unwrap().parse().unwrap();
-            let minute : i8 = caps.name("minute").unwrap().parse().unwrap();
-            let second : i8 = caps.name("second").unwrap().parse().unwrap();
-            let flag        = caps.name("flag").and_then(|c| parse_time_type(&c[0..1]))
+            let sign   : i8 = if caps.name("sign").map(|m| m.as_str()).unwrap() == "-" { -1 } else { 1 };
+            let hour   : i8 = try!(caps.name("hour").map(|m| m.as_str()).unwrap().parse());
+            let minute : i8 = try!(caps.name("minute").map(|m| m.as_str()).unwrap().parse());
+            let second : i8 = try!(caps.name("second").map(|m| m.as_str()).unwrap().parse());
+            let flag        = caps.name("flag").map(|m| m.as_str()).and_then(|c| parse_time_type(&c[0..1]))
                                           .unwrap_or(TimeType::Wall);
 
             Ok(TimeSpecAndType(TimeSpec::HoursMinutesSeconds(hour * sign, minute * sign, second * sign), flag))
@@ -756,6 +1106,64 @@ impl FromStr for TimeSpec {
     }
 }
 
+/// Parses the SAVE field of a rule line, which can end with `s` to say the
+/// time is standard time or `d` to say it’s daylight-saving time, whatever
+/// the amount.
+fn parse_save(input: &str) -> Result<(TimeSpec, Option<bool>), Error> {
+    let (amount, is_dst) = if input.ends_with('s') {
+        (&input[.. input.len() - 1], Some(false))
+    }
+    else if input.ends_with('d') {
+        (&input[.. input.len() - 1], Some(true))
+    }
+    else {
+        (input, None)
+    };
+
+    let time_to_add = try!(amount.parse());
+    Ok((time_to_add, is_dst))
+}
+
+/// Parses the field in the given capturing group, returning an error that
+/// says which field it was and where it starts if it can’t be parsed.
+fn parse_field<T: FromStr<Err=Error>>(caps: &Captures, group: &str, field: &'static str) -> Result<T, Error> {
+    let value = caps.name(group).unwrap();
+    value.as_str().parse().map_err(|_| Error::InvalidField(field, value.start()))
+}
+
+/// Parses the field in the given capturing group in the same way as
+/// `parse_field`, if the line has it.
+fn parse_optional_field<T: FromStr<Err=Error>>(caps: &Captures, group: &str, field: &'static str) -> Result<Option<T>, Error> {
+    match caps.name(group) {
+        Some(_)  => parse_field(caps, group, field).map(Some),
+        None     => Ok(None),
+    }
+}
+
+/// Returns the error for when the field in the given capturing group isn’t
+/// valid.
+fn invalid_field(caps: &Captures, group: &str, field: &'static str) -> Error {
+    Error::InvalidField(field, caps.name(group).unwrap().start())
+}
+
+/// Returns whether the input is the given word, or the start of it at least
+/// the given number of letters long, as `zic` accepts for keywords.
+fn is_abbreviation(input: &str, word: &str, min_length: usize) -> bool {
+    input.len() >= min_length && word.starts_with(input)
+}
+
+/// Returns the value of the name that the input is the start of, ignoring
+/// case, as long as it isn’t the start of any of the others as well.
+fn unique_abbreviation<T: Copy>(input: &str, names: &[(&'static str, T)]) -> Result<T, Error> {
+    let input = input.to_ascii_lowercase();
+    let mut matches = names.iter().filter(|&&(name, _)| is_abbreviation(&input, name, 1));
+
+    match (matches.next(), matches.next()) {
+        (Some(&(_, value)), None)  => Ok(value),
+        _                          => Err(Error::Fail),
+    }
+}
+
 /// Select which time type to use based on a timestamp’s suffix.
 fn parse_time_type(c: &str) -> Option<TimeType> {
     Some(match c {
@@ -768,22 +1176,45 @@ fn parse_time_type(c: &str) -> Option<TimeType> {
 
 
 /// An error that can occur during parsing.
-#[derive(PartialEq, Debug, Copy, Clone)]
+#[derive(PartialEq, Eq, PartialOrd, Ord, Hash, Debug, Copy, Clone)]
 pub enum Error {
 
     /// TODO: more error types
-    Fail
+    Fail,
+
+    /// The line is of a kind that was recognised, but the field with the
+    /// given name, which starts at the given byte offset into the line,
+    /// couldn’t be parsed.
+    InvalidField(&'static str, usize),
+}
+
+impl Error {
+
+    /// Returns the byte offset into the line of the field that couldn’t be
+    /// parsed, if it’s known.
+    pub fn offset(&self) -> Option<usize> {
+        match *self {
+            Error::Fail                      => None,
+            Error::InvalidField(_, offset)   => Some(offset),
+        }
+    }
 }
 
 impl fmt::Display for Error {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        write!(f, "{}", self.description())
+        match *self {
+            Error::Fail                   => write!(f, "{}", self.description()),
+            Error::InvalidField(field, _) => write!(f, "invalid {} field", field),
+        }
     }
 }
 
 impl ErrorTrait for Error {
     fn description(&self) -> &str {
-        "parse error"
+        match *self {
+            Error::Fail             => "parse error",
+            Error::InvalidField(..) => "invalid field",
+        }
     }
 
     fn cause(&self) -> Option<&ErrorTrait> {
@@ -791,8 +1222,15 @@ impl ErrorTrait for Error {
     }
 }
 
+/// Numbers too big to fit in their fields fail to parse like anything else.
+impl From<ParseIntError> for Error {
+    fn from(_: ParseIntError) -> Error {
+        Error::Fail
+    }
+}
+
 /// A type of valid line that has been parsed.
-#[derive(PartialEq, Debug, Copy, Clone)]
+#[derive(PartialEq, Eq, PartialOrd, Ord, Hash, Debug, Copy, Clone)]
 pub enum Line<'line> {
 
     /// This line is empty.
@@ -814,23 +1252,39 @@ pub enum Line<'line> {
 impl<'line> Line<'line> {
 
     /// Attempt to parse this line, returning a `Line` depending on what
-    /// type of line it was, or an `Error` if it couldn't be parsed.
+    /// type of line it was, or an `Error` if it couldn't be parsed. Once it’s
+    /// clear what kind of line it is, the error says which of its fields was
+    /// wrong, and where that field starts.
     pub fn from_str(input: &str) -> Result<Line, Error> {
         if EMPTY_LINE.is_match(input) {
-            Ok(Line::Space)
-        }
-        else if let Ok(zone) = Zone::from_str(input) {
-            Ok(Line::Zone(zone))
+            return Ok(Line::Space);
         }
-        else if let Some(caps) = CONTINUATION_LINE.captures(input) {
-            Ok(Line::Continuation(try!(ZoneInfo::from_captures(caps))))
+
+        match Zone::from_str(input) {
+            Ok(zone)                            => return Ok(Line::Zone(zone)),
+            Err(e @ Error::InvalidField(..))    => return Err(e),
+            Err(Error::Fail)                    => {},
         }
-        else if let Ok(rule) = Rule::from_str(input) {
-            Ok(Line::Rule(rule))
+
+        match Rule::from_str(input) {
+            Ok(rule)                            => return Ok(Line::Rule(rule)),
+            Err(e @ Error::InvalidField(..))    => return Err(e),
+            Err(Error::Fail)                    => {},
         }
-        else if let Ok(link) = Link::from_str(input) {
+
+        if let Ok(link) = Link::from_str(input) {
             Ok(Line::Link(link))
         }
+        else if let Some(caps) = CONTINUATION_LINE.captures(input) {
+
+            // A continuation line has no keyword, so one that doesn’t start
+            // with an offset isn’t recognised as one at all.
+            match ZoneInfo::from_captures(caps) {
+                Ok(info)                                => Ok(Line::Continuation(info)),
+                Err(Error::InvalidField("GMTOFF", _))   => Err(Error::Fail),
+                Err(e)                                  => Err(e),
+            }
+        }
         else {
             Err(Error::Fail)
         }
@@ -842,7 +1296,7 @@ impl<'line> Line<'line> {
 mod test {
     pub use std::str::FromStr;
     pub use super::*;
-    pub use datetime::{Weekday, Month};
+    pub use datetime::{Weekday, Month, LocalDate};
 
     macro_rules! test {
         ($name:ident: $input:expr => $result:expr) => {
@@ -868,6 +1322,7 @@ mod test {
             day:          DaySpec::Last(WeekdaySpec(Weekday::Sunday)),
             time:         TimeSpec::HoursMinutes(2, 0).with_type(TimeType::Wall),
             time_to_add:  TimeSpec::HoursMinutes(1, 0),
+            is_dst:       None,
             letters:      Some("D"),
         })));
 
@@ -879,6 +1334,7 @@ mod test {
             day:          DaySpec::Ordinal(10),
             time:         TimeSpec::HoursMinutes(2, 0).with_type(TimeType::Standard),
             time_to_add:  TimeSpec::Hours(0),
+            is_dst:       None,
             letters:      None,
         })));
 
@@ -890,11 +1346,71 @@ mod test {
             day:          DaySpec::FirstOnOrAfter(WeekdaySpec(Weekday::Sunday), 1),
             time:         TimeSpec::HoursMinutes(1, 0).with_type(TimeType::UTC),
             time_to_add:  TimeSpec::HoursMinutes(1, 0),
+            is_dst:       None,
+            letters:      Some("S"),
+        })));
+
+        test!(no_hyphen: "Rule	EU	1977	1980	HEY	Apr	Sun>=1	 1:00u	1:00	S"         => Err(Error::InvalidField("TYPE", 18)));
+        test!(bad_month: "Rule	EU	1977	1980	-	Febtober	Sun>=1	 1:00u	1:00	S" => Err(Error::InvalidField("IN", 20)));
+        test!(big_year:  "Rule	EU	99999999999999999999	only	-	Apr	1	1:00u	1:00	S" => Err(Error::InvalidField("FROM", 8)));
+        test!(big_day:   "Rule	EU	1977	1980	-	Apr	Sun>=1000	 1:00u	1:00	S"     => Err(Error::InvalidField("ON", 24)));
+
+        // An hour can only have two digits, so one that long never gets as
+        // far as being converted to a number.
+        test!(long_hour: "Rule	EU	1977	1980	-	Apr	Sun>=1	 1000:00u	1:00	S"     => Err(Error::InvalidField("AT", 32)));
+
+        test!(save_dst: "Rule	Test	1971	only	-	Oct	31	 2:00u	0d	-" => Ok(Line::Rule(Rule {
+            name:         "Test",
+            from_year:    YearSpec::Number(1971),
+            to_year:      None,
+            month:        MonthSpec(Month::October),
+            day:          DaySpec::Ordinal(31),
+            time:         TimeSpec::HoursMinutes(2, 0).with_type(TimeType::UTC),
+            time_to_add:  TimeSpec::Hours(0),
+            is_dst:       Some(true),
+            letters:      None,
+        })));
+
+        test!(save_standard: "Rule	Test	1971	only	-	Mar	lastSun	 2:00u	1:00s	-" => Ok(Line::Rule(Rule {
+            name:         "Test",
+            from_year:    YearSpec::Number(1971),
+            to_year:      None,
+            month:        MonthSpec(Month::March),
+            day:          DaySpec::Last(WeekdaySpec(Weekday::Sunday)),
+            time:         TimeSpec::HoursMinutes(2, 0).with_type(TimeType::UTC),
+            time_to_add:  TimeSpec::HoursMinutes(1, 0),
+            is_dst:       Some(false),
+            letters:      None,
+        })));
+
+        test!(save_other: "Rule	Test	1971	only	-	Oct	31	 2:00u	0u	-" => Err(Error::InvalidField("SAVE", 36)));
+
+        test!(slim: "R d 1916 o - Jun 14 23s 1 S" => Ok(Line::Rule(Rule {
+            name:         "d",
+            from_year:    YearSpec::Number(1916),
+            to_year:      None,
+            month:        MonthSpec(Month::June),
+            day:          DaySpec::Ordinal(14),
+            time:         TimeSpec::Hours(23).with_type(TimeType::Standard),
+            time_to_add:  TimeSpec::Hours(1),
+            is_dst:       None,
+            letters:      Some("S"),
+        })));
+
+        test!(slim_2: "R E 1981 ma - Mar lastSu 1u 1 S" => Ok(Line::Rule(Rule {
+            name:         "E",
+            from_year:    YearSpec::Number(1981),
+            to_year:      Some(YearSpec::Maximum),
+            month:        MonthSpec(Month::March),
+            day:          DaySpec::Last(WeekdaySpec(Weekday::Sunday)),
+            time:         TimeSpec::Hours(1).with_type(TimeType::UTC),
+            time_to_add:  TimeSpec::Hours(1),
+            is_dst:       None,
             letters:      Some("S"),
         })));
 
-        test!(no_hyphen: "Rule	EU	1977	1980	HEY	Apr	Sun>=1	 1:00u	1:00	S"         => Err(Error::Fail));
-        test!(bad_month: "Rule	EU	1977	1980	-	Febtober	Sun>=1	 1:00u	1:00	S" => Err(Error::Fail));
+        test!(ambiguous_month:   "R E 1981 ma - Ma lastSu 1u 1 S" => Err(Error::InvalidField("IN", 14)));
+        test!(ambiguous_weekday: "R E 1981 ma - Mar lastS 1u 1 S" => Err(Error::InvalidField("ON", 18)));
     }
 
     mod zones {
@@ -935,6 +1451,48 @@ mod test {
             },
         })));
 
+        test!(slim: "Z Africa/Abidjan -0:16:8 - LMT 1912" => Ok(Line::Zone(Zone {
+            name: "Africa/Abidjan",
+            info: ZoneInfo {
+                utc_offset:  TimeSpec::HoursMinutesSeconds(0, -16, -8),
+                saving:      Saving::NoSaving,
+                format:      "LMT",
+                time:        Some(ChangeTime::UntilYear(YearSpec::Number(1912))),
+            },
+        })));
+
+        test!(slim_continuation: "-4:42:45 - SMT 1910 Ja 10" => Ok(Line::Continuation(ZoneInfo {
+            utc_offset:  TimeSpec::HoursMinutesSeconds(-4, -42, -45),
+            saving:      Saving::NoSaving,
+            format:      "SMT",
+            time:        Some(ChangeTime::UntilDay(YearSpec::Number(1910), MonthSpec(Month::January), DaySpec::Ordinal(10))),
+        })));
+
+        test!(quoted: "Zone  Asia/Kolkata  5:30  -  <+0530>/<+0630>" => Ok(Line::Zone(Zone {
+            name: "Asia/Kolkata",
+            info: ZoneInfo {
+                utc_offset:  TimeSpec::HoursMinutes(5, 30),
+                saving:      Saving::NoSaving,
+                format:      "<+0530>/<+0630>",
+                time:        None,
+            },
+        })));
+
+        test!(quoted_empty:     "Zone  Test/Zone  5:30  -  <>"        => Err(Error::InvalidField("FORMAT", 26)));
+        test!(quoted_unclosed:  "Zone  Test/Zone  5:30  -  <+0530"    => Err(Error::InvalidField("FORMAT", 26)));
+        test!(quoted_invalid:   "Zone  Test/Zone  5:30  -  <+05,30>"  => Err(Error::InvalidField("FORMAT", 26)));
+
+        test!(bad_until_month:  "Zone  Test/Zone  5:30  -  IST  1945 Octember"  => Err(Error::InvalidField("MONTH", 36)));
+        test!(bad_continuation: "\t\t5:30  Nope!  IST"                           => Err(Error::InvalidField("RULES/SAVE", 8)));
+        test!(unknown_keyword:  "Zome  Test/Zone  5:30  -  IST"                 => Err(Error::Fail));
+
+        test!(slim_saving: "1 2 CEMT 1944" => Ok(Line::Continuation(ZoneInfo {
+            utc_offset:  TimeSpec::Hours(1),
+            saving:      Saving::OneOff(TimeSpec::Hours(2)),
+            format:      "CEMT",
+            time:        Some(ChangeTime::UntilYear(YearSpec::Number(1944))),
+        })));
+
         #[test]
         fn negative_offsets() {
             static LINE: &'static str = "Zone    Europe/London   -0:01:15 -  LMT 1847 Dec  1  0:00s";
@@ -962,14 +1520,129 @@ mod test {
         new:       "Asia/Istanbul",
     })));
 
+    test!(slim_link: "L Europe/Istanbul Asia/Istanbul" => Ok(Line::Link(Link {
+        existing:  "Europe/Istanbul",
+        new:       "Asia/Istanbul",
+    })));
+
+    #[test]
+    fn weekday_before_spills_into_previous_month() {
+        let day = DaySpec::LastOnOrBefore(WeekdaySpec(Weekday::Friday), 1);
+        assert_eq!(day.to_concrete_date(2017, Month::April), LocalDate::ymd(2017, Month::March, 31).unwrap());
+    }
+
+    #[test]
+    fn weekday_after_spills_into_next_month() {
+        let day = DaySpec::FirstOnOrAfter(WeekdaySpec(Weekday::Sunday), 29);
+        assert_eq!(day.to_concrete_date(2017, Month::February), LocalDate::ymd(2017, Month::March, 5).unwrap());
+    }
+
+    #[test]
+    fn weekday_on_or_before_includes_the_day() {
+        let day = DaySpec::LastOnOrBefore(WeekdaySpec(Weekday::Saturday), 30);
+        assert_eq!(day.to_concrete_date(2016, Month::April), LocalDate::ymd(2016, Month::April, 30).unwrap());
+    }
+
     #[test]
     fn month() {
         assert_eq!(MonthSpec::from_str("Aug"), Ok(MonthSpec(Month::August)));
         assert_eq!(MonthSpec::from_str("December"), Ok(MonthSpec(Month::December)));
     }
 
+    #[test]
+    fn change_times() {
+        use datetime::zone::TimeType;
+
+        assert_eq!("1971 Oct 31 2:00s".parse(), Ok(ChangeTime::UntilTime(YearSpec::Number(1971), MonthSpec(Month::October), DaySpec::Ordinal(31), TimeSpec::HoursMinutes(2, 0).with_type(TimeType::Standard))));
+        assert_eq!("1943  Oct  lastSun".parse(), Ok(ChangeTime::UntilDay(YearSpec::Number(1943), MonthSpec(Month::October), DaySpec::Last(WeekdaySpec(Weekday::Sunday)))));
+        assert_eq!("1919".parse(), Ok(ChangeTime::UntilYear(YearSpec::Number(1919))));
+        assert_eq!("".parse::<ChangeTime>(), Err(Error::Fail));
+        assert_eq!("1971 Oct 31 2:00 extra".parse::<ChangeTime>(), Err(Error::Fail));
+    }
+
+    #[test]
+    fn distinct_lines() {
+        use std::collections::HashSet;
+        use datetime::zone::TimeType;
+
+        // Lines that only differ in their spacing are the same line.
+        let lines: HashSet<Line> = [ "Rule  EU  1981  max  -  Mar  lastSun  1:00u  1:00  S",
+                                     "Rule EU 1981 max - Mar lastSun 1:00u 1:00 S",
+                                     "Rule EU 1981 max - Mar lastSun 1:00s 1:00 S" ].iter()
+                                   .map(|l| Line::from_str(l).unwrap())
+                                   .collect();
+        assert_eq!(lines.len(), 2);
+
+        assert!(MonthSpec(Month::January) < MonthSpec(Month::December));
+        assert!(WeekdaySpec(Weekday::Sunday) < WeekdaySpec(Weekday::Monday));
+        assert!(TimeSpec::Hours(2).with_type(TimeType::Wall) < TimeSpec::Hours(2).with_type(TimeType::UTC));
+    }
+
+    #[test]
+    fn savings() {
+        assert_eq!(Saving::from_str("-"), Ok(Saving::NoSaving));
+        assert_eq!(Saving::from_str("EU"), Ok(Saving::Multiple("EU")));
+        assert_eq!(Saving::from_str("1:00"), Ok(Saving::OneOff(TimeSpec::HoursMinutes(1, 0))));
+    }
+
+    mod constructors {
+        use super::*;
+        use datetime::zone::TimeType;
+
+        #[test]
+        fn days() {
+            assert_eq!(DaySpec::last(Weekday::Sunday), DaySpec::from_str("lastSun").unwrap());
+            assert_eq!(DaySpec::first_on_or_after(Weekday::Sunday, 8), Some(DaySpec::from_str("Sun>=8").unwrap()));
+            assert_eq!(DaySpec::last_on_or_before(Weekday::Friday, 32), None);
+            assert_eq!(DaySpec::ordinal(0), None);
+        }
+
+        #[test]
+        fn day_accessors() {
+            let day = DaySpec::from_str("Fri<=13").unwrap();
+            assert_eq!(day.weekday(), Some(Weekday::Friday));
+            assert_eq!(day.day_of_month(), Some(13));
+            assert_eq!(DaySpec::from_str("lastMon").unwrap().day_of_month(), None);
+            assert_eq!(DaySpec::from_str("5").unwrap().weekday(), None);
+        }
+
+        #[test]
+        fn times() {
+            assert_eq!(TimeSpec::new(2, 0, 0), Some(TimeSpec::Hours(2)));
+            assert_eq!(TimeSpec::new(-0, -30, 0), Some(TimeSpec::from_str("-0:30").unwrap()));
+            assert_eq!(TimeSpec::new(1, 2, 3).map(|t| t.components()), Some((1, 2, 3)));
+            assert_eq!(TimeSpec::new(1, -30, 0), None);
+            assert_eq!(TimeSpec::new(1, 60, 0), None);
+        }
+
+        #[test]
+        fn time_types() {
+            let time = TimeSpecAndType::from_str("2:00s").unwrap();
+            assert_eq!(time.time_spec(), TimeSpec::HoursMinutes(2, 0));
+            assert_eq!(time.time_type(), TimeType::Standard);
+        }
+
+        #[test]
+        fn months_and_years() {
+            assert_eq!(MonthSpec::new(Month::March).month(), Month::March);
+            assert_eq!(WeekdaySpec::new(Weekday::Monday).weekday(), Weekday::Monday);
+            assert_eq!(YearSpec::Number(1996).number(), Some(1996));
+            assert_eq!(YearSpec::Maximum.number(), None);
+        }
+    }
+
     test!(golb: "GOLB" => Err(Error::Fail));
 
+    #[test]
+    fn error_messages() {
+        let err = Line::from_str("Rule  EU  1977  1980  -  Apr  Sun>=1  1:00u  1:00x  S").unwrap_err();
+        assert_eq!(err.to_string(), "invalid SAVE field");
+        assert_eq!(err.offset(), Some(45));
+
+        assert_eq!(Error::Fail.to_string(), "parse error");
+        assert_eq!(Error::Fail.offset(), None);
+    }
+
     test!(comment: "# this is a comment" => Ok(Line::Space));
     test!(another_comment: "     # so is this" => Ok(Line::Space));
     test!(multiple_hash: "     # so is this ## " => Ok(Line::Space));
@@ -0,0 +1,296 @@
+//! Parsing whole zoneinfo files into a table in one go.
+//!
+//! Turning the text of a file into a `Table` means stripping the comment
+//! from each line, parsing what’s left with the `line` module, and adding
+//! the result to a `TableBuilder`, keeping track of which lines failed
+//! along the way. The `parse_table` function does all of that for one
+//! piece of text, and `parse_sources` does it for several, such as the
+//! files of a tz database release, as though they were read in order.
+//!
+//! Rather than stopping at the first bad line, every error gets collected,
+//! along with the line it was on, so they can all be fixed at once.
+//!
+//! Comments normally get thrown away, but `parse_sources_with` can keep
+//! them, attaching each run of comment lines to the line after it, for
+//! programs that show the data to people.
+//!
+//! ## Example
+//!
+//! ```
+//! use zoneinfo_parse::parse_table;
+//!
+//! let table = parse_table("Zone  Pacific/Auckland  11:39:04  -  LMT  1868  Nov  2  # Local mean time\n\
+//!                          Link  Pacific/Auckland  Antarctica/McMurdo\n").unwrap();
+//! assert!(table.get_zoneset("Antarctica/McMurdo").is_some());
+//!
+//! let errors = parse_table("Zone  Pacific/Auckland\n").unwrap_err();
+//! assert_eq!(errors[0].line, 1);
+//! ```
+
+use std::collections::HashMap;
+use std::error::Error as ErrorTrait;
+use std::fmt;
+
+use line::{Line, split_lines};
+use table::{Table, TableBuilder, MergeError};
+
+
+/// A line of zoneinfo source that either couldn’t be parsed, or couldn’t be
+/// added to the table.
+#[derive(PartialEq, Debug, Clone)]
+pub struct ParseError {
+
+    /// The name of the source the line was in, as given to `parse_sources`,
+    /// or `None` if it came from `parse_table`.
+    pub source: Option<String>,
+
+    /// The number of the line, counting from 1.
+    pub line: usize,
+
+    /// A human-readable description of what was wrong with it.
+    pub error: String,
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.source {
+            Some(ref source)  => write!(f, "{}:{}: {}", source, self.line, self.error),
+            None              => write!(f, "line {}: {}", self.line, self.error),
+        }
+    }
+}
+
+impl ErrorTrait for ParseError {
+    fn description(&self) -> &str {
+        &self.error
+    }
+}
+
+
+/// Settings for how zoneinfo text gets parsed.
+#[derive(PartialEq, Debug, Copy, Clone, Default)]
+pub struct ParseOptions {
+
+    /// Whether to keep the comments, attaching each one to the next rule,
+    /// zone, continuation, or link line, along with any comment at the end
+    /// of that line, so they end up in the table’s `comments`. Blank lines
+    /// don’t separate comments, so everything since the line before gets
+    /// attached.
+    pub comments: bool,
+}
+
+
+/// Parses the given zoneinfo text into a table, returning every line that
+/// couldn’t be used if there were any.
+pub fn parse_table(text: &str) -> Result<Table, Vec<ParseError>> {
+    parse(&[ (None, text) ], &ParseOptions::default())
+}
+
+/// Parses several pieces of zoneinfo text, each given with a name for it
+/// (such as its filename) to appear in errors, into one table. Zones can
+/// use rulesets from earlier sources, but a zone’s continuation lines have
+/// to be in the same source as the zone.
+pub fn parse_sources<'a, I>(sources: I) -> Result<Table, Vec<ParseError>>
+where I: IntoIterator<Item=(&'a str, &'a str)> {
+    parse_sources_with(sources, &ParseOptions::default())
+}
+
+/// Parses several pieces of zoneinfo text into one table, in the same way
+/// as `parse_sources`, but with the given options.
+pub fn parse_sources_with<'a, I>(sources: I, options: &ParseOptions) -> Result<Table, Vec<ParseError>>
+where I: IntoIterator<Item=(&'a str, &'a str)> {
+    let sources: Vec<(Option<&str>, &str)> = sources.into_iter().map(|(name, text)| (Some(name), text)).collect();
+    parse(&sources, options)
+}
+
+fn parse(sources: &[(Option<&str>, &str)], options: &ParseOptions) -> Result<Table, Vec<ParseError>> {
+    let mut builder = TableBuilder::new();
+    let mut errors = Vec::new();
+
+    for &(source, text) in sources {
+        let error = |line: usize, error: String| ParseError {
+            source: source.map(|s| s.to_owned()),
+            line:   line,
+            error:  error,
+        };
+
+        // Each source gets built up on its own and then merged in, so a
+        // zone at the end of one can’t be continued by the next. Merging
+        // only knows the names of the zones and links it had problems
+        // with, so remember which lines those were on.
+        let mut part = TableBuilder::partial();
+        let mut source_errors = Vec::new();
+        let mut zone_lines = HashMap::new();
+        let mut link_lines = HashMap::new();
+
+        for (index, line) in split_lines(text).enumerate() {
+            let line_number = index + 1;
+
+            let line = match line.find('#') {
+                Some(pos) => {
+                    if options.comments {
+                        part.add_comment(comment_text(&line[pos + 1 ..]));
+                    }
+
+                    &line[.. pos]
+                },
+                None => line,
+            };
+
+            let result = match Line::from_str(line) {
+                Err(e)                        => Err(e.to_string()),
+                Ok(Line::Space)               => Ok(()),
+                Ok(Line::Zone(zone))          => {
+                    let _ = zone_lines.entry(zone.name.to_owned()).or_insert(line_number);
+                    part.add_zone_line(zone).map_err(|e| e.to_string())
+                },
+                Ok(Line::Continuation(cont))  => part.add_continuation_line(cont).map_err(|e| e.to_string()),
+                Ok(Line::Rule(rule))          => part.add_rule_line(rule).map_err(|e| e.to_string()),
+                Ok(Line::Link(link))          => {
+                    let _ = link_lines.entry(link.new.to_owned()).or_insert(line_number);
+                    part.add_link_line(link).map_err(|e| e.to_string())
+                },
+            };
+
+            if let Err(e) = result {
+                source_errors.push(error(line_number, e));
+            }
+        }
+
+        for e in builder.merge(part) {
+            let line = match e {
+                MergeError::UnknownRuleset { .. } | MergeError::DuplicateZone(_) => zone_lines[e.name()],
+                MergeError::DuplicateLink(_) | MergeError::CircularLink(_)      => link_lines[e.name()],
+            };
+
+            source_errors.push(error(line, e.to_string()));
+        }
+
+        source_errors.sort_by_key(|e| e.line);
+        errors.extend(source_errors);
+    }
+
+    if errors.is_empty() {
+        Ok(builder.build())
+    }
+    else {
+        Err(errors)
+    }
+}
+
+/// Returns the text of a comment, without the space that usually comes
+/// after the `#`, or any trailing whitespace.
+fn comment_text(comment: &str) -> &str {
+    let comment = comment.trim_end();
+    if comment.starts_with(' ') { &comment[1 ..] } else { comment }
+}
+
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn table() {
+        let table = parse_table("# A comment\n\
+                                 Rule  Test  2000  only  -  Mar  lastSun  1:00u  1:00  S  # Spring\n\
+                                 \n\
+                                 Zone  Test/Zone  0:00  Test  TE%sT  2001\n\
+                                 \x20                1:00  -     TST\n\
+                                 Link  Test/Zone  Test/Link\n").unwrap();
+
+        assert_eq!(table.rulesets["Test"].len(), 1);
+        assert_eq!(table.zonesets["Test/Zone"].len(), 2);
+        assert_eq!(table.links["Test/Link"], "Test/Zone");
+    }
+
+    #[test]
+    fn errors() {
+        let errors = parse_table("Zone  Test/Zone  0:00  Missing  T%sT\n\
+                                  Nonsense\n\
+                                  Link  Test/Zone  Test/Zone\n").unwrap_err();
+
+        let lines: Vec<usize> = errors.iter().map(|e| e.line).collect();
+        assert_eq!(lines, vec![ 1, 2, 3 ]);
+        assert_eq!(errors[1].to_string(), "line 2: parse error");
+    }
+
+    #[test]
+    fn windows_text() {
+        let unix = "# Made\u{a0}up\n\
+                    Zone  Test/Zone  0:00  -  TST  2001\n\
+                    \x20                1:00  -  TST\n\
+                    Link  Test/Zone  Test/Link\n";
+        let windows = "\u{feff}# Made\u{a0}up\r\n\
+                       Zone\u{a0}Test/Zone  0:00  -  TST  2001 \r\r\n\
+                       \u{a0}               1:00  -  TST\r\n\
+                       Link  Test/Zone  Test/Link\r\n";
+
+        let options = ParseOptions { comments: true };
+        let expected = parse_sources_with(vec![ ("test", unix) ], &options).unwrap();
+        assert_eq!(parse_sources_with(vec![ ("test", windows) ], &options).unwrap(), expected);
+    }
+
+    #[test]
+    fn sources() {
+        let table = parse_sources(vec![
+            ("rules", "Rule  Test  2000  only  -  Mar  lastSun  1:00u  1:00  S\n"),
+            ("zones", "Zone  Test/Zone  0:00  Test  TE%sT\n"),
+        ]).unwrap();
+        assert!(table.get_zoneset("Test/Zone").is_some());
+
+        let errors = parse_sources(vec![
+            ("first",  "Zone  Test/Zone  0:00  -  TST\n"),
+            ("second", "\n\nZone  Test/Zone  1:00  -  TST\n"),
+        ]).unwrap_err();
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].source, Some("second".to_owned()));
+        assert_eq!(errors[0].line, 3);
+    }
+
+    #[test]
+    fn comments() {
+        let text = "# Rules for testing\n\
+                    Rule  Test  2000  only  -  Mar  lastSun  1:00u  1:00  S\n\
+                    Rule  Test  2000  only  -  Oct  lastSun  1:00u  0     -  # Autumn\n\
+                    \n\
+                    # Test Zone\n\
+                    #\n\
+                    #   It’s made up.\n\
+                    Zone  Test/Zone  0:00  Test  TE%sT  2001\n\
+                    # Changed its mind\n\
+                    \x20                1:00  -     TST\n\
+                    Link  Test/Zone  Test/Link  # Alias\n";
+
+        let table = parse_sources_with(vec![ ("test", text) ], &ParseOptions { comments: true }).unwrap();
+        assert_eq!(table.comments.rule("Test", 0), Some("Rules for testing"));
+        assert_eq!(table.comments.rule("Test", 1), Some("Autumn"));
+        assert_eq!(table.comments.zone("Test/Zone", 0), Some("Test Zone\n\n  It’s made up."));
+        assert_eq!(table.comments.zone("Test/Zone", 1), Some("Changed its mind"));
+        assert_eq!(table.comments.link("Test/Link"), Some("Alias"));
+
+        let table = parse_table(text).unwrap();
+        assert!(table.comments.is_empty());
+    }
+
+    #[test]
+    fn comments_across_sources() {
+        let table = parse_sources_with(vec![
+            ("first",  "# First\nRule  Test  2000  only  -  Mar  lastSun  1:00u  1:00  S\n"),
+            ("second", "# Second\nRule  Test  2000  only  -  Oct  lastSun  1:00u  0     -\n"),
+        ], &ParseOptions { comments: true }).unwrap();
+
+        assert_eq!(table.comments.rule("Test", 0), Some("First"));
+        assert_eq!(table.comments.rule("Test", 1), Some("Second"));
+    }
+
+    #[test]
+    fn continuation_in_next_source() {
+        let errors = parse_sources(vec![
+            ("first",  "Zone  Test/Zone  0:00  -  TST  2000\n"),
+            ("second", "                 1:00  -  TST\n"),
+        ]).unwrap_err();
+        assert_eq!(errors[0].source, Some("second".to_owned()));
+        assert_eq!(errors[0].line, 1);
+    }
+}
@@ -0,0 +1,461 @@
+//! Writing a table back out as zoneinfo source text.
+//!
+//! A table can be read in, changed, and then written back out, so programs
+//! can patch the data rather than editing the text by hand. Each zone gets
+//! written with the rulesets it uses in front of it, unless an earlier zone
+//! used them already, and the links to it after it, so everything about a
+//! zone is in one place:
+//!
+//! ```text
+//! Rule	EU	1981	max	-	Mar	lastSun	1:00u	1:00	S
+//! Rule	EU	1996	max	-	Oct	lastSun	1:00u	0	-
+//! Zone	Europe/Paris	0:09:21	-	LMT	1891 Mar 16
+//! 			1:00	EU	CE%sT
+//! Link	Europe/Paris	Europe/Monaco
+//! ```
+//!
+//! Any rulesets that no zone uses come after all the zones, and any links
+//! to zones that don’t exist come last of all. If the table has comments,
+//! each one gets written on the lines before the line it was attached to.
+//!
+//! Reading the text back in gives the same table, but it won’t be the same
+//! as the text the table was originally read from: the columns are
+//! separated by tabs, times are given in hours and minutes, and the links
+//! point straight to the zone at the end of any chain.
+//!
+//! The table can also be written in the slim form of the `tzdata.zi` file
+//! that comes with the tz database, with `write_slim_source`. That has the
+//! lines in the same order, but without comments or indentation, with the
+//! columns separated by single spaces, and with everything spelled as
+//! briefly as `zic` allows, such as `R` for `Rule`, `o` for `only`, `Ja`
+//! for `Jan`, and `2` for `2:00`:
+//!
+//! ```text
+//! R EU 1981 ma - Mar lastSu 1u 1 S
+//! R EU 1996 ma - O lastSu 1u 0 -
+//! Z Europe/Paris 0:09:21 - LMT 1891 Mar 16
+//! 1 EU CE%sT
+//! L Europe/Paris Europe/Monaco
+//! ```
+//!
+//! ## Example
+//!
+//! ```
+//! use zoneinfo_parse::parse_table;
+//!
+//! let table = parse_table("Zone  Pacific/Auckland  11:39:04  -  LMT  1868  Nov  2\n").unwrap();
+//!
+//! let mut source = Vec::new();
+//! table.write_source(&mut source).unwrap();
+//! assert_eq!(String::from_utf8(source).unwrap(), "Zone\tPacific/Auckland\t11:39:04\t-\tLMT\t1868 Nov 2\n");
+//! ```
+
+use std::collections::HashSet;
+use std::io::Write;
+use std::io::Result as IOResult;
+
+use datetime::{Month, Weekday};
+use datetime::zone::TimeType;
+
+use line::{ChangeTime, DaySpec, MonthSpec, YearSpec};
+use table::{Table, RuleInfo, ZoneInfo, Saving, Format};
+
+
+/// The two ways the text can be spelled.
+#[derive(PartialEq, Debug, Copy, Clone)]
+enum Form {
+
+    /// Everything spelled out in full, with comments.
+    Full,
+
+    /// Everything as short as it can be, without comments.
+    Slim,
+}
+
+impl Form {
+
+    /// Returns what goes between the columns of a line.
+    fn separator(self) -> &'static str {
+        match self {
+            Form::Full  => "\t",
+            Form::Slim  => " ",
+        }
+    }
+}
+
+
+impl Table {
+
+    /// Writes the whole table as zoneinfo source text, grouped by zone, in
+    /// order of zone name.
+    pub fn write_source<W: Write>(&self, w: &mut W) -> IOResult<()> {
+        self.write_form(w, Form::Full)
+    }
+
+    /// Writes the whole table as zoneinfo source text in the same order as
+    /// `write_source`, but in the slim form of a `tzdata.zi` file.
+    pub fn write_slim_source<W: Write>(&self, w: &mut W) -> IOResult<()> {
+        self.write_form(w, Form::Slim)
+    }
+
+    fn write_form<W: Write>(&self, w: &mut W, form: Form) -> IOResult<()> {
+        let mut zone_names: Vec<&String> = self.zonesets.keys().collect();
+        zone_names.sort();
+
+        let mut link_names: Vec<&String> = self.links.keys().collect();
+        link_names.sort();
+
+        let mut written_rulesets = HashSet::new();
+
+        for name in &zone_names {
+            let zoneset = &self.zonesets[*name];
+
+            for info in zoneset {
+                if let Saving::Multiple(ref ruleset_name) = info.saving {
+                    if written_rulesets.insert(ruleset_name.clone()) {
+                        try!(self.write_ruleset(w, ruleset_name, form));
+                    }
+                }
+            }
+
+            for (index, info) in zoneset.iter().enumerate() {
+                if form == Form::Full {
+                    try!(write_comment(w, self.comments.zone(name, index)));
+                }
+
+                match (index, form) {
+                    (0, Form::Full)  => try!(write!(w, "Zone\t{}\t", name)),
+                    (0, Form::Slim)  => try!(write!(w, "Z {} ", name)),
+                    (_, Form::Full)  => try!(write!(w, "\t\t\t")),
+                    (_, Form::Slim)  => {},
+                }
+                try!(write_zone_info(w, info, form));
+            }
+
+            for link_name in link_names.iter().filter(|l| &self.links[**l] == *name) {
+                try!(self.write_link(w, link_name, form));
+            }
+        }
+
+        let mut ruleset_names: Vec<&String> = self.rulesets.keys().filter(|r| !written_rulesets.contains(*r)).collect();
+        ruleset_names.sort();
+        for ruleset_name in ruleset_names {
+            try!(self.write_ruleset(w, ruleset_name, form));
+        }
+
+        for link_name in link_names.iter().filter(|l| !self.zonesets.contains_key(&self.links[**l])) {
+            try!(self.write_link(w, link_name, form));
+        }
+
+        Ok(())
+    }
+
+    /// Writes every rule in the ruleset with the given name.
+    fn write_ruleset<W: Write>(&self, w: &mut W, ruleset_name: &str, form: Form) -> IOResult<()> {
+        for (index, rule) in self.rulesets[ruleset_name].iter().enumerate() {
+            if form == Form::Full {
+                try!(write_comment(w, self.comments.rule(ruleset_name, index)));
+            }
+            try!(write_rule(w, ruleset_name, rule, form));
+        }
+
+        Ok(())
+    }
+
+    /// Writes the link with the given name.
+    fn write_link<W: Write>(&self, w: &mut W, link_name: &str, form: Form) -> IOResult<()> {
+        match form {
+            Form::Full  => {
+                try!(write_comment(w, self.comments.link(link_name)));
+                writeln!(w, "Link\t{}\t{}", self.links[link_name], link_name)
+            },
+            Form::Slim  => writeln!(w, "L {} {}", self.links[link_name], link_name),
+        }
+    }
+}
+
+
+/// Writes a comment, if there is one, with each of its lines as a comment
+/// line.
+fn write_comment<W: Write>(w: &mut W, comment: Option<&str>) -> IOResult<()> {
+    if let Some(comment) = comment {
+        for line in comment.split('\n') {
+            if line.is_empty() {
+                try!(writeln!(w, "#"));
+            }
+            else {
+                try!(writeln!(w, "# {}", line));
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Writes one rule line.
+fn write_rule<W: Write>(w: &mut W, ruleset_name: &str, rule: &RuleInfo, form: Form) -> IOResult<()> {
+    let to_year = match (rule.to_year, form) {
+        (Some(year), _)     => describe_year(year, form),
+        (None, Form::Full)  => "only".to_owned(),
+        (None, Form::Slim)  => "o".to_owned(),
+    };
+
+    let save = match rule.time_to_add {
+        0 => "0".to_owned(),
+        s => describe_time(s, form),
+    };
+
+    let save_suffix = match rule.is_dst {
+        Some(true)   => "d",
+        Some(false)  => "s",
+        None         => "",
+    };
+
+    let letters = match rule.letters {
+        Some(ref letters) if !letters.is_empty() => letters.to_string(),
+        _                                       => "-".to_owned(),
+    };
+
+    let keyword = match form {
+        Form::Full  => "Rule",
+        Form::Slim  => "R",
+    };
+
+    let fields = [
+        keyword, ruleset_name, &describe_year(rule.from_year, form), &to_year, "-", describe_month(rule.month, form),
+        &describe_day(rule.day, form), &format!("{}{}", describe_time(rule.time, form), time_type_suffix(rule.time_type)),
+        &format!("{}{}", save, save_suffix), &letters,
+    ];
+
+    writeln!(w, "{}", fields.join(form.separator()))
+}
+
+/// Writes the columns of a zone or continuation line after its name.
+fn write_zone_info<W: Write>(w: &mut W, info: &ZoneInfo, form: Form) -> IOResult<()> {
+    let saving = match info.saving {
+        Saving::NoSaving          => "-".to_owned(),
+        Saving::OneOff(amount)    => describe_time(amount, form),
+        Saving::Multiple(ref s)   => s.clone(),
+    };
+
+    let format = match info.format {
+        Format::Constant(ref s) | Format::Placeholder(ref s)  => s.to_string(),
+        Format::Alternate { ref standard, ref dst }           => format!("{}/{}", standard, dst),
+    };
+
+    let sep = form.separator();
+    try!(write!(w, "{}{}{}{}{}", describe_time(info.offset, form), sep, saving, sep, format));
+
+    if let Some(until) = info.end_time {
+        try!(write!(w, "{}{}", sep, describe_until(until, form)));
+    }
+
+    writeln!(w, "")
+}
+
+/// Formats an “until” time with as many fields as it was given with.
+fn describe_until(until: ChangeTime, form: Form) -> String {
+    match until {
+        ChangeTime::UntilYear(y)              => describe_year(y, form),
+        ChangeTime::UntilMonth(y, m)          => format!("{} {}", describe_year(y, form), describe_month(m, form)),
+        ChangeTime::UntilDay(y, m, d)         => format!("{} {} {}", describe_year(y, form), describe_month(m, form), describe_day(d, form)),
+        ChangeTime::UntilTime(y, m, d, time)  => format!("{} {} {} {}{}", describe_year(y, form), describe_month(m, form), describe_day(d, form),
+                                                         describe_time(time.0.as_seconds(), form), time_type_suffix(time.1)),
+    }
+}
+
+fn describe_year(year: YearSpec, form: Form) -> String {
+    match (year, form) {
+        (YearSpec::Minimum, Form::Full)  => "min".to_owned(),
+        (YearSpec::Minimum, Form::Slim)  => "mi".to_owned(),
+        (YearSpec::Maximum, Form::Full)  => "max".to_owned(),
+        (YearSpec::Maximum, Form::Slim)  => "ma".to_owned(),
+        (YearSpec::Number(n), _)         => n.to_string(),
+    }
+}
+
+/// Returns the name of a month: its first three letters, or in the slim
+/// form, as few letters as tell it apart from the other months.
+fn describe_month(month: MonthSpec, form: Form) -> &'static str {
+    let name = match month.0 {
+        Month::January   => "Jan",  Month::February  => "Feb",  Month::March     => "Mar",
+        Month::April     => "Apr",  Month::May       => "May",  Month::June      => "Jun",
+        Month::July      => "Jul",  Month::August    => "Aug",  Month::September => "Sep",
+        Month::October   => "Oct",  Month::November  => "Nov",  Month::December  => "Dec",
+    };
+
+    if form == Form::Full {
+        return name;
+    }
+
+    match month.0 {
+        Month::January   => "Ja",   Month::February  => "F",    Month::April     => "Ap",
+        Month::August    => "Au",   Month::September => "S",    Month::October   => "O",
+        Month::November  => "N",    Month::December  => "D",    _                => name,
+    }
+}
+
+fn describe_day(day: DaySpec, form: Form) -> String {
+    match day {
+        DaySpec::Ordinal(day)             => day.to_string(),
+        DaySpec::Last(w)                  => format!("last{}", describe_weekday(w.0, form)),
+        DaySpec::LastOnOrBefore(w, day)   => format!("{}<={}", describe_weekday(w.0, form), day),
+        DaySpec::FirstOnOrAfter(w, day)   => format!("{}>={}", describe_weekday(w.0, form), day),
+    }
+}
+
+fn describe_weekday(weekday: Weekday, form: Form) -> &'static str {
+    match (weekday, form) {
+        (Weekday::Sunday,    Form::Full)  => "Sun",
+        (Weekday::Monday,    Form::Full)  => "Mon",
+        (Weekday::Tuesday,   Form::Full)  => "Tue",
+        (Weekday::Wednesday, Form::Full)  => "Wed",
+        (Weekday::Thursday,  Form::Full)  => "Thu",
+        (Weekday::Friday,    Form::Full)  => "Fri",
+        (Weekday::Saturday,  Form::Full)  => "Sat",
+        (Weekday::Sunday,    Form::Slim)  => "Su",
+        (Weekday::Monday,    Form::Slim)  => "M",
+        (Weekday::Tuesday,   Form::Slim)  => "Tu",
+        (Weekday::Wednesday, Form::Slim)  => "W",
+        (Weekday::Thursday,  Form::Slim)  => "Th",
+        (Weekday::Friday,    Form::Slim)  => "F",
+        (Weekday::Saturday,  Form::Slim)  => "Sa",
+    }
+}
+
+/// Returns the letter that goes after a time to say what kind it is: none
+/// for wall-clock time, `s` for standard time, and `u` for UTC.
+fn time_type_suffix(time_type: TimeType) -> &'static str {
+    match time_type {
+        TimeType::Wall      => "",
+        TimeType::Standard  => "s",
+        TimeType::UTC       => "u",
+    }
+}
+
+/// Formats a number of seconds as hours, minutes, and seconds, leaving off
+/// the seconds when there are none, such as “5:30” or “-0:01:15”. In the
+/// slim form, whole hours are written without any minutes, such as “2”.
+fn describe_time(seconds: i64, form: Form) -> String {
+    let sign = if seconds < 0 { "-" } else { "" };
+    let seconds = seconds.abs();
+
+    match (seconds % 3600, seconds % 60, form) {
+        (0, _, Form::Slim)  => format!("{}{}", sign, seconds / 3600),
+        (_, 0, _)           => format!("{}{}:{:02}", sign, seconds / 3600, seconds % 3600 / 60),
+        (_, s, _)           => format!("{}{}:{:02}:{:02}", sign, seconds / 3600, seconds % 3600 / 60, s),
+    }
+}
+
+
+#[cfg(test)]
+mod test {
+    use parse::{parse_table, parse_sources_with, ParseOptions};
+
+    const SOURCE: &'static str = "\
+        Rule  EU      1981  max   -  Mar  lastSun  1:00u  1:00  S\n\
+        Rule  EU      1996  max   -  Oct  lastSun  1:00u  0     -\n\
+        Rule  France  1916  only  -  Jun  14       23:00s 1:00  S\n\
+        Rule  France  1916  1919  -  Oct  Sun>=1   23:00s 0     -\n\
+        Rule  France  1945  only  -  Sep  16       3:00   0s    -\n\
+        Rule  Unused  1970  only  -  Jan  Sat<=7   -1:30  0:30  X\n\
+        Zone  Europe/Paris  0:09:21  -       LMT    1891 Mar 16\n\
+        \x20                0:09:21  -       PMT    1911 Mar 11\n\
+        \x20                0:00     France  WE%sT  1940 Jun 14 23:00\n\
+        \x20                1:00     2:00    CEMT   1944\n\
+        \x20                1:00     France  CE%sT  1977\n\
+        \x20                1:00     EU      CE%sT\n\
+        Zone  America/Los_Angeles  -7:52:58  -  LMT  1883 Nov 18 20:00u\n\
+        \x20                       -8:00     -  PST/PDT\n\
+        Link  Europe/Paris  Europe/Monaco\n\
+        Link  Nowhere/Zone  Nowhere/Link\n";
+
+    #[test]
+    fn round_trip() {
+        let table = parse_table(SOURCE).unwrap();
+
+        let mut buf = Vec::new();
+        table.write_source(&mut buf).unwrap();
+        let source = String::from_utf8(buf).unwrap();
+
+        assert_eq!(parse_table(&source).unwrap(), table);
+    }
+
+    #[test]
+    fn grouped_by_zone() {
+        let table = parse_table(SOURCE).unwrap();
+
+        let mut buf = Vec::new();
+        table.write_source(&mut buf).unwrap();
+        let source = String::from_utf8(buf).unwrap();
+
+        let kinds: Vec<String> = source.lines().map(|line| {
+            let fields: Vec<&str> = line.split('\t').collect();
+            if fields[0].is_empty() { "\t".to_owned() } else { format!("{} {}", fields[0], fields[1]) }
+        }).collect();
+
+        assert_eq!(kinds, vec![
+            "Zone America/Los_Angeles", "\t",
+            "Rule France", "Rule France", "Rule France", "Rule EU", "Rule EU",
+            "Zone Europe/Paris", "\t", "\t", "\t", "\t", "\t",
+            "Link Europe/Paris",
+            "Rule Unused",
+            "Link Nowhere/Zone",
+        ]);
+
+        assert!(source.contains("Rule\tFrance\t1916\t1919\t-\tOct\tSun>=1\t23:00s\t0\t-\n"));
+        assert!(source.contains("Rule\tFrance\t1945\tonly\t-\tSep\t16\t3:00\t0s\t-\n"));
+        assert!(source.contains("Rule\tUnused\t1970\tonly\t-\tJan\tSat<=7\t-1:30\t0:30\tX\n"));
+        assert!(source.contains("\t\t\t0:00\tFrance\tWE%sT\t1940 Jun 14 23:00\n"));
+        assert!(source.contains("Zone\tAmerica/Los_Angeles\t-7:52:58\t-\tLMT\t1883 Nov 18 20:00u\n"));
+    }
+
+    #[test]
+    fn comments() {
+        let options = ParseOptions { comments: true };
+        let table = parse_sources_with(vec![ ("test", "# Made up\n#\n# Really\nZone  Test/Zone  1:00  -  TST  # Test\n") ], &options).unwrap();
+
+        let mut buf = Vec::new();
+        table.write_source(&mut buf).unwrap();
+        let source = String::from_utf8(buf).unwrap();
+
+        assert_eq!(source, "# Made up\n#\n# Really\n# Test\nZone\tTest/Zone\t1:00\t-\tTST\n");
+        assert_eq!(parse_sources_with(vec![ ("test", &*source) ], &options).unwrap(), table);
+    }
+
+    #[test]
+    fn slim_round_trip() {
+        let table = parse_table(SOURCE).unwrap();
+
+        let mut buf = Vec::new();
+        table.write_slim_source(&mut buf).unwrap();
+        let slim = parse_table(&String::from_utf8(buf).unwrap()).unwrap();
+
+        // The until times keep how they were written, so compare the tables
+        // by what they write out in full instead.
+        let (mut expected, mut actual) = (Vec::new(), Vec::new());
+        table.write_source(&mut expected).unwrap();
+        slim.write_source(&mut actual).unwrap();
+        assert_eq!(String::from_utf8(actual).unwrap(), String::from_utf8(expected).unwrap());
+    }
+
+    #[test]
+    fn slim() {
+        let options = ParseOptions { comments: true };
+        let table = parse_sources_with(vec![ ("test", &*format!("# Made up\n{}", SOURCE)) ], &options).unwrap();
+
+        let mut buf = Vec::new();
+        table.write_slim_source(&mut buf).unwrap();
+        let source = String::from_utf8(buf).unwrap();
+
+        assert!(!source.contains('#'));
+        assert!(!source.contains('\t'));
+        assert!(source.contains("R France 1916 1919 - O Su>=1 23s 0 -\n"));
+        assert!(source.contains("R France 1945 o - S 16 3 0s -\n"));
+        assert!(source.contains("R Unused 1970 o - Ja Sa<=7 -1:30 0:30 X\n"));
+        assert!(source.contains("R EU 1981 ma - Mar lastSu 1u 1 S\n"));
+        assert!(source.contains("\n0 France WE%sT 1940 Jun 14 23\n"));
+        assert!(source.contains("Z America/Los_Angeles -7:52:58 - LMT 1883 N 18 20u\n"));
+        assert!(source.contains("L Europe/Paris Europe/Monaco\n"));
+    }
+}
@@ -44,9 +44,27 @@ pub trait Structure {
 
 impl Structure for Table {
     fn structure(&self) -> TableStructure {
+        let names = self.zonesets.keys().chain(self.links.keys());
+        TableStructure::from_names(names.map(|name| &**name))
+    }
+}
+
+
+/// The structure of a set of time zone names.
+#[derive(PartialEq, Debug)]
+pub struct TableStructure<'table> {
+    mappings: BTreeMap<&'table str, BTreeSet<Child<'table>>>,
+}
+
+impl<'table> TableStructure<'table> {
+
+    /// Determines the structure of an arbitrary set of time zone names,
+    /// for when they aren’t coming straight from a `Table`.
+    pub fn from_names<I>(names: I) -> TableStructure<'table>
+    where I: IntoIterator<Item=&'table str> {
         let mut mappings = BTreeMap::new();
 
-        for key in self.zonesets.keys().chain(self.links.keys()) {
+        for key in names {
 
             // Extract the name from the *last* slash. So
             // `America/Kentucky/Louisville` is split into
@@ -78,13 +96,6 @@ impl Structure for Table {
     }
 }
 
-
-/// The structure of a set of time zone names.
-#[derive(PartialEq, Debug)]
-pub struct TableStructure<'table> {
-    mappings: BTreeMap<&'table str, BTreeSet<Child<'table>>>,
-}
-
 impl<'table> IntoIterator for TableStructure<'table> {
     type Item = TableStructureEntry<'table>;
     type IntoIter = Iter<'table>;
@@ -0,0 +1,385 @@
+//! Parsing the tables that say where each zone is used.
+//!
+//! Alongside the zoneinfo files, the tz database comes with a few
+//! tab-separated tables of metadata. The main one is `zone1970.tab`, which
+//! has one row for each zone that has had distinct clocks since 1970, with:
+//!
+//! - a comma-separated list of the ISO 3166 codes of the countries that use
+//!   the zone, most populous first;
+//! - the coordinates of the zone’s principal city, in ISO 6709 format, such
+//!   as `+5130-00007` for London;
+//! - the zone’s name;
+//! - optionally, a comment distinguishing the zone from others in the same
+//!   country.
+//!
+//! The older `zone.tab` has the same columns, but only ever one country code
+//! per row, so it can be read by the same parser.
+//!
+//! ## Example
+//!
+//! ```
+//! use zoneinfo_parse::tab::parse_zone_tab;
+//!
+//! let rows = parse_zone_tab("# comment\nAE,OM\t+2518+05518\tAsia/Dubai\n").unwrap();
+//! assert_eq!(rows[0].countries, vec![ "AE", "OM" ]);
+//! assert_eq!(rows[0].zone, "Asia/Dubai");
+//! assert_eq!(rows[0].latitude, 25.3);
+//! ```
+//!
+//! ## Nearest zones
+//!
+//! The rows can also be wrapped in a `Metadata` value, whose `nearest_zone`
+//! method picks the zone whose principal city is closest to a point, by
+//! great-circle distance. This is only a guess — the closest city can be
+//! across a border — but it’s a plausible default for a device that knows
+//! where it is but hasn’t been told its time zone.
+//!
+//! ## Countries
+//!
+//! `Metadata` can also answer which zones a country uses, with
+//! `zones_for_country`, and which country a zone is used in, with
+//! `country_for_zone`. Some of the zones in `zone.tab` are links in newer
+//! releases of the database, such as `Europe/Oslo`, so giving the metadata
+//! a table with `with_links` makes these methods work with canonical zone
+//! names instead.
+
+use std::collections::HashMap;
+use std::error::Error as ErrorTrait;
+use std::fmt;
+
+use line::split_lines;
+use table::Table;
+
+
+/// One row of a `zone1970.tab` or `zone.tab` file.
+#[derive(PartialEq, Debug, Clone)]
+pub struct ZoneLocation {
+
+    /// The ISO 3166 codes of the countries that use this zone.
+    pub countries: Vec<String>,
+
+    /// The latitude of the zone’s principal city, in degrees north.
+    pub latitude: f64,
+
+    /// The longitude of the zone’s principal city, in degrees east.
+    pub longitude: f64,
+
+    /// The name of the zone.
+    pub zone: String,
+
+    /// The comment describing which part of the countries use this zone,
+    /// if there is one.
+    pub comment: Option<String>,
+}
+
+
+/// The metadata about where zones are used, from a `zone1970.tab` or
+/// `zone.tab` file.
+#[derive(PartialEq, Debug, Clone, Default)]
+pub struct Metadata {
+
+    /// The rows of the file, in the order they appeared.
+    pub locations: Vec<ZoneLocation>,
+
+    /// Mapping of link names to the names of the zones they link to, for
+    /// resolving the zone names in the rows and in queries. This is empty
+    /// unless a table’s links have been added with `with_links`.
+    pub links: HashMap<String, String>,
+}
+
+impl Metadata {
+
+    /// Parses the contents of a `zone1970.tab` or `zone.tab` file into
+    /// metadata.
+    pub fn from_zone_tab(input: &str) -> Result<Metadata, Error> {
+        let locations = try!(parse_zone_tab(input));
+        Ok(Metadata { locations: locations, links: HashMap::new() })
+    }
+
+    /// Returns this metadata with the links from the given table, so names
+    /// that are links in the table get resolved to the zones they link to.
+    pub fn with_links(mut self, table: &Table) -> Metadata {
+        self.links = table.links.clone();
+        self
+    }
+
+    /// Returns the name of the zone that the given name links to, or the
+    /// name itself if it isn’t a link.
+    pub fn canonical_name<'a>(&'a self, name: &'a str) -> &'a str {
+        self.links.get(name).map(|n| &**n).unwrap_or(name)
+    }
+
+    /// Returns the canonical names of the zones used in the country with
+    /// the given ISO 3166 alpha-2 code, in the order they appear in the
+    /// file, without any duplicates. The code is matched without regard
+    /// to case.
+    pub fn zones_for_country(&self, alpha2: &str) -> Vec<&str> {
+        let mut zones = Vec::new();
+
+        for location in &self.locations {
+            if location.countries.iter().any(|c| c.eq_ignore_ascii_case(alpha2)) {
+                let zone = self.canonical_name(&location.zone);
+                if !zones.contains(&zone) {
+                    zones.push(zone);
+                }
+            }
+        }
+
+        zones
+    }
+
+    /// Returns the code of the country that uses the zone with the given
+    /// name, or `None` if there’s no row for it. If the zone is used in more
+    /// than one country, this is the most populous.
+    ///
+    /// A row for the name itself is used if there is one, so in `zone.tab`,
+    /// `Europe/Oslo` is in Norway even if it links to `Europe/Berlin`;
+    /// otherwise, the name is resolved as a link, so `Europe/Belfast` is in
+    /// Britain.
+    pub fn country_for_zone(&self, name: &str) -> Option<&str> {
+        let location = self.locations.iter().find(|l| l.zone == name).or_else(|| {
+            let canonical = self.canonical_name(name);
+            self.locations.iter().find(|l| self.canonical_name(&l.zone) == canonical)
+        });
+
+        location.and_then(|l| l.countries.first()).map(|c| &**c)
+    }
+
+    /// Returns the name of the zone whose principal city is closest to the
+    /// given latitude and longitude, in degrees north and east, or `None`
+    /// if there are no locations at all.
+    pub fn nearest_zone(&self, latitude: f64, longitude: f64) -> Option<&str> {
+        let mut nearest: Option<(f64, &str)> = None;
+
+        for location in &self.locations {
+            let distance = angular_distance(latitude, longitude, location.latitude, location.longitude);
+            match nearest {
+                Some((best, _)) if best <= distance => {},
+                _ => nearest = Some((distance, &location.zone)),
+            }
+        }
+
+        nearest.map(|(_, zone)| zone)
+    }
+}
+
+/// Returns the angle between two points on a sphere, in radians, using the
+/// haversine formula, which stays accurate for points close together.
+fn angular_distance(latitude_a: f64, longitude_a: f64, latitude_b: f64, longitude_b: f64) -> f64 {
+    let (phi_a, phi_b) = (latitude_a.to_radians(), latitude_b.to_radians());
+    let half_dphi = (phi_b - phi_a) / 2.0;
+    let half_dlambda = (longitude_b - longitude_a).to_radians() / 2.0;
+
+    let a = half_dphi.sin().powi(2) + phi_a.cos() * phi_b.cos() * half_dlambda.sin().powi(2);
+    2.0 * a.sqrt().min(1.0).asin()
+}
+
+
+/// Parses the contents of a `zone1970.tab` or `zone.tab` file, skipping
+/// comments and blank lines.
+pub fn parse_zone_tab(input: &str) -> Result<Vec<ZoneLocation>, Error> {
+    let mut rows = Vec::new();
+
+    for (index, line) in split_lines(input).enumerate() {
+        let line_number = index + 1;
+        if line.starts_with('#') || line.trim().is_empty() {
+            continue;
+        }
+
+        // The columns are separated by tabs, but the values in them
+        // shouldn’t have any whitespace around them.
+        let fields: Vec<&str> = line.split('\t').map(|f| f.trim()).collect();
+        if fields.len() < 3 || fields.len() > 4 {
+            return Err(Error::WrongFieldCount(line_number));
+        }
+
+        let (latitude, longitude) = match parse_coordinates(fields[1]) {
+            Some(coords) => coords,
+            None         => return Err(Error::BadCoordinates(line_number)),
+        };
+
+        rows.push(ZoneLocation {
+            countries: fields[0].split(',').map(|c| c.to_owned()).collect(),
+            latitude:  latitude,
+            longitude: longitude,
+            zone:      fields[2].to_owned(),
+            comment:   fields.get(3).map(|c| (*c).to_owned()),
+        });
+    }
+
+    Ok(rows)
+}
+
+/// Parses a pair of ISO 6709 coordinates, such as `+4230+00131` or
+/// `-720041+0023206`, into degrees of latitude and longitude.
+fn parse_coordinates(input: &str) -> Option<(f64, f64)> {
+    if input.is_empty() || !input.bytes().all(|b| b < 128) {
+        return None;
+    }
+
+    let split = match input[1..].find(|c| c == '+' || c == '-') {
+        Some(pos) => pos + 1,
+        None      => return None,
+    };
+
+    let latitude = match parse_angle(&input[..split], 2) {
+        Some(l) => l,
+        None    => return None,
+    };
+
+    let longitude = match parse_angle(&input[split..], 3) {
+        Some(l) => l,
+        None    => return None,
+    };
+
+    Some((latitude, longitude))
+}
+
+/// Parses a signed angle with the given number of degree digits, followed
+/// by two digits of minutes and optionally two of seconds.
+fn parse_angle(input: &str, degree_digits: usize) -> Option<f64> {
+    let sign = match input.chars().next() {
+        Some('+') => 1.0,
+        Some('-') => -1.0,
+        _         => return None,
+    };
+
+    let digits = &input[1..];
+    if !digits.chars().all(|c| c.is_digit(10)) {
+        return None;
+    }
+
+    let seconds = if digits.len() == degree_digits + 2 {
+        0.0
+    }
+    else if digits.len() == degree_digits + 4 {
+        digits[degree_digits + 2 ..].parse::<f64>().unwrap()
+    }
+    else {
+        return None;
+    };
+
+    let degrees = digits[.. degree_digits].parse::<f64>().unwrap();
+    let minutes = digits[degree_digits .. degree_digits + 2].parse::<f64>().unwrap();
+
+    Some(sign * (degrees + minutes / 60.0 + seconds / 3600.0))
+}
+
+
+/// An error that can occur when parsing a table.
+#[derive(PartialEq, Debug, Copy, Clone)]
+pub enum Error {
+
+    /// A line didn’t have between three and four tab-separated fields.
+    WrongFieldCount(usize),
+
+    /// A line’s coordinates weren’t in ISO 6709 format.
+    BadCoordinates(usize),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Error::WrongFieldCount(line)  => write!(f, "line {}: wrong number of fields", line),
+            Error::BadCoordinates(line)   => write!(f, "line {}: invalid coordinates", line),
+        }
+    }
+}
+
+impl ErrorTrait for Error {
+    fn description(&self) -> &str {
+        match *self {
+            Error::WrongFieldCount(_)  => "wrong number of fields",
+            Error::BadCoordinates(_)   => "invalid coordinates",
+        }
+    }
+}
+
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use parse::parse_table;
+
+    #[test]
+    fn minutes() {
+        let rows = parse_zone_tab("AD\t+4230+00131\tEurope/Andorra\n").unwrap();
+        assert_eq!(rows, vec![ ZoneLocation {
+            countries: vec![ "AD".to_owned() ],
+            latitude:  42.5,
+            longitude: 1.0 + 31.0 / 60.0,
+            zone:      "Europe/Andorra".to_owned(),
+            comment:   None,
+        } ]);
+    }
+
+    #[test]
+    fn seconds_and_comment() {
+        let rows = parse_zone_tab("AQ\t-720041+0023206\tAntarctica/Troll\tTroll\n").unwrap();
+        assert_eq!(rows[0].latitude, -(72.0 + 0.0 / 60.0 + 41.0 / 3600.0));
+        assert_eq!(rows[0].longitude, 2.0 + 32.0 / 60.0 + 6.0 / 3600.0);
+        assert_eq!(rows[0].comment, Some("Troll".to_owned()));
+    }
+
+    #[test]
+    fn windows_text() {
+        let rows = parse_zone_tab("\u{feff}# comment\r\nAD\t+4230+00131 \tEurope/Andorra\r\r\n").unwrap();
+        assert_eq!(rows[0].zone, "Europe/Andorra");
+        assert_eq!(rows[0].comment, None);
+    }
+
+    #[test]
+    fn errors() {
+        assert_eq!(parse_zone_tab("# fine\nAD\t+4230+00131\n"), Err(Error::WrongFieldCount(2)));
+        assert_eq!(parse_zone_tab("AD\t4230+00131\tEurope/Andorra\n"), Err(Error::BadCoordinates(1)));
+        assert_eq!(parse_zone_tab("AD\t+42300+00131\tEurope/Andorra\n"), Err(Error::BadCoordinates(1)));
+    }
+
+    #[test]
+    fn nearest() {
+        let metadata = Metadata::from_zone_tab("GB,GG,IM,JE\t+513030-0000731\tEurope/London\n\
+                                                FR,MC\t+4852+00220\tEurope/Paris\n\
+                                                NZ,AQ\t-3652+17446\tPacific/Auckland\n\
+                                                WS\t-1350-17144\tPacific/Apia\n").unwrap();
+
+        // Oxford is closer to London than to Paris.
+        assert_eq!(metadata.nearest_zone(51.75, -1.26), Some("Europe/London"));
+        assert_eq!(metadata.nearest_zone(49.5, 2.0), Some("Europe/Paris"));
+
+        // Fiji is closer to Apia across the antimeridian than to Auckland.
+        assert_eq!(metadata.nearest_zone(-18.1, 178.4), Some("Pacific/Apia"));
+
+        assert_eq!(Metadata::default().nearest_zone(0.0, 0.0), None);
+    }
+
+    #[test]
+    fn countries() {
+        let metadata = Metadata::from_zone_tab("GB,GG,IM,JE\t+513030-0000731\tEurope/London\n\
+                                                DE,DK,NO,SE,SJ\t+5230+01322\tEurope/Berlin\tmost of Germany\n\
+                                                DE\t+4742+00841\tEurope/Busingen\tBüsingen\n\
+                                                NO\t+5955+01045\tEurope/Oslo\n").unwrap();
+
+        assert_eq!(metadata.zones_for_country("DE"), vec![ "Europe/Berlin", "Europe/Busingen" ]);
+        assert_eq!(metadata.zones_for_country("no"), vec![ "Europe/Berlin", "Europe/Oslo" ]);
+        assert_eq!(metadata.zones_for_country("FR"), Vec::<&str>::new());
+
+        assert_eq!(metadata.country_for_zone("Europe/Berlin"), Some("DE"));
+        assert_eq!(metadata.country_for_zone("Europe/Oslo"), Some("NO"));
+        assert_eq!(metadata.country_for_zone("Europe/Belfast"), None);
+    }
+
+    #[test]
+    fn countries_with_links() {
+        let table = parse_table("Zone  Europe/London  0:00  -  GMT\n\
+                                 Zone  Europe/Berlin  1:00  -  CET\n\
+                                 Link  Europe/Berlin  Europe/Oslo\n\
+                                 Link  Europe/London  Europe/Belfast\n").unwrap();
+        let metadata = Metadata::from_zone_tab("GB,GG,IM,JE\t+513030-0000731\tEurope/London\n\
+                                                DE,DK,NO,SE,SJ\t+5230+01322\tEurope/Berlin\n\
+                                                NO\t+5955+01045\tEurope/Oslo\n").unwrap().with_links(&table);
+
+        assert_eq!(metadata.zones_for_country("NO"), vec![ "Europe/Berlin" ]);
+        assert_eq!(metadata.country_for_zone("Europe/Oslo"), Some("NO"));
+        assert_eq!(metadata.country_for_zone("Europe/Belfast"), Some("GB"));
+    }
+}
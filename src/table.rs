@@ -13,6 +13,21 @@
 //! This module only deals with constructing a table from data: any analysis
 //! of the data is done elsewhere.
 //!
+//! The same few abbreviations and rule letters turn up over and over again
+//! in the data, so a builder only keeps one copy of each, and shares it
+//! between every line that uses it.
+//!
+//!
+//! Tables don’t have to come from text: the `add_rule`, `add_zone`, and
+//! `add_link` methods take the same `RuleInfo` and `ZoneInfo` values that a
+//! table is made of, built in code, so tests and generators of synthetic
+//! zones don’t need to write out zoneinfo lines only to have them parsed.
+//!
+//! Comments usually get thrown away, but much of the history of the data is
+//! in them, so a builder can also be given comments with `add_comment`.
+//! Each one gets attached to the next line added, and ends up in the
+//! table’s `comments`.
+//!
 //!
 //! ## Example
 //!
@@ -34,16 +49,19 @@
 //! ```
 
 use std::collections::hash_map::{HashMap, Entry};
+use std::collections::HashSet;
 use std::error::Error as ErrorTrait;
 use std::fmt;
+use std::str::FromStr;
+use std::sync::Arc;
 
-use line::{self, YearSpec, MonthSpec, DaySpec, ChangeTime};
+use line::{self, YearSpec, MonthSpec, DaySpec, ChangeTime, unquote_abbreviation};
 use datetime::{LocalDateTime, LocalTime};
 use datetime::zone::TimeType;
 
 
 /// A **table** of all the data in one or more zoneinfo files.
-#[derive(PartialEq, Debug, Default)]
+#[derive(PartialEq, Debug, Clone, Default)]
 pub struct Table {
 
     /// Mapping of ruleset names to rulesets.
@@ -54,6 +72,10 @@ pub struct Table {
 
     /// Mapping of link timezone names, to the names they link to.
     pub links: HashMap<String, String>,
+
+    /// The comments that were attached to the lines the table was built
+    /// from. This is empty unless the builder was given any.
+    pub comments: Comments,
 }
 
 
@@ -61,13 +83,16 @@ impl Table {
 
     /// Tries to find the zoneset with the given name by looking it up in
     /// either the zonesets map or the links map.
+    ///
+    /// Tables produced by a `TableBuilder` have their links resolved so that
+    /// each one points directly at a zoneset. Returns `None` if the link’s
+    /// target doesn’t exist.
     pub fn get_zoneset(&self, zone_name: &str) -> Option<&[ZoneInfo]> {
-        if self.zonesets.contains_key(zone_name) {
-            Some(&*self.zonesets[zone_name])
+        if let Some(zoneset) = self.zonesets.get(zone_name) {
+            Some(&*zoneset)
         }
-        else if self.links.contains_key(zone_name) {
-            let target = &self.links[zone_name];
-            Some(&*self.zonesets[&*target])
+        else if let Some(target) = self.links.get(zone_name) {
+            self.zonesets.get(target).map(|z| &**z)
         }
         else {
             None
@@ -76,12 +101,57 @@ impl Table {
 }
 
 
+/// The comments attached to lines of zoneinfo data, for tables whose
+/// builders were given comments. A line’s comment is every comment line
+/// since the line before it, along with the comment at the end of the line
+/// itself, joined with newlines.
+#[derive(PartialEq, Debug, Clone, Default)]
+pub struct Comments {
+
+    /// The comments on rules, by the name of their ruleset and their index
+    /// within it.
+    pub rules: HashMap<(String, usize), String>,
+
+    /// The comments on zone and continuation lines, by the name of the
+    /// zone and the index of the line’s `ZoneInfo` within its zoneset.
+    pub zones: HashMap<(String, usize), String>,
+
+    /// The comments on links, by the name of the link.
+    pub links: HashMap<String, String>,
+}
+
+impl Comments {
+
+    /// Returns the comment on the rule at the given index of the ruleset
+    /// with the given name, if it has one.
+    pub fn rule(&self, ruleset_name: &str, index: usize) -> Option<&str> {
+        self.rules.get(&(ruleset_name.to_owned(), index)).map(|c| &**c)
+    }
+
+    /// Returns the comment on the line at the given index of the zoneset
+    /// with the given name, if it has one.
+    pub fn zone(&self, zone_name: &str, index: usize) -> Option<&str> {
+        self.zones.get(&(zone_name.to_owned(), index)).map(|c| &**c)
+    }
+
+    /// Returns the comment on the link with the given name, if it has one.
+    pub fn link(&self, link_name: &str) -> Option<&str> {
+        self.links.get(link_name).map(|c| &**c)
+    }
+
+    /// Returns whether there are no comments at all.
+    pub fn is_empty(&self) -> bool {
+        self.rules.is_empty() && self.zones.is_empty() && self.links.is_empty()
+    }
+}
+
+
 /// An owned rule definition line.
 ///
 /// This mimics the `Rule` struct in the `line` module, only its uses owned
-/// Strings instead of string slices, and has had some pre-processing
+/// strings instead of string slices, and has had some pre-processing
 /// applied to it.
-#[derive(PartialEq, Debug)]
+#[derive(PartialEq, Debug, Clone)]
 pub struct RuleInfo {
 
     /// The year that this rule *starts* applying.
@@ -106,13 +176,36 @@ pub struct RuleInfo {
     /// The amount of time to save.
     pub time_to_add: i64,
 
+    /// Whether this rule’s time is daylight-saving time, if its line said
+    /// so, or `None` to go by whether there’s any time to save.
+    pub is_dst: Option<bool>,
+
     /// Any extra letters that should be added to this time zone’s
     /// abbreviation, in place of `%s`.
-    pub letters: Option<String>,
+    pub letters: Option<Arc<str>>,
 }
 
 impl<'line> From<line::Rule<'line>> for RuleInfo {
     fn from(info: line::Rule) -> RuleInfo {
+        RuleInfo::from_line(info, &mut Interner::default())
+    }
+}
+
+/// Parses a rule line on its own, such as for adding it to a builder with
+/// `TableBuilder::add_rule`. The name of its ruleset gets left out.
+impl FromStr for RuleInfo {
+    type Err = line::Error;
+
+    fn from_str(input: &str) -> Result<RuleInfo, Self::Err> {
+        line::Rule::from_str(input).map(RuleInfo::from)
+    }
+}
+
+impl RuleInfo {
+
+    /// Converts a rule line, sharing its letters with any other lines that
+    /// have the same ones.
+    fn from_line(info: line::Rule, interner: &mut Interner) -> RuleInfo {
         RuleInfo {
             from_year:    info.from_year,
             to_year:      info.to_year,
@@ -121,12 +214,16 @@ impl<'line> From<line::Rule<'line>> for RuleInfo {
             time:         info.time.0.as_seconds(),
             time_type:    info.time.1,
             time_to_add:  info.time_to_add.as_seconds(),
-            letters:      info.letters.map(str::to_owned),
+            is_dst:       info.is_dst,
+            letters:      info.letters.map(|l| interner.intern(l)),
         }
     }
-}
 
-impl RuleInfo {
+    /// Returns whether this rule’s time is daylight-saving time: what its
+    /// line said, or otherwise whether there’s any time to save.
+    pub fn is_daylight_saving(&self) -> bool {
+        self.is_dst.unwrap_or(self.time_to_add != 0)
+    }
 
     /// Returns whether this rule is in effect during the given year.
     pub fn applies_to_year(&self, year: i64) -> bool {
@@ -150,9 +247,11 @@ impl RuleInfo {
             TimeType::Wall      => utc_offset + dst_offset,
         };
 
+        // The time can be before midnight or after the end of the day, as
+        // it can in the rules from a compiled file’s TZ string, so it gets
+        // added on to the start of the day rather than made into a time.
         let date = self.day.to_concrete_date(year, self.month.0);
-        let time = LocalTime::from_seconds_since_midnight(self.time);
-        LocalDateTime::new(date, time) - Duration::of(offset)
+        LocalDateTime::new(date, LocalTime::midnight()) + Duration::of(self.time - offset)
     }
 }
 
@@ -162,9 +261,9 @@ impl RuleInfo {
 /// This struct mimics the `ZoneInfo` struct in the `line` module, *not* the
 /// `Zone` struct, which is the key name in the map—this is just the value.
 ///
-/// As with `RuleInfo`, this struct uses owned Strings rather than string
+/// As with `RuleInfo`, this struct uses owned strings rather than string
 /// slices.
-#[derive(PartialEq, Debug)]
+#[derive(PartialEq, Eq, Hash, Debug, Clone)]
 pub struct ZoneInfo {
 
     /// The number of seconds that need to be added to UTC to get the
@@ -185,6 +284,29 @@ pub struct ZoneInfo {
 
 impl<'line> From<line::ZoneInfo<'line>> for ZoneInfo {
     fn from(info: line::ZoneInfo) -> ZoneInfo {
+        ZoneInfo::from_line(info, &mut Interner::default())
+    }
+}
+
+/// Parses a zone or continuation line on its own, such as for adding it to
+/// a builder with `TableBuilder::add_zone`. A zone line’s name gets left out.
+impl FromStr for ZoneInfo {
+    type Err = line::Error;
+
+    fn from_str(input: &str) -> Result<ZoneInfo, Self::Err> {
+        match try!(line::Line::from_str(input)) {
+            line::Line::Zone(zone)          => Ok(ZoneInfo::from(zone.info)),
+            line::Line::Continuation(info)  => Ok(ZoneInfo::from(info)),
+            _                               => Err(line::Error::Fail),
+        }
+    }
+}
+
+impl ZoneInfo {
+
+    /// Converts a zone line, sharing its abbreviation format with any other
+    /// lines that have the same one.
+    fn from_line(info: line::ZoneInfo, interner: &mut Interner) -> ZoneInfo {
         ZoneInfo {
             offset: info.utc_offset.as_seconds(),
             saving: match info.saving {
@@ -192,7 +314,7 @@ impl<'line> From<line::ZoneInfo<'line>> for ZoneInfo {
                 line::Saving::Multiple(s)  => Saving::Multiple(s.to_owned()),
                 line::Saving::OneOff(t)    => Saving::OneOff(t.as_seconds()),
             },
-            format:   Format::new(info.format),
+            format:   Format::interned(info.format, interner),
             end_time: info.time,
         }
     }
@@ -204,7 +326,7 @@ impl<'line> From<line::ZoneInfo<'line>> for ZoneInfo {
 /// different types of value.
 ///
 /// This is the owned version of the `Saving` type in the `line` module.
-#[derive(PartialEq, Debug)]
+#[derive(PartialEq, Eq, Hash, Debug, Clone)]
 pub enum Saving {
 
     /// Just stick to the base offset.
@@ -222,66 +344,101 @@ pub enum Saving {
 
 
 /// The format string to generate a time zone abbreviation from.
-#[derive(PartialEq, Debug, Clone)]
+#[derive(PartialEq, Eq, Hash, Debug, Clone)]
 pub enum Format {
 
     /// A constant format, which remains the same throughout both standard
     /// and DST timespans.
-    Constant(String),
+    Constant(Arc<str>),
 
     /// An alternate format, such as “PST/PDT”, which changes between
     /// standard and DST timespans.
     Alternate {
 
         /// Abbreviation to use during Standard Time.
-        standard: String,
+        standard: Arc<str>,
 
         /// Abbreviation to use during Summer Time.
-        dst: String,
+        dst: Arc<str>,
     },
 
     /// A format with a placeholder `%s`, which uses the `letters` field in
     /// a `RuleInfo` to generate the time zone abbreviation.
-    Placeholder(String),
+    Placeholder(Arc<str>),
 }
 
 impl Format {
 
     /// Convert the template into one of the `Format` variants. This can’t
     /// fail, as any syntax that doesn’t match one of the two formats will
-    /// just be a ‘constant’ format.
+    /// just be a ‘constant’ format. Abbreviations quoted in angle brackets,
+    /// such as `<+0530>`, have the brackets taken off.
     pub fn new(template: &str) -> Format {
+        Format::interned(template, &mut Interner::default())
+    }
+
+    /// Converts the template in the same way as `new`, but sharing the
+    /// strings with any other formats that have the same ones.
+    fn interned(template: &str, interner: &mut Interner) -> Format {
+        fn unquote(abbreviation: &str) -> &str {
+            unquote_abbreviation(abbreviation).unwrap_or(abbreviation)
+        }
+
         if let Some(pos) = template.find('/') {
             Format::Alternate {
-                standard:  template[.. pos].to_owned(),
-                dst:       template[pos + 1 ..].to_owned(),
+                standard:  interner.intern(unquote(&template[.. pos])),
+                dst:       interner.intern(unquote(&template[pos + 1 ..])),
             }
         }
         else if template.contains("%s") {
-            Format::Placeholder(template.to_owned())
+            Format::Placeholder(interner.intern(unquote(template)))
         }
         else {
-            Format::Constant(template.to_owned())
+            Format::Constant(interner.intern(unquote(template)))
         }
     }
 
-    pub fn format(&self, dst_offset: i64, letters: Option<&String>) -> String {
+    /// Returns the same format, but with its strings shared with any other
+    /// formats that have the same ones.
+    fn reinterned(&self, interner: &mut Interner) -> Format {
+        match *self {
+            Format::Constant(ref s)              => Format::Constant(interner.intern(s)),
+            Format::Placeholder(ref s)           => Format::Placeholder(interner.intern(s)),
+            Format::Alternate { ref standard, ref dst } => Format::Alternate {
+                standard: interner.intern(standard),
+                dst:      interner.intern(dst),
+            },
+        }
+    }
+
+    /// Generates the abbreviation for a timespan with the given total UTC
+    /// offset, in seconds, and DST flag, using the letters of the rule in
+    /// effect, if there is one. A `%z` gets replaced with the offset, such
+    /// as `+0530` or `-03`.
+    pub fn format(&self, utc_offset: i64, is_dst: bool, letters: Option<&str>) -> String {
         let letters = match letters {
-            Some(l) => &**l,
-            None    => "",
+            Some("-") | None  => "",
+            Some(l)           => l,
         };
 
-        match *self {
-            Format::Constant(ref s) => s.clone(),
+        let abbreviation = match *self {
+            Format::Constant(ref s) => s.to_string(),
             Format::Placeholder(ref s) => s.replace("%s", letters),
-            Format::Alternate { ref standard, .. } if dst_offset == 0 => standard.clone(),
-            Format::Alternate { ref dst, .. } => dst.clone(),
+            Format::Alternate { ref standard, .. } if !is_dst => standard.to_string(),
+            Format::Alternate { ref dst, .. } => dst.to_string(),
+        };
+
+        if abbreviation.contains("%z") {
+            abbreviation.replace("%z", &numeric_abbreviation(utc_offset))
+        }
+        else {
+            abbreviation
         }
     }
 
     pub fn format_constant(&self) -> String {
         if let Format::Constant(ref s) = *self {
-            s.clone()
+            s.to_string()
         }
         else {
             panic!("Expected a constant formatting string");
@@ -290,6 +447,35 @@ impl Format {
 }
 
 
+/// Generates a time zone abbreviation from the text of a FORMAT field, in
+/// the same way as the timespans worked out by the `transitions` module get
+/// theirs: `%s` is replaced with the LETTER/S of the rule in effect, if
+/// any, `%z` with the total UTC offset in seconds, and one half of a
+/// slash pair such as `GMT/BST` is picked by whether it’s daylight-saving
+/// time.
+pub fn format_abbreviation(format: &str, letters: Option<&str>, is_dst: bool, utc_offset: i64) -> String {
+    Format::new(format).format(utc_offset, is_dst, letters)
+}
+
+/// Writes a UTC offset in seconds the way `%z` does: a sign and two digits
+/// of hours, followed by the minutes and seconds only if they’re needed.
+fn numeric_abbreviation(utc_offset: i64) -> String {
+    let sign = if utc_offset < 0 { '-' } else { '+' };
+    let offset = utc_offset.abs();
+    let (hours, minutes, seconds) = (offset / 3600, offset / 60 % 60, offset % 60);
+
+    if seconds != 0 {
+        format!("{}{:02}{:02}{:02}", sign, hours, minutes, seconds)
+    }
+    else if minutes != 0 {
+        format!("{}{:02}{:02}", sign, hours, minutes)
+    }
+    else {
+        format!("{}{:02}", sign, hours)
+    }
+}
+
+
 /// A builder for `Table` values based on various line definitions.
 #[derive(PartialEq, Debug)]
 pub struct TableBuilder {
@@ -297,10 +483,32 @@ pub struct TableBuilder {
     /// The table that’s being built up.
     table: Table,
 
-    /// If the last line was a zone definition, then this holds its name.
-    /// `None` otherwise. This is so continuation lines can be added to the
-    /// same zone as the original zone line.
-    current_zoneset_name: Option<String>,
+    /// If the last line was a zone definition, then this holds the index
+    /// of its name in `zone_order`. `None` otherwise. This is so
+    /// continuation lines can be added to the same zone as the original
+    /// zone line.
+    current_zoneset: Option<usize>,
+
+    /// One copy of each abbreviation and set of rule letters seen so far.
+    interner: Interner,
+
+    /// Whether this builder is for part of a table, in which case zones can
+    /// refer to rulesets that haven’t been defined yet.
+    partial: bool,
+
+    /// The zones and rulesets they referred to that hadn’t been defined
+    /// when they were added to a partial builder, in order.
+    pending_rulesets: Vec<(String, String)>,
+
+    /// The names of the zones, in the order they were added.
+    zone_order: Vec<String>,
+
+    /// The names of the links, in the order they were added.
+    link_order: Vec<String>,
+
+    /// The comments added since the last line, waiting to be attached to
+    /// the next one.
+    pending_comment: Option<String>,
 }
 
 impl TableBuilder {
@@ -309,7 +517,35 @@ impl TableBuilder {
     pub fn new() -> TableBuilder {
         TableBuilder {
             table: Table::default(),
-            current_zoneset_name: None,
+            current_zoneset: None,
+            interner: Interner::default(),
+            partial: false,
+            pending_rulesets: Vec::new(),
+            zone_order: Vec::new(),
+            link_order: Vec::new(),
+            pending_comment: None,
+        }
+    }
+
+    /// Creates a new builder for one part of a table, such as one file out
+    /// of several, so the parts can be built up separately (on separate
+    /// threads, say) and then combined with `merge`.
+    ///
+    /// Zones added to a partial builder are allowed to refer to rulesets it
+    /// hasn’t seen, as they could be in one of the earlier parts: whether
+    /// they are gets checked when it’s merged.
+    pub fn partial() -> TableBuilder {
+        TableBuilder { partial: true, .. TableBuilder::new() }
+    }
+
+    /// Adds a comment, which gets attached to whichever rule, zone,
+    /// continuation, or link line gets added next. Several comments in a
+    /// row get joined together with newlines. If the next line can’t be
+    /// added, its comment gets thrown away with it.
+    pub fn add_comment(&mut self, comment: &str) {
+        match self.pending_comment {
+            Some(ref mut pending) => { pending.push('\n'); pending.push_str(comment); },
+            None                  => self.pending_comment = Some(comment.to_owned()),
         }
     }
 
@@ -318,19 +554,42 @@ impl TableBuilder {
     /// Returns an error if there’s already a zone with the same name, or the
     /// zone refers to a ruleset that hasn’t been defined yet.
     pub fn add_zone_line<'line>(&mut self, zone_line: line::Zone<'line>) -> Result<(), Error<'line>> {
+        let comment = self.pending_comment.take();
+        let mut pending = None;
         if let line::Saving::Multiple(ruleset_name) = zone_line.info.saving {
             if !self.table.rulesets.contains_key(ruleset_name) {
-                return Err(Error::UnknownRuleset(ruleset_name));
+                if !self.partial {
+                    return Err(Error::UnknownRuleset(ruleset_name));
+                }
+
+                pending = Some(ruleset_name);
             }
         }
 
-        let mut zoneset = match self.table.zonesets.entry(zone_line.name.to_owned()) {
+        let info = ZoneInfo::from_line(zone_line.info, &mut self.interner);
+        try!(self.add_zoneset(zone_line.name, vec![ info ]));
+
+        if let Some(ruleset_name) = pending {
+            self.pending_rulesets.push((zone_line.name.to_owned(), ruleset_name.to_owned()));
+        }
+
+        if let Some(comment) = comment {
+            let _ = self.table.comments.zones.insert((zone_line.name.to_owned(), 0), comment);
+        }
+
+        self.current_zoneset = Some(self.zone_order.len() - 1);
+        Ok(())
+    }
+
+    /// Adds a whole zoneset under the given name, unless there’s already one
+    /// with that name.
+    fn add_zoneset<'line>(&mut self, name: &'line str, zoneset: Vec<ZoneInfo>) -> Result<(), Error<'line>> {
+        match self.table.zonesets.entry(name.to_owned()) {
             Entry::Occupied(_)  => return Err(Error::DuplicateZone),
-            Entry::Vacant(e)    => e.insert(Vec::new()),
-        };
+            Entry::Vacant(e)    => { let _ = e.insert(zoneset); },
+        }
 
-        zoneset.push(zone_line.info.into());
-        self.current_zoneset_name = Some(zone_line.name.to_owned());
+        self.zone_order.push(name.to_owned());
         Ok(())
     }
 
@@ -339,48 +598,276 @@ impl TableBuilder {
     /// Returns an error if the builder wasn’t expecting a continuation line
     /// (meaning, the previous line wasn’t a zone line)
     pub fn add_continuation_line(&mut self, continuation_line: line::ZoneInfo) -> Result<(), Error> {
-        let mut zoneset = match self.current_zoneset_name {
-            Some(ref name) => self.table.zonesets.get_mut(name).unwrap(),
+        let comment = self.pending_comment.take();
+        let info = ZoneInfo::from_line(continuation_line, &mut self.interner);
+        let name = match self.current_zoneset {
+            Some(index) => &self.zone_order[index],
             None => return Err(Error::SurpriseContinuationLine),
         };
 
-        zoneset.push(continuation_line.into());
+        let zoneset = self.table.zonesets.get_mut(name).unwrap();
+        if let Some(comment) = comment {
+            let _ = self.table.comments.zones.insert((name.clone(), zoneset.len()), comment);
+        }
+
+        zoneset.push(info);
         Ok(())
     }
 
     /// Adds a new line describing one entry in a ruleset, creating that set
     /// if it didn’t exist already.
     pub fn add_rule_line(&mut self, rule_line: line::Rule) -> Result<(), Error> {
-        let ruleset = self.table.rulesets
-                                .entry(rule_line.name.to_owned())
-                                .or_insert_with(Vec::new);
+        let name = rule_line.name;
+        let info = RuleInfo::from_line(rule_line, &mut self.interner);
+
+        if let Some(comment) = self.pending_comment.take() {
+            let index = self.table.rulesets.get(name).map(|r| r.len()).unwrap_or(0);
+            let _ = self.table.comments.rules.insert((name.to_owned(), index), comment);
+        }
+
+        // Only copy the name for the first rule in each set.
+        if let Some(ruleset) = self.table.rulesets.get_mut(name) {
+            ruleset.push(info);
+            self.current_zoneset = None;
+            return Ok(());
+        }
 
-        ruleset.push(rule_line.into());
-        self.current_zoneset_name = None;
+        let _ = self.table.rulesets.insert(name.to_owned(), vec![ info ]);
+        self.current_zoneset = None;
         Ok(())
     }
 
     /// Adds a new line linking one zone to another.
     ///
-    /// Returns an error if there was already a link with that name.
+    /// Links are allowed to point to other links, as long as following
+    /// them eventually reaches a zone. Returns an error if there was already
+    /// a link with that name, or if this link would complete a cycle.
     pub fn add_link_line<'line>(&mut self, link_line: line::Link<'line>) -> Result<(), Error<'line>> {
-        match self.table.links.entry(link_line.new.to_owned()) {
-            Entry::Occupied(_)  => Err(Error::DuplicateLink(link_line.new)),
+        let comment = self.pending_comment.take();
+        try!(self.add_link(link_line.existing, link_line.new));
+
+        if let Some(comment) = comment {
+            let _ = self.table.comments.links.insert(link_line.new.to_owned(), comment);
+        }
+
+        Ok(())
+    }
+
+    /// Adds a rule built in code to the ruleset with the given name,
+    /// creating that set if it didn’t exist already, as though it had been
+    /// read from a rule line. Its `from_year` has to be a year number.
+    pub fn add_rule(&mut self, name: &str, mut rule: RuleInfo) {
+        rule.letters = rule.letters.map(|l| self.interner.intern(&l));
+        self.table.rulesets.entry(name.to_owned()).or_insert_with(Vec::new).push(rule);
+        self.current_zoneset = None;
+    }
+
+    /// Adds a whole zone built in code, with one `ZoneInfo` for each of the
+    /// lines it would have had, as though it had been read from them.
+    ///
+    /// Returns an error if there’s already a zone with the same name, or
+    /// (unless this is a partial builder) the zone refers to a ruleset
+    /// that hasn’t been added yet. As the zone’s values are owned, the
+    /// error is a `MergeError`, which owns the names it mentions.
+    pub fn add_zone(&mut self, name: &str, mut zoneset: Vec<ZoneInfo>) -> Result<(), MergeError> {
+        let mut pending = Vec::new();
+        for info in &mut zoneset {
+            if let Saving::Multiple(ref ruleset_name) = info.saving {
+                if !self.table.rulesets.contains_key(ruleset_name) {
+                    if !self.partial {
+                        return Err(MergeError::UnknownRuleset { zone: name.to_owned(), ruleset: ruleset_name.clone() });
+                    }
+
+                    pending.push((name.to_owned(), ruleset_name.clone()));
+                }
+            }
+
+            info.format = info.format.reinterned(&mut self.interner);
+        }
+
+        if self.add_zoneset(name, zoneset).is_err() {
+            return Err(MergeError::DuplicateZone(name.to_owned()));
+        }
+
+        self.pending_rulesets.extend(pending);
+        self.current_zoneset = None;
+        Ok(())
+    }
+
+    /// Adds a link from the new name to the existing one, in the same way
+    /// as a link line. Returns an error if there was already a link with
+    /// that name, or if this link would complete a cycle.
+    pub fn add_link<'line>(&mut self, existing: &'line str, new: &'line str) -> Result<(), Error<'line>> {
+        try!(self.insert_link(existing, new));
+        self.current_zoneset = None;
+        Ok(())
+    }
+
+    /// Adds a link from the new name to the existing one, unless there’s
+    /// already a link with that name or it would complete a cycle.
+    fn insert_link<'line>(&mut self, existing: &'line str, new: &'line str) -> Result<(), Error<'line>> {
+
+        // Adding a link can only create a cycle if following the chain of
+        // links from its target leads back round to the new name.
+        let mut target = existing;
+        loop {
+            if target == new {
+                return Err(Error::CircularLink(new));
+            }
+
+            match self.table.links.get(target) {
+                Some(next) => target = next,
+                None       => break,
+            }
+        }
+
+        match self.table.links.entry(new.to_owned()) {
+            Entry::Occupied(_)  => Err(Error::DuplicateLink(new)),
             Entry::Vacant(e)    => {
-                let _ = e.insert(link_line.existing.to_owned());
-                self.current_zoneset_name = None;
+                let _ = e.insert(existing.to_owned());
+                self.link_order.push(new.to_owned());
                 Ok(())
             }
         }
     }
 
+    /// Adds everything in a partial builder to this one, as though its lines
+    /// had been added to this builder after all of its own.
+    ///
+    /// Rather than stopping at the first problem, the zones and links that
+    /// would have been rejected get left out, and a `MergeError` gets
+    /// returned for each of them, in the order their lines were added.
+    /// Rulesets get added to any existing sets with the same names.
+    pub fn merge(&mut self, mut other: TableBuilder) -> Vec<MergeError> {
+        let mut errors = Vec::new();
+
+        // The zones could only have used the rulesets from the parts before
+        // this one, not ones defined after them in the same part.
+        for (zone, ruleset) in other.pending_rulesets.drain(..) {
+            if !self.table.rulesets.contains_key(&ruleset) {
+                let _ = other.table.zonesets.remove(&zone);
+                errors.push(MergeError::UnknownRuleset { zone: zone, ruleset: ruleset });
+            }
+        }
+
+        // The other builder’s rules go after any already in the same sets,
+        // so their comments have to move along with them.
+        let mut comments = other.table.comments;
+        for ((name, index), comment) in comments.rules.drain() {
+            let offset = self.table.rulesets.get(&name).map(|r| r.len()).unwrap_or(0);
+            let _ = self.table.comments.rules.insert((name, index + offset), comment);
+        }
+
+        for (name, rules) in other.table.rulesets.drain() {
+            self.table.rulesets.entry(name).or_insert_with(Vec::new).extend(rules);
+        }
+
+        for name in &other.zone_order {
+            if let Some(zoneset) = other.table.zonesets.remove(name) {
+                let length = zoneset.len();
+                if self.add_zoneset(name, zoneset).is_err() {
+                    errors.push(MergeError::DuplicateZone(name.clone()));
+                    continue;
+                }
+
+                for index in 0 .. length {
+                    if let Some(comment) = comments.zones.remove(&(name.clone(), index)) {
+                        let _ = self.table.comments.zones.insert((name.clone(), index), comment);
+                    }
+                }
+            }
+        }
+
+        for name in &other.link_order {
+            if let Err(e) = self.insert_link(&other.table.links[name], name) {
+                errors.push(match e {
+                    Error::CircularLink(_)  => MergeError::CircularLink(name.clone()),
+                    _                       => MergeError::DuplicateLink(name.clone()),
+                });
+            }
+            else if let Some(comment) = comments.links.remove(name) {
+                let _ = self.table.comments.links.insert(name.clone(), comment);
+            }
+        }
+
+        self.current_zoneset = None;
+        errors
+    }
+
+    /// Adds everything in a partial builder to this one in the same way as
+    /// `merge`, except that its zones and links take the place of any zones
+    /// or links this builder already has with the same names, instead of
+    /// being rejected as duplicates.
+    ///
+    /// This is how the tz database’s `backzone` file is meant to be used:
+    /// it has the pre-1970 history of places whose clocks have agreed with
+    /// another zone’s since then, so the main files only have links for
+    /// them, which its zones replace.
+    pub fn overlay(&mut self, other: TableBuilder) -> Vec<MergeError> {
+        for name in other.zone_order.iter().chain(other.link_order.iter()) {
+            if self.table.zonesets.remove(name).is_some() {
+                self.zone_order.retain(|n| n != name);
+                self.table.comments.zones.retain(|&(ref n, _), _| n != name);
+            }
+
+            if self.table.links.remove(name).is_some() {
+                self.link_order.retain(|n| n != name);
+                let _ = self.table.comments.links.remove(name);
+            }
+        }
+
+        self.merge(other)
+    }
+
     /// Returns the table after it’s finished being built.
-    pub fn build(self) -> Table {
+    ///
+    /// Any links that point to other links get resolved here, so that every
+    /// link in the table points directly to the zone at the end of its
+    /// chain.
+    pub fn build(mut self) -> Table {
+        let mut resolved = HashMap::new();
+
+        for (name, target) in &self.table.links {
+            let mut target = target;
+
+            // This can’t loop forever, as cycles get rejected when the
+            // links are added.
+            while let Some(next) = self.table.links.get(target) {
+                target = next;
+            }
+
+            let _ = resolved.insert(name.clone(), target.clone());
+        }
+
+        self.table.links = resolved;
         self.table
     }
 }
 
 
+/// A set of strings where each one only gets stored once, however many
+/// times it gets interned.
+#[derive(PartialEq, Debug, Default)]
+struct Interner {
+    strings: HashSet<Arc<str>>,
+}
+
+impl Interner {
+
+    /// Returns the shared copy of the given string, adding it first if this
+    /// is the first time it’s been seen.
+    fn intern(&mut self, string: &str) -> Arc<str> {
+        if let Some(shared) = self.strings.get(string) {
+            return shared.clone();
+        }
+
+        let shared: Arc<str> = Arc::from(string);
+        let _ = self.strings.insert(shared.clone());
+        shared
+    }
+}
+
+
 /// Something that can go wrong while constructing a `Table`.
 #[derive(PartialEq, Debug, Copy, Clone)]
 pub enum Error<'line> {
@@ -397,11 +884,21 @@ pub enum Error<'line> {
 
     /// A zone line was passed in, but there’s already a zone with that name.
     DuplicateZone,
+
+    /// A link line was passed in that would make a chain of links loop
+    /// back round on itself, so it could never reach a zone.
+    CircularLink(&'line str),
 }
 
 impl<'line> fmt::Display for Error<'line> {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        write!(f, "{}", self.description())
+        match *self {
+            Error::SurpriseContinuationLine  => write!(f, "{}: continuation line without a zone line", self.description()),
+            Error::UnknownRuleset(name)      => write!(f, "{}: unknown ruleset {:?}", self.description(), name),
+            Error::DuplicateLink(name)       => write!(f, "{}: duplicate link {:?}", self.description(), name),
+            Error::DuplicateZone             => write!(f, "{}: duplicate zone", self.description()),
+            Error::CircularLink(name)        => write!(f, "{}: link {:?} would form a cycle", self.description(), name),
+        }
     }
 }
 
@@ -414,3 +911,264 @@ impl<'line> ErrorTrait for Error<'line> {
         None
     }
 }
+
+
+/// Something that can go wrong while merging one `TableBuilder` into
+/// another, or adding a zone built in code to one. These are the same
+/// problems as the `Error`s that adding the lines one at a time would have
+/// run into, but each one also has the name of the zone or link whose line
+/// was the problem.
+#[derive(PartialEq, Debug, Clone)]
+pub enum MergeError {
+
+    /// A zone referred to a ruleset that hadn’t been defined.
+    UnknownRuleset {
+
+        /// The name of the zone.
+        zone: String,
+
+        /// The name of the ruleset it referred to.
+        ruleset: String,
+    },
+
+    /// There was already a link with this name.
+    DuplicateLink(String),
+
+    /// There was already a zone with this name.
+    DuplicateZone(String),
+
+    /// This link would have made a chain of links loop back round on
+    /// itself.
+    CircularLink(String),
+}
+
+impl MergeError {
+
+    /// Returns the name of the zone or link whose line was the problem.
+    pub fn name(&self) -> &str {
+        match *self {
+            MergeError::UnknownRuleset { ref zone, .. }  => zone,
+            MergeError::DuplicateLink(ref name)          => name,
+            MergeError::DuplicateZone(ref name)          => name,
+            MergeError::CircularLink(ref name)           => name,
+        }
+    }
+
+    /// Returns the same error as the one adding the line on its own would
+    /// have returned.
+    pub fn as_error(&self) -> Error {
+        match *self {
+            MergeError::UnknownRuleset { ref ruleset, .. }  => Error::UnknownRuleset(ruleset),
+            MergeError::DuplicateLink(ref name)             => Error::DuplicateLink(name),
+            MergeError::DuplicateZone(_)                    => Error::DuplicateZone,
+            MergeError::CircularLink(ref name)              => Error::CircularLink(name),
+        }
+    }
+}
+
+impl fmt::Display for MergeError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        self.as_error().fmt(f)
+    }
+}
+
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use line::{Zone, Rule, Link};
+    use parse::parse_table;
+
+    fn link<'line>(existing: &'line str, new: &'line str) -> Link<'line> {
+        Link { existing: existing, new: new }
+    }
+
+    #[test]
+    fn link_to_link() {
+        let mut builder = TableBuilder::new();
+        builder.add_zone_line(Zone::from_str("Zone  Test/Zone  1:00  -  TST").unwrap()).unwrap();
+        builder.add_link_line(link("Test/Middle", "Test/End")).unwrap();
+        builder.add_link_line(link("Test/Zone", "Test/Middle")).unwrap();
+
+        let table = builder.build();
+        assert_eq!(table.links["Test/End"], "Test/Zone");
+        assert_eq!(table.links["Test/Middle"], "Test/Zone");
+        assert!(table.get_zoneset("Test/End").is_some());
+    }
+
+    #[test]
+    fn link_to_nothing() {
+        let mut builder = TableBuilder::new();
+        builder.add_link_line(link("Test/Missing", "Test/Link")).unwrap();
+
+        let table = builder.build();
+        assert!(table.get_zoneset("Test/Link").is_none());
+    }
+
+    #[test]
+    fn link_to_itself() {
+        let mut builder = TableBuilder::new();
+        assert_eq!(builder.add_link_line(link("Test/Link", "Test/Link")), Err(Error::CircularLink("Test/Link")));
+    }
+
+    #[test]
+    fn shared_strings() {
+        let mut builder = TableBuilder::new();
+        builder.add_rule_line(Rule::from_str("Rule  EU  1981  max  -  Mar  lastSun  1:00u  1:00  S").unwrap()).unwrap();
+        builder.add_rule_line(Rule::from_str("Rule  C-Eur  1981  max  -  Mar  lastSun  1:00s  1:00  S").unwrap()).unwrap();
+        builder.add_zone_line(Zone::from_str("Zone  Europe/Paris  1:00  EU  CE%sT").unwrap()).unwrap();
+        builder.add_zone_line(Zone::from_str("Zone  Europe/Berlin  1:00  C-Eur  CE%sT").unwrap()).unwrap();
+
+        let table = builder.build();
+        let letters: Vec<&Arc<str>> = table.rulesets.values().map(|r| r[0].letters.as_ref().unwrap()).collect();
+        assert!(Arc::ptr_eq(letters[0], letters[1]));
+
+        match (&table.zonesets["Europe/Paris"][0].format, &table.zonesets["Europe/Berlin"][0].format) {
+            (&Format::Placeholder(ref a), &Format::Placeholder(ref b)) => assert!(Arc::ptr_eq(a, b)),
+            otherwise => panic!("Expected placeholder formats, got {:?}", otherwise),
+        }
+    }
+
+    #[test]
+    fn quoted_abbreviations() {
+        assert_eq!(Format::new("<+0530>"), Format::Constant(Arc::from("+0530")));
+        assert_eq!(Format::new("<-03>/<-02>"), Format::Alternate { standard: Arc::from("-03"), dst: Arc::from("-02") });
+        assert_eq!(Format::new("<+05>/<+06>").format(21600, true, None), "+06");
+    }
+
+    #[test]
+    fn abbreviations() {
+        assert_eq!(format_abbreviation("CE%sT", Some("S"), true, 7200), "CEST");
+        assert_eq!(format_abbreviation("CE%sT", Some("-"), false, 3600), "CET");
+        assert_eq!(format_abbreviation("CE%sT", None, false, 3600), "CET");
+        assert_eq!(format_abbreviation("GMT/BST", None, true, 3600), "BST");
+        assert_eq!(format_abbreviation("GMT/IST", None, false, 0), "GMT");
+        assert_eq!(format_abbreviation("LMT", None, true, -75), "LMT");
+    }
+
+    #[test]
+    fn numeric_abbreviations() {
+        assert_eq!(format_abbreviation("%z", None, false, 0), "+00");
+        assert_eq!(format_abbreviation("%z", None, false, -10800), "-03");
+        assert_eq!(format_abbreviation("%z", None, true, 20700), "+0545");
+        assert_eq!(format_abbreviation("%z", None, false, -16966), "-044246");
+    }
+
+    #[test]
+    fn merge() {
+        let mut first = TableBuilder::new();
+        first.add_rule_line(Rule::from_str("Rule  EU  1981  max  -  Mar  lastSun  1:00u  1:00  S").unwrap()).unwrap();
+        first.add_zone_line(Zone::from_str("Zone  Europe/London  0:00  EU  GMT/BST").unwrap()).unwrap();
+        first.add_link_line(link("Europe/London", "GB")).unwrap();
+
+        let mut second = TableBuilder::partial();
+        second.add_zone_line(Zone::from_str("Zone  Europe/Paris  1:00  EU  CE%sT").unwrap()).unwrap();
+        second.add_zone_line(Zone::from_str("Zone  Europe/Berlin  1:00  US  CE%sT").unwrap()).unwrap();
+        second.add_zone_line(Zone::from_str("Zone  Europe/London  0:00  -  GMT").unwrap()).unwrap();
+        second.add_rule_line(Rule::from_str("Rule  US  1967  max  -  Apr  lastSun  2:00  1:00  D").unwrap()).unwrap();
+        second.add_link_line(link("GB", "Europe/Belfast")).unwrap();
+        second.add_link_line(link("Europe/Paris", "GB")).unwrap();
+
+        assert_eq!(first.merge(second), vec![
+            MergeError::UnknownRuleset { zone: "Europe/Berlin".to_owned(), ruleset: "US".to_owned() },
+            MergeError::DuplicateZone("Europe/London".to_owned()),
+            MergeError::DuplicateLink("GB".to_owned()),
+        ]);
+
+        let table = first.build();
+        assert!(table.get_zoneset("Europe/Paris").is_some());
+        assert!(table.get_zoneset("Europe/Berlin").is_none());
+        assert_eq!(table.links["Europe/Belfast"], "Europe/London");
+        assert_eq!(table.rulesets.len(), 2);
+    }
+
+    #[test]
+    fn overlay() {
+        let mut main = TableBuilder::new();
+        main.add_zone_line(Zone::from_str("Zone  Test/Zone  1:00  -  TST").unwrap()).unwrap();
+        main.add_link_line(link("Test/Zone", "Test/Link")).unwrap();
+        main.add_link_line(link("Test/Zone", "Test/Other")).unwrap();
+
+        let mut backzone = TableBuilder::partial();
+        backzone.add_zone_line(Zone::from_str("Zone  Test/Link  0:30  -  LMT").unwrap()).unwrap();
+
+        assert_eq!(main.overlay(backzone), vec![]);
+        let table = main.build();
+        assert_eq!(table.zonesets["Test/Link"][0].offset, 1800);
+        assert!(table.links.get("Test/Link").is_none());
+        assert_eq!(table.links["Test/Other"], "Test/Zone");
+    }
+
+    #[test]
+    fn merge_cycle() {
+        let mut first = TableBuilder::new();
+        first.add_link_line(link("Test/A", "Test/B")).unwrap();
+
+        let mut second = TableBuilder::partial();
+        second.add_link_line(link("Test/B", "Test/A")).unwrap();
+        assert_eq!(first.merge(second), vec![ MergeError::CircularLink("Test/A".to_owned()) ]);
+    }
+
+    #[test]
+    fn values_built_in_code() {
+        use datetime::Month;
+        use datetime::Weekday;
+        use line::{DaySpec, MonthSpec, YearSpec};
+
+        let mut builder = TableBuilder::new();
+        builder.add_rule("EU", RuleInfo {
+            from_year:   YearSpec::Number(1981),
+            to_year:     Some(YearSpec::Maximum),
+            month:       MonthSpec::new(Month::March),
+            day:         DaySpec::last(Weekday::Sunday),
+            time:        3600,
+            time_type:   TimeType::UTC,
+            time_to_add: 3600,
+            is_dst:      None,
+            letters:     Some(Arc::from("S")),
+        });
+
+        let zone = |ruleset: &str| vec![ ZoneInfo {
+            offset:   3600,
+            saving:   Saving::Multiple(ruleset.to_owned()),
+            format:   Format::new("CE%sT"),
+            end_time: None,
+        } ];
+
+        builder.add_zone("Europe/Paris", zone("EU")).unwrap();
+        assert_eq!(builder.add_zone("Europe/Paris", zone("EU")), Err(MergeError::DuplicateZone("Europe/Paris".to_owned())));
+        assert_eq!(builder.add_zone("Europe/Berlin", zone("US")),
+                   Err(MergeError::UnknownRuleset { zone: "Europe/Berlin".to_owned(), ruleset: "US".to_owned() }));
+        builder.add_link("Europe/Paris", "Europe/Monaco").unwrap();
+
+        let mut text = TableBuilder::new();
+        text.add_rule_line(Rule::from_str("Rule  EU  1981  max  -  Mar  lastSun  1:00u  1:00  S").unwrap()).unwrap();
+        text.add_zone_line(Zone::from_str("Zone  Europe/Paris  1:00  EU  CE%sT").unwrap()).unwrap();
+        text.add_link_line(link("Europe/Paris", "Europe/Monaco")).unwrap();
+        assert_eq!(builder.build(), text.build());
+    }
+
+    #[test]
+    fn parsing_owned_lines() {
+        let mut builder = TableBuilder::new();
+        builder.add_rule("EU", "Rule  EU  1981  max  -  Mar  lastSun  1:00u  1:00  S".parse().unwrap());
+        builder.add_zone("Europe/Paris", vec![ "Zone  Europe/Paris  0:09:21  -  LMT  1911 Mar 11".parse().unwrap(),
+                                               "1:00  EU  CE%sT".parse().unwrap() ]).unwrap();
+
+        let text = parse_table("Rule  EU  1981  max  -  Mar  lastSun  1:00u  1:00  S\n\
+                                Zone  Europe/Paris  0:09:21  -  LMT  1911 Mar 11\n\
+                                \t1:00  EU  CE%sT\n").unwrap();
+        assert_eq!(builder.build(), text);
+
+        assert_eq!("Link  Europe/Paris  Europe/Monaco".parse::<RuleInfo>(), Err(line::Error::Fail));
+        assert_eq!("Link  Europe/Paris  Europe/Monaco".parse::<ZoneInfo>(), Err(line::Error::Fail));
+    }
+
+    #[test]
+    fn link_cycle() {
+        let mut builder = TableBuilder::new();
+        builder.add_link_line(link("Test/A", "Test/B")).unwrap();
+        builder.add_link_line(link("Test/B", "Test/C")).unwrap();
+        assert_eq!(builder.add_link_line(link("Test/C", "Test/A")), Err(Error::CircularLink("Test/A")));
+    }
+}
@@ -0,0 +1,163 @@
+//! Converting between UTC and the TAI and GPS time scales.
+//!
+//! Unix timestamps count the seconds since 1970 as though every day had
+//! 86,400 of them, so they skip over the leap seconds that get added to UTC
+//! to keep it in step with the Earth’s rotation. International Atomic Time
+//! (TAI) doesn’t: it counts every second, and has been ahead of UTC by a
+//! whole number of seconds since 1972, starting at ten and changing by one
+//! with each leap second. GPS time is the same, but was started nineteen
+//! seconds behind TAI, on 6 January 1980.
+//!
+//! The functions here use the leap seconds read by the `leap` module to
+//! convert between them. TAI times are given in the same form as Unix
+//! timestamps, counting from 1970 with TAI − UTC added on, which is what
+//! Linux’s `CLOCK_TAI` gives. GPS times are given as the number of seconds
+//! since the GPS epoch, as receivers give them.
+//!
+//! Before 1972, UTC was kept close to TAI by stretching its seconds rather
+//! than adding whole ones, which these functions don’t try to reproduce:
+//! they treat TAI − UTC as ten seconds for all earlier times. And as leap
+//! seconds are only announced a few months ahead, the offsets after the
+//! table expires are only guesses.
+//!
+//! ## Example
+//!
+//! ```
+//! use zoneinfo_parse::leap::parse_leapseconds;
+//! use zoneinfo_parse::tai::{tai_offset, utc_to_tai, tai_to_utc};
+//!
+//! let table = parse_leapseconds("Leap\t2016\tDec\t31\t23:59:60\t+\tS\n").unwrap();
+//! assert_eq!(tai_offset(&table, 1_483_228_800), 11);
+//! assert_eq!(utc_to_tai(&table, 1_483_228_800), 1_483_228_811);
+//! assert_eq!(tai_to_utc(&table, 1_483_228_811), 1_483_228_800);
+//! ```
+
+use leap::LeapSecondTable;
+
+
+/// The number of seconds TAI was ahead of UTC before the first leap second,
+/// at the start of 1972.
+pub const INITIAL_TAI_OFFSET: i64 = 10;
+
+/// The number of seconds GPS time is behind TAI.
+pub const GPS_TAI_OFFSET: i64 = 19;
+
+/// The Unix timestamp of the GPS epoch, midnight UTC at the start of
+/// 6 January 1980.
+pub const GPS_EPOCH: i64 = 315_964_800;
+
+
+/// Returns the number of seconds TAI is ahead of UTC at the given Unix
+/// timestamp: ten, plus the corrections of every leap second up to then.
+pub fn tai_offset(table: &LeapSecondTable, utc: i64) -> i64 {
+    table.leap_seconds.iter()
+         .take_while(|leap| leap.timestamp <= utc)
+         .fold(INITIAL_TAI_OFFSET, |offset, leap| offset + leap.correction)
+}
+
+/// Converts a Unix timestamp to a TAI time.
+pub fn utc_to_tai(table: &LeapSecondTable, utc: i64) -> i64 {
+    utc + tai_offset(table, utc)
+}
+
+/// Converts a TAI time to a Unix timestamp. As Unix timestamps can’t
+/// represent an added leap second, both it and the second before it give
+/// the same timestamp.
+pub fn tai_to_utc(table: &LeapSecondTable, tai: i64) -> i64 {
+    let mut offset = INITIAL_TAI_OFFSET;
+
+    for leap in &table.leap_seconds {
+        // The new offset takes over from the leap second itself if one was
+        // added, or from the second before the one that was removed.
+        let new_offset = offset + leap.correction;
+        if tai < leap.timestamp + offset.min(new_offset) {
+            break;
+        }
+
+        offset = new_offset;
+    }
+
+    tai - offset
+}
+
+/// Converts a Unix timestamp to a GPS time.
+pub fn utc_to_gps(table: &LeapSecondTable, utc: i64) -> i64 {
+    utc_to_tai(table, utc) - GPS_TAI_OFFSET - GPS_EPOCH
+}
+
+/// Converts a GPS time to a Unix timestamp, treating added leap seconds as
+/// `tai_to_utc` does.
+pub fn gps_to_utc(table: &LeapSecondTable, gps: i64) -> i64 {
+    tai_to_utc(table, gps + GPS_TAI_OFFSET + GPS_EPOCH)
+}
+
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use leap::{parse_leapseconds, LeapSecond, LeapSecondTable};
+
+    const SAMPLE: &'static str = "Leap\t1972\tJun\t30\t23:59:60\t+\tS\n\
+                                  Leap\t1972\tDec\t31\t23:59:60\t+\tS\n\
+                                  Leap\t2016\tDec\t31\t23:59:60\t+\tS\n";
+
+    #[test]
+    fn offsets() {
+        let table = parse_leapseconds(SAMPLE).unwrap();
+        assert_eq!(tai_offset(&table, 0), 10);
+        assert_eq!(tai_offset(&table, 78_796_799), 10);
+        assert_eq!(tai_offset(&table, 78_796_800), 11);
+        assert_eq!(tai_offset(&table, 94_694_400), 12);
+        assert_eq!(tai_offset(&table, 1_483_228_800), 13);
+    }
+
+    #[test]
+    fn round_trip() {
+        let table = parse_leapseconds(SAMPLE).unwrap();
+        for &utc in &[ 0, 78_796_799, 78_796_800, 100_000_000, 1_483_228_799, 1_483_228_800 ] {
+            assert_eq!(tai_to_utc(&table, utc_to_tai(&table, utc)), utc);
+            assert_eq!(gps_to_utc(&table, utc_to_gps(&table, utc)), utc);
+        }
+    }
+
+    #[test]
+    fn added_leap_second() {
+        let table = parse_leapseconds(SAMPLE).unwrap();
+
+        // 23:59:59 and 23:59:60 at the end of 2016, then midnight.
+        assert_eq!(tai_to_utc(&table, 1_483_228_811), 1_483_228_799);
+        assert_eq!(tai_to_utc(&table, 1_483_228_812), 1_483_228_799);
+        assert_eq!(tai_to_utc(&table, 1_483_228_813), 1_483_228_800);
+    }
+
+    #[test]
+    fn removed_leap_second() {
+        let table = LeapSecondTable {
+            leap_seconds: vec![ LeapSecond { timestamp: 1_000_000_000, correction: -1, rolling: false } ],
+            expires:      None,
+        };
+
+        // The second before the timestamp gets skipped.
+        assert_eq!(tai_to_utc(&table, 1_000_000_008), 999_999_998);
+        assert_eq!(tai_to_utc(&table, 1_000_000_009), 1_000_000_000);
+        assert_eq!(utc_to_tai(&table, 999_999_998), 1_000_000_008);
+        assert_eq!(utc_to_tai(&table, 1_000_000_000), 1_000_000_009);
+    }
+
+    #[test]
+    fn gps() {
+        // By 1980, there had been nine leap seconds, putting UTC nineteen
+        // seconds behind TAI, so GPS time started out the same as UTC.
+        let table = parse_leapseconds("Leap\t1972\tJun\t30\t23:59:60\t+\tS\n\
+                                       Leap\t1972\tDec\t31\t23:59:60\t+\tS\n\
+                                       Leap\t1973\tDec\t31\t23:59:60\t+\tS\n\
+                                       Leap\t1974\tDec\t31\t23:59:60\t+\tS\n\
+                                       Leap\t1975\tDec\t31\t23:59:60\t+\tS\n\
+                                       Leap\t1976\tDec\t31\t23:59:60\t+\tS\n\
+                                       Leap\t1977\tDec\t31\t23:59:60\t+\tS\n\
+                                       Leap\t1978\tDec\t31\t23:59:60\t+\tS\n\
+                                       Leap\t1979\tDec\t31\t23:59:60\t+\tS\n").unwrap();
+        assert_eq!(utc_to_gps(&table, GPS_EPOCH), 0);
+        assert_eq!(gps_to_utc(&table, 0), GPS_EPOCH);
+    }
+}
@@ -0,0 +1,127 @@
+//! Using computed timespans with the `time` crate.
+//!
+//! The `time` crate represents offsets from UTC with its `UtcOffset` type,
+//! and instants with `OffsetDateTime`, but leaves it to other crates to
+//! say which offset a zone has at which instant. `TimeOffsets` answers that
+//! from a `FixedTimespanSet`, in `time`’s own types, so programs that use
+//! `time` don’t need to write their own adapter.
+//!
+//! Like the `chrono_zone` module, it borrows the timespan set it was made
+//! from. It is only available when the crate is compiled with the `time`
+//! feature.
+//!
+//! ## Example
+//!
+//! ```
+//! # #[cfg(feature = "time")] extern crate time;
+//! # extern crate zoneinfo_parse;
+//! # #[cfg(feature = "time")] fn main() {
+//! use time::OffsetDateTime;
+//! use zoneinfo_parse::time_offsets::TimeOffsets;
+//! use zoneinfo_parse::transitions::{FixedTimespan, FixedTimespanSet};
+//!
+//! let set = FixedTimespanSet {
+//!     first: FixedTimespan { utc_offset: 19_800, dst_offset: 0, is_dst: false, name: "IST".to_owned() },
+//!     rest:  vec![],
+//! };
+//!
+//! let offsets = TimeOffsets::from(&set);
+//! let local = offsets.to_zone(OffsetDateTime::UNIX_EPOCH);
+//! assert_eq!((local.hour(), local.minute()), (5, 30));
+//! assert_eq!(local.offset().whole_seconds(), 19_800);
+//! # }
+//! # #[cfg(not(feature = "time"))] fn main() {}
+//! ```
+
+use time::{OffsetDateTime, PrimitiveDateTime, UtcOffset};
+use datetime::LocalDateTime;
+
+use transitions::{FixedTimespan, FixedTimespanSet, LocalResolution};
+
+
+/// Returns the total offset of the given timespan, daylight-saving time
+/// included, as a `UtcOffset`.
+pub fn utc_offset(timespan: &FixedTimespan) -> UtcOffset {
+    // Offsets in the tz database are all well within the range of a
+    // `UtcOffset`, which goes up to a day either way.
+    UtcOffset::from_whole_seconds(timespan.total_offset() as i32).unwrap()
+}
+
+
+/// A set of timespans, giving offsets in the `time` crate’s types.
+#[derive(PartialEq, Debug, Copy, Clone)]
+pub struct TimeOffsets<'set> {
+
+    /// The timespans to find offsets in.
+    pub timespans: &'set FixedTimespanSet,
+}
+
+impl<'set> From<&'set FixedTimespanSet> for TimeOffsets<'set> {
+    fn from(timespans: &'set FixedTimespanSet) -> TimeOffsets<'set> {
+        TimeOffsets { timespans: timespans }
+    }
+}
+
+impl<'set> TimeOffsets<'set> {
+
+    /// Returns the offset in effect at the given instant.
+    pub fn offset_at(&self, datetime: OffsetDateTime) -> UtcOffset {
+        utc_offset(self.timespans.timespan_at(datetime.unix_timestamp()))
+    }
+
+    /// Returns the given instant with the offset in effect at that instant,
+    /// so its date and time are the local ones.
+    pub fn to_zone(&self, datetime: OffsetDateTime) -> OffsetDateTime {
+        datetime.to_offset(self.offset_at(datetime))
+    }
+
+    /// Returns every instant that the given local date and time refers to:
+    /// one usually, but two if it happened twice as the clocks went back
+    /// (earlier one first), or none if it got skipped as they went forward.
+    pub fn from_local(&self, local: PrimitiveDateTime) -> Vec<OffsetDateTime> {
+        let seconds = local.assume_utc().unix_timestamp();
+
+        match self.timespans.resolve_local(LocalDateTime::at(seconds)) {
+            LocalResolution::Unique(timespan)           => vec![ local.assume_offset(utc_offset(&timespan)) ],
+            LocalResolution::Ambiguous(earlier, later)  => vec![ local.assume_offset(utc_offset(&earlier)), local.assume_offset(utc_offset(&later)) ],
+            LocalResolution::Gap(_, _)                  => Vec::new(),
+        }
+    }
+}
+
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use transitions::london_2024;
+
+    fn instant(timestamp: i64) -> OffsetDateTime {
+        OffsetDateTime::from_unix_timestamp(timestamp).unwrap()
+    }
+
+    #[test]
+    fn offsets() {
+        let set = london_2024();
+        let offsets = TimeOffsets::from(&set);
+        assert_eq!(offsets.offset_at(instant(1_711_846_799)), UtcOffset::UTC);
+        assert_eq!(offsets.offset_at(instant(1_711_846_800)).whole_seconds(), 3600);
+        assert_eq!(offsets.to_zone(instant(1_711_846_800)).hour(), 2);
+    }
+
+    #[test]
+    fn local_times() {
+        let set = london_2024();
+        let offsets = TimeOffsets::from(&set);
+
+        let summer = instant(1_717_243_200);
+        let summer = PrimitiveDateTime::new(summer.date(), summer.time());
+        assert_eq!(offsets.from_local(summer), vec![ instant(1_717_239_600) ]);
+
+        let skipped = instant(1_711_848_600);
+        assert!(offsets.from_local(PrimitiveDateTime::new(skipped.date(), skipped.time())).is_empty());
+
+        let repeated = instant(1_729_992_600);
+        let repeated = offsets.from_local(PrimitiveDateTime::new(repeated.date(), repeated.time()));
+        assert_eq!(repeated, vec![ instant(1_729_989_000), instant(1_729_992_600) ]);
+    }
+}
@@ -0,0 +1,124 @@
+//! A time zone to use at runtime, once its transitions have been computed.
+//!
+//! The `transitions` module turns a table into fixed timespan sets, which
+//! say everything there is to know about a zone’s offsets, but not in a
+//! form that answers the questions programs actually ask of a time zone:
+//! what the offset is at a given instant, what the local time is then, and
+//! which instant a given local time refers to. The `TimeZone` type here
+//! answers them, so the computed data can be used directly, without
+//! converting it into another crate’s time zone type first.
+//!
+//! ## Example
+//!
+//! ```
+//! use zoneinfo_parse::line::Zone;
+//! use zoneinfo_parse::table::TableBuilder;
+//! use zoneinfo_parse::timezone::TimeZone;
+//! use zoneinfo_parse::transitions::TransitionOptions;
+//!
+//! let mut builder = TableBuilder::new();
+//! builder.add_zone_line(Zone::from_str("Zone  Asia/Kolkata  5:30  -  IST").unwrap()).unwrap();
+//! let table = builder.build();
+//!
+//! let zone = TimeZone::from_table(&table, "Asia/Kolkata", &TransitionOptions::default()).unwrap();
+//! assert_eq!(zone.offset_at(0).total_offset(), 19_800);
+//! assert_eq!(zone.offset_at(0).name, "IST");
+//! ```
+
+use datetime::LocalDateTime;
+
+use table::Table;
+use transitions::{FixedTimespan, FixedTimespanSet, LocalResolution, TableTransitions, TransitionOptions};
+
+
+/// A named time zone, with every timespan it has been through.
+#[derive(PartialEq, Debug, Clone)]
+pub struct TimeZone {
+
+    /// The name of the zone, such as `Europe/London`.
+    pub name: String,
+
+    /// The zone’s timespans, and the instants it changes between them.
+    pub timespans: FixedTimespanSet,
+}
+
+impl TimeZone {
+
+    /// Creates a time zone with the given name and timespans.
+    pub fn new(name: String, timespans: FixedTimespanSet) -> TimeZone {
+        TimeZone {
+            name:      name,
+            timespans: timespans,
+        }
+    }
+
+    /// Computes the time zone with the given name from a table, expanding
+    /// rules over the years given in the options. The zone keeps the name
+    /// it was asked for, even if it’s a link.
+    /// Returns `None` if the table doesn’t contain a time zone with that name.
+    pub fn from_table(table: &Table, name: &str, options: &TransitionOptions) -> Option<TimeZone> {
+        table.timespans(name, options).map(|set| TimeZone::new(name.to_owned(), set))
+    }
+
+    /// Returns the timespan in effect at the given Unix timestamp, which has
+    /// the UTC offset, the daylight-saving offset, and the abbreviation in
+    /// use at that instant.
+    pub fn offset_at(&self, timestamp: i64) -> &FixedTimespan {
+        self.timespans.timespan_at(timestamp)
+    }
+
+    /// Returns the local date and time in this zone at the given Unix
+    /// timestamp.
+    pub fn to_local(&self, timestamp: i64) -> LocalDateTime {
+        LocalDateTime::at(timestamp + self.offset_at(timestamp).total_offset())
+    }
+
+    /// Works out which instant the given local date and time refers to in
+    /// this zone. Around a transition, it could refer to two of them, or
+    /// none at all: see `LocalResolution` for how each case is returned.
+    pub fn from_local(&self, local: LocalDateTime) -> LocalResolution {
+        self.timespans.resolve_local(local)
+    }
+}
+
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use transitions::london_2024;
+    use datetime::{LocalDate, LocalTime, Month};
+
+    fn london() -> TimeZone {
+        TimeZone::new("Europe/London".to_owned(), london_2024())
+    }
+
+    fn local(month: Month, day: i8, hour: i8, minute: i8) -> LocalDateTime {
+        LocalDateTime::new(LocalDate::ymd(2024, month, day).unwrap(), LocalTime::hm(hour, minute).unwrap())
+    }
+
+    #[test]
+    fn offsets() {
+        let zone = london();
+        assert_eq!(zone.offset_at(1_711_846_799).name, "GMT");
+        assert_eq!(zone.offset_at(1_711_846_800).name, "BST");
+        assert_eq!(zone.offset_at(1_729_990_800).name, "GMT");
+        assert_eq!(zone.offset_at(i64::min_value()).name, "GMT");
+    }
+
+    #[test]
+    fn local_times() {
+        let zone = london();
+        assert_eq!(zone.to_local(1_711_846_800), local(Month::March, 31, 2, 0));
+        assert_eq!(zone.to_local(1_711_846_799), LocalDateTime::at(1_711_846_799));
+    }
+
+    #[test]
+    fn resolving() {
+        let zone = london();
+        assert_eq!(zone.from_local(local(Month::June, 1, 12, 0)), LocalResolution::Unique(FixedTimespan::new(0, 3600, "BST")));
+        assert_eq!(zone.from_local(local(Month::March, 31, 1, 30)),
+                   LocalResolution::Gap(FixedTimespan::new(0, 0, "GMT"), FixedTimespan::new(0, 3600, "BST")));
+        assert_eq!(zone.from_local(local(Month::October, 27, 1, 30)),
+                   LocalResolution::Ambiguous(FixedTimespan::new(0, 3600, "BST"), FixedTimespan::new(0, 0, "GMT")));
+    }
+}
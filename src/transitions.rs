@@ -6,17 +6,22 @@
 //! time zone. These timespan sets can then be iterated over to produce
 //! *transitions*: when the local time changes from one offset to another.
 //!
-//! These sets are returned as `FixedTimespanSet` values, rather than
-//! iterators, because the generation logic does not output the timespans
-//! in any particular order, meaning they need to be sorted before they’re
-//! returned—so we may as well just return the vector, rather than an
-//! iterator over the vector.
+//! These sets are returned as `FixedTimespanSet` values by the `timespans`
+//! method. When only some of the transitions are needed, such as the ones
+//! around the current time, the `transitions_iter` method returns an
+//! iterator that computes them a year at a time instead. The generation
+//! logic does not output the timespans in any particular order, so the
+//! iterator holds each one back until it knows nothing generated later
+//! could come before it. The `resolve_local` method uses this iterator to
+//! work out whether a local time happens once, twice, or not at all, and
+//! the `offset_at` method uses it to find the offset at a single instant.
 //!
-//! Similarly, there is a fixed set of years that is iterated over
-//! (currently 1800..2100), rather than having an iterator that produces
-//! timespans indefinitely. Not only do we need a complete set of timespans
-//! for sorting, but it is not necessarily advisable to rely on offset
-//! changes so far into the future!
+//! There is a fixed set of years that is iterated over (by default, 1800 to
+//! 2099), rather than producing timespans indefinitely, as it is not
+//! necessarily advisable to rely on offset changes so far into the future!
+//! The range can be changed by passing a different set of
+//! `TransitionOptions`: a smaller range produces less data, at the cost of
+//! covering fewer years.
 //!
 //! ### Example
 //!
@@ -100,9 +105,24 @@
 //!
 //! The logic in this file is based off of `zic.c`, which comes with the
 //! zoneinfo files and is in the public domain.
+//!
+//! ### Rule activations
+//!
+//! The rules in a ruleset can also be expanded on their own, without a zone,
+//! using the `rule_activations` method. This returns each occasion that a
+//! rule comes into effect in a range of years. Without a zone, there are no
+//! UTC or DST offsets to apply, so the times are returned as they’re written
+//! in the rules, along with the type of time they’re in.
+
+use std::cmp::min;
+use std::collections::VecDeque;
+use std::i64;
+use std::ops::Range;
+use std::sync::Arc;
 
 use table::{Table, Saving, RuleInfo, ZoneInfo};
-use datetime::LocalDateTime;
+use datetime::{LocalDate, LocalDateTime, LocalTime, Duration, DatePiece, Month};
+use datetime::zone::TimeType;
 
 
 /// A set of timespans, separated by the instances at which the timespans
@@ -110,7 +130,7 @@ use datetime::LocalDateTime;
 ///
 /// This mimics the `FixedTimespanSet` struct in `datetime::cal::zone`,
 /// except it uses owned `Vec`s instead of slices.
-#[derive(PartialEq, Debug, Clone)]
+#[derive(PartialEq, Eq, PartialOrd, Ord, Hash, Debug, Clone)]
 pub struct FixedTimespanSet {
 
     /// The first timespan, which is assumed to have been in effect up until
@@ -127,12 +147,64 @@ pub struct FixedTimespanSet {
 }
 
 
+impl FixedTimespanSet {
+
+    /// Returns the timespan in effect at the given Unix timestamp. The
+    /// transitions are in order, so this finds it with a binary search,
+    /// rather than looking through every one.
+    pub fn timespan_at(&self, timestamp: i64) -> &FixedTimespan {
+        match self.transitions_until(timestamp) {
+            0     => &self.first,
+            count => &self.rest[count - 1].1,
+        }
+    }
+
+    /// Works out what the given local date and time means in this set of
+    /// timespans: whether it happened once, twice, or not at all. Only the
+    /// transitions within a couple of days of it get looked at.
+    pub fn resolve_local(&self, local: LocalDateTime) -> LocalResolution {
+        let local = local.to_instant().seconds();
+        let start = self.transitions_until(local.saturating_sub(YEAR_MARGIN));
+        let first = match start {
+            0     => self.first.clone(),
+            count => self.rest[count - 1].1.clone(),
+        };
+
+        resolve(first, self.rest[start ..].iter().cloned(), local)
+    }
+
+    /// Drops every transition outside the given range of Unix timestamps,
+    /// for when the years before and after it will never be needed. The
+    /// timespan in effect at `start` becomes the first one, and transitions
+    /// at or after `end` get left out, so the timespan in effect before
+    /// `end` carries on indefinitely.
+    pub fn truncate(&mut self, start: i64, end: i64) {
+        let skipped = self.transitions_until(start);
+        if skipped > 0 {
+            self.first = self.rest[skipped - 1].1.clone();
+            let _ = self.rest.drain(.. skipped);
+        }
+
+        self.rest.retain(|&(at, _)| at < end);
+    }
+
+    /// Returns how many transitions happen at or before the given Unix
+    /// timestamp.
+    fn transitions_until(&self, timestamp: i64) -> usize {
+        match self.rest.binary_search_by(|&(at, _)| at.cmp(&timestamp)) {
+            Ok(index)   => index + 1,
+            Err(index)  => index,
+        }
+    }
+}
+
+
 /// An individual timespan with a fixed offset.
 ///
 /// This mimics the `FixedTimespan` struct in `datetime::cal::zone`, except
-/// instead of “total offset” and “is DST” fields, it has separate UTC and
-/// DST fields. Also, the name is an owned `String` here instead of a slice.
-#[derive(PartialEq, Debug, Clone)]
+/// instead of a “total offset” field, it has separate UTC and DST fields.
+/// Also, the name is an owned `String` here instead of a slice.
+#[derive(PartialEq, Eq, PartialOrd, Ord, Hash, Debug, Clone)]
 pub struct FixedTimespan {
 
     /// The number of seconds offset from UTC during this timespan.
@@ -141,237 +213,811 @@ pub struct FixedTimespan {
     /// The number of *extra* daylight-saving seconds during this timespan.
     pub dst_offset: i64,
 
+    /// Whether this timespan counts as daylight-saving time. This is usually
+    /// whether there are any daylight-saving seconds, but a rule can say
+    /// otherwise with an `s` or `d` after its SAVE field.
+    pub is_dst: bool,
+
     /// The abbreviation in use during this timespan.
     pub name: String,
 }
 
 impl FixedTimespan {
 
+    /// Returns a timespan with the given offsets and abbreviation, which
+    /// counts as daylight-saving time if it has any daylight-saving seconds.
+    pub fn new(utc_offset: i64, dst_offset: i64, name: &str) -> FixedTimespan {
+        FixedTimespan {
+            utc_offset: utc_offset,
+            dst_offset: dst_offset,
+            is_dst:     dst_offset != 0,
+            name:       name.to_owned(),
+        }
+    }
+
     /// The total offset in effect during this timespan.
     pub fn total_offset(&self) -> i64 {
         self.utc_offset + self.dst_offset
     }
+
+    /// Whether this timespan is indistinguishable from another to anything
+    /// that only looks at the total offset, whether DST is in effect, and
+    /// the abbreviation, as the generated data does.
+    pub fn looks_the_same_as(&self, other: &FixedTimespan) -> bool {
+        self.total_offset() == other.total_offset()
+            && self.is_dst == other.is_dst
+            && self.name == other.name
+    }
+}
+
+
+/// A single occasion on which a rule comes into effect.
+#[derive(PartialEq, Debug, Clone)]
+pub struct RuleActivation {
+
+    /// The date and time the rule comes into effect, as written in the rule.
+    pub datetime: LocalDateTime,
+
+    /// Whether `datetime` is in UTC, standard time, or wall clock time.
+    pub time_type: TimeType,
+
+    /// The number of seconds of daylight-saving time in effect once the rule
+    /// has come into effect.
+    pub save: i64,
+
+    /// The letters to substitute into a zone’s abbreviation format while
+    /// the rule is in effect, if any.
+    pub letters: Option<Arc<str>>,
+}
+
+
+/// Options that control how timespans are computed.
+#[derive(PartialEq, Debug, Clone, Copy)]
+pub struct TransitionOptions {
+
+    /// The first year to expand rules in. Rules that would have come into
+    /// effect before this year are ignored.
+    pub min_year: i64,
+
+    /// The last year to expand rules in. Zones that keep observing
+    /// daylight-saving time will have no more transitions after this year.
+    pub max_year: i64,
+
+    /// Whether to leave out transitions that don’t change anything visible:
+    /// the total offset, whether daylight-saving time is in effect, and the
+    /// abbreviation all stay the same, even though the UTC and DST offsets
+    /// that add up to the total may be different. On by default.
+    pub dedup: bool,
+}
+
+impl Default for TransitionOptions {
+    fn default() -> TransitionOptions {
+        TransitionOptions {
+            min_year: 1800,
+            max_year: 2099,
+            dedup:    true,
+        }
+    }
+}
+
+
+/// What a local date and time means in a particular time zone.
+///
+/// Most local times happen exactly once, but when the clocks go back, the
+/// local times in the hour (or however long) that gets repeated happen
+/// twice, and when the clocks go forward, the ones in the hour that gets
+/// skipped never happen at all. Each timespan here can be used to turn the
+/// local time into an instant, by subtracting its total offset.
+#[derive(PartialEq, Eq, Hash, Debug, Clone)]
+pub enum LocalResolution {
+
+    /// The local time happens exactly once, during this timespan.
+    Unique(FixedTimespan),
+
+    /// The local time happens twice, first during the earlier timespan and
+    /// then again during the later one.
+    Ambiguous(FixedTimespan, FixedTimespan),
+
+    /// The local time got skipped over when the clocks went forward from the
+    /// first timespan to the second.
+    Gap(FixedTimespan, FixedTimespan),
 }
 
 
-/// Trait to put the `timespans` method on Tables.
+/// Trait to put the `timespans`, `transitions_iter`, `transitions_between`,
+/// `next_transition`, `offset_at`, `resolve_local`, and `rule_activations`
+/// methods on Tables.
 pub trait TableTransitions {
 
-    /// Computes a fixed timespan set for the timezone with the given name.
+    /// Computes a fixed timespan set for the timezone with the given name,
+    /// expanding rules over the years given in the options.
+    /// Returns `None` if the table doesn’t contain a time zone with that name.
+    fn timespans(&self, zone_name: &str, options: &TransitionOptions) -> Option<FixedTimespanSet>;
+
+    /// Returns an iterator over the transitions for the timezone with the
+    /// given name, which computes them as they’re needed rather than all at
+    /// once. This is faster when only the first few are needed.
+    /// Returns `None` if the table doesn’t contain a time zone with that name.
+    fn transitions_iter(&self, zone_name: &str, options: &TransitionOptions) -> Option<Transitions>;
+
+    /// Returns an iterator over the transitions for the timezone with the
+    /// given name that happen at or after the `start` Unix timestamp and
+    /// before the `end` one, such as the ones in a single year. The earlier
+    /// transitions still get computed, to know which rules are in effect,
+    /// but they don’t get kept, and none after the end get computed at all.
+    /// Returns `None` if the table doesn’t contain a time zone with that name.
+    fn transitions_between(&self, zone_name: &str, start: i64, end: i64, options: &TransitionOptions) -> Option<TransitionsBetween>;
+
+    /// Returns the first transition strictly after the given Unix timestamp
+    /// in the timezone with the given name: the instant it happens at, and
+    /// the timespan that comes into effect then. Only as many transitions as
+    /// are needed to find it get computed.
+    /// Returns `None` if the table doesn’t contain a time zone with that name,
+    /// or if the zone has no more transitions within the years in the options.
+    fn next_transition(&self, zone_name: &str, timestamp: i64, options: &TransitionOptions) -> Option<(i64, FixedTimespan)>;
+
+    /// Returns the timespan in effect at the given Unix timestamp in the
+    /// timezone with the given name, which has its total offset, its DST
+    /// offset, and its abbreviation. Only the transitions up to the timestamp
+    /// get computed, rather than the zone’s whole timespan set.
     /// Returns `None` if the table doesn’t contain a time zone with that name.
-    fn timespans(&self, zone_name: &str) -> Option<FixedTimespanSet>;
+    fn offset_at(&self, zone_name: &str, timestamp: i64, options: &TransitionOptions) -> Option<FixedTimespan>;
+
+    /// Works out what the given local date and time means in the timezone
+    /// with the given name: whether it happened once, twice, or not at all.
+    /// Only as many transitions as are needed to find out get computed.
+    /// Returns `None` if the table doesn’t contain a time zone with that name.
+    fn resolve_local(&self, zone_name: &str, local: LocalDateTime, options: &TransitionOptions) -> Option<LocalResolution>;
+
+    /// Expands the ruleset with the given name into every occasion one of its
+    /// rules comes into effect during the given range of years, in order.
+    /// Returns `None` if the table doesn’t contain a ruleset with that name.
+    fn rule_activations(&self, ruleset_name: &str, years: Range<i64>) -> Option<Vec<RuleActivation>>;
 }
 
 
 impl TableTransitions for Table {
 
-    fn timespans(&self, zone_name: &str) -> Option<FixedTimespanSet> {
-        let mut builder = FixedTimespanSetBuilder::default();
+    fn timespans(&self, zone_name: &str, options: &TransitionOptions) -> Option<FixedTimespanSet> {
+        self.transitions_iter(zone_name, options).map(|iter| {
+            let first = iter.first().clone();
+
+            FixedTimespanSet {
+                first: first,
+                rest:  iter.collect(),
+            }
+        })
+    }
+
+    fn transitions_iter(&self, zone_name: &str, options: &TransitionOptions) -> Option<Transitions> {
+        self.get_zoneset(zone_name)
+            .map(|zoneset| Transitions::new(self, zoneset, *options))
+    }
 
-        let zoneset = match self.get_zoneset(zone_name) {
-            Some(zones) => zones,
+    fn transitions_between(&self, zone_name: &str, start: i64, end: i64, options: &TransitionOptions) -> Option<TransitionsBetween> {
+        self.transitions_iter(zone_name, options).map(|iter| {
+            TransitionsBetween {
+                transitions: iter,
+                start:       start,
+                end:         end,
+            }
+        })
+    }
+
+    fn next_transition(&self, zone_name: &str, timestamp: i64, options: &TransitionOptions) -> Option<(i64, FixedTimespan)> {
+        self.transitions_iter(zone_name, options)
+            .and_then(|mut iter| iter.find(|&(at, _)| at > timestamp))
+    }
+
+    fn offset_at(&self, zone_name: &str, timestamp: i64, options: &TransitionOptions) -> Option<FixedTimespan> {
+        self.transitions_iter(zone_name, options).map(|iter| {
+            let mut current = iter.first().clone();
+
+            for (at, timespan) in iter {
+                if at > timestamp {
+                    break;
+                }
+
+                current = timespan;
+            }
+
+            current
+        })
+    }
+
+    fn resolve_local(&self, zone_name: &str, local: LocalDateTime, options: &TransitionOptions) -> Option<LocalResolution> {
+        self.transitions_iter(zone_name, options).map(|iter| {
+            let first = iter.first().clone();
+            resolve(first, iter, local.to_instant().seconds())
+        })
+    }
+
+    fn rule_activations(&self, ruleset_name: &str, years: Range<i64>) -> Option<Vec<RuleActivation>> {
+        let rules = match self.rulesets.get(ruleset_name) {
+            Some(rules) => rules,
             None => return None,
         };
 
-        for (i, zone_info) in zoneset.iter().enumerate() {
-            let mut dst_offset = 0;
-            let use_until      = i != zoneset.len() - 1;
-            let utc_offset     = zone_info.offset;
+        let mut activations = Vec::new();
 
-            let mut insert_start_transition = i > 0;
-            let mut start_zone_id = None;
-            let mut start_utc_offset = zone_info.offset;
-            let mut start_dst_offset = 0;
+        for year in years {
+            let mut this_year = rules.iter()
+                .filter(|r| r.applies_to_year(year))
+                .map(|r| {
+                    let date = r.day.to_concrete_date(year, r.month.0);
 
-            match zone_info.saving {
-                Saving::NoSaving => {
-                    builder.add_fixed_saving(zone_info, 0, &mut dst_offset, utc_offset, &mut insert_start_transition, &mut start_zone_id);
-                },
+                    // Rules can take effect at 24:00, so add the time on
+                    // as a duration rather than constructing a LocalTime.
+                    RuleActivation {
+                        datetime:  LocalDateTime::new(date, LocalTime::midnight()) + Duration::of(r.time),
+                        time_type: r.time_type,
+                        save:      r.time_to_add,
+                        letters:   r.letters.clone(),
+                    }
+                })
+                .collect::<Vec<_>>();
 
-                Saving::OneOff(amount) => {
-                    builder.add_fixed_saving(zone_info, amount, &mut dst_offset, utc_offset, &mut insert_start_transition, &mut start_zone_id);
-                },
+            this_year.sort_by(|a, b| a.datetime.cmp(&b.datetime));
+            activations.extend(this_year);
+        }
 
-                Saving::Multiple(ref rules) => {
-                    let rules = &self.rulesets[&*rules];
-                    builder.add_multiple_saving(zone_info, &*rules, &mut dst_offset, use_until, utc_offset, &mut insert_start_transition, &mut start_zone_id, &mut start_utc_offset, &mut start_dst_offset);
-                }
+        Some(activations)
+    }
+}
+
+
+/// Resolves a local time, given as the number of seconds since the Unix
+/// epoch if it were in UTC, against a zone’s transitions.
+///
+/// Each timespan covers the instants from one transition up to the next,
+/// and the local time happens during a timespan if subtracting that
+/// timespan’s offset gives an instant it covers. If none of them do, then
+/// the local time must fall between the local times just before and just
+/// after some transition that moved the clocks forward. Transitions more
+/// than `YEAR_MARGIN` after the local time can’t make a difference, as no
+/// offset is that large, so the iterator doesn’t get run any further.
+fn resolve<I>(first: FixedTimespan, transitions: I, local: i64) -> LocalResolution
+where I: Iterator<Item=(i64, FixedTimespan)> {
+    let mut matches = Vec::new();
+    let mut gap = None;
+
+    let mut previous = first;
+    let mut start = i64::MIN;
+
+    for (at, next) in transitions {
+        if at > local.saturating_add(YEAR_MARGIN) {
+            break;
+        }
+
+        let instant = local - previous.total_offset();
+        if instant >= start && instant < at {
+            matches.push(previous.clone());
+        }
+
+        if local >= at + previous.total_offset() && local < at + next.total_offset() {
+            gap = Some((previous.clone(), next.clone()));
+        }
+
+        start = at;
+        previous = next;
+    }
+
+    // Whichever timespan comes last lasts until after the local time.
+    if local - previous.total_offset() >= start {
+        matches.push(previous);
+    }
+
+    let mut matches = matches.into_iter();
+    match (matches.next(), matches.last()) {
+        (Some(earlier), Some(later)) => LocalResolution::Ambiguous(earlier, later),
+        (Some(only), None)           => LocalResolution::Unique(only),
+        (None, _)                    => {
+            let (before, after) = gap.expect("local time in no timespan and no gap");
+            LocalResolution::Gap(before, after)
+        },
+    }
+}
+
+
+/// An iterator over the transitions in one time zone, in order, computing
+/// them as they’re needed. Returned by `transitions_iter`.
+///
+/// Each item is the instant a transition happens, as a Unix timestamp, and
+/// the timespan that comes into effect at that instant. These are the same
+/// as the `rest` field of the `FixedTimespanSet` that `timespans` returns.
+#[derive(Debug)]
+pub struct Transitions<'table> {
+    generator: Generator<'table>,
+    optimiser: Optimiser,
+    first: FixedTimespan,
+}
+
+impl<'table> Transitions<'table> {
+    fn new(table: &'table Table, zoneset: &'table [ZoneInfo], options: TransitionOptions) -> Transitions<'table> {
+        let mut generator = Generator::new(table, zoneset, options);
+        let first = generator.first();
+
+        Transitions {
+            generator: generator,
+            optimiser: Optimiser::new(first.clone(), options.dedup),
+            first:     first,
+        }
+    }
+
+    /// The timespan in effect before the first transition.
+    pub fn first(&self) -> &FixedTimespan {
+        &self.first
+    }
+}
+
+impl<'table> Iterator for Transitions<'table> {
+    type Item = (i64, FixedTimespan);
+
+    fn next(&mut self) -> Option<(i64, FixedTimespan)> {
+        loop {
+            if let Some(transition) = self.optimiser.pop() {
+                return Some(transition);
             }
 
-            if insert_start_transition && start_zone_id.is_some() {
-                let t = (builder.start_time.expect("Start time"), FixedTimespan {
-                    utc_offset: start_utc_offset,
-                    dst_offset: start_dst_offset,
-                    name:       start_zone_id.clone().expect("Start zone ID"),
-                });
-                builder.rest.push(t);
+            match self.generator.next() {
+                Some(transition) => self.optimiser.push(transition),
+                None => {
+                    self.optimiser.finish();
+                    return self.optimiser.pop();
+                },
             }
+        }
+    }
+}
 
-            if use_until {
-                builder.start_time = Some(zone_info.end_time.expect("End time").to_timestamp() - utc_offset - dst_offset);
+
+/// An iterator over the transitions of a zone within a range of instants,
+/// returned by `transitions_between`.
+#[derive(Debug)]
+pub struct TransitionsBetween<'table> {
+    transitions: Transitions<'table>,
+    start: i64,
+    end: i64,
+}
+
+impl<'table> Iterator for TransitionsBetween<'table> {
+    type Item = (i64, FixedTimespan);
+
+    fn next(&mut self) -> Option<(i64, FixedTimespan)> {
+        for (at, timespan) in &mut self.transitions {
+            if at >= self.end {
+                return None;
+            }
+            else if at >= self.start {
+                return Some((at, timespan));
             }
         }
 
-        Some(builder.build())
+        None
     }
 }
 
+
+/// The state kept while expanding one zone line.
 #[derive(Debug, Default)]
-struct FixedTimespanSetBuilder {
-    first: Option<FixedTimespan>,
-    rest: Vec<(i64, FixedTimespan)>,
+struct LineState {
+    dst_offset: i64,
+    is_dst: bool,
+    insert_start_transition: bool,
+    start_zone_id: Option<String>,
+    start_utc_offset: i64,
+    start_dst_offset: i64,
+    start_is_dst: bool,
+}
+
+/// Generates the transitions for a zoneset a piece at a time: a whole line
+/// with a fixed saving, or one year of a line that uses rules.
+///
+/// The transitions for each piece don’t come out in order, so they get held
+/// in `pending` until nothing generated later could come before them. As
+/// the lines of a zoneset are in order, nothing after a line can come
+/// before that line’s end; and as rules are expanded one year at a time,
+/// nothing in later years can come before the start of the next year, give
+/// or take the largest possible UTC offset.
+#[derive(Debug)]
+struct Generator<'table> {
+    table: &'table Table,
+    zoneset: &'table [ZoneInfo],
+    options: TransitionOptions,
 
+    /// The index of the zone line being expanded.
+    index: usize,
+
+    /// The next year to expand rules in, or `None` if the line at `index`
+    /// hasn’t been started yet.
+    year: Option<i64>,
+    line: LineState,
+
+    first: Option<FixedTimespan>,
     start_time: Option<i64>,
     until_time: Option<i64>,
+
+    /// Transitions that have been generated, but could still have others
+    /// come before them.
+    pending: Vec<(i64, FixedTimespan)>,
+
+    /// Transitions that are known to be in order.
+    released: VecDeque<(i64, FixedTimespan)>,
 }
 
-impl FixedTimespanSetBuilder {
-    fn add_fixed_saving(&mut self, timespan: &ZoneInfo, amount: i64,
-            dst_offset: &mut i64, utc_offset: i64, insert_start_transition: &mut bool,
-            start_zone_id: &mut Option<String>)
-    {
-        *dst_offset = amount;
-        *start_zone_id = Some(timespan.format.format(*dst_offset, None));
+/// A margin wider than any UTC offset, including daylight-saving time, so
+/// that no rule in a year can take effect more than this long before the
+/// year begins in UTC.
+const YEAR_MARGIN: i64 = 2 * 24 * 60 * 60;
 
-        if *insert_start_transition {
-            let time = self.start_time.unwrap();
-            let timespan = FixedTimespan {
-                utc_offset: timespan.offset,
-                dst_offset: *dst_offset,
-                name:       start_zone_id.clone().unwrap_or("".to_owned()),
+impl<'table> Generator<'table> {
+    fn new(table: &'table Table, zoneset: &'table [ZoneInfo], options: TransitionOptions) -> Generator<'table> {
+        Generator {
+            table:       table,
+            zoneset:     zoneset,
+            options:     options,
+            index:       0,
+            year:        None,
+            line:        LineState::default(),
+            first:       None,
+            start_time:  None,
+            until_time:  None,
+            pending:     Vec::new(),
+            released:    VecDeque::new(),
+        }
+    }
+
+    /// Returns the timespan in effect before the first transition, which is
+    /// the first line’s if it has a fixed saving, or the first timespan
+    /// without daylight-saving time otherwise.
+    fn first(&mut self) -> FixedTimespan {
+        loop {
+            if let Some(ref first) = self.first {
+                return first.clone();
+            }
+
+            if let Some(t) = self.released.iter().find(|t| !t.1.is_dst) {
+                return t.1.clone();
+            }
+
+            if self.index >= self.zoneset.len() {
+                panic!("No timespan without daylight-saving time");
+            }
+
+            let _ = self.step();
+        }
+    }
+
+    /// Returns the next transition in order, generating more if necessary.
+    fn next(&mut self) -> Option<(i64, FixedTimespan)> {
+        loop {
+            if let Some(t) = self.released.pop_front() {
+                return Some(t);
+            }
+
+            if !self.step() {
+                return None;
+            }
+        }
+    }
+
+    /// Generates the transitions for the next piece of the zoneset, then
+    /// releases the ones that are known to be in order. Returns `false` if
+    /// there was nothing left to generate.
+    fn step(&mut self) -> bool {
+        let zoneset = self.zoneset;
+        let zone_info = match zoneset.get(self.index) {
+            Some(z) => z,
+            None    => return false,
+        };
+
+        let use_until = self.index != zoneset.len() - 1;
+
+        if self.year.is_none() {
+            self.line = LineState {
+                insert_start_transition: self.index > 0,
+                start_utc_offset: zone_info.offset,
+                .. LineState::default()
             };
+            self.year = Some(self.options.min_year);
+        }
+
+        let watermark = match zone_info.saving {
+            Saving::NoSaving => {
+                self.add_fixed_saving(zone_info, 0);
+                self.finish_line(zone_info, use_until)
+            },
 
-            self.rest.push((time, timespan));
-            *insert_start_transition = false;
+            Saving::OneOff(amount) => {
+                self.add_fixed_saving(zone_info, amount);
+                self.finish_line(zone_info, use_until)
+            },
+
+            Saving::Multiple(ref rules) => {
+                let table = self.table;
+                let rules = &table.rulesets[&*rules];
+                let year = self.year.unwrap();
+
+                if year > self.options.max_year || (use_until && year > LocalDateTime::at(zone_info.end_time.unwrap().to_timestamp()).year()) {
+                    self.finish_line(zone_info, use_until)
+                }
+                else {
+                    self.add_multiple_saving(zone_info, rules, year, use_until);
+                    self.year = Some(year + 1);
+                    self.year_watermark(year + 1)
+                }
+            },
+        };
+
+        self.release(watermark);
+        true
+    }
+
+    /// Moves the pending transitions before the watermark into the released
+    /// queue, in order. The sort is stable, so transitions at the same
+    /// instant stay in the order they were generated.
+    fn release(&mut self, watermark: i64) {
+        self.pending.sort_by(|a, b| a.0.cmp(&b.0));
+        let count = self.pending.iter().take_while(|t| t.0 < watermark).count();
+        self.released.extend(self.pending.drain(..count));
+    }
+
+    /// Adds the transition at the start of the current line, if it needs
+    /// one, and moves on to the next line. Returns the instant nothing
+    /// generated afterwards can come before: the start of the next line.
+    fn finish_line(&mut self, zone_info: &ZoneInfo, use_until: bool) -> i64 {
+        if self.line.insert_start_transition && self.line.start_zone_id.is_some() {
+            self.push_start_transition();
+        }
+
+        self.index += 1;
+        self.year = None;
+
+        if use_until {
+            let start_time = zone_info.end_time.expect("End time").to_timestamp() - zone_info.offset - self.line.dst_offset;
+            self.start_time = Some(start_time);
+            start_time
         }
         else {
-            self.first = Some(FixedTimespan {
-                utc_offset: utc_offset,
-                dst_offset: *dst_offset,
-                name:       start_zone_id.clone().unwrap_or("".to_owned()),
-            });
+            i64::MAX
         }
     }
 
-    #[allow(unused_results)]
-    fn add_multiple_saving(&mut self, timespan: &ZoneInfo, rules: &[RuleInfo],
-            dst_offset: &mut i64, use_until: bool, utc_offset: i64, insert_start_transition: &mut bool,
-            start_zone_id: &mut Option<String>, start_utc_offset: &mut i64, start_dst_offset: &mut i64)
-    {
-        use std::mem::replace;
-        use datetime::DatePiece;
-
-        for year in 1800..2100 {
-            if use_until && year > LocalDateTime::at(timespan.end_time.unwrap().to_timestamp()).year() {
-                break;
+    /// Returns the instant nothing generated from the given year onwards can
+    /// come before, adding the start transition early if it’s been decided.
+    fn year_watermark(&mut self, next_year: i64) -> i64 {
+        let year_start = LocalDateTime::new(LocalDate::ymd(next_year, Month::January, 1).unwrap(), LocalTime::midnight());
+        let watermark = year_start.to_instant().seconds() - YEAR_MARGIN;
+
+        if self.line.insert_start_transition {
+            let start_time = self.start_time.expect("Start time");
+
+            // Rules after the start of the line can’t change the start
+            // transition, so it can be added without waiting for the
+            // whole line to be expanded.
+            if start_time < watermark && self.line.start_zone_id.is_some() {
+                self.push_start_transition();
+                self.line.insert_start_transition = false;
+            }
+            else {
+                return min(watermark, start_time);
             }
+        }
 
-            let mut activated_rules = rules.iter()
-                                           .filter(|r| r.applies_to_year(year))
-                                           .collect::<Vec<_>>();
+        watermark
+    }
 
-            loop {
-                if use_until {
-                    self.until_time = Some(timespan.end_time.unwrap().to_timestamp() - utc_offset - *dst_offset);
-                }
+    fn push_start_transition(&mut self) {
+        let t = (self.start_time.expect("Start time"), FixedTimespan {
+            utc_offset: self.line.start_utc_offset,
+            dst_offset: self.line.start_dst_offset,
+            is_dst:     self.line.start_is_dst,
+            name:       self.line.start_zone_id.clone().expect("Start zone ID"),
+        });
+        self.pending.push(t);
+    }
 
-                // Find the minimum rule based on the current UTC and DST offsets.
-                // (this can be replaced with min_by when it stabilises):
-                //.min_by(|r| r.1.absolute_datetime(year, utc_offset, dst_offset));
-                let pos = {
-                    let earliest = activated_rules.iter().enumerate()
-                        .map(|(i, r)| (r.absolute_datetime(year, utc_offset, *dst_offset), i))
-                        .min()
-                        .map(|(_, i)| i);
-
-                    match earliest {
-                        Some(p) => p,
-                        None    => break,
-                    }
-                };
+    fn add_fixed_saving(&mut self, timespan: &ZoneInfo, amount: i64) {
+        self.line.dst_offset = amount;
+        self.line.is_dst = amount != 0;
+        self.line.start_zone_id = Some(timespan.format.format(timespan.offset + amount, amount != 0, None));
 
-                let earliest_rule = activated_rules.remove(pos);
-                let earliest_at = earliest_rule.absolute_datetime(year, utc_offset, *dst_offset).to_instant().seconds();
+        let fixed = FixedTimespan {
+            utc_offset: timespan.offset,
+            dst_offset: amount,
+            is_dst:     amount != 0,
+            name:       self.line.start_zone_id.clone().unwrap_or("".to_owned()),
+        };
 
-                if use_until && earliest_at >= self.until_time.unwrap() {
-                    break;
-                }
+        if self.line.insert_start_transition {
+            let time = self.start_time.unwrap();
+            self.pending.push((time, fixed));
+            self.line.insert_start_transition = false;
+        }
+        else {
+            self.first = Some(fixed);
+        }
+    }
+
+    fn add_multiple_saving(&mut self, timespan: &ZoneInfo, rules: &[RuleInfo], year: i64, use_until: bool) {
+        let utc_offset = timespan.offset;
+
+        let mut activated_rules = rules.iter()
+                                       .filter(|r| r.applies_to_year(year))
+                                       .collect::<Vec<_>>();
+
+        loop {
+            if use_until {
+                self.until_time = Some(timespan.end_time.unwrap().to_timestamp() - utc_offset - self.line.dst_offset);
+            }
 
-                *dst_offset = earliest_rule.time_to_add;
+            // Find the minimum rule based on the current UTC and DST offsets.
+            // (this can be replaced with min_by when it stabilises):
+            //.min_by(|r| r.1.absolute_datetime(year, utc_offset, dst_offset));
+            let pos = {
+                let dst_offset = self.line.dst_offset;
+                let earliest = activated_rules.iter().enumerate()
+                    .map(|(i, r)| (r.absolute_datetime(year, utc_offset, dst_offset), i))
+                    .min()
+                    .map(|(_, i)| i);
 
-                if *insert_start_transition && earliest_at == self.start_time.unwrap() {
-                    *insert_start_transition = false;
+                match earliest {
+                    Some(p) => p,
+                    None    => break,
                 }
+            };
 
-                if *insert_start_transition {
-                    if earliest_at < self.start_time.unwrap() {
-                        replace(start_utc_offset, timespan.offset);
-                        replace(start_dst_offset, *dst_offset);
-                        replace(start_zone_id, Some(timespan.format.format(*dst_offset, earliest_rule.letters.as_ref())));
-                        continue;
-                    }
+            let earliest_rule = activated_rules.remove(pos);
+            let earliest_at = earliest_rule.absolute_datetime(year, utc_offset, self.line.dst_offset).to_instant().seconds();
 
-                    if start_zone_id.is_none() && *start_utc_offset + *start_dst_offset == timespan.offset + *dst_offset {
-                        replace(start_zone_id, Some(timespan.format.format(*dst_offset, earliest_rule.letters.as_ref())));
-                    }
+            if use_until && earliest_at >= self.until_time.unwrap() {
+                break;
+            }
+
+            self.line.dst_offset = earliest_rule.time_to_add;
+            self.line.is_dst = earliest_rule.is_daylight_saving();
+
+            if self.line.insert_start_transition && earliest_at == self.start_time.unwrap() {
+                self.line.insert_start_transition = false;
+            }
+
+            if self.line.insert_start_transition {
+                if earliest_at < self.start_time.unwrap() {
+                    self.line.start_utc_offset = timespan.offset;
+                    self.line.start_dst_offset = self.line.dst_offset;
+                    self.line.start_is_dst = self.line.is_dst;
+                    self.line.start_zone_id = Some(timespan.format.format(timespan.offset + self.line.dst_offset, self.line.is_dst, earliest_rule.letters.as_ref().map(|l| &**l)));
+                    continue;
                 }
 
-                let t = (earliest_at, FixedTimespan {
-                    utc_offset: timespan.offset,
-                    dst_offset: earliest_rule.time_to_add,
-                    name:       timespan.format.format(earliest_rule.time_to_add, earliest_rule.letters.as_ref()),
-                });
-                self.rest.push(t);
+                if self.line.start_zone_id.is_none() && self.line.start_utc_offset + self.line.start_dst_offset == timespan.offset + self.line.dst_offset {
+                    self.line.start_is_dst = self.line.is_dst;
+                    self.line.start_zone_id = Some(timespan.format.format(timespan.offset + self.line.dst_offset, self.line.is_dst, earliest_rule.letters.as_ref().map(|l| &**l)));
+                }
             }
+
+            let t = (earliest_at, FixedTimespan {
+                utc_offset: timespan.offset,
+                dst_offset: earliest_rule.time_to_add,
+                is_dst:     self.line.is_dst,
+                name:       timespan.format.format(timespan.offset + earliest_rule.time_to_add, self.line.is_dst, earliest_rule.letters.as_ref().map(|l| &**l)),
+            });
+            self.pending.push(t);
         }
+    }
+}
+
+
+/// Removes redundant transitions from a stream of them, as they come in.
+///
+/// A transition is redundant if it changes to the same timespan that was
+/// already in effect, or if it happens so soon after the previous one that
+/// the local time of the previous one is never reached, in which case the
+/// previous one takes on its timespan. Because of this, the last two
+/// transitions can still change, so they’re held back until more arrive.
+///
+/// If `dedup` is set, transitions that don’t change the total offset, DST
+/// flag, or abbreviation from the last one emitted get removed as well.
+#[derive(Debug)]
+struct Optimiser {
+    first: FixedTimespan,
+    window: VecDeque<(i64, FixedTimespan)>,
+    ready: VecDeque<(i64, FixedTimespan)>,
+    emitted_any: bool,
+    dedup: bool,
+    last_emitted: FixedTimespan,
+}
 
+impl Optimiser {
+    fn new(first: FixedTimespan, dedup: bool) -> Optimiser {
+        Optimiser {
+            last_emitted: first.clone(),
+            first:        first,
+            window:       VecDeque::new(),
+            ready:        VecDeque::new(),
+            emitted_any:  false,
+            dedup:        dedup,
+        }
     }
 
-    fn build(mut self) -> FixedTimespanSet {
-        self.rest.sort_by(|a, b| a.0.cmp(&b.0));
+    fn push(&mut self, transition: (i64, FixedTimespan)) {
+        if self.window.len() > 1 {
+            let from = transition.0;
+            let to = self.window[1].0;
+            if from + self.window[1].1.total_offset() <= to + self.window[0].1.total_offset() {
+                self.window[1].1 = transition.1;
+                return;
+            }
+        }
 
-        let first = match self.first {
-            Some(ft) => ft,
-            None     => self.rest.iter().find(|t| t.1.dst_offset == 0).unwrap().1.clone(),
+        let differs = match self.window.back() {
+            Some(last) => last.1 != transition.1,
+            None       => true,
         };
 
-        let mut zoneset = FixedTimespanSet {
-            first: first,
-            rest:  self.rest,
-        };
-        optimise(&mut zoneset);
-        zoneset
+        if differs {
+            if self.window.len() > 1 {
+                let oldest = self.window.pop_front().unwrap();
+                self.emit(oldest);
+            }
+
+            self.window.push_back(transition);
+        }
     }
-}
 
-#[allow(unused_results)]  // for remove
-fn optimise(transitions: &mut FixedTimespanSet) {
-    let mut from_i = 0;
-    let mut to_i = 0;
-
-    while from_i < transitions.rest.len() {
-        if to_i > 1 {
-            let from = transitions.rest[from_i].0;
-            let to = transitions.rest[to_i - 1].0;
-            if from + transitions.rest[to_i - 1].1.total_offset() <= to + transitions.rest[to_i - 2].1.total_offset() {
-                transitions.rest[to_i - 1].1 = transitions.rest[from_i].1.clone();
-                from_i += 1;
-                continue;
+    /// Emits the transitions that were being held back, as no more are
+    /// coming.
+    fn finish(&mut self) {
+        while let Some(transition) = self.window.pop_front() {
+            self.emit(transition);
+        }
+    }
+
+    fn emit(&mut self, transition: (i64, FixedTimespan)) {
+        // The very first transition is unnecessary if it doesn’t change
+        // anything from the initial timespan.
+        if !self.emitted_any {
+            self.emitted_any = true;
+            if transition.1 == self.first {
+                return;
             }
         }
 
-        if to_i == 0 || transitions.rest[to_i - 1].1 != transitions.rest[from_i].1 {
-            transitions.rest[to_i] = transitions.rest[from_i].clone();
-            to_i += 1;
+        if self.dedup && transition.1.looks_the_same_as(&self.last_emitted) {
+            return;
         }
 
-        from_i += 1
+        self.last_emitted = transition.1.clone();
+        self.ready.push_back(transition);
     }
 
-    transitions.rest.truncate(to_i);
+    fn pop(&mut self) -> Option<(i64, FixedTimespan)> {
+        self.ready.pop_front()
+    }
+}
 
-    if !transitions.rest.is_empty() && transitions.first == transitions.rest[0].1 {
-        transitions.rest.remove(0);
+/// Removes redundant transitions from a complete timespan set.
+#[cfg(test)]
+fn optimise(transitions: &mut FixedTimespanSet) {
+    let mut optimiser = Optimiser::new(transitions.first.clone(), false);
+    for transition in transitions.rest.drain(..) {
+        optimiser.push(transition);
+    }
+
+    optimiser.finish();
+    transitions.rest = optimiser.ready.into_iter().collect();
+}
+
+/// Europe/London around the clocks going forward and back in 2024, for
+/// tests that need a zone with a transition each way.
+#[cfg(test)]
+pub fn london_2024() -> FixedTimespanSet {
+    FixedTimespanSet {
+        first: FixedTimespan::new(0, 0, "GMT"),
+        rest:  vec![
+            (1_711_846_800, FixedTimespan::new(0, 3600, "BST")),
+            (1_729_990_800, FixedTimespan::new(0, 0, "GMT")),
+        ],
     }
 }
 
@@ -389,20 +1035,20 @@ mod test {
     #[allow(unused_results)]
     fn optimise_macquarie() {
         let mut transitions = FixedTimespanSet {
-            first: FixedTimespan { utc_offset:     0, dst_offset:    0, name:  "zzz".to_owned() },
+            first: FixedTimespan { utc_offset:     0, dst_offset:    0, is_dst: false, name:  "zzz".to_owned() },
             rest: vec![
-                (-2_214_259_200, FixedTimespan { utc_offset: 36000,  dst_offset:    0,  name: "AEST".to_owned() }),
-                (-1_680_508_800, FixedTimespan { utc_offset: 36000,  dst_offset: 3600,  name: "AEDT".to_owned() }),
-                (-1_669_892_400, FixedTimespan { utc_offset: 36000,  dst_offset: 3600,  name: "AEDT".to_owned() }),  // gets removed
-                (-1_665_392_400, FixedTimespan { utc_offset: 36000,  dst_offset:    0,  name: "AEST".to_owned() }),
-                (-1_601_719_200, FixedTimespan { utc_offset:     0,  dst_offset:    0,  name:  "zzz".to_owned() }),
-                (  -687_052_800, FixedTimespan { utc_offset: 36000,  dst_offset:    0,  name: "AEST".to_owned() }),
-                (   -94_730_400, FixedTimespan { utc_offset: 36000,  dst_offset:    0,  name: "AEST".to_owned() }),  // also gets removed
-                (   -71_136_000, FixedTimespan { utc_offset: 36000,  dst_offset: 3600,  name: "AEDT".to_owned() }),
-                (   -55_411_200, FixedTimespan { utc_offset: 36000,  dst_offset:    0,  name: "AEST".to_owned() }),
-                (   -37_267_200, FixedTimespan { utc_offset: 36000,  dst_offset: 3600,  name: "AEDT".to_owned() }),
-                (   -25_776_000, FixedTimespan { utc_offset: 36000,  dst_offset:    0,  name: "AEST".to_owned() }),
-                (    -5_817_600, FixedTimespan { utc_offset: 36000,  dst_offset: 3600,  name: "AEDT".to_owned() }),
+                (-2_214_259_200, FixedTimespan { utc_offset: 36000,  dst_offset:    0,  is_dst: false, name: "AEST".to_owned() }),
+                (-1_680_508_800, FixedTimespan { utc_offset: 36000,  dst_offset: 3600,  is_dst:  true, name: "AEDT".to_owned() }),
+                (-1_669_892_400, FixedTimespan { utc_offset: 36000,  dst_offset: 3600,  is_dst:  true, name: "AEDT".to_owned() }),  // gets removed
+                (-1_665_392_400, FixedTimespan { utc_offset: 36000,  dst_offset:    0,  is_dst: false, name: "AEST".to_owned() }),
+                (-1_601_719_200, FixedTimespan { utc_offset:     0,  dst_offset:    0,  is_dst: false, name:  "zzz".to_owned() }),
+                (  -687_052_800, FixedTimespan { utc_offset: 36000,  dst_offset:    0,  is_dst: false, name: "AEST".to_owned() }),
+                (   -94_730_400, FixedTimespan { utc_offset: 36000,  dst_offset:    0,  is_dst: false, name: "AEST".to_owned() }),  // also gets removed
+                (   -71_136_000, FixedTimespan { utc_offset: 36000,  dst_offset: 3600,  is_dst:  true, name: "AEDT".to_owned() }),
+                (   -55_411_200, FixedTimespan { utc_offset: 36000,  dst_offset:    0,  is_dst: false, name: "AEST".to_owned() }),
+                (   -37_267_200, FixedTimespan { utc_offset: 36000,  dst_offset: 3600,  is_dst:  true, name: "AEDT".to_owned() }),
+                (   -25_776_000, FixedTimespan { utc_offset: 36000,  dst_offset:    0,  is_dst: false, name: "AEST".to_owned() }),
+                (    -5_817_600, FixedTimespan { utc_offset: 36000,  dst_offset: 3600,  is_dst:  true, name: "AEDT".to_owned() }),
             ],
         };
 
@@ -413,4 +1059,90 @@ mod test {
         optimise(&mut transitions);
         assert_eq!(transitions, result);
     }
+
+    #[test]
+    fn truncate() {
+        let timespan = |name: &str| FixedTimespan { utc_offset: 0, dst_offset: 0, is_dst: false, name: name.to_owned() };
+        let mut set = FixedTimespanSet {
+            first: timespan("LMT"),
+            rest:  vec![ (-100, timespan("A")), (0, timespan("B")), (100, timespan("C")), (200, timespan("D")) ],
+        };
+
+        set.truncate(0, 200);
+        assert_eq!(set, FixedTimespanSet { first: timespan("B"), rest: vec![ (100, timespan("C")) ] });
+
+        set.truncate(-1000, 1000);
+        assert_eq!(set, FixedTimespanSet { first: timespan("B"), rest: vec![ (100, timespan("C")) ] });
+    }
+
+    #[test]
+    #[allow(unused_results)]
+    fn iterator_is_lazy() {
+        use line::{YearSpec, MonthSpec, DaySpec};
+        use table::{RuleInfo, ZoneInfo, Saving, Format};
+        use datetime::Month::{March, October};
+
+        let rule = |month, save| RuleInfo {
+            from_year:   YearSpec::Number(1980),
+            to_year:     Some(YearSpec::Maximum),
+            month:       MonthSpec(month),
+            day:         DaySpec::Ordinal(1),
+            time:        0,
+            time_type:   TimeType::UTC,
+            time_to_add: save,
+            is_dst:      None,
+            letters:     None,
+        };
+
+        let mut table = Table::default();
+        table.rulesets.insert("Test".to_owned(), vec![ rule(March, 3600), rule(October, 0) ]);
+        table.zonesets.insert("Test/Zone".to_owned(), vec![ ZoneInfo {
+            offset:    0,
+            saving:    Saving::Multiple("Test".to_owned()),
+            format:    Format::new("TEST"),
+            end_time:  None,
+        } ]);
+
+        let mut iter = table.transitions_iter("Test/Zone", &TransitionOptions::default()).unwrap();
+        assert_eq!(iter.first().dst_offset, 0);
+        assert_eq!(iter.next().map(|t| t.1.dst_offset), Some(3600));
+        assert_eq!(iter.next().map(|t| t.1.dst_offset), Some(0));
+
+        // Only the first few years should have been expanded.
+        assert!(iter.generator.year.unwrap() < 1990);
+    }
+
+    #[test]
+    fn save_suffix() {
+        use parse::parse_table;
+
+        let table = parse_table("\
+            Rule  Test  1980  max  -  Mar  1  0:00u  0d     D\n\
+            Rule  Test  1980  max  -  Oct  1  0:00u  1:00s  S\n\
+            Zone  Test/Zone  0:00  Test  X%sT\n").unwrap();
+
+        let set = table.timespans("Test/Zone", &TransitionOptions { max_year: 1981, .. TransitionOptions::default() }).unwrap();
+        assert_eq!(set.rest[0].1, FixedTimespan { utc_offset: 0, dst_offset: 0,    is_dst: true,  name: "XDT".to_owned() });
+        assert_eq!(set.rest[1].1, FixedTimespan { utc_offset: 0, dst_offset: 3600, is_dst: false, name: "XST".to_owned() });
+    }
+
+    #[test]
+    fn distinct_timespans() {
+        use std::collections::{BTreeSet, HashSet};
+        use parse::parse_table;
+
+        let table = parse_table("\
+            Rule  Test  1980  max  -  Mar  1  0:00u  1:00  D\n\
+            Rule  Test  1980  max  -  Oct  1  0:00u  0     S\n\
+            Zone  Test/Zone  0:00  Test  X%sT\n").unwrap();
+
+        // Two years of transitions only have two different timespans, which
+        // come out in order of their offsets.
+        let set = table.timespans("Test/Zone", &TransitionOptions { max_year: 1981, .. TransitionOptions::default() }).unwrap();
+        let distinct: BTreeSet<&FixedTimespan> = set.rest.iter().map(|t| &t.1).collect();
+        assert_eq!(distinct.into_iter().map(|t| &*t.name).collect::<Vec<_>>(), vec![ "XST", "XDT" ]);
+
+        let sets: HashSet<FixedTimespanSet> = vec![ set.clone(), set ].into_iter().collect();
+        assert_eq!(sets.len(), 1);
+    }
 }
@@ -0,0 +1,254 @@
+//! Checking a built Table for mistakes.
+//!
+//! A `TableBuilder` catches the problems it can while lines are being added
+//! one at a time, such as duplicate zones or continuation lines in the wrong
+//! place. But some problems can only be seen once the whole table has been
+//! assembled, such as a rule set that nothing uses, or a link to a zone that
+//! was never defined. Tables can also be constructed by hand, skipping the
+//! builder’s checks entirely.
+//!
+//! The `validate` method goes through a table and returns every problem it
+//! finds, rather than stopping at the first one, so they can all be fixed in
+//! one go.
+//!
+//! ## Example
+//!
+//! ```
+//! use zoneinfo_parse::line::Link;
+//! use zoneinfo_parse::table::TableBuilder;
+//! use zoneinfo_parse::validation::{TableValidation, Problem};
+//!
+//! let mut builder = TableBuilder::new();
+//! builder.add_link_line(Link::from_str("Link  Nowhere/Zone  Nowhere/Link").unwrap()).unwrap();
+//! let table = builder.build();
+//!
+//! assert_eq!(table.validate(), vec![
+//!     Problem::DanglingLink { link: "Nowhere/Link".to_owned(), target: "Nowhere/Zone".to_owned() },
+//! ]);
+//! ```
+
+use std::collections::HashSet;
+use std::fmt;
+
+use line::{ChangeTime, YearSpec};
+use table::{Table, Saving};
+
+
+/// Trait to put the `validate` method on Tables.
+pub trait TableValidation {
+
+    /// Checks this table for problems, returning all of them in a sorted
+    /// order. An empty vector means the table is fine.
+    fn validate(&self) -> Vec<Problem>;
+}
+
+impl TableValidation for Table {
+    fn validate(&self) -> Vec<Problem> {
+        let mut problems = Vec::new();
+        let mut used_rulesets = HashSet::new();
+
+        for (zone_name, zoneset) in &self.zonesets {
+            let mut previous_until = None;
+
+            for (index, zone_info) in zoneset.iter().enumerate() {
+                if let Saving::Multiple(ref ruleset_name) = zone_info.saving {
+                    let _ = used_rulesets.insert(&**ruleset_name);
+
+                    if !self.rulesets.contains_key(ruleset_name) {
+                        problems.push(Problem::UnknownRuleset {
+                            zone:     zone_name.clone(),
+                            ruleset:  ruleset_name.clone(),
+                        });
+                    }
+                }
+
+                let is_last = index == zoneset.len() - 1;
+                match zone_info.end_time {
+                    None if !is_last => {
+                        problems.push(Problem::MissingUntil {
+                            zone:  zone_name.clone(),
+                            index: index,
+                        });
+                    },
+
+                    Some(until) => {
+                        if let Some(timestamp) = comparable_timestamp(until) {
+                            if previous_until.map_or(false, |previous| timestamp <= previous) {
+                                problems.push(Problem::UntilOutOfOrder {
+                                    zone:  zone_name.clone(),
+                                    index: index,
+                                });
+                            }
+
+                            previous_until = Some(timestamp);
+                        }
+                    },
+
+                    None => {},
+                }
+            }
+        }
+
+        for ruleset_name in self.rulesets.keys() {
+            if !used_rulesets.contains(&**ruleset_name) {
+                problems.push(Problem::UnusedRuleset(ruleset_name.clone()));
+            }
+        }
+
+        for (link_name, target) in &self.links {
+            if !self.zonesets.contains_key(target) {
+                problems.push(Problem::DanglingLink {
+                    link:   link_name.clone(),
+                    target: target.clone(),
+                });
+            }
+        }
+
+        problems.sort();
+        problems
+    }
+}
+
+/// Returns the timestamp of the given change time, or `None` if it uses a
+/// year such as `max` that can’t be turned into one.
+fn comparable_timestamp(time: ChangeTime) -> Option<i64> {
+    let year = match time {
+        ChangeTime::UntilYear(y)          => y,
+        ChangeTime::UntilMonth(y, ..)     => y,
+        ChangeTime::UntilDay(y, ..)       => y,
+        ChangeTime::UntilTime(y, ..)      => y,
+    };
+
+    match year {
+        YearSpec::Number(_) => Some(time.to_timestamp()),
+        _                   => None,
+    }
+}
+
+
+/// A problem found while validating a table.
+#[derive(PartialEq, Eq, PartialOrd, Ord, Debug, Clone)]
+pub enum Problem {
+
+    /// A zone refers to a ruleset that isn’t in the table.
+    UnknownRuleset {
+
+        /// The name of the zone with the reference.
+        zone: String,
+
+        /// The name of the ruleset it refers to.
+        ruleset: String,
+    },
+
+    /// A zone line that isn’t the last in its zoneset has no ‘until’ time,
+    /// so the lines after it could never come into effect.
+    MissingUntil {
+
+        /// The name of the zone.
+        zone: String,
+
+        /// The index of the line within the zoneset, starting at zero.
+        index: usize,
+    },
+
+    /// A zone line’s ‘until’ time is no later than the one before it, so
+    /// the two lines overlap or are in the wrong order.
+    UntilOutOfOrder {
+
+        /// The name of the zone.
+        zone: String,
+
+        /// The index of the out-of-order line within the zoneset, starting
+        /// at zero.
+        index: usize,
+    },
+
+    /// A ruleset is defined, but no zone uses it.
+    UnusedRuleset(String),
+
+    /// A link points to a zone that isn’t in the table.
+    DanglingLink {
+
+        /// The name of the link.
+        link: String,
+
+        /// The name of the zone it points to.
+        target: String,
+    },
+}
+
+impl fmt::Display for Problem {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Problem::UnknownRuleset { ref zone, ref ruleset } => write!(f, "zone {} uses unknown ruleset {}", zone, ruleset),
+            Problem::MissingUntil { ref zone, index }         => write!(f, "zone {} line {} has no until time but is not the last line", zone, index + 1),
+            Problem::UntilOutOfOrder { ref zone, index }      => write!(f, "zone {} line {} has an until time no later than the line before it", zone, index + 1),
+            Problem::UnusedRuleset(ref name)                  => write!(f, "ruleset {} is never used", name),
+            Problem::DanglingLink { ref link, ref target }    => write!(f, "link {} points to nonexistent zone {}", link, target),
+        }
+    }
+}
+
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use line::{ChangeTime, YearSpec};
+    use table::{Table, ZoneInfo, RuleInfo, Saving, Format};
+
+    fn zone_info(saving: Saving, until: Option<i64>) -> ZoneInfo {
+        ZoneInfo {
+            offset:    0,
+            saving:    saving,
+            format:    Format::new("TEST"),
+            end_time:  until.map(|y| ChangeTime::UntilYear(YearSpec::Number(y))),
+        }
+    }
+
+    #[test]
+    fn empty() {
+        assert_eq!(Table::default().validate(), vec![]);
+    }
+
+    #[test]
+    fn unknown_and_unused_rulesets() {
+        use line::{MonthSpec, DaySpec};
+        use datetime::Month;
+        use datetime::zone::TimeType;
+
+        let mut table = Table::default();
+        let _ = table.zonesets.insert("Test/Zone".to_owned(), vec![ zone_info(Saving::Multiple("Missing".to_owned()), None) ]);
+        let _ = table.rulesets.insert("Unused".to_owned(), vec![ RuleInfo {
+            from_year:   YearSpec::Number(1980),
+            to_year:     None,
+            month:       MonthSpec(Month::January),
+            day:         DaySpec::Ordinal(1),
+            time:        0,
+            time_type:   TimeType::Wall,
+            time_to_add: 0,
+            is_dst:      None,
+            letters:     None,
+        } ]);
+
+        assert_eq!(table.validate(), vec![
+            Problem::UnknownRuleset { zone: "Test/Zone".to_owned(), ruleset: "Missing".to_owned() },
+            Problem::UnusedRuleset("Unused".to_owned()),
+        ]);
+    }
+
+    #[test]
+    fn until_times() {
+        let mut table = Table::default();
+        let _ = table.zonesets.insert("Test/Zone".to_owned(), vec![
+            zone_info(Saving::NoSaving, Some(1990)),
+            zone_info(Saving::NoSaving, None),
+            zone_info(Saving::NoSaving, Some(1980)),
+            zone_info(Saving::NoSaving, None),
+        ]);
+
+        assert_eq!(table.validate(), vec![
+            Problem::MissingUntil { zone: "Test/Zone".to_owned(), index: 1 },
+            Problem::UntilOutOfOrder { zone: "Test/Zone".to_owned(), index: 2 },
+        ]);
+    }
+}
@@ -0,0 +1,202 @@
+//! Using the parser from JavaScript, through WebAssembly.
+//!
+//! Editors that check zoneinfo files as they’re being written can’t shell
+//! out to `zic`, but they can run WebAssembly. This module wraps the parts
+//! of the crate they need with `wasm-bindgen`: `parse` reads source text
+//! into a `ZoneTable`, whose `offsetAt` method gives the timespan in effect
+//! at an instant in one of its zones, and whose `transitions` method lists
+//! a zone’s transitions between two instants.
+//!
+//! JavaScript numbers can hold every whole number of seconds the database
+//! deals with exactly, so timestamps and offsets are passed as numbers
+//! rather than as `BigInt`s. Errors are thrown as strings: for `parse`, one
+//! line for each line of source that couldn’t be read, in the same form as
+//! `ParseError` displays them.
+//!
+//! It is only available when the crate is compiled with the `wasm` feature.
+//! The module itself comes from building the crate as a `cdylib` for the
+//! `wasm32-unknown-unknown` target, which `cargo rustc` can do without the
+//! crate having to be a `cdylib` for everyone else:
+//!
+//! ```sh
+//! cargo rustc --lib --release --target wasm32-unknown-unknown --features wasm --crate-type cdylib
+//! wasm-bindgen --out-dir pkg target/wasm32-unknown-unknown/release/zoneinfo_parse.wasm
+//! ```
+//!
+//! ## Example
+//!
+//! ```js
+//! import { parse } from "zoneinfo_parse";
+//!
+//! const table = parse("Zone  Asia/Kolkata  5:30  -  IST\n");
+//! table.offsetAt("Asia/Kolkata", 0).totalOffset;  // 19800
+//! ```
+
+use wasm_bindgen::prelude::*;
+
+use parse::parse_table;
+use table::Table;
+use transitions::{FixedTimespan, TableTransitions, TransitionOptions};
+
+
+/// Parses zoneinfo source text into a table, throwing every error found if
+/// any of its lines couldn’t be read.
+#[wasm_bindgen]
+pub fn parse(source: &str) -> Result<ZoneTable, JsValue> {
+    match parse_table(source) {
+        Ok(table)   => Ok(ZoneTable { table: table }),
+        Err(errors) => {
+            let messages: Vec<String> = errors.iter().map(|e| e.to_string()).collect();
+            Err(JsValue::from_str(&messages.join("\n")))
+        },
+    }
+}
+
+
+/// A table parsed from zoneinfo source text.
+#[wasm_bindgen]
+#[derive(Debug)]
+pub struct ZoneTable {
+    table: Table,
+}
+
+#[wasm_bindgen]
+impl ZoneTable {
+
+    /// Returns the names of every zone and link in the table, in order.
+    #[wasm_bindgen(js_name = zoneNames)]
+    pub fn zone_names(&self) -> Vec<String> {
+        let mut names: Vec<String> = self.table.zonesets.keys().chain(self.table.links.keys()).cloned().collect();
+        names.sort();
+        names
+    }
+
+    /// Returns the timespan in effect at the given Unix timestamp in the
+    /// zone with the given name, throwing if there’s no such zone.
+    #[wasm_bindgen(js_name = offsetAt)]
+    pub fn offset_at(&self, zone_name: &str, timestamp: f64) -> Result<Timespan, JsValue> {
+        match self.table.offset_at(zone_name, timestamp as i64, &TransitionOptions::default()) {
+            Some(timespan)  => Ok(Timespan::from(timespan)),
+            None            => Err(no_such_zone(zone_name)),
+        }
+    }
+
+    /// Returns the transitions in the zone with the given name that happen
+    /// at or after the `start` Unix timestamp and before the `end` one,
+    /// throwing if there’s no such zone.
+    pub fn transitions(&self, zone_name: &str, start: f64, end: f64) -> Result<Vec<Transition>, JsValue> {
+        match self.table.transitions_between(zone_name, start as i64, end as i64, &TransitionOptions::default()) {
+            Some(transitions)  => Ok(transitions.map(|(at, timespan)| Transition { at: at as f64, timespan: Timespan::from(timespan) }).collect()),
+            None               => Err(no_such_zone(zone_name)),
+        }
+    }
+}
+
+fn no_such_zone(zone_name: &str) -> JsValue {
+    JsValue::from_str(&format!("No zone named {:?}", zone_name))
+}
+
+
+/// A period of time with a fixed offset, as seen from JavaScript.
+#[wasm_bindgen]
+#[derive(PartialEq, Debug, Clone)]
+pub struct Timespan {
+    timespan: FixedTimespan,
+}
+
+impl From<FixedTimespan> for Timespan {
+    fn from(timespan: FixedTimespan) -> Timespan {
+        Timespan { timespan: timespan }
+    }
+}
+
+#[wasm_bindgen]
+impl Timespan {
+
+    /// The number of seconds offset from UTC, not counting daylight-saving
+    /// time.
+    #[wasm_bindgen(getter = utcOffset)]
+    pub fn utc_offset(&self) -> f64 {
+        self.timespan.utc_offset as f64
+    }
+
+    /// The number of extra daylight-saving seconds.
+    #[wasm_bindgen(getter = dstOffset)]
+    pub fn dst_offset(&self) -> f64 {
+        self.timespan.dst_offset as f64
+    }
+
+    /// The total number of seconds offset from UTC.
+    #[wasm_bindgen(getter = totalOffset)]
+    pub fn total_offset(&self) -> f64 {
+        self.timespan.total_offset() as f64
+    }
+
+    /// Whether this timespan counts as daylight-saving time.
+    #[wasm_bindgen(getter = isDst)]
+    pub fn is_dst(&self) -> bool {
+        self.timespan.is_dst
+    }
+
+    /// The abbreviation in use, such as `CEST`.
+    #[wasm_bindgen(getter)]
+    pub fn name(&self) -> String {
+        self.timespan.name.clone()
+    }
+}
+
+
+/// The instant a zone changes over to a new timespan.
+#[wasm_bindgen]
+#[derive(PartialEq, Debug, Clone)]
+pub struct Transition {
+    at: f64,
+    timespan: Timespan,
+}
+
+#[wasm_bindgen]
+impl Transition {
+
+    /// The Unix timestamp the transition happens at.
+    #[wasm_bindgen(getter)]
+    pub fn at(&self) -> f64 {
+        self.at
+    }
+
+    /// The timespan that comes into effect.
+    #[wasm_bindgen(getter)]
+    pub fn timespan(&self) -> Timespan {
+        self.timespan.clone()
+    }
+}
+
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const SOURCE: &'static str = "\
+        Rule  EU    1981  max  -  Mar  lastSun  1:00u  1:00  S\n\
+        Rule  EU    1996  max  -  Oct  lastSun  1:00u  0     -\n\
+        Zone  Europe/Paris  1:00  EU  CE%sT\n";
+
+    #[test]
+    fn offsets() {
+        let table = parse(SOURCE).unwrap();
+        assert_eq!(table.zone_names(), vec![ "Europe/Paris".to_owned() ]);
+
+        let summer = table.offset_at("Europe/Paris", 1_719_792_000.0).unwrap();
+        assert_eq!(summer.total_offset(), 7200.0);
+        assert_eq!(summer.name(), "CEST");
+        assert!(summer.is_dst());
+    }
+
+    #[test]
+    fn transitions() {
+        let table = parse(SOURCE).unwrap();
+
+        let transitions = table.transitions("Europe/Paris", 1_704_067_200.0, 1_735_689_600.0).unwrap();
+        let summary: Vec<(f64, String)> = transitions.iter().map(|t| (t.at(), t.timespan().name())).collect();
+        assert_eq!(summary, vec![ (1_711_846_800.0, "CEST".to_owned()), (1_729_990_800.0, "CET".to_owned()) ]);
+    }
+}
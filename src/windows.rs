@@ -0,0 +1,240 @@
+//! Parsing the mapping between Windows zone names and zoneinfo ones.
+//!
+//! Windows doesn’t use the tz database’s names for its time zones: it has
+//! its own, such as `W. Europe Standard Time`. The Unicode CLDR project
+//! publishes a `windowsZones.xml` file that maps each of them to the zones
+//! used for it in each territory, with one `mapZone` element per mapping:
+//!
+//! ```xml
+//! <mapZone other="W. Europe Standard Time" territory="001" type="Europe/Berlin"/>
+//! <mapZone other="W. Europe Standard Time" territory="CH" type="Europe/Zurich"/>
+//! ```
+//!
+//! The territory `001` stands for the whole world, and marks the zone to
+//! use when nothing more specific is known. A territory can list more than
+//! one zone, separated by spaces, in which case the first is the main one.
+//!
+//! Only the `mapZone` elements get read, so rather than depending on a
+//! whole XML parser, this module picks them out of the text itself.
+//!
+//! ## Example
+//!
+//! ```
+//! use zoneinfo_parse::windows::{parse_windows_zones, default_zones};
+//!
+//! let xml = r#"<mapZone other="W. Europe Standard Time" territory="001" type="Europe/Berlin"/>"#;
+//! let mappings = parse_windows_zones(xml).unwrap();
+//! assert_eq!(mappings[0].zones, vec![ "Europe/Berlin" ]);
+//! assert_eq!(default_zones(&mappings)["W. Europe Standard Time"], "Europe/Berlin");
+//! ```
+
+use std::collections::BTreeMap;
+use std::error::Error as ErrorTrait;
+use std::fmt;
+
+use regex::Regex;
+
+
+lazy_static! {
+
+    /// Format of one attribute of an element, in either kind of quotes.
+    static ref ATTRIBUTE: Regex = Regex::new(r##"(?x)
+        ( ?P<name> [ A-Z a-z _ : - ]+ ) \s* = \s*
+        (?: " ( ?P<double> [^"]* ) " | ' ( ?P<single> [^']* ) ' )
+    "##).unwrap();
+}
+
+
+/// The territory code that marks a Windows zone’s default mapping.
+pub const DEFAULT_TERRITORY: &'static str = "001";
+
+
+/// One `mapZone` element of a `windowsZones.xml` file.
+#[derive(PartialEq, Debug, Clone)]
+pub struct WindowsZone {
+
+    /// The name Windows uses for the zone, such as `W. Europe Standard Time`.
+    pub windows_name: String,
+
+    /// The CLDR code of the territory this mapping is for: usually an ISO
+    /// 3166 country code, or `001` for the default.
+    pub territory: String,
+
+    /// The names of the zoneinfo zones used in that territory, main one
+    /// first.
+    pub zones: Vec<String>,
+}
+
+
+/// Parses the contents of a `windowsZones.xml` file, returning its
+/// mappings in the order they appear. Everything apart from the `mapZone`
+/// elements, including comments, gets skipped.
+pub fn parse_windows_zones(input: &str) -> Result<Vec<WindowsZone>, Error> {
+    let mut mappings = Vec::new();
+    let mut rest = input;
+
+    while let Some(start) = rest.find('<') {
+        let line_number = input[.. input.len() - rest.len() + start].matches('\n').count() + 1;
+        rest = &rest[start ..];
+
+        if rest.starts_with("<!--") {
+            match rest.find("-->") {
+                Some(end) => { rest = &rest[end + 3 ..]; continue },
+                None      => return Err(Error::Unterminated(line_number)),
+            }
+        }
+
+        let end = match rest.find('>') {
+            Some(end) => end,
+            None      => return Err(Error::Unterminated(line_number)),
+        };
+
+        let element = &rest[1 .. end];
+        rest = &rest[end + 1 ..];
+
+        let is_map_zone = element.starts_with("mapZone")
+            && element["mapZone".len() ..].chars().next().map_or(true, |c| c.is_whitespace() || c == '/');
+
+        if is_map_zone {
+            mappings.push(try!(parse_map_zone(element, line_number)));
+        }
+    }
+
+    Ok(mappings)
+}
+
+/// Parses the attributes of a `mapZone` element, which has to have all
+/// three of them.
+fn parse_map_zone(element: &str, line_number: usize) -> Result<WindowsZone, Error> {
+    let mut other = None;
+    let mut territory = None;
+    let mut zones = None;
+
+    for caps in ATTRIBUTE.captures_iter(element) {
+        let value = unescape(caps.name("double").map(|m| m.as_str()).or(caps.name("single").map(|m| m.as_str())).unwrap_or(""));
+
+        match caps.name("name").map(|m| m.as_str()).unwrap() {
+            "other"      => other = Some(value),
+            "territory"  => territory = Some(value),
+            "type"       => zones = Some(value),
+             _           => {},
+        }
+    }
+
+    let windows_name = try!(other.ok_or(Error::MissingAttribute(line_number, "other")));
+    let territory    = try!(territory.ok_or(Error::MissingAttribute(line_number, "territory")));
+    let zones        = try!(zones.ok_or(Error::MissingAttribute(line_number, "type")));
+
+    Ok(WindowsZone {
+        windows_name: windows_name,
+        territory:    territory,
+        zones:        zones.split_whitespace().map(|z| z.to_owned()).collect(),
+    })
+}
+
+/// Replaces the predefined XML entities with the characters they stand
+/// for. The ampersand goes last, so `&amp;lt;` comes out as `&lt;`.
+fn unescape(value: &str) -> String {
+    value.replace("&lt;", "<")
+         .replace("&gt;", ">")
+         .replace("&quot;", "\"")
+         .replace("&apos;", "'")
+         .replace("&amp;", "&")
+}
+
+/// Returns the default zone for each Windows zone name: the main zone of
+/// its mapping for territory `001`. Names without one are left out.
+pub fn default_zones(mappings: &[WindowsZone]) -> BTreeMap<String, String> {
+    let mut defaults = BTreeMap::new();
+
+    for mapping in mappings.iter().filter(|m| m.territory == DEFAULT_TERRITORY) {
+        if let Some(zone) = mapping.zones.first() {
+            let _ = defaults.entry(mapping.windows_name.clone()).or_insert_with(|| zone.clone());
+        }
+    }
+
+    defaults
+}
+
+
+/// An error that can occur when parsing a `windowsZones.xml` file.
+#[derive(PartialEq, Debug, Copy, Clone)]
+pub enum Error {
+
+    /// An element or comment starting on this line never ended.
+    Unterminated(usize),
+
+    /// A `mapZone` element on this line didn’t have the named attribute.
+    MissingAttribute(usize, &'static str),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Error::Unterminated(line)            => write!(f, "line {}: unterminated element", line),
+            Error::MissingAttribute(line, name)  => write!(f, "line {}: mapZone without {:?} attribute", line, name),
+        }
+    }
+}
+
+impl ErrorTrait for Error {
+    fn description(&self) -> &str {
+        match *self {
+            Error::Unterminated(_)         => "unterminated element",
+            Error::MissingAttribute(..)    => "missing attribute",
+        }
+    }
+}
+
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const SAMPLE: &'static str = r#"<?xml version="1.0" encoding="UTF-8" ?>
+<supplementalData>
+    <windowsZones>
+        <mapTimezones otherVersion="7e11200" typeVersion="2021a">
+            <!-- (UTC+01:00) Amsterdam, Berlin, Bern, Rome, Stockholm, Vienna -->
+            <mapZone other="W. Europe Standard Time" territory="001" type="Europe/Berlin"/>
+            <mapZone other="W. Europe Standard Time" territory="DE" type="Europe/Berlin Europe/Busingen"/>
+            <!-- <mapZone other="Commented Out" territory="001" type="Etc/UTC"/> -->
+            <mapZone type='America/New_York' territory='001' other='Eastern Standard Time' />
+        </mapTimezones>
+    </windowsZones>
+</supplementalData>
+"#;
+
+    #[test]
+    fn mappings() {
+        let mappings = parse_windows_zones(SAMPLE).unwrap();
+        assert_eq!(mappings.len(), 3);
+        assert_eq!(mappings[1], WindowsZone {
+            windows_name: "W. Europe Standard Time".to_owned(),
+            territory:    "DE".to_owned(),
+            zones:        vec![ "Europe/Berlin".to_owned(), "Europe/Busingen".to_owned() ],
+        });
+        assert_eq!(mappings[2].windows_name, "Eastern Standard Time");
+    }
+
+    #[test]
+    fn defaults() {
+        let defaults = default_zones(&parse_windows_zones(SAMPLE).unwrap());
+        assert_eq!(defaults.len(), 2);
+        assert_eq!(defaults["W. Europe Standard Time"], "Europe/Berlin");
+        assert_eq!(defaults["Eastern Standard Time"], "America/New_York");
+    }
+
+    #[test]
+    fn entities() {
+        let mappings = parse_windows_zones(r#"<mapZone other="A &amp; B" territory="001" type="Etc/UTC"/>"#).unwrap();
+        assert_eq!(mappings[0].windows_name, "A & B");
+    }
+
+    #[test]
+    fn errors() {
+        assert_eq!(parse_windows_zones("<a/>\n<mapZone other=\"X\" type=\"Etc/UTC\"/>"), Err(Error::MissingAttribute(2, "territory")));
+        assert_eq!(parse_windows_zones("\n\n<mapZone other=\"X\""), Err(Error::Unterminated(3)));
+        assert_eq!(parse_windows_zones("<!-- never closed"), Err(Error::Unterminated(1)));
+    }
+}
@@ -0,0 +1,313 @@
+//! Checking computed transitions against the output of `zdump -v`.
+//!
+//! The `zdump` program that comes with the tz database prints what the C
+//! library thinks a zone’s offsets are. With `-v`, it prints two lines for
+//! each transition, one for the last second before it and one for the
+//! instant it happens, each with the time in UT, the local time, the
+//! abbreviation, whether daylight-saving time is in effect, and the total
+//! UTC offset in seconds:
+//!
+//! ```text
+//! Europe/London  Sun Mar 31 00:59:59 2024 UT = Sun Mar 31 00:59:59 2024 GMT isdst=0 gmtoff=0
+//! Europe/London  Sun Mar 31 01:00:00 2024 UT = Sun Mar 31 02:00:00 2024 BST isdst=1 gmtoff=3600
+//! ```
+//!
+//! Each of these lines is something the transitions computed by this crate
+//! should agree with, which makes saved `zdump` output a way of checking
+//! them that doesn’t depend on this crate’s TZif reader or writer, or on
+//! having any compiled files to hand. `parse_zdump` reads the lines, and
+//! `compare` lists the ones a table disagrees with.
+//!
+//! The lines `zdump` prints for times it can’t represent, which end in
+//! `= NULL`, get skipped. Older versions write `UTC` instead of `UT`, which
+//! is accepted too.
+//!
+//! ## Example
+//!
+//! ```
+//! use zoneinfo_parse::parse_table;
+//! use zoneinfo_parse::transitions::TransitionOptions;
+//! use zoneinfo_parse::zdump::{parse_zdump, compare};
+//!
+//! let table = parse_table("Zone  Asia/Kolkata  5:30  -  IST\n").unwrap();
+//! let observations = parse_zdump("Asia/Kolkata  Thu Jan  1 00:00:00 1970 UT = Thu Jan  1 05:30:00 1970 IST isdst=0 gmtoff=19800\n").unwrap();
+//! assert!(compare(&table, &observations, &TransitionOptions::default()).is_empty());
+//! ```
+
+use std::error::Error as ErrorTrait;
+use std::fmt;
+
+use datetime::{LocalDate, LocalDateTime, LocalTime};
+
+use line::{MonthSpec, YearSpec, split_lines};
+use table::Table;
+use timezone::TimeZone;
+use transitions::{FixedTimespan, TransitionOptions};
+
+
+/// What `zdump` says about one instant in one zone, from one line of its
+/// output.
+#[derive(PartialEq, Debug, Clone)]
+pub struct Observation {
+
+    /// The name of the zone, such as `Europe/London`.
+    pub zone: String,
+
+    /// The instant, as a Unix timestamp.
+    pub instant: i64,
+
+    /// The time zone abbreviation in effect at that instant.
+    pub abbreviation: String,
+
+    /// Whether daylight-saving time is in effect at that instant.
+    pub is_dst: bool,
+
+    /// The total UTC offset in effect at that instant, in seconds.
+    pub utc_offset: i64,
+}
+
+impl Observation {
+
+    /// Returns whether the given timespan has the same total offset,
+    /// abbreviation, and daylight-saving time as this line says.
+    pub fn agrees_with(&self, timespan: &FixedTimespan) -> bool {
+        timespan.total_offset() == self.utc_offset
+            && timespan.name == self.abbreviation
+            && (timespan.is_dst) == self.is_dst
+    }
+}
+
+
+/// Parses the output of `zdump -v` into one observation for each line.
+/// Blank lines and lines for times `zdump` can’t represent are skipped.
+pub fn parse_zdump(input: &str) -> Result<Vec<Observation>, Error> {
+    let mut observations = Vec::new();
+
+    for (index, line) in split_lines(input).enumerate() {
+        let line_number = index + 1;
+        let fields: Vec<&str> = line.split_whitespace().collect();
+
+        if fields.is_empty() || fields.last() == Some(&"NULL") {
+            continue;
+        }
+        else if fields.len() != 16 || (fields[6] != "UT" && fields[6] != "UTC") || fields[7] != "=" {
+            return Err(Error::WrongFieldCount(line_number));
+        }
+
+        let instant = try!(parse_time(&fields[2..6]).ok_or(Error::BadTime(line_number)));
+        let local = try!(parse_time(&fields[9..13]).ok_or(Error::BadTime(line_number)));
+
+        let is_dst = match fields[14] {
+            "isdst=0"  => false,
+            "isdst=1"  => true,
+             _         => return Err(Error::BadField(line_number)),
+        };
+
+        let utc_offset: i64 = match fields[15].starts_with("gmtoff=") {
+            true   => try!(fields[15]["gmtoff=".len() ..].parse().map_err(|_| Error::BadField(line_number))),
+            false  => return Err(Error::BadField(line_number)),
+        };
+
+        if local - instant != utc_offset {
+            return Err(Error::BadField(line_number));
+        }
+
+        observations.push(Observation {
+            zone:          fields[0].to_owned(),
+            instant:       instant,
+            abbreviation:  fields[13].to_owned(),
+            is_dst:        is_dst,
+            utc_offset:    utc_offset,
+        });
+    }
+
+    Ok(observations)
+}
+
+/// Parses the month, day, time, and year fields of a line, such as
+/// `Mar 31 00:59:59 2024`, into a Unix timestamp.
+fn parse_time(fields: &[&str]) -> Option<i64> {
+    let month = match fields[0].parse() {
+        Ok(MonthSpec(month)) => month,
+        Err(_)               => return None,
+    };
+
+    let day = match fields[1].parse() {
+        Ok(day) => day,
+        Err(_)  => return None,
+    };
+
+    let parts: Vec<i8> = match fields[2].split(':').map(|p| p.parse().ok()).collect() {
+        Some(parts) => parts,
+        None        => return None,
+    };
+
+    let year = match fields[3].parse() {
+        Ok(YearSpec::Number(year)) => year,
+        _                          => return None,
+    };
+
+    if parts.len() != 3 {
+        return None;
+    }
+
+    match (LocalDate::ymd(year, month, day), LocalTime::hms(parts[0], parts[1], parts[2])) {
+        (Ok(date), Ok(time))  => Some(LocalDateTime::new(date, time).to_instant().seconds()),
+        _                     => None,
+    }
+}
+
+
+/// A line of `zdump` output that a table disagrees with.
+#[derive(PartialEq, Debug, Clone)]
+pub struct Mismatch {
+
+    /// What `zdump` says.
+    pub expected: Observation,
+
+    /// The timespan the table has in effect at the same instant, or `None`
+    /// if the table doesn’t have the zone at all.
+    pub actual: Option<FixedTimespan>,
+}
+
+impl fmt::Display for Mismatch {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let e = &self.expected;
+        try!(write!(f, "{} at {}: zdump has {} (offset {}, DST {})", e.zone, e.instant, e.abbreviation, e.utc_offset, e.is_dst));
+        match self.actual {
+            Some(ref a)  => write!(f, " but the table has {} (offset {}, DST {})", a.name, a.total_offset(), a.is_dst),
+            None         => write!(f, " but the table has no such zone"),
+        }
+    }
+}
+
+/// Checks each observation against the timespan the table has in effect at
+/// the same instant, returning the ones it disagrees with, sorted by zone
+/// name and then by instant. The options need to cover all the years the
+/// observations are from.
+pub fn compare(table: &Table, observations: &[Observation], options: &TransitionOptions) -> Vec<Mismatch> {
+    let mut observations: Vec<&Observation> = observations.iter().collect();
+    observations.sort_by(|a, b| (&a.zone, a.instant).cmp(&(&b.zone, b.instant)));
+
+    let mut mismatches = Vec::new();
+    let mut zone: Option<TimeZone> = None;
+
+    for observation in observations {
+        // The observations are sorted, so each zone only needs its
+        // transitions computing once.
+        if zone.as_ref().map(|z| &z.name) != Some(&observation.zone) {
+            zone = TimeZone::from_table(table, &observation.zone, options);
+        }
+
+        let actual = zone.as_ref().map(|z| z.offset_at(observation.instant));
+
+        if !actual.map_or(false, |a| observation.agrees_with(a)) {
+            mismatches.push(Mismatch {
+                expected: observation.clone(),
+                actual:   actual.cloned(),
+            });
+        }
+    }
+
+    mismatches
+}
+
+
+/// An error that can occur when parsing the output of `zdump`.
+#[derive(PartialEq, Debug, Copy, Clone)]
+pub enum Error {
+
+    /// A line didn’t have the fields of a `zdump -v` line.
+    WrongFieldCount(usize),
+
+    /// A line’s UT or local time wasn’t valid.
+    BadTime(usize),
+
+    /// A line’s `isdst` or `gmtoff` field wasn’t valid, or the offset
+    /// didn’t match the difference between the two times.
+    BadField(usize),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Error::WrongFieldCount(line)  => write!(f, "line {}: wrong number of fields", line),
+            Error::BadTime(line)          => write!(f, "line {}: invalid date or time", line),
+            Error::BadField(line)         => write!(f, "line {}: invalid isdst or gmtoff", line),
+        }
+    }
+}
+
+impl ErrorTrait for Error {
+    fn description(&self) -> &str {
+        match *self {
+            Error::WrongFieldCount(_)  => "wrong number of fields",
+            Error::BadTime(_)          => "invalid date or time",
+            Error::BadField(_)         => "invalid isdst or gmtoff",
+        }
+    }
+}
+
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use parse::parse_table;
+    use transitions::{FixedTimespan, TransitionOptions};
+
+    const LONDON: &'static str = "\
+        Europe/London  -9223372036854775808 = NULL\n\
+        Europe/London  Sun Mar 31 00:59:59 2024 UT = Sun Mar 31 00:59:59 2024 GMT isdst=0 gmtoff=0\n\
+        Europe/London  Sun Mar 31 01:00:00 2024 UT = Sun Mar 31 02:00:00 2024 BST isdst=1 gmtoff=3600\n\
+        \n\
+        America/Sao_Paulo  Sun Feb 17 01:59:59 2019 UTC = Sat Feb 16 23:59:59 2019 -02 isdst=1 gmtoff=-7200\n";
+
+    #[test]
+    fn lines() {
+        let observations = parse_zdump(LONDON).unwrap();
+        assert_eq!(observations.len(), 3);
+        assert_eq!(observations[1], Observation {
+            zone:          "Europe/London".to_owned(),
+            instant:       1_711_846_800,
+            abbreviation:  "BST".to_owned(),
+            is_dst:        true,
+            utc_offset:    3600,
+        });
+        assert_eq!(observations[2].utc_offset, -7200);
+        assert_eq!(observations[2].abbreviation, "-02");
+    }
+
+    #[test]
+    fn errors() {
+        assert_eq!(parse_zdump("Europe/London  Sun Mar 31 00:59:59 2024 UT = Sun Mar 31 00:59:59 2024 GMT\n"), Err(Error::WrongFieldCount(1)));
+        assert_eq!(parse_zdump("Europe/London  Sun Mar 32 00:59:59 2024 UT = Sun Mar 31 00:59:59 2024 GMT isdst=0 gmtoff=0\n"), Err(Error::BadTime(1)));
+        assert_eq!(parse_zdump("Europe/London  Sun Mar 31 00:59:59 2024 UT = Sun Mar 31 00:59:59 2024 GMT isdst=0 gmtoff=60\n"), Err(Error::BadField(1)));
+    }
+
+    #[test]
+    fn agreement() {
+        let table = parse_table("Rule  GB  2024  only  -  Mar  31  1:00u  1:00  -\n\
+                                 Rule  GB  2024  only  -  Oct  27  1:00u  0     -\n\
+                                 Zone  Europe/London  0:00  GB  GMT/BST\n").unwrap();
+        let observations = parse_zdump(LONDON).unwrap();
+
+        let mismatches = compare(&table, &observations, &TransitionOptions::default());
+        assert_eq!(mismatches.len(), 1);
+        assert_eq!(mismatches[0].expected.zone, "America/Sao_Paulo");
+        assert_eq!(mismatches[0].actual, None);
+    }
+
+    #[test]
+    fn mismatches() {
+        let table = parse_table("Rule  GB  2024  only  -  Mar  31  2:00u  1:00  -\n\
+                                 Rule  GB  2024  only  -  Oct  27  1:00u  0     -\n\
+                                 Zone  Europe/London  0:00  GB  GMT/BST\n").unwrap();
+        let observations = parse_zdump(LONDON).unwrap();
+
+        let mismatches = compare(&table, &observations, &TransitionOptions::default());
+        assert_eq!(mismatches.len(), 2);
+        assert_eq!(mismatches[1].expected.instant, 1_711_846_800);
+        assert_eq!(mismatches[1].actual, Some(FixedTimespan { utc_offset: 0, dst_offset: 0, is_dst: false, name: "GMT".to_owned() }));
+        assert_eq!(mismatches[1].to_string(), "Europe/London at 1711846800: zdump has BST (offset 3600, DST true) but the table has GMT (offset 0, DST false)");
+    }
+}
@@ -3,7 +3,7 @@ extern crate datetime;
 
 use zoneinfo_parse::line::{Line, DaySpec, WeekdaySpec, MonthSpec, YearSpec, TimeSpec, ChangeTime};
 use zoneinfo_parse::table::{Saving, ZoneInfo, RuleInfo, Table, Format, TableBuilder};
-use zoneinfo_parse::transitions::{FixedTimespan, FixedTimespanSet, TableTransitions};
+use zoneinfo_parse::transitions::{FixedTimespan, FixedTimespanSet, TableTransitions, TransitionOptions};
 use datetime::Weekday::*;
 use datetime::Month::*;
 use datetime::zone::TimeType;
@@ -20,8 +20,8 @@ fn no_transitions() {
     let mut table = Table::default();
     table.zonesets.insert("Test/Zone".to_owned(), vec![ zone ]);
 
-    assert_eq!(table.timespans("Test/Zone"), Some(FixedTimespanSet {
-        first: FixedTimespan { utc_offset: 1234, dst_offset: 0, name: "TEST".to_owned() },
+    assert_eq!(table.timespans("Test/Zone", &TransitionOptions::default()), Some(FixedTimespanSet {
+        first: FixedTimespan { utc_offset: 1234, dst_offset: 0, is_dst: false, name: "TEST".to_owned() },
         rest:  vec![],
     }));
 }
@@ -46,13 +46,13 @@ fn one_transition() {
     table.zonesets.insert("Test/Zone".to_owned(), vec![ zone_1, zone_2 ]);
 
     let expected = FixedTimespanSet {
-        first:       FixedTimespan { utc_offset: 1234, dst_offset: 0, name: "TEST".to_owned() },
+        first:       FixedTimespan { utc_offset: 1234, dst_offset: 0, is_dst: false, name: "TEST".to_owned() },
         rest: vec![
-            (122222, FixedTimespan { utc_offset: 5678, dst_offset: 0, name: "TSET".to_owned() }),
+            (122222, FixedTimespan { utc_offset: 5678, dst_offset: 0, is_dst: false, name: "TSET".to_owned() }),
         ],
     };
 
-    assert_eq!(table.timespans("Test/Zone"), Some(expected));
+    assert_eq!(table.timespans("Test/Zone", &TransitionOptions::default()), Some(expected));
 }
 
 
@@ -83,22 +83,24 @@ fn two_transitions() {
     table.zonesets.insert("Test/Zone".to_owned(), vec![ zone_1, zone_2, zone_3 ]);
 
     let expected = FixedTimespanSet {
-        first: FixedTimespan { utc_offset: 1234, dst_offset: 0, name: "TEST".to_owned(), },
+        first: FixedTimespan { utc_offset: 1234, dst_offset: 0, is_dst: false, name: "TEST".to_owned(), },
         rest: vec![
             (122222, FixedTimespan {
                 utc_offset: 3456,
                 dst_offset: 0,
+                is_dst:     false,
                 name: "TSET".to_owned(),
             }),
             (231111, FixedTimespan {
                 utc_offset: 5678,
                 dst_offset: 0,
+                is_dst:     false,
                 name: "ESTE".to_owned(),
             }),
         ],
     };
 
-    assert_eq!(table.timespans("Test/Zone"), Some(expected));
+    assert_eq!(table.timespans("Test/Zone", &TransitionOptions::default()), Some(expected));
 }
 
 #[test]
@@ -112,6 +114,7 @@ fn one_rule() {
             time:        0,
             time_type:   TimeType::UTC,
             time_to_add: 1000,
+            is_dst:      None,
             letters:     None,
         }
     ];
@@ -134,10 +137,10 @@ fn one_rule() {
     table.zonesets.insert("Test/Zone".to_owned(), vec![ lmt, zone ]);
     table.rulesets.insert("Dwayne".to_owned(), ruleset);
 
-    assert_eq!(table.timespans("Test/Zone"), Some(FixedTimespanSet {
-        first: FixedTimespan { utc_offset: 0, dst_offset: 0, name: "LMT".to_owned() },
+    assert_eq!(table.timespans("Test/Zone", &TransitionOptions::default()), Some(FixedTimespanSet {
+        first: FixedTimespan { utc_offset: 0, dst_offset: 0, is_dst: false, name: "LMT".to_owned() },
         rest:  vec![
-            (318_470_400, FixedTimespan { utc_offset: 2000, dst_offset: 1000, name: "TEST".to_owned() })
+            (318_470_400, FixedTimespan { utc_offset: 2000, dst_offset: 1000, is_dst: true, name: "TEST".to_owned() })
         ],
     }));
 }
@@ -153,6 +156,7 @@ fn two_rules() {
             time:        0,
             time_type:   TimeType::UTC,
             time_to_add: 1000,
+            is_dst:      None,
             letters:     None,
         },
         RuleInfo {
@@ -163,6 +167,7 @@ fn two_rules() {
             time:        0,
             time_type:   TimeType::UTC,
             time_to_add: 1500,
+            is_dst:      None,
             letters:     None,
         },
     ];
@@ -185,11 +190,72 @@ fn two_rules() {
     table.zonesets.insert("Test/Zone".to_owned(), vec![ lmt, zone ]);
     table.rulesets.insert("Dwayne".to_owned(), ruleset);
 
-    assert_eq!(table.timespans("Test/Zone"), Some(FixedTimespanSet {
-        first: FixedTimespan { utc_offset: 0, dst_offset: 0, name: "LMT".to_owned() },
+    assert_eq!(table.timespans("Test/Zone", &TransitionOptions::default()), Some(FixedTimespanSet {
+        first: FixedTimespan { utc_offset: 0, dst_offset: 0, is_dst: false, name: "LMT".to_owned() },
         rest: vec![
-            (318_470_400, FixedTimespan { utc_offset: 2000, dst_offset: 1000, name: "TEST".to_owned() }),
-            (600_566_400, FixedTimespan { utc_offset: 2000, dst_offset: 1500, name: "TEST".to_owned() }),
+            (318_470_400, FixedTimespan { utc_offset: 2000, dst_offset: 1000, is_dst: true, name: "TEST".to_owned() }),
+            (600_566_400, FixedTimespan { utc_offset: 2000, dst_offset: 1500, is_dst: true, name: "TEST".to_owned() }),
+        ],
+    }));
+}
+
+#[test]
+fn limited_years() {
+    let ruleset = vec![
+        RuleInfo {
+            from_year:   YearSpec::Number(1980),
+            to_year:     Some(YearSpec::Maximum),
+            month:       MonthSpec(February),
+            day:         DaySpec::Ordinal(4),
+            time:        0,
+            time_type:   TimeType::UTC,
+            time_to_add: 1000,
+            is_dst:      None,
+            letters:     None,
+        },
+        RuleInfo {
+            from_year:   YearSpec::Number(1980),
+            to_year:     Some(YearSpec::Maximum),
+            month:       MonthSpec(August),
+            day:         DaySpec::Ordinal(4),
+            time:        0,
+            time_type:   TimeType::UTC,
+            time_to_add: 0,
+            is_dst:      None,
+            letters:     None,
+        },
+    ];
+
+    let lmt = ZoneInfo {
+        offset: 0,
+        format: Format::new("LMT"),
+        saving: Saving::NoSaving,
+        end_time: Some(ChangeTime::UntilYear(YearSpec::Number(1980))),
+    };
+
+    let zone = ZoneInfo {
+        offset: 2000,
+        format: Format::new("TEST"),
+        saving: Saving::Multiple("Dwayne".to_owned()),
+        end_time: None,
+    };
+
+    let mut table = Table::default();
+    table.zonesets.insert("Test/Zone".to_owned(), vec![ lmt, zone ]);
+    table.rulesets.insert("Dwayne".to_owned(), ruleset);
+
+    let options = TransitionOptions { min_year: 1800, max_year: 1981, .. TransitionOptions::default() };
+    let from_iter = table.transitions_iter("Test/Zone", &options).unwrap().collect::<Vec<_>>();
+    assert_eq!(from_iter, table.timespans("Test/Zone", &options).unwrap().rest);
+
+    assert_eq!(table.timespans("Test/Zone", &options), Some(FixedTimespanSet {
+        first: FixedTimespan { utc_offset: 0, dst_offset: 0, is_dst: false, name: "LMT".to_owned() },
+        rest: vec![
+            (315_532_800, FixedTimespan { utc_offset: 2000, dst_offset: 0,    is_dst: false, name: "TEST".to_owned() }),
+            (318_470_400, FixedTimespan { utc_offset: 2000, dst_offset: 1000, is_dst:  true, name: "TEST".to_owned() }),
+            (334_195_200, FixedTimespan { utc_offset: 2000, dst_offset: 0,    is_dst: false, name: "TEST".to_owned() }),
+            (350_092_800, FixedTimespan { utc_offset: 2000, dst_offset: 1000, is_dst:  true, name: "TEST".to_owned() }),
+            (365_731_200, FixedTimespan { utc_offset: 2000, dst_offset: 0,    is_dst: false, name: "TEST".to_owned() }),
         ],
     }));
 }
@@ -197,23 +263,23 @@ fn two_rules() {
 #[test]
 fn tripoli() {
     let libya = vec![
-        RuleInfo { from_year: YearSpec::Number(1951), to_year: None,                         month: MonthSpec(October),   day: DaySpec::Ordinal(14),               time: 7200, time_type: TimeType::Wall, time_to_add: 3600, letters: Some("S".to_owned()) },
-        RuleInfo { from_year: YearSpec::Number(1952), to_year: None,                         month: MonthSpec(January),   day: DaySpec::Ordinal(1),                time: 0,    time_type: TimeType::Wall, time_to_add: 0,    letters: None                 },
-        RuleInfo { from_year: YearSpec::Number(1953), to_year: None,                         month: MonthSpec(October),   day: DaySpec::Ordinal(9),                time: 7200, time_type: TimeType::Wall, time_to_add: 3600, letters: Some("S".to_owned()) },
-        RuleInfo { from_year: YearSpec::Number(1954), to_year: None,                         month: MonthSpec(January),   day: DaySpec::Ordinal(1),                time: 0,    time_type: TimeType::Wall, time_to_add: 0,    letters: None                 },
-        RuleInfo { from_year: YearSpec::Number(1955), to_year: None,                         month: MonthSpec(September), day: DaySpec::Ordinal(30),               time: 0,    time_type: TimeType::Wall, time_to_add: 3600, letters: Some("S".to_owned()) },
-        RuleInfo { from_year: YearSpec::Number(1956), to_year: None,                         month: MonthSpec(January),   day: DaySpec::Ordinal(1),                time: 0,    time_type: TimeType::Wall, time_to_add: 0,    letters: None                 },
-        RuleInfo { from_year: YearSpec::Number(1982), to_year: Some(YearSpec::Number(1984)), month: MonthSpec(April),     day: DaySpec::Ordinal(1),                time: 0,    time_type: TimeType::Wall, time_to_add: 3600, letters: Some("S".to_owned()) },
-        RuleInfo { from_year: YearSpec::Number(1982), to_year: Some(YearSpec::Number(1985)), month: MonthSpec(October),   day: DaySpec::Ordinal(1),                time: 0,    time_type: TimeType::Wall, time_to_add: 0,    letters: None                 },
-        RuleInfo { from_year: YearSpec::Number(1985), to_year: None,                         month: MonthSpec(April),     day: DaySpec::Ordinal(6),                time: 0,    time_type: TimeType::Wall, time_to_add: 3600, letters: Some("S".to_owned()) },
-        RuleInfo { from_year: YearSpec::Number(1986), to_year: None,                         month: MonthSpec(April),     day: DaySpec::Ordinal(4),                time: 0,    time_type: TimeType::Wall, time_to_add: 3600, letters: Some("S".to_owned()) },
-        RuleInfo { from_year: YearSpec::Number(1986), to_year: None,                         month: MonthSpec(October),   day: DaySpec::Ordinal(3),                time: 0,    time_type: TimeType::Wall, time_to_add: 0,    letters: None                 },
-        RuleInfo { from_year: YearSpec::Number(1987), to_year: Some(YearSpec::Number(1989)), month: MonthSpec(April),     day: DaySpec::Ordinal(1),                time: 0,    time_type: TimeType::Wall, time_to_add: 3600, letters: Some("S".to_owned()) },
-        RuleInfo { from_year: YearSpec::Number(1987), to_year: Some(YearSpec::Number(1989)), month: MonthSpec(October),   day: DaySpec::Ordinal(1),                time: 0,    time_type: TimeType::Wall, time_to_add: 0,    letters: None                 },
-        RuleInfo { from_year: YearSpec::Number(1997), to_year: None,                         month: MonthSpec(April),     day: DaySpec::Ordinal(4),                time: 0,    time_type: TimeType::Wall, time_to_add: 3600, letters: Some("S".to_owned()) },
-        RuleInfo { from_year: YearSpec::Number(1997), to_year: None,                         month: MonthSpec(October),   day: DaySpec::Ordinal(4),                time: 0,    time_type: TimeType::Wall, time_to_add: 0,    letters: None                 },
-        RuleInfo { from_year: YearSpec::Number(2013), to_year: None,                         month: MonthSpec(March),     day: DaySpec::Last(WeekdaySpec(Friday)), time: 3600, time_type: TimeType::Wall, time_to_add: 3600, letters: Some("S".to_owned()) },
-        RuleInfo { from_year: YearSpec::Number(2013), to_year: None,                         month: MonthSpec(October),   day: DaySpec::Last(WeekdaySpec(Friday)), time: 7200, time_type: TimeType::Wall, time_to_add: 0,    letters: None                 },
+        RuleInfo { from_year: YearSpec::Number(1951), to_year: None,                         month: MonthSpec(October),   day: DaySpec::Ordinal(14),               time: 7200, time_type: TimeType::Wall, time_to_add: 3600, is_dst: None, letters: Some("S".into())     },
+        RuleInfo { from_year: YearSpec::Number(1952), to_year: None,                         month: MonthSpec(January),   day: DaySpec::Ordinal(1),                time: 0,    time_type: TimeType::Wall, time_to_add: 0,    is_dst: None, letters: None                 },
+        RuleInfo { from_year: YearSpec::Number(1953), to_year: None,                         month: MonthSpec(October),   day: DaySpec::Ordinal(9),                time: 7200, time_type: TimeType::Wall, time_to_add: 3600, is_dst: None, letters: Some("S".into())     },
+        RuleInfo { from_year: YearSpec::Number(1954), to_year: None,                         month: MonthSpec(January),   day: DaySpec::Ordinal(1),                time: 0,    time_type: TimeType::Wall, time_to_add: 0,    is_dst: None, letters: None                 },
+        RuleInfo { from_year: YearSpec::Number(1955), to_year: None,                         month: MonthSpec(September), day: DaySpec::Ordinal(30),               time: 0,    time_type: TimeType::Wall, time_to_add: 3600, is_dst: None, letters: Some("S".into())     },
+        RuleInfo { from_year: YearSpec::Number(1956), to_year: None,                         month: MonthSpec(January),   day: DaySpec::Ordinal(1),                time: 0,    time_type: TimeType::Wall, time_to_add: 0,    is_dst: None, letters: None                 },
+        RuleInfo { from_year: YearSpec::Number(1982), to_year: Some(YearSpec::Number(1984)), month: MonthSpec(April),     day: DaySpec::Ordinal(1),                time: 0,    time_type: TimeType::Wall, time_to_add: 3600, is_dst: None, letters: Some("S".into())     },
+        RuleInfo { from_year: YearSpec::Number(1982), to_year: Some(YearSpec::Number(1985)), month: MonthSpec(October),   day: DaySpec::Ordinal(1),                time: 0,    time_type: TimeType::Wall, time_to_add: 0,    is_dst: None, letters: None                 },
+        RuleInfo { from_year: YearSpec::Number(1985), to_year: None,                         month: MonthSpec(April),     day: DaySpec::Ordinal(6),                time: 0,    time_type: TimeType::Wall, time_to_add: 3600, is_dst: None, letters: Some("S".into())     },
+        RuleInfo { from_year: YearSpec::Number(1986), to_year: None,                         month: MonthSpec(April),     day: DaySpec::Ordinal(4),                time: 0,    time_type: TimeType::Wall, time_to_add: 3600, is_dst: None, letters: Some("S".into())     },
+        RuleInfo { from_year: YearSpec::Number(1986), to_year: None,                         month: MonthSpec(October),   day: DaySpec::Ordinal(3),                time: 0,    time_type: TimeType::Wall, time_to_add: 0,    is_dst: None, letters: None                 },
+        RuleInfo { from_year: YearSpec::Number(1987), to_year: Some(YearSpec::Number(1989)), month: MonthSpec(April),     day: DaySpec::Ordinal(1),                time: 0,    time_type: TimeType::Wall, time_to_add: 3600, is_dst: None, letters: Some("S".into())     },
+        RuleInfo { from_year: YearSpec::Number(1987), to_year: Some(YearSpec::Number(1989)), month: MonthSpec(October),   day: DaySpec::Ordinal(1),                time: 0,    time_type: TimeType::Wall, time_to_add: 0,    is_dst: None, letters: None                 },
+        RuleInfo { from_year: YearSpec::Number(1997), to_year: None,                         month: MonthSpec(April),     day: DaySpec::Ordinal(4),                time: 0,    time_type: TimeType::Wall, time_to_add: 3600, is_dst: None, letters: Some("S".into())     },
+        RuleInfo { from_year: YearSpec::Number(1997), to_year: None,                         month: MonthSpec(October),   day: DaySpec::Ordinal(4),                time: 0,    time_type: TimeType::Wall, time_to_add: 0,    is_dst: None, letters: None                 },
+        RuleInfo { from_year: YearSpec::Number(2013), to_year: None,                         month: MonthSpec(March),     day: DaySpec::Last(WeekdaySpec(Friday)), time: 3600, time_type: TimeType::Wall, time_to_add: 3600, is_dst: None, letters: Some("S".into())     },
+        RuleInfo { from_year: YearSpec::Number(2013), to_year: None,                         month: MonthSpec(October),   day: DaySpec::Last(WeekdaySpec(Friday)), time: 7200, time_type: TimeType::Wall, time_to_add: 0,    is_dst: None, letters: None                 },
     ];
 
     let zone = vec![
@@ -232,41 +298,41 @@ fn tripoli() {
     table.zonesets.insert("Test/Zone".to_owned(), zone);
     table.rulesets.insert("Libya".to_owned(), libya);
 
-    assert_eq!(table.timespans("Test/Zone"), Some(FixedTimespanSet {
-        first: FixedTimespan { utc_offset: 3164,  dst_offset:    0,  name:  "LMT".to_owned() },
+    assert_eq!(table.timespans("Test/Zone", &TransitionOptions::default()), Some(FixedTimespanSet {
+        first: FixedTimespan { utc_offset: 3164,  dst_offset:    0,  is_dst: false, name:  "LMT".to_owned() },
         rest: vec![
-            (-1_577_926_364, FixedTimespan { utc_offset: 3600,  dst_offset:    0,  name:  "CET".to_owned() }),
-            (  -574_902_000, FixedTimespan { utc_offset: 3600,  dst_offset: 3600,  name: "CEST".to_owned() }),
-            (  -568_087_200, FixedTimespan { utc_offset: 3600,  dst_offset:    0,  name:  "CET".to_owned() }),
-            (  -512_175_600, FixedTimespan { utc_offset: 3600,  dst_offset: 3600,  name: "CEST".to_owned() }),
-            (  -504_928_800, FixedTimespan { utc_offset: 3600,  dst_offset:    0,  name:  "CET".to_owned() }),
-            (  -449_888_400, FixedTimespan { utc_offset: 3600,  dst_offset: 3600,  name: "CEST".to_owned() }),
-            (  -441_856_800, FixedTimespan { utc_offset: 3600,  dst_offset:    0,  name:  "CET".to_owned() }),
-            (  -347_158_800, FixedTimespan { utc_offset: 7200,  dst_offset:    0,  name:  "EET".to_owned() }),
-            (   378_684_000, FixedTimespan { utc_offset: 3600,  dst_offset:    0,  name:  "CET".to_owned() }),
-            (   386_463_600, FixedTimespan { utc_offset: 3600,  dst_offset: 3600,  name: "CEST".to_owned() }),
-            (   402_271_200, FixedTimespan { utc_offset: 3600,  dst_offset:    0,  name:  "CET".to_owned() }),
-            (   417_999_600, FixedTimespan { utc_offset: 3600,  dst_offset: 3600,  name: "CEST".to_owned() }),
-            (   433_807_200, FixedTimespan { utc_offset: 3600,  dst_offset:    0,  name:  "CET".to_owned() }),
-            (   449_622_000, FixedTimespan { utc_offset: 3600,  dst_offset: 3600,  name: "CEST".to_owned() }),
-            (   465_429_600, FixedTimespan { utc_offset: 3600,  dst_offset:    0,  name:  "CET".to_owned() }),
-            (   481_590_000, FixedTimespan { utc_offset: 3600,  dst_offset: 3600,  name: "CEST".to_owned() }),
-            (   496_965_600, FixedTimespan { utc_offset: 3600,  dst_offset:    0,  name:  "CET".to_owned() }),
-            (   512_953_200, FixedTimespan { utc_offset: 3600,  dst_offset: 3600,  name: "CEST".to_owned() }),
-            (   528_674_400, FixedTimespan { utc_offset: 3600,  dst_offset:    0,  name:  "CET".to_owned() }),
-            (   544_230_000, FixedTimespan { utc_offset: 3600,  dst_offset: 3600,  name: "CEST".to_owned() }),
-            (   560_037_600, FixedTimespan { utc_offset: 3600,  dst_offset:    0,  name:  "CET".to_owned() }),
-            (   575_852_400, FixedTimespan { utc_offset: 3600,  dst_offset: 3600,  name: "CEST".to_owned() }),
-            (   591_660_000, FixedTimespan { utc_offset: 3600,  dst_offset:    0,  name:  "CET".to_owned() }),
-            (   607_388_400, FixedTimespan { utc_offset: 3600,  dst_offset: 3600,  name: "CEST".to_owned() }),
-            (   623_196_000, FixedTimespan { utc_offset: 3600,  dst_offset:    0,  name:  "CET".to_owned() }),
-            (   641_775_600, FixedTimespan { utc_offset: 7200,  dst_offset:    0,  name:  "EET".to_owned() }),
-            (   844_034_400, FixedTimespan { utc_offset: 3600,  dst_offset:    0,  name:  "CET".to_owned() }),
-            (   860_108_400, FixedTimespan { utc_offset: 3600,  dst_offset: 3600,  name: "CEST".to_owned() }),
-            (   875_916_000, FixedTimespan { utc_offset: 7200,  dst_offset:    0,  name:  "EET".to_owned() }),
-            ( 1_352_505_600, FixedTimespan { utc_offset: 3600,  dst_offset:    0,  name:  "CET".to_owned() }),
-            ( 1_364_515_200, FixedTimespan { utc_offset: 3600,  dst_offset: 3600,  name: "CEST".to_owned() }),
-            ( 1_382_659_200, FixedTimespan { utc_offset: 7200,  dst_offset:    0,  name:  "EET".to_owned() }),
+            (-1_577_926_364, FixedTimespan { utc_offset: 3600,  dst_offset:    0,  is_dst: false, name:  "CET".to_owned() }),
+            (  -574_902_000, FixedTimespan { utc_offset: 3600,  dst_offset: 3600,  is_dst:  true, name: "CEST".to_owned() }),
+            (  -568_087_200, FixedTimespan { utc_offset: 3600,  dst_offset:    0,  is_dst: false, name:  "CET".to_owned() }),
+            (  -512_175_600, FixedTimespan { utc_offset: 3600,  dst_offset: 3600,  is_dst:  true, name: "CEST".to_owned() }),
+            (  -504_928_800, FixedTimespan { utc_offset: 3600,  dst_offset:    0,  is_dst: false, name:  "CET".to_owned() }),
+            (  -449_888_400, FixedTimespan { utc_offset: 3600,  dst_offset: 3600,  is_dst:  true, name: "CEST".to_owned() }),
+            (  -441_856_800, FixedTimespan { utc_offset: 3600,  dst_offset:    0,  is_dst: false, name:  "CET".to_owned() }),
+            (  -347_158_800, FixedTimespan { utc_offset: 7200,  dst_offset:    0,  is_dst: false, name:  "EET".to_owned() }),
+            (   378_684_000, FixedTimespan { utc_offset: 3600,  dst_offset:    0,  is_dst: false, name:  "CET".to_owned() }),
+            (   386_463_600, FixedTimespan { utc_offset: 3600,  dst_offset: 3600,  is_dst:  true, name: "CEST".to_owned() }),
+            (   402_271_200, FixedTimespan { utc_offset: 3600,  dst_offset:    0,  is_dst: false, name:  "CET".to_owned() }),
+            (   417_999_600, FixedTimespan { utc_offset: 3600,  dst_offset: 3600,  is_dst:  true, name: "CEST".to_owned() }),
+            (   433_807_200, FixedTimespan { utc_offset: 3600,  dst_offset:    0,  is_dst: false, name:  "CET".to_owned() }),
+            (   449_622_000, FixedTimespan { utc_offset: 3600,  dst_offset: 3600,  is_dst:  true, name: "CEST".to_owned() }),
+            (   465_429_600, FixedTimespan { utc_offset: 3600,  dst_offset:    0,  is_dst: false, name:  "CET".to_owned() }),
+            (   481_590_000, FixedTimespan { utc_offset: 3600,  dst_offset: 3600,  is_dst:  true, name: "CEST".to_owned() }),
+            (   496_965_600, FixedTimespan { utc_offset: 3600,  dst_offset:    0,  is_dst: false, name:  "CET".to_owned() }),
+            (   512_953_200, FixedTimespan { utc_offset: 3600,  dst_offset: 3600,  is_dst:  true, name: "CEST".to_owned() }),
+            (   528_674_400, FixedTimespan { utc_offset: 3600,  dst_offset:    0,  is_dst: false, name:  "CET".to_owned() }),
+            (   544_230_000, FixedTimespan { utc_offset: 3600,  dst_offset: 3600,  is_dst:  true, name: "CEST".to_owned() }),
+            (   560_037_600, FixedTimespan { utc_offset: 3600,  dst_offset:    0,  is_dst: false, name:  "CET".to_owned() }),
+            (   575_852_400, FixedTimespan { utc_offset: 3600,  dst_offset: 3600,  is_dst:  true, name: "CEST".to_owned() }),
+            (   591_660_000, FixedTimespan { utc_offset: 3600,  dst_offset:    0,  is_dst: false, name:  "CET".to_owned() }),
+            (   607_388_400, FixedTimespan { utc_offset: 3600,  dst_offset: 3600,  is_dst:  true, name: "CEST".to_owned() }),
+            (   623_196_000, FixedTimespan { utc_offset: 3600,  dst_offset:    0,  is_dst: false, name:  "CET".to_owned() }),
+            (   641_775_600, FixedTimespan { utc_offset: 7200,  dst_offset:    0,  is_dst: false, name:  "EET".to_owned() }),
+            (   844_034_400, FixedTimespan { utc_offset: 3600,  dst_offset:    0,  is_dst: false, name:  "CET".to_owned() }),
+            (   860_108_400, FixedTimespan { utc_offset: 3600,  dst_offset: 3600,  is_dst:  true, name: "CEST".to_owned() }),
+            (   875_916_000, FixedTimespan { utc_offset: 7200,  dst_offset:    0,  is_dst: false, name:  "EET".to_owned() }),
+            ( 1_352_505_600, FixedTimespan { utc_offset: 3600,  dst_offset:    0,  is_dst: false, name:  "CET".to_owned() }),
+            ( 1_364_515_200, FixedTimespan { utc_offset: 3600,  dst_offset: 3600,  is_dst:  true, name: "CEST".to_owned() }),
+            ( 1_382_659_200, FixedTimespan { utc_offset: 7200,  dst_offset:    0,  is_dst: false, name:  "EET".to_owned() }),
         ],
     }));
 }
@@ -290,5 +356,234 @@ Zone    Asia/Dushanbe   4:35:12 -   LMT 1924 May  2
         }
     }
     let table = table.build();
-    let _ = table.timespans("Asia/Dushanbe").unwrap();
+    let _ = table.timespans("Asia/Dushanbe", &TransitionOptions::default()).unwrap();
+}
+
+#[test]
+fn mauritius_rule_activations() {
+    use datetime::{LocalDate, LocalTime, LocalDateTime};
+    use zoneinfo_parse::transitions::RuleActivation;
+
+    static ZONEINFO : &'static str = r#"
+Rule Mauritius      1982    only    -       Oct     10      0:00    1:00    S
+Rule Mauritius      1983    only    -       Mar     21      0:00    0       -
+Rule Mauritius      2008    only    -       Oct     lastSun 2:00    1:00    S
+Rule Mauritius      2009    only    -       Mar     lastSun 2:00    0       -
+"#;
+
+    let mut table = TableBuilder::new();
+    for line in ZONEINFO.lines() {
+        match Line::from_str(line).unwrap() {
+            Line::Rule(rule) => table.add_rule_line(rule).unwrap(),
+            Line::Space => {},
+            _ => unreachable!(),
+        }
+    }
+    let table = table.build();
+
+    let at = |y, m, d, h| LocalDateTime::new(LocalDate::ymd(y, m, d).unwrap(), LocalTime::hm(h, 0).unwrap());
+    let activations = table.rule_activations("Mauritius", 1983..2010).unwrap();
+
+    assert_eq!(activations, vec![
+        RuleActivation { datetime: at(1983, March, 21, 0),   time_type: TimeType::Wall, save: 0,    letters: None },
+        RuleActivation { datetime: at(2008, October, 26, 2), time_type: TimeType::Wall, save: 3600, letters: Some("S".into())     },
+        RuleActivation { datetime: at(2009, March, 29, 2),   time_type: TimeType::Wall, save: 0,    letters: None },
+    ]);
+
+    assert_eq!(table.rule_activations("Nowhere", 1983..2010), None);
+}
+
+#[test]
+fn dedup() {
+    let zone_1 = ZoneInfo {
+        offset: 0,
+        format: Format::new("TEST"),
+        saving: Saving::OneOff(7200),
+        end_time: Some(ChangeTime::UntilYear(YearSpec::Number(1970))),
+    };
+
+    // The same total offset and abbreviation as before, made up differently.
+    let zone_2 = ZoneInfo {
+        offset: 3600,
+        format: Format::new("TEST"),
+        saving: Saving::OneOff(3600),
+        end_time: Some(ChangeTime::UntilYear(YearSpec::Number(1971))),
+    };
+
+    let zone_3 = ZoneInfo {
+        offset: 0,
+        format: Format::new("TEST"),
+        saving: Saving::NoSaving,
+        end_time: None,
+    };
+
+    let mut table = Table::default();
+    table.zonesets.insert("Test/Zone".to_owned(), vec![ zone_1, zone_2, zone_3 ]);
+
+    let with_dedup = table.timespans("Test/Zone", &TransitionOptions::default()).unwrap();
+    assert_eq!(with_dedup.rest, vec![
+        (31_528_800, FixedTimespan { utc_offset: 0, dst_offset: 0, is_dst: false, name: "TEST".to_owned() }),
+    ]);
+
+    let options = TransitionOptions { dedup: false, .. TransitionOptions::default() };
+    let without_dedup = table.timespans("Test/Zone", &options).unwrap();
+    assert_eq!(without_dedup.rest.len(), 2);
+}
+
+#[test]
+fn resolve_local() {
+    use datetime::LocalDateTime;
+    use zoneinfo_parse::transitions::LocalResolution::*;
+
+    let zone_1 = ZoneInfo {
+        offset: 0,
+        format: Format::new("TEST"),
+        saving: Saving::NoSaving,
+        end_time: Some(ChangeTime::UntilYear(YearSpec::Number(1970))),
+    };
+
+    let zone_2 = ZoneInfo {
+        offset: 3600,
+        format: Format::new("TSET"),
+        saving: Saving::NoSaving,
+        end_time: Some(ChangeTime::UntilYear(YearSpec::Number(1971))),
+    };
+
+    let zone_3 = ZoneInfo {
+        offset: 0,
+        format: Format::new("TEST"),
+        saving: Saving::NoSaving,
+        end_time: None,
+    };
+
+    let mut table = Table::default();
+    table.zonesets.insert("Test/Zone".to_owned(), vec![ zone_1, zone_2, zone_3 ]);
+
+    let zero = FixedTimespan { utc_offset: 0,    dst_offset: 0, is_dst: false, name: "TEST".to_owned() };
+    let one  = FixedTimespan { utc_offset: 3600, dst_offset: 0, is_dst: false, name: "TSET".to_owned() };
+    let resolve = |local| table.resolve_local("Test/Zone", LocalDateTime::at(local), &TransitionOptions::default()).unwrap();
+
+    // The clocks go forward from midnight to 1am at the start of 1970...
+    assert_eq!(resolve(-1),         Unique(zero.clone()));
+    assert_eq!(resolve(1800),       Gap(zero.clone(), one.clone()));
+    assert_eq!(resolve(3600),       Unique(one.clone()));
+
+    // ...and back from midnight to 11pm at the start of 1971.
+    assert_eq!(resolve(31_532_399), Unique(one.clone()));
+    assert_eq!(resolve(31_534_200), Ambiguous(one.clone(), zero.clone()));
+    assert_eq!(resolve(31_536_000), Unique(zero.clone()));
+
+    assert_eq!(table.resolve_local("Test/Nowhere", LocalDateTime::at(0), &TransitionOptions::default()), None);
+}
+
+#[test]
+fn transitions_between() {
+    let ruleset = vec![
+        RuleInfo {
+            from_year:   YearSpec::Number(1980),
+            to_year:     Some(YearSpec::Maximum),
+            month:       MonthSpec(February),
+            day:         DaySpec::Ordinal(4),
+            time:        0,
+            time_type:   TimeType::UTC,
+            time_to_add: 1000,
+            is_dst:      None,
+            letters:     None,
+        },
+        RuleInfo {
+            from_year:   YearSpec::Number(1980),
+            to_year:     Some(YearSpec::Maximum),
+            month:       MonthSpec(August),
+            day:         DaySpec::Ordinal(4),
+            time:        0,
+            time_type:   TimeType::UTC,
+            time_to_add: 0,
+            is_dst:      None,
+            letters:     None,
+        },
+    ];
+
+    let zone = ZoneInfo {
+        offset: 2000,
+        format: Format::new("TEST"),
+        saving: Saving::Multiple("Dwayne".to_owned()),
+        end_time: None,
+    };
+
+    let mut table = Table::default();
+    table.zonesets.insert("Test/Zone".to_owned(), vec![ zone ]);
+    table.rulesets.insert("Dwayne".to_owned(), ruleset);
+
+    // Just the transitions in 1981, from the start of the year up to but
+    // not including the start of the next one.
+    let options = TransitionOptions::default();
+    let year = table.transitions_between("Test/Zone", 347_155_200, 378_691_200, &options).unwrap().collect::<Vec<_>>();
+    assert_eq!(year, vec![
+        (350_092_800, FixedTimespan { utc_offset: 2000, dst_offset: 1000, is_dst:  true, name: "TEST".to_owned() }),
+        (365_731_200, FixedTimespan { utc_offset: 2000, dst_offset: 0,    is_dst: false, name: "TEST".to_owned() }),
+    ]);
+
+    let all = table.timespans("Test/Zone", &options).unwrap().rest;
+    let middle = table.transitions_between("Test/Zone", 1_000_000_000, 2_000_000_000, &options).unwrap().collect::<Vec<_>>();
+    assert_eq!(middle, all.into_iter().filter(|&(at, _)| at >= 1_000_000_000 && at < 2_000_000_000).collect::<Vec<_>>());
+
+    assert_eq!(table.transitions_between("Test/Zone", 350_092_800, 350_092_800, &options).unwrap().next(), None);
+    assert!(table.transitions_between("Test/Nowhere", 0, 1, &options).is_none());
+}
+
+#[test]
+fn next_transition() {
+    let zone_1 = ZoneInfo {
+        offset: 0,
+        format: Format::new("TEST"),
+        saving: Saving::NoSaving,
+        end_time: Some(ChangeTime::UntilYear(YearSpec::Number(1970))),
+    };
+
+    let zone_2 = ZoneInfo {
+        offset: 3600,
+        format: Format::new("TSET"),
+        saving: Saving::NoSaving,
+        end_time: None,
+    };
+
+    let mut table = Table::default();
+    table.zonesets.insert("Test/Zone".to_owned(), vec![ zone_1, zone_2 ]);
+
+    let options = TransitionOptions::default();
+    let expected = Some((0, FixedTimespan { utc_offset: 3600, dst_offset: 0, is_dst: false, name: "TSET".to_owned() }));
+
+    assert_eq!(table.next_transition("Test/Zone", -1, &options), expected);
+    assert_eq!(table.next_transition("Test/Zone", 0, &options), None);
+    assert_eq!(table.next_transition("Test/Nowhere", -1, &options), None);
+}
+
+#[test]
+fn offset_at() {
+    let zone_1 = ZoneInfo {
+        offset: 0,
+        format: Format::new("TEST"),
+        saving: Saving::NoSaving,
+        end_time: Some(ChangeTime::UntilYear(YearSpec::Number(1970))),
+    };
+
+    let zone_2 = ZoneInfo {
+        offset: 3600,
+        format: Format::new("TSET"),
+        saving: Saving::OneOff(1800),
+        end_time: None,
+    };
+
+    let mut table = Table::default();
+    table.zonesets.insert("Test/Zone".to_owned(), vec![ zone_1, zone_2 ]);
+    table.links.insert("Test/Link".to_owned(), "Test/Zone".to_owned());
+
+    let options = TransitionOptions::default();
+    let before = FixedTimespan { utc_offset: 0, dst_offset: 0, is_dst: false, name: "TEST".to_owned() };
+    let after = FixedTimespan { utc_offset: 3600, dst_offset: 1800, is_dst:  true, name: "TSET".to_owned() };
+
+    assert_eq!(table.offset_at("Test/Zone", -1, &options), Some(before));
+    assert_eq!(table.offset_at("Test/Zone", 0, &options), Some(after));
+    assert_eq!(table.offset_at("Test/Link", 1_000_000_000, &options).map(|t| t.total_offset()), Some(5400));
+    assert_eq!(table.offset_at("Test/Nowhere", 0, &options), None);
 }